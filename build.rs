@@ -42,34 +42,49 @@ fn main() {
         .expect("OUT_DIR should be set by cargo")
         .into();
 
-    let template_dir = manifest_dir.join("templates").join("new");
-    let dst_file = out_dir.join("template.zip");
-
-    println!(
-        "Creating template zip: template_dir '{}', destination archive '{}'",
-        template_dir.display(),
-        dst_file.display()
-    );
+    let templates = [
+        (
+            manifest_dir.join("templates").join("new"),
+            out_dir.join("template.zip"),
+        ),
+        (
+            manifest_dir.join("templates").join("examples"),
+            out_dir.join("examples.zip"),
+        ),
+        (
+            manifest_dir.join("templates").join("workspace"),
+            out_dir.join("workspace.zip"),
+        ),
+        (
+            manifest_dir.join("templates").join("trait"),
+            out_dir.join("trait.zip"),
+        ),
+    ];
 
     generate_cargo_keys();
     rerun_if_git_head_changed();
 
-    std::process::exit(
-        match zip_dir(&template_dir, &dst_file, CompressionMethod::Stored) {
+    for (template_dir, dst_file) in &templates {
+        println!(
+            "Creating template zip: template_dir '{}', destination archive '{}'",
+            template_dir.display(),
+            dst_file.display()
+        );
+
+        match zip_dir(template_dir, dst_file, CompressionMethod::Stored) {
             Ok(_) => {
                 println!(
                     "done: {} written to {}",
                     template_dir.display(),
                     dst_file.display()
                 );
-                0
             }
             Err(e) => {
                 eprintln!("Error: {:?}", e);
-                1
+                std::process::exit(1);
             }
-        },
-    );
+        }
+    }
 }
 
 fn zip_dir(src_dir: &Path, dst_file: &Path, method: CompressionMethod) -> Result<()> {
@@ -0,0 +1,287 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::{Node, BUNDLE_ENV, CARGO_CONTRACT_ENV};
+use anyhow::{Context, Result};
+use blake2::{Blake2b, Digest};
+use codec::{Decode, Encode};
+use serde_json::{json, Value};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The development account the contract is deployed and called by, unless another signer is set.
+const ALICE: &str = "//Alice";
+
+/// The SS58 address of `//Alice`, the origin of queries.
+const ALICE_ADDRESS: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+/// The endowment of deployed contracts, enough to exceed the subsistence threshold of dev chains.
+const ENDOWMENT: u128 = 1_000_000_000_000_000;
+
+/// The gas limit of calls and queries.
+const GAS_LIMIT: u64 = 500_000_000_000;
+
+/// The SS58 address format of substrate dev chains.
+const SS58_FORMAT: u8 = 42;
+
+/// Flag set in the result of a contract execution if the contract reverted its state changes.
+const FLAG_REVERT: u64 = 1;
+
+/// The outcome of a dry-run of a message, see [`Contract::dry_run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DryRun {
+    /// The message returned the encoded output.
+    Success(Vec<u8>),
+    /// The contract reverted its state changes, returning the encoded output.
+    Reverted(Vec<u8>),
+    /// The contract trapped with the error reported by the node.
+    Trapped(String),
+}
+
+/// A contract instantiated on a [`Node`].
+#[derive(Debug)]
+pub struct Contract<'a> {
+    node: &'a Node,
+    address: String,
+    bundle: PathBuf,
+    metadata: Value,
+    suri: String,
+}
+
+impl<'a> Contract<'a> {
+    /// Deploys the contract of the crate under test and instantiates it with the `constructor`
+    /// and its encoded `args`, e.g. `(false,)` or `()` if it takes no arguments.
+    ///
+    /// The contract is built by `cargo contract build` unless it was built by
+    /// `cargo contract test --e2e`.
+    pub fn deploy<Args: Encode>(node: &'a Node, constructor: &str, args: Args) -> Result<Self> {
+        let bundle = match env::var(BUNDLE_ENV) {
+            Ok(bundle) => PathBuf::from(bundle),
+            Err(_) => build()?,
+        };
+        Self::deploy_bundle(node, &bundle, constructor, args)
+    }
+
+    /// Deploys the contract of the `<name>.contract` bundle and instantiates it with the
+    /// `constructor` and its encoded `args`.
+    pub fn deploy_bundle<Args: Encode>(
+        node: &'a Node,
+        bundle: &Path,
+        constructor: &str,
+        args: Args,
+    ) -> Result<Self> {
+        let bundle = bundle
+            .canonicalize()
+            .context(format!("The bundle {} doesn't exist", bundle.display()))?;
+        let contents = fs::read(&bundle)?;
+        let metadata = serde_json::from_slice(&contents)
+            .context(format!("The bundle {} is no valid JSON", bundle.display()))?;
+
+        let bundle_arg = bundle.display().to_string();
+        cargo_contract(&[
+            "deploy",
+            &bundle_arg,
+            "--url",
+            node.ws_url(),
+            "--suri",
+            ALICE,
+        ])?;
+        let output = cargo_contract(&[
+            "instantiate",
+            "--bundle",
+            &bundle_arg,
+            "--constructor",
+            constructor,
+            "--data",
+            &hex::encode(args.encode()),
+            "--endowment",
+            &ENDOWMENT.to_string(),
+            "--gas",
+            &GAS_LIMIT.to_string(),
+            "--url",
+            node.ws_url(),
+            "--suri",
+            ALICE,
+        ])?;
+        Ok(Self {
+            node,
+            address: instantiated_address(&output)?,
+            bundle,
+            metadata,
+            suri: ALICE.into(),
+        })
+    }
+
+    /// Returns the contract with calls signed by the secret key URI `suri`, e.g. `//Bob`.
+    pub fn with_signer(mut self, suri: &str) -> Self {
+        self.suri = suri.into();
+        self
+    }
+
+    /// Returns the SS58 address of the contract.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Dry-runs the `message` with its encoded `args` and decodes its return value.
+    ///
+    /// Fails if the contract traps or reverts.
+    pub fn query<R: Decode>(&self, message: &str, args: impl Encode) -> Result<R> {
+        match self.dry_run(message, args)? {
+            DryRun::Success(output) => R::decode(&mut &output[..])
+                .context(format!("Failed to decode the output of {}", message)),
+            DryRun::Reverted(_) => anyhow::bail!("The query of {} reverted", message),
+            DryRun::Trapped(error) => {
+                anyhow::bail!("The query of {} trapped: {}", message, error)
+            }
+        }
+    }
+
+    /// Dry-runs the `message` with its encoded `args`, returning its outcome.
+    pub fn dry_run(&self, message: &str, args: impl Encode) -> Result<DryRun> {
+        let mut data = self.selector(message)?;
+        data.extend(args.encode());
+        let request = json!({
+            "origin": ALICE_ADDRESS,
+            "dest": self.address,
+            "value": 0,
+            "gasLimit": GAS_LIMIT,
+            "inputData": format!("0x{}", hex::encode(&data)),
+        });
+        let result: Value = self.node.request("contracts_call", json!([request]))?;
+        let success = match result.get("success") {
+            Some(success) => success,
+            None => return Ok(DryRun::Trapped(result["error"].to_string())),
+        };
+        let output = success["data"].as_str().unwrap_or_default();
+        let output = hex::decode(output.trim_start_matches("0x"))?;
+        if success["flags"].as_u64().unwrap_or_default() & FLAG_REVERT == FLAG_REVERT {
+            return Ok(DryRun::Reverted(output));
+        }
+        Ok(DryRun::Success(output))
+    }
+
+    /// Submits a call of the `message` with its encoded `args`, and waits for its inclusion.
+    pub fn call(&self, message: &str, args: impl Encode) -> Result<()> {
+        self.call_with_value(message, args, 0)
+    }
+
+    /// Submits a call of the payable `message` transferring `value` to the contract.
+    pub fn call_with_value(&self, message: &str, args: impl Encode, value: u128) -> Result<()> {
+        cargo_contract(&[
+            "call",
+            "--contract",
+            &self.address,
+            "--bundle",
+            &self.bundle.display().to_string(),
+            "--message",
+            message,
+            "--data",
+            &hex::encode(args.encode()),
+            "--value",
+            &value.to_string(),
+            "--gas",
+            &GAS_LIMIT.to_string(),
+            "--url",
+            self.node.ws_url(),
+            "--suri",
+            &self.suri,
+        ])?;
+        Ok(())
+    }
+
+    /// Returns the selector of the `message` from the metadata of the contract.
+    fn selector(&self, message: &str) -> Result<Vec<u8>> {
+        let metadata = self.metadata.get("V1").unwrap_or(&self.metadata);
+        let selector = metadata["spec"]["messages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|spec| spec["label"] == message || spec["name"] == json!([message]))
+            .and_then(|spec| spec["selector"].as_str())
+            .ok_or_else(|| anyhow::anyhow!("The contract has no message {}", message))?;
+        Ok(hex::decode(selector.trim_start_matches("0x"))?)
+    }
+}
+
+/// Builds the contract in the current directory and returns the path of its bundle.
+fn build() -> Result<PathBuf> {
+    let output = cargo_contract(&["build", "--output", "json"])?;
+    let result: Value = serde_json::from_str(&output).context("Unexpected output of the build")?;
+    result["dest_bundle"]
+        .as_str()
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("The build created no bundle"))
+}
+
+/// Runs `cargo contract` with the `args` and returns its output.
+fn cargo_contract(args: &[&str]) -> Result<String> {
+    let binary = env::var(CARGO_CONTRACT_ENV).unwrap_or_else(|_| "cargo-contract".into());
+    let mut command = Command::new(&binary);
+    command.arg("contract").args(args);
+    // instantiations outside of the contract directory are not recorded in its deployments
+    if args[0] != "build" {
+        command.current_dir(env::temp_dir());
+    }
+    let output = command
+        .output()
+        .context(format!("Failed to run `{}`", binary))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`cargo contract {}` failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr)
+        )
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Returns the SS58 address of the contract in the `output` of `cargo contract instantiate`,
+/// which prints the hex encoded account.
+fn instantiated_address(output: &str) -> Result<String> {
+    let account = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Contract account: "))
+        .and_then(|account| account.split_whitespace().next())
+        .ok_or_else(|| anyhow::anyhow!("Unexpected output of the instantiation: {}", output))?;
+    let account = hex::decode(account.trim_start_matches("0x"))?;
+    Ok(ss58(&account))
+}
+
+/// Returns the SS58 address of the `account` in the format of dev chains.
+fn ss58(account: &[u8]) -> String {
+    let mut address = vec![SS58_FORMAT];
+    address.extend(account);
+    let checksum = Blake2b::new().chain(b"SS58PRE").chain(&address).finalize();
+    address.extend(&checksum[..2]);
+    bs58::encode(address).into_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_is_taken_from_the_instantiation() {
+        let output = "\tContract account: \
+            d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d (5GrwvaEF...)\n";
+        assert_eq!(instantiated_address(output).unwrap(), ALICE_ADDRESS);
+        assert!(instantiated_address("Code hash: 0x00").is_err());
+    }
+}
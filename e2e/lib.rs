@@ -0,0 +1,59 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Harness for end-to-end tests of smart contracts against an ephemeral substrate node.
+//!
+//! A test starts a [`Node`], deploys the contract of the crate under test with
+//! [`Contract::deploy`] and interacts with it via typed calls:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use contract_e2e::{Contract, Node};
+//!
+//! let node = Node::start()?;
+//! let flipper = Contract::deploy(&node, "new", (false,))?;
+//! flipper.call("flip", ())?;
+//! assert!(flipper.query::<bool>("get", ())?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! `cargo contract test --e2e` starts a single node for all tests, builds the contract once and
+//! runs the tests with the `e2e-tests` feature of the contract enabled. Run by plain `cargo test`,
+//! every [`Node::start`] spawns its own node and the contract is built by `cargo contract build`.
+//!
+//! The node is spawned from the binary in `CONTRACTS_NODE`, or `substrate-contracts-node` on the
+//! `PATH`. Contracts are built, deployed and called by the `cargo-contract` binary in
+//! `CARGO_CONTRACT`, or the one on the `PATH`.
+
+mod contract;
+mod node;
+
+pub use self::{
+    contract::{Contract, DryRun},
+    node::Node,
+};
+
+/// Environment variable of the websocket url of the node started by `cargo contract test --e2e`.
+pub const NODE_WS_URL_ENV: &str = "CONTRACT_E2E_NODE_WS_URL";
+/// Environment variable of the HTTP RPC url of the node started by `cargo contract test --e2e`.
+pub const NODE_RPC_URL_ENV: &str = "CONTRACT_E2E_NODE_RPC_URL";
+/// Environment variable of the bundle of the contract built by `cargo contract test --e2e`.
+pub const BUNDLE_ENV: &str = "CONTRACT_E2E_BUNDLE";
+/// Environment variable of the node binary to spawn.
+pub const NODE_BINARY_ENV: &str = "CONTRACTS_NODE";
+/// Environment variable of the `cargo-contract` binary to build, deploy and call contracts with.
+pub const CARGO_CONTRACT_ENV: &str = "CARGO_CONTRACT";
@@ -0,0 +1,169 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::{NODE_BINARY_ENV, NODE_RPC_URL_ENV, NODE_WS_URL_ENV};
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::{
+    env,
+    net::TcpListener,
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The node binary spawned if `CONTRACTS_NODE` is not set.
+const DEFAULT_NODE_BINARY: &str = "substrate-contracts-node";
+
+/// How long to wait for a spawned node to answer RPC requests.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A substrate node with the contracts pallet, killed on drop if it was spawned.
+#[derive(Debug)]
+pub struct Node {
+    ws_url: String,
+    rpc_url: String,
+    process: Option<Child>,
+}
+
+impl Node {
+    /// Connects to the node started by `cargo contract test --e2e`, or spawns a new one.
+    pub fn start() -> Result<Self> {
+        match (env::var(NODE_WS_URL_ENV), env::var(NODE_RPC_URL_ENV)) {
+            (Ok(ws_url), Ok(rpc_url)) => Ok(Self {
+                ws_url,
+                rpc_url,
+                process: None,
+            }),
+            _ => Self::spawn(),
+        }
+    }
+
+    /// Spawns a development node on free ports, with a temporary chain that is removed when the
+    /// node is dropped.
+    pub fn spawn() -> Result<Self> {
+        let binary = env::var(NODE_BINARY_ENV).unwrap_or_else(|_| DEFAULT_NODE_BINARY.into());
+        let ws_port = free_port()?;
+        let rpc_port = free_port()?;
+        let p2p_port = free_port()?;
+        let process = Command::new(&binary)
+            .args(["--dev", "--tmp", "--no-prometheus", "--no-telemetry"])
+            .args(["--ws-port", &ws_port.to_string()])
+            .args(["--rpc-port", &rpc_port.to_string()])
+            .args(["--port", &p2p_port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context(format!(
+                "Failed to spawn the node `{}`, install it or point `{}` to it",
+                binary, NODE_BINARY_ENV
+            ))?;
+        let mut node = Self {
+            ws_url: format!("ws://127.0.0.1:{}", ws_port),
+            rpc_url: format!("http://127.0.0.1:{}", rpc_port),
+            process: Some(process),
+        };
+        node.wait_until_ready()?;
+        Ok(node)
+    }
+
+    /// Returns the websocket url of the node.
+    pub fn ws_url(&self) -> &str {
+        &self.ws_url
+    }
+
+    /// Returns the HTTP RPC url of the node.
+    pub fn rpc_url(&self) -> &str {
+        &self.rpc_url
+    }
+
+    /// Sends the RPC request for `method` with the supplied `params` and decodes the result.
+    pub fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response: Value = ureq::post(&self.rpc_url)
+            .send_json(request)
+            .map_err(|err| anyhow::anyhow!("RPC request `{}` failed: {}", method, err))?
+            .into_json()
+            .context(format!("Invalid response for RPC request `{}`", method))?;
+        if let Some(error) = response.get("error") {
+            anyhow::bail!("RPC request `{}` returned an error: {}", method, error);
+        }
+        serde_json::from_value(response["result"].clone())
+            .context(format!("Unexpected result for RPC request `{}`", method))
+    }
+
+    /// Waits until the spawned node answers RPC requests.
+    fn wait_until_ready(&mut self) -> Result<()> {
+        let started = Instant::now();
+        loop {
+            if self.request::<Value>("system_health", json!([])).is_ok() {
+                return Ok(());
+            }
+            if let Some(process) = &mut self.process {
+                if let Some(status) = process.try_wait()? {
+                    anyhow::bail!("The node exited on startup with {}", status)
+                }
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                anyhow::bail!(
+                    "The node didn't answer RPC requests within {}s",
+                    STARTUP_TIMEOUT.as_secs()
+                )
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        if let Some(process) = &mut self.process {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+/// Returns a free local port.
+fn free_port() -> Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_of_runner_is_not_spawned() {
+        // given
+        env::set_var(NODE_WS_URL_ENV, "ws://127.0.0.1:9944");
+        env::set_var(NODE_RPC_URL_ENV, "http://127.0.0.1:9933");
+
+        // when
+        let node = Node::start().expect("the node of the runner is used");
+
+        // then
+        assert_eq!(node.ws_url(), "ws://127.0.0.1:9944");
+        assert_eq!(node.rpc_url(), "http://127.0.0.1:9933");
+        assert!(node.process.is_none());
+    }
+}
@@ -28,7 +28,7 @@
 //! let language = SourceLanguage::new(Language::Ink, Version::new(2, 1, 0));
 //! let compiler = SourceCompiler::new(Compiler::RustC, Version::parse("1.46.0-nightly").unwrap());
 //! let wasm = SourceWasm::new(vec![0u8]);
-//! let source = Source::new(Some(wasm), CodeHash([0u8; 32]), language, compiler);
+//! let source = Source::new(Some(wasm), CodeHash([0u8; 32]), language, compiler, None);
 //! let contract = Contract::builder()
 //!     .name("incrementer".to_string())
 //!     .version(Version::new(2, 1, 0))
@@ -119,6 +119,11 @@ pub struct Source {
     compiler: SourceCompiler,
     #[serde(skip_serializing_if = "Option::is_none")]
     wasm: Option<SourceWasm>,
+    /// Extra information about the environment in which the contract was built.
+    ///
+    /// Useful for verifying how an artifact was produced.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build_info: Option<Map<String, Value>>,
 }
 
 impl Source {
@@ -128,12 +133,14 @@ impl Source {
         hash: CodeHash,
         language: SourceLanguage,
         compiler: SourceCompiler,
+        build_info: Option<Map<String, Value>>,
     ) -> Self {
         Source {
             hash,
             language,
             compiler,
             wasm,
+            build_info,
         }
     }
 }
@@ -521,7 +528,23 @@ mod tests {
         let compiler =
             SourceCompiler::new(Compiler::RustC, Version::parse("1.46.0-nightly").unwrap());
         let wasm = SourceWasm::new(vec![0u8, 1u8, 2u8]);
-        let source = Source::new(Some(wasm), CodeHash([0u8; 32]), language, compiler);
+        let build_info = json! {
+            {
+                "example_compiler_version": 42,
+                "example_settings": [],
+                "example_name": "increment"
+            }
+        }
+        .as_object()
+        .unwrap()
+        .clone();
+        let source = Source::new(
+            Some(wasm),
+            CodeHash([0u8; 32]),
+            language,
+            compiler,
+            Some(build_info),
+        );
         let contract = Contract::builder()
             .name("incrementer".to_string())
             .version(Version::new(2, 1, 0))
@@ -566,7 +589,12 @@ mod tests {
                     "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
                     "language": "ink! 2.1.0",
                     "compiler": "rustc 1.46.0-nightly",
-                    "wasm": "0x000102"
+                    "wasm": "0x000102",
+                    "build_info": {
+                        "example_compiler_version": 42,
+                        "example_settings": [],
+                        "example_name": "increment"
+                    }
                 },
                 "contract": {
                     "name": "incrementer",
@@ -603,7 +631,7 @@ mod tests {
         let language = SourceLanguage::new(Language::Ink, Version::new(2, 1, 0));
         let compiler =
             SourceCompiler::new(Compiler::RustC, Version::parse("1.46.0-nightly").unwrap());
-        let source = Source::new(None, CodeHash([0u8; 32]), language, compiler);
+        let source = Source::new(None, CodeHash([0u8; 32]), language, compiler, None);
         let contract = Contract::builder()
             .name("incrementer".to_string())
             .version(Version::new(2, 1, 0))
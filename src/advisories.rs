@@ -0,0 +1,199 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Checks the dependencies of a contract against advisories about versions of ink! and
+//! `parity-scale-codec` with known bugs affecting contracts on chain.
+//!
+//! Advisories are bundled with cargo-contract and can be supplemented by an advisory list, read
+//! from a file or fetched from a url, which contains the advisories as JSON:
+//!
+//! ```json
+//! [{
+//!   "id": "INK-2021-0001",
+//!   "package": "ink_storage",
+//!   "versions": ">=3.0.0-rc1, <3.0.0-rc4",
+//!   "description": "Storage keys of nested collections may collide"
+//! }]
+//! ```
+
+use crate::crate_metadata::CrateMetadata;
+use anyhow::{Context, Result};
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use std::{fmt, fs};
+
+/// Advisories bundled with this version of cargo-contract as `(id, package, versions,
+/// description)`.
+///
+/// Advisories published after the release are picked up via an advisory list.
+const BUNDLED_ADVISORIES: &[(&str, &str, &str, &str)] = &[];
+
+/// The packages whose versions are checked, since they determine the behaviour of contracts on
+/// chain.
+const CHECKED_PACKAGES: [&str; 9] = [
+    "ink_lang",
+    "ink_lang_codegen",
+    "ink_env",
+    "ink_storage",
+    "ink_primitives",
+    "ink_prelude",
+    "ink_metadata",
+    "parity-scale-codec",
+    "parity-scale-codec-derive",
+];
+
+/// An advisory about versions of a package.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct Advisory {
+    pub id: String,
+    pub package: String,
+    /// The affected versions.
+    pub versions: VersionReq,
+    pub description: String,
+}
+
+/// A dependency of the contract affected by an advisory.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Finding {
+    pub advisory: Advisory,
+    pub version: Version,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} is affected by {}: {}",
+            self.advisory.package, self.version, self.advisory.id, self.advisory.description
+        )
+    }
+}
+
+/// Returns the dependencies of the contract affected by the bundled advisories, or those of the
+/// advisory list at `advisory_db`.
+pub(crate) fn check(
+    crate_metadata: &CrateMetadata,
+    advisory_db: Option<&str>,
+) -> Result<Vec<Finding>> {
+    let mut advisories = bundled()?;
+    if let Some(advisory_db) = advisory_db {
+        advisories.extend(load(advisory_db)?);
+    }
+    let dependencies = crate_metadata
+        .cargo_meta
+        .packages
+        .iter()
+        .filter(|package| CHECKED_PACKAGES.contains(&package.name.as_str()))
+        .map(|package| (package.name.as_str(), &package.version));
+    Ok(affected(&advisories, dependencies))
+}
+
+/// Returns the `dependencies` affected by the `advisories`.
+fn affected<'a>(
+    advisories: &[Advisory],
+    dependencies: impl Iterator<Item = (&'a str, &'a Version)>,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (name, version) in dependencies {
+        for advisory in advisories {
+            if advisory.package == name && advisory.versions.matches(version) {
+                findings.push(Finding {
+                    advisory: advisory.clone(),
+                    version: version.clone(),
+                });
+            }
+        }
+    }
+    findings
+}
+
+fn bundled() -> Result<Vec<Advisory>> {
+    BUNDLED_ADVISORIES
+        .iter()
+        .map(|(id, package, versions, description)| {
+            Ok(Advisory {
+                id: id.to_string(),
+                package: package.to_string(),
+                versions: VersionReq::parse(versions)?,
+                description: description.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Loads the advisory list from the file at `location`, or fetches it if it is an http(s) url.
+fn load(location: &str) -> Result<Vec<Advisory>> {
+    let contents = if location.starts_with("http://") || location.starts_with("https://") {
+        ureq::get(location)
+            .call()
+            .map_err(|err| anyhow::anyhow!("Fetching the advisories failed: {}", err))?
+            .into_string()?
+    } else {
+        fs::read_to_string(location).context(format!("Failed to read {}", location))?
+    };
+    serde_json::from_str(&contents).context(format!("Invalid advisory list {}", location))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    #[test]
+    fn affected_versions_are_found() {
+        with_tmp_dir(|path| {
+            // given
+            let advisory_db = path.join("advisories.json");
+            fs::write(
+                &advisory_db,
+                r#"[{
+                    "id": "INK-TEST-0001",
+                    "package": "ink_storage",
+                    "versions": ">=3.0.0-rc1, <3.0.0-rc4",
+                    "description": "Storage keys of nested collections may collide"
+                }]"#,
+            )?;
+            let advisories = load(advisory_db.to_str().unwrap())?;
+            let affected_version = Version::parse("3.0.0-rc3")?;
+            let fixed_version = Version::parse("3.0.0-rc4")?;
+
+            // when
+            let findings = affected(
+                &advisories,
+                vec![
+                    ("ink_storage", &affected_version),
+                    ("ink_env", &affected_version),
+                    ("ink_storage", &fixed_version),
+                ]
+                .into_iter(),
+            );
+
+            // then
+            assert_eq!(findings.len(), 1);
+            assert_eq!(
+                findings[0].to_string(),
+                "ink_storage 3.0.0-rc3 is affected by INK-TEST-0001: \
+                Storage keys of nested collections may collide"
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn bundled_advisories_are_valid() {
+        assert!(bundled().is_ok());
+    }
+}
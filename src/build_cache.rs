@@ -0,0 +1,399 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Skipping of builds whose inputs did not change since the previous build, and caching of
+//! optimized Wasm binaries.
+
+use crate::{
+    cmd::metadata::{self, blake2_hash, code_tree_hash, source_tree_hash},
+    crate_metadata::CrateMetadata,
+    toolchain, BuildArtifacts, OptimizationResult,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Record of the previous build of a contract.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct BuildRecord {
+    /// The fingerprint of the inputs of the build.
+    fingerprint: String,
+    /// The fingerprint of the inputs of the build affecting the Wasm.
+    #[serde(default)]
+    code_fingerprint: Option<String>,
+    /// The Wasm size before the optimizations.
+    pub original_size: f64,
+    /// The Wasm size after the optimizations.
+    pub optimized_size: f64,
+    /// The version of the optimizer.
+    pub optimizer_version: String,
+    /// The estimated Wasm size after the instrumentation by `pallet-contracts`.
+    #[serde(default)]
+    pub instrumented_size: Option<f64>,
+}
+
+/// Fingerprint of the inputs of a contract build.
+///
+/// Covers the sources of the contract and of its local path dependencies, the `Cargo.lock` of its
+/// workspace, the versions of `cargo-contract` and `rustc` as well as the settings of the build,
+/// which include the version of the optimizer.
+///
+/// The inputs affecting only the metadata, i.e. doc comments and the settings in
+/// `METADATA_SETTINGS`, are left out of the separate fingerprint of the Wasm. If only they
+/// changed, the Wasm of the previous build is reused and just the metadata is regenerated.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Fingerprint {
+    inputs: String,
+    code: String,
+}
+
+impl Fingerprint {
+    /// The build settings which don't affect the Wasm.
+    const METADATA_SETTINGS: [&'static str; 2] = ["build_artifact", "compress_bundle"];
+
+    /// Computes the fingerprint of the build of the contract with the supplied `settings`.
+    pub fn new(crate_metadata: &CrateMetadata, settings: &serde_json::Value) -> Result<Self> {
+        let directory = crate_metadata.manifest_path.absolute_directory()?;
+        let lock_file = crate_metadata.cargo_meta.workspace_root.join("Cargo.lock");
+        let lock = fs::read(&lock_file).unwrap_or_default();
+        let rustc =
+            toolchain::version_meta(crate_metadata.toolchain.as_deref())?.short_version_string;
+
+        let mut code_settings = settings.clone();
+        if let Some(code_settings) = code_settings.as_object_mut() {
+            for setting in &Self::METADATA_SETTINGS {
+                code_settings.remove(*setting);
+            }
+        }
+        let mut sources = source_tree_hash(&directory)?.0.to_vec();
+        let mut code_sources = code_tree_hash(&directory)?.0.to_vec();
+        for dependency in path_dependencies(crate_metadata) {
+            sources.extend(source_tree_hash(&dependency)?.0.iter());
+            code_sources.extend(code_tree_hash(&dependency)?.0.iter());
+        }
+        let inputs = fingerprint(&sources, &lock, &rustc, settings)?;
+        let code = fingerprint(&code_sources, &lock, &rustc, &code_settings)?;
+        Ok(Fingerprint { inputs, code })
+    }
+
+    /// Returns the record of the previous build, if it had the same fingerprint and all of its
+    /// artifacts still exist.
+    pub fn previous_build(
+        &self,
+        crate_metadata: &CrateMetadata,
+        build_artifact: BuildArtifacts,
+    ) -> Result<Option<BuildRecord>> {
+        let record = match read_record(crate_metadata)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        if record.fingerprint != self.inputs {
+            return Ok(None);
+        }
+        let artifacts_exist = match build_artifact {
+            BuildArtifacts::All => {
+                let metadata = metadata::artifact_paths(crate_metadata);
+                crate_metadata.dest_wasm.exists()
+                    && metadata.dest_metadata.exists()
+                    && metadata.dest_bundle.exists()
+            }
+            BuildArtifacts::BundleOnly => {
+                crate_metadata.dest_wasm.exists()
+                    && metadata::artifact_paths(crate_metadata)
+                        .dest_bundle
+                        .exists()
+            }
+            BuildArtifacts::CodeOnly => crate_metadata.dest_wasm.exists(),
+            BuildArtifacts::CheckOnly | BuildArtifacts::MetadataOnly => false,
+        };
+        Ok(if artifacts_exist { Some(record) } else { None })
+    }
+
+    /// Returns the record of the previous build, if it built the same Wasm and the Wasm still
+    /// exists.
+    ///
+    /// Only the metadata has to be regenerated in this case.
+    pub fn previous_code_build(
+        &self,
+        crate_metadata: &CrateMetadata,
+    ) -> Result<Option<BuildRecord>> {
+        let record = match read_record(crate_metadata)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let same_code = record.code_fingerprint.as_deref() == Some(self.code.as_str());
+        Ok(if same_code && crate_metadata.dest_wasm.exists() {
+            Some(record)
+        } else {
+            None
+        })
+    }
+
+    /// Records the build with this fingerprint, so that it can be skipped next time.
+    pub fn record(
+        self,
+        crate_metadata: &CrateMetadata,
+        optimization_result: &OptimizationResult,
+    ) -> Result<()> {
+        let record = BuildRecord {
+            fingerprint: self.inputs,
+            code_fingerprint: Some(self.code),
+            original_size: optimization_result.original_size,
+            optimized_size: optimization_result.optimized_size,
+            optimizer_version: optimization_result.optimizer_version.clone(),
+            instrumented_size: optimization_result.instrumented_size,
+        };
+        let path = record_path(crate_metadata);
+        fs::write(&path, serde_json::to_vec_pretty(&record)?).context(format!(
+            "Writing the build record {} failed",
+            path.display()
+        ))
+    }
+}
+
+/// Returns the directories of the local path dependencies of the contract, i.e. the packages it
+/// depends on which are not fetched from a registry or git, in a stable order.
+fn path_dependencies(crate_metadata: &CrateMetadata) -> Vec<PathBuf> {
+    let metadata = &crate_metadata.cargo_meta;
+    let resolve = match &metadata.resolve {
+        Some(resolve) => resolve,
+        None => return Vec::new(),
+    };
+    let mut reachable = HashSet::new();
+    let mut pending = vec![&crate_metadata.root_package.id];
+    while let Some(id) = pending.pop() {
+        if let Some(node) = resolve.nodes.iter().find(|node| &node.id == id) {
+            for dependency in &node.dependencies {
+                if reachable.insert(dependency) {
+                    pending.push(dependency);
+                }
+            }
+        }
+    }
+    let mut directories = metadata
+        .packages
+        .iter()
+        .filter(|package| package.source.is_none() && reachable.contains(&package.id))
+        .filter(|package| package.id != crate_metadata.root_package.id)
+        .filter_map(|package| package.manifest_path.parent())
+        .map(|directory| PathBuf::from(directory.as_str()))
+        .collect::<Vec<_>>();
+    directories.sort();
+    directories.dedup();
+    directories
+}
+
+/// Cache of optimized Wasm binaries, keyed by the hash of the unoptimized Wasm and the settings
+/// of the optimizer.
+///
+/// Avoids re-running `wasm-opt` if cargo produced the same Wasm as in a previous build.
+pub(crate) struct OptimizedWasmCache {
+    directory: PathBuf,
+}
+
+impl OptimizedWasmCache {
+    /// Maximum number of optimized binaries kept in the cache, the least recently used ones are
+    /// evicted.
+    const MAX_ENTRIES: usize = 16;
+
+    /// Returns the cache located in the target directory of the contract.
+    pub fn new(crate_metadata: &CrateMetadata) -> Self {
+        Self {
+            directory: crate_metadata.target_directory.join("wasm-opt-cache"),
+        }
+    }
+
+    /// Returns the cache key of the unoptimized `wasm` optimized with the `settings`.
+    ///
+    /// The `settings` have to contain everything influencing the output of the optimizer,
+    /// including its version.
+    pub fn key(wasm: &[u8], settings: &str) -> String {
+        let mut content = blake2_hash(wasm).0.to_vec();
+        content.extend(settings.as_bytes());
+        blake2_hash(&content)
+            .0
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Returns the path of the cached optimized Wasm for `key`, if any.
+    pub fn get(&self, key: &str) -> Option<PathBuf> {
+        let path = self.entry(key);
+        if !path.exists() {
+            return None;
+        }
+        // mark the entry as recently used by rewriting it, a failure only affects eviction
+        if let Ok(wasm) = fs::read(&path) {
+            let _ = fs::write(&path, wasm);
+        }
+        Some(path)
+    }
+
+    /// Stores the `optimized` Wasm under `key` and evicts the least recently used entries.
+    pub fn insert(&self, key: &str, optimized: &Path) -> Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        fs::copy(optimized, self.entry(key)).context(format!(
+            "Caching the optimized wasm in {} failed",
+            self.directory.display()
+        ))?;
+        self.evict()
+    }
+
+    /// Removes the least recently used entries exceeding `MAX_ENTRIES`.
+    fn evict(&self) -> Result<()> {
+        let mut entries = fs::read_dir(&self.directory)?
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                Some((modified, entry.path()))
+            })
+            .collect::<Vec<_>>();
+        if entries.len() <= Self::MAX_ENTRIES {
+            return Ok(());
+        }
+        entries.sort();
+        let excess = entries.len() - Self::MAX_ENTRIES;
+        for (_, path) in entries.into_iter().take(excess) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn entry(&self, key: &str) -> PathBuf {
+        self.directory.join(key).with_extension("wasm")
+    }
+}
+
+/// Returns the hash of the inputs of a build, formatted as hex.
+fn fingerprint(
+    sources: &[u8],
+    lock: &[u8],
+    rustc: &str,
+    settings: &serde_json::Value,
+) -> Result<String> {
+    let mut content = Vec::new();
+    content.extend(sources.iter());
+    content.extend(blake2_hash(lock).0.iter());
+    content.extend(env!("CARGO_PKG_VERSION").as_bytes());
+    content.push(0);
+    content.extend(rustc.as_bytes());
+    content.push(0);
+    content.extend(serde_json::to_vec(settings)?);
+    Ok(blake2_hash(&content)
+        .0
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Reads the record of the previous build of the contract, if there is a valid one.
+fn read_record(crate_metadata: &CrateMetadata) -> Result<Option<BuildRecord>> {
+    let path = record_path(crate_metadata);
+    if !path.exists() {
+        return Ok(None);
+    }
+    match serde_json::from_slice(&fs::read(&path)?) {
+        Ok(record) => Ok(Some(record)),
+        Err(err) => {
+            log::warn!("Ignoring invalid build record {}: {}", path.display(), err);
+            Ok(None)
+        }
+    }
+}
+
+/// Path of the record of the previous build of the contract.
+fn record_path(crate_metadata: &CrateMetadata) -> PathBuf {
+    crate_metadata
+        .target_directory
+        .join(format!("{}.build.json", crate_metadata.package_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    #[test]
+    fn build_record_roundtrips() {
+        let record = BuildRecord {
+            fingerprint: "00ff".to_string(),
+            code_fingerprint: Some("0f0f".to_string()),
+            original_size: 12.5,
+            optimized_size: 3.25,
+            optimizer_version: "wasm-opt version 101".to_string(),
+            instrumented_size: Some(4.5),
+        };
+
+        let json = serde_json::to_vec(&record).unwrap();
+        let decoded: BuildRecord = serde_json::from_slice(&json).unwrap();
+
+        assert_eq!(decoded.fingerprint, "00ff");
+        assert_eq!(decoded.code_fingerprint.as_deref(), Some("0f0f"));
+        assert_eq!(decoded.optimized_size, 3.25);
+        assert_eq!(decoded.optimizer_version, "wasm-opt version 101");
+        assert_eq!(decoded.instrumented_size, Some(4.5));
+    }
+
+    #[test]
+    fn cache_key_depends_on_wasm_and_settings() {
+        let key = OptimizedWasmCache::key(b"wasm", "-O3 wasm-opt version 101");
+
+        assert_eq!(
+            key,
+            OptimizedWasmCache::key(b"wasm", "-O3 wasm-opt version 101")
+        );
+        assert_ne!(
+            key,
+            OptimizedWasmCache::key(b"wasm!", "-O3 wasm-opt version 101")
+        );
+        assert_ne!(
+            key,
+            OptimizedWasmCache::key(b"wasm", "-Oz wasm-opt version 101")
+        );
+    }
+
+    #[test]
+    fn cached_wasm_is_returned_and_old_entries_are_evicted() {
+        with_tmp_dir(|path| {
+            // given
+            let cache = OptimizedWasmCache {
+                directory: path.join("cache"),
+            };
+            let optimized = path.join("optimized.wasm");
+            fs::write(&optimized, b"optimized")?;
+
+            // when
+            for index in 0..=OptimizedWasmCache::MAX_ENTRIES {
+                cache.insert(&format!("{:02}", index), &optimized)?;
+            }
+
+            // then
+            let cached = cache.get("16").expect("entry must be cached");
+            assert_eq!(fs::read(cached)?, b"optimized");
+            assert_eq!(
+                fs::read_dir(path.join("cache"))?.count(),
+                OptimizedWasmCache::MAX_ENTRIES
+            );
+            assert!(cache.get("unknown").is_none());
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,451 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Access to the `<name>.contract` bundle, which contains the metadata of a contract together
+//! with its Wasm in `source.wasm`.
+//!
+//! Bundles built with `--compress-bundle` contain the zstd compressed Wasm, which is indicated by
+//! `source.compression`.
+//!
+//! A bundle may carry an author `signature` over the hashes of its Wasm and its metadata, attached
+//! by `cargo contract bundle sign`. The metadata hash covers the whole bundle except the Wasm,
+//! its compression and the signature itself.
+
+use crate::cmd::metadata::{blake2_hash, WASM_COMPRESSION};
+#[cfg(feature = "extrinsics")]
+use crate::cmd::{trait_definition::contract_of, verify_metadata::label};
+use anyhow::{Context, Result};
+use impl_serde::serialize as serde_hex;
+#[cfg(feature = "extrinsics")]
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+#[cfg(feature = "extrinsics")]
+use sp_core::{
+    crypto::{AccountId32, Pair, Ss58Codec},
+    sr25519,
+};
+#[cfg(feature = "extrinsics")]
+use std::fmt;
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The file extension of contract bundles.
+const BUNDLE_EXTENSION: &str = "contract";
+
+/// The signature scheme of bundle signatures.
+#[cfg(feature = "extrinsics")]
+const SIGNATURE_SCHEME: &str = "sr25519";
+
+/// The author signature of a bundle, stored in its `signature`.
+#[cfg(feature = "extrinsics")]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleSignature {
+    /// The signature scheme, always `sr25519`.
+    scheme: String,
+    /// The SS58 address of the author.
+    signer: String,
+    /// The BLAKE2 256-bit hash of the Wasm, i.e. the code hash.
+    wasm_hash: String,
+    /// The BLAKE2 256-bit hash of the metadata.
+    metadata_hash: String,
+    /// The signature over the concatenated Wasm and metadata hashes.
+    signature: String,
+}
+
+/// The callable items of a contract, as listed in the `spec` of its metadata.
+#[cfg(feature = "extrinsics")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SpecItem {
+    Constructor,
+    Message,
+}
+
+#[cfg(feature = "extrinsics")]
+impl SpecItem {
+    fn key(self) -> &'static str {
+        match self {
+            SpecItem::Constructor => "constructors",
+            SpecItem::Message => "messages",
+        }
+    }
+}
+
+#[cfg(feature = "extrinsics")]
+impl fmt::Display for SpecItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpecItem::Constructor => write!(f, "constructor"),
+            SpecItem::Message => write!(f, "message"),
+        }
+    }
+}
+
+/// A `<name>.contract` bundle.
+#[derive(Debug)]
+pub(crate) struct ContractBundle {
+    path: PathBuf,
+    /// The JSON object of the bundle.
+    json: Value,
+}
+
+impl ContractBundle {
+    /// Returns `true` if the file at `path` is a contract bundle, judged by its extension.
+    pub fn is_bundle(path: &Path) -> bool {
+        path.extension() == Some(OsStr::new(BUNDLE_EXTENSION))
+    }
+
+    /// Loads the bundle at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents =
+            fs::read(path).context(format!("Failed to read the bundle {}", path.display()))?;
+        Self::parse(path, &contents)
+    }
+
+    /// Parses the `contents` of a bundle, e.g. fetched from a registry, originating from `path`.
+    pub fn parse(path: &Path, contents: &[u8]) -> Result<Self> {
+        let json: Map<String, Value> = serde_json::from_slice(contents)
+            .context(format!("The bundle {} is no valid JSON", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            json: Value::Object(json),
+        })
+    }
+
+    /// Returns the Wasm of the contract, after checking it against the code hash of the bundle.
+    ///
+    /// Compressed Wasm is decompressed transparently.
+    pub fn wasm(&self) -> Result<Vec<u8>> {
+        let wasm = self
+            .source_field("wasm")
+            .context("The bundle contains no Wasm, it was probably built with `code-only`")?;
+        let mut wasm = decode_hex(wasm)?;
+        match self
+            .json
+            .get("source")
+            .and_then(|source| source.get("compression"))
+        {
+            None => (),
+            Some(Value::String(compression)) if compression == WASM_COMPRESSION => {
+                wasm = zstd::decode_all(&wasm[..]).context(format!(
+                    "Failed to decompress the Wasm of the bundle {}",
+                    self.path.display()
+                ))?;
+            }
+            Some(compression) => {
+                anyhow::bail!("Unsupported compression {} of the Wasm", compression)
+            }
+        }
+        if blake2_hash(&wasm).0 != self.code_hash()? {
+            anyhow::bail!(
+                "The Wasm of the bundle {} doesn't match its code hash",
+                self.path.display()
+            )
+        }
+        Ok(wasm)
+    }
+
+    /// Returns the code hash of the contract, i.e. the BLAKE2 256-bit hash of its Wasm.
+    pub fn code_hash(&self) -> Result<[u8; 32]> {
+        let hash = decode_hex(self.source_field("hash")?)?;
+        let mut code_hash = [0u8; 32];
+        if hash.len() != code_hash.len() {
+            anyhow::bail!("The code hash of the bundle should be 32 bytes in length")
+        }
+        code_hash.copy_from_slice(&hash);
+        Ok(code_hash)
+    }
+
+    /// Returns the JSON of the bundle.
+    pub fn json(&self) -> &Map<String, Value> {
+        self.json
+            .as_object()
+            .expect("the bundle is parsed as JSON object; qed")
+    }
+
+    /// Returns the field `name` of the `source` of the bundle.
+    fn source_field(&self, name: &str) -> Result<&str> {
+        self.json
+            .get("source")
+            .and_then(|source| source.get(name))
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("The bundle has no `source.{}`", name))
+    }
+}
+
+/// Signing and the lookup of selectors, used by the commands interacting with a chain.
+#[cfg(feature = "extrinsics")]
+impl ContractBundle {
+    /// Returns the path the bundle was loaded from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the name of the contract, as recorded in `contract.name`.
+    pub fn name(&self) -> Option<&str> {
+        self.json
+            .get("contract")
+            .and_then(|contract| contract.get("name"))
+            .and_then(Value::as_str)
+    }
+
+    /// Signs the hashes of the Wasm and the metadata with `pair`, replacing any previous
+    /// signature.
+    ///
+    /// Returns the account of the author.
+    pub fn sign(&mut self, pair: &sr25519::Pair) -> Result<AccountId32> {
+        let wasm_hash = blake2_hash(&self.wasm()?).0;
+        let metadata_hash = self.metadata_hash()?;
+        let signature = pair.sign(&signing_payload(&wasm_hash, &metadata_hash));
+        let signer = AccountId32::from(pair.public().0);
+        let signature = BundleSignature {
+            scheme: SIGNATURE_SCHEME.to_string(),
+            signer: signer.to_ss58check(),
+            wasm_hash: encode_hex(&wasm_hash),
+            metadata_hash: encode_hex(&metadata_hash),
+            signature: encode_hex(signature.as_ref()),
+        };
+        self.json["signature"] = serde_json::to_value(signature)?;
+        Ok(signer)
+    }
+
+    /// Verifies the author signature of the bundle against its current Wasm and metadata.
+    ///
+    /// Returns the account of the author, or `None` if the bundle is not signed.
+    pub fn verify_signature(&self) -> Result<Option<AccountId32>> {
+        let signature = match self.json.get("signature") {
+            Some(signature) => signature,
+            None => return Ok(None),
+        };
+        let signature: BundleSignature = serde_json::from_value(signature.clone())
+            .context("The signature of the bundle is malformed")?;
+        if signature.scheme != SIGNATURE_SCHEME {
+            anyhow::bail!("Unsupported signature scheme {}", signature.scheme)
+        }
+        let wasm_hash = blake2_hash(&self.wasm()?).0;
+        if decode_hex(&signature.wasm_hash)? != wasm_hash {
+            anyhow::bail!("The Wasm of the bundle was modified after it was signed")
+        }
+        let metadata_hash = self.metadata_hash()?;
+        if decode_hex(&signature.metadata_hash)? != metadata_hash {
+            anyhow::bail!("The metadata of the bundle was modified after it was signed")
+        }
+
+        let signer = AccountId32::from_ss58check(&signature.signer).map_err(|err| {
+            anyhow::anyhow!("Invalid signer address {}: {:?}", signature.signer, err)
+        })?;
+        let raw_signature = decode_hex(&signature.signature)?;
+        if raw_signature.len() != 64 {
+            anyhow::bail!("The signature of the bundle should be 64 bytes in length")
+        }
+        let public = sr25519::Public::from_raw(*signer.as_ref());
+        let payload = signing_payload(&wasm_hash, &metadata_hash);
+        if !sr25519::Pair::verify(
+            &sr25519::Signature::from_slice(&raw_signature),
+            &payload[..],
+            &public,
+        ) {
+            anyhow::bail!("Invalid signature of {}", signature.signer)
+        }
+        Ok(Some(signer))
+    }
+
+    /// Writes the bundle back to the path it was loaded from.
+    pub fn save(&self) -> Result<()> {
+        fs::write(&self.path, serde_json::to_string(&self.json)?).context(format!(
+            "Failed to write the bundle {}",
+            self.path.display()
+        ))
+    }
+
+    /// Returns the selector of the constructor or message with the label `name`.
+    pub fn selector(&self, item: SpecItem, name: &str) -> Result<Vec<u8>> {
+        let items = self
+            .spec()?
+            .get(item.key())
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow::anyhow!("The metadata contains no {}s", item))?;
+        let labels = items.iter().map(label).collect::<Vec<_>>();
+        let selector = items
+            .iter()
+            .zip(&labels)
+            .find(|(_, label)| label.as_deref() == Some(name))
+            .and_then(|(item, _)| item.get("selector"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No {} `{}` found, expected one of: {}",
+                    item,
+                    name,
+                    labels
+                        .iter()
+                        .flatten()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?;
+        decode_hex(selector)
+    }
+
+    /// Returns the BLAKE2 256-bit hash of the bundle without the Wasm, its compression and the
+    /// signature.
+    fn metadata_hash(&self) -> Result<[u8; 32]> {
+        let mut json = self.json().clone();
+        json.remove("signature");
+        if let Some(source) = json.get_mut("source").and_then(Value::as_object_mut) {
+            source.remove("wasm");
+            source.remove("compression");
+        }
+        Ok(blake2_hash(&serde_json::to_vec(&json)?).0)
+    }
+
+    /// Returns the `spec` of the metadata, which is nested in the version of the metadata format
+    /// for newer ink! versions.
+    fn spec(&self) -> Result<&Map<String, Value>> {
+        contract_of(&self.json)
+            .get("spec")
+            .and_then(Value::as_object)
+            .ok_or_else(|| anyhow::anyhow!("The bundle contains no contract spec"))
+    }
+}
+
+/// Returns the message signed by the author of a bundle.
+#[cfg(feature = "extrinsics")]
+fn signing_payload(wasm_hash: &[u8; 32], metadata_hash: &[u8; 32]) -> Vec<u8> {
+    let mut payload = wasm_hash.to_vec();
+    payload.extend(metadata_hash);
+    payload
+}
+
+/// Encodes `data` as `0x` prefixed hex string.
+#[cfg(feature = "extrinsics")]
+fn encode_hex(data: &[u8]) -> String {
+    serde_hex::to_hex(data, false)
+}
+
+/// Decodes a `0x` prefixed hex string.
+fn decode_hex(input: &str) -> Result<Vec<u8>> {
+    serde_hex::from_hex(input).map_err(|err| anyhow::anyhow!("Invalid hex data {}: {}", input, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::metadata::compress_source_wasm, util::tests::with_tmp_dir};
+    use serde_json::json;
+
+    fn write_bundle(path: &Path, wasm: &[u8]) -> Result<PathBuf> {
+        let bundle = json!({
+            "metadataVersion": "0.1.0",
+            "source": {
+                "hash": serde_hex::to_hex(&blake2_hash(wasm).0, false),
+                "wasm": serde_hex::to_hex(wasm, false),
+            },
+            "spec": {
+                "constructors": [{ "name": ["new"], "selector": "0x9bae9d5e", "args": [] }],
+                "messages": [{ "name": ["flip"], "selector": "0x633aa551", "args": [] }],
+            },
+        });
+        let bundle_path = path.join("flipper.contract");
+        fs::write(&bundle_path, bundle.to_string())?;
+        Ok(bundle_path)
+    }
+
+    #[test]
+    fn wasm_and_code_hash_are_read_from_bundle() {
+        with_tmp_dir(|path| {
+            // given
+            let bundle_path = write_bundle(path, b"\0asm")?;
+
+            // when
+            let bundle = ContractBundle::load(&bundle_path)?;
+
+            // then
+            assert!(ContractBundle::is_bundle(&bundle_path));
+            assert_eq!(bundle.wasm()?, b"\0asm".to_vec());
+            assert_eq!(bundle.code_hash()?, blake2_hash(b"\0asm").0);
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[cfg(feature = "extrinsics")]
+    fn selectors_are_read_from_bundle() {
+        with_tmp_dir(|path| {
+            // given
+            let bundle = ContractBundle::load(&write_bundle(path, b"\0asm")?)?;
+
+            // when
+            let constructor = bundle.selector(SpecItem::Constructor, "new")?;
+            let message = bundle.selector(SpecItem::Message, "flip")?;
+            let unknown = bundle.selector(SpecItem::Message, "get");
+
+            // then
+            assert_eq!(constructor, vec![0x9b, 0xae, 0x9d, 0x5e]);
+            assert_eq!(message, vec![0x63, 0x3a, 0xa5, 0x51]);
+            assert!(unknown
+                .unwrap_err()
+                .to_string()
+                .contains("expected one of: flip"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn compressed_wasm_is_decompressed() {
+        with_tmp_dir(|path| {
+            // given
+            let wasm = b"\0asm\x01\0\0\0".repeat(64);
+            let bundle_path = write_bundle(path, &wasm)?;
+            let mut bundle: Value = serde_json::from_slice(&fs::read(&bundle_path)?)?;
+            compress_source_wasm(&mut bundle)?;
+            fs::write(&bundle_path, bundle.to_string())?;
+
+            // when
+            let bundle = ContractBundle::load(&bundle_path)?;
+
+            // then
+            assert_eq!(bundle.source_field("compression")?, WASM_COMPRESSION);
+            assert!(bundle.source_field("wasm")?.len() < 2 * wasm.len());
+            assert_eq!(bundle.wasm()?, wasm);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn tampered_wasm_is_rejected() {
+        with_tmp_dir(|path| {
+            // given
+            let bundle_path = write_bundle(path, b"\0asm")?;
+            let tampered = fs::read_to_string(&bundle_path)?.replace(
+                &serde_hex::to_hex(b"\0asm", false),
+                &serde_hex::to_hex(b"\0wasm", false),
+            );
+            fs::write(&bundle_path, tampered)?;
+
+            // when
+            let res = ContractBundle::load(&bundle_path)?.wasm();
+
+            // then
+            assert!(res.is_err());
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    metadata,
+    trait_definition::{contract_of, Registry},
+    verify_metadata::label,
+};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fs,
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+/// The file name of the snapshot, next to the `Cargo.toml` of the contract.
+const SNAPSHOT_FILE: &str = "abi.snapshot";
+
+/// Stores or checks a snapshot of the public ABI of a contract.
+///
+/// The snapshot is a normalized, line based rendering of the metadata: the selector and
+/// signature of every constructor and message, the events with their indexed fields and the
+/// definitions of the custom types they refer to. Types are rendered structurally, so that
+/// changes of e.g. the `Balance` type are detected, and the lines are sorted, so that
+/// reordering the sources doesn't change the snapshot. Commit the snapshot and check it in CI
+/// to protect against accidental breaking changes.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "abi-snapshot")]
+pub struct AbiSnapshotCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Path to the metadata or the `<name>.contract` bundle, defaults to
+    /// `./target/ink/metadata.json`
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+    /// Path to the snapshot, defaults to `abi.snapshot` next to the Cargo.toml
+    #[structopt(long, parse(from_os_str))]
+    snapshot: Option<PathBuf>,
+    /// Fail if the ABI differs from the snapshot instead of updating it
+    #[structopt(long)]
+    check: bool,
+}
+
+impl AbiSnapshotCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+                metadata::artifact_paths(&crate_metadata).dest_metadata
+            }
+        };
+        let contents = fs::read(&path).context(format!(
+            "Failed to read the metadata {}, build the contract first",
+            path.display()
+        ))?;
+        let metadata: Value = serde_json::from_slice(&contents)
+            .context(format!("The metadata {} is no valid JSON", path.display()))?;
+        let snapshot_path = match &self.snapshot {
+            Some(snapshot) => snapshot.clone(),
+            None => manifest_path.absolute_directory()?.join(SNAPSHOT_FILE),
+        };
+        let current = snapshot(&metadata)?;
+
+        if !self.check {
+            fs::write(&snapshot_path, &current).context(format!(
+                "Failed to write the snapshot {}",
+                snapshot_path.display()
+            ))?;
+            return Ok(format!(
+                "Wrote the ABI snapshot {}",
+                snapshot_path.display()
+            ));
+        }
+        let stored = fs::read_to_string(&snapshot_path).context(format!(
+            "Failed to read the snapshot {}, create it by `cargo contract abi-snapshot`",
+            snapshot_path.display()
+        ))?;
+        let changes = diff(&stored, &current);
+        if !changes.is_empty() {
+            anyhow::bail!(
+                "The ABI differs from the snapshot {}:\n  {}\n\nUpdate the snapshot by \
+                `cargo contract abi-snapshot` if the changes are intended",
+                snapshot_path.display(),
+                changes.join("\n  ")
+            )
+        }
+        Ok(format!(
+            "The ABI matches the snapshot {}",
+            snapshot_path.display()
+        ))
+    }
+}
+
+/// Returns the snapshot of the ABI of the contract described by the `metadata`.
+fn snapshot(metadata: &Value) -> Result<String> {
+    let contract = contract_of(metadata);
+    let registry = Registry::new(contract).with_derives("");
+    let mut definitions = BTreeMap::new();
+    let mut signature = |item: &Value, kind: &str| -> Result<String> {
+        let name = label(item).ok_or_else(|| anyhow::anyhow!("A {} has no name", kind))?;
+        let args = item["args"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|arg| {
+                let ty = arg["type"]["type"]
+                    .as_u64()
+                    .ok_or_else(|| anyhow::anyhow!("An argument of {} has no type", name))?;
+                let indexed = if arg["indexed"] == true {
+                    " indexed"
+                } else {
+                    ""
+                };
+                Ok(format!(
+                    "{}: {}{}",
+                    label(arg).unwrap_or_else(|| "_".into()),
+                    registry.render(ty, &mut definitions)?,
+                    indexed
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut signature = format!("{} {}({})", kind, name, args.join(", "));
+        if let Some(ty) = item["returnType"]["type"].as_u64() {
+            signature.push_str(&format!(" -> {}", registry.render(ty, &mut definitions)?));
+        }
+        if let Some(selector) = item["selector"].as_str() {
+            signature.push_str(&format!(" {}", selector));
+        }
+        for flag in &["mutates", "payable"] {
+            if item[*flag] == true {
+                signature.push_str(&format!(" {}", flag));
+            }
+        }
+        Ok(signature)
+    };
+
+    let mut lines = BTreeSet::new();
+    for (key, kind) in &[
+        ("constructors", "constructor"),
+        ("messages", "message"),
+        ("events", "event"),
+    ] {
+        for item in contract["spec"][*key]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            lines.insert(signature(item, kind)?);
+        }
+    }
+    let types = definitions
+        .values()
+        .filter(|definition| !definition.is_empty())
+        .map(|definition| {
+            definition
+                .replace("(pub ", "(")
+                .replace(", pub ", ", ")
+                .split_whitespace()
+                .filter(|token| *token != "pub")
+                .collect::<Vec<_>>()
+                .join(" ")
+                .replace(", }", " }")
+        })
+        .map(|definition| format!("type {}", definition.trim_end_matches(';')));
+    Ok(lines
+        .into_iter()
+        .chain(types)
+        .map(|line| format!("{}\n", line))
+        .collect())
+}
+
+/// Returns the lines removed from the `stored` snapshot, prefixed by `-`, and the lines added in
+/// the `current` one, prefixed by `+`.
+fn diff(stored: &str, current: &str) -> Vec<String> {
+    let stored = stored.lines().collect::<BTreeSet<_>>();
+    let current = current.lines().collect::<BTreeSet<_>>();
+    stored
+        .difference(&current)
+        .map(|line| format!("- {}", line))
+        .chain(
+            current
+                .difference(&stored)
+                .map(|line| format!("+ {}", line)),
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata(balance: &str) -> Value {
+        json!({
+            "V1": {
+                "types": [
+                    { "id": 0, "type": { "def": { "primitive": balance } } },
+                    { "id": 1, "type": { "path": ["erc20", "erc20", "Error"], "def": { "variant": {
+                      "variants": [{ "name": "InsufficientBalance" },
+                                   { "name": "Custom", "fields": [{ "type": 2 }] }] } } } },
+                    { "id": 2, "type": { "def": { "primitive": "str" } } },
+                    { "id": 3, "type": { "path": ["Result"], "params": [4, 1],
+                      "def": { "variant": { "variants": [
+                        { "name": "Ok", "fields": [{ "type": 4 }] },
+                        { "name": "Err", "fields": [{ "type": 1 }] }] } } } },
+                    { "id": 4, "type": { "def": { "tuple": [] } } },
+                ],
+                "spec": {
+                    "constructors": [{ "label": "new", "selector": "0x9bae9d5e", "payable": false,
+                      "args": [{ "label": "supply", "type": { "type": 0 } }] }],
+                    "messages": [
+                        { "label": "transfer", "selector": "0x84a15da1", "mutates": true,
+                          "payable": false, "returnType": { "type": 3 },
+                          "args": [{ "label": "value", "type": { "type": 0 } }] },
+                        { "label": "total_supply", "selector": "0xdb6375a8", "mutates": false,
+                          "payable": false, "returnType": { "type": 0 }, "args": [] },
+                    ],
+                    "events": [{ "label": "Transfer", "args": [
+                        { "label": "value", "indexed": true, "type": { "type": 0 } }] }],
+                },
+            },
+        })
+    }
+
+    #[test]
+    fn snapshot_is_normalized() {
+        let snapshot = snapshot(&metadata("u128")).unwrap();
+
+        assert_eq!(
+            snapshot,
+            "constructor new(supply: u128) 0x9bae9d5e\n\
+            event Transfer(value: u128 indexed)\n\
+            message total_supply() -> u128 0xdb6375a8\n\
+            message transfer(value: u128) -> Result<(), Error> 0x84a15da1 mutates\n\
+            type enum Error { InsufficientBalance, Custom(String) }\n"
+        );
+    }
+
+    #[test]
+    fn changed_types_are_reported() {
+        let stored = snapshot(&metadata("u128")).unwrap();
+        let current = snapshot(&metadata("u64")).unwrap();
+
+        let changes = diff(&stored, &current);
+
+        assert_eq!(changes.len(), 8);
+        assert!(changes.contains(&"- message total_supply() -> u128 0xdb6375a8".to_string()));
+        assert!(changes.contains(&"+ message total_supply() -> u64 0xdb6375a8".to_string()));
+        assert!(diff(&stored, &stored).is_empty());
+    }
+}
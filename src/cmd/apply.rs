@@ -0,0 +1,807 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Declarative deployments of contracts, described by a deployment manifest:
+//!
+//! ```yaml
+//! contracts:
+//!   - name: token
+//!     bundle: target/ink/erc20.contract
+//!     constructor: new
+//!     args: [1000000]
+//!   - name: exchange
+//!     bundle: ../exchange/target/ink/exchange.contract
+//!     args: ["${token}"]
+//!     endowment: "10000000000000000"
+//! fund:
+//!   - to: "${exchange}"
+//!     amount: 1000000000000
+//! networks:
+//!   production:
+//!     contracts:
+//!       token:
+//!         args: [21000000]
+//! ```
+//!
+//! Contracts are instantiated in order, `${name}` arguments refer to the address of an earlier
+//! contract. The overrides of the `--network` profile replace the settings of its contracts and
+//! the funding steps.
+//!
+//! The applied contracts and funding steps are kept per node in a state file next to the
+//! manifest, e.g. `deploy.state.json`. Contracts whose code or constructor call changed are
+//! instantiated again, as are the contracts referring to them.
+
+use super::{
+    deploy::load_contract_code,
+    scale_json::{item, ScaleJson},
+    trait_definition::{contract_of, Registry},
+    verify::is_code_stored,
+};
+use crate::{bundle::ContractBundle, deployments, rpc::RpcClient, ExtrinsicOpts};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use impl_serde::serialize as serde_hex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sp_core::{
+    crypto::{AccountId32, Ss58Codec},
+    H256,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+use subxt::{balances::*, contracts::*, system::System, ClientBuilder, DefaultNodeRuntime};
+
+/// The constructor called if a contract doesn't specify one.
+const DEFAULT_CONSTRUCTOR: &str = "new";
+
+/// The endowment of contracts which don't specify one.
+const DEFAULT_ENDOWMENT: u128 = 1_000_000_000_000_000;
+
+/// Applies a deployment manifest to a chain, printing the plan of changes first.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "apply")]
+pub struct ApplyCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Path to the deployment manifest
+    #[structopt(parse(from_os_str), default_value = "deploy.yaml")]
+    file: PathBuf,
+    /// Only print the plan, without submitting any extrinsics
+    #[structopt(long)]
+    plan: bool,
+    /// Apply the plan without asking for confirmation
+    #[structopt(long, short)]
+    yes: bool,
+    /// Maximum amount of gas to be used for instantiations
+    #[structopt(name = "gas", long, default_value = "500000000")]
+    gas_limit: u64,
+}
+
+impl ApplyCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest = DeployManifest::load(&self.file, self.extrinsic_opts.network.as_deref())?;
+        let url = self.extrinsic_opts.url()?;
+        let state_path = self.file.with_extension("state.json");
+        let mut state = State::load(&state_path)?;
+        let network_state = state.0.entry(url.to_string()).or_default();
+
+        let rpc = RpcClient::new(&url)?;
+        let steps = plan(&manifest, network_state, |code_hash| {
+            is_code_stored(&rpc, code_hash)
+        })?;
+        println!("{} {}", "Plan for".bold(), url);
+        for step in &steps {
+            println!("  {}", step);
+        }
+        let changes = steps
+            .iter()
+            .filter(|step| !matches!(step, Step::Keep { .. }))
+            .count();
+        if changes == 0 {
+            return Ok("No changes, the deployment is up to date".into());
+        }
+        if self.plan {
+            return Ok(format!("{} changes planned", changes));
+        }
+        if !self.yes && !apply_confirmed()? {
+            return Ok("Nothing applied".into());
+        }
+
+        let signer = self.extrinsic_opts.signer()?;
+        let mut addresses = HashMap::new();
+        let result = async_std::task::block_on(async {
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_url(&url.to_string())
+                .build()
+                .await?;
+            for step in &steps {
+                match step {
+                    Step::Upload { bundle, .. } => {
+                        let code = load_contract_code(
+                            &self.extrinsic_opts.manifest_path()?,
+                            Some(&bundle.path().to_path_buf()),
+                        )?;
+                        let events = cli.put_code_and_watch(signer.as_ref(), &code).await?;
+                        events
+                            .code_stored()?
+                            .ok_or_else(|| anyhow::anyhow!("Failed to find CodeStored event"))?;
+                    }
+                    Step::Instantiate { name, contract, .. } => {
+                        let args = resolve_args(&contract.args, &addresses)?;
+                        let data =
+                            constructor_data(&contract.bundle, &contract.constructor, &args)?;
+                        let code_hash = contract.bundle.code_hash()?;
+                        let events = cli
+                            .instantiate_and_watch(
+                                signer.as_ref(),
+                                contract.endowment,
+                                self.gas_limit,
+                                &H256(code_hash),
+                                &data,
+                            )
+                            .await?;
+                        let instantiated = events
+                            .instantiated()?
+                            .ok_or_else(|| anyhow::anyhow!("Failed to find Instantiated event"))?;
+                        deployments::record_instantiation(
+                            &self.extrinsic_opts,
+                            Some(&contract.bundle),
+                            &instantiated.contract,
+                            &code_hash,
+                        )?;
+                        network_state.contracts.insert(
+                            name.clone(),
+                            AppliedContract {
+                                contract: instantiated.contract.to_ss58check(),
+                                code_hash: serde_hex::to_hex(&code_hash, false),
+                                data: serde_hex::to_hex(&data, false),
+                            },
+                        );
+                        addresses.insert(name.clone(), instantiated.contract);
+                    }
+                    Step::Keep { name, contract } => {
+                        addresses.insert(name.clone(), contract.clone());
+                    }
+                    Step::Fund { to, amount } => {
+                        let account = resolve_account(to, &addresses)?
+                            .expect("accounts are instantiated before they are funded; qed");
+                        let dest: <DefaultNodeRuntime as System>::Address = account.clone().into();
+                        cli.transfer_and_watch(signer.as_ref(), &dest, *amount)
+                            .await?;
+                        network_state.funded.push(AppliedFunding {
+                            to: account.to_ss58check(),
+                            amount: amount.to_string(),
+                        });
+                    }
+                }
+                println!("{} {}", "Applied".green().bold(), step);
+            }
+            Ok::<_, anyhow::Error>(())
+        });
+        // keep the state of the steps applied before a failure
+        state.save(&state_path)?;
+        result?;
+        Ok(format!(
+            "{} {} changes to {}",
+            "Applied".green().bold(),
+            changes,
+            url
+        ))
+    }
+}
+
+/// The contracts and funding steps of a deployment.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeployManifest {
+    #[serde(default)]
+    contracts: Vec<ContractSpec>,
+    #[serde(default)]
+    fund: Vec<FundStep>,
+    /// The overrides of network profiles.
+    #[serde(default)]
+    networks: BTreeMap<String, NetworkOverrides>,
+}
+
+/// A contract instantiated by the deployment.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ContractSpec {
+    name: String,
+    /// Path to the bundle of the contract, relative to the manifest.
+    bundle: PathBuf,
+    constructor: Option<String>,
+    #[serde(default)]
+    args: Vec<Value>,
+    endowment: Option<Amount>,
+}
+
+/// A transfer to an account or a contract of the deployment.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FundStep {
+    /// An SS58 address or a `${name}` reference to a contract.
+    to: String,
+    amount: Amount,
+}
+
+/// The settings replaced on a network.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NetworkOverrides {
+    #[serde(default)]
+    contracts: BTreeMap<String, ContractOverride>,
+    fund: Option<Vec<FundStep>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ContractOverride {
+    bundle: Option<PathBuf>,
+    constructor: Option<String>,
+    args: Option<Vec<Value>>,
+    endowment: Option<Amount>,
+}
+
+/// A balance, as number or as decimal string for amounts exceeding the integers of YAML.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Amount {
+    Number(u64),
+    String(String),
+}
+
+impl Amount {
+    fn value(&self) -> Result<u128> {
+        match self {
+            Amount::Number(amount) => Ok(*amount as u128),
+            Amount::String(amount) => amount.parse().context(format!("Invalid amount {}", amount)),
+        }
+    }
+}
+
+impl DeployManifest {
+    /// Loads the manifest at `path` with the overrides of the `network` applied.
+    fn load(path: &Path, network: Option<&str>) -> Result<Self> {
+        let contents =
+            fs::read(path).context(format!("Failed to read the manifest {}", path.display()))?;
+        let mut manifest: Self = serde_yaml::from_slice(&contents)
+            .context(format!("Invalid deployment manifest {}", path.display()))?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        let overrides = network
+            .and_then(|network| manifest.networks.remove(network))
+            .unwrap_or_default();
+        for (name, overrides) in overrides.contracts {
+            let contract = manifest
+                .contracts
+                .iter_mut()
+                .find(|contract| contract.name == name)
+                .ok_or_else(|| anyhow::anyhow!("The overrides refer to no contract {}", name))?;
+            if let Some(bundle) = overrides.bundle {
+                contract.bundle = bundle;
+            }
+            if overrides.constructor.is_some() {
+                contract.constructor = overrides.constructor;
+            }
+            if let Some(args) = overrides.args {
+                contract.args = args;
+            }
+            if overrides.endowment.is_some() {
+                contract.endowment = overrides.endowment;
+            }
+        }
+        if let Some(fund) = overrides.fund {
+            manifest.fund = fund;
+        }
+        for contract in &mut manifest.contracts {
+            contract.bundle = base.join(&contract.bundle);
+        }
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Checks that the names of the contracts are unique and references point to earlier ones.
+    fn validate(&self) -> Result<()> {
+        let mut names = Vec::new();
+        for contract in &self.contracts {
+            if names.contains(&contract.name.as_str()) {
+                anyhow::bail!("The contract {} is declared twice", contract.name)
+            }
+            for name in contract.args.iter().flat_map(references) {
+                if !names.contains(&name) {
+                    anyhow::bail!(
+                        "The contract {} refers to {}, which is not declared before it",
+                        contract.name,
+                        name
+                    )
+                }
+            }
+            names.push(&contract.name);
+        }
+        for fund in &self.fund {
+            match reference(&fund.to) {
+                Some(name) if !names.contains(&name) => {
+                    anyhow::bail!("The funding of {} refers to no contract", fund.to)
+                }
+                Some(_) => (),
+                None => {
+                    crate::parse_account(&fund.to)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The applied deployments, by the url of the node.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State(BTreeMap<String, NetworkState>);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct NetworkState {
+    #[serde(default)]
+    contracts: BTreeMap<String, AppliedContract>,
+    #[serde(default)]
+    funded: Vec<AppliedFunding>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AppliedContract {
+    contract: String,
+    code_hash: String,
+    /// The call data of the constructor.
+    data: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct AppliedFunding {
+    to: String,
+    /// As string, since balances may exceed the integers of JSON parsers.
+    amount: String,
+}
+
+impl State {
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        serde_json::from_slice(&fs::read(path)?)
+            .context(format!("Invalid deployment state {}", path.display()))
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// A step of the plan.
+enum Step {
+    /// Uploads the code of the bundle, which is not stored on chain yet.
+    Upload {
+        name: String,
+        bundle: ContractBundle,
+    },
+    /// Instantiates the contract, replacing a previously applied one.
+    Instantiate {
+        name: String,
+        contract: PlannedContract,
+        replaces: Option<String>,
+    },
+    /// Keeps the applied contract, which is up to date.
+    Keep {
+        name: String,
+        contract: AccountId32,
+    },
+    Fund {
+        to: String,
+        amount: u128,
+    },
+}
+
+struct PlannedContract {
+    bundle: ContractBundle,
+    constructor: String,
+    args: Vec<Value>,
+    endowment: u128,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args = |args: &[Value]| {
+            args.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Step::Upload { name, .. } => write!(f, "{} upload the code of {}", "+".green(), name),
+            Step::Instantiate {
+                name,
+                contract,
+                replaces,
+            } => {
+                match replaces {
+                    Some(address) => write!(f, "{} replace {} {}", "~".yellow(), name, address)?,
+                    None => write!(f, "{} instantiate {}", "+".green(), name)?,
+                }
+                write!(
+                    f,
+                    " with {}({})",
+                    contract.constructor,
+                    args(&contract.args)
+                )
+            }
+            Step::Keep { name, contract } => write!(
+                f,
+                "{} keep {} {}",
+                "=".dimmed(),
+                name,
+                contract.to_ss58check()
+            ),
+            Step::Fund { to, amount } => write!(f, "{} fund {} with {}", "+".green(), to, amount),
+        }
+    }
+}
+
+/// Returns the steps applying the `manifest` to a chain with the applied `state`.
+///
+/// Contracts are kept if their code and the call data of their constructor are unchanged, code is
+/// uploaded unless `is_stored`.
+fn plan(
+    manifest: &DeployManifest,
+    state: &NetworkState,
+    is_stored: impl Fn(&[u8; 32]) -> Result<bool>,
+) -> Result<Vec<Step>> {
+    let mut steps = Vec::new();
+    let mut addresses = HashMap::new();
+    let mut uploads = Vec::new();
+    // stands in for contracts which are not instantiated yet, to check the encoding of arguments
+    let placeholder = AccountId32::new([0; 32]);
+    for spec in &manifest.contracts {
+        let bundle = ContractBundle::load(&spec.bundle)?;
+        let code_hash = bundle.code_hash()?;
+        let constructor = spec
+            .constructor
+            .clone()
+            .unwrap_or_else(|| DEFAULT_CONSTRUCTOR.into());
+        let known = spec
+            .args
+            .iter()
+            .flat_map(references)
+            .all(|name| addresses.contains_key(name));
+        let mut placeholders = HashMap::new();
+        for name in spec.args.iter().flat_map(references) {
+            let address = addresses.get(name).unwrap_or(&placeholder);
+            placeholders.insert(name.to_string(), AccountId32::clone(address));
+        }
+        let data = constructor_data(
+            &bundle,
+            &constructor,
+            &resolve_args(&spec.args, &placeholders)?,
+        )
+        .context(format!("Invalid constructor call of {}", spec.name))?;
+
+        let applied = state.contracts.get(&spec.name);
+        match applied {
+            Some(applied)
+                if known
+                    && applied.code_hash == serde_hex::to_hex(&code_hash, false)
+                    && applied.data == serde_hex::to_hex(&data, false) =>
+            {
+                let contract = crate::parse_account(&applied.contract)?;
+                addresses.insert(spec.name.as_str(), contract.clone());
+                steps.push(Step::Keep {
+                    name: spec.name.clone(),
+                    contract,
+                });
+                continue;
+            }
+            _ => (),
+        }
+        if !uploads.contains(&code_hash) && !is_stored(&code_hash)? {
+            uploads.push(code_hash);
+            steps.push(Step::Upload {
+                name: spec.name.clone(),
+                bundle: ContractBundle::load(&spec.bundle)?,
+            });
+        }
+        let endowment = match &spec.endowment {
+            Some(endowment) => endowment.value()?,
+            None => DEFAULT_ENDOWMENT,
+        };
+        steps.push(Step::Instantiate {
+            name: spec.name.clone(),
+            contract: PlannedContract {
+                bundle,
+                constructor,
+                args: spec.args.clone(),
+                endowment,
+            },
+            replaces: applied.map(|applied| applied.contract.clone()),
+        });
+    }
+    for fund in &manifest.fund {
+        let amount = fund.amount.value()?;
+        let applied = resolve_account(&fund.to, &addresses)?.map_or(false, |account| {
+            let funding = AppliedFunding {
+                to: account.to_ss58check(),
+                amount: amount.to_string(),
+            };
+            state.funded.contains(&funding)
+        });
+        if !applied {
+            steps.push(Step::Fund {
+                to: fund.to.clone(),
+                amount,
+            });
+        }
+    }
+    Ok(steps)
+}
+
+/// Returns the call data of the `constructor` of the contract in the `bundle`.
+fn constructor_data(bundle: &ContractBundle, constructor: &str, args: &[Value]) -> Result<Vec<u8>> {
+    let metadata = Value::Object(bundle.json().clone());
+    let spec = contract_of(&metadata);
+    let registry = Registry::new(spec);
+    ScaleJson::new(&registry).encode_message(item(spec, "constructors", constructor)?, args)
+}
+
+/// Returns the name of the contract a `${name}` reference refers to.
+fn reference(value: &str) -> Option<&str> {
+    value.strip_prefix("${")?.strip_suffix('}')
+}
+
+/// Returns the names of the contracts referred to by the argument `value`.
+fn references(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(value) => reference(value).into_iter().collect(),
+        Value::Array(values) => values.iter().flat_map(references).collect(),
+        Value::Object(values) => values.values().flat_map(references).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Replaces the references of the `args` with the addresses of the contracts.
+fn resolve_args<K>(args: &[Value], addresses: &HashMap<K, AccountId32>) -> Result<Vec<Value>>
+where
+    K: std::borrow::Borrow<str> + std::hash::Hash + Eq,
+{
+    fn resolve<K>(value: &Value, addresses: &HashMap<K, AccountId32>) -> Result<Value>
+    where
+        K: std::borrow::Borrow<str> + std::hash::Hash + Eq,
+    {
+        Ok(match value {
+            Value::String(value) => match reference(value) {
+                Some(name) => addresses
+                    .get(name)
+                    .ok_or_else(|| anyhow::anyhow!("The contract {} is not instantiated", name))?
+                    .to_ss58check()
+                    .into(),
+                None => value.clone().into(),
+            },
+            Value::Array(values) => values
+                .iter()
+                .map(|value| resolve(value, addresses))
+                .collect::<Result<_>>()?,
+            Value::Object(values) => Value::Object(
+                values
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), resolve(value, addresses)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            _ => value.clone(),
+        })
+    }
+    args.iter().map(|arg| resolve(arg, addresses)).collect()
+}
+
+/// Returns the account funded by a step: an SS58 address or a reference to a contract, which is
+/// `None` if the contract is not instantiated yet.
+fn resolve_account<K>(to: &str, addresses: &HashMap<K, AccountId32>) -> Result<Option<AccountId32>>
+where
+    K: std::borrow::Borrow<str> + std::hash::Hash + Eq,
+{
+    match reference(to) {
+        Some(name) => Ok(addresses.get(name).cloned()),
+        None => crate::parse_account(to).map(Some),
+    }
+}
+
+/// Asks the user whether the plan should be applied.
+fn apply_confirmed() -> Result<bool> {
+    eprint!("Apply the plan? [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use serde_json::json;
+
+    const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    const ALICE_HEX: &str = "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+    const BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+
+    const MANIFEST: &str = r#"
+contracts:
+  - name: token
+    bundle: token.contract
+    args: []
+  - name: exchange
+    bundle: exchange.contract
+    args: ["${token}"]
+fund:
+  - to: "${exchange}"
+    amount: 1000
+networks:
+  production:
+    contracts:
+      exchange:
+        constructor: default
+        endowment: "100000000000000000000"
+    fund: []
+"#;
+
+    /// Writes a bundle whose `new` constructor takes the `args`, a list of `AccountId` labels.
+    fn write_bundle(path: &Path, hash: u8, args: &[&str]) -> Result<()> {
+        let args = args
+            .iter()
+            .map(|label| json!({ "label": label, "type": { "type": 1 } }))
+            .collect::<Vec<_>>();
+        let bundle = json!({
+            "source": { "hash": format!("0x{}", hex::encode([hash; 32])) },
+            "V1": {
+                "spec": {
+                    "constructors": [{ "label": "new", "selector": "0x9bae9d5e", "args": args }],
+                    "messages": [],
+                },
+                "types": [
+                    { "id": 0, "type": { "def": { "primitive": "u8" } } },
+                    { "id": 1, "type": { "path": ["ink_env", "types", "AccountId"],
+                      "def": { "composite": { "fields": [{ "type": 2 }] } } } },
+                    { "id": 2, "type": { "def": { "array": { "len": 32, "type": 0 } } } },
+                ],
+            },
+        });
+        fs::write(path, bundle.to_string())?;
+        Ok(())
+    }
+
+    #[test]
+    fn network_overrides_replace_settings() {
+        with_tmp_dir(|path| {
+            // given
+            let manifest_path = path.join("deploy.yaml");
+            fs::write(&manifest_path, MANIFEST)?;
+
+            // when
+            let local = DeployManifest::load(&manifest_path, None)?;
+            let production = DeployManifest::load(&manifest_path, Some("production"))?;
+
+            // then
+            assert_eq!(local.contracts[1].constructor, None);
+            assert_eq!(local.contracts[1].bundle, path.join("exchange.contract"));
+            assert_eq!(local.fund.len(), 1);
+            let exchange = &production.contracts[1];
+            assert_eq!(exchange.constructor.as_deref(), Some("default"));
+            assert_eq!(exchange.args, vec![json!("${token}")]);
+            assert_eq!(
+                exchange.endowment.as_ref().unwrap().value()?,
+                100_000_000_000_000_000_000
+            );
+            assert!(production.fund.is_empty());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn references_must_point_to_earlier_contracts() {
+        with_tmp_dir(|path| {
+            // given
+            let manifest_path = path.join("deploy.yaml");
+            fs::write(
+                &manifest_path,
+                "contracts:\n\
+                - { name: exchange, bundle: exchange.contract, args: [\"${token}\"] }\n\
+                - { name: token, bundle: token.contract }\n",
+            )?;
+
+            // when
+            let result = DeployManifest::load(&manifest_path, None);
+
+            // then
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "The contract exchange refers to token, which is not declared before it"
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn plan_instantiates_changed_contracts_and_their_dependents() {
+        with_tmp_dir(|path| {
+            // given
+            let manifest_path = path.join("deploy.yaml");
+            fs::write(&manifest_path, MANIFEST)?;
+            write_bundle(&path.join("token.contract"), 1, &[])?;
+            write_bundle(&path.join("exchange.contract"), 2, &["token"])?;
+            let manifest = DeployManifest::load(&manifest_path, None)?;
+            let mut state = NetworkState::default();
+            state.contracts.insert(
+                "token".into(),
+                AppliedContract {
+                    contract: ALICE.into(),
+                    code_hash: serde_hex::to_hex(&[1; 32], false),
+                    data: "0x9bae9d5e".into(),
+                },
+            );
+            state.contracts.insert(
+                "exchange".into(),
+                AppliedContract {
+                    contract: BOB.into(),
+                    code_hash: serde_hex::to_hex(&[2; 32], false),
+                    data: format!("0x9bae9d5e{}", ALICE_HEX),
+                },
+            );
+            state.funded.push(AppliedFunding {
+                to: BOB.into(),
+                amount: "1000".into(),
+            });
+            let stored = |code_hash: &[u8; 32]| Ok(code_hash == &[1; 32]);
+
+            // when
+            let unchanged = plan(&manifest, &state, stored)?;
+            state.contracts.get_mut("token").unwrap().data = "0x00".into();
+            let changed = plan(&manifest, &state, stored)?;
+
+            // then
+            assert!(matches!(
+                unchanged.as_slice(),
+                [Step::Keep { .. }, Step::Keep { .. }]
+            ));
+            // the exchange refers to the replaced token
+            assert!(matches!(
+                changed.as_slice(),
+                [
+                    Step::Instantiate {
+                        replaces: Some(_),
+                        ..
+                    },
+                    Step::Upload { .. },
+                    Step::Instantiate {
+                        replaces: Some(_),
+                        ..
+                    },
+                    Step::Fund { amount: 1000, .. },
+                ]
+            ));
+            Ok(())
+        })
+    }
+}
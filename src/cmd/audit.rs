@@ -0,0 +1,291 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::{
+    bundle::ContractBundle, crate_metadata::CrateMetadata, validate_wasm, workspace::ManifestPath,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use parity_wasm::elements::Module;
+use std::{cmp::Reverse, convert::TryFrom, fmt, fmt::Write, fs, path::PathBuf, str::FromStr};
+use structopt::StructOpt;
+
+/// The exports of an ink! contract, any other export is unexpected.
+const CONTRACT_EXPORTS: [&str; 2] = ["call", "deploy"];
+
+/// Size from which a data segment is reported as unexpectedly large.
+const LARGE_DATA_SEGMENT: usize = 4096;
+
+/// Fragments of the messages of the panic machinery, which are only contained in the data if the
+/// formatting code of panics is linked into the contract.
+const PANIC_MESSAGES: [&str; 4] = [
+    "panicked at",
+    "index out of bounds",
+    "called `Option::unwrap()`",
+    "called `Result::unwrap()`",
+];
+
+/// Host functions whose results can be predicted or influenced by block authors.
+const PREDICTABLE_HOST_FUNCTIONS: [&str; 1] = ["seal_random"];
+
+/// Scans the Wasm of a contract for red flags and prints a report of the findings.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "audit")]
+pub struct AuditCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Fail if there is a finding of at least this severity
+    #[structopt(long, value_name = "high | medium | low")]
+    fail_on: Option<Severity>,
+    /// Path to the Wasm or the `<name>.contract` bundle, defaults to
+    /// `./target/ink/<name>.wasm`
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+}
+
+/// The severity of a finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "high" => Ok(Severity::High),
+            "medium" => Ok(Severity::Medium),
+            "low" => Ok(Severity::Low),
+            _ => anyhow::bail!("Severity must be one of `high`, `medium` or `low`"),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::High => write!(f, "{}", "HIGH".red().bold()),
+            Severity::Medium => write!(f, "{}", "MEDIUM".yellow().bold()),
+            Severity::Low => write!(f, "{}", "LOW".bold()),
+        }
+    }
+}
+
+/// A red flag in the Wasm of a contract.
+#[derive(Debug, PartialEq)]
+struct Finding {
+    severity: Severity,
+    message: String,
+}
+
+impl AuditCommand {
+    pub fn exec(&self) -> Result<String> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                CrateMetadata::collect(&manifest_path)?.dest_wasm
+            }
+        };
+        let code = if ContractBundle::is_bundle(&path) {
+            ContractBundle::load(&path)?.wasm()?
+        } else {
+            fs::read(&path).context(format!(
+                "Failed to read the Wasm {}, build the contract first",
+                path.display()
+            ))?
+        };
+        let module: Module = parity_wasm::deserialize_buffer(&code)
+            .context(format!("The Wasm {} is invalid", path.display()))?;
+
+        let mut findings = audit(&module);
+        findings.sort_by_key(|finding| Reverse(finding.severity));
+        let mut report = String::new();
+        if findings.is_empty() {
+            write!(report, "{}", "No findings".bright_green().bold())?;
+        } else {
+            writeln!(report, "{}", "Findings".bold())?;
+            for finding in &findings {
+                writeln!(report, "  {}\t{}", finding.severity, finding.message)?;
+            }
+        }
+        if let Some(fail_on) = self.fail_on {
+            if findings.iter().any(|finding| finding.severity >= fail_on) {
+                anyhow::bail!("{}", report.trim_end())
+            }
+        }
+        Ok(report.trim_end().to_string())
+    }
+}
+
+/// Returns the red flags in the `module`.
+fn audit(module: &Module) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for import in module
+        .import_section()
+        .map(|imports| imports.entries())
+        .unwrap_or_default()
+    {
+        if validate_wasm::check_import(import.module(), import.field(), import.external()).is_err()
+        {
+            findings.push(Finding {
+                severity: Severity::High,
+                message: format!(
+                    "Import {}.{} is not provided by pallet-contracts, it is either \
+                    non-deterministic or the upload fails",
+                    import.module(),
+                    import.field()
+                ),
+            });
+        } else if PREDICTABLE_HOST_FUNCTIONS.contains(&import.field()) {
+            findings.push(Finding {
+                severity: Severity::Medium,
+                message: format!(
+                    "Import {}.{} returns randomness which block authors can predict or \
+                    influence",
+                    import.module(),
+                    import.field()
+                ),
+            });
+        }
+    }
+
+    let unexpected_exports = module
+        .export_section()
+        .map(|exports| exports.entries())
+        .unwrap_or_default()
+        .iter()
+        .map(|export| export.field())
+        .filter(|field| !CONTRACT_EXPORTS.contains(field))
+        .collect::<Vec<_>>();
+    if !unexpected_exports.is_empty() {
+        findings.push(Finding {
+            severity: Severity::Medium,
+            message: format!(
+                "{} exports besides `call` and `deploy`: {}",
+                unexpected_exports.len(),
+                unexpected_exports.join(", ")
+            ),
+        });
+    }
+
+    let segments = module
+        .data_section()
+        .map(|data| data.entries())
+        .unwrap_or_default();
+    for (index, segment) in segments.iter().enumerate() {
+        if segment.value().len() > LARGE_DATA_SEGMENT {
+            findings.push(Finding {
+                severity: Severity::Low,
+                message: format!(
+                    "Data segment {} is unexpectedly large with {} bytes",
+                    index,
+                    segment.value().len()
+                ),
+            });
+        }
+    }
+    let panic_messages = PANIC_MESSAGES
+        .iter()
+        .filter(|message| {
+            segments
+                .iter()
+                .any(|segment| contains(segment.value(), message.as_bytes()))
+        })
+        .map(|message| format!("\"{}\"", message))
+        .collect::<Vec<_>>();
+    if !panic_messages.is_empty() {
+        findings.push(Finding {
+            severity: Severity::Medium,
+            message: format!(
+                "The panic formatting machinery is linked into the contract, the data contains \
+                {}. Avoid formatting in panics or build without `--debug`",
+                panic_messages.join(", ")
+            ),
+        });
+    }
+
+    findings
+}
+
+/// Returns `true` if `needle` is contained in `haystack`.
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_module(contract: &str) -> Module {
+        let wasm = wabt::wat2wasm(contract).expect("invalid wabt");
+        parity_wasm::deserialize_buffer(&wasm).expect("deserializing must work")
+    }
+
+    #[test]
+    fn clean_contract_has_no_findings() {
+        let module = create_module(
+            r#"(module
+                (import "seal0" "seal_input" (func (param i32 i32)))
+                (import "env" "memory" (memory 2 16))
+                (func (export "call"))
+                (func (export "deploy"))
+            )"#,
+        );
+
+        assert_eq!(audit(&module), Vec::new());
+    }
+
+    #[test]
+    fn red_flags_are_found() {
+        // given
+        let module = create_module(
+            r#"(module
+                (import "wasi_snapshot_preview1" "random_get" (func (param i32 i32) (result i32)))
+                (import "seal0" "seal_random" (func (param i32 i32 i32 i32)))
+                (import "env" "memory" (memory 2 16))
+                (func (export "call"))
+                (func (export "deploy"))
+                (func (export "__data_end"))
+                (data (i32.const 0) "panicked at 'overflow'")
+            )"#,
+        );
+
+        // when
+        let severities = audit(&module)
+            .into_iter()
+            .map(|finding| finding.severity)
+            .collect::<Vec<_>>();
+
+        // then
+        assert_eq!(
+            severities,
+            vec![
+                Severity::High,
+                Severity::Medium,
+                Severity::Medium,
+                Severity::Medium
+            ]
+        );
+    }
+}
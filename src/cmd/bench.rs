@@ -0,0 +1,683 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Gas benchmarks of the messages of a deployed contract, described by a benchmark file:
+//!
+//! ```yaml
+//! messages:
+//!   - message: transfer
+//!     args: ["5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty", 100]
+//!   - message: balance_of
+//!     args: ["5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty"]
+//! ```
+//!
+//! Every message is dry-run `--runs` times with the arguments in the JSON representation of
+//! `cargo contract metadata schema`. Nodes with weights v2 also report the proof size and the
+//! storage deposit of a call, older ones only the consumed gas as `ref_time`.
+//!
+//! The results are stored by the code hash of the contract in `bench-results/<code hash>.json`
+//! next to the manifest of the project, to compare later builds against them with `--compare`.
+
+use super::{
+    call::resolve_bundle,
+    scale_json::{item, ScaleJson},
+    trait_definition::{contract_of, Registry},
+    verify::contract_code_hash,
+};
+use crate::{bundle::ContractBundle, rpc::RpcClient, ExtrinsicOpts, OriginOpts};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Write as _},
+    fs,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+/// The directory of the stored results, next to the manifest of the project.
+const RESULTS_DIR: &str = "bench-results";
+
+/// Benchmarks the gas of messages of a deployed contract by dry-running them repeatedly.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "bench")]
+pub struct BenchCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    #[structopt(flatten)]
+    origin_opts: OriginOpts,
+    /// Path to the benchmark file listing the messages and their arguments
+    #[structopt(parse(from_os_str), default_value = "bench.yaml")]
+    file: PathBuf,
+    /// The address of the contract to benchmark
+    #[structopt(name = "contract", long, parse(try_from_str = crate::parse_account))]
+    contract: AccountId32,
+    /// Path to the `<name>.contract` bundle of the contract, resolved from the deployment records
+    /// or the network profile if not supplied
+    #[structopt(long, parse(from_os_str))]
+    bundle: Option<PathBuf>,
+    /// The number of dry-runs of each message
+    #[structopt(long, default_value = "10")]
+    runs: u32,
+    /// Maximum amount of gas to be used for each dry-run
+    #[structopt(name = "gas", long, default_value = "500000000")]
+    gas_limit: u64,
+    /// Print the results as JSON instead of a table
+    #[structopt(long)]
+    json: bool,
+    /// Compare the results with the stored results of a previous build, given by its code hash,
+    /// a prefix of it, or the path to its results
+    #[structopt(long)]
+    compare: Option<String>,
+    /// The comparison fails if the average ref_time or proof size of a message increased by more
+    /// than this percentage
+    #[structopt(long, default_value = "5")]
+    threshold: u64,
+}
+
+/// The messages of a benchmark file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BenchFile {
+    messages: Vec<BenchMessage>,
+}
+
+impl BenchFile {
+    fn load(path: &Path) -> Result<Self> {
+        let contents =
+            fs::read(path).context(format!("Failed to read the benchmarks {}", path.display()))?;
+        serde_yaml::from_slice(&contents).context(format!("Invalid benchmarks {}", path.display()))
+    }
+}
+
+/// A message benchmarked with the same arguments in every run.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BenchMessage {
+    message: String,
+    #[serde(default)]
+    args: Vec<Value>,
+    /// The value transferred with the call.
+    #[serde(default)]
+    value: u64,
+}
+
+/// The costs of a single dry-run of a message.
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct Measurement {
+    pub(super) ref_time: u64,
+    /// The proof size, if reported by the node.
+    pub(super) proof_size: Option<u64>,
+    /// The storage deposit charged, negative if refunded, if reported by the node.
+    pub(super) storage_deposit: Option<i128>,
+}
+
+/// The minimum, average and maximum of a cost over all runs of a message.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Stats<T> {
+    min: T,
+    avg: T,
+    max: T,
+}
+
+/// The benchmark results of a message.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BenchResult {
+    message: String,
+    runs: u32,
+    ref_time: Stats<u64>,
+    proof_size: Option<Stats<u64>>,
+    storage_deposit: Option<Stats<i128>>,
+}
+
+/// The benchmark results of a build of a contract, stored by its code hash.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredResults {
+    code_hash: String,
+    results: Vec<BenchResult>,
+}
+
+/// The change of the average of a cost of a message against the baseline.
+#[derive(Debug, PartialEq)]
+struct Comparison {
+    message: String,
+    cost: &'static str,
+    baseline: u64,
+    current: u64,
+    /// Whether the cost increased by more than the threshold.
+    regressed: bool,
+}
+
+impl BenchCommand {
+    pub fn exec(&self) -> Result<String> {
+        let bench = BenchFile::load(&self.file)?;
+        if self.runs == 0 {
+            anyhow::bail!("At least one run of each message is required")
+        }
+        let bundle = match &self.bundle {
+            Some(bundle) => ContractBundle::load(bundle)?,
+            None => resolve_bundle(&self.extrinsic_opts, &self.contract)?,
+        };
+        let metadata = Value::Object(bundle.json().clone());
+        let spec = contract_of(&metadata);
+        let registry = Registry::new(spec);
+        let codec = ScaleJson::new(&registry);
+        let origin = self.origin_opts.origin(&self.extrinsic_opts)?;
+        let rpc = RpcClient::new(&self.extrinsic_opts.url()?)?;
+        let results_dir = self
+            .extrinsic_opts
+            .manifest_path()?
+            .absolute_directory()?
+            .join(RESULTS_DIR);
+        let baseline = self
+            .compare
+            .as_ref()
+            .map(|baseline| load_baseline(&results_dir, baseline))
+            .transpose()?;
+
+        let mut results = Vec::new();
+        for message in &bench.messages {
+            let data =
+                codec.encode_message(item(spec, "messages", &message.message)?, &message.args)?;
+            let call_request = json!({
+                "origin": origin.to_ss58check(),
+                "dest": self.contract.to_ss58check(),
+                "value": message.value,
+                "gasLimit": self.gas_limit,
+                "inputData": format!("0x{}", hex::encode(&data)),
+            });
+            let measurements = (0..self.runs)
+                .map(|_| {
+                    let result: Value = rpc.request("contracts_call", json!([call_request]))?;
+                    measure(&result)
+                })
+                .collect::<Result<Vec<_>>>()
+                .context(format!("Failed to dry-run {}", message.message))?;
+            results.push(BenchResult::new(&message.message, &measurements));
+        }
+        let stored = StoredResults {
+            code_hash: format!(
+                "0x{}",
+                hex::encode(contract_code_hash(&rpc, &self.contract)?)
+            ),
+            results,
+        };
+        store(&results_dir, &stored)?;
+
+        let output = if self.json {
+            serde_json::to_string_pretty(&stored.results)?
+        } else {
+            table(&stored.results)?
+        };
+        let baseline = match baseline {
+            Some(baseline) => baseline,
+            None => return Ok(output),
+        };
+        println!("{}\n", output);
+        let comparisons = compare(&baseline.results, &stored.results, self.threshold);
+        println!("{}", comparison_table(&comparisons)?);
+        let regressions = comparisons
+            .iter()
+            .filter(|comparison| comparison.regressed)
+            .count();
+        if regressions > 0 {
+            anyhow::bail!(
+                "{} costs regressed by more than {}% against the build {}",
+                regressions,
+                self.threshold,
+                baseline.code_hash
+            )
+        }
+        Ok(format!(
+            "No costs regressed by more than {}% against the build {}",
+            self.threshold, baseline.code_hash
+        ))
+    }
+}
+
+/// Writes the `results` to the `dir` of the stored results, named by their code hash.
+fn store(dir: &Path, results: &StoredResults) -> Result<()> {
+    fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!("{}.json", results.code_hash));
+    fs::write(&path, serde_json::to_string_pretty(results)?)
+        .context(format!("Failed to write {}", path.display()))
+}
+
+/// Loads the results of the `baseline`, given by the path to its results or by its code hash or
+/// a prefix of it, from the `dir` of the stored results.
+fn load_baseline(dir: &Path, baseline: &str) -> Result<StoredResults> {
+    let path = Path::new(baseline);
+    let path = if path.is_file() {
+        path.to_path_buf()
+    } else {
+        let prefix = format!("0x{}", baseline.trim_start_matches("0x").to_lowercase());
+        let entries = fs::read_dir(dir).context(format!(
+            "No stored benchmark results in {}, run the benchmarks of the baseline first",
+            dir.display()
+        ))?;
+        let mut matches = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .map_or(false, |name| {
+                        name.starts_with(&prefix) && name.ends_with(".json")
+                    })
+            })
+            .collect::<Vec<_>>();
+        match matches.len() {
+            0 => anyhow::bail!("No stored benchmark results of the build {}", baseline),
+            1 => matches.remove(0),
+            _ => anyhow::bail!(
+                "The code hash {} is ambiguous, it matches {} stored results",
+                baseline,
+                matches.len()
+            ),
+        }
+    };
+    let contents =
+        fs::read(&path).context(format!("Failed to read the results {}", path.display()))?;
+    serde_json::from_slice(&contents).context(format!("Invalid results {}", path.display()))
+}
+
+/// Compares the average ref_time and proof size of the messages of the `current` results with
+/// the `baseline`, regressing if they increased by more than `threshold` percent.
+///
+/// Messages and costs missing in one of the results are not compared.
+fn compare(baseline: &[BenchResult], current: &[BenchResult], threshold: u64) -> Vec<Comparison> {
+    let mut comparisons = Vec::new();
+    for result in current {
+        let old = match baseline.iter().find(|old| old.message == result.message) {
+            Some(old) => old,
+            None => continue,
+        };
+        let costs = [
+            (
+                "ref_time",
+                Some(old.ref_time.avg),
+                Some(result.ref_time.avg),
+            ),
+            (
+                "proof_size",
+                old.proof_size.as_ref().map(|stats| stats.avg),
+                result.proof_size.as_ref().map(|stats| stats.avg),
+            ),
+        ];
+        for (cost, baseline, current) in costs.iter() {
+            if let (Some(baseline), Some(current)) = (*baseline, *current) {
+                let increase = current.saturating_sub(baseline);
+                comparisons.push(Comparison {
+                    message: result.message.clone(),
+                    cost: *cost,
+                    baseline,
+                    current,
+                    regressed: increase.saturating_mul(100) > baseline.saturating_mul(threshold),
+                });
+            }
+        }
+    }
+    comparisons
+}
+
+/// Returns a line per compared cost with its change against the baseline.
+fn comparison_table(comparisons: &[Comparison]) -> Result<String> {
+    let width = comparisons
+        .iter()
+        .map(|comparison| comparison.message.len())
+        .chain(Some("message".len()))
+        .max()
+        .unwrap_or_default();
+    let mut table = String::new();
+    writeln!(
+        table,
+        "{:<width$}  {:<10}  {:>16}  {:>16}  {:>8}",
+        "message".bold(),
+        "cost".bold(),
+        "baseline".bold(),
+        "current".bold(),
+        "change".bold(),
+        width = width
+    )?;
+    for comparison in comparisons {
+        let change = (comparison.current as f64 - comparison.baseline as f64) * 100.0
+            / comparison.baseline.max(1) as f64;
+        let change = format!("{:+.1}%", change);
+        writeln!(
+            table,
+            "{:<width$}  {:<10}  {:>16}  {:>16}  {:>8}",
+            comparison.message,
+            comparison.cost,
+            comparison.baseline,
+            comparison.current,
+            if comparison.regressed {
+                change.red().bold()
+            } else {
+                change.normal()
+            },
+            width = width
+        )?;
+    }
+    Ok(table.trim_end().to_string())
+}
+
+impl BenchResult {
+    fn new(message: &str, measurements: &[Measurement]) -> Self {
+        let proof_sizes = measurements
+            .iter()
+            .map(|measurement| measurement.proof_size)
+            .collect::<Option<Vec<_>>>();
+        let storage_deposits = measurements
+            .iter()
+            .map(|measurement| measurement.storage_deposit)
+            .collect::<Option<Vec<_>>>();
+        Self {
+            message: message.into(),
+            runs: measurements.len() as u32,
+            ref_time: stats(
+                measurements
+                    .iter()
+                    .map(|measurement| measurement.ref_time.into()),
+            )
+            .into_u64(),
+            proof_size: proof_sizes
+                .map(|sizes| stats(sizes.into_iter().map(i128::from)).into_u64()),
+            storage_deposit: storage_deposits.map(|deposits| stats(deposits.into_iter())),
+        }
+    }
+}
+
+impl Stats<i128> {
+    fn into_u64(self) -> Stats<u64> {
+        let convert = |value| u64::try_from(value).unwrap_or(u64::MAX);
+        Stats {
+            min: convert(self.min),
+            avg: convert(self.avg),
+            max: convert(self.max),
+        }
+    }
+}
+
+/// Returns the minimum, average and maximum of the non-empty `values`.
+fn stats(values: impl Iterator<Item = i128>) -> Stats<i128> {
+    let values = values.collect::<Vec<_>>();
+    Stats {
+        min: values.iter().copied().min().unwrap_or_default(),
+        avg: values.iter().sum::<i128>() / values.len().max(1) as i128,
+        max: values.iter().copied().max().unwrap_or_default(),
+    }
+}
+
+/// Returns the costs of the `result` of the `contracts_call` RPC.
+///
+/// Older nodes return `{ "success": { "gasConsumed": .. } }` or `{ "error": .. }`, nodes with
+/// weights v2 `{ "gasConsumed": { "refTime": .., "proofSize": .. }, "storageDeposit": { "charge":
+/// .. }, "result": { "ok": .. } }`.
+pub(super) fn measure(result: &Value) -> Result<Measurement> {
+    if let Some(success) = result.get("success") {
+        let gas_consumed = success
+            .get("gasConsumed")
+            .or_else(|| success.get("gas_consumed"))
+            .and_then(number)
+            .ok_or_else(|| anyhow::anyhow!("No consumed gas in the result {}", result))?;
+        return Ok(Measurement {
+            ref_time: gas_consumed as u64,
+            ..Default::default()
+        });
+    }
+    if result.get("error").is_some() {
+        anyhow::bail!("The contract execution trapped")
+    }
+    let outcome = result
+        .get("result")
+        .ok_or_else(|| anyhow::anyhow!("Unexpected result {}", result))?;
+    if outcome.get("err").or_else(|| outcome.get("Err")).is_some() {
+        anyhow::bail!("The contract execution failed: {}", outcome)
+    }
+    let gas_consumed = &result["gasConsumed"];
+    let (ref_time, proof_size) = match gas_consumed.get("refTime") {
+        Some(ref_time) => (
+            number(ref_time),
+            gas_consumed.get("proofSize").and_then(number),
+        ),
+        None => (number(gas_consumed), None),
+    };
+    let deposit = &result["storageDeposit"];
+    let storage_deposit = match (deposit.get("charge"), deposit.get("refund")) {
+        (Some(charge), _) => number(charge).map(|charge| charge as i128),
+        (None, Some(refund)) => number(refund).map(|refund| -(refund as i128)),
+        (None, None) => None,
+    };
+    Ok(Measurement {
+        ref_time: ref_time
+            .ok_or_else(|| anyhow::anyhow!("No consumed gas in the result {}", result))?
+            as u64,
+        proof_size: proof_size.map(|size| size as u64),
+        storage_deposit,
+    })
+}
+
+/// Returns the integer of a number, or of a decimal or hex string as returned for wide integers.
+pub(super) fn number(value: &Value) -> Option<u128> {
+    match value {
+        Value::Number(number) => number.as_u64().map(u128::from),
+        Value::String(string) => match string.strip_prefix("0x") {
+            Some(hex) => u128::from_str_radix(hex, 16).ok(),
+            None => string.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+/// Returns a line per message with the minimum, average and maximum of each cost.
+fn table(results: &[BenchResult]) -> Result<String> {
+    let width = results
+        .iter()
+        .map(|result| result.message.len())
+        .chain(Some("message".len()))
+        .max()
+        .unwrap_or_default();
+    let cell = |stats: Option<String>| stats.unwrap_or_else(|| "-".into());
+    let mut table = String::new();
+    writeln!(
+        table,
+        "{:<width$}  {:>5}  {:<32}  {:<32}  {}",
+        "message".bold(),
+        "runs".bold(),
+        "ref_time min/avg/max".bold(),
+        "proof_size min/avg/max".bold(),
+        "storage deposit min/avg/max".bold(),
+        width = width
+    )?;
+    for result in results {
+        writeln!(
+            table,
+            "{:<width$}  {:>5}  {:<32}  {:<32}  {}",
+            result.message,
+            result.runs,
+            result.ref_time.to_string(),
+            cell(result.proof_size.as_ref().map(ToString::to_string)),
+            cell(result.storage_deposit.as_ref().map(ToString::to_string)),
+            width = width
+        )?;
+    }
+    Ok(table.trim_end().to_string())
+}
+
+impl<T: fmt::Display> fmt::Display for Stats<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.min, self.avg, self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    #[test]
+    fn costs_are_read_from_legacy_and_weight_v2_results() {
+        let legacy = json!({ "success": { "flags": 0, "data": "0x", "gasConsumed": 1200 } });
+        assert_eq!(
+            measure(&legacy).unwrap(),
+            Measurement {
+                ref_time: 1200,
+                ..Default::default()
+            }
+        );
+
+        let weight_v2 = json!({
+            "gasConsumed": { "refTime": 3000, "proofSize": "0x400" },
+            "storageDeposit": { "refund": "1000000000000" },
+            "result": { "ok": { "flags": 0, "data": "0x" } },
+        });
+        assert_eq!(
+            measure(&weight_v2).unwrap(),
+            Measurement {
+                ref_time: 3000,
+                proof_size: Some(1024),
+                storage_deposit: Some(-1_000_000_000_000),
+            }
+        );
+
+        assert!(measure(&json!({ "error": null })).is_err());
+    }
+
+    #[test]
+    fn results_aggregate_the_runs() {
+        // given
+        let run = |ref_time, proof_size| Measurement {
+            ref_time,
+            proof_size,
+            storage_deposit: None,
+        };
+
+        // when
+        let result = BenchResult::new(
+            "transfer",
+            &[run(100, Some(10)), run(300, Some(20)), run(200, Some(30))],
+        );
+
+        // then
+        assert_eq!(
+            result.ref_time,
+            Stats {
+                min: 100,
+                avg: 200,
+                max: 300
+            }
+        );
+        assert_eq!(
+            result.proof_size,
+            Some(Stats {
+                min: 10,
+                avg: 20,
+                max: 30
+            })
+        );
+        assert_eq!(result.storage_deposit, None);
+        assert!(table(&[result])
+            .unwrap()
+            .contains("transfer      3  100/200/300"));
+    }
+
+    #[test]
+    fn costs_are_compared_with_the_baseline() {
+        let result = |message: &str, ref_time, proof_size| {
+            BenchResult::new(
+                message,
+                &[Measurement {
+                    ref_time,
+                    proof_size,
+                    storage_deposit: None,
+                }],
+            )
+        };
+        let baseline = [
+            result("transfer", 1000, Some(100)),
+            result("balance_of", 500, None),
+        ];
+        let current = [
+            result("transfer", 1050, Some(106)),
+            result("balance_of", 400, Some(10)),
+            result("approve", 700, None),
+        ];
+
+        let comparisons = compare(&baseline, &current, 5);
+
+        let regressed = comparisons
+            .iter()
+            .map(|comparison| (comparison.cost, comparison.regressed))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            regressed,
+            vec![
+                ("ref_time", false),
+                ("proof_size", true),
+                ("ref_time", false)
+            ]
+        );
+        assert_eq!(comparisons[2].message, "balance_of");
+        assert_eq!(comparisons[2].current, 400);
+    }
+
+    #[test]
+    fn baselines_are_found_by_code_hash_prefix() {
+        with_tmp_dir(|path| {
+            let results = |code_hash: &str| StoredResults {
+                code_hash: code_hash.into(),
+                results: Vec::new(),
+            };
+            store(path, &results(&format!("0x{}", "ab".repeat(32))))?;
+            store(path, &results(&format!("0x{}", "ac".repeat(32))))?;
+
+            assert_eq!(
+                load_baseline(path, "ABAB")?.code_hash,
+                format!("0x{}", "ab".repeat(32))
+            );
+            assert!(load_baseline(path, "0xa").is_err());
+            assert!(load_baseline(path, "ff").is_err());
+            let file = path.join(format!("0x{}.json", "ac".repeat(32)));
+            assert_eq!(
+                load_baseline(path, file.to_str().unwrap())?.code_hash,
+                format!("0x{}", "ac".repeat(32))
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn bench_file_is_parsed() {
+        with_tmp_dir(|path| {
+            let file = path.join("bench.yaml");
+            fs::write(
+                &file,
+                "messages:\n  - message: transfer\n    args: [\"5F\", 100]\n    value: 5\n  \
+                - message: total_supply\n",
+            )?;
+            let bench = BenchFile::load(&file)?;
+            assert_eq!(bench.messages.len(), 2);
+            assert_eq!(bench.messages[0].args, vec![json!("5F"), json!(100)]);
+            assert_eq!(bench.messages[0].value, 5);
+            assert!(bench.messages[1].args.is_empty());
+            Ok(())
+        })
+    }
+}
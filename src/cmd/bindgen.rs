@@ -0,0 +1,1001 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    metadata,
+    trait_definition::{contract_of, doc_comment, docs, selector_bytes, Registry},
+    verify_metadata::label,
+};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use heck::{CamelCase as _, MixedCase as _, SnakeCase as _};
+use serde_json::Value;
+use std::{collections::BTreeMap, convert::TryFrom, fmt::Write, fs, path::PathBuf};
+use structopt::StructOpt;
+
+/// Types which are declared by the header of the generated bindings.
+const TS_PROVIDED_TYPES: [&str; 3] = ["AccountId", "Hash", "BigInteger"];
+
+/// Words which can't be used as the names of arguments.
+const TS_RESERVED_WORDS: [&str; 12] = [
+    "break", "case", "class", "default", "delete", "function", "import", "new", "options",
+    "return", "this", "var",
+];
+
+/// The declarations every generated client starts with.
+const TS_HEADER: &str = "\
+import type { ApiPromise } from '@polkadot/api';
+import type { SubmittableExtrinsic } from '@polkadot/api/types';
+import { Abi, ContractPromise } from '@polkadot/api-contract';
+import type { ContractOptions } from '@polkadot/api-contract/types';
+import type { ContractExecResultResult, Weight } from '@polkadot/types/interfaces';
+
+/** Integers wider than 32 bits, a `number` if it is a safe integer and a hex `string` otherwise. */
+export type BigInteger = number | string;
+/** An SS58 encoded account id. */
+export type AccountId = string;
+/** A hex encoded hash. */
+export type Hash = string;
+
+/** The outcome of a query of the contract. */
+export interface QueryResult<T> {
+  /** The decoded return value, `undefined` if the call failed. */
+  value?: T;
+  result: ContractExecResultResult;
+  gasRequired: Weight;
+}
+";
+
+/// The helpers every generated client ends with, resolving messages by their selector.
+const TS_HELPERS: &str = "
+  private messageMethod(selector: string): string {
+    const message = this.contract.abi.messages.find(
+      (message) => message.selector.toHex() === selector
+    );
+    if (!message) {
+      throw new Error(`The ABI of the contract has no message ${selector}`);
+    }
+    return message.method;
+  }
+
+  private async queryMessage<T>(
+    selector: string,
+    caller: string,
+    options: ContractOptions,
+    args: unknown[]
+  ): Promise<QueryResult<T>> {
+    const { result, output, gasRequired } = await this.contract.query[
+      this.messageMethod(selector)
+    ](caller, options, ...args);
+    return { value: output?.toJSON() as T | undefined, result, gasRequired };
+  }
+
+  private txMessage(
+    selector: string,
+    options: ContractOptions,
+    args: unknown[]
+  ): SubmittableExtrinsic<'promise'> {
+    return this.contract.tx[this.messageMethod(selector)](options, ...args);
+  }
+}
+";
+
+/// The type aliases declared by the header of the generated Rust module.
+const RUST_ALIASES: [&str; 5] = ["AccountId", "Balance", "Hash", "Timestamp", "BlockNumber"];
+
+/// Keywords which have to be escaped as the names of arguments.
+const RUST_KEYWORDS: [&str; 12] = [
+    "as", "crate", "fn", "impl", "in", "loop", "match", "mod", "move", "ref", "type", "where",
+];
+
+/// The attributes of the structs and enums of the generated Rust module.
+const RUST_DERIVES: &str = "#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]\n";
+
+/// The declarations every generated Rust module starts with.
+const RUST_HEADER: &str = "\
+//!
+//! Requires the `parity-scale-codec` dependency renamed to `scale`, `sp-core` and the
+//! `substrate-subxt` dependency renamed to `subxt`.
+
+#![allow(dead_code, clippy::all)]
+
+use scale::{Decode, Encode};
+use std::marker::PhantomData;
+use subxt::{contracts::*, system::System, Client, DefaultNodeRuntime, ExtrinsicSuccess, Signer};
+
+/// The account id type of the default environment.
+pub type AccountId = sp_core::crypto::AccountId32;
+/// The balance type of the default environment.
+pub type Balance = u128;
+/// The hash type of the default environment.
+pub type Hash = sp_core::H256;
+/// The timestamp type of the default environment.
+pub type Timestamp = u64;
+/// The block number type of the default environment.
+pub type BlockNumber = u32;
+
+/// A call of a message of the contract, returning an `R`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageCall<R> {
+    /// The address of the contract.
+    pub contract: AccountId,
+    /// The selector of the message followed by its SCALE encoded arguments.
+    pub data: Vec<u8>,
+    marker: PhantomData<fn() -> R>,
+}
+
+impl<R: Decode> MessageCall<R> {
+    /// Decodes the return value of the message from the output of a dry run of the call.
+    pub fn decode_output(&self, output: &[u8]) -> Result<R, scale::Error> {
+        R::decode(&mut &output[..])
+    }
+
+    /// Submits the call signed by the `signer`, transferring `value` to the contract, and waits
+    /// until it is included in a block.
+    pub async fn submit(
+        &self,
+        client: &Client<DefaultNodeRuntime>,
+        signer: &(dyn Signer<DefaultNodeRuntime> + Send + Sync),
+        value: Balance,
+        gas_limit: u64,
+    ) -> Result<ExtrinsicSuccess<DefaultNodeRuntime>, subxt::Error> {
+        let dest: <DefaultNodeRuntime as System>::Address = self.contract.clone().into();
+        client
+            .call_and_watch(signer, &dest, value, gas_limit, &self.data)
+            .await
+    }
+}
+";
+
+/// The decoding of the events of the contract `{{name}}` in the generated Rust module.
+const RUST_EVENTS_IMPL: &str = r#"
+impl {{name}}Event {
+    /// Returns the events emitted by the contract at `contract` during an extrinsic.
+    pub fn from_extrinsic(
+        contract: &AccountId,
+        result: &ExtrinsicSuccess<DefaultNodeRuntime>,
+    ) -> Vec<Self> {
+        result
+            .events
+            .iter()
+            .filter(|event| {
+                event.module == "Contracts"
+                    && (event.variant == "ContractExecution" || event.variant == "ContractEmitted")
+            })
+            .filter_map(|event| {
+                let (emitter, data) = <(AccountId, Vec<u8>)>::decode(&mut &event.data[..]).ok()?;
+                if &emitter != contract {
+                    return None;
+                }
+                Self::decode(&mut &data[..]).ok()
+            })
+            .collect()
+    }
+}
+"#;
+
+/// The contract `{{name}}` with the call builders of its `{{methods}}` in the generated Rust
+/// module.
+const RUST_CONTRACT: &str = r#"#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct {{name}} {
+    /// The address of the contract.
+    pub address: AccountId,
+}
+
+impl {{name}} {
+    /// Returns the contract at `address`.
+    pub fn new(address: AccountId) -> Self {
+        Self { address }
+    }
+{{methods}}
+    fn message_call<R>(&self, selector: [u8; 4], args: impl Encode) -> MessageCall<R> {
+        let mut data = selector.to_vec();
+        args.encode_to(&mut data);
+        MessageCall {
+            contract: self.address.clone(),
+            data,
+            marker: PhantomData,
+        }
+    }
+}
+"#;
+
+/// The languages bindings can be generated for.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Lang {
+    /// A TypeScript client on top of `@polkadot/api-contract`.
+    TypeScript,
+    /// A Rust module on top of `substrate-subxt`.
+    Rust,
+}
+
+impl std::str::FromStr for Lang {
+    type Err = String;
+    fn from_str(lang: &str) -> Result<Self, Self::Err> {
+        match lang {
+            "ts" => Ok(Lang::TypeScript),
+            "rust" => Ok(Lang::Rust),
+            _ => Err("Could not parse language, expected ts or rust".to_string()),
+        }
+    }
+}
+
+/// Generates a typed client of the contract from its metadata, so that frontends don't have to
+/// hand-write the ABI of the contract.
+///
+/// The TypeScript client has a method per message, taking the typed arguments and returning the
+/// typed result of a query or the extrinsic of a transaction, and decodes the typed events.
+///
+/// The Rust module has a call builder per message, submitting the call via `substrate-subxt`
+/// and decoding its typed result, and an enum of the events.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "bindgen")]
+pub struct BindgenCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// The language of the bindings
+    #[structopt(long, default_value = "ts", value_name = "ts | rust")]
+    lang: Lang,
+    /// Path to the metadata or the `<name>.contract` bundle, defaults to
+    /// `./target/ink/metadata.json`
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+    /// Path of the generated file, defaults to `<name>.ts` or `<name>.rs` next to the metadata
+    #[structopt(short, long, parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+impl BindgenCommand {
+    pub fn exec(&self) -> Result<String> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+                metadata::artifact_paths(&crate_metadata).dest_metadata
+            }
+        };
+        let contents = fs::read(&path).context(format!(
+            "Failed to read the metadata {}, build the contract first",
+            path.display()
+        ))?;
+        let metadata: Value = serde_json::from_slice(&contents)
+            .context(format!("The metadata {} is no valid JSON", path.display()))?;
+        let name = metadata
+            .get("contract")
+            .and_then(|contract| contract.get("name"))
+            .and_then(Value::as_str)
+            .unwrap_or("contract");
+
+        let (source, extension, lang) = match self.lang {
+            Lang::TypeScript => (typescript(name, &metadata)?, "ts", "TypeScript"),
+            Lang::Rust => (rust(name, &metadata)?, "rs", "Rust"),
+        };
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => path.with_file_name(format!("{}.{}", name, extension)),
+        };
+        fs::write(&output, source)
+            .context(format!("Failed to write the bindings {}", output.display()))?;
+        Ok(format!(
+            "Generated the {} bindings of {} at {}",
+            lang,
+            name,
+            output.display()
+        ))
+    }
+}
+
+/// Returns the TypeScript client of the contract `name` described by the `metadata`.
+fn typescript(name: &str, metadata: &Value) -> Result<String> {
+    let contract = contract_of(metadata);
+    let spec = contract
+        .get("spec")
+        .ok_or_else(|| anyhow::anyhow!("The metadata contains no contract spec"))?;
+    let mut types = TypeScript {
+        registry: Registry::new(contract),
+        definitions: BTreeMap::new(),
+    };
+    let items = |kind: &str| {
+        spec.get(kind)
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    };
+    let class_name = name.to_camel_case();
+
+    let mut events = String::new();
+    let mut event_variants = Vec::new();
+    for event in items("events") {
+        let event_name = label(event).ok_or_else(|| anyhow::anyhow!("An event has no name"))?;
+        let interface = format!("{}Event", event_name.to_camel_case());
+        write!(
+            events,
+            "\n{}export interface {} {{\n",
+            js_doc(docs(event), ""),
+            interface
+        )?;
+        for arg in event
+            .get("args")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            let arg_name = label(arg).ok_or_else(|| {
+                anyhow::anyhow!("An argument of the event {} has no name", event_name)
+            })?;
+            writeln!(
+                events,
+                "{}  {}: {};",
+                js_doc(docs(arg), "  "),
+                arg_name.to_mixed_case(),
+                types.type_spec(&arg["type"])?
+            )?;
+        }
+        writeln!(events, "}}")?;
+        event_variants.push(format!("{{ name: '{}'; args: {} }}", event_name, interface));
+    }
+
+    let mut methods = String::new();
+    for message in items("messages") {
+        let label = label(message).ok_or_else(|| anyhow::anyhow!("A message has no name"))?;
+        // messages of traits are labeled `Trait::message`
+        let method = label
+            .rsplit("::")
+            .next()
+            .unwrap_or_default()
+            .to_mixed_case();
+        let selector = message
+            .get("selector")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("The message {} has no selector", label))?;
+        let mut params = Vec::new();
+        let mut args = Vec::new();
+        for arg in message
+            .get("args")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            let mut arg_name = label_of(arg, &label)?.to_mixed_case();
+            if TS_RESERVED_WORDS.contains(&arg_name.as_str()) {
+                arg_name.push('_');
+            }
+            params.push(format!("{}: {}", arg_name, types.type_spec(&arg["type"])?));
+            args.push(arg_name);
+        }
+        let returns = match message.get("returnType").filter(|ty| !ty.is_null()) {
+            Some(ty) => types.type_spec(ty)?,
+            None => "null".to_string(),
+        };
+        let params = params
+            .iter()
+            .map(|param| format!("{}, ", param))
+            .collect::<String>();
+        let args = args.join(", ");
+        let doc = js_doc(docs(message), "  ");
+
+        if message.get("mutates").and_then(Value::as_bool) == Some(true) {
+            write!(
+                methods,
+                "\n{doc}  {method}({params}options: ContractOptions = {{}}): \
+                SubmittableExtrinsic<'promise'> {{\n    \
+                return this.txMessage('{selector}', options, [{args}]);\n  }}\n\n  \
+                /** Dry runs `{method}`, e.g. to estimate its gas and check its result. */\n  \
+                dryRun{camel}(\n    caller: string,\n    \
+                {params}options: ContractOptions = {{}}\n  \
+                ): Promise<QueryResult<{returns}>> {{\n    \
+                return this.queryMessage('{selector}', caller, options, [{args}]);\n  }}\n",
+                doc = doc,
+                method = method,
+                camel = method.to_camel_case(),
+                params = params,
+                selector = selector,
+                args = args,
+                returns = returns,
+            )?;
+        } else {
+            write!(
+                methods,
+                "\n{doc}  {method}(\n    caller: string,\n    \
+                {params}options: ContractOptions = {{}}\n  \
+                ): Promise<QueryResult<{returns}>> {{\n    \
+                return this.queryMessage('{selector}', caller, options, [{args}]);\n  }}\n",
+                doc = doc,
+                method = method,
+                params = params,
+                selector = selector,
+                args = args,
+                returns = returns,
+            )?;
+        }
+    }
+    if !event_variants.is_empty() {
+        write!(
+            methods,
+            "\n  /** Decodes the data of an event emitted by the contract. */\n  \
+            decodeEvent(data: Uint8Array): {class}Event {{\n    \
+            const {{ event, args }} = this.contract.abi.decodeEvent(data);\n    \
+            const decoded: Record<string, unknown> = {{}};\n    \
+            event.args.forEach((arg, index) => {{\n      \
+            decoded[arg.name] = args[index].toJSON();\n    }});\n    \
+            return {{ name: event.identifier, args: decoded }} as {class}Event;\n  }}\n",
+            class = class_name
+        )?;
+    }
+
+    let mut source = format!(
+        "// Generated by `cargo contract bindgen` from the metadata of the {} contract, do not \
+        edit.\n{}",
+        name, TS_HEADER
+    );
+    for definition in types.definitions.values() {
+        write!(source, "\n{}", definition)?;
+    }
+    source.push_str(&events);
+    if !event_variants.is_empty() {
+        write!(
+            source,
+            "\n/** The events emitted by the contract. */\nexport type {}Event =\n  | {};\n",
+            class_name,
+            event_variants.join("\n  | ")
+        )?;
+    }
+    write!(
+        source,
+        "\n{}export class {} {{\n  readonly contract: ContractPromise;\n\n  \
+        constructor(api: ApiPromise, abi: Abi | Record<string, unknown> | string, address: \
+        string) {{\n    this.contract = new ContractPromise(api, abi, address);\n  }}\n{}{}",
+        js_doc(docs(spec), ""),
+        class_name,
+        methods,
+        TS_HELPERS
+    )?;
+    Ok(source)
+}
+
+/// Returns the Rust module of the contract `name` described by the `metadata`.
+fn rust(name: &str, metadata: &Value) -> Result<String> {
+    let contract = contract_of(metadata);
+    let spec = contract
+        .get("spec")
+        .ok_or_else(|| anyhow::anyhow!("The metadata contains no contract spec"))?;
+    let registry = Registry::new(contract).with_derives(RUST_DERIVES);
+    let mut definitions = BTreeMap::new();
+    let mut type_spec = |spec: &Value| {
+        let id = spec
+            .get("type")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow::anyhow!("A type reference has no type id"))?;
+        let rendered = registry.render(id, &mut definitions)?;
+        // aliases like `Balance` are kept if the header declares them
+        let display_name = spec
+            .get("displayName")
+            .and_then(Value::as_array)
+            .and_then(|segments| segments.last())
+            .and_then(Value::as_str)
+            .filter(|name| RUST_ALIASES.contains(name));
+        Ok::<_, anyhow::Error>(display_name.map_or(rendered, ToString::to_string))
+    };
+    let items = |kind: &str| {
+        spec.get(kind)
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    };
+    let struct_name = name.to_camel_case();
+
+    let mut methods = String::new();
+    for message in items("messages") {
+        let label = label(message).ok_or_else(|| anyhow::anyhow!("A message has no name"))?;
+        // messages of traits are labeled `Trait::message`
+        let method = label
+            .rsplit("::")
+            .next()
+            .unwrap_or_default()
+            .to_snake_case();
+        let selector = message
+            .get("selector")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("The message {} has no selector", label))?;
+        let mut params = String::new();
+        let mut args = Vec::new();
+        for arg in message
+            .get("args")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            let mut arg_name = label_of(arg, &label)?.to_snake_case();
+            if RUST_KEYWORDS.contains(&arg_name.as_str()) {
+                arg_name.insert_str(0, "r#");
+            }
+            write!(params, ", {}: {}", arg_name, type_spec(&arg["type"])?)?;
+            args.push(arg_name);
+        }
+        let returns = match message.get("returnType").filter(|ty| !ty.is_null()) {
+            Some(ty) => type_spec(ty)?,
+            None => "()".to_string(),
+        };
+        let args = match args.as_slice() {
+            [arg] => format!("({},)", arg),
+            args => format!("({})", args.join(", ")),
+        };
+        write!(
+            methods,
+            "\n{}    pub fn {}(&self{}) -> MessageCall<{}> {{\n        \
+            self.message_call({}, {})\n    }}\n",
+            doc_comment(docs(message).as_deref(), "    ")?,
+            method,
+            params,
+            returns,
+            selector_bytes(selector)?,
+            args
+        )?;
+    }
+
+    let mut events = String::new();
+    for event in items("events") {
+        let event_name = label(event).ok_or_else(|| anyhow::anyhow!("An event has no name"))?;
+        writeln!(
+            events,
+            "{}    {} {{",
+            doc_comment(docs(event).as_deref(), "    ")?,
+            event_name.to_camel_case()
+        )?;
+        for arg in event
+            .get("args")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            let arg_name = label(arg).ok_or_else(|| {
+                anyhow::anyhow!("An argument of the event {} has no name", event_name)
+            })?;
+            writeln!(
+                events,
+                "{}        {}: {},",
+                doc_comment(docs(arg).as_deref(), "        ")?,
+                arg_name.to_snake_case(),
+                type_spec(&arg["type"])?
+            )?;
+        }
+        writeln!(events, "    }},")?;
+    }
+
+    let mut source = format!(
+        "//! Generated by `cargo contract bindgen` from the metadata of the {} contract, do not\n\
+        //! edit.\n{}",
+        name, RUST_HEADER
+    );
+    for definition in definitions.values() {
+        write!(source, "\n{}", definition)?;
+    }
+    if !events.is_empty() {
+        write!(
+            source,
+            "\n/// The events emitted by the contract.\n{}pub enum {}Event {{\n{}}}\n{}",
+            RUST_DERIVES,
+            struct_name,
+            events,
+            RUST_EVENTS_IMPL.replace("{{name}}", &struct_name)
+        )?;
+    }
+    write!(
+        source,
+        "\n{}{}",
+        doc_comment(docs(spec).as_deref(), "")?,
+        RUST_CONTRACT
+            .replace("{{name}}", &struct_name)
+            .replace("{{methods}}", &methods)
+    )?;
+    Ok(source)
+}
+
+/// Returns the name of an argument of the message `message`.
+fn label_of(arg: &Value, message: &str) -> Result<String> {
+    label(arg).ok_or_else(|| anyhow::anyhow!("An argument of the message {} has no name", message))
+}
+
+/// Returns the `docs` as JSDoc comment, indented by `indent`.
+fn js_doc(docs: Option<String>, indent: &str) -> String {
+    match docs {
+        Some(docs) if !docs.contains('\n') => format!("{}/** {} */\n", indent, docs.trim()),
+        Some(docs) => {
+            let lines = docs
+                .lines()
+                .map(|line| format!("{} * {}", indent, line).trim_end().to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("{}/**\n{}\n{} */\n", indent, lines, indent)
+        }
+        None => String::new(),
+    }
+}
+
+/// Renders the types of the type registry as the TypeScript types of their JSON representation
+/// in `@polkadot/api`.
+struct TypeScript<'a> {
+    registry: Registry<'a>,
+    /// The declarations of the custom types, by their name.
+    definitions: BTreeMap<String, String>,
+}
+
+impl<'a> TypeScript<'a> {
+    /// Returns the TypeScript type of an argument or return type `spec`.
+    fn type_spec(&mut self, spec: &Value) -> Result<String> {
+        let id = spec
+            .get("type")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow::anyhow!("A type reference has no type id"))?;
+        self.render(id)
+    }
+
+    /// Returns the TypeScript type of the type `id`, adding the declarations of the custom types
+    /// it refers to to the `definitions`.
+    fn render(&mut self, id: u64) -> Result<String> {
+        let ty = self.registry.get(id)?;
+        let def = ty
+            .get("def")
+            .and_then(Value::as_object)
+            .ok_or_else(|| anyhow::anyhow!("The type {} has no definition", id))?;
+        let field_type = |field: &Value| {
+            field
+                .get("type")
+                .and_then(Value::as_u64)
+                .or_else(|| field.as_u64())
+                .ok_or_else(|| anyhow::anyhow!("The type {} refers to no type id", field))
+        };
+
+        if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+            return Ok(primitive_type(primitive).to_string());
+        }
+        if let Some(element) = def.get("sequence").or_else(|| def.get("array")) {
+            let element = field_type(element)?;
+            // byte sequences are represented as hex strings
+            if self.registry.is_primitive(element, "u8") {
+                return Ok("string".to_string());
+            }
+            return Ok(format!("Array<{}>", self.render(element)?));
+        }
+        if let Some(compact) = def.get("compact") {
+            return self.render(field_type(compact)?);
+        }
+        if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+            let fields = tuple
+                .iter()
+                .map(|field| self.render(field_type(field)?))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(if fields.is_empty() {
+                "null".to_string()
+            } else {
+                format!("[{}]", fields.join(", "))
+            });
+        }
+
+        let name = ty
+            .get("path")
+            .and_then(Value::as_array)
+            .and_then(|path| path.last())
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("The custom type {} has no path", id))?
+            .to_string();
+        let params = self
+            .registry
+            .params(id)?
+            .into_iter()
+            .map(|param| self.render(param))
+            .collect::<Result<Vec<_>>>()?;
+        match (name.as_str(), params.as_slice()) {
+            (name, _) if TS_PROVIDED_TYPES.contains(&name) => Ok(name.to_string()),
+            ("Option", [value]) => Ok(format!("{} | null", value)),
+            ("Result", [ok, err]) => Ok(format!("{{ ok: {} }} | {{ err: {} }}", ok, err)),
+            // generic types are inlined, as their type parameters are substituted
+            (_, [_, ..]) => self.structure(def),
+            _ => {
+                if !self.definitions.contains_key(&name) {
+                    // the name is reserved first, as the type may refer to itself
+                    self.definitions.insert(name.clone(), String::new());
+                    let docs = js_doc(docs(ty), "");
+                    let definition = match self.named_fields(def.get("composite"))? {
+                        Some(fields) => format!("export interface {} {}\n", name, fields),
+                        None => format!("export type {} = {};\n", name, self.structure(def)?),
+                    };
+                    self.definitions
+                        .insert(name.clone(), format!("{}{}", docs, definition));
+                }
+                Ok(name)
+            }
+        }
+    }
+
+    /// Returns the TypeScript type of a struct or enum definition.
+    ///
+    /// Enums without fields are represented by the name of their variant, other enums by an
+    /// object with the camel cased name of their variant as key.
+    fn structure(&mut self, def: &serde_json::Map<String, Value>) -> Result<String> {
+        if let Some(composite) = def.get("composite") {
+            return self.fields(composite);
+        }
+        let variants = def
+            .get("variant")
+            .and_then(|variant| variant.get("variants"))
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow::anyhow!("The type {:?} can't be generated", def))?;
+        let has_fields = |variant: &Value| {
+            matches!(
+                variant.get("fields").and_then(Value::as_array),
+                Some(fields) if !fields.is_empty()
+            )
+        };
+        let mut rendered = Vec::new();
+        for variant in variants {
+            let name = variant
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow::anyhow!("A variant has no name"))?;
+            if variants.iter().any(has_fields) {
+                let fields = self.fields(variant)?;
+                rendered.push(format!("{{ {}: {} }}", name.to_mixed_case(), fields));
+            } else {
+                rendered.push(format!("'{}'", name));
+            }
+        }
+        Ok(if rendered.is_empty() {
+            "never".to_string()
+        } else {
+            rendered.join(" | ")
+        })
+    }
+
+    /// Returns the TypeScript type of the fields of a struct or enum variant.
+    fn fields(&mut self, item: &Value) -> Result<String> {
+        if let Some(fields) = self.named_fields(Some(item))? {
+            return Ok(fields);
+        }
+        let fields = item
+            .get("fields")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|field| {
+                let id = field
+                    .get("type")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| anyhow::anyhow!("A field refers to no type id"))?;
+                self.render(id)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(match fields.as_slice() {
+            [] => "null".to_string(),
+            // newtypes are represented by their field
+            [field] => field.clone(),
+            fields => format!("[{}]", fields.join(", ")),
+        })
+    }
+
+    /// Returns the object type of the fields of a struct or enum variant, if they are named.
+    fn named_fields(&mut self, item: Option<&Value>) -> Result<Option<String>> {
+        let fields = item
+            .and_then(|item| item.get("fields"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if fields.is_empty() || fields.iter().any(|field| field.get("name").is_none()) {
+            return Ok(None);
+        }
+        let mut object = "{\n".to_string();
+        for field in fields {
+            let name = field
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let id = field
+                .get("type")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow::anyhow!("The field {} refers to no type id", name))?;
+            writeln!(object, "  {}: {};", name.to_mixed_case(), self.render(id)?)?;
+        }
+        object.push('}');
+        Ok(Some(object))
+    }
+}
+
+/// Returns the TypeScript type of the JSON representation of a primitive.
+fn primitive_type(primitive: &str) -> &'static str {
+    match primitive {
+        "bool" => "boolean",
+        "str" | "char" => "string",
+        "u8" | "u16" | "u32" | "i8" | "i16" | "i32" => "number",
+        _ => "BigInteger",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use serde_json::json;
+
+    fn metadata() -> Value {
+        json!({
+            "contract": { "name": "my_token" },
+            "V1": {
+                "spec": {
+                    "docs": [" A token."],
+                    "events": [{
+                        "label": "Transfer",
+                        "args": [
+                            { "label": "from", "type": { "type": 2 }, "indexed": true, "docs": [] },
+                            { "label": "value", "type": { "type": 3 }, "indexed": false,
+                              "docs": [] }
+                        ],
+                        "docs": [" Emitted on transfers."]
+                    }],
+                    "messages": [{
+                        "label": "balance_of", "selector": "0x0f755a56",
+                        "mutates": false, "payable": false,
+                        "args": [{ "label": "owner", "type": { "type": 1 } }],
+                        "returnType": { "type": 3, "displayName": ["Balance"] },
+                        "docs": [" Returns the balance of the owner."]
+                    }, {
+                        "label": "Token::transfer", "selector": "0x84a15da1",
+                        "mutates": true, "payable": false,
+                        "args": [
+                            { "label": "to", "type": { "type": 1 } },
+                            { "label": "value", "type": { "type": 3 } }
+                        ],
+                        "returnType": { "type": 4 },
+                        "docs": []
+                    }]
+                },
+                "types": [
+                    { "id": 0, "type": { "def": { "primitive": "u8" } } },
+                    { "id": 1, "type": { "path": ["ink_env", "types", "AccountId"],
+                      "def": { "composite": { "fields": [{ "type": 5 }] } } } },
+                    { "id": 2, "type": { "path": ["Option"],
+                      "params": [{ "name": "T", "type": 1 }],
+                      "def": { "variant": { "variants": [
+                          { "name": "None" }, { "name": "Some", "fields": [{ "type": 1 }] }
+                      ] } } } },
+                    { "id": 3, "type": { "def": { "primitive": "u128" } } },
+                    { "id": 4, "type": { "path": ["Result"],
+                      "params": [{ "name": "T", "type": 6 }, { "name": "E", "type": 7 }],
+                      "def": { "variant": { "variants": [] } } } },
+                    { "id": 5, "type": { "def": { "array": { "len": 32, "type": 0 } } } },
+                    { "id": 6, "type": { "def": { "tuple": [] } } },
+                    { "id": 7, "type": { "path": ["my_token", "Error"],
+                      "def": { "variant": { "variants": [
+                          { "name": "InsufficientBalance" },
+                          { "name": "Custom", "fields": [{ "type": 8 }] }
+                      ] } } } },
+                    { "id": 8, "type": { "path": ["my_token", "Details"],
+                      "def": { "composite": { "fields": [
+                          { "name": "error_code", "type": 0 },
+                          { "name": "payload", "type": 9 }
+                      ] } } } },
+                    { "id": 9, "type": { "def": { "sequence": { "type": 0 } } } }
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn typed_client_is_generated() {
+        // when
+        let source = typescript("my_token", &metadata()).unwrap();
+
+        // then
+        assert!(source.contains(
+            "export type Error = { insufficientBalance: null } | { custom: Details };\n"
+        ));
+        assert!(source
+            .contains("export interface Details {\n  errorCode: number;\n  payload: string;\n}\n"));
+        assert!(source.contains(
+            "/** Emitted on transfers. */\nexport interface TransferEvent {\n  \
+            from: AccountId | null;\n  value: BigInteger;\n}\n"
+        ));
+        assert!(source.contains(
+            "export type MyTokenEvent =\n  | { name: 'Transfer'; args: TransferEvent };\n"
+        ));
+        assert!(source.contains("/** A token. */\nexport class MyToken {\n"));
+        assert!(source.contains(
+            "  /** Returns the balance of the owner. */\n  balanceOf(\n    caller: string,\n    \
+            owner: AccountId, options: ContractOptions = {}\n  ): Promise<QueryResult<BigInteger>> \
+            {\n    return this.queryMessage('0x0f755a56', caller, options, [owner]);\n  }\n"
+        ));
+        assert!(source.contains(
+            "  transfer(to: AccountId, value: BigInteger, options: ContractOptions = {}): \
+            SubmittableExtrinsic<'promise'> {\n    \
+            return this.txMessage('0x84a15da1', options, [to, value]);\n  }\n"
+        ));
+        assert!(source.contains(
+            "): Promise<QueryResult<{ ok: null } | { err: Error }>> {\n    \
+            return this.queryMessage('0x84a15da1', caller, options, [to, value]);"
+        ));
+        assert!(source.contains("  decodeEvent(data: Uint8Array): MyTokenEvent {\n"));
+    }
+
+    #[test]
+    fn typed_rust_module_is_generated() {
+        // when
+        let source = rust("my_token", &metadata()).unwrap();
+
+        // then
+        assert!(source.contains(
+            "#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]\n\
+            pub enum Error {\n    InsufficientBalance,\n    Custom(Details),\n}\n"
+        ));
+        assert!(source.contains("pub struct Details {\n    pub error_code: u8,\n"));
+        assert!(source.contains(
+            "pub enum MyTokenEvent {\n    /// Emitted on transfers.\n    Transfer {\n        \
+            from: Option<AccountId>,\n        value: u128,\n    },\n}\n"
+        ));
+        assert!(source.contains("impl MyTokenEvent {\n"));
+        assert!(source.contains(
+            "    /// Returns the balance of the owner.\n    \
+            pub fn balance_of(&self, owner: AccountId) -> MessageCall<Balance> {\n        \
+            self.message_call([0x0f, 0x75, 0x5a, 0x56], (owner,))\n    }\n"
+        ));
+        assert!(source.contains(
+            "    pub fn transfer(&self, to: AccountId, value: u128) \
+            -> MessageCall<Result<(), Error>> {\n        \
+            self.message_call([0x84, 0xa1, 0x5d, 0xa1], (to, value))\n    }\n"
+        ));
+    }
+
+    #[test]
+    fn invalid_selectors_are_rejected() {
+        assert_eq!(
+            selector_bytes("0xCAFEBABE").unwrap(),
+            "[0xca, 0xfe, 0xba, 0xbe]"
+        );
+        assert!(selector_bytes("0xCAFE").is_err());
+        assert!(selector_bytes("0xCAFEBAXX").is_err());
+    }
+
+    #[test]
+    fn bindings_are_written_next_to_the_metadata() {
+        with_tmp_dir(|path| {
+            // given
+            let metadata_path = path.join("metadata.json");
+            fs::write(&metadata_path, metadata().to_string())?;
+            let cmd = BindgenCommand::from_iter(&[
+                "bindgen",
+                "--lang",
+                "ts",
+                metadata_path.to_str().unwrap(),
+            ]);
+
+            // when
+            let message = cmd.exec()?;
+
+            // then
+            assert!(path.join("my_token.ts").exists());
+            assert!(message.starts_with("Generated the TypeScript bindings of my_token"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn unknown_languages_are_rejected() {
+        assert!("ts".parse::<Lang>().is_ok());
+        assert_eq!("rust".parse::<Lang>(), Ok(Lang::Rust));
+        assert!("python".parse::<Lang>().is_err());
+    }
+}
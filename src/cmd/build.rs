@@ -14,29 +14,133 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+use super::{lint, verify_metadata};
+#[cfg(not(feature = "binaryen-as-dependency"))]
+use crate::wasm_opt;
 use crate::{
-    crate_metadata::CrateMetadata,
-    maybe_println, util, validate_wasm,
-    workspace::{Manifest, ManifestPath, Profile, Workspace},
-    BuildArtifacts, BuildResult, OptimizationPasses, OptimizationResult, UnstableFlags,
-    UnstableOptions, Verbosity, VerbosityFlags,
+    advisories,
+    build_cache::{Fingerprint, OptimizedWasmCache},
+    code_size::{BudgetExceeded, CodeSizeBudget, MaxCodeSize},
+    crate_metadata::{self, CrateMetadata},
+    maybe_println, polkavm,
+    size_report::{SizeDiff, SizeRecord, SizeReport},
+    toolchain, util,
+    validate_wasm::{self, ScheduleLimits, WasmFeature},
+    verifiable, wasm_compatibility,
+    workspace::{Lto, Manifest, ManifestPath, PanicStrategy, Profile, ProfileOverrides, Workspace},
+    BuildArtifacts, BuildResult, BuildTarget, OptimizationPasses, OptimizationResult, OutputType,
+    UnstableFlags, UnstableOptions, Verbosity, VerbosityFlags, WorkspaceBuildResult,
 };
-use anyhow::{Context, Result};
+use anyhow::{Context, Error, Result};
 use colored::Colorize;
-use parity_wasm::elements::{External, MemoryType, Module, Section};
+use parity_wasm::elements::{CustomSection, External, MemoryType, Module, Section};
+#[cfg(not(feature = "binaryen-as-dependency"))]
 use regex::Regex;
 use std::{
+    collections::VecDeque,
     convert::TryFrom,
     ffi::OsStr,
     fs::metadata,
     path::{Path, PathBuf},
-    process::Command,
-    str,
+    sync::{mpsc, Arc, Mutex},
+    thread,
 };
+#[cfg(not(feature = "binaryen-as-dependency"))]
+use std::{process::Command, str};
 use structopt::StructOpt;
 
-/// This is the maximum number of pages available for a contract to allocate.
-const MAX_MEMORY_PAGES: u32 = 16;
+/// Arguments to use when executing `build` or `check` commands.
+#[derive(Default)]
+pub(crate) struct ExecuteArgs {
+    /// The location of the Cargo manifest (`Cargo.toml`) file to use.
+    pub(crate) manifest_path: ManifestPath,
+    pub(crate) verbosity: Verbosity,
+    pub(crate) build_artifact: BuildArtifacts,
+    pub(crate) unstable_flags: UnstableFlags,
+    pub(crate) optimization_passes: OptimizationPasses,
+    /// Custom wasm-opt passes, run in addition to the `optimization_passes`.
+    pub(crate) custom_passes: Vec<String>,
+    /// Analyze the size of the contract by function and crate.
+    pub(crate) size_report: bool,
+    /// Keep the names and panic messages in the contract, for meaningful diagnostics on traps.
+    pub(crate) debug: bool,
+    /// Custom sections to retain in the final wasm, all others are stripped.
+    pub(crate) keep_sections: Vec<String>,
+    /// Custom sections to strip from the final wasm, even if they would be retained otherwise.
+    pub(crate) strip_sections: Vec<String>,
+    /// Custom sections to inject into the final wasm.
+    pub(crate) add_sections: Vec<AddSection>,
+    /// Directory to write the artifacts to, defaults to the `ink` directory of the target
+    /// directory.
+    pub(crate) output_dir: Option<PathBuf>,
+    /// Build even if nothing changed since the previous build.
+    pub(crate) force: bool,
+    /// Features of the contract to activate.
+    pub(crate) features: Vec<String>,
+    /// Do not activate the default features of the contract for the metadata generation.
+    pub(crate) no_default_features: bool,
+    /// Flags to pass to the compiler in addition to the linker arguments.
+    pub(crate) rustflags: Option<String>,
+    /// Settings overriding the `[profile.release]` of the contract.
+    pub(crate) profile: ProfileOverrides,
+    /// The format the result is printed in.
+    pub(crate) output_type: OutputType,
+    /// The schedule limits of the target chain the contract is validated against.
+    pub(crate) limits: ScheduleLimits,
+    /// Estimate the stack height of the contract and validate it against the `limits`.
+    pub(crate) estimate_stack_height: bool,
+    /// Proposals beyond the Wasm MVP which are supported by the target chain.
+    pub(crate) wasm_features: Vec<WasmFeature>,
+    /// The maximum size of the optimized contract.
+    pub(crate) code_size_budget: Option<CodeSizeBudget>,
+    /// The executor the contract is built for.
+    pub(crate) target: BuildTarget,
+    /// Compress the wasm inside the `.contract` bundle with zstd.
+    pub(crate) compress_bundle: bool,
+    /// Fail if a dependency is affected by an advisory, instead of only warning.
+    pub(crate) deny_advisories: bool,
+    /// Path or url of an advisory list supplementing the bundled advisories.
+    pub(crate) advisory_db: Option<String>,
+    /// Run the ink! lints before building, failing on their findings.
+    pub(crate) lint: bool,
+}
+
+/// A custom section to inject into the final wasm.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AddSection {
+    /// The name of the custom section.
+    pub name: String,
+    /// The content of the custom section.
+    pub payload: Vec<u8>,
+}
+
+impl std::str::FromStr for AddSection {
+    type Err = Error;
+
+    /// Parses `<name>=<content>` or `<name>=@<file>`, reading the content from the file.
+    fn from_str(input: &str) -> Result<Self> {
+        let mut split = input.splitn(2, '=');
+        let name = split.next().unwrap_or_default();
+        let value = split.next().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invalid custom section '{}', expected `<name>=<content>` or `<name>=@<file>`",
+                input
+            )
+        })?;
+        if name.is_empty() {
+            anyhow::bail!("The name of the custom section '{}' is empty", input)
+        }
+        let payload = match value.strip_prefix('@') {
+            Some(path) => std::fs::read(path)
+                .context(format!("Reading custom section content from {}", path))?,
+            None => value.as_bytes().to_vec(),
+        };
+        Ok(AddSection {
+            name: name.to_string(),
+            payload,
+        })
+    }
+}
 
 /// Executes build of the smart-contract which produces a wasm binary that is ready for deploying.
 ///
@@ -51,12 +155,17 @@ pub struct BuildCommand {
     ///
     /// - `all`: Generate the Wasm, the metadata and a bundled `<name>.contract` file.
     ///
-    /// - `code-only`: Only the Wasm is created, generation of metadata and a bundled
-    ///   `<name>.contract` file is skipped.
+    /// - `wasm`: Only the Wasm is created, generation of metadata and a bundled
+    ///   `<name>.contract` file is skipped. `code-only` is accepted as well.
+    ///
+    /// - `metadata`: Only the metadata is generated, the Wasm is not built.
+    ///
+    /// - `bundle`: The Wasm and a bundled `<name>.contract` file are created, the separate
+    ///   metadata is skipped.
     #[structopt(
         long = "generate",
         default_value = "all",
-        value_name = "all | code-only",
+        value_name = "all | wasm | metadata | bundle",
         verbatim_doc_comment
     )]
     build_artifact: BuildArtifacts,
@@ -74,8 +183,8 @@ pub struct BuildCommand {
     ///
     /// - `3`, execute 3 optimization passes (spends potentially a lot of time optimizing)
     ///
-    /// - `4`, execute 4 optimization passes (also flatten the IR, which can take a lot more time and memory
-    /// but is useful on more nested / complex / less-optimized input)
+    /// - `4`, execute 4 optimization passes (also flatten the IR, which can take a lot more time
+    ///   and memory but is useful on more nested / complex / less-optimized input)
     ///
     /// - `s`, execute default optimization passes, focusing on code size
     ///
@@ -88,38 +197,350 @@ pub struct BuildCommand {
     ///   The CLI argument always takes precedence over the profile value.
     #[structopt(long = "optimization-passes")]
     optimization_passes: Option<OptimizationPasses>,
+    /// Comma separated list of wasm-opt passes to run in addition to the optimization passes,
+    /// e.g. `--custom-passes dce,vacuum`.
+    ///
+    /// - It is possible to define the custom passes in the `[package.metadata.contract]` of your
+    ///   `Cargo.toml` as e.g. `custom-passes = ["dce", "vacuum"]`.
+    ///   The CLI argument always takes precedence over the profile value.
+    #[structopt(long = "custom-passes", use_delimiter = true)]
+    custom_passes: Vec<String>,
+    /// Break the size of the contract down by function and crate, to find out which
+    /// dependencies are bloating it.
+    ///
+    /// Sizes are determined after the wasm-opt optimizations. Unless the names are kept, the
+    /// contract is optimized a second time with the names preserved for the report.
+    #[structopt(long = "size-report")]
+    size_report: bool,
+    /// Build a debug contract, which keeps the names of functions and the panic messages.
+    ///
+    /// Traps of a debug contract on a development chain produce meaningful diagnostics.
+    /// The contract is considerably larger, don't deploy it to production.
+    #[structopt(long)]
+    debug: bool,
+    /// Retain the custom section with this name in the final wasm, all custom sections are
+    /// stripped by default.
+    #[structopt(long = "keep-section", value_name = "name")]
+    keep_sections: Vec<String>,
+    /// Strip the custom section with this name from the final wasm, e.g. the `name` section of
+    /// a debug build.
+    #[structopt(long = "strip-section", value_name = "name")]
+    strip_sections: Vec<String>,
+    /// Inject a custom section into the final wasm, e.g. `--add-section license=Apache-2.0`.
+    ///
+    /// The content is read from a file if it starts with `@`, e.g. `--add-section hash=@hash.txt`.
+    #[structopt(long = "add-section", value_name = "name=content")]
+    add_sections: Vec<AddSection>,
+    /// Directory to write the wasm, metadata and bundle artifacts to, defaults to
+    /// `target/ink`
+    #[structopt(long, parse(from_os_str))]
+    output_dir: Option<PathBuf>,
+    /// Build all ink! contracts of the workspace.
+    ///
+    /// With `--output-dir` the artifacts of each contract are written to a subdirectory named
+    /// after the contract.
+    #[structopt(long)]
+    all: bool,
+    /// Number of contracts to build concurrently with `--all`.
+    ///
+    /// Each contract is built in its own target directory `target/ink/<name>`, so concurrent
+    /// builds don't wait for each other's lock of the target directory. They only share the
+    /// package cache, which cargo locks while it downloads dependencies. The output of concurrent
+    /// builds is interleaved.
+    #[structopt(long, short = "j", default_value = "1")]
+    jobs: usize,
+    /// Build even if neither the sources nor the settings changed since the previous build.
+    ///
+    /// Otherwise the build is skipped and the artifacts of the previous build are reused.
+    #[structopt(long)]
+    force: bool,
+    /// Comma separated list of features of the contract to activate, for the Wasm build as well
+    /// as the metadata generation.
+    #[structopt(long, use_delimiter = true)]
+    features: Vec<String>,
+    /// Do not activate the default features of the contract.
+    ///
+    /// The Wasm build never activates the default features, since they enable `std`. Hence this
+    /// only affects the metadata generation, which is compiled natively and then usually needs
+    /// `--features std`.
+    #[structopt(long)]
+    no_default_features: bool,
+    /// Flags to pass to the compiler, in addition to the linker arguments required for
+    /// contracts, e.g. `--rustflags="--cfg feature_x"`.
+    #[structopt(long, allow_hyphen_values = true)]
+    rustflags: Option<String>,
+    /// Enable or disable overflow checks, overriding `overflow-checks` in `[profile.release]`.
+    ///
+    /// Without overflow checks arithmetic overflows wrap around silently instead of trapping.
+    #[structopt(long, value_name = "true | false")]
+    overflow_checks: Option<bool>,
+    /// The link-time optimization to use, overriding `lto` in `[profile.release]`.
+    #[structopt(long, value_name = "fat | thin | off | false")]
+    lto: Option<Lto>,
+    /// The number of codegen units, overriding `codegen-units` in `[profile.release]`.
+    #[structopt(long, value_name = "n")]
+    codegen_units: Option<u32>,
+    /// The panic strategy, overriding `panic` in `[profile.release]`.
+    ///
+    /// Only `abort` is supported for contracts, since unwinding is not available on-chain.
+    #[structopt(long, value_name = "abort | unwind")]
+    panic: Option<PanicStrategy>,
+    /// Print the result as `human` readable report or as `json`, containing the artifact paths,
+    /// the code hash, the sizes and the hash of the metadata.
+    ///
+    /// With `json` the progress output is suppressed.
+    #[structopt(long, default_value = "human", value_name = "human | json")]
+    output: OutputType,
+    /// Estimate the stack height of the contract and fail if it exceeds the `stack-height` limit.
+    ///
+    /// The memory and table of the contract are always validated against the limits of the
+    /// schedule, which can be set in the `[package.metadata.contract.limits]` of your
+    /// `Cargo.toml` as e.g. `memory-pages = 16`, `table-size = 4096` or `stack-height = 65536`.
+    #[structopt(long)]
+    estimate_stack_height: bool,
+    /// Comma separated list of proposals beyond the Wasm MVP which are supported by the target
+    /// chain, e.g. `--wasm-features multi-value,mutable-globals`. All others are rejected.
+    ///
+    /// The contract is compiled with the `multi-value` and `mutable-globals` proposals if they
+    /// are listed, and without them otherwise, since older `pallet-contracts` schedules reject
+    /// them. The other proposals are never used.
+    ///
+    /// The instructions of `sign-ext` and `bulk-memory` can't be post-processed yet, listing
+    /// them is an error.
+    ///
+    /// - It is possible to define the features of the target chain in the
+    ///   `[package.metadata.contract]` of your `Cargo.toml` as e.g.
+    ///   `wasm-features = ["mutable-globals"]`.
+    ///   The CLI argument always takes precedence over the profile value.
+    #[structopt(long, use_delimiter = true)]
+    wasm_features: Vec<WasmFeature>,
+    /// Fail the build if the optimized contract is larger than this number of bytes.
+    ///
+    /// - It is possible to define the budget in the `[package.metadata.contract.code-size]` of
+    ///   your `Cargo.toml` as e.g. `max = 65536`, or derive it from `MaxCodeLen` of the chain of
+    ///   a network profile via `network = "production"`. Set `exceeded = "warn"` to only warn
+    ///   about contracts exceeding the budget.
+    ///   The CLI argument always takes precedence over the profile value.
+    #[structopt(long, value_name = "bytes")]
+    max_code_size: Option<u32>,
+    /// Build the contract for `wasm` or, experimentally, as PolkaVM blob for the RISC-V based
+    /// contracts executor via `riscv`.
+    ///
+    /// RISC-V builds require a toolchain supporting the `riscv32emac-unknown-none-polkavm`
+    /// target, which the released compilers don't, and `polkatool`. The Wasm specific post
+    /// processing and validation are skipped.
+    #[structopt(long, default_value = "wasm", value_name = "wasm | riscv")]
+    target: BuildTarget,
+    /// Compress the wasm inside the `.contract` bundle with zstd.
+    ///
+    /// The bundle is decompressed transparently when used by `deploy`, `instantiate` and `call`.
+    #[structopt(long)]
+    compress_bundle: bool,
+    /// Build inside a pinned Docker image, so that anyone can reproduce the Wasm
+    /// byte-for-byte.
+    ///
+    /// The image fixes the versions of rustc, wasm-opt and cargo-contract. Its digest is
+    /// recorded in the `build_info` of the metadata. Requires Docker.
+    #[structopt(long, conflicts_with_all = &["all", "output-dir"])]
+    verifiable: bool,
+    /// The image to run a `--verifiable` build in, defaults to the image of this version of
+    /// cargo-contract
+    #[structopt(long, requires = "verifiable")]
+    image: Option<String>,
+    /// Fail the build if the contract depends on versions of ink! or parity-scale-codec with
+    /// known bugs, instead of only warning about them.
+    #[structopt(long)]
+    deny_advisories: bool,
+    /// Path or url of an advisory list, checked in addition to the advisories bundled with
+    /// cargo-contract.
+    #[structopt(long, value_name = "path | url")]
+    advisory_db: Option<String>,
+    /// Run the ink! lints on the contract before building it and fail on their findings,
+    /// see `cargo contract lint`.
+    #[structopt(long)]
+    lint: bool,
 }
 
 impl BuildCommand {
     pub fn exec(&self) -> Result<BuildResult> {
         let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        execute(self.execute_args(manifest_path, self.output_dir.clone())?)
+    }
+
+    /// Returns `true` if all contracts of the workspace should be built.
+    pub fn is_workspace_build(&self) -> bool {
+        self.all
+    }
+
+    /// Returns `true` if the contract should be built inside a pinned Docker image.
+    pub fn is_verifiable_build(&self) -> bool {
+        self.verifiable
+    }
+
+    /// Builds the contract inside a pinned Docker image, which prints the result of the build.
+    pub fn exec_verifiable(&self) -> Result<()> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+        let image = self.image.as_deref().unwrap_or(verifiable::DEFAULT_IMAGE);
+        let args = verifiable::container_args(&std::env::args().collect::<Vec<_>>());
+        verifiable::execute(&crate_metadata, image, &args)
+    }
+
+    /// Builds all ink! contracts of the workspace, one after another.
+    pub fn exec_workspace(&self) -> Result<WorkspaceBuildResult> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let contracts = crate_metadata::workspace_contracts(&manifest_path)?;
+        if contracts.is_empty() {
+            anyhow::bail!(
+                "No ink! contracts found in the workspace of {}",
+                manifest_path.as_ref().display()
+            )
+        }
+
+        let builds = contracts
+            .into_iter()
+            .map(|(name, contract)| {
+                let output_dir = self.output_dir.as_ref().map(|dir| dir.join(&name));
+                let args = self.execute_args(contract, output_dir)?;
+                Ok((name, args))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(WorkspaceBuildResult {
+            contracts: execute_concurrently(builds, self.jobs)?,
+            verbosity: TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?,
+            output_type: self.output,
+        })
+    }
+
+    /// Returns the arguments for building the contract at `manifest_path`.
+    fn execute_args(
+        &self,
+        manifest_path: ManifestPath,
+        output_dir: Option<PathBuf>,
+    ) -> Result<ExecuteArgs> {
         let unstable_flags: UnstableFlags =
             TryFrom::<&UnstableOptions>::try_from(&self.unstable_options)?;
-        let verbosity = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
+        let verbosity = match self.output {
+            // don't mix the progress with the JSON output
+            OutputType::Json => Verbosity::Quiet,
+            OutputType::HumanReadable => TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?,
+        };
 
-        // The CLI flag `optimization-passes` overwrites optimization passes which are
-        // potentially defined in the `Cargo.toml` profile.
+        // The CLI flags `optimization-passes` and `custom-passes` overwrite the settings which
+        // are potentially defined in the `Cargo.toml` profile.
+        let mut manifest = Manifest::new(manifest_path.clone())?;
         let optimization_passes = match self.optimization_passes {
             Some(opt_passes) => opt_passes,
-            None => {
-                let mut manifest = Manifest::new(manifest_path.clone())?;
-                match manifest.get_profile_optimization_passes() {
-                    // if no setting is found, neither on the cli nor in the profile,
-                    // then we use the default
-                    None => OptimizationPasses::default(),
-                    Some(opt_passes) => opt_passes,
-                }
-            }
+            // if no setting is found, neither on the cli nor in the profile, then we use the
+            // default
+            None => manifest
+                .get_profile_optimization_passes()
+                .unwrap_or_default(),
+        };
+        let custom_passes = if !self.custom_passes.is_empty() {
+            self.custom_passes.clone()
+        } else {
+            manifest.get_profile_custom_passes()?.unwrap_or_default()
+        };
+        let limits = manifest.get_profile_schedule_limits()?;
+        let wasm_features = if !self.wasm_features.is_empty() {
+            self.wasm_features.clone()
+        } else {
+            manifest.get_profile_wasm_features()?.unwrap_or_default()
+        };
+        validate_wasm::ensure_processable(&wasm_features)?;
+        let code_size_budget = match (self.max_code_size, manifest.get_profile_code_size_budget()?)
+        {
+            (Some(max), budget) => Some(CodeSizeBudget {
+                max: MaxCodeSize::Bytes(max),
+                exceeded: budget.map(|budget| budget.exceeded).unwrap_or_default(),
+            }),
+            (None, budget) => budget,
         };
 
-        execute(
-            &manifest_path,
+        let args = ExecuteArgs {
+            manifest_path,
             verbosity,
-            self.build_artifact,
+            build_artifact: self.build_artifact,
             unstable_flags,
             optimization_passes,
-        )
+            custom_passes: normalize_custom_passes(&custom_passes)?,
+            size_report: self.size_report,
+            debug: self.debug,
+            keep_sections: self.keep_sections.clone(),
+            strip_sections: self.strip_sections.clone(),
+            add_sections: self.add_sections.clone(),
+            output_dir,
+            force: self.force,
+            features: self.features.clone(),
+            no_default_features: self.no_default_features,
+            rustflags: self.rustflags.clone(),
+            profile: ProfileOverrides {
+                lto: self.lto,
+                codegen_units: self.codegen_units,
+                overflow_checks: self.overflow_checks,
+                panic: self.panic,
+            },
+            output_type: self.output,
+            limits,
+            estimate_stack_height: self.estimate_stack_height,
+            wasm_features,
+            code_size_budget,
+            target: self.target,
+            compress_bundle: self.compress_bundle,
+            deny_advisories: self.deny_advisories,
+            advisory_db: self.advisory_db.clone(),
+            lint: self.lint,
+        };
+        Ok(args)
+    }
+}
+
+/// Builds the named contracts on up to `jobs` threads.
+///
+/// The contracts of a workspace have separate target directories, see `CrateMetadata::collect`,
+/// hence cargo runs their builds in parallel. Returns the results in the order of `builds`. No
+/// further builds are started once a build failed.
+fn execute_concurrently(
+    builds: Vec<(String, ExecuteArgs)>,
+    jobs: usize,
+) -> Result<Vec<(String, BuildResult)>> {
+    let jobs = jobs.max(1).min(builds.len());
+    let queue = Arc::new(Mutex::new(
+        builds.into_iter().enumerate().collect::<VecDeque<_>>(),
+    ));
+    let (sender, receiver) = mpsc::channel();
+    let workers = (0..jobs)
+        .map(|_| {
+            let queue = queue.clone();
+            let sender = sender.clone();
+            thread::spawn(move || loop {
+                let next = queue.lock().expect("build queue lock poisoned").pop_front();
+                let (index, (name, args)) = match next {
+                    Some(build) => build,
+                    None => break,
+                };
+                let result = execute(args).context(format!("Building contract {} failed", name));
+                if result.is_err() {
+                    queue.lock().expect("build queue lock poisoned").clear();
+                }
+                let _ = sender.send((index, name, result));
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(sender);
+
+    let mut results = receiver.iter().collect::<Vec<_>>();
+    for worker in workers {
+        worker
+            .join()
+            .map_err(|_| anyhow::anyhow!("Build thread panicked"))?;
     }
+    results.sort_by_key(|(index, _, _)| *index);
+    results
+        .into_iter()
+        .map(|(_, name, result)| Ok((name, result?)))
+        .collect()
 }
 
 #[derive(Debug, StructOpt)]
@@ -140,22 +561,73 @@ impl CheckCommand {
         let unstable_flags: UnstableFlags =
             TryFrom::<&UnstableOptions>::try_from(&self.unstable_options)?;
         let verbosity: Verbosity = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
-        execute(
-            &manifest_path,
+
+        let args = ExecuteArgs {
+            manifest_path,
             verbosity,
-            BuildArtifacts::CheckOnly,
+            build_artifact: BuildArtifacts::CheckOnly,
             unstable_flags,
-            OptimizationPasses::Zero,
-        )
+            optimization_passes: OptimizationPasses::Zero,
+            custom_passes: Vec::new(),
+            size_report: false,
+            debug: false,
+            keep_sections: Vec::new(),
+            strip_sections: Vec::new(),
+            add_sections: Vec::new(),
+            output_dir: None,
+            force: false,
+            features: Vec::new(),
+            no_default_features: false,
+            rustflags: None,
+            profile: ProfileOverrides::default(),
+            output_type: OutputType::HumanReadable,
+            limits: ScheduleLimits::default(),
+            estimate_stack_height: false,
+            wasm_features: Vec::new(),
+            code_size_budget: None,
+            target: BuildTarget::Wasm,
+            compress_bundle: false,
+            deny_advisories: false,
+            advisory_db: None,
+            lint: false,
+        };
+        execute(args)
     }
 }
 
+/// Strips an optional leading `--` from the supplied wasm-opt pass names and ensures they are
+/// valid pass names, so they can't be used to smuggle other arguments to wasm-opt.
+fn normalize_custom_passes(passes: &[String]) -> Result<Vec<String>> {
+    passes
+        .iter()
+        .map(|pass| {
+            let pass = pass.trim().trim_start_matches("--");
+            let valid = !pass.is_empty()
+                && pass
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            if !valid {
+                anyhow::bail!("Invalid wasm-opt pass name '{}'", pass)
+            }
+            Ok(pass.to_string())
+        })
+        .collect()
+}
+
 /// Executes the supplied cargo command on the project in the specified directory, defaults to the
 /// current directory.
 ///
 /// Uses the unstable cargo feature [`build-std`](https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#build-std)
 /// to build the standard library with [`panic_immediate_abort`](https://github.com/johnthagen/min-sized-rust#remove-panic-string-formatting-with-panic_immediate_abort)
 /// which reduces the size of the Wasm binary by not including panic strings and formatting code.
+/// For `debug` builds the panic strings and formatting code are kept.
+///
+/// The `features` of the contract are activated and the `rustflags` are passed to the compiler
+/// in addition to the required linker arguments. The compiler uses exactly the
+/// `wasm_features` proposals beyond the Wasm MVP.
+///
+/// For the experimental RISC-V `target` only `core` and `alloc` are built and no linker
+/// arguments are passed, the ELF is linked into a PolkaVM blob by `polkatool` afterwards.
 ///
 /// # Cargo.toml optimizations
 ///
@@ -163,35 +635,97 @@ impl CheckCommand {
 /// the final Wasm binary size.
 ///
 /// Preferred default `[profile.release]` settings will be added if they are missing, existing
-/// user-defined settings will be preserved.
+/// user-defined settings will be preserved. The `profile` overrides take precedence over both.
+/// The resulting settings are checked for compatibility with on-chain execution before building.
 ///
 /// To disable this and use the original `Cargo.toml` as is then pass the `-Z original_manifest` flag.
+#[allow(clippy::too_many_arguments)]
 fn exec_cargo_for_wasm_target(
     crate_metadata: &CrateMetadata,
     command: &str,
     verbosity: Verbosity,
     unstable_flags: &UnstableFlags,
+    target: BuildTarget,
+    debug: bool,
+    features: &[String],
+    rustflags: Option<&str>,
+    profile: &ProfileOverrides,
+    wasm_features: &[WasmFeature],
 ) -> Result<()> {
-    util::assert_channel()?;
-
-    // set linker args via RUSTFLAGS.
-    // Currently will override user defined RUSTFLAGS from .cargo/config. See https://github.com/paritytech/cargo-contract/issues/98.
-    std::env::set_var(
-        "RUSTFLAGS",
-        "-C link-arg=-z -C link-arg=stack-size=65536 -C link-arg=--import-memory",
-    );
+    let toolchain = crate_metadata.toolchain.as_deref();
+    if let Some(toolchain) = toolchain {
+        toolchain::ensure_installed(toolchain)?;
+    }
+    util::assert_channel(toolchain)?;
+    if target == BuildTarget::RiscV {
+        polkavm::ensure_target_supported(toolchain)?;
+    }
+    let incompatibilities = check_wasm_compatibility(crate_metadata, target, features, verbosity);
 
     let cargo_build = |manifest_path: &ManifestPath| {
         let target_dir = &crate_metadata.target_directory;
-        let args = [
-            "--target=wasm32-unknown-unknown",
-            "-Zbuild-std",
-            "-Zbuild-std-features=panic_immediate_abort",
+        let target_dir_arg = format!("--target-dir={}", target_dir.to_string_lossy());
+        let target_arg = format!("--target={}", target.rustc_target());
+        let build_std_arg = match target {
+            BuildTarget::Wasm => "-Zbuild-std",
+            BuildTarget::RiscV => "-Zbuild-std=core,alloc",
+        };
+        let mut args = vec![
+            target_arg.as_str(),
+            build_std_arg,
             "--no-default-features",
             "--release",
-            &format!("--target-dir={}", target_dir.to_string_lossy()),
+            target_dir_arg.as_str(),
         ];
-        util::invoke_cargo(command, &args, manifest_path.directory(), verbosity)?;
+        if !debug && target == BuildTarget::Wasm {
+            args.push("-Zbuild-std-features=panic_immediate_abort");
+        }
+        let features_arg = format!("--features={}", features.join(","));
+        if !features.is_empty() {
+            args.push(features_arg.as_str());
+        }
+        // set linker args via RUSTFLAGS, only for the cargo process so that concurrent builds
+        // don't interfere.
+        // Currently will override user defined RUSTFLAGS from .cargo/config. See https://github.com/paritytech/cargo-contract/issues/98.
+        let mut rustflags_env = String::new();
+        if target == BuildTarget::Wasm {
+            rustflags_env.push_str(
+                "-C link-arg=-z -C link-arg=stack-size=65536 -C link-arg=--import-memory",
+            );
+            // the DWARF of debug builds is preserved in a sidecar file to resolve traps
+            if debug {
+                rustflags_env.push_str(" -C debuginfo=2");
+            }
+            rustflags_env.push_str(" -C target-feature=");
+            rustflags_env.push_str(&validate_wasm::target_features(wasm_features));
+        }
+        if let Some(rustflags) = rustflags {
+            rustflags_env.push(' ');
+            rustflags_env.push_str(rustflags);
+        }
+        let env = vec![("RUSTFLAGS", Some(rustflags_env.as_str()))];
+        util::invoke_cargo(
+            command,
+            &args,
+            manifest_path.directory(),
+            verbosity,
+            env,
+            toolchain,
+        )
+        .map_err(|err| {
+            if incompatibilities.is_empty() {
+                return err;
+            }
+            err.context(format!(
+                "The contract depends on crates which can't be compiled for {}:\n\t{}",
+                target.rustc_target(),
+                incompatibilities
+                    .iter()
+                    .map(|incompatibility| incompatibility.to_string().replace('\n', "\n\t"))
+                    .collect::<Vec<_>>()
+                    .join("\n\t")
+            ))
+        })?;
 
         Ok(())
     };
@@ -204,24 +738,113 @@ fn exec_cargo_for_wasm_target(
             "with 'original-manifest' enabled, the contract binary may not be of optimal size."
                 .bold()
         );
+        if !profile.is_empty() {
+            anyhow::bail!("Profile settings can't be overridden with 'original-manifest' enabled")
+        }
+        let warnings = Manifest::new(crate_metadata.manifest_path.clone())?
+            .check_profile_release_compatibility()?;
+        print_profile_warnings(&warnings, verbosity);
         cargo_build(&crate_metadata.manifest_path)?;
     } else {
-        Workspace::new(&crate_metadata.cargo_meta, &crate_metadata.root_package.id)?
-            .with_root_package_manifest(|manifest| {
-                manifest
-                    .with_removed_crate_type("rlib")?
-                    .with_profile_release_defaults(Profile::default_contract_release())?;
-                Ok(())
-            })?
-            .using_temp(cargo_build)?;
+        let mut warnings = Vec::new();
+        let mut workspace =
+            Workspace::new(&crate_metadata.cargo_meta, &crate_metadata.root_package.id)?;
+        workspace.with_root_package_manifest(|manifest| {
+            manifest
+                .with_removed_crate_type("rlib")?
+                .with_profile_release_defaults(Profile::default_contract_release())?
+                .with_profile_release_overrides(profile)?;
+            warnings = manifest.check_profile_release_compatibility()?;
+            Ok(())
+        })?;
+        print_profile_warnings(&warnings, verbosity);
+        workspace.using_temp(cargo_build)?;
     }
 
-    // clear RUSTFLAGS
-    std::env::remove_var("RUSTFLAGS");
+    Ok(())
+}
+
+/// Warns about the dependencies of the contract which can't be compiled for the `target`, e.g.
+/// because they link the Rust standard library, and returns them.
+///
+/// A failing check doesn't fail the build, the compiler reports the actual errors.
+fn check_wasm_compatibility(
+    crate_metadata: &CrateMetadata,
+    target: BuildTarget,
+    features: &[String],
+    verbosity: Verbosity,
+) -> Vec<wasm_compatibility::Incompatibility> {
+    let incompatibilities = match wasm_compatibility::check(
+        &crate_metadata.manifest_path,
+        target.rustc_target(),
+        features,
+    ) {
+        Ok(incompatibilities) => incompatibilities,
+        Err(err) => {
+            if let Verbosity::Verbose = verbosity {
+                eprintln!(
+                    "{} skipped the dependency compatibility check: {:?}",
+                    "warning:".yellow().bold(),
+                    err
+                );
+            }
+            return Vec::new();
+        }
+    };
+    for incompatibility in &incompatibilities {
+        maybe_println!(
+            verbosity,
+            "{} {}",
+            "warning:".yellow().bold(),
+            incompatibility.to_string().bold()
+        );
+    }
+    incompatibilities
+}
 
+/// Warns about the dependencies of the contract affected by advisories, or fails if
+/// `deny_advisories` is set.
+fn check_advisories(
+    crate_metadata: &CrateMetadata,
+    advisory_db: Option<&str>,
+    deny_advisories: bool,
+    verbosity: Verbosity,
+) -> Result<()> {
+    let findings = advisories::check(crate_metadata, advisory_db)?;
+    if deny_advisories && !findings.is_empty() {
+        let findings = findings
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n\t");
+        anyhow::bail!(
+            "The contract depends on versions with known bugs:\n\t{}",
+            findings
+        )
+    }
+    for finding in findings {
+        maybe_println!(
+            verbosity,
+            "{} {}",
+            "warning:".yellow().bold(),
+            finding.to_string().bold()
+        );
+    }
     Ok(())
 }
 
+/// Prints the warnings about profile settings which are not advisable for contracts.
+fn print_profile_warnings(warnings: &[String], verbosity: Verbosity) {
+    for warning in warnings {
+        maybe_println!(
+            verbosity,
+            "{} {}",
+            "warning:".yellow().bold(),
+            warning.bold()
+        );
+    }
+}
+
 /// Ensures the wasm memory import of a given module has the maximum number of pages.
 ///
 /// Iterates over the import section, finds the memory import entry if any and adjusts the maximum
@@ -251,29 +874,87 @@ fn ensure_maximum_memory_pages(module: &mut Module, maximum_allowed_pages: u32)
         }
     } else {
         let initial = mem_ty.limits().initial();
-        *mem_ty = MemoryType::new(initial, Some(MAX_MEMORY_PAGES));
+        *mem_ty = MemoryType::new(initial, Some(maximum_allowed_pages));
     }
 
     Ok(())
 }
 
-/// Strips all custom sections.
+/// Strips all custom sections, except for the ones named in `keep`.
 ///
 /// Presently all custom sections are not required so they can be stripped safely.
-fn strip_custom_sections(module: &mut Module) {
-    module.sections_mut().retain(|section| {
-        !matches!(
-            section,
-            Section::Custom(_) | Section::Name(_) | Section::Reloc(_)
-        )
+fn strip_custom_sections(module: &mut Module, keep: &[String]) {
+    let keep = |name: &str| keep.iter().any(|keep| keep == name);
+    module.sections_mut().retain(|section| match section {
+        Section::Custom(custom) => keep(custom.name()),
+        Section::Name(_) => keep("name"),
+        Section::Reloc(_) => false,
+        _ => true,
     });
 }
 
+/// Writes the DWARF sections of the original wasm to the debug info sidecar file next to the
+/// final wasm.
+///
+/// The sidecar is used by `cargo contract resolve-trap` to map functions of the contract to their
+/// source location.
+fn write_debug_info(crate_metadata: &CrateMetadata) -> Result<()> {
+    let module = parity_wasm::deserialize_file(&crate_metadata.original_wasm)?;
+    let sections = module
+        .custom_sections()
+        .filter(|section| {
+            section
+                .name()
+                .starts_with(super::resolve_trap::DWARF_SECTION_PREFIX)
+        })
+        .map(|section| Section::Custom(section.clone()))
+        .collect::<Vec<_>>();
+    let dest = super::resolve_trap::debug_info_path(&crate_metadata.dest_wasm);
+    if sections.is_empty() {
+        log::warn!(
+            "The original wasm contains no DWARF, {} is not written",
+            dest.display()
+        );
+        return Ok(());
+    }
+    parity_wasm::serialize_to_file(&dest, Module::new(sections))?;
+    Ok(())
+}
+
+/// Appends the supplied custom sections to the wasm file at `wasm`.
+fn add_custom_sections(wasm: &Path, sections: &[AddSection]) -> Result<()> {
+    let mut module = parity_wasm::deserialize_file(wasm)?;
+    for section in sections {
+        module
+            .sections_mut()
+            .push(Section::Custom(CustomSection::new(
+                section.name.clone(),
+                section.payload.clone(),
+            )));
+    }
+    parity_wasm::serialize_to_file(wasm, module)?;
+    Ok(())
+}
+
 /// Performs required post-processing steps on the wasm artifact.
-fn post_process_wasm(crate_metadata: &CrateMetadata) -> Result<()> {
+///
+/// Returns the post-processed module before its custom sections are stripped, whose names
+/// attribute the items of the size report. The custom sections named in `keep_sections` are
+/// retained in the `dest_wasm`.
+///
+/// The contract is validated against the schedule `limits`, including its estimated stack height
+/// if `estimate_stack_height` is set, and must not use Wasm proposals except for `wasm_features`.
+fn post_process_wasm(
+    crate_metadata: &CrateMetadata,
+    keep_sections: &[String],
+    limits: &ScheduleLimits,
+    estimate_stack_height: bool,
+    wasm_features: &[WasmFeature],
+) -> Result<Module> {
     // Deserialize wasm module from a file.
-    let mut module =
-        parity_wasm::deserialize_file(&crate_metadata.original_wasm).context(format!(
+    let mut module = parity_wasm::deserialize_file(&crate_metadata.original_wasm)
+        .map_err(|err| validate_wasm::describe_deserialization_error(err, wasm_features))
+        .context(format!(
             "Loading original wasm file '{}'",
             crate_metadata.original_wasm.display()
         ))?;
@@ -285,10 +966,14 @@ fn post_process_wasm(crate_metadata: &CrateMetadata) -> Result<()> {
     if pwasm_utils::optimize(&mut module, ["call", "deploy"].to_vec()).is_err() {
         anyhow::bail!("Optimizer failed");
     }
-    ensure_maximum_memory_pages(&mut module, MAX_MEMORY_PAGES)?;
-    strip_custom_sections(&mut module);
-
+    ensure_maximum_memory_pages(&mut module, limits.memory_pages)?;
+    // validate before the names are stripped, so that unknown imports can be attributed
     validate_wasm::validate_import_section(&module)?;
+    validate_wasm::validate_no_floating_point(&module)?;
+    validate_wasm::validate_limits(&module, limits, estimate_stack_height)?;
+    validate_wasm::validate_features(&module, wasm_features)?;
+    let named = module.clone();
+    strip_custom_sections(&mut module, keep_sections);
 
     debug_assert!(
         !module.clone().to_bytes().unwrap().is_empty(),
@@ -296,32 +981,41 @@ fn post_process_wasm(crate_metadata: &CrateMetadata) -> Result<()> {
     );
 
     parity_wasm::serialize_to_file(&crate_metadata.dest_wasm, module)?;
-    Ok(())
+    Ok(named)
 }
 
 /// Attempts to perform optional wasm optimization using `binaryen`.
 ///
 /// The intention is to reduce the size of bloated wasm binaries as a result of missing
 /// optimizations (or bugs?) between Rust and Wasm.
+///
+/// If `keep_names` is `true` the names are preserved by the optimizations. The optimizations
+/// may use the `wasm_features` proposals beyond the Wasm MVP.
+///
+/// The optimized wasm is cached, so the optimizations are not run again if cargo produces the
+/// same wasm in a subsequent build.
 fn optimize_wasm(
     crate_metadata: &CrateMetadata,
     optimization_passes: OptimizationPasses,
+    custom_passes: &[String],
+    keep_names: bool,
+    wasm_features: &[WasmFeature],
 ) -> Result<OptimizationResult> {
     let mut dest_optimized = crate_metadata.dest_wasm.clone();
     dest_optimized.set_file_name(format!("{}-opt.wasm", crate_metadata.package_name));
-    let _ = do_optimization(
-        crate_metadata.dest_wasm.as_os_str(),
-        &dest_optimized.as_os_str(),
+
+    let optimizer_version = optimizer_version()?;
+    optimize_cached(
+        crate_metadata,
+        &crate_metadata.dest_wasm,
+        &dest_optimized,
         optimization_passes,
+        custom_passes,
+        keep_names,
+        wasm_features,
+        &optimizer_version,
     )?;
 
-    if !dest_optimized.exists() {
-        return Err(anyhow::anyhow!(
-            "Optimization failed, optimized wasm output file `{}` not found.",
-            dest_optimized.display()
-        ));
-    }
-
     let original_size = metadata(&crate_metadata.dest_wasm)?.len() as f64 / 1000.0;
     let optimized_size = metadata(&dest_optimized)?.len() as f64 / 1000.0;
 
@@ -331,54 +1025,261 @@ fn optimize_wasm(
         dest_wasm: crate_metadata.dest_wasm.clone(),
         original_size,
         optimized_size,
+        optimization_passes,
+        custom_passes: custom_passes.to_vec(),
+        debug_info: keep_names,
+        optimizer_version,
+        instrumented_size: None,
     })
 }
 
-/// Optimizes the Wasm supplied as `crate_metadata.dest_wasm` using
-/// the `wasm-opt` binary.
+/// Optimizes the Wasm at `source` into `dest_optimized`, unless the optimized Wasm is cached.
+#[allow(clippy::too_many_arguments)]
+fn optimize_cached(
+    crate_metadata: &CrateMetadata,
+    source: &Path,
+    dest_optimized: &Path,
+    optimization_passes: OptimizationPasses,
+    custom_passes: &[String],
+    keep_names: bool,
+    wasm_features: &[WasmFeature],
+    optimizer_version: &str,
+) -> Result<()> {
+    let settings = format!(
+        "-O{} {:?} {} {:?} {}",
+        optimization_passes, custom_passes, keep_names, wasm_features, optimizer_version
+    );
+    let cache = OptimizedWasmCache::new(crate_metadata);
+    let key = OptimizedWasmCache::key(&std::fs::read(source)?, &settings);
+    match cache.get(&key) {
+        Some(cached) => {
+            log::info!("Using the cached optimized wasm {}", cached.display());
+            std::fs::copy(&cached, dest_optimized)?;
+        }
+        None => {
+            do_optimization(
+                source.as_os_str(),
+                dest_optimized.as_os_str(),
+                optimization_passes,
+                custom_passes,
+                keep_names,
+                wasm_features,
+            )?;
+            if dest_optimized.exists() {
+                if let Err(err) = cache.insert(&key, dest_optimized) {
+                    log::warn!("{:?}", err);
+                }
+            }
+        }
+    }
+
+    if !dest_optimized.exists() {
+        return Err(anyhow::anyhow!(
+            "Optimization failed, optimized wasm output file `{}` not found.",
+            dest_optimized.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the size report of the optimized contract.
+///
+/// The optimized `dest_wasm` only contains the names attributing its items to functions and
+/// crates if `keep_names` is set. Otherwise the `named` post-processed module is optimized with
+/// the names preserved, which doesn't change the code, and that Wasm is analyzed instead.
+#[allow(clippy::too_many_arguments)]
+fn optimized_size_report(
+    crate_metadata: &CrateMetadata,
+    named: Module,
+    optimization_passes: OptimizationPasses,
+    custom_passes: &[String],
+    keep_names: bool,
+    wasm_features: &[WasmFeature],
+    optimizer_version: &str,
+) -> Result<SizeReport> {
+    if keep_names {
+        return SizeReport::analyze(&parity_wasm::deserialize_file(&crate_metadata.dest_wasm)?);
+    }
+    let tmp_dir = tempfile::Builder::new()
+        .prefix("cargo-contract.size-report.")
+        .tempdir()?;
+    let source = tmp_dir.path().join("named.wasm");
+    let dest_optimized = tmp_dir.path().join("named-opt.wasm");
+    parity_wasm::serialize_to_file(&source, named)?;
+    optimize_cached(
+        crate_metadata,
+        &source,
+        &dest_optimized,
+        optimization_passes,
+        custom_passes,
+        true,
+        wasm_features,
+        optimizer_version,
+    )?;
+    SizeReport::analyze(&parity_wasm::deserialize_file(&dest_optimized)?)
+}
+
+/// Estimates the size of the contract on-chain, after `pallet-contracts` injected the gas
+/// metering and the stack height limiter into it.
+///
+/// The gas costs of the schedule of the chain only affect the encoding of the constants of the
+/// gas metering, hence the estimate is close to the actual size.
+fn instrumented_size(module: &Module, stack_height: u32) -> Result<f64> {
+    let module = pwasm_utils::inject_gas_counter(
+        module.clone(),
+        &pwasm_utils::rules::Set::default(),
+        "seal0",
+    )
+    .map_err(|_| anyhow::anyhow!("Injecting the gas metering into the contract failed"))?;
+    let module =
+        pwasm_utils::stack_height::inject_limiter(module, stack_height).map_err(|err| {
+            anyhow::anyhow!(
+                "Injecting the stack height limiter into the contract failed: {:?}",
+                err
+            )
+        })?;
+    Ok(module.to_bytes()?.len() as f64 / 1000.0)
+}
+
+/// Optimizes the Wasm supplied as `dest_wasm` using the `binaryen-rs` dependency.
 ///
 /// The supplied `optimization_level` denotes the number of optimization passes,
-/// resulting in potentially a lot of time spent optimizing.
+/// resulting in potentially a lot of time spent optimizing. The `custom_passes`
+/// are run afterwards, in the supplied order.
 ///
 /// If successful, the optimized wasm is written to `dest_optimized`.
+#[cfg(feature = "binaryen-as-dependency")]
 fn do_optimization(
     dest_wasm: &OsStr,
     dest_optimized: &OsStr,
     optimization_level: OptimizationPasses,
+    custom_passes: &[String],
+    debug_info: bool,
+    wasm_features: &[WasmFeature],
 ) -> Result<()> {
-    // check `wasm-opt` is installed
-    let which = which::which("wasm-opt");
-    if which.is_err() {
+    if !wasm_features.is_empty() {
         anyhow::bail!(
+            "The bundled binaryen only supports the Wasm MVP, use an installed wasm-opt to \
+            build contracts using proposals beyond it"
+        )
+    }
+    let dest_wasm_content = std::fs::read(dest_wasm)?;
+
+    let codegen_config = binaryen::CodegenConfig {
+        // number of optimization passes (spends potentially a lot of time optimizing)
+        optimization_level: optimization_level.to_passes(),
+        // the amount of shrinkage to apply, focusing on code size
+        shrink_level: optimization_level.to_shrink(),
+        // keep the names for debug builds
+        debug_info,
+    };
+    log::info!(
+        "Optimization level passed to binaryen dependency: {}, shrink level: {}, \
+        custom passes: {:?}",
+        codegen_config.optimization_level,
+        codegen_config.shrink_level,
+        custom_passes
+    );
+
+    let mut module = binaryen::Module::read(&dest_wasm_content)
+        .map_err(|_| anyhow::anyhow!("binaryen failed to read file content"))?;
+    module.optimize(&codegen_config);
+    if !custom_passes.is_empty() {
+        module
+            .run_optimization_passes(custom_passes, &codegen_config)
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "binaryen failed to run the custom passes {:?}",
+                    custom_passes
+                )
+            })?;
+    }
+
+    std::fs::write(dest_optimized, module.write())?;
+    Ok(())
+}
+
+/// Returns a description of the bundled `binaryen-rs` optimizer.
+#[cfg(feature = "binaryen-as-dependency")]
+fn optimizer_version() -> Result<String> {
+    Ok("binaryen-rs (bundled)".to_string())
+}
+
+/// Returns the version of `wasm-opt`, after checking that it is compatible.
+#[cfg(not(feature = "binaryen-as-dependency"))]
+fn optimizer_version() -> Result<String> {
+    check_wasm_opt_version_compatibility(&wasm_opt_path()?)
+}
+
+/// Returns the path of the `wasm-opt` binary, offering to download it if it is not installed.
+#[cfg(not(feature = "binaryen-as-dependency"))]
+fn wasm_opt_path() -> Result<PathBuf> {
+    match wasm_opt::find_or_download()? {
+        Some(path) => Ok(path),
+        None => anyhow::bail!(
             "wasm-opt not found! Make sure the binary is in your PATH environment.\n\
             We use this tool to optimize the size of your contract's Wasm binary.\n\n\
             wasm-opt is part of the binaryen package. You can find detailed\n\
             installation instructions on https://github.com/WebAssembly/binaryen#tools.\n\n\
-
             There are ready-to-install packages for many platforms:\n\
             * Debian/Ubuntu: apt-get install binaryen\n\
             * Homebrew: brew install binaryen\n\
             * Arch Linux: pacman -S binaryen\n\
-            * Windows: binary releases at https://github.com/WebAssembly/binaryen/releases"
+            * Windows: binary releases at https://github.com/WebAssembly/binaryen/releases\n\n\
+            Alternatively install cargo-contract with `--features binaryen-as-dependency`\n\
+            to use a bundled binaryen instead."
                 .to_string()
                 .bright_yellow()
-        );
+        ),
     }
-    let wasm_opt_path = which
-        .as_ref()
-        .expect("we just checked if which returned an err; qed")
-        .as_path();
-    log::info!("Path to wasm-opt executable: {}", wasm_opt_path.display());
+}
 
-    let _ = check_wasm_opt_version_compatibility(wasm_opt_path)?;
+/// Optimizes the Wasm supplied as `crate_metadata.dest_wasm` using
+/// the `wasm-opt` binary.
+///
+/// The supplied `optimization_level` denotes the number of optimization passes,
+/// resulting in potentially a lot of time spent optimizing. The `custom_passes`
+/// are run afterwards, in the supplied order. Only the `wasm_features` proposals beyond
+/// the Wasm MVP are enabled.
+///
+/// The compatibility of the `wasm-opt` version is checked by `optimizer_version`.
+///
+/// If successful, the optimized wasm is written to `dest_optimized`.
+#[cfg(not(feature = "binaryen-as-dependency"))]
+fn do_optimization(
+    dest_wasm: &OsStr,
+    dest_optimized: &OsStr,
+    optimization_level: OptimizationPasses,
+    custom_passes: &[String],
+    debug_info: bool,
+    wasm_features: &[WasmFeature],
+) -> Result<()> {
+    // check `wasm-opt` is installed, offer to download it otherwise
+    let wasm_opt_path = wasm_opt_path()?;
+    let wasm_opt_path = wasm_opt_path.as_path();
+    log::info!("Path to wasm-opt executable: {}", wasm_opt_path.display());
 
     log::info!(
-        "Optimization level passed to wasm-opt: {}",
-        optimization_level
+        "Optimization level passed to wasm-opt: {}, custom passes: {:?}",
+        optimization_level,
+        custom_passes
     );
-    let output = Command::new(wasm_opt_path)
-        .arg(dest_wasm)
+    let mut cmd = Command::new(wasm_opt_path);
+    cmd.arg(dest_wasm)
         .arg(format!("-O{}", optimization_level))
+        .args(custom_passes.iter().map(|pass| format!("--{}", pass)))
+        // the target features section is stripped, hence the features have to be passed
+        .arg("--mvp-features")
+        .args(
+            wasm_features
+                .iter()
+                .map(|feature| format!("--enable-{}", feature.binaryen_feature())),
+        );
+    if debug_info {
+        // preserve the names section
+        cmd.arg("-g");
+    }
+    let output = cmd
         .arg("-o")
         .arg(dest_optimized)
         // the memory in our module is imported, `wasm-opt` needs to be told that
@@ -410,8 +1311,9 @@ fn do_optimization(
 /// Checks if the wasm-opt binary under `wasm_opt_path` returns a version
 /// compatible with `cargo-contract`.
 ///
-/// Currently this must be a version >= 99.
-fn check_wasm_opt_version_compatibility(wasm_opt_path: &Path) -> Result<()> {
+/// Currently this must be a version >= 99. Returns the version output of wasm-opt.
+#[cfg(not(feature = "binaryen-as-dependency"))]
+fn check_wasm_opt_version_compatibility(wasm_opt_path: &Path) -> Result<String> {
     let cmd = Command::new(wasm_opt_path)
         .arg("--version")
         .output()
@@ -477,7 +1379,7 @@ fn check_wasm_opt_version_compatibility(wasm_opt_path: &Path) -> Result<()> {
             version_number
         );
     }
-    Ok(())
+    Ok(version_stdout.to_string())
 }
 
 /// Asserts that the contract's dependencies are compatible to the ones used in ink!.
@@ -496,42 +1398,329 @@ fn assert_compatible_ink_dependencies(
 ) -> Result<()> {
     for dependency in ["parity-scale-codec", "scale-info"].iter() {
         let args = ["-i", dependency, "--duplicates"];
-        let _ = util::invoke_cargo("tree", &args, manifest_path.directory(), verbosity).map_err(
-            |_| {
-                anyhow::anyhow!(
-                    "Mismatching versions of `{}` were found!\n\
+        let _ = util::invoke_cargo(
+            "tree",
+            args,
+            manifest_path.directory(),
+            verbosity,
+            vec![],
+            None,
+        )
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "Mismatching versions of `{}` were found!\n\
                      Please ensure that your contract and your ink! dependencies use a compatible \
                      version of this package.",
-                    dependency
-                )
-            },
-        )?;
+                dependency
+            )
+        })?;
     }
     Ok(())
 }
 
-/// Executes build of the smart-contract which produces a wasm binary that is ready for deploying.
+/// Builds the contract as PolkaVM blob with the experimental RISC-V backend.
 ///
-/// It does so by invoking `cargo build` and then post processing the final binary.
-pub(crate) fn execute(
-    manifest_path: &ManifestPath,
-    verbosity: Verbosity,
+/// The metadata and the bundle are generated as for Wasm contracts, containing the blob instead
+/// of the Wasm.
+#[allow(clippy::too_many_arguments)]
+fn execute_riscv(
+    crate_metadata: &CrateMetadata,
     build_artifact: BuildArtifacts,
-    unstable_flags: UnstableFlags,
-    optimization_passes: OptimizationPasses,
-) -> Result<BuildResult> {
-    let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+    verbosity: Verbosity,
+    unstable_flags: &UnstableFlags,
+    features: &[String],
+    no_default_features: bool,
+    rustflags: Option<&str>,
+    profile: &ProfileOverrides,
+    output_type: OutputType,
+    compress_bundle: bool,
+) -> Result<BuildResult> {
+    maybe_println!(
+        verbosity,
+        "{} {}",
+        "warning:".yellow().bold(),
+        "the RISC-V backend is experimental, the PolkaVM blob can't be deployed to chains \
+        executing Wasm contracts."
+            .bold()
+    );
+    maybe_println!(
+        verbosity,
+        " {} {}",
+        format!("[1/{}]", build_artifact.steps()).bold(),
+        "Building cargo project".bright_green().bold()
+    );
+    exec_cargo_for_wasm_target(
+        crate_metadata,
+        "build",
+        verbosity,
+        unstable_flags,
+        BuildTarget::RiscV,
+        false,
+        features,
+        rustflags,
+        profile,
+        &[],
+    )?;
+
+    maybe_println!(
+        verbosity,
+        " {} {}",
+        format!("[2/{}]", build_artifact.steps()).bold(),
+        "Linking PolkaVM blob".bright_green().bold()
+    );
+    maybe_println!(
+        verbosity,
+        " {} {}",
+        format!("[3/{}]", build_artifact.steps()).bold(),
+        "Optimizing PolkaVM blob".bright_green().bold()
+    );
+    // `polkatool link` optimizes the blob while linking it
+    let optimization_result = polkavm::link(crate_metadata)?;
+
+    let metadata_result = if build_artifact.includes_metadata() || build_artifact.includes_bundle()
+    {
+        Some(super::metadata::execute(
+            crate_metadata,
+            Some(&optimization_result),
+            verbosity,
+            build_artifact,
+            unstable_flags,
+            features,
+            no_default_features,
+            compress_bundle,
+        )?)
+    } else {
+        None
+    };
+    Ok(BuildResult {
+        dest_wasm: Some(optimization_result.dest_wasm.clone()),
+        metadata_result,
+        target_directory: crate_metadata.artifact_directory.clone(),
+        optimization_result: Some(optimization_result),
+        build_artifact,
+        verbosity,
+        size_report: None,
+        size_changes: None,
+        output_type,
+    })
+}
+
+/// Executes build of the smart-contract which produces a wasm binary that is ready for deploying.
+///
+/// It does so by invoking `cargo build` and then post processing the final binary.
+pub(crate) fn execute(args: ExecuteArgs) -> Result<BuildResult> {
+    let ExecuteArgs {
+        manifest_path,
+        verbosity,
+        build_artifact,
+        unstable_flags,
+        optimization_passes,
+        custom_passes,
+        size_report,
+        debug,
+        mut keep_sections,
+        strip_sections,
+        add_sections,
+        output_dir,
+        force,
+        features,
+        no_default_features,
+        rustflags,
+        profile,
+        output_type,
+        limits,
+        estimate_stack_height,
+        wasm_features,
+        code_size_budget,
+        target,
+        compress_bundle,
+        deny_advisories,
+        advisory_db,
+        lint,
+    } = args;
+
+    if debug {
+        keep_sections.push("name".to_string());
+    }
+    keep_sections.retain(|section| !strip_sections.contains(section));
+    let keep_names = keep_sections.iter().any(|section| section == "name");
+    let enabled_features = validate_wasm::enabled_features(&wasm_features);
+
+    let mut crate_metadata = CrateMetadata::collect(&manifest_path)?;
+    if let Some(output_dir) = output_dir {
+        std::fs::create_dir_all(&output_dir).context(format!(
+            "Creating the output directory {} failed",
+            output_dir.display()
+        ))?;
+        crate_metadata = crate_metadata.with_artifact_directory(output_dir.canonicalize()?);
+    }
+    check_advisories(
+        &crate_metadata,
+        advisory_db.as_deref(),
+        deny_advisories,
+        verbosity,
+    )?;
+    if lint {
+        lint::execute(&crate_metadata, None, true)?;
+    }
+    // the metadata doesn't depend on the target, hence metadata-only builds never build it
+    if target == BuildTarget::RiscV && build_artifact.includes_wasm() {
+        return execute_riscv(
+            &crate_metadata,
+            build_artifact,
+            verbosity,
+            &unstable_flags,
+            &features,
+            no_default_features,
+            rustflags.as_deref(),
+            &profile,
+            output_type,
+            compress_bundle,
+        );
+    }
+
+    // checks and size reports always require a build
+    let fingerprint = if !build_artifact.includes_wasm() || size_report || force {
+        None
+    } else {
+        let settings = serde_json::json!({
+            "build_artifact": format!("{:?}", build_artifact),
+            "original_manifest": unstable_flags.original_manifest,
+            "optimization_passes": optimization_passes.to_string(),
+            "custom_passes": custom_passes,
+            "debug": debug,
+            "keep_sections": keep_sections,
+            "add_sections": add_sections
+                .iter()
+                .map(|section| (&section.name, &section.payload))
+                .collect::<Vec<_>>(),
+            "artifact_directory": crate_metadata.artifact_directory,
+            "features": features,
+            "no_default_features": no_default_features,
+            "rustflags": rustflags,
+            "profile": format!("{:?}", profile),
+            "limits": format!("{:?}", limits),
+            "estimate_stack_height": estimate_stack_height,
+            "wasm_features": format!("{:?}", wasm_features),
+            "code_size_budget": format!("{:?}", code_size_budget),
+            "compress_bundle": compress_bundle,
+            "optimizer_version": optimizer_version()?,
+        });
+        Some(Fingerprint::new(&crate_metadata, &settings)?)
+    };
+    if let Some(fingerprint) = &fingerprint {
+        // if only inputs of the metadata changed, the Wasm is reused and the metadata regenerated
+        let previous = match fingerprint.previous_build(&crate_metadata, build_artifact)? {
+            Some(previous) => Some((previous, false)),
+            None => fingerprint
+                .previous_code_build(&crate_metadata)?
+                .map(|previous| (previous, true)),
+        };
+        if let Some((previous, metadata_changed)) = previous {
+            if metadata_changed {
+                maybe_println!(
+                    verbosity,
+                    " {} {}",
+                    "The code didn't change since the previous build,"
+                        .bright_green()
+                        .bold(),
+                    "reusing its Wasm. Pass `--force` to build anyway."
+                );
+            } else {
+                maybe_println!(
+                    verbosity,
+                    " {} {}",
+                    "Nothing changed since the previous build,"
+                        .bright_green()
+                        .bold(),
+                    "reusing its artifacts. Pass `--force` to build anyway."
+                );
+            }
+            let optimization_result = OptimizationResult {
+                dest_wasm: crate_metadata.dest_wasm.clone(),
+                original_size: previous.original_size,
+                optimized_size: previous.optimized_size,
+                optimization_passes,
+                custom_passes,
+                debug_info: keep_names,
+                optimizer_version: previous.optimizer_version,
+                instrumented_size: previous.instrumented_size,
+            };
+            let metadata_result =
+                if !(build_artifact.includes_metadata() || build_artifact.includes_bundle()) {
+                    None
+                } else if metadata_changed {
+                    Some(super::metadata::execute(
+                        &crate_metadata,
+                        Some(&optimization_result),
+                        verbosity,
+                        build_artifact,
+                        &unstable_flags,
+                        &features,
+                        no_default_features,
+                        compress_bundle,
+                    )?)
+                } else {
+                    Some(super::metadata::artifact_paths(&crate_metadata))
+                };
+            if metadata_changed {
+                fingerprint
+                    .clone()
+                    .record(&crate_metadata, &optimization_result)?;
+            }
+            return Ok(BuildResult {
+                dest_wasm: Some(crate_metadata.dest_wasm.clone()),
+                metadata_result,
+                target_directory: crate_metadata.artifact_directory,
+                optimization_result: Some(optimization_result),
+                build_artifact,
+                verbosity,
+                size_report: None,
+                size_changes: None,
+                output_type,
+            });
+        }
+    }
 
     assert_compatible_ink_dependencies(&manifest_path, verbosity)?;
 
-    let build = || -> Result<OptimizationResult> {
+    let build = || -> Result<(OptimizationResult, Option<SizeReport>, Option<SizeDiff>)> {
+        // resolved before building, so that an unreachable network fails early
+        let max_code_size = code_size_budget
+            .as_ref()
+            .map(|budget| budget.max.bytes(&manifest_path))
+            .transpose()?;
         maybe_println!(
             verbosity,
             " {} {}",
             format!("[1/{}]", build_artifact.steps()).bold(),
             "Building cargo project".bright_green().bold()
         );
-        exec_cargo_for_wasm_target(&crate_metadata, "build", verbosity, &unstable_flags)?;
+        if debug {
+            maybe_println!(
+                verbosity,
+                "{} {}",
+                "warning:".yellow().bold(),
+                "this is a debug build, the contract is larger than needed and must not be \
+                deployed to production."
+                    .bold()
+            );
+        }
+        exec_cargo_for_wasm_target(
+            &crate_metadata,
+            "build",
+            verbosity,
+            &unstable_flags,
+            target,
+            debug,
+            &features,
+            rustflags.as_deref(),
+            &profile,
+            &enabled_features,
+        )?;
+        if debug {
+            write_debug_info(&crate_metadata)?;
+        }
 
         maybe_println!(
             verbosity,
@@ -539,7 +1728,13 @@ pub(crate) fn execute(
             format!("[2/{}]", build_artifact.steps()).bold(),
             "Post processing wasm file".bright_green().bold()
         );
-        post_process_wasm(&crate_metadata)?;
+        let named = post_process_wasm(
+            &crate_metadata,
+            &keep_sections,
+            &limits,
+            estimate_stack_height,
+            &wasm_features,
+        )?;
 
         maybe_println!(
             verbosity,
@@ -547,60 +1742,170 @@ pub(crate) fn execute(
             format!("[3/{}]", build_artifact.steps()).bold(),
             "Optimizing wasm file".bright_green().bold()
         );
-        let optimization_result = optimize_wasm(&crate_metadata, optimization_passes)?;
+        let mut optimization_result = optimize_wasm(
+            &crate_metadata,
+            optimization_passes,
+            &custom_passes,
+            keep_names,
+            &enabled_features,
+        )?;
+        if !add_sections.is_empty() {
+            add_custom_sections(&crate_metadata.dest_wasm, &add_sections)?;
+        }
+        let report = optimized_size_report(
+            &crate_metadata,
+            named,
+            optimization_passes,
+            &custom_passes,
+            keep_names,
+            &enabled_features,
+            &optimization_result.optimizer_version,
+        )?;
+
+        let final_wasm = parity_wasm::deserialize_file(&crate_metadata.dest_wasm)?;
+        optimization_result.instrumented_size =
+            Some(instrumented_size(&final_wasm, limits.stack_height)?);
+        let size_changes = SizeRecord::new(&report, &final_wasm)?
+            .update(&crate_metadata.size_record())?
+            .filter(|diff| !diff.is_empty());
+        let mut attach_size_report = size_report;
+        if let (Some(budget), Some(max_code_size)) = (&code_size_budget, max_code_size) {
+            let size = metadata(&crate_metadata.dest_wasm)?.len();
+            if let Some(violation) = CodeSizeBudget::violation(max_code_size, size) {
+                match budget.exceeded {
+                    BudgetExceeded::Fail => {
+                        anyhow::bail!("{}\n\n{}", violation, report.display())
+                    }
+                    BudgetExceeded::Warn => {
+                        maybe_println!(
+                            verbosity,
+                            "{} {}",
+                            "warning:".yellow().bold(),
+                            violation.bold()
+                        );
+                        attach_size_report = true;
+                    }
+                }
+            }
+        }
+        let size_report = if attach_size_report {
+            Some(report)
+        } else {
+            None
+        };
 
-        Ok(optimization_result)
+        Ok((optimization_result, size_report, size_changes))
     };
 
-    let (opt_result, metadata_result) = match build_artifact {
+    let (opt_result, metadata_result, size_report, size_changes) = match build_artifact {
         BuildArtifacts::CheckOnly => {
-            exec_cargo_for_wasm_target(&crate_metadata, "check", verbosity, &unstable_flags)?;
-            (None, None)
+            maybe_println!(
+                verbosity,
+                " {} {}",
+                format!("[1/{}]", build_artifact.steps()).bold(),
+                "Checking cargo project".bright_green().bold()
+            );
+            exec_cargo_for_wasm_target(
+                &crate_metadata,
+                "check",
+                verbosity,
+                &unstable_flags,
+                target,
+                debug,
+                &features,
+                rustflags.as_deref(),
+                &profile,
+                &enabled_features,
+            )?;
+
+            maybe_println!(
+                verbosity,
+                " {} {}",
+                format!("[2/{}]", build_artifact.steps()).bold(),
+                "Validating metadata".bright_green().bold()
+            );
+            let ink_meta = super::metadata::generate_ink_metadata(
+                &crate_metadata,
+                verbosity,
+                &unstable_flags,
+                &features,
+                no_default_features,
+            )?;
+            verify_metadata::validate(&serde_json::Value::Object(ink_meta))?;
+            (None, None, None, None)
         }
         BuildArtifacts::CodeOnly => {
-            let optimization_result = build()?;
-            (Some(optimization_result), None)
+            let (optimization_result, size_report, size_changes) = build()?;
+            (Some(optimization_result), None, size_report, size_changes)
         }
-        BuildArtifacts::All => {
-            let optimization_result = build()?;
+        BuildArtifacts::MetadataOnly => {
+            let metadata_result = super::metadata::execute(
+                &crate_metadata,
+                None,
+                verbosity,
+                build_artifact,
+                &unstable_flags,
+                &features,
+                no_default_features,
+                compress_bundle,
+            )?;
+            (None, Some(metadata_result), None, None)
+        }
+        BuildArtifacts::All | BuildArtifacts::BundleOnly => {
+            let (optimization_result, size_report, size_changes) = build()?;
 
             let metadata_result = super::metadata::execute(
                 &crate_metadata,
-                optimization_result.dest_wasm.as_path(),
+                Some(&optimization_result),
                 verbosity,
-                build_artifact.steps(),
+                build_artifact,
                 &unstable_flags,
+                &features,
+                no_default_features,
+                compress_bundle,
             )?;
-            (Some(optimization_result), Some(metadata_result))
+            (
+                Some(optimization_result),
+                Some(metadata_result),
+                size_report,
+                size_changes,
+            )
         }
     };
+    if let (Some(fingerprint), Some(optimization_result)) = (fingerprint, &opt_result) {
+        fingerprint.record(&crate_metadata, optimization_result)?;
+    }
     let dest_wasm = opt_result.as_ref().map(|r| r.dest_wasm.clone());
     Ok(BuildResult {
         dest_wasm,
         metadata_result,
-        target_directory: crate_metadata.target_directory,
+        target_directory: crate_metadata.artifact_directory,
         optimization_result: opt_result,
         build_artifact,
         verbosity,
+        size_report,
+        size_changes,
+        output_type,
     })
 }
 
 #[cfg(feature = "test-ci-only")]
 #[cfg(test)]
 mod tests_ci_only {
-    use super::{assert_compatible_ink_dependencies, check_wasm_opt_version_compatibility};
+    #[cfg(not(feature = "binaryen-as-dependency"))]
+    use super::check_wasm_opt_version_compatibility;
+    use super::{assert_compatible_ink_dependencies, BuildTarget, ExecuteArgs};
     use crate::{
         cmd::{self, BuildCommand},
         util::tests::with_tmp_dir,
         workspace::Manifest,
-        BuildArtifacts, ManifestPath, OptimizationPasses, UnstableFlags, UnstableOptions,
-        Verbosity, VerbosityFlags,
-    };
-    use std::{
-        io::Write,
-        os::unix::fs::PermissionsExt,
-        path::{Path, PathBuf},
+        BuildArtifacts, ManifestPath, OptimizationPasses, OutputType, UnstableOptions, Verbosity,
+        VerbosityFlags,
     };
+    use std::path::PathBuf;
+    #[cfg(not(feature = "binaryen-as-dependency"))]
+    use std::{io::Write, os::unix::fs::PermissionsExt, path::Path};
+    use structopt::StructOpt;
 
     /// Modifies the `Cargo.toml` under the supplied `cargo_toml_path` by
     /// setting `optimization-passes` in `[package.metadata.contract]` to `passes`.
@@ -623,6 +1928,7 @@ mod tests_ci_only {
     /// "wasm-opt version `version`".
     ///
     /// Returns the path to this file.
+    #[cfg(not(feature = "binaryen-as-dependency"))]
     fn mock_wasm_opt_version(tmp_dir: &Path, version: &str) -> PathBuf {
         let path = tmp_dir.join("wasm-opt-mocked");
         {
@@ -642,14 +1948,12 @@ mod tests_ci_only {
             cmd::new::execute("new_project", Some(path)).expect("new project creation failed");
             let manifest_path =
                 ManifestPath::new(&path.join("new_project").join("Cargo.toml")).unwrap();
-            let res = super::execute(
-                &manifest_path,
-                Verbosity::Default,
-                BuildArtifacts::CodeOnly,
-                UnstableFlags::default(),
-                OptimizationPasses::default(),
-            )
-            .expect("build failed");
+            let args = ExecuteArgs {
+                manifest_path,
+                build_artifact: BuildArtifacts::CodeOnly,
+                ..Default::default()
+            };
+            let res = super::execute(args).expect("build failed");
 
             // our ci has set `CARGO_TARGET_DIR` to cache artifacts.
             // this dir does not include `/target/` as a path, hence
@@ -664,12 +1968,352 @@ mod tests_ci_only {
                 "CodeOnly should not generate the metadata"
             );
 
-            let optimized_size = res.optimization_result.unwrap().optimized_size;
+            let optimization = res.optimization_result.unwrap();
+            let optimized_size = optimization.optimized_size;
             assert!(optimized_size > 0.0);
 
             // our optimized contract template should always be below 3k.
             assert!(optimized_size < 3.0);
 
+            // the instrumentation adds the gas metering and the stack height limiter
+            let instrumented_size = optimization.instrumented_size.expect("must be estimated");
+            assert!(instrumented_size > optimized_size);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn example_templates_must_build() {
+        with_tmp_dir(|path| {
+            for name in &["erc20", "erc721", "multisig", "dns"] {
+                // given
+                let template = name.parse().expect("template must parse");
+                cmd::new::execute_with_template(name, Some(path), template, &Default::default())
+                    .expect("new project creation failed");
+                let manifest_path = ManifestPath::new(&path.join(name).join("Cargo.toml")).unwrap();
+                let args = ExecuteArgs {
+                    manifest_path,
+                    build_artifact: BuildArtifacts::CodeOnly,
+                    ..Default::default()
+                };
+
+                // when
+                let res = super::execute(args);
+
+                // then
+                assert!(res.is_ok(), "template {} failed to build", name);
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn artifacts_must_be_written_to_output_dir() {
+        with_tmp_dir(|path| {
+            // given
+            cmd::new::execute("new_project", Some(path)).expect("new project creation failed");
+            let manifest_path =
+                ManifestPath::new(&path.join("new_project").join("Cargo.toml")).unwrap();
+            let output_dir = path.join("artifacts");
+
+            // when
+            let args = ExecuteArgs {
+                manifest_path,
+                build_artifact: BuildArtifacts::All,
+                output_dir: Some(output_dir.clone()),
+                ..Default::default()
+            };
+            let res = super::execute(args).expect("build failed");
+
+            // then
+            let output_dir = output_dir.canonicalize()?;
+            assert_eq!(res.target_directory, output_dir);
+            assert!(output_dir.join("new_project.wasm").exists());
+            assert!(output_dir.join("new_project.contract").exists());
+            assert!(output_dir.join("metadata.json").exists());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn build_result_must_be_available_as_json() {
+        with_tmp_dir(|path| {
+            // given
+            cmd::new::execute("new_project", Some(path)).expect("new project creation failed");
+            let manifest_path = path.join("new_project").join("Cargo.toml");
+            let cmd = BuildCommand::from_iter(&[
+                "build",
+                "--manifest-path",
+                manifest_path.to_str().unwrap(),
+                "--output",
+                "json",
+            ]);
+
+            // when
+            let res = cmd.exec().expect("build failed");
+            let json = res.to_json()?;
+
+            // then
+            assert_eq!(res.output_type, OutputType::Json);
+            assert!(!res.verbosity.is_verbose());
+            let code_hash = json["code_hash"].as_str().expect("code hash must exist");
+            assert!(code_hash.starts_with("0x") && code_hash.len() == 66);
+            assert!(json["metadata_hash"].is_string());
+            assert!(json["dest_bundle"].is_string());
+            assert!(json["optimized_size_kb"].as_f64().unwrap() > 0.0);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn build_all_contracts_of_workspace() {
+        with_tmp_dir(|path| {
+            // given
+            cmd::new::execute("first", Some(path)).expect("new project creation failed");
+            cmd::new::execute("second", Some(path)).expect("new project creation failed");
+            std::fs::write(
+                path.join("Cargo.toml"),
+                "[workspace]\nmembers = [\"first\", \"second\"]\n",
+            )?;
+            let output_dir = path.join("artifacts");
+            let cmd = BuildCommand::from_iter(&[
+                "build",
+                "--all",
+                "--jobs",
+                "2",
+                "--manifest-path",
+                path.join("Cargo.toml").to_str().unwrap(),
+                "--output-dir",
+                output_dir.to_str().unwrap(),
+            ]);
+
+            // when
+            let res = cmd.exec_workspace().expect("workspace build failed");
+
+            // then
+            let names = res
+                .contracts
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(names, vec!["first", "second"]);
+            assert!(output_dir.join("first").join("first.contract").exists());
+            assert!(output_dir.join("second").join("second.contract").exists());
+            assert!(res.display().contains("second"));
+            // separate target directories, so that cargo doesn't serialize the builds
+            let target = path.join("target").join("ink");
+            assert!(target.join("first").join("wasm32-unknown-unknown").exists());
+            assert!(target
+                .join("second")
+                .join("wasm32-unknown-unknown")
+                .exists());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn build_all_contracts_of_generated_workspace() {
+        with_tmp_dir(|path| {
+            // given
+            let contracts = vec![
+                "router".to_string(),
+                "factory".to_string(),
+                "pair".to_string(),
+            ];
+            cmd::new::execute_workspace("dex", Some(path), &contracts, &Default::default())
+                .expect("new workspace creation failed");
+            let cmd = BuildCommand::from_iter(&[
+                "build",
+                "--all",
+                "--manifest-path",
+                path.join("dex").join("Cargo.toml").to_str().unwrap(),
+            ]);
+
+            // when
+            let res = cmd.exec_workspace().expect("workspace build failed");
+
+            // then
+            let mut names = res
+                .contracts
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>();
+            names.sort_unstable();
+            assert_eq!(names, vec!["factory", "pair", "router"]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn stub_of_generated_trait_definition_must_build() {
+        with_tmp_dir(|path| {
+            // given
+            let spec = path.join("token.toml");
+            std::fs::write(
+                &spec,
+                r#"
+                types = """
+                #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+                #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+                pub enum Error { InsufficientBalance }
+                """
+
+                [[message]]
+                name = "balance_of"
+                args = [{ name = "owner", type = "AccountId" }]
+                returns = "Balance"
+
+                [[message]]
+                name = "transfer"
+                mutates = true
+                selector = "0xdb20f9f5"
+                args = [{ name = "to", type = "AccountId" }, { name = "value", type = "Balance" }]
+                returns = "Result<(), Error>"
+                "#,
+            )?;
+            cmd::trait_definition::execute("token", Some(path), &spec, &Default::default())
+                .expect("trait definition generation failed");
+            let manifest_path =
+                ManifestPath::new(&path.join("token").join("stub").join("Cargo.toml")).unwrap();
+            let args = ExecuteArgs {
+                manifest_path,
+                build_artifact: BuildArtifacts::CodeOnly,
+                ..Default::default()
+            };
+
+            // when
+            let res = super::execute(args);
+
+            // then
+            assert!(res.is_ok(), "stub of the trait definition failed to build");
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn unchanged_build_must_be_skipped() {
+        with_tmp_dir(|path| {
+            // given
+            cmd::new::execute("new_project", Some(path)).expect("new project creation failed");
+            let manifest_path =
+                ManifestPath::new(&path.join("new_project").join("Cargo.toml")).unwrap();
+            let args = || ExecuteArgs {
+                manifest_path: manifest_path.clone(),
+                build_artifact: BuildArtifacts::CodeOnly,
+                ..Default::default()
+            };
+            let first = super::execute(args()).expect("build failed");
+            let first_modified =
+                std::fs::metadata(first.dest_wasm.as_ref().unwrap())?.modified()?;
+
+            // when
+            let second = super::execute(args()).expect("build failed");
+
+            // then
+            let second_modified =
+                std::fs::metadata(second.dest_wasm.as_ref().unwrap())?.modified()?;
+            assert_eq!(first_modified, second_modified);
+            assert_eq!(
+                first.optimization_result.unwrap().optimized_size,
+                second.optimization_result.unwrap().optimized_size
+            );
+
+            // when
+            let lib = path.join("new_project").join("lib.rs");
+            let source = std::fs::read_to_string(&lib)?;
+            std::fs::write(&lib, format!("{}\n", source))?;
+            let third = super::execute(args()).expect("build failed");
+
+            // then
+            let third_modified =
+                std::fs::metadata(third.dest_wasm.as_ref().unwrap())?.modified()?;
+            assert_ne!(first_modified, third_modified);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn changed_path_dependency_must_not_be_skipped() {
+        with_tmp_dir(|path| {
+            // given
+            cmd::new::execute("new_project", Some(path)).expect("new project creation failed");
+            let helper = path.join("helper");
+            std::fs::create_dir_all(helper.join("src"))?;
+            std::fs::write(
+                helper.join("Cargo.toml"),
+                "[package]\nname = \"helper\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+            )?;
+            std::fs::write(helper.join("src").join("lib.rs"), "#![no_std]\n")?;
+            let manifest = path.join("new_project").join("Cargo.toml");
+            let contents = std::fs::read_to_string(&manifest)?;
+            std::fs::write(
+                &manifest,
+                contents.replacen(
+                    "[dependencies]\n",
+                    "[dependencies]\nhelper = { path = \"../helper\" }\n",
+                    1,
+                ),
+            )?;
+            let manifest_path = ManifestPath::new(&manifest).unwrap();
+            let args = || ExecuteArgs {
+                manifest_path: manifest_path.clone(),
+                build_artifact: BuildArtifacts::CodeOnly,
+                ..Default::default()
+            };
+            let first = super::execute(args()).expect("build failed");
+            let first_modified =
+                std::fs::metadata(first.dest_wasm.as_ref().unwrap())?.modified()?;
+
+            // when
+            std::fs::write(
+                helper.join("src").join("lib.rs"),
+                "#![no_std]\npub fn answer() -> u32 { 42 }\n",
+            )?;
+            let second = super::execute(args()).expect("build failed");
+
+            // then
+            let second_modified =
+                std::fs::metadata(second.dest_wasm.as_ref().unwrap())?.modified()?;
+            assert_ne!(first_modified, second_modified);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn changed_docs_only_regenerate_the_metadata() {
+        with_tmp_dir(|path| {
+            // given
+            cmd::new::execute("new_project", Some(path)).expect("new project creation failed");
+            let manifest_path =
+                ManifestPath::new(&path.join("new_project").join("Cargo.toml")).unwrap();
+            let args = || ExecuteArgs {
+                manifest_path: manifest_path.clone(),
+                ..Default::default()
+            };
+            let first = super::execute(args()).expect("build failed");
+            let first_modified =
+                std::fs::metadata(first.dest_wasm.as_ref().unwrap())?.modified()?;
+
+            // when
+            let lib = path.join("new_project").join("lib.rs");
+            let source = std::fs::read_to_string(&lib)?;
+            std::fs::write(
+                &lib,
+                source.replace(
+                    "/// Simply returns the current value",
+                    "/// Changed. Simply returns the current value",
+                ),
+            )?;
+            let second = super::execute(args()).expect("build failed");
+
+            // then
+            let second_modified =
+                std::fs::metadata(second.dest_wasm.as_ref().unwrap())?.modified()?;
+            assert_eq!(first_modified, second_modified);
+            let metadata = second.metadata_result.expect("metadata must be generated");
+            let metadata = std::fs::read_to_string(metadata.dest_metadata)?;
+            assert!(metadata.contains("Changed."));
             Ok(())
         })
     }
@@ -683,14 +2327,12 @@ mod tests_ci_only {
             let manifest_path = ManifestPath::new(&project_dir.join("Cargo.toml")).unwrap();
 
             // when
-            super::execute(
-                &manifest_path,
-                Verbosity::Default,
-                BuildArtifacts::CheckOnly,
-                UnstableFlags::default(),
-                OptimizationPasses::default(),
-            )
-            .expect("build failed");
+            let args = ExecuteArgs {
+                manifest_path,
+                build_artifact: BuildArtifacts::CheckOnly,
+                ..Default::default()
+            };
+            super::execute(args).expect("build failed");
 
             // then
             assert!(
@@ -720,6 +2362,34 @@ mod tests_ci_only {
 
                 // we choose zero optimization passes as the "cli" parameter
                 optimization_passes: Some(OptimizationPasses::Zero),
+                custom_passes: Vec::new(),
+                size_report: false,
+                debug: false,
+                keep_sections: Vec::new(),
+                strip_sections: Vec::new(),
+                add_sections: Vec::new(),
+                output_dir: None,
+                all: false,
+                jobs: 1,
+                force: false,
+                features: Vec::new(),
+                no_default_features: false,
+                rustflags: None,
+                overflow_checks: None,
+                lto: None,
+                codegen_units: None,
+                panic: None,
+                output: OutputType::HumanReadable,
+                estimate_stack_height: false,
+                wasm_features: Vec::new(),
+                max_code_size: None,
+                target: BuildTarget::Wasm,
+                compress_bundle: false,
+                verifiable: false,
+                image: None,
+                deny_advisories: false,
+                advisory_db: None,
+                lint: false,
             };
 
             // when
@@ -759,6 +2429,34 @@ mod tests_ci_only {
 
                 // we choose no optimization passes as the "cli" parameter
                 optimization_passes: None,
+                custom_passes: Vec::new(),
+                size_report: false,
+                debug: false,
+                keep_sections: Vec::new(),
+                strip_sections: Vec::new(),
+                add_sections: Vec::new(),
+                output_dir: None,
+                all: false,
+                jobs: 1,
+                force: false,
+                features: Vec::new(),
+                no_default_features: false,
+                rustflags: None,
+                overflow_checks: None,
+                lto: None,
+                codegen_units: None,
+                panic: None,
+                output: OutputType::HumanReadable,
+                estimate_stack_height: false,
+                wasm_features: Vec::new(),
+                max_code_size: None,
+                target: BuildTarget::Wasm,
+                compress_bundle: false,
+                verifiable: false,
+                image: None,
+                deny_advisories: false,
+                advisory_db: None,
+                lint: false,
             };
 
             // when
@@ -783,6 +2481,103 @@ mod tests_ci_only {
         })
     }
 
+    #[test]
+    fn optimization_settings_must_be_recorded_in_build_result() {
+        with_tmp_dir(|path| {
+            // given
+            cmd::new::execute("new_project", Some(path)).expect("new project creation failed");
+            let cargo_toml_path = path.join("new_project").join("Cargo.toml");
+            let cmd = BuildCommand {
+                manifest_path: Some(cargo_toml_path),
+                build_artifact: BuildArtifacts::CodeOnly,
+                verbosity: VerbosityFlags::default(),
+                unstable_options: UnstableOptions::default(),
+                optimization_passes: Some(OptimizationPasses::Z),
+                custom_passes: vec!["dce".to_string(), "--vacuum".to_string()],
+                size_report: true,
+                debug: false,
+                keep_sections: Vec::new(),
+                strip_sections: Vec::new(),
+                add_sections: Vec::new(),
+                output_dir: None,
+                all: false,
+                jobs: 1,
+                force: false,
+                features: Vec::new(),
+                no_default_features: false,
+                rustflags: None,
+                overflow_checks: None,
+                lto: None,
+                codegen_units: None,
+                panic: None,
+                output: OutputType::HumanReadable,
+                estimate_stack_height: false,
+                wasm_features: Vec::new(),
+                max_code_size: None,
+                target: BuildTarget::Wasm,
+                compress_bundle: false,
+                verifiable: false,
+                image: None,
+                deny_advisories: false,
+                advisory_db: None,
+                lint: false,
+            };
+
+            // when
+            let res = cmd.exec().expect("build failed");
+            let size_report = res.size_report;
+            let optimization = res
+                .optimization_result
+                .expect("no optimization result available");
+
+            // then
+            // the report covers the optimized wasm, and still attributes it to functions
+            let size_report = size_report.expect("no size report available");
+            let dest_wasm = res.dest_wasm.expect("no wasm available");
+            assert!(u64::from(size_report.total) < std::fs::metadata(&dest_wasm)?.len());
+            assert!(size_report
+                .items
+                .iter()
+                .any(|item| !item.name.starts_with("func[")));
+            assert_eq!(optimization.optimization_passes, OptimizationPasses::Z);
+            assert_eq!(optimization.display_settings(), "-Oz --dce --vacuum");
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn debug_build_must_keep_names() {
+        with_tmp_dir(|path| {
+            // given
+            cmd::new::execute("new_project", Some(path)).expect("new project creation failed");
+            let manifest_path =
+                ManifestPath::new(&path.join("new_project").join("Cargo.toml")).unwrap();
+            let args = ExecuteArgs {
+                manifest_path,
+                build_artifact: BuildArtifacts::CodeOnly,
+                debug: true,
+                ..Default::default()
+            };
+
+            // when
+            let res = super::execute(args).expect("build failed");
+
+            // then
+            let dest_wasm = res.dest_wasm.unwrap();
+            let module = parity_wasm::deserialize_file(&dest_wasm)?;
+            assert!(module
+                .custom_sections()
+                .any(|section| section.name() == "name"));
+            let debug_info = parity_wasm::deserialize_file(
+                crate::cmd::resolve_trap::debug_info_path(&dest_wasm),
+            )?;
+            assert!(debug_info
+                .custom_sections()
+                .any(|section| section.name() == ".debug_info"));
+            Ok(())
+        })
+    }
+
     #[test]
     fn project_template_dependencies_must_be_ink_compatible() {
         with_tmp_dir(|path| {
@@ -829,6 +2624,7 @@ mod tests_ci_only {
         })
     }
 
+    #[cfg(not(feature = "binaryen-as-dependency"))]
     #[test]
     fn incompatible_wasm_opt_version_must_be_detected_if_built_from_repo() {
         with_tmp_dir(|path| {
@@ -849,6 +2645,7 @@ mod tests_ci_only {
         })
     }
 
+    #[cfg(not(feature = "binaryen-as-dependency"))]
     #[test]
     fn compatible_wasm_opt_version_must_be_detected_if_built_from_repo() {
         with_tmp_dir(|path| {
@@ -865,6 +2662,7 @@ mod tests_ci_only {
         })
     }
 
+    #[cfg(not(feature = "binaryen-as-dependency"))]
     #[test]
     fn incompatible_wasm_opt_version_must_be_detected_if_installed_as_package() {
         with_tmp_dir(|path| {
@@ -885,6 +2683,7 @@ mod tests_ci_only {
         })
     }
 
+    #[cfg(not(feature = "binaryen-as-dependency"))]
     #[test]
     fn compatible_wasm_opt_version_must_be_detected_if_installed_as_package() {
         with_tmp_dir(|path| {
@@ -901,3 +2700,182 @@ mod tests_ci_only {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        add_custom_sections, normalize_custom_passes, strip_custom_sections, AddSection,
+        BuildCommand, Lto, PanicStrategy,
+    };
+    use crate::{util::tests::with_tmp_dir, BuildArtifacts, BuildTarget, OutputType};
+    use parity_wasm::elements::{CustomSection, Module, Section};
+    use structopt::StructOpt;
+
+    fn module_with_custom_sections() -> Module {
+        let mut module = Module::default();
+        let sections = module.sections_mut();
+        sections.push(Section::Custom(CustomSection::new("name".into(), vec![])));
+        sections.push(Section::Custom(CustomSection::new(
+            "producers".into(),
+            vec![],
+        )));
+        module
+    }
+
+    #[test]
+    fn strip_custom_sections_keeps_requested_sections() {
+        let mut module = module_with_custom_sections();
+        strip_custom_sections(&mut module, &["name".to_string()]);
+        let names = module
+            .custom_sections()
+            .map(|section| section.name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["name".to_string()]);
+
+        let mut module = module_with_custom_sections();
+        strip_custom_sections(&mut module, &[]);
+        assert_eq!(module.custom_sections().count(), 0);
+    }
+
+    #[test]
+    fn add_section_is_parsed() {
+        let section: AddSection = "license=Apache-2.0".parse().unwrap();
+        assert_eq!(section.name, "license");
+        assert_eq!(section.payload, b"Apache-2.0".to_vec());
+
+        assert!("license".parse::<AddSection>().is_err());
+        assert!("=Apache-2.0".parse::<AddSection>().is_err());
+    }
+
+    #[test]
+    fn add_section_content_is_read_from_file() {
+        with_tmp_dir(|path| {
+            // given
+            let file = path.join("hash.txt");
+            std::fs::write(&file, "0x1234")?;
+
+            // when
+            let section: AddSection = format!("source-hash=@{}", file.display()).parse()?;
+
+            // then
+            assert_eq!(section.payload, b"0x1234".to_vec());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn custom_sections_are_added_to_wasm() {
+        with_tmp_dir(|path| {
+            // given
+            let wasm = path.join("contract.wasm");
+            parity_wasm::serialize_to_file(&wasm, Module::default())?;
+            let sections = vec![AddSection {
+                name: "license".to_string(),
+                payload: b"Apache-2.0".to_vec(),
+            }];
+
+            // when
+            add_custom_sections(&wasm, &sections)?;
+
+            // then
+            let module = parity_wasm::deserialize_file(&wasm)?;
+            let section = module
+                .custom_sections()
+                .next()
+                .expect("custom section must exist");
+            assert_eq!(section.name(), "license");
+            assert_eq!(section.payload(), b"Apache-2.0");
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn features_and_rustflags_are_parsed() {
+        let cmd = BuildCommand::from_iter(&[
+            "build",
+            "--features",
+            "feature-a,feature-b",
+            "--no-default-features",
+            "--rustflags",
+            "--cfg feature_x",
+        ]);
+
+        assert_eq!(cmd.features, vec!["feature-a", "feature-b"]);
+        assert_eq!(cmd.rustflags.as_deref(), Some("--cfg feature_x"));
+        assert!(cmd.no_default_features);
+
+        let cmd = BuildCommand::from_iter(&["build", "--rustflags=--cfg feature_y"]);
+        assert_eq!(cmd.rustflags.as_deref(), Some("--cfg feature_y"));
+    }
+
+    #[test]
+    fn profile_overrides_are_parsed() {
+        let cmd = BuildCommand::from_iter(&[
+            "build",
+            "--overflow-checks",
+            "false",
+            "--lto",
+            "thin",
+            "--codegen-units",
+            "16",
+            "--panic",
+            "abort",
+        ]);
+
+        assert_eq!(cmd.overflow_checks, Some(false));
+        assert_eq!(cmd.lto, Some(Lto::Thin));
+        assert_eq!(cmd.codegen_units, Some(16));
+        assert_eq!(cmd.panic, Some(PanicStrategy::Abort));
+    }
+
+    #[test]
+    fn output_type_is_parsed() {
+        let cmd = BuildCommand::from_iter(&["build", "--output", "json"]);
+        assert_eq!(cmd.output, OutputType::Json);
+
+        let cmd = BuildCommand::from_iter(&["build"]);
+        assert_eq!(cmd.output, OutputType::HumanReadable);
+    }
+
+    #[test]
+    fn build_target_is_parsed() {
+        let cmd = BuildCommand::from_iter(&["build", "--target", "riscv"]);
+        assert_eq!(cmd.target, BuildTarget::RiscV);
+        assert_eq!(
+            cmd.target.rustc_target(),
+            "riscv32emac-unknown-none-polkavm"
+        );
+
+        let cmd = BuildCommand::from_iter(&["build"]);
+        assert_eq!(cmd.target, BuildTarget::Wasm);
+    }
+
+    #[test]
+    fn generated_artifacts_are_parsed() {
+        let generate =
+            |artifacts| BuildCommand::from_iter(&["build", "--generate", artifacts]).build_artifact;
+        assert_eq!(generate("wasm"), BuildArtifacts::CodeOnly);
+        assert_eq!(generate("code-only"), BuildArtifacts::CodeOnly);
+        assert_eq!(generate("metadata"), BuildArtifacts::MetadataOnly);
+        assert_eq!(generate("bundle"), BuildArtifacts::BundleOnly);
+
+        let cmd = BuildCommand::from_iter(&["build"]);
+        assert_eq!(cmd.build_artifact, BuildArtifacts::All);
+    }
+
+    #[test]
+    fn custom_passes_are_normalized() {
+        let passes = vec!["dce".to_string(), "--vacuum".to_string()];
+        assert_eq!(
+            normalize_custom_passes(&passes).unwrap(),
+            vec!["dce".to_string(), "vacuum".to_string()]
+        );
+    }
+
+    #[test]
+    fn custom_passes_must_not_contain_other_arguments() {
+        let passes = vec!["dce -o /tmp/out.wasm".to_string()];
+        assert!(normalize_custom_passes(&passes).is_err());
+        assert!(normalize_custom_passes(&["--".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{convert::TryFrom, path::PathBuf};
+
+use anyhow::Result;
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use structopt::StructOpt;
+
+use crate::{
+    bundle::ContractBundle, cmd::metadata, crate_metadata::CrateMetadata, secret::SecretOpts,
+    workspace::ManifestPath,
+};
+
+/// Sign and verify `<name>.contract` bundles.
+#[derive(Debug, StructOpt)]
+pub enum BundleCommand {
+    /// Attach an author signature over the hashes of the Wasm and the metadata to a bundle
+    #[structopt(name = "sign")]
+    Sign(SignCommand),
+    /// Verify the author signature of a bundle
+    #[structopt(name = "verify")]
+    Verify(VerifyCommand),
+}
+
+impl BundleCommand {
+    pub fn exec(&self) -> Result<String> {
+        match self {
+            BundleCommand::Sign(sign) => sign.exec(),
+            BundleCommand::Verify(verify) => verify.exec(),
+        }
+    }
+}
+
+/// Signs a bundle with the key of its author.
+///
+/// The signature is stored in the bundle and verified by `deploy` before uploading the Wasm.
+#[derive(Debug, StructOpt)]
+pub struct SignCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    #[structopt(flatten)]
+    secret: SecretOpts,
+    /// Path to the bundle, defaults to `./target/ink/<name>.contract`
+    #[structopt(parse(from_os_str))]
+    bundle_path: Option<PathBuf>,
+}
+
+impl SignCommand {
+    pub fn exec(&self) -> Result<String> {
+        let bundle_path = bundle_path(self.manifest_path.as_ref(), self.bundle_path.as_ref())?;
+        let mut bundle = ContractBundle::load(&bundle_path)?;
+        let signer = bundle.sign(&self.secret.pair()?)?;
+        bundle.save()?;
+        Ok(format!(
+            "Signed {} as {}",
+            bundle_path.display(),
+            signer.to_ss58check()
+        ))
+    }
+}
+
+/// Verifies the author signature of a bundle.
+#[derive(Debug, StructOpt)]
+pub struct VerifyCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// The SS58 address of the expected author
+    #[structopt(long, parse(try_from_str = crate::parse_account))]
+    signer: Option<AccountId32>,
+    /// Path to the bundle, defaults to `./target/ink/<name>.contract`
+    #[structopt(parse(from_os_str))]
+    bundle_path: Option<PathBuf>,
+}
+
+impl VerifyCommand {
+    pub fn exec(&self) -> Result<String> {
+        let bundle_path = bundle_path(self.manifest_path.as_ref(), self.bundle_path.as_ref())?;
+        let signer = ContractBundle::load(&bundle_path)?
+            .verify_signature()?
+            .ok_or_else(|| anyhow::anyhow!("The bundle {} is not signed", bundle_path.display()))?;
+        if let Some(expected) = &self.signer {
+            if expected != &signer {
+                anyhow::bail!(
+                    "The bundle is signed by {}, expected {}",
+                    signer.to_ss58check(),
+                    expected.to_ss58check()
+                )
+            }
+        }
+        Ok(format!(
+            "Valid signature of {} by {}",
+            bundle_path.display(),
+            signer.to_ss58check()
+        ))
+    }
+}
+
+/// Returns the supplied bundle path, or the bundle of the contract at `manifest_path`.
+fn bundle_path(manifest_path: Option<&PathBuf>, path: Option<&PathBuf>) -> Result<PathBuf> {
+    match path {
+        Some(path) => Ok(path.clone()),
+        None => {
+            let manifest_path = ManifestPath::try_from(manifest_path)?;
+            let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+            Ok(metadata::artifact_paths(&crate_metadata).dest_bundle)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::metadata::blake2_hash, util::tests::with_tmp_dir};
+    use std::{fs, path::Path};
+
+    const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    const BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+
+    fn write_bundle(path: &Path) -> Result<PathBuf> {
+        let wasm = b"\0asm\x01\0\0\0";
+        let bundle = serde_json::json!({
+            "source": {
+                "hash": format!("0x{}", hex::encode(blake2_hash(wasm).0)),
+                "wasm": format!("0x{}", hex::encode(wasm)),
+            },
+            "contract": { "name": "flipper", "version": "0.1.0" },
+        });
+        let bundle_path = path.join("flipper.contract");
+        fs::write(&bundle_path, bundle.to_string())?;
+        Ok(bundle_path)
+    }
+
+    fn sign(bundle_path: &Path) -> Result<String> {
+        SignCommand::from_iter(&["sign", "--suri", "//Alice", bundle_path.to_str().unwrap()]).exec()
+    }
+
+    fn verify(bundle_path: &Path, signer: &str) -> Result<String> {
+        VerifyCommand::from_iter(&["verify", "--signer", signer, bundle_path.to_str().unwrap()])
+            .exec()
+    }
+
+    #[test]
+    fn signed_bundle_is_verified() {
+        with_tmp_dir(|path| {
+            // given
+            let bundle_path = write_bundle(path)?;
+
+            // when
+            sign(&bundle_path)?;
+
+            // then
+            assert!(verify(&bundle_path, ALICE)?.ends_with(ALICE));
+            assert!(verify(&bundle_path, BOB).is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn modified_metadata_is_rejected() {
+        with_tmp_dir(|path| {
+            // given
+            let bundle_path = write_bundle(path)?;
+            sign(&bundle_path)?;
+
+            // when
+            let modified = fs::read_to_string(&bundle_path)?.replace("flipper", "flopper");
+            fs::write(&bundle_path, modified)?;
+
+            // then
+            let err = verify(&bundle_path, ALICE).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "The metadata of the bundle was modified after it was signed"
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn unsigned_bundle_is_rejected() {
+        with_tmp_dir(|path| {
+            let bundle_path = write_bundle(path)?;
+            assert!(verify(&bundle_path, ALICE).is_err());
+            Ok(())
+        })
+    }
+}
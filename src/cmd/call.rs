@@ -0,0 +1,701 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::Result;
+use codec::Encode;
+use colored::Colorize;
+use heck::MixedCase as _;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::{fmt, path::PathBuf};
+use structopt::StructOpt;
+use subxt::{contracts::*, system::System, ClientBuilder, DefaultNodeRuntime, Signer};
+use url::Url;
+
+use super::{
+    receipt::{Receipt, ReceiptOpts},
+    scale_json::{item, ScaleJson},
+    trait_definition::{contract_of, Registry},
+    verify::contract_code_hash,
+};
+use crate::{
+    bundle::{ContractBundle, SpecItem},
+    deployments::MetadataSources,
+    rpc::RpcClient,
+    ExtrinsicOpts, HexData, OriginOpts,
+};
+
+/// Flag set in the result of a contract execution if the contract reverted its state changes.
+pub(super) const FLAG_REVERT: u32 = 1;
+
+/// The version of unsigned extrinsics, without the signed bit set.
+const EXTRINSIC_VERSION: u8 = 4;
+
+/// Calls a message of a deployed contract.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "call")]
+pub struct CallCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    #[structopt(flatten)]
+    origin_opts: OriginOpts,
+    #[structopt(flatten)]
+    receipt_opts: ReceiptOpts,
+    /// The address of the contract to call
+    #[structopt(name = "contract", long, parse(try_from_str = crate::parse_account))]
+    contract: AccountId32,
+    /// Path to the `<name>.contract` bundle of the contract
+    #[structopt(long, parse(from_os_str))]
+    bundle: Option<PathBuf>,
+    /// Name of the message to call, its selector is taken from the `--bundle`.
+    ///
+    /// Without a `--bundle` the metadata is resolved by the code hash of the contract on chain
+    /// from the deployment records, or the `registry` and `ipfs-gateway` of the network profile.
+    #[structopt(long)]
+    message: Option<String>,
+    /// Hex encoded call data: the message selector followed by the SCALE encoded arguments.
+    ///
+    /// If a `--message` is supplied, only its SCALE encoded arguments.
+    #[structopt(long, default_value = "")]
+    data: HexData,
+    /// Transfers a value to the contract with the call
+    #[structopt(name = "value", long, default_value = "0")]
+    value: u128,
+    /// Maximum amount of gas to be used for this command
+    #[structopt(name = "gas", long, default_value = "500000000")]
+    gas_limit: u64,
+    /// Dry-run the call via RPC instead of submitting an extrinsic.
+    ///
+    /// No secret key URI is required if the account to call from is supplied via `--origin`.
+    #[structopt(long)]
+    dry_run: bool,
+    /// Print the call in the hex encodings polkadot-js apps expects instead of submitting it,
+    /// to hand it to governance or other signers.
+    ///
+    /// Prints the call data, the encoded `contracts.call` with its hash and the unsigned
+    /// extrinsic. No secret key URI is required.
+    #[structopt(long, conflicts_with = "dry-run")]
+    prepare: bool,
+    /// Succeed only if the call fails, i.e. the contract traps, reverts or returns an `Err`.
+    ///
+    /// The call is dry-run instead of submitted, no extrinsic fees are spent on it. No secret key
+    /// URI is required if the account to call from is supplied via `--origin`.
+    #[structopt(long, conflicts_with = "prepare")]
+    expect_failure: bool,
+    /// Succeed only if the call fails with this error of the contract, e.g.
+    /// `MyError::InsufficientBalance`.
+    ///
+    /// The variant is compared with the decoded error of the `--message`, the type prefix is
+    /// optional. Implies `--expect-failure`.
+    #[structopt(long, conflicts_with = "prepare", requires = "message")]
+    expect_error: Option<String>,
+}
+
+/// Result of dry-running a contract call, as returned by the `contracts_call` RPC.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ContractExecResult {
+    /// The contract was executed successfully.
+    Success {
+        /// The return flags.
+        flags: u32,
+        /// The hex encoded output data.
+        data: String,
+        /// The amount of gas consumed by the call.
+        #[serde(alias = "gasConsumed")]
+        gas_consumed: u64,
+    },
+    /// The execution of the contract failed.
+    Error(()),
+}
+
+/// The way a call of a contract failed.
+#[derive(Debug, PartialEq)]
+enum CallFailure {
+    /// The execution of the contract trapped.
+    Trapped,
+    /// The contract reverted its state changes, returning the decoded output.
+    Reverted(Value),
+    /// The message returned the decoded `Err` without reverting.
+    Err(Value),
+}
+
+impl CallFailure {
+    /// Returns the failure of the dry-run `result` of a call, or `None` if it succeeded.
+    ///
+    /// The output is decoded by `decode`.
+    fn of(
+        result: &ContractExecResult,
+        decode: impl Fn(&[u8]) -> Result<Value>,
+    ) -> Result<Option<Self>> {
+        let (flags, data) = match result {
+            ContractExecResult::Success { flags, data, .. } => (flags, data),
+            ContractExecResult::Error(()) => return Ok(Some(CallFailure::Trapped)),
+        };
+        let output = decode(&hex::decode(data.trim_start_matches("0x"))?)?;
+        if flags & FLAG_REVERT == FLAG_REVERT {
+            return Ok(Some(CallFailure::Reverted(output)));
+        }
+        Ok(output.get("err").cloned().map(CallFailure::Err))
+    }
+
+    /// Returns the decoded error of the contract, if it returned one.
+    fn error(&self) -> Option<&Value> {
+        match self {
+            CallFailure::Trapped => None,
+            CallFailure::Reverted(output) => output.get("err"),
+            CallFailure::Err(error) => Some(error),
+        }
+    }
+}
+
+impl fmt::Display for CallFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallFailure::Trapped => write!(f, "the contract execution trapped"),
+            CallFailure::Reverted(output) => write!(f, "the contract reverted with {}", output),
+            CallFailure::Err(error) => write!(f, "the message returned the error {}", error),
+        }
+    }
+}
+
+impl CallCommand {
+    pub fn exec(&self) -> Result<String> {
+        if self.prepare {
+            let (data, call) = self.encode_call()?;
+            return Ok(serde_json::to_string_pretty(
+                &self.prepared_call(&data, &call),
+            )?);
+        }
+        if self.expect_failure || self.expect_error.is_some() {
+            return self.expect_failed_call();
+        }
+        if self.dry_run {
+            let origin = self.origin_opts.origin(&self.extrinsic_opts)?;
+            let result = self.call_rpc(&origin)?;
+            Ok(display_exec_result(&origin, &result))
+        } else {
+            if self.origin_opts.account().is_some() {
+                anyhow::bail!(
+                    "`--origin` is only supported for dry-runs, \
+                    submitted calls always originate from the account of the secret key URI"
+                )
+            }
+            self.call_extrinsic()
+        }
+    }
+
+    /// Dry-runs the call, succeeding only if it fails with the `--expect-error` if supplied.
+    fn expect_failed_call(&self) -> Result<String> {
+        let origin = self.origin_opts.origin(&self.extrinsic_opts)?;
+        let result = self.call_rpc(&origin)?;
+        let failure = match &self.message {
+            Some(message) => {
+                let bundle = self.bundle()?;
+                let metadata = Value::Object(bundle.json().clone());
+                let spec = contract_of(&metadata);
+                let registry = Registry::new(spec);
+                let message = item(spec, "messages", message)?;
+                CallFailure::of(&result, |output| {
+                    ScaleJson::new(&registry).decode_output(message, output)
+                })?
+            }
+            None => CallFailure::of(&result, |output| {
+                Ok(format!("0x{}", hex::encode(output)).into())
+            })?,
+        };
+        let failure = failure.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Expected the call to fail, but it succeeded\n\t{}",
+                display_exec_result(&origin, &result)
+            )
+        })?;
+        if let Some(expected) = &self.expect_error {
+            if !error_matches(expected, failure.error()) {
+                anyhow::bail!(
+                    "Expected the call to fail with {}, but {}",
+                    expected,
+                    failure
+                )
+            }
+        }
+        Ok(format!(
+            "{} {}",
+            "Failed as expected:".bright_green().bold(),
+            failure
+        ))
+    }
+
+    /// Returns the call data, prefixed with the selector of the `--message` if supplied.
+    fn call_data(&self) -> Result<Vec<u8>> {
+        match &self.message {
+            Some(message) => {
+                let mut data = self.bundle()?.selector(SpecItem::Message, message)?;
+                data.extend(&self.data.0);
+                Ok(data)
+            }
+            None => Ok(self.data.0.clone()),
+        }
+    }
+
+    /// Returns the bundle supplied via `--bundle`, or resolves the metadata of the contract from
+    /// the configured sources by its code hash on chain.
+    fn bundle(&self) -> Result<ContractBundle> {
+        if let Some(bundle) = &self.bundle {
+            return ContractBundle::load(bundle);
+        }
+        resolve_bundle(&self.extrinsic_opts, &self.contract)
+    }
+
+    /// Dry-runs the call from `origin` via the `contracts_call` RPC.
+    ///
+    /// The origin of the call does not need to be backed by any secret material.
+    fn call_rpc(&self, origin: &AccountId32) -> Result<ContractExecResult> {
+        let data = self.call_data()?;
+        dry_run(
+            &self.extrinsic_opts.url()?,
+            origin,
+            &self.contract,
+            self.value,
+            self.gas_limit,
+            &data,
+        )
+    }
+
+    /// Returns the call data and the `contracts.call` submitting it, encoded with the call index
+    /// of the runtime of the node.
+    fn encode_call(&self) -> Result<(Vec<u8>, Vec<u8>)> {
+        let data = self.call_data()?;
+        let dest: <DefaultNodeRuntime as System>::Address = self.contract.clone().into();
+        async_std::task::block_on(async move {
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_url(&self.extrinsic_opts.url()?.to_string())
+                .build()
+                .await?;
+            let call = cli.encode(CallCall {
+                dest: &dest,
+                value: self.value,
+                gas_limit: self.gas_limit,
+                data: &data,
+            })?;
+            Ok((data, call.0))
+        })
+    }
+
+    /// Returns the prepared call in the shapes of polkadot-js apps: the hex encoded call data of
+    /// the contract, the encoded `contracts.call` and its hash as shown by the extrinsics tab,
+    /// and the unsigned extrinsic submitting it.
+    fn prepared_call(&self, data: &[u8], call: &[u8]) -> Value {
+        let hex = |bytes: &[u8]| format!("0x{}", hex::encode(bytes));
+        // extrinsics are prefixed with their compact encoded length
+        let mut extrinsic = vec![EXTRINSIC_VERSION];
+        extrinsic.extend(call);
+        json!({
+            "contract": self.contract.to_ss58check(),
+            // as string, since balances may exceed the safe integers of JavaScript
+            "value": self.value.to_string(),
+            "gasLimit": self.gas_limit,
+            "data": hex(data),
+            "call": hex(call),
+            "callHash": hex(&sp_core::blake2_256(call)),
+            "extrinsic": hex(&extrinsic.encode()),
+        })
+    }
+
+    /// Submits the call as an extrinsic signed by the supplied secret key URI.
+    fn call_extrinsic(&self) -> Result<String> {
+        let signer = self.extrinsic_opts.signer()?;
+        let account = signer.account_id().clone();
+        let data = self.call_data()?;
+        let dest: <DefaultNodeRuntime as System>::Address = self.contract.clone().into();
+        let result = async_std::task::block_on(async {
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_url(&self.extrinsic_opts.url()?.to_string())
+                .build()
+                .await?;
+            let result = cli
+                .call_and_watch(signer.as_ref(), &dest, self.value, self.gas_limit, &data)
+                .await?;
+            Ok::<_, anyhow::Error>(result)
+        })?;
+
+        // the events of the contract are decoded if its metadata is at hand anyway
+        let metadata = match (
+            self.receipt_opts.is_requested(),
+            &self.bundle,
+            &self.message,
+        ) {
+            (true, Some(_), _) | (true, _, Some(_)) => {
+                Some(Value::Object(self.bundle()?.json().clone()))
+            }
+            _ => None,
+        };
+        let receipt = Receipt {
+            command: "call",
+            signer: account,
+            inputs: json!({
+                "contract": self.contract.to_ss58check(),
+                "message": self.message,
+                "data": format!("0x{}", hex::encode(&data)),
+                "value": self.value.to_string(),
+            }),
+            result: Value::Null,
+            gas_limit: Some(self.gas_limit),
+        };
+        receipt.write(
+            &self.receipt_opts,
+            &self.extrinsic_opts,
+            &result,
+            metadata.as_ref().map(|metadata| (&self.contract, metadata)),
+        )?;
+        Ok(format!(
+            "Call of contract {} included in block {:?}",
+            self.contract.to_ss58check(),
+            result.block
+        ))
+    }
+}
+
+/// Resolves the metadata of the `contract` by its code hash on chain from the deployment records,
+/// or the `registry` and `ipfs-gateway` of the network profile.
+pub(super) fn resolve_bundle(
+    extrinsic_opts: &ExtrinsicOpts,
+    contract: &AccountId32,
+) -> Result<ContractBundle> {
+    let rpc = RpcClient::new(&extrinsic_opts.url()?)?;
+    let code_hash = contract_code_hash(&rpc, contract)?;
+    let manifest_path = extrinsic_opts.manifest_path()?;
+    let network = extrinsic_opts.network()?.unwrap_or_default();
+    let sources = MetadataSources {
+        manifest_path: &manifest_path,
+        registry: network.registry.as_ref(),
+        ipfs_gateway: network.ipfs_gateway.as_ref(),
+    };
+    sources.resolve(&contract.to_ss58check(), &code_hash)
+}
+
+/// Dry-runs the call of the contract `dest` with `data` from `origin` via the `contracts_call` RPC
+/// of the node at `url`.
+///
+/// The origin of the call does not need to be backed by any secret material.
+pub(super) fn dry_run(
+    url: &Url,
+    origin: &AccountId32,
+    dest: &AccountId32,
+    value: u128,
+    gas_limit: u64,
+    data: &[u8],
+) -> Result<ContractExecResult> {
+    dry_run_at(url, None, origin, dest, value, gas_limit, data)
+}
+
+/// Dry-runs the call like [`dry_run`] on the state of the block with the hash `at`, or of the
+/// latest block.
+pub(super) fn dry_run_at(
+    url: &Url,
+    at: Option<&str>,
+    origin: &AccountId32,
+    dest: &AccountId32,
+    value: u128,
+    gas_limit: u64,
+    data: &[u8],
+) -> Result<ContractExecResult> {
+    let call_request = json!({
+        "origin": origin.to_ss58check(),
+        "dest": dest.to_ss58check(),
+        "value": value,
+        "gasLimit": gas_limit,
+        "inputData": format!("0x{}", hex::encode(data)),
+    });
+    let params = match at {
+        Some(at) => json!([call_request, at]),
+        None => json!([call_request]),
+    };
+    RpcClient::new(url)?.request("contracts_call", params)
+}
+
+/// Returns `true` if the decoded `error` of a contract is the `expected` variant, e.g.
+/// `MyError::InsufficientBalance`.
+fn error_matches(expected: &str, error: Option<&Value>) -> bool {
+    let variant = expected.rsplit("::").next().unwrap_or(expected);
+    match error {
+        // variants without fields are decoded as their name, others as object
+        Some(Value::String(name)) => name == variant,
+        Some(Value::Object(object)) => object.contains_key(&variant.to_mixed_case()),
+        _ => false,
+    }
+}
+
+/// Returns a human readable representation of the dry-run result.
+pub(super) fn display_exec_result(origin: &AccountId32, result: &ContractExecResult) -> String {
+    let result = match result {
+        ContractExecResult::Success {
+            flags,
+            data,
+            gas_consumed,
+        } => {
+            let reverted = flags & FLAG_REVERT == FLAG_REVERT;
+            format!(
+                "{}\n\t{} {}\n\t{} {}\n\t{} {}",
+                if reverted {
+                    "Reverted".bright_yellow().bold()
+                } else {
+                    "Success".bright_green().bold()
+                },
+                "Data:".bold(),
+                data,
+                "Flags:".bold(),
+                flags,
+                "Gas consumed:".bold(),
+                gas_consumed
+            )
+        }
+        ContractExecResult::Error(()) => "Error, the contract execution trapped"
+            .bright_red()
+            .bold()
+            .to_string(),
+    };
+    format!(
+        "{} {}\n\t{} {}",
+        "Dry-run from:".bold(),
+        origin.to_ss58check(),
+        "Result:".bold(),
+        result
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    #[test]
+    fn decodes_rpc_exec_result() {
+        let success: ContractExecResult = serde_json::from_str(
+            r#"{ "success": { "flags": 0, "data": "0x01", "gas_consumed": 1000 } }"#,
+        )
+        .expect("decoding success failed");
+        assert!(matches!(
+            success,
+            ContractExecResult::Success {
+                flags: 0,
+                gas_consumed: 1000,
+                ..
+            }
+        ));
+
+        let error: ContractExecResult =
+            serde_json::from_str(r#"{ "error": null }"#).expect("decoding error failed");
+        assert!(matches!(error, ContractExecResult::Error(())));
+    }
+
+    #[test]
+    fn failures_of_calls_are_classified() {
+        let result = |flags, data: &str| ContractExecResult::Success {
+            flags,
+            data: data.into(),
+            gas_consumed: 0,
+        };
+        let decode = |output: &[u8]| {
+            Ok(match output {
+                [0] => json!({ "ok": null }),
+                _ => json!({ "err": "InsufficientBalance" }),
+            })
+        };
+
+        assert_eq!(
+            CallFailure::of(&ContractExecResult::Error(()), decode).unwrap(),
+            Some(CallFailure::Trapped)
+        );
+        assert_eq!(CallFailure::of(&result(0, "0x00"), decode).unwrap(), None);
+        assert_eq!(
+            CallFailure::of(&result(0, "0x01"), decode).unwrap(),
+            Some(CallFailure::Err(json!("InsufficientBalance")))
+        );
+        assert_eq!(
+            CallFailure::of(&result(FLAG_REVERT, "0x01"), decode).unwrap(),
+            Some(CallFailure::Reverted(
+                json!({ "err": "InsufficientBalance" })
+            ))
+        );
+    }
+
+    #[test]
+    fn expected_errors_are_matched_by_variant() {
+        let unit = json!("InsufficientBalance");
+        assert!(error_matches("MyError::InsufficientBalance", Some(&unit)));
+        assert!(error_matches("InsufficientBalance", Some(&unit)));
+        assert!(!error_matches(
+            "MyError::InsufficientAllowance",
+            Some(&unit)
+        ));
+
+        let with_fields = json!({ "insufficientBalance": { "required": 10 } });
+        assert!(error_matches(
+            "MyError::InsufficientBalance",
+            Some(&with_fields)
+        ));
+        assert!(!error_matches("MyError::InsufficientBalance", None));
+    }
+
+    #[test]
+    fn origin_takes_precedence_over_secret() {
+        let bob = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+        let opts = ExtrinsicOpts::from_iter(&["", "--suri", "//Alice"]);
+        let origin_opts = OriginOpts::from_iter(&["", "--origin", bob]);
+        let origin = origin_opts.origin(&opts).expect("origin must be resolved");
+        assert_eq!(origin.to_ss58check(), bob);
+    }
+
+    #[test]
+    fn origin_defaults_to_account_of_secret() {
+        let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let opts = ExtrinsicOpts::from_iter(&["", "--suri", "//Alice"]);
+        let origin = OriginOpts::default()
+            .origin(&opts)
+            .expect("origin must be resolved");
+        assert_eq!(origin.to_ss58check(), alice);
+    }
+
+    #[test]
+    fn origin_is_only_accepted_by_dry_running_commands() {
+        let bob = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+        assert!(ExtrinsicOpts::from_iter_safe(&["", "--origin", bob]).is_err());
+        let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let cmd = CallCommand::from_iter_safe(&["call", "--contract", alice, "--origin", bob]);
+        assert!(cmd.is_ok());
+    }
+
+    #[test]
+    fn url_overrides_default_node() {
+        let opts = ExtrinsicOpts::from_iter(&[""]);
+        assert_eq!(opts.url().unwrap().as_str(), "ws://localhost:9944/");
+
+        let opts = ExtrinsicOpts::from_iter(&["", "--url", "wss://rpc.example.com"]);
+        assert_eq!(opts.url().unwrap().as_str(), "wss://rpc.example.com/");
+    }
+
+    #[test]
+    fn network_profile_is_loaded_from_manifest_path() {
+        with_tmp_dir(|path| {
+            // given
+            let manifest_path = path.join("Cargo.toml");
+            std::fs::write(
+                &manifest_path,
+                "[package]\n\
+                name = \"flipper\"\n\
+                version = \"0.1.0\"\n\
+                [package.metadata.contract.networks.local]\n\
+                url = \"ws://127.0.0.1:9955\"\n",
+            )?;
+
+            // when
+            let opts = ExtrinsicOpts::from_iter(&[
+                "",
+                "--manifest-path",
+                manifest_path.to_str().unwrap(),
+                "--network",
+                "local",
+            ]);
+
+            // then
+            assert_eq!(opts.url()?.as_str(), "ws://127.0.0.1:9955/");
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[ignore] // depends on a local substrate node running, or `CARGO_CONTRACT_RPC_REPLAY`
+    fn dry_run_without_secret() {
+        let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let cmd = CallCommand::from_iter(&[
+            "call",
+            "--origin",
+            alice,
+            "--contract",
+            alice,
+            "--data",
+            "00",
+            "--dry-run",
+        ]);
+        assert!(cmd.exec().is_ok());
+    }
+
+    #[test]
+    fn prepared_call_has_polkadot_js_shapes() {
+        // given
+        let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let cmd =
+            CallCommand::from_iter(&["call", "--contract", alice, "--value", "5", "--prepare"]);
+
+        // when
+        let prepared = cmd.prepared_call(&[0x63, 0x3a, 0xa5, 0x51], &[0x12, 0x02]);
+
+        // then
+        assert_eq!(
+            prepared,
+            json!({
+                "contract": alice,
+                "value": "5",
+                "gasLimit": 500_000_000,
+                "data": "0x633aa551",
+                "call": "0x1202",
+                "callHash": "0x0a762c20812d64848a10ff8b9a506521d83e0566bdcf8808505d1325f18df957",
+                "extrinsic": "0x0c041202",
+            })
+        );
+    }
+
+    #[test]
+    fn prepare_conflicts_with_dry_run() {
+        let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let result =
+            CallCommand::from_iter_safe(&["call", "--contract", alice, "--prepare", "--dry-run"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn message_selector_is_prepended_to_data() {
+        with_tmp_dir(|path| {
+            // given
+            let bundle = path.join("flipper.contract");
+            let metadata = r#"{
+                "spec": { "messages": [{ "name": ["flip"], "selector": "0x633aa551" }] }
+            }"#;
+            std::fs::write(&bundle, metadata)?;
+            let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+            let cmd = CallCommand::from_iter(&[
+                "call",
+                "--contract",
+                alice,
+                "--bundle",
+                bundle.to_str().unwrap(),
+                "--message",
+                "flip",
+                "--data",
+                "01",
+            ]);
+
+            // when
+            let data = cmd.call_data()?;
+
+            // then
+            assert_eq!(data, vec![0x63, 0x3a, 0xa5, 0x51, 0x01]);
+            Ok(())
+        })
+    }
+}
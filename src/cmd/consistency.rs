@@ -0,0 +1,208 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::verify::contract_code_hash;
+use crate::{
+    deployments::{Deployment, Deployments},
+    network::NetworkProfile,
+    rpc::RpcClient,
+    workspace::ManifestPath,
+};
+use anyhow::Result;
+use colored::Colorize;
+use impl_serde::serialize as serde_hex;
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::{convert::TryFrom, fmt::Write, path::PathBuf};
+use structopt::StructOpt;
+
+/// Checks that the same code of a contract is live on all networks it is deployed to.
+///
+/// The latest deployment of the contract on each network is taken from the deployment records
+/// in `deployments.json`, and the code hash of the contract is queried from the node of the
+/// network profile.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "consistency")]
+pub struct ConsistencyCommand {
+    /// Path to the Cargo.toml of the contract, defaults to the one in the current directory
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// The name of the contract in the deployment records
+    name: String,
+    /// The network profiles to check, separated by commas
+    #[structopt(long, use_delimiter = true, required = true)]
+    networks: Vec<String>,
+}
+
+/// The state of the contract on a network.
+#[derive(Debug, PartialEq)]
+enum LiveCode {
+    /// The contract is live with the code hash.
+    Live([u8; 32]),
+    /// No deployment of the contract on the network is recorded.
+    NotDeployed,
+    /// The code hash couldn't be queried.
+    Unavailable(String),
+}
+
+impl ConsistencyCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let deployments = Deployments::load(&manifest_path)?;
+        let mut states = Vec::new();
+        for network in &self.networks {
+            let deployment = latest_deployment(&deployments, &self.name, network);
+            let state = match deployment {
+                Some(deployment) => {
+                    let profile = NetworkProfile::load(&manifest_path, network)?;
+                    let url = profile.url.unwrap_or_else(|| deployment.url.clone());
+                    match query_code_hash(&url, &deployment.contract) {
+                        Ok(code_hash) => LiveCode::Live(code_hash),
+                        Err(err) => LiveCode::Unavailable(err.to_string()),
+                    }
+                }
+                None => LiveCode::NotDeployed,
+            };
+            states.push((network.as_str(), deployment, state));
+        }
+
+        let report = report(&states)?;
+        if is_consistent(&states) {
+            Ok(format!(
+                "{} {} runs the same code on all networks\n{}",
+                "Consistent:".bright_green().bold(),
+                self.name,
+                report
+            ))
+        } else {
+            anyhow::bail!(
+                "{} the code of {} diverges between the networks\n{}",
+                "Divergence:".bright_red().bold(),
+                self.name,
+                report
+            )
+        }
+    }
+}
+
+/// Returns the most recent recorded deployment of the contract `name` to `network`.
+fn latest_deployment<'a>(
+    deployments: &'a Deployments,
+    name: &str,
+    network: &str,
+) -> Option<&'a Deployment> {
+    deployments.deployments.iter().rev().find(|deployment| {
+        deployment.name.as_deref() == Some(name) && deployment.network.as_deref() == Some(network)
+    })
+}
+
+/// Queries the code hash of the contract at the SS58 `address` from the node at `url`.
+fn query_code_hash(url: &url::Url, address: &str) -> Result<[u8; 32]> {
+    let contract = AccountId32::from_ss58check(address)
+        .map_err(|err| anyhow::anyhow!("Invalid address {}: {:?}", address, err))?;
+    let rpc = RpcClient::new(url)?;
+    contract_code_hash(&rpc, &contract)
+}
+
+/// Returns `true` if the contract is live on all networks with the same code hash.
+fn is_consistent(states: &[(&str, Option<&Deployment>, LiveCode)]) -> bool {
+    let mut code_hashes = states.iter().map(|(_, _, state)| match state {
+        LiveCode::Live(code_hash) => Some(code_hash),
+        _ => None,
+    });
+    match code_hashes.next() {
+        Some(Some(first)) => code_hashes.all(|code_hash| code_hash == Some(first)),
+        _ => false,
+    }
+}
+
+/// Returns a line per network with the address and the live code hash of the contract.
+fn report(states: &[(&str, Option<&Deployment>, LiveCode)]) -> Result<String> {
+    let mut report = String::new();
+    for (network, deployment, state) in states {
+        let address = deployment.map_or("-", |deployment| deployment.contract.as_str());
+        let state = match state {
+            LiveCode::Live(code_hash) => {
+                let code_hash = serde_hex::to_hex(code_hash, false);
+                match deployment {
+                    Some(deployment) if deployment.code_hash != code_hash => {
+                        format!("{} (recorded {})", code_hash.yellow(), deployment.code_hash)
+                    }
+                    _ => code_hash,
+                }
+            }
+            LiveCode::NotDeployed => "no deployment recorded".yellow().to_string(),
+            LiveCode::Unavailable(err) => format!("{} {}", "unavailable:".red(), err),
+        };
+        writeln!(report, "\t{:<16} {:<48} {}", network.bold(), address, state)?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deployment(network: &str, code_hash: &str) -> Deployment {
+        Deployment {
+            name: Some("erc20".to_string()),
+            network: Some(network.to_string()),
+            url: url::Url::parse("ws://localhost:9944").unwrap(),
+            contract: "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY".to_string(),
+            code_hash: code_hash.to_string(),
+            bundle: None,
+        }
+    }
+
+    #[test]
+    fn latest_deployment_per_network_is_taken() {
+        let deployments = Deployments {
+            deployments: vec![
+                deployment("kusama", "0x01"),
+                deployment("polkadot", "0x02"),
+                deployment("kusama", "0x03"),
+            ],
+            ..Default::default()
+        };
+
+        let kusama = latest_deployment(&deployments, "erc20", "kusama");
+        let westend = latest_deployment(&deployments, "erc20", "westend");
+        let other = latest_deployment(&deployments, "flipper", "kusama");
+
+        assert_eq!(kusama.map(|d| d.code_hash.as_str()), Some("0x03"));
+        assert_eq!(westend, None);
+        assert_eq!(other, None);
+    }
+
+    #[test]
+    fn divergent_code_hashes_are_inconsistent() {
+        let same = vec![
+            ("kusama", None, LiveCode::Live([1; 32])),
+            ("polkadot", None, LiveCode::Live([1; 32])),
+        ];
+        let divergent = vec![
+            ("kusama", None, LiveCode::Live([1; 32])),
+            ("polkadot", None, LiveCode::Live([2; 32])),
+        ];
+        let missing = vec![
+            ("kusama", None, LiveCode::Live([1; 32])),
+            ("polkadot", None, LiveCode::NotDeployed),
+        ];
+
+        assert!(is_consistent(&same));
+        assert!(!is_consistent(&divergent));
+        assert!(!is_consistent(&missing));
+    }
+}
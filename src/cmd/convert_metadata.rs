@@ -0,0 +1,340 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Conversion between the formats of ink! metadata.
+//!
+//! - `V0`: the contract `spec`, `storage` and `types` are stored at the top level. Types are
+//!   identified by their 1-based position in the registry and list the ids of their `params`.
+//! - `V1`: the contract is nested in `V1`. Every type carries its 0-based `id` and names its
+//!   `params`.
+//!
+//! The `source`, `contract` and `user` sections are the same in both formats.
+
+use super::verify_metadata::TYPE_REFERENCE_KEYS;
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::{fmt, fs, path::PathBuf, str::FromStr};
+use structopt::StructOpt;
+
+/// The sections of the metadata describing the contract itself.
+const CONTRACT_SECTIONS: [&str; 3] = ["spec", "storage", "types"];
+
+/// The formats of ink! metadata.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetadataVersion {
+    V0,
+    V1,
+}
+
+impl MetadataVersion {
+    /// Returns the format of the `metadata`.
+    fn detect(metadata: &Map<String, Value>) -> Result<Self> {
+        if metadata.contains_key("spec") {
+            Ok(MetadataVersion::V0)
+        } else if metadata.contains_key("V1") {
+            Ok(MetadataVersion::V1)
+        } else {
+            anyhow::bail!("Unknown metadata format, expected V0 or V1")
+        }
+    }
+}
+
+impl fmt::Display for MetadataVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetadataVersion::V0 => write!(f, "V0"),
+            MetadataVersion::V1 => write!(f, "V1"),
+        }
+    }
+}
+
+impl FromStr for MetadataVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(version: &str) -> Result<Self> {
+        match version {
+            "v0" | "V0" => Ok(MetadataVersion::V0),
+            "v1" | "V1" => Ok(MetadataVersion::V1),
+            _ => anyhow::bail!("Unknown metadata version {}, expected v0 or v1", version),
+        }
+    }
+}
+
+/// Converts metadata, e.g. for frontends pinned to an older format.
+///
+/// Accepts both the `metadata.json` and the `<name>.contract` bundle.
+#[derive(Debug, StructOpt)]
+pub struct ConvertMetadataCommand {
+    /// The format to convert to: `v0` or `v1`
+    #[structopt(long, value_name = "v0 | v1")]
+    to: MetadataVersion,
+    /// Write the converted metadata to this file instead of printing it
+    #[structopt(long, short, parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Path to the metadata
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+}
+
+impl ConvertMetadataCommand {
+    /// Converts the metadata and writes it to the `--output`, or prints it if none is supplied.
+    pub fn exec(&self) -> Result<Option<String>> {
+        let contents = fs::read(&self.path).context(format!(
+            "Failed to read the metadata {}",
+            self.path.display()
+        ))?;
+        let metadata: Value = serde_json::from_slice(&contents).context(format!(
+            "The metadata {} is no valid JSON",
+            self.path.display()
+        ))?;
+        let converted = serde_json::to_string_pretty(&convert(metadata, self.to)?)?;
+        match &self.output {
+            Some(output) => {
+                fs::write(output, converted)
+                    .context(format!("Failed to write {}", output.display()))?;
+                Ok(Some(format!(
+                    "Converted {} to {} in {}",
+                    self.path.display(),
+                    self.to,
+                    output.display()
+                )))
+            }
+            None => {
+                // printed as is, so that it can be piped into other tools
+                println!("{}", converted);
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Converts the `metadata` to the format `to`.
+fn convert(metadata: Value, to: MetadataVersion) -> Result<Value> {
+    let mut metadata = match metadata {
+        Value::Object(metadata) => metadata,
+        _ => anyhow::bail!("The metadata is no JSON object"),
+    };
+    match (MetadataVersion::detect(&metadata)?, to) {
+        (MetadataVersion::V0, MetadataVersion::V1) => {
+            let mut contract = Map::new();
+            for section in &CONTRACT_SECTIONS {
+                if let Some(value) = metadata.remove(*section) {
+                    contract.insert(section.to_string(), value);
+                }
+            }
+            let mut contract = Value::Object(contract);
+            shift_references(&mut contract, -1)?;
+            if let Some(types) = contract.get_mut("types").and_then(Value::as_array_mut) {
+                let entries = std::mem::take(types);
+                *types = entries
+                    .into_iter()
+                    .enumerate()
+                    .map(|(id, mut ty)| {
+                        name_params(&mut ty);
+                        json!({ "id": id, "type": ty })
+                    })
+                    .collect();
+            }
+            metadata.insert("V1".into(), contract);
+        }
+        (MetadataVersion::V1, MetadataVersion::V0) => {
+            let mut contract = metadata.remove("V1").unwrap_or(Value::Null);
+            if let Some(types) = contract.get_mut("types").and_then(Value::as_array_mut) {
+                types.sort_by_key(|entry| entry.get("id").and_then(Value::as_u64));
+                for (position, entry) in types.iter_mut().enumerate() {
+                    if entry.get("id").and_then(Value::as_u64) != Some(position as u64) {
+                        anyhow::bail!(
+                            "The type ids are not consecutive, which can't be expressed in V0"
+                        )
+                    }
+                    let mut ty = entry.get_mut("type").map(Value::take).unwrap_or_default();
+                    unname_params(&mut ty);
+                    *entry = ty;
+                }
+            }
+            shift_references(&mut contract, 1)?;
+            let contract = match contract {
+                Value::Object(contract) => contract,
+                _ => anyhow::bail!("`V1` is no JSON object"),
+            };
+            metadata.extend(contract);
+        }
+        _ => (),
+    }
+    Ok(Value::Object(metadata))
+}
+
+/// Shifts all type references nested in `value` by `shift`, converting between 0-based and
+/// 1-based type ids.
+fn shift_references(value: &mut Value, shift: i64) -> Result<()> {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                let is_reference_list = key == "tuple" || key == "params";
+                match value {
+                    Value::Number(_) if TYPE_REFERENCE_KEYS.contains(&key.as_str()) => {
+                        shift_reference(value, shift)?
+                    }
+                    Value::Array(values) if is_reference_list => {
+                        for value in values {
+                            match value {
+                                Value::Number(_) => shift_reference(value, shift)?,
+                                _ => shift_references(value, shift)?,
+                            }
+                        }
+                    }
+                    _ => shift_references(value, shift)?,
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                shift_references(value, shift)?;
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Shifts the type reference `id` by `shift`.
+fn shift_reference(id: &mut Value, shift: i64) -> Result<()> {
+    let shifted = id
+        .as_i64()
+        .map(|id| id + shift)
+        .filter(|id| *id >= 0)
+        .ok_or_else(|| anyhow::anyhow!("Invalid type reference {}", id))?;
+    *id = shifted.into();
+    Ok(())
+}
+
+/// Converts the V0 `params` of a type, which are only type ids, into V1 params.
+///
+/// V0 doesn't record the names of the params, hence they are named by position: `T0`, `T1`, ...
+fn name_params(ty: &mut Value) {
+    if let Some(params) = ty.get_mut("params").and_then(Value::as_array_mut) {
+        for (position, param) in params.iter_mut().enumerate() {
+            *param = json!({ "name": format!("T{}", position), "type": param.take() });
+        }
+    }
+}
+
+/// Converts the V1 `params` of a type into V0 params, dropping their names.
+fn unname_params(ty: &mut Value) {
+    if let Some(params) = ty.get_mut("params").and_then(Value::as_array_mut) {
+        let ids = params
+            .iter_mut()
+            .filter_map(|param| param.get_mut("type").map(Value::take))
+            .filter(|id| !id.is_null())
+            .collect();
+        *params = ids;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    fn v0() -> Value {
+        json!({
+            "metadataVersion": "0.1.0",
+            "source": { "hash": "0x00" },
+            "contract": { "name": "flipper", "version": "0.1.0" },
+            "spec": {
+                "constructors": [{ "name": ["new"], "selector": "0x9bae9d5e", "args": [
+                    { "name": "init_value", "type": { "type": 1, "displayName": ["bool"] } }
+                ] }],
+                "messages": [{ "name": ["get"], "selector": "0x2f865bd9", "args": [],
+                    "returnType": { "type": 2, "displayName": ["Option"] } }]
+            },
+            "storage": { "cell": { "key": "0x00", "ty": 1 } },
+            "types": [
+                { "def": { "primitive": "bool" } },
+                { "path": ["Option"], "params": [1],
+                  "def": { "variant": { "variants": [
+                      { "name": "None" }, { "name": "Some", "fields": [{ "type": 1 }] }
+                  ] } } },
+                { "def": { "tuple": [1, 2] } }
+            ]
+        })
+    }
+
+    #[test]
+    fn v0_is_converted_to_v1() {
+        // when
+        let v1 = convert(v0(), MetadataVersion::V1).unwrap();
+
+        // then
+        assert!(v1.get("spec").is_none());
+        assert_eq!(v1["contract"]["name"], "flipper");
+        let contract = &v1["V1"];
+        assert_eq!(
+            contract["spec"]["constructors"][0]["args"][0]["type"]["type"],
+            0
+        );
+        assert_eq!(contract["spec"]["messages"][0]["returnType"]["type"], 1);
+        assert_eq!(contract["storage"]["cell"]["ty"], 0);
+        assert_eq!(contract["types"][1]["id"], 1);
+        assert_eq!(
+            contract["types"][1]["type"]["params"],
+            json!([{ "name": "T0", "type": 0 }])
+        );
+        assert_eq!(
+            contract["types"][1]["type"]["def"]["variant"]["variants"][1]["fields"][0]["type"],
+            0
+        );
+        assert_eq!(contract["types"][2]["type"]["def"]["tuple"], json!([0, 1]));
+    }
+
+    #[test]
+    fn conversion_round_trips() {
+        let v1 = convert(v0(), MetadataVersion::V1).unwrap();
+        assert_eq!(convert(v1, MetadataVersion::V0).unwrap(), v0());
+    }
+
+    #[test]
+    fn conversion_to_same_version_keeps_metadata() {
+        assert_eq!(convert(v0(), MetadataVersion::V0).unwrap(), v0());
+    }
+
+    #[test]
+    fn converted_metadata_is_written_to_output() {
+        with_tmp_dir(|path| {
+            // given
+            let metadata_path = path.join("metadata.json");
+            let output = path.join("metadata.v1.json");
+            fs::write(&metadata_path, v0().to_string())?;
+            let cmd = ConvertMetadataCommand::from_iter(&[
+                "convert",
+                "--to",
+                "v1",
+                "--output",
+                output.to_str().unwrap(),
+                metadata_path.to_str().unwrap(),
+            ]);
+
+            // when
+            let res = cmd.exec()?;
+
+            // then
+            assert!(res.is_some());
+            let converted: Value = serde_json::from_slice(&fs::read(&output)?)?;
+            assert!(converted.get("V1").is_some());
+            Ok(())
+        })
+    }
+}
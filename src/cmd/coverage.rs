@@ -0,0 +1,570 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Coverage of the off-chain tests of a contract.
+//!
+//! The tests are built with `-C instrument-coverage` into a separate target directory, the raw
+//! profiles they write are merged by `llvm-profdata` and exported by `llvm-cov`, both from the
+//! `llvm-tools-preview` component of the toolchain. The report is written as `coverage.json`,
+//! whose totals CI can track over time, and as `lcov.info` for coverage services.
+
+use super::{metadata, trait_definition::contract_of, verify_metadata::label};
+use crate::{crate_metadata::CrateMetadata, toolchain};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt::{self, Write},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Source files of dependencies and the standard library, excluded from the lcov report.
+const IGNORED_FILES: &str = r"/\.cargo/registry/|/\.cargo/git/|/rustc/|/\.rustup/";
+
+/// The `kind` of the regions of `llvm-cov` which are code, rather than expansions or skipped.
+const CODE_REGION: u64 = 0;
+
+/// The coverage of the off-chain tests of a contract.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    lines: Ratio,
+    functions: Ratio,
+    modules: Vec<ModuleCoverage>,
+    messages: Vec<MessageCoverage>,
+}
+
+/// The coverage of a module of the contract.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ModuleCoverage {
+    module: String,
+    lines: Ratio,
+    functions: Ratio,
+}
+
+/// The coverage of the function implementing a message.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageCoverage {
+    message: String,
+    /// The number of calls of the message by the tests.
+    calls: u64,
+    regions: Ratio,
+}
+
+/// The number of covered items out of all of them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+struct Ratio {
+    covered: u64,
+    count: u64,
+    percent: f64,
+}
+
+impl Ratio {
+    fn new(covered: u64, count: u64) -> Self {
+        let percent = if count == 0 {
+            100.0
+        } else {
+            // rounded to two decimals, so that reports compare stably
+            (covered as f64 * 10_000.0 / count as f64).round() / 100.0
+        };
+        Self {
+            covered,
+            count,
+            percent,
+        }
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.covered + other.covered, self.count + other.count)
+    }
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>6.2}% ({}/{})",
+            self.percent, self.covered, self.count
+        )
+    }
+}
+
+/// Runs the off-chain tests of the contract with coverage instrumentation and reports their
+/// coverage, passing `args` on to `cargo test`.
+pub(super) fn execute(crate_metadata: &CrateMetadata, args: &[String]) -> Result<String> {
+    let toolchain = crate_metadata.toolchain.as_deref();
+    let llvm_profdata = llvm_tool(toolchain, "llvm-profdata")?;
+    let llvm_cov = llvm_tool(toolchain, "llvm-cov")?;
+    let coverage_dir = crate_metadata.artifact_directory.join("coverage");
+    let profiles_dir = coverage_dir.join("profiles");
+    if profiles_dir.exists() {
+        fs::remove_dir_all(&profiles_dir)?;
+    }
+    fs::create_dir_all(&profiles_dir)?;
+
+    let cargo = |extra: &[&str]| {
+        let mut rustflags = env::var("RUSTFLAGS").unwrap_or_default();
+        rustflags.push_str(" -C instrument-coverage");
+        let mut cargo = toolchain::cargo_command(toolchain);
+        cargo
+            .arg("test")
+            .arg("--manifest-path")
+            .arg(crate_metadata.manifest_path.as_ref())
+            .args(extra)
+            .env("RUSTFLAGS", rustflags.trim())
+            // a separate target directory keeps the regular builds from being invalidated
+            .env("CARGO_TARGET_DIR", coverage_dir.join("target"))
+            .env("LLVM_PROFILE_FILE", profiles_dir.join("%p-%m.profraw"));
+        cargo
+    };
+    super::test::run(cargo(&[]).args(args))?;
+
+    // the test binaries are built already, their paths are listed by a build without running
+    let cargo_args = args
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let output = cargo(&["--no-run", "--message-format=json"])
+        .args(&cargo_args)
+        .output()
+        .context("Error listing the test binaries")?;
+    let binaries = test_binaries(&String::from_utf8_lossy(&output.stdout));
+    if binaries.is_empty() {
+        anyhow::bail!("The contract has no test binaries to report the coverage of")
+    }
+
+    let profdata = coverage_dir.join("coverage.profdata");
+    let profiles = fs::read_dir(&profiles_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut merge = Command::new(&llvm_profdata);
+    merge
+        .args(["merge", "-sparse", "-o"])
+        .arg(&profdata)
+        .args(&profiles);
+    output_of(&mut merge)?;
+
+    let export = |format: &str| -> Result<String> {
+        let mut export = Command::new(&llvm_cov);
+        export
+            .arg("export")
+            .arg(format!("-format={}", format))
+            .arg(format!("-ignore-filename-regex={}", IGNORED_FILES))
+            .arg("-instr-profile")
+            .arg(&profdata);
+        for (position, binary) in binaries.iter().enumerate() {
+            if position > 0 {
+                export.arg("-object");
+            }
+            export.arg(binary);
+        }
+        output_of(&mut export)
+    };
+    let lcov_path = coverage_dir.join("lcov.info");
+    fs::write(&lcov_path, export("lcov")?)?;
+    let exported: Value = serde_json::from_str(&export("text")?)
+        .context("The export of `llvm-cov` is no valid JSON")?;
+
+    let metadata_path = metadata::artifact_paths(crate_metadata).dest_metadata;
+    let messages = match fs::read(&metadata_path) {
+        Ok(contents) => message_labels(&serde_json::from_slice(&contents)?),
+        Err(_) => {
+            log::warn!(
+                "No metadata at {}, build the contract to report the coverage of its messages",
+                metadata_path.display()
+            );
+            Vec::new()
+        }
+    };
+    let source_dir = crate_metadata
+        .manifest_path
+        .absolute_directory()?
+        .join("src");
+    let crate_name = crate_metadata.package_name.replace('-', "_");
+    let coverage = report(&exported, &source_dir, &crate_name, &messages)?;
+    let json_path = coverage_dir.join("coverage.json");
+    fs::write(&json_path, serde_json::to_string_pretty(&coverage)?)?;
+
+    Ok(format!(
+        "{}\n\nWrote the coverage report {} and {}",
+        table(&coverage)?,
+        json_path.display(),
+        lcov_path.display()
+    ))
+}
+
+/// Returns the path of the binary of the LLVM `tool` of the `llvm-tools-preview` component of
+/// the pinned `toolchain`, or of the active one.
+fn llvm_tool(toolchain: Option<&str>, tool: &str) -> Result<PathBuf> {
+    let sysroot = output_of(toolchain::rustc_command(toolchain).args(["--print", "sysroot"]))?;
+    let host = toolchain::version_meta(toolchain)?.host;
+    let path = Path::new(sysroot.trim())
+        .join("lib")
+        .join("rustlib")
+        .join(host)
+        .join("bin")
+        .join(tool);
+    if !path.exists() {
+        anyhow::bail!(
+            "{} is missing, install it by `rustup component add llvm-tools-preview{}`",
+            tool,
+            toolchain
+                .map(|toolchain| format!(" --toolchain {}", toolchain))
+                .unwrap_or_default()
+        )
+    }
+    Ok(path)
+}
+
+/// Runs `command` and returns its standard output, failing with its standard error.
+fn output_of(command: &mut Command) -> Result<String> {
+    log::info!("Invoking {:?}", command);
+    let output = command
+        .output()
+        .context(format!("Error executing `{:?}`", command))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`{:?}` failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr)
+        )
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Returns the test binaries among the JSON messages of a cargo build.
+fn test_binaries(messages: &str) -> Vec<PathBuf> {
+    messages
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| {
+            message["reason"] == "compiler-artifact" && message["profile"]["test"] == true
+        })
+        .filter_map(|message| message["executable"].as_str().map(PathBuf::from))
+        .collect()
+}
+
+/// Returns the labels of the messages of the contract described by the `metadata`.
+fn message_labels(metadata: &Value) -> Vec<String> {
+    let contract = contract_of(metadata);
+    contract["spec"]["messages"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(label)
+        .collect()
+}
+
+/// Returns the coverage report of the sources in `source_dir` from the JSON `export` of
+/// `llvm-cov`.
+///
+/// A message is implemented by the functions of the crate `crate_name` named after it, their
+/// instantiations in the test binaries are merged.
+fn report(
+    export: &Value,
+    source_dir: &Path,
+    crate_name: &str,
+    messages: &[String],
+) -> Result<Report> {
+    let data = export["data"]
+        .as_array()
+        .and_then(|data| data.first())
+        .ok_or_else(|| anyhow::anyhow!("The export of `llvm-cov` contains no data"))?;
+
+    let mut modules = BTreeMap::<String, (Ratio, Ratio)>::new();
+    for file in data["files"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+    {
+        let path = match file["filename"]
+            .as_str()
+            .and_then(|path| Path::new(path).strip_prefix(source_dir).ok())
+        {
+            Some(path) => path,
+            None => continue,
+        };
+        let ratio = |kind: &str| {
+            let summary = &file["summary"][kind];
+            Ratio::new(
+                summary["covered"].as_u64().unwrap_or_default(),
+                summary["count"].as_u64().unwrap_or_default(),
+            )
+        };
+        let entry = modules.entry(module_of(crate_name, path)).or_default();
+        *entry = (entry.0.add(ratio("lines")), entry.1.add(ratio("functions")));
+    }
+    let (lines, functions) = modules
+        .values()
+        .fold((Ratio::new(0, 0), Ratio::new(0, 0)), |total, module| {
+            (total.0.add(module.0), total.1.add(module.1))
+        });
+
+    let all_functions = data["functions"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let messages = messages
+        .iter()
+        .map(|message| {
+            let name = message.rsplit("::").next().unwrap_or(message);
+            let suffix = format!("::{}", name);
+            let mut calls = 0;
+            // the execution counts of each region, keyed by its file and start and end position
+            let mut regions = BTreeMap::<(String, Vec<u64>), u64>::new();
+            for function in all_functions {
+                let demangled = function["name"]
+                    .as_str()
+                    .map(|name| format!("{:#}", rustc_demangle::demangle(name)))
+                    .unwrap_or_default();
+                let in_crate = demangled.starts_with(&format!("{}::", crate_name))
+                    || demangled.starts_with(&format!("<{}::", crate_name));
+                if !in_crate || !demangled.ends_with(&suffix) {
+                    continue;
+                }
+                calls += function["count"].as_u64().unwrap_or_default();
+                let filenames = function["filenames"]
+                    .as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                for region in function["regions"]
+                    .as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                {
+                    let region = region
+                        .as_array()
+                        .map(Vec::as_slice)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|value| value.as_u64().unwrap_or_default())
+                        .collect::<Vec<_>>();
+                    if region.len() < 8 || region[7] != CODE_REGION {
+                        continue;
+                    }
+                    let file = filenames
+                        .get(region[5] as usize)
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    *regions.entry((file, region[..4].to_vec())).or_default() += region[4];
+                }
+            }
+            MessageCoverage {
+                message: message.clone(),
+                calls,
+                regions: Ratio::new(
+                    regions.values().filter(|count| **count > 0).count() as u64,
+                    regions.len() as u64,
+                ),
+            }
+        })
+        .collect();
+
+    Ok(Report {
+        lines,
+        functions,
+        modules: modules
+            .into_iter()
+            .map(|(module, (lines, functions))| ModuleCoverage {
+                module,
+                lines,
+                functions,
+            })
+            .collect(),
+        messages,
+    })
+}
+
+/// Returns the module path of the source file at `path`, relative to the `src` directory of the
+/// crate `crate_name`.
+fn module_of(crate_name: &str, path: &Path) -> String {
+    let mut segments = path
+        .with_extension("")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    if matches!(
+        segments.last().map(String::as_str),
+        Some("mod") | Some("lib")
+    ) {
+        segments.pop();
+    }
+    Some(crate_name.to_string())
+        .into_iter()
+        .chain(segments)
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// Renders the coverage of the modules and messages as table.
+fn table(report: &Report) -> Result<String> {
+    let width = report
+        .modules
+        .iter()
+        .map(|module| module.module.len())
+        .chain(report.messages.iter().map(|message| message.message.len()))
+        .chain(Some("module".len()))
+        .max()
+        .unwrap_or_default();
+    let mut table = String::new();
+    writeln!(
+        table,
+        "{:<width$}  {:<24}  {}",
+        "module".bold(),
+        "lines".bold(),
+        "functions".bold(),
+        width = width
+    )?;
+    for module in &report.modules {
+        writeln!(
+            table,
+            "{:<width$}  {:<24}  {}",
+            module.module,
+            module.lines.to_string(),
+            module.functions,
+            width = width
+        )?;
+    }
+    writeln!(
+        table,
+        "{:<width$}  {:<24}  {}",
+        "total".bold(),
+        report.lines.to_string(),
+        report.functions,
+        width = width
+    )?;
+    if !report.messages.is_empty() {
+        writeln!(
+            table,
+            "\n{:<width$}  {:<24}  {}",
+            "message".bold(),
+            "regions".bold(),
+            "calls".bold(),
+            width = width
+        )?;
+        for message in &report.messages {
+            // messages no test calls are highlighted
+            let name = if message.calls == 0 {
+                message.message.yellow().to_string()
+            } else {
+                message.message.clone()
+            };
+            writeln!(
+                table,
+                "{:<width$}  {:<24}  {}",
+                name,
+                message.regions.to_string(),
+                message.calls,
+                width = width + name.len() - message.message.len()
+            )?;
+        }
+    }
+    Ok(table.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// An export of `llvm-cov` of the `erc20` contract with a `helpers` module.
+    fn export() -> Value {
+        json!({
+            "data": [{
+                "files": [
+                    { "filename": "/contract/src/lib.rs", "summary": {
+                        "lines": { "count": 40, "covered": 30 },
+                        "functions": { "count": 8, "covered": 6 } } },
+                    { "filename": "/contract/src/helpers/mod.rs", "summary": {
+                        "lines": { "count": 10, "covered": 5 },
+                        "functions": { "count": 2, "covered": 1 } } },
+                    { "filename": "/home/.cargo/registry/src/ink_env/lib.rs", "summary": {
+                        "lines": { "count": 1000, "covered": 1 },
+                        "functions": { "count": 100, "covered": 1 } } },
+                ],
+                "functions": [
+                    { "name": "_ZN5erc205erc205Erc208transfer17h0123456789abcdefE", "count": 3,
+                      "filenames": ["/contract/src/lib.rs"],
+                      "regions": [[10, 5, 12, 6, 3, 0, 0, 0], [11, 9, 11, 20, 0, 0, 0, 0]] },
+                    // a second instantiation, covering the other region
+                    { "name": "_ZN5erc205erc205Erc208transfer17hfedcba9876543210E", "count": 1,
+                      "filenames": ["/contract/src/lib.rs"],
+                      "regions": [[10, 5, 12, 6, 1, 0, 0, 0], [11, 9, 11, 20, 1, 0, 0, 0]] },
+                    { "name": "_ZN5erc205erc205Erc207approve17h0123456789abcdefE", "count": 0,
+                      "filenames": ["/contract/src/lib.rs"],
+                      "regions": [[20, 5, 22, 6, 0, 0, 0, 0]] },
+                ],
+            }],
+        })
+    }
+
+    #[test]
+    fn coverage_is_reported_per_module_of_the_contract() {
+        let report = report(&export(), Path::new("/contract/src"), "erc20", &[]).unwrap();
+
+        assert_eq!(report.lines, Ratio::new(35, 50));
+        assert_eq!(report.lines.percent, 70.0);
+        assert_eq!(report.functions, Ratio::new(7, 10));
+        let modules = report
+            .modules
+            .iter()
+            .map(|module| (module.module.as_str(), module.lines.covered))
+            .collect::<Vec<_>>();
+        assert_eq!(modules, vec![("erc20", 30), ("erc20::helpers", 5)]);
+    }
+
+    #[test]
+    fn instantiations_of_messages_are_merged() {
+        let messages = vec!["transfer".to_string(), "approve".to_string()];
+
+        let report = report(&export(), Path::new("/contract/src"), "erc20", &messages).unwrap();
+
+        assert_eq!(report.messages[0].calls, 4);
+        assert_eq!(report.messages[0].regions, Ratio::new(2, 2));
+        assert_eq!(report.messages[1].calls, 0);
+        assert_eq!(report.messages[1].regions, Ratio::new(0, 1));
+    }
+
+    #[test]
+    fn test_binaries_are_read_from_cargo_messages() {
+        let messages = [
+            json!({ "reason": "compiler-artifact", "profile": { "test": false },
+                    "executable": null }),
+            json!({ "reason": "compiler-artifact", "profile": { "test": true },
+                    "executable": "/target/debug/deps/erc20-0123" }),
+            json!({ "reason": "build-finished", "success": true }),
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        assert_eq!(
+            test_binaries(&messages),
+            vec![PathBuf::from("/target/debug/deps/erc20-0123")]
+        );
+    }
+}
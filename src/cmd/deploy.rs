@@ -17,27 +17,52 @@
 use std::{fs, io::Read, path::PathBuf};
 
 use anyhow::{Context, Result};
-use sp_core::H256;
-use subxt::{contracts::*, ClientBuilder, DefaultNodeRuntime};
+use serde_json::json;
+use sp_core::{crypto::Ss58Codec, H256};
+use subxt::{contracts::*, ClientBuilder, DefaultNodeRuntime, Signer};
 
-use crate::{crate_metadata, ExtrinsicOpts};
+use super::receipt::{Receipt, ReceiptOpts};
+use crate::{
+    bundle::ContractBundle, crate_metadata, validate_wasm, workspace::ManifestPath, ExtrinsicOpts,
+};
 
-/// Load the wasm blob from the specified path.
+/// Load the wasm blob from the specified path, which is either a wasm file or a contract bundle.
 ///
-/// Defaults to the target contract wasm in the current project, inferred via the crate metadata.
-fn load_contract_code(path: Option<&PathBuf>) -> Result<Vec<u8>> {
+/// Defaults to the target contract wasm of the project at `manifest_path`, inferred via the crate
+/// metadata.
+pub(super) fn load_contract_code(
+    manifest_path: &ManifestPath,
+    path: Option<&PathBuf>,
+) -> Result<Vec<u8>> {
     let contract_wasm_path = match path {
         Some(path) => path.clone(),
         None => {
-            let metadata = crate_metadata::CrateMetadata::collect(&Default::default())?;
+            let metadata = crate_metadata::CrateMetadata::collect(manifest_path)?;
             metadata.dest_wasm
         }
     };
     log::info!("Contract code path: {}", contract_wasm_path.display());
-    let mut data = Vec::new();
-    let mut file = fs::File::open(&contract_wasm_path)
-        .context(format!("Failed to open {}", contract_wasm_path.display()))?;
-    file.read_to_end(&mut data)?;
+    let data = if ContractBundle::is_bundle(&contract_wasm_path) {
+        let bundle = ContractBundle::load(&contract_wasm_path)?;
+        // fail before submitting the code of a bundle which was tampered with after signing
+        if let Some(signer) = bundle.verify_signature()? {
+            log::info!("Bundle signed by {}", signer.to_ss58check());
+        }
+        bundle.wasm()?
+    } else {
+        let mut data = Vec::new();
+        let mut file = fs::File::open(&contract_wasm_path)
+            .context(format!("Failed to open {}", contract_wasm_path.display()))?;
+        file.read_to_end(&mut data)?;
+        data
+    };
+
+    // fail before submitting the code, which would be rejected by the chain
+    let module = parity_wasm::deserialize_buffer(&data).context(format!(
+        "Failed to parse the contract code {}",
+        contract_wasm_path.display()
+    ))?;
+    validate_wasm::validate_import_section(&module)?;
 
     Ok(data)
 }
@@ -45,29 +70,41 @@ fn load_contract_code(path: Option<&PathBuf>) -> Result<Vec<u8>> {
 /// Put contract code to a smart contract enabled substrate chain.
 /// Returns the code hash of the deployed contract if successful.
 ///
-/// Optionally supply the contract wasm path, defaults to destination contract file inferred from
-/// Cargo.toml of the current contract project.
+/// Optionally supply the contract wasm or bundle path, defaults to destination contract file inferred from
+/// the Cargo.toml supplied via `--manifest-path`, or the one of the current contract project.
 ///
 /// Creates an extrinsic with the `Contracts::put_code` Call, submits via RPC, then waits for
 /// the `ContractsEvent::CodeStored` event.
 pub(crate) fn execute_deploy(
     extrinsic_opts: &ExtrinsicOpts,
+    receipt_opts: &ReceiptOpts,
     contract_wasm_path: Option<&PathBuf>,
 ) -> Result<H256> {
-    let code = load_contract_code(contract_wasm_path)?;
+    let code = load_contract_code(&extrinsic_opts.manifest_path()?, contract_wasm_path)?;
 
     async_std::task::block_on(async move {
         let cli = ClientBuilder::<DefaultNodeRuntime>::new()
-            .set_url(&extrinsic_opts.url.to_string())
+            .set_url(&extrinsic_opts.url()?.to_string())
             .build()
             .await?;
         let signer = extrinsic_opts.signer()?;
 
-        let events = cli.put_code_and_watch(&signer, &code).await?;
+        let events = cli.put_code_and_watch(signer.as_ref(), &code).await?;
         let code_stored = events
             .code_stored()?
             .ok_or(anyhow::anyhow!("Failed to find CodeStored event"))?;
 
+        let receipt = Receipt {
+            command: "deploy",
+            signer: signer.account_id().clone(),
+            inputs: json!({
+                "code": contract_wasm_path.map(|path| path.display().to_string()),
+                "codeSize": code.len(),
+            }),
+            result: json!({ "codeHash": format!("{:?}", code_stored.code_hash) }),
+            gas_limit: None,
+        };
+        receipt.write(receipt_opts, extrinsic_opts, &events, None)?;
         Ok(code_stored.code_hash)
     })
 }
@@ -76,8 +113,16 @@ pub(crate) fn execute_deploy(
 mod tests {
     use std::{fs, io::Write};
 
-    use crate::{cmd::deploy::execute_deploy, util::tests::with_tmp_dir, ExtrinsicOpts};
+    use super::load_contract_code;
+    use crate::{
+        cmd::{deploy::execute_deploy, metadata::blake2_hash, ReceiptOpts},
+        rpc_fixture,
+        util::tests::with_tmp_dir,
+        workspace::ManifestPath,
+        ExtrinsicOpts,
+    };
     use assert_matches::assert_matches;
+    use structopt::StructOpt;
 
     const CONTRACT: &str = r#"
 (module
@@ -87,25 +132,53 @@ mod tests {
 "#;
 
     #[test]
-    #[ignore] // depends on a local substrate node running
-    fn deploy_contract() {
+    fn contract_code_is_loaded_from_bundle() {
         with_tmp_dir(|path| {
+            // given
             let wasm = wabt::wat2wasm(CONTRACT).expect("invalid wabt");
-
-            let wasm_path = path.join("test.wasm");
-            let mut file = fs::File::create(&wasm_path).unwrap();
-            let _ = file.write_all(&wasm);
-
-            let url = url::Url::parse("ws://localhost:9944").unwrap();
-            let extrinsic_opts = ExtrinsicOpts {
-                url,
-                suri: "//Alice".into(),
-                password: None,
-            };
-            let result = execute_deploy(&extrinsic_opts, Some(&wasm_path));
-
-            assert_matches!(result, Ok(_));
+            let bundle = serde_json::json!({
+                "source": {
+                    "hash": format!("0x{}", hex::encode(blake2_hash(&wasm).0)),
+                    "wasm": format!("0x{}", hex::encode(&wasm)),
+                }
+            });
+            let bundle_path = path.join("test.contract");
+            fs::write(&bundle_path, bundle.to_string())?;
+            let manifest_path = ManifestPath::new(path.join("Cargo.toml"))?;
+
+            // when
+            let code = load_contract_code(&manifest_path, Some(&bundle_path))?;
+
+            // then
+            assert_eq!(code, wasm);
             Ok(())
         })
     }
+
+    #[test]
+    #[ignore] // its RPC fixture is yet to be recorded, see `rpc_fixture::with_fixture`
+    fn deploy_contract() {
+        rpc_fixture::with_fixture("deploy_contract.json", || {
+            with_tmp_dir(|path| {
+                let wasm = wabt::wat2wasm(CONTRACT).expect("invalid wabt");
+
+                let wasm_path = path.join("test.wasm");
+                let mut file = fs::File::create(&wasm_path).unwrap();
+                let _ = file.write_all(&wasm);
+
+                let extrinsic_opts = ExtrinsicOpts::from_iter(&[
+                    "",
+                    "--url",
+                    "ws://localhost:9944",
+                    "--suri",
+                    "//Alice",
+                ]);
+                let result =
+                    execute_deploy(&extrinsic_opts, &ReceiptOpts::default(), Some(&wasm_path));
+
+                assert_matches!(result, Ok(_));
+                Ok(())
+            })
+        })
+    }
 }
@@ -0,0 +1,680 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    metadata,
+    trait_definition::{contract_of, docs, Registry},
+    verify_metadata::label,
+};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::{collections::BTreeMap, convert::TryFrom, fmt::Write, fs, path::PathBuf};
+use structopt::StructOpt;
+
+/// The style sheet of the HTML documentation.
+const HTML_STYLE: &str = "
+body { font-family: sans-serif; line-height: 1.5; max-width: 60em; margin: 2em auto; }
+code, pre { font-family: monospace; background: #f4f4f4; }
+pre { padding: 1em; overflow-x: auto; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ddd; padding: 0.25em 0.75em; text-align: left; }
+";
+
+/// The format of the documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl std::str::FromStr for DocFormat {
+    type Err = String;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "markdown" | "md" => Ok(DocFormat::Markdown),
+            "html" => Ok(DocFormat::Html),
+            _ => Err("Could not parse format, expected markdown or html".to_string()),
+        }
+    }
+}
+
+/// Generates the documentation of the constructors, messages, events, errors and types of the
+/// contract from its metadata, to be published alongside the contract.
+///
+/// Each item is documented with its doc comments, selectors and the types of its arguments. The
+/// errors are the `Err` types of the `Result`s returned by the messages and constructors.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "doc")]
+pub struct DocCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// The format of the documentation
+    #[structopt(long, default_value = "markdown", value_name = "markdown | html")]
+    format: DocFormat,
+    /// Path to the metadata or the `<name>.contract` bundle, defaults to
+    /// `./target/ink/metadata.json`
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+    /// Path of the generated file, defaults to `<name>.md` or `<name>.html` next to the metadata
+    #[structopt(short, long, parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+impl DocCommand {
+    pub fn exec(&self) -> Result<String> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+                metadata::artifact_paths(&crate_metadata).dest_metadata
+            }
+        };
+        let contents = fs::read(&path).context(format!(
+            "Failed to read the metadata {}, build the contract first",
+            path.display()
+        ))?;
+        let metadata: Value = serde_json::from_slice(&contents)
+            .context(format!("The metadata {} is no valid JSON", path.display()))?;
+        let name = metadata
+            .get("contract")
+            .and_then(|contract| contract.get("name"))
+            .and_then(Value::as_str)
+            .unwrap_or("contract");
+
+        let document = document(name, &metadata)?;
+        let (source, extension) = match self.format {
+            DocFormat::Markdown => (document.markdown()?, "md"),
+            DocFormat::Html => (document.html()?, "html"),
+        };
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => path.with_file_name(format!("{}.{}", name, extension)),
+        };
+        fs::write(&output, source).context(format!(
+            "Failed to write the documentation {}",
+            output.display()
+        ))?;
+        Ok(format!(
+            "Generated the documentation of {} at {}",
+            name,
+            output.display()
+        ))
+    }
+}
+
+/// The documentation of a contract, independent of the format it is rendered in.
+struct Document {
+    title: String,
+    docs: Option<String>,
+    facts: Vec<(&'static str, Inline)>,
+    sections: Vec<Section>,
+}
+
+/// A section of the documentation, e.g. the messages of the contract.
+struct Section {
+    title: &'static str,
+    items: Vec<Item>,
+}
+
+/// A documented item, e.g. a message.
+struct Item {
+    name: String,
+    docs: Option<String>,
+    facts: Vec<(&'static str, Inline)>,
+    /// The arguments or fields of the item.
+    table: Option<Table>,
+    /// The Rust definition of a type.
+    code: Option<String>,
+}
+
+/// A table of the arguments or fields of an item.
+struct Table {
+    headers: &'static [&'static str],
+    rows: Vec<Vec<Inline>>,
+}
+
+/// A piece of text within a paragraph or table cell.
+enum Inline {
+    Text(String),
+    Code(String),
+    /// A list of code, e.g. the names of messages.
+    Codes(Vec<String>),
+}
+
+/// Returns the documentation of the contract `name` described by the `metadata`.
+fn document(name: &str, metadata: &Value) -> Result<Document> {
+    let contract = contract_of(metadata);
+    let spec = contract
+        .get("spec")
+        .ok_or_else(|| anyhow::anyhow!("The metadata contains no contract spec"))?;
+    // the definitions of the types are shown without their derives
+    let registry = Registry::new(contract).with_derives("");
+    let mut definitions = BTreeMap::new();
+    let items = |kind: &str| {
+        spec.get(kind)
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    };
+
+    let mut facts = Vec::new();
+    let fields = [
+        ("Version", metadata.pointer("/contract/version")),
+        ("Code hash", metadata.pointer("/source/hash")),
+        ("Language", metadata.pointer("/source/language")),
+        ("Compiler", metadata.pointer("/source/compiler")),
+    ];
+    for (fact, value) in fields.iter() {
+        if let Some(value) = value.and_then(Value::as_str) {
+            facts.push((*fact, Inline::Code(value.to_string())));
+        }
+    }
+
+    // the messages and constructors returning each error type
+    let mut errors = BTreeMap::<String, Vec<String>>::new();
+    let mut callables = |kind: &str, section: &'static str| -> Result<Section> {
+        let mut section = Section {
+            title: section,
+            items: Vec::new(),
+        };
+        for item in items(kind) {
+            let name = label(item).ok_or_else(|| anyhow::anyhow!("A {} has no name", kind))?;
+            let mut facts = Vec::new();
+            if let Some(selector) = item.get("selector").and_then(Value::as_str) {
+                facts.push(("Selector", Inline::Code(selector.to_string())));
+            }
+            if let Some(mutates) = item.get("mutates").and_then(Value::as_bool) {
+                facts.push(("Mutates", yes_no(mutates)));
+            }
+            if let Some(payable) = item.get("payable").and_then(Value::as_bool) {
+                facts.push(("Payable", yes_no(payable)));
+            }
+            if let Some(returns) = item.get("returnType").filter(|ty| !ty.is_null()) {
+                facts.push((
+                    "Returns",
+                    Inline::Code(registry.type_spec(returns, &mut definitions)?),
+                ));
+                if let Some(error) = error_type(&registry, returns, &mut definitions)? {
+                    errors.entry(error).or_default().push(name.clone());
+                }
+            }
+            let rows = args(item)
+                .iter()
+                .map(|arg| {
+                    Ok(vec![
+                        Inline::Code(label(arg).unwrap_or_default()),
+                        Inline::Code(registry.type_spec(&arg["type"], &mut definitions)?),
+                    ])
+                })
+                .collect::<Result<Vec<_>>>()?;
+            section.items.push(Item {
+                docs: docs(item),
+                facts,
+                table: Some(Table {
+                    headers: &["Argument", "Type"],
+                    rows,
+                })
+                .filter(|table| !table.rows.is_empty()),
+                code: None,
+                name,
+            });
+        }
+        Ok(section)
+    };
+    let constructors = callables("constructors", "Constructors")?;
+    let messages = callables("messages", "Messages")?;
+
+    let mut events = Section {
+        title: "Events",
+        items: Vec::new(),
+    };
+    for event in items("events") {
+        let rows = args(event)
+            .iter()
+            .map(|arg| {
+                Ok(vec![
+                    Inline::Code(label(arg).unwrap_or_default()),
+                    Inline::Code(registry.type_spec(&arg["type"], &mut definitions)?),
+                    yes_no(arg.get("indexed").and_then(Value::as_bool) == Some(true)),
+                ])
+            })
+            .collect::<Result<Vec<_>>>()?;
+        events.items.push(Item {
+            name: label(event).ok_or_else(|| anyhow::anyhow!("An event has no name"))?,
+            docs: docs(event),
+            facts: Vec::new(),
+            table: Some(Table {
+                headers: &["Field", "Type", "Indexed"],
+                rows,
+            })
+            .filter(|table| !table.rows.is_empty()),
+            code: None,
+        });
+    }
+
+    let definition = |name: &str| {
+        definitions
+            .get(name)
+            .filter(|definition| !definition.is_empty())
+            .cloned()
+    };
+    let error_items = errors
+        .iter()
+        .map(|(error, callables)| Item {
+            name: error.clone(),
+            docs: None,
+            facts: vec![("Returned by", Inline::Codes(callables.clone()))],
+            table: None,
+            code: definition(error),
+        })
+        .collect();
+    let type_items = definitions
+        .keys()
+        .filter(|name| !errors.contains_key(*name))
+        .map(|name| Item {
+            name: name.clone(),
+            docs: None,
+            facts: Vec::new(),
+            table: None,
+            code: definition(name),
+        })
+        .collect();
+
+    let sections = vec![
+        constructors,
+        messages,
+        events,
+        Section {
+            title: "Errors",
+            items: error_items,
+        },
+        Section {
+            title: "Types",
+            items: type_items,
+        },
+    ];
+    Ok(Document {
+        title: name.to_string(),
+        docs: docs(spec),
+        facts,
+        sections: sections
+            .into_iter()
+            .filter(|section| !section.items.is_empty())
+            .collect(),
+    })
+}
+
+/// Returns the arguments of a constructor, message or event.
+fn args(item: &Value) -> &[Value] {
+    item.get("args")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Returns the `Err` type of the type `returns`, if it is a `Result`.
+fn error_type(
+    registry: &Registry,
+    returns: &Value,
+    definitions: &mut BTreeMap<String, String>,
+) -> Result<Option<String>> {
+    let id = match returns.get("type").and_then(Value::as_u64) {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+    let is_result = registry
+        .get(id)?
+        .get("path")
+        .and_then(Value::as_array)
+        .and_then(|path| path.last())
+        .and_then(Value::as_str)
+        == Some("Result");
+    match registry.params(id)?.as_slice() {
+        [_, error] if is_result => Ok(Some(registry.render(*error, definitions)?)),
+        _ => Ok(None),
+    }
+}
+
+/// Returns `yes` or `no` for the `flag`.
+fn yes_no(flag: bool) -> Inline {
+    Inline::Text(if flag { "yes" } else { "no" }.to_string())
+}
+
+impl Document {
+    /// Returns the documentation as Markdown.
+    fn markdown(&self) -> Result<String> {
+        let mut source = format!("# {}\n\n", self.title);
+        markdown_body(&mut source, self.docs.as_deref(), &self.facts)?;
+        for section in &self.sections {
+            writeln!(source, "## {}\n", section.title)?;
+            for item in &section.items {
+                writeln!(source, "### `{}`\n", item.name)?;
+                markdown_body(&mut source, item.docs.as_deref(), &item.facts)?;
+                if let Some(table) = &item.table {
+                    writeln!(source, "| {} |", table.headers.join(" | "))?;
+                    writeln!(source, "|{}", " --- |".repeat(table.headers.len()))?;
+                    for row in &table.rows {
+                        let cells = row
+                            .iter()
+                            .map(|cell| markdown_inline(cell).replace('|', "\\|"))
+                            .collect::<Vec<_>>();
+                        writeln!(source, "| {} |", cells.join(" | "))?;
+                    }
+                    source.push('\n');
+                }
+                if let Some(code) = &item.code {
+                    writeln!(source, "```rust\n{}```\n", code)?;
+                }
+            }
+        }
+        Ok(source.trim_end().to_string() + "\n")
+    }
+
+    /// Returns the documentation as standalone HTML page, with a table of contents.
+    ///
+    /// Doc comments are shown as paragraphs of text, without rendering their Markdown.
+    fn html(&self) -> Result<String> {
+        let mut source = format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+            <title>{title}</title>\n<style>{style}</style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+            title = escape(&self.title),
+            style = HTML_STYLE
+        );
+        html_body(&mut source, self.docs.as_deref(), &self.facts)?;
+        source.push_str("<nav>\n<ul>\n");
+        for section in &self.sections {
+            writeln!(
+                source,
+                "<li><a href=\"#{}\">{}</a></li>",
+                anchor(section.title, None),
+                section.title
+            )?;
+        }
+        source.push_str("</ul>\n</nav>\n");
+
+        for section in &self.sections {
+            writeln!(
+                source,
+                "<section id=\"{}\">\n<h2>{}</h2>",
+                anchor(section.title, None),
+                section.title
+            )?;
+            for item in &section.items {
+                writeln!(
+                    source,
+                    "<h3 id=\"{}\"><code>{}</code></h3>",
+                    anchor(section.title, Some(&item.name)),
+                    escape(&item.name)
+                )?;
+                html_body(&mut source, item.docs.as_deref(), &item.facts)?;
+                if let Some(table) = &item.table {
+                    source.push_str("<table>\n<thead>\n<tr>");
+                    for header in table.headers {
+                        write!(source, "<th>{}</th>", header)?;
+                    }
+                    source.push_str("</tr>\n</thead>\n<tbody>\n");
+                    for row in &table.rows {
+                        source.push_str("<tr>");
+                        for cell in row {
+                            write!(source, "<td>{}</td>", html_inline(cell))?;
+                        }
+                        source.push_str("</tr>\n");
+                    }
+                    source.push_str("</tbody>\n</table>\n");
+                }
+                if let Some(code) = &item.code {
+                    writeln!(source, "<pre><code>{}</code></pre>", escape(code))?;
+                }
+            }
+            source.push_str("</section>\n");
+        }
+        source.push_str("</body>\n</html>\n");
+        Ok(source)
+    }
+}
+
+/// Writes the `docs` and `facts` of an item as Markdown to `source`.
+fn markdown_body(source: &mut String, docs: Option<&str>, facts: &[(&str, Inline)]) -> Result<()> {
+    if let Some(docs) = docs {
+        writeln!(source, "{}\n", docs.trim())?;
+    }
+    for (fact, value) in facts {
+        writeln!(source, "- **{}:** {}", fact, markdown_inline(value))?;
+    }
+    if !facts.is_empty() {
+        source.push('\n');
+    }
+    Ok(())
+}
+
+/// Returns the `inline` text as Markdown.
+fn markdown_inline(inline: &Inline) -> String {
+    match inline {
+        Inline::Text(text) => text.clone(),
+        Inline::Code(code) => format!("`{}`", code),
+        Inline::Codes(codes) => codes
+            .iter()
+            .map(|code| format!("`{}`", code))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// Writes the `docs` and `facts` of an item as HTML to `source`.
+fn html_body(source: &mut String, docs: Option<&str>, facts: &[(&str, Inline)]) -> Result<()> {
+    for paragraph in docs.unwrap_or_default().trim().split("\n\n") {
+        if !paragraph.trim().is_empty() {
+            writeln!(source, "<p>{}</p>", escape(paragraph.trim()))?;
+        }
+    }
+    if !facts.is_empty() {
+        source.push_str("<ul>\n");
+        for (fact, value) in facts {
+            writeln!(
+                source,
+                "<li><strong>{}:</strong> {}</li>",
+                fact,
+                html_inline(value)
+            )?;
+        }
+        source.push_str("</ul>\n");
+    }
+    Ok(())
+}
+
+/// Returns the `inline` text as HTML.
+fn html_inline(inline: &Inline) -> String {
+    match inline {
+        Inline::Text(text) => escape(text),
+        Inline::Code(code) => format!("<code>{}</code>", escape(code)),
+        Inline::Codes(codes) => codes
+            .iter()
+            .map(|code| format!("<code>{}</code>", escape(code)))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// Returns the id of the HTML element of a section or of an item within it.
+fn anchor(section: &str, item: Option<&str>) -> String {
+    let section = section.to_lowercase();
+    match item {
+        Some(item) => format!("{}-{}", section, item.replace("::", "-")),
+        None => section,
+    }
+}
+
+/// Escapes the characters of `text` with a special meaning in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use serde_json::json;
+
+    fn metadata() -> Value {
+        json!({
+            "source": { "hash": "0x1234", "language": "ink! 3.0.0-rc3" },
+            "contract": { "name": "my_token", "version": "0.1.0" },
+            "V1": {
+                "spec": {
+                    "docs": [" A token."],
+                    "constructors": [{
+                        "label": "new", "selector": "0x9bae9d5e", "payable": false,
+                        "args": [{ "label": "supply", "type": { "type": 3 } }],
+                        "docs": [" Creates the token with the `supply`."]
+                    }],
+                    "events": [{
+                        "label": "Transfer",
+                        "args": [
+                            { "label": "from", "type": { "type": 2 }, "indexed": true, "docs": [] },
+                            { "label": "value", "type": { "type": 3 }, "indexed": false,
+                              "docs": [] }
+                        ],
+                        "docs": [" Emitted on transfers."]
+                    }],
+                    "messages": [{
+                        "label": "balance_of", "selector": "0x0f755a56",
+                        "mutates": false, "payable": false,
+                        "args": [{ "label": "owner", "type": { "type": 1 } }],
+                        "returnType": { "type": 3, "displayName": ["Balance"] },
+                        "docs": [" Returns the balance of the owner."]
+                    }, {
+                        "label": "Token::transfer", "selector": "0x84a15da1",
+                        "mutates": true, "payable": false,
+                        "args": [
+                            { "label": "to", "type": { "type": 1 } },
+                            { "label": "value", "type": { "type": 3 } }
+                        ],
+                        "returnType": { "type": 4 },
+                        "docs": []
+                    }]
+                },
+                "types": [
+                    { "id": 0, "type": { "def": { "primitive": "u8" } } },
+                    { "id": 1, "type": { "path": ["ink_env", "types", "AccountId"],
+                      "def": { "composite": { "fields": [{ "type": 5 }] } } } },
+                    { "id": 2, "type": { "path": ["Option"],
+                      "params": [{ "name": "T", "type": 1 }],
+                      "def": { "variant": { "variants": [
+                          { "name": "None" }, { "name": "Some", "fields": [{ "type": 1 }] }
+                      ] } } } },
+                    { "id": 3, "type": { "def": { "primitive": "u128" } } },
+                    { "id": 4, "type": { "path": ["Result"],
+                      "params": [{ "name": "T", "type": 6 }, { "name": "E", "type": 7 }],
+                      "def": { "variant": { "variants": [] } } } },
+                    { "id": 5, "type": { "def": { "array": { "len": 32, "type": 0 } } } },
+                    { "id": 6, "type": { "def": { "tuple": [] } } },
+                    { "id": 7, "type": { "path": ["my_token", "Error"],
+                      "def": { "variant": { "variants": [
+                          { "name": "InsufficientBalance" },
+                          { "name": "Custom", "fields": [{ "type": 8 }] }
+                      ] } } } },
+                    { "id": 8, "type": { "path": ["my_token", "Details"],
+                      "def": { "composite": { "fields": [
+                          { "name": "error_code", "type": 0 },
+                          { "name": "payload", "type": 9 }
+                      ] } } } },
+                    { "id": 9, "type": { "def": { "sequence": { "type": 0 } } } }
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn markdown_documents_all_items() {
+        // when
+        let markdown = document("my_token", &metadata())
+            .and_then(|document| document.markdown())
+            .unwrap();
+
+        // then
+        assert!(markdown.starts_with(
+            "# my_token\n\nA token.\n\n- **Version:** `0.1.0`\n- **Code hash:** `0x1234`\n\
+            - **Language:** `ink! 3.0.0-rc3`\n\n## Constructors\n\n### `new`\n\n\
+            Creates the token with the `supply`.\n\n- **Selector:** `0x9bae9d5e`\n\
+            - **Payable:** no\n\n| Argument | Type |\n| --- | --- |\n| `supply` | `u128` |\n"
+        ));
+        assert!(markdown.contains(
+            "### `Token::transfer`\n\n- **Selector:** `0x84a15da1`\n- **Mutates:** yes\n\
+            - **Payable:** no\n- **Returns:** `Result<(), Error>`\n\n"
+        ));
+        assert!(markdown.contains(
+            "## Events\n\n### `Transfer`\n\nEmitted on transfers.\n\n\
+            | Field | Type | Indexed |\n| --- | --- | --- |\n\
+            | `from` | `Option<AccountId>` | yes |\n| `value` | `u128` | no |\n"
+        ));
+        assert!(markdown.contains(
+            "## Errors\n\n### `Error`\n\n- **Returned by:** `Token::transfer`\n\n\
+            ```rust\npub enum Error {\n    InsufficientBalance,\n    Custom(Details),\n}\n```\n"
+        ));
+        assert!(markdown.ends_with(
+            "## Types\n\n### `Details`\n\n```rust\npub struct Details {\n    \
+            pub error_code: u8,\n    pub payload: Vec<u8>,\n}\n```\n"
+        ));
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        // when
+        let html = document("my_token", &metadata())
+            .and_then(|document| document.html())
+            .unwrap();
+
+        // then
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<li><a href=\"#errors\">Errors</a></li>\n"));
+        assert!(
+            html.contains("<h3 id=\"messages-Token-transfer\"><code>Token::transfer</code></h3>\n")
+        );
+        assert!(html
+            .contains("<li><strong>Returns:</strong> <code>Result&lt;(), Error&gt;</code></li>\n"));
+        assert!(html.contains("<p>Creates the token with the `supply`.</p>\n"));
+        assert!(html.ends_with("</section>\n</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn documentation_is_written_next_to_the_metadata() {
+        with_tmp_dir(|path| {
+            // given
+            let metadata_path = path.join("metadata.json");
+            fs::write(&metadata_path, metadata().to_string())?;
+            let cmd = DocCommand::from_iter(&[
+                "doc",
+                "--format",
+                "html",
+                metadata_path.to_str().unwrap(),
+            ]);
+
+            // when
+            let message = cmd.exec()?;
+
+            // then
+            assert!(path.join("my_token.html").exists());
+            assert!(message.starts_with("Generated the documentation of my_token"));
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,319 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::{crate_metadata::CrateMetadata, util, workspace::ManifestPath, Verbosity};
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::{
+    convert::TryFrom,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+use structopt::StructOpt;
+
+/// Prints the code generated by the ink! macros.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "expand")]
+pub struct ExpandCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Print only the items with this path or a suffix of it, e.g. `Flipper`, `Flipper::flip`
+    /// or `flipper::Flipper`. For a type, its definition and all of its `impl` blocks are
+    /// printed.
+    #[structopt(long)]
+    item: Option<String>,
+    /// Features of the contract to activate
+    #[structopt(long, use_delimiter = true)]
+    features: Vec<String>,
+}
+
+impl ExpandCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+        let expanded = expand(&crate_metadata, &self.features)?;
+        let source = match format(&expanded, &crate_metadata.root_package.edition) {
+            Ok(source) => source,
+            Err(err) if self.item.is_none() => {
+                log::warn!("Printing the expanded code unformatted: {:#}", err);
+                expanded
+            }
+            Err(err) => return Err(err.context("Filtering the expanded code requires rustfmt")),
+        };
+
+        let item = match &self.item {
+            Some(item) => item,
+            None => return Ok(source),
+        };
+        let matches = filter(&source, item);
+        if matches.is_empty() {
+            anyhow::bail!("No item {} found in the expanded code", item)
+        }
+        Ok(matches.join("\n"))
+    }
+}
+
+/// Returns the source of the contract after the expansion of all macros.
+///
+/// Uses the unstable `-Zunpretty=expanded` of rustc, which is enabled on stable toolchains
+/// by `RUSTC_BOOTSTRAP`, like `cargo expand` does. The contract is checked in a separate target
+/// directory, so that the flag doesn't invalidate the artifacts of regular builds.
+fn expand(crate_metadata: &CrateMetadata, features: &[String]) -> Result<String> {
+    let target_dir = crate_metadata.artifact_directory.join("expand");
+    let target_dir_arg = format!("--target-dir={}", target_dir.display());
+    let mut args = vec![
+        "--lib".to_string(),
+        "--profile=check".to_string(),
+        crate_metadata.manifest_path.cargo_arg(),
+        target_dir_arg,
+    ];
+    if !features.is_empty() {
+        args.push(format!("--features={}", features.join(",")));
+    }
+    args.push("--".to_string());
+    args.push("-Zunpretty=expanded".to_string());
+    let stdout = util::invoke_cargo(
+        "rustc",
+        &args,
+        crate_metadata.manifest_path.directory(),
+        // the verbosity flags would be passed on to rustc after the `--`
+        Verbosity::Default,
+        vec![("RUSTC_BOOTSTRAP", Some("1"))],
+        crate_metadata.toolchain.as_deref(),
+    )?;
+    String::from_utf8(stdout).context("The expanded code is no valid UTF-8")
+}
+
+/// Formats the `source` with `rustfmt`.
+fn format(source: &str, edition: &str) -> Result<String> {
+    let mut rustfmt = Command::new("rustfmt");
+    rustfmt
+        .args(["--edition", edition, "--emit", "stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    let mut child = rustfmt
+        .spawn()
+        .context("Failed to run rustfmt, install it by `rustup component add rustfmt`")?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("Failed to open the stdin of rustfmt"))?
+        .write_all(source.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!("rustfmt failed with exit code: {:?}", output.status.code())
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// An item of the formatted source, by the lines it spans including its attributes.
+struct Item {
+    start: usize,
+    end: usize,
+}
+
+/// Returns the sources of the items of the formatted `source` whose path ends with `item`.
+fn filter(source: &str, item: &str) -> Vec<String> {
+    let query = item.split("::").collect::<Vec<_>>();
+    let lines = source.lines().collect::<Vec<_>>();
+    let mut items = Vec::new();
+    collect_items(&lines, 0, lines.len(), 0, &[], &query, &mut items);
+    items
+        .iter()
+        .map(|item| {
+            lines[item.start..=item.end]
+                .iter()
+                .map(|line| format!("{}\n", line))
+                .collect::<String>()
+        })
+        .collect()
+}
+
+/// Adds the items at `indent` between the lines `start` and `end` matching the `query`,
+/// descending into the items which don't match.
+fn collect_items(
+    lines: &[&str],
+    start: usize,
+    end: usize,
+    indent: usize,
+    path: &[String],
+    query: &[&str],
+    items: &mut Vec<Item>,
+) {
+    let header = Regex::new(
+        r#"^(?:pub(?:\([^)]*\))?\s+)?(?:(?:const|async|unsafe|default|extern\s+"[^"]*")\s+)*(fn|struct|enum|union|mod|trait|const|static|type|impl)\b(.*)$"#,
+    )
+    .expect("the header regex is valid");
+    let mut attributes = None;
+    let mut index = start;
+    while index < end {
+        let line = lines[index];
+        let trimmed = line.trim_start();
+        if line.len() - trimmed.len() != indent || trimmed.is_empty() {
+            index += 1;
+            continue;
+        }
+        if trimmed.starts_with("#[") || trimmed.starts_with("///") || trimmed.starts_with(")]") {
+            attributes.get_or_insert(index);
+            index += 1;
+            continue;
+        }
+        let item_start = attributes.take().unwrap_or(index);
+        let (kind, rest) = match header.captures(trimmed) {
+            Some(captures) => (captures[1].to_string(), captures[2].to_string()),
+            None => {
+                index += 1;
+                continue;
+            }
+        };
+        let item_end = (index..end)
+            .find(|line| {
+                let trimmed = lines[*line].trim_start();
+                let is_closing = *line == index || lines[*line].len() - trimmed.len() == indent;
+                is_closing
+                    && (trimmed.ends_with(';')
+                        || trimmed.ends_with("{}")
+                        || (*line != index && trimmed.starts_with('}')))
+            })
+            .unwrap_or(end - 1);
+
+        let name = item_name(&kind, &rest);
+        let mut item_path = path.to_vec();
+        if name != "_" {
+            item_path.push(name);
+        }
+        if item_path.len() >= query.len() && item_path[item_path.len() - query.len()..] == *query {
+            items.push(Item {
+                start: item_start,
+                end: item_end,
+            });
+        } else if item_end > index + 1 {
+            collect_items(
+                lines,
+                index + 1,
+                item_end,
+                indent + 4,
+                &item_path,
+                query,
+                items,
+            );
+        }
+        index = item_end + 1;
+    }
+}
+
+/// Returns the name of an item of the `kind` whose header continues with `rest`, the name of
+/// the implementing type for `impl` blocks.
+fn item_name(kind: &str, rest: &str) -> String {
+    let rest = rest.trim_start();
+    if kind != "impl" {
+        return rest
+            .trim_start_matches("mut ")
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .next()
+            .unwrap_or_default()
+            .to_string();
+    }
+    let ty = rest.rsplit(" for ").next().unwrap_or(rest);
+    // skip the generic parameters of the impl, e.g. `<'a, T: Trait<U>>`
+    let ty = if rest.starts_with('<') && ty == rest {
+        let mut depth = 0;
+        let close = ty
+            .char_indices()
+            .find(|(_, c)| {
+                match c {
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    _ => (),
+                }
+                depth == 0
+            })
+            .map_or(0, |(index, _)| index + 1);
+        &ty[close..]
+    } else {
+        ty
+    };
+    ty.trim_start()
+        .split(|c: char| c == '<' || c == '{' || c.is_whitespace())
+        .next()
+        .unwrap_or_default()
+        .rsplit("::")
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPANDED: &str = r#"#![feature(prelude_import)]
+#[prelude_import]
+use std::prelude::rust_2018::*;
+pub mod flipper {
+    #[cfg(not(feature = "ink-as-dependency"))]
+    pub struct Flipper {
+        value: bool,
+    }
+    const _: () = {
+        impl ::ink_lang::reflect::DispatchableMessageInfo<0x633AA551_u32> for Flipper {
+            const SELECTOR: [u8; 4] = [0x63, 0x3A, 0xA5, 0x51];
+        }
+    };
+    impl<'a> Flipper {
+        pub fn new(
+            init_value: bool,
+        ) -> Self {
+            Self { value: init_value }
+        }
+        pub fn flip(&mut self) {
+            self.value = !self.value;
+        }
+    }
+    pub struct Unit;
+}
+"#;
+
+    #[test]
+    fn types_are_printed_with_their_impls() {
+        let matches = filter(EXPANDED, "flipper::Flipper");
+
+        assert_eq!(matches.len(), 3);
+        assert_eq!(
+            matches[0],
+            "    #[cfg(not(feature = \"ink-as-dependency\"))]\n    pub struct Flipper {\n        \
+            value: bool,\n    }\n"
+        );
+        assert!(matches[1].contains("const SELECTOR"));
+        assert!(matches[2].starts_with("    impl<'a> Flipper {\n"));
+    }
+
+    #[test]
+    fn methods_are_matched_by_suffix() {
+        let matches = filter(EXPANDED, "Flipper::new");
+
+        assert_eq!(
+            matches,
+            vec!["        pub fn new(\n            init_value: bool,\n        ) -> Self {\n            \
+                Self { value: init_value }\n        }\n"]
+        );
+        assert_eq!(filter(EXPANDED, "Unit"), vec!["    pub struct Unit;\n"]);
+        assert!(filter(EXPANDED, "Unknown").is_empty());
+    }
+}
@@ -0,0 +1,330 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A local, read-only fork of a live chain at a block, served by `cargo contract fork`.
+//!
+//! The fork is a JSON-RPC endpoint which answers every request with the state of the forked
+//! block: requests reading the state get the hash of the block as their `at` parameter, and
+//! requests for the latest block get the forked one. The answers are kept in memory, so that
+//! the state pulled from the chain doesn't change while the fork is running and is only fetched
+//! once. Contracts are executed by the `contracts_call` RPC of the chain on the state of the
+//! forked block, so dry-runs against the fork behave as if submitted right after that block.
+//!
+//! Requests submitting extrinsics are rejected, nothing is ever submitted to the chain.
+
+use super::verify::{contract_code_hash, stored_code};
+use crate::rpc::RpcClient;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use sp_core::{
+    crypto::{AccountId32, Ss58Codec},
+    hashing::{blake2_128, twox_128},
+    H256,
+};
+use std::{
+    collections::HashMap,
+    io::Read,
+    sync::{Arc, Mutex},
+    thread,
+};
+use structopt::StructOpt;
+use url::Url;
+
+/// The methods reading the state of a block, by the position of their block hash parameter.
+const PINNED_METHODS: [(&str, usize); 14] = [
+    ("state_getStorage", 1),
+    ("state_getStorageHash", 1),
+    ("state_getStorageSize", 1),
+    ("state_getKeysPaged", 3),
+    ("state_getMetadata", 0),
+    ("state_getRuntimeVersion", 0),
+    ("state_call", 2),
+    ("childstate_getStorage", 2),
+    ("childstate_getKeys", 2),
+    ("contracts_call", 1),
+    ("contracts_getStorage", 2),
+    ("contracts_rentProjection", 1),
+    ("chain_getHeader", 0),
+    ("chain_getBlock", 0),
+];
+
+/// Serves a read-only fork of a live chain at a block, to dry-run calls against its state.
+///
+/// Point `--url` of `cargo contract call --dry-run` or `cargo contract serve` to the fork to see
+/// what a call would do on the chain right now, without submitting it.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "fork")]
+pub struct ForkCommand {
+    /// Websockets or HTTP url of a node of the chain to fork, e.g. `wss://rpc.shiden.astar.network`
+    #[structopt(long, parse(try_from_str))]
+    from: Url,
+    /// The block to fork at, by number or hash, defaults to the latest finalized block
+    #[structopt(long)]
+    at: Option<String>,
+    /// Address of a contract whose info, code and account are pulled into the fork on start
+    #[structopt(long = "contract", parse(try_from_str = crate::parse_account))]
+    contracts: Vec<AccountId32>,
+    /// The port to serve the fork on, bound to localhost only
+    #[structopt(long, default_value = "9955")]
+    port: u16,
+}
+
+impl ForkCommand {
+    pub fn exec(&self) -> Result<Option<String>> {
+        let remote = RpcClient::new(&self.from)?;
+        let at = block_hash(&remote, self.at.as_deref())?;
+        let fork = Arc::new(Fork {
+            remote,
+            at,
+            cache: Default::default(),
+        });
+        let server = tiny_http::Server::http(("127.0.0.1", self.port)).map_err(|err| {
+            anyhow::anyhow!("Failed to serve the fork on port {}: {}", self.port, err)
+        })?;
+        let serving = fork.clone();
+        let server = thread::spawn(move || serve(&server, &serving));
+
+        let url = Url::parse(&format!("ws://127.0.0.1:{}", self.port))?;
+        let local = RpcClient::new(&url)?;
+        for contract in &self.contracts {
+            pull(&local, contract).context(format!(
+                "Failed to pull the contract {}",
+                contract.to_ss58check()
+            ))?;
+        }
+        println!(
+            "Serving the fork of {} at block {}, dry-run calls against it with `--url {}`",
+            self.from, fork.at, url
+        );
+        server
+            .join()
+            .map_err(|_| anyhow::anyhow!("The fork server panicked"))?;
+        Ok(None)
+    }
+}
+
+/// Returns the hash of the block `at`, given by number or hash, or of the latest finalized block.
+pub(super) fn block_hash(remote: &RpcClient, at: Option<&str>) -> Result<String> {
+    let hash: Option<String> = match at {
+        Some(hash) if hash.starts_with("0x") => {
+            if hash.len() != 66 || !hash[2..].chars().all(|c| c.is_ascii_hexdigit()) {
+                anyhow::bail!("The block hash {} has no 32 hex encoded bytes", hash)
+            }
+            return Ok(hash.to_lowercase());
+        }
+        Some(number) => {
+            let number: u64 = number.parse().context(format!(
+                "Invalid block {}, expected a number or hash",
+                number
+            ))?;
+            remote.request("chain_getBlockHash", json!([number]))?
+        }
+        None => Some(remote.request("chain_getFinalizedHead", json!([]))?),
+    };
+    hash.ok_or_else(|| anyhow::anyhow!("The block {} is unknown", at.unwrap_or_default()))
+}
+
+/// Pulls the info, code and account of the `contract` into the fork at `local`.
+fn pull(local: &RpcClient, contract: &AccountId32) -> Result<()> {
+    let code_hash = contract_code_hash(local, contract)?;
+    stored_code(local, &H256(code_hash))?;
+    let account: &[u8] = contract.as_ref();
+    let mut key = twox_128(b"System").to_vec();
+    key.extend(twox_128(b"Account").iter());
+    key.extend(blake2_128(account).iter());
+    key.extend(account);
+    let _: Option<String> = local.request(
+        "state_getStorage",
+        json!([format!("0x{}", hex::encode(key))]),
+    )?;
+    Ok(())
+}
+
+/// Answers the JSON-RPC requests to the `server` from the `fork`.
+fn serve(server: &tiny_http::Server, fork: &Fork) {
+    for mut request in server.incoming_requests() {
+        let mut body = Vec::new();
+        let response = match request.as_reader().read_to_end(&mut body) {
+            Ok(_) => fork.respond(&body),
+            Err(err) => error_response(Value::Null, -32700, &err.into()),
+        };
+        let header = tiny_http::Header::from_bytes(&b"Content-Type"[..], "application/json")
+            .expect("the content type is a valid header value");
+        let response = tiny_http::Response::from_string(response.to_string()).with_header(header);
+        if let Err(err) = request.respond(response) {
+            log::warn!("Failed to respond to a request: {}", err);
+        }
+    }
+}
+
+/// The state of a chain at a block, pulled on demand.
+struct Fork {
+    remote: RpcClient,
+    /// The hash of the forked block.
+    at: String,
+    /// The results of the requests by their method and params.
+    cache: Mutex<HashMap<String, Value>>,
+}
+
+/// The way the fork answers a request.
+#[derive(Debug, PartialEq)]
+enum Route {
+    /// Forwarded to the chain with the params, the result is cached.
+    Forward(Value),
+    /// Answered by the fork with the result.
+    Local(Value),
+}
+
+impl Fork {
+    /// Returns the JSON-RPC response to the request `body`.
+    fn respond(&self, body: &[u8]) -> Value {
+        let request: Value = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(err) => return error_response(Value::Null, -32700, &err.into()),
+        };
+        let id = request["id"].clone();
+        let method = request["method"].as_str().unwrap_or_default();
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        match self.request(method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => error_response(id, -32000, &err),
+        }
+    }
+
+    /// Returns the result of the request of `method` with `params` at the forked block.
+    fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let params = match route(method, params, &self.at)? {
+            Route::Local(result) => return Ok(result),
+            Route::Forward(params) => params,
+        };
+        let key = format!("{}{}", method, params);
+        if let Some(result) = self.cache().get(&key) {
+            return Ok(result.clone());
+        }
+        let result: Value = self.remote.request(method, params)?;
+        self.cache().insert(key, result.clone());
+        Ok(result)
+    }
+
+    fn cache(&self) -> std::sync::MutexGuard<'_, HashMap<String, Value>> {
+        self.cache
+            .lock()
+            .expect("the cache lock is never poisoned; qed")
+    }
+}
+
+/// Returns how the request of `method` with `params` is answered by the fork at the block `at`.
+fn route(method: &str, params: Value, at: &str) -> Result<Route> {
+    if method.starts_with("author_") || method.contains("subscribe") {
+        anyhow::bail!(
+            "`{}` is not supported by the read-only fork, dry-run the call instead",
+            method
+        )
+    }
+    let mut params = match params {
+        Value::Array(params) => params,
+        Value::Null => Vec::new(),
+        _ => anyhow::bail!("The fork supports positional params only"),
+    };
+    let latest = params.first().map_or(true, Value::is_null);
+    if method == "chain_getFinalizedHead" || (method == "chain_getBlockHash" && latest) {
+        return Ok(Route::Local(json!(at)));
+    }
+    if let Some((_, position)) = PINNED_METHODS.iter().find(|(name, _)| *name == method) {
+        if params.len() <= *position {
+            params.resize(*position + 1, Value::Null);
+        }
+        if params[*position].is_null() {
+            params[*position] = json!(at);
+        }
+    }
+    Ok(Route::Forward(Value::Array(params)))
+}
+
+fn error_response(id: Value, code: i64, err: &anyhow::Error) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": format!("{:#}", err) },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AT: &str = "0x1234";
+
+    #[test]
+    fn state_requests_are_pinned_to_the_forked_block() {
+        assert_eq!(
+            route("state_getStorage", json!(["0xaa"]), AT).unwrap(),
+            Route::Forward(json!(["0xaa", AT]))
+        );
+        assert_eq!(
+            route("state_getKeysPaged", json!(["0xaa", 10]), AT).unwrap(),
+            Route::Forward(json!(["0xaa", 10, null, AT]))
+        );
+        assert_eq!(
+            route("contracts_call", json!([{ "dest": "5C4h" }, null]), AT).unwrap(),
+            Route::Forward(json!([{ "dest": "5C4h" }, AT]))
+        );
+        // explicitly requested blocks are kept
+        assert_eq!(
+            route("chain_getHeader", json!(["0x5678"]), AT).unwrap(),
+            Route::Forward(json!(["0x5678"]))
+        );
+        assert_eq!(
+            route("system_chain", Value::Null, AT).unwrap(),
+            Route::Forward(json!([]))
+        );
+    }
+
+    #[test]
+    fn the_forked_block_is_the_latest_one() {
+        assert_eq!(
+            route("chain_getFinalizedHead", json!([]), AT).unwrap(),
+            Route::Local(json!(AT))
+        );
+        assert_eq!(
+            route("chain_getBlockHash", json!([]), AT).unwrap(),
+            Route::Local(json!(AT))
+        );
+        assert_eq!(
+            route("chain_getBlockHash", json!([0]), AT).unwrap(),
+            Route::Forward(json!([0]))
+        );
+    }
+
+    #[test]
+    fn submissions_are_rejected() {
+        assert!(route("author_submitExtrinsic", json!(["0xaa"]), AT).is_err());
+        assert!(route("chain_subscribeNewHeads", json!([]), AT).is_err());
+    }
+
+    #[test]
+    fn block_hashes_are_validated() {
+        let remote = RpcClient::new(&Url::parse("ws://localhost:9944").unwrap()).unwrap();
+        let hash = format!("0x{}", "AB".repeat(32));
+
+        assert_eq!(
+            block_hash(&remote, Some(&hash)).unwrap(),
+            hash.to_lowercase()
+        );
+        assert!(block_hash(&remote, Some("0x1234")).is_err());
+        assert!(block_hash(&remote, Some("latest")).is_err());
+    }
+}
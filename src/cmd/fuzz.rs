@@ -0,0 +1,609 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    metadata,
+    trait_definition::{contract_of, Registry},
+    verify_metadata::label,
+};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fmt::Write,
+    fs,
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+/// The declarations of every generated harness, after its module documentation.
+const HARNESS_HEADER: &str = r#"
+#![cfg(feature = "e2e-tests")]
+
+use contract_e2e::{Contract, DryRun, Node};
+use proptest::{
+    prelude::*,
+    strategy::Union,
+    test_runner::{Config, TestRunner},
+};
+use scale::{Compact, Encode, Output};
+
+/// The number of cases of each message.
+const CASES: u32 = {{cases}};
+
+/// Arguments encoded by the strategies, passed on as they are.
+#[derive(Debug, Clone)]
+struct Encoded(Vec<u8>);
+
+impl Encode for Encoded {
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        dest.write(&self.0)
+    }
+}
+
+/// Checks the `outcome` of the dry-run of the `message` with the encoded `args`.
+///
+/// TODO: add the invariants of the contract, e.g. on its output or by querying its state.
+fn invariants(
+    _contract: &Contract,
+    message: &str,
+    args: &[u8],
+    outcome: &DryRun,
+) -> Result<(), TestCaseError> {
+    if let DryRun::Trapped(error) = outcome {
+        return Err(TestCaseError::fail(format!(
+            "{} trapped for the arguments {:?}: {}",
+            message, args, error
+        )));
+    }
+    Ok(())
+}
+
+/// Deploys the contract with the default value of every argument of the constructor.
+///
+/// TODO: replace the arguments with meaningful ones.
+fn deploy(node: &Node) -> Contract<'_> {
+    Contract::deploy(node, "{{constructor}}", Encoded(vec!{{constructor_args}}))
+        .expect("the contract is deployed")
+}
+
+/// Dry-runs the `message` with the encoded arguments of `args` in every case.
+fn fuzz(message: &str, args: BoxedStrategy<Vec<u8>>) {
+    let node = Node::start().expect("the node starts");
+    let contract = deploy(&node);
+    TestRunner::new(Config::with_cases(CASES))
+        .run(&args, |args| {
+            let outcome = contract
+                .dry_run(message, Encoded(args.clone()))
+                .map_err(|err| TestCaseError::fail(err.to_string()))?;
+            invariants(&contract, message, &args, &outcome)
+        })
+        .unwrap_or_else(|err| panic!("{}", err));
+}
+
+/// Deploys the contract by the `constructor` with the encoded arguments of `args` in every case.
+fn fuzz_deploy(constructor: &str, args: BoxedStrategy<Vec<u8>>) {
+    let node = Node::start().expect("the node starts");
+    TestRunner::new(Config::with_cases(CASES))
+        .run(&args, |args| {
+            Contract::deploy(&node, constructor, Encoded(args.clone()))
+                .map(|_| ())
+                .map_err(|err| {
+                    TestCaseError::fail(format!(
+                        "{} failed for the arguments {:?}: {}",
+                        constructor, args, err
+                    ))
+                })
+        })
+        .unwrap_or_else(|err| panic!("{}", err));
+}
+
+/// Returns the concatenated encodings of the `parts`, e.g. the fields of a struct.
+fn concat(parts: Vec<BoxedStrategy<Vec<u8>>>) -> BoxedStrategy<Vec<u8>> {
+    parts.prop_map(|parts| parts.concat()).boxed()
+}
+
+/// Returns the encodings of sequences of up to 8 elements.
+fn sequence(element: BoxedStrategy<Vec<u8>>) -> BoxedStrategy<Vec<u8>> {
+    prop::collection::vec(element, 0..8)
+        .prop_map(|elements| {
+            let mut encoded = Compact(elements.len() as u32).encode();
+            encoded.extend(elements.concat());
+            encoded
+        })
+        .boxed()
+}
+
+/// Returns the encodings of arrays of `len` elements.
+fn array(element: BoxedStrategy<Vec<u8>>, len: usize) -> BoxedStrategy<Vec<u8>> {
+    prop::collection::vec(element, len)
+        .prop_map(|elements| elements.concat())
+        .boxed()
+}
+
+/// Returns the encodings of the variants of an enum, prefixed with their index.
+fn variants(variants: Vec<(u8, Vec<BoxedStrategy<Vec<u8>>>)>) -> BoxedStrategy<Vec<u8>> {
+    Union::new(variants.into_iter().map(|(index, fields)| {
+        let mut parts = vec![Just(vec![index]).boxed()];
+        parts.extend(fields);
+        concat(parts)
+    }))
+    .boxed()
+}
+"#;
+
+/// Generates a fuzz harness for the messages of the contract from its metadata.
+///
+/// The harness is an end-to-end test with a proptest strategy per argument type of the
+/// constructor and the messages, derived from the type registry of the contract. Each case
+/// deploys the contract or dry-runs a message with random, structurally valid arguments against
+/// a development node, failing on traps and on violations of the invariants added to the harness.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "fuzz")]
+pub struct FuzzCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Path to the metadata or the `<name>.contract` bundle, defaults to
+    /// `./target/ink/metadata.json`
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+    /// The messages to fuzz, separated by commas, defaults to all messages
+    #[structopt(long, use_delimiter = true)]
+    messages: Vec<String>,
+    /// The constructor the contract is deployed with, defaults to the first one
+    #[structopt(long)]
+    constructor: Option<String>,
+    /// The number of cases of each message
+    #[structopt(long, default_value = "64")]
+    cases: u32,
+    /// Path of the generated harness, defaults to `tests/fuzz.rs` of the contract
+    #[structopt(short, long, parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+impl FuzzCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+                metadata::artifact_paths(&crate_metadata).dest_metadata
+            }
+        };
+        let contents = fs::read(&path).context(format!(
+            "Failed to read the metadata {}, build the contract first",
+            path.display()
+        ))?;
+        let metadata: Value = serde_json::from_slice(&contents)
+            .context(format!("The metadata {} is no valid JSON", path.display()))?;
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => manifest_path
+                .absolute_directory()?
+                .join("tests")
+                .join("fuzz.rs"),
+        };
+        if output.exists() {
+            anyhow::bail!(
+                "The harness {} already exists, remove it to generate it again",
+                output.display()
+            )
+        }
+
+        let harness = harness(
+            &metadata,
+            &self.messages,
+            self.constructor.as_deref(),
+            self.cases,
+        )?;
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&output, harness)
+            .context(format!("Failed to write the harness {}", output.display()))?;
+        Ok(format!(
+            "Generated the fuzz harness {}, run it by `cargo contract test --e2e`. It requires \
+            the dev-dependencies `contract-e2e`, `proptest` and `scale`",
+            output.display()
+        ))
+    }
+}
+
+/// Returns the fuzz harness of the `messages` of the contract described by the `metadata`, all
+/// of them if none are selected.
+fn harness(
+    metadata: &Value,
+    messages: &[String],
+    constructor: Option<&str>,
+    cases: u32,
+) -> Result<String> {
+    let contract = contract_of(metadata);
+    let name = metadata["contract"]["name"].as_str().unwrap_or("contract");
+    let items = |kind: &str| {
+        contract["spec"][kind]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    };
+    let constructor = match constructor {
+        Some(constructor) => items("constructors")
+            .iter()
+            .find(|item| label(item).as_deref() == Some(constructor))
+            .ok_or_else(|| anyhow::anyhow!("The contract has no constructor {}", constructor))?,
+        None => items("constructors")
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("The contract has no constructor"))?,
+    };
+    let selected = select_messages(contract, messages)?;
+
+    let mut strategies = Strategies {
+        registry: Registry::new(contract),
+        functions: BTreeMap::new(),
+        stack: BTreeSet::new(),
+    };
+    let mut constructor_args = Vec::new();
+    let mut constructor_strategies = Vec::new();
+    for arg in args(constructor)? {
+        strategies.default_encoding(arg, &mut constructor_args, &mut BTreeSet::new())?;
+        constructor_strategies.push(strategies.reference(arg)?);
+    }
+    let mut tests = String::new();
+    if !constructor_strategies.is_empty() {
+        let label = label(constructor).unwrap_or_default();
+        write!(
+            tests,
+            "\n#[test]\nfn fuzz_deploy_{}() {{\n    fuzz_deploy(\"{}\", concat(vec![{}]));\n}}\n",
+            label.to_lowercase(),
+            label,
+            constructor_strategies.join(", ")
+        )?;
+    }
+    for message in selected {
+        let label = label(message).ok_or_else(|| anyhow::anyhow!("A message has no name"))?;
+        let args = args(message)?
+            .into_iter()
+            .map(|id| strategies.reference(id))
+            .collect::<Result<Vec<_>>>()?;
+        write!(
+            tests,
+            "\n#[test]\nfn fuzz_{}() {{\n    fuzz(\"{}\", concat(vec![{}]));\n}}\n",
+            label.replace("::", "_").to_lowercase(),
+            label,
+            args.join(", ")
+        )?;
+    }
+
+    let header = HARNESS_HEADER
+        .replace("{{cases}}", &cases.to_string())
+        .replace("{{constructor}}", &label(constructor).unwrap_or_default())
+        .replace("{{constructor_args}}", &format!("{:?}", constructor_args));
+    Ok(format!(
+        "//! Fuzz tests of the messages of the `{}` contract, generated by `cargo contract fuzz`.\n\
+        //!\n\
+        //! Every case dry-runs a message with random, structurally valid SCALE encoded arguments \
+        against a\n\
+        //! development node. Traps fail the case, as do violations of the `invariants`. Run the \
+        tests by\n\
+        //! `cargo contract test --e2e`.\n\
+        //!\n\
+        //! Requires the dev-dependencies `contract-e2e`, `proptest` and `parity-scale-codec` \
+        renamed to\n\
+        //! `scale`.\n{}{}{}",
+        name,
+        header,
+        strategies.functions.values().cloned().collect::<String>(),
+        tests
+    ))
+}
+
+/// Returns the `messages` of the `contract`, all of them if none are selected.
+pub(super) fn select_messages<'a>(
+    contract: &'a Value,
+    messages: &[String],
+) -> Result<Vec<&'a Value>> {
+    let selected = contract["spec"]["messages"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter(|message| {
+            messages.is_empty()
+                || matches!(label(message), Some(label) if messages.contains(&label))
+        })
+        .collect::<Vec<_>>();
+    for message in messages {
+        if !selected
+            .iter()
+            .any(|item| label(item).as_deref() == Some(message))
+        {
+            anyhow::bail!("The contract has no message {}", message)
+        }
+    }
+    Ok(selected)
+}
+
+/// Returns the type ids of the arguments of a constructor or message.
+pub(super) fn args(item: &Value) -> Result<Vec<u64>> {
+    item["args"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|arg| {
+            arg["type"]["type"]
+                .as_u64()
+                .ok_or_else(|| anyhow::anyhow!("An argument of {:?} has no type", label(item)))
+        })
+        .collect()
+}
+
+/// The strategies of the types of the arguments of the fuzzed messages.
+struct Strategies<'a> {
+    registry: Registry<'a>,
+    /// The generated function of each type returning its strategy.
+    functions: BTreeMap<u64, String>,
+    /// The types whose functions are being generated, to detect recursive types.
+    stack: BTreeSet<u64>,
+}
+
+impl<'a> Strategies<'a> {
+    /// Returns the expression of the strategy of the type `id`, generating its function.
+    ///
+    /// References to a type from within its own definition get its default encoding, as the
+    /// strategy of a recursive type would never be constructed.
+    fn reference(&mut self, id: u64) -> Result<String> {
+        if self.stack.contains(&id) {
+            let mut default = Vec::new();
+            self.default_encoding(id, &mut default, &mut BTreeSet::new())?;
+            return Ok(format!("Just(vec!{:?}).boxed()", default));
+        }
+        if !self.functions.contains_key(&id) {
+            self.stack.insert(id);
+            let strategy = self.strategy(id);
+            self.stack.remove(&id);
+            let name = self.registry.render(id, &mut BTreeMap::new())?;
+            self.functions.insert(
+                id,
+                format!(
+                    "\n/// Returns the encodings of `{}`.\nfn type_{}() -> BoxedStrategy<Vec<u8>> \
+                    {{\n    {}\n}}\n",
+                    name, id, strategy?
+                ),
+            );
+        }
+        Ok(format!("type_{}()", id))
+    }
+
+    /// Returns the expression of the strategy of the type `id`.
+    fn strategy(&mut self, id: u64) -> Result<String> {
+        let def = definition(self.registry.get(id)?, id)?;
+        if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+            return Ok(match primitive {
+                "str" => "any::<String>().prop_map(|value| value.encode()).boxed()".into(),
+                "char" => "any::<char>().prop_map(|value| (value as u32).encode()).boxed()".into(),
+                "u256" | "i256" => {
+                    "any::<[u8; 32]>().prop_map(|value| value.to_vec()).boxed()".into()
+                }
+                primitive => format!(
+                    "any::<{}>().prop_map(|value| value.encode()).boxed()",
+                    primitive
+                ),
+            });
+        }
+        if let Some(compact) = def.get("compact") {
+            let inner = definition(self.registry.get(field_type(compact)?)?, id)?;
+            let primitive = inner
+                .get("primitive")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow::anyhow!("Only compact integers are supported"))?;
+            return Ok(format!(
+                "any::<{}>().prop_map(|value| Compact(value).encode()).boxed()",
+                primitive
+            ));
+        }
+        if let Some(sequence) = def.get("sequence") {
+            return Ok(format!(
+                "sequence({})",
+                self.reference(field_type(sequence)?)?
+            ));
+        }
+        if let Some(array) = def.get("array") {
+            let len = array["len"].as_u64().unwrap_or_default();
+            return Ok(format!(
+                "array({}, {})",
+                self.reference(field_type(array)?)?,
+                len
+            ));
+        }
+        if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+            let fields = tuple
+                .iter()
+                .map(|field| self.reference(field_type(field)?))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(format!("concat(vec![{}])", fields.join(", ")));
+        }
+        if let Some(composite) = def.get("composite") {
+            return Ok(format!(
+                "concat(vec![{}])",
+                self.fields(composite)?.join(", ")
+            ));
+        }
+        let variants = def["variant"]["variants"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("The type {} can't be fuzzed", id))?;
+        if variants.is_empty() {
+            return Ok("Just(Vec::new()).boxed()".into());
+        }
+        let variants = variants
+            .iter()
+            .enumerate()
+            .map(|(position, variant)| {
+                Ok(format!(
+                    "({}, vec![{}])",
+                    variant_index(variant, position),
+                    self.fields(variant)?.join(", ")
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(format!("variants(vec![{}])", variants.join(", ")))
+    }
+
+    /// Returns the strategies of the fields of a struct or enum variant.
+    fn fields(&mut self, item: &Value) -> Result<Vec<String>> {
+        item["fields"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|field| self.reference(field_type(field)?))
+            .collect()
+    }
+
+    /// Appends the encoding of the default value of the type `id` to `output`: zero, empty or
+    /// the first variant.
+    fn default_encoding(
+        &self,
+        id: u64,
+        output: &mut Vec<u8>,
+        visiting: &mut BTreeSet<u64>,
+    ) -> Result<()> {
+        if !visiting.insert(id) {
+            // the value of a recursive type ends at its recursion
+            return Ok(());
+        }
+        let def = definition(self.registry.get(id)?, id)?;
+        if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+            let len = match primitive {
+                "bool" | "u8" | "i8" | "str" => 1,
+                "u16" | "i16" => 2,
+                "u32" | "i32" | "char" => 4,
+                "u64" | "i64" => 8,
+                "u128" | "i128" => 16,
+                _ => 32,
+            };
+            output.extend(vec![0; len]);
+        } else if def.contains_key("compact") || def.contains_key("sequence") {
+            // the compact encoded zero or length
+            output.push(0);
+        } else if let Some(array) = def.get("array") {
+            for _ in 0..array["len"].as_u64().unwrap_or_default() {
+                self.default_encoding(field_type(array)?, output, visiting)?;
+            }
+        } else if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+            for field in tuple {
+                self.default_encoding(field_type(field)?, output, visiting)?;
+            }
+        } else if let Some(composite) = def.get("composite") {
+            self.default_fields(composite, output, visiting)?;
+        } else if let Some(variant) = def["variant"]["variants"]
+            .as_array()
+            .and_then(|variants| variants.first())
+        {
+            output.push(variant_index(variant, 0));
+            self.default_fields(variant, output, visiting)?;
+        }
+        visiting.remove(&id);
+        Ok(())
+    }
+
+    /// Appends the encodings of the default values of the fields of a struct or enum variant.
+    fn default_fields(
+        &self,
+        item: &Value,
+        output: &mut Vec<u8>,
+        visiting: &mut BTreeSet<u64>,
+    ) -> Result<()> {
+        for field in item["fields"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            self.default_encoding(field_type(field)?, output, visiting)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the definition of the type `ty` with the id `id`.
+pub(super) fn definition(ty: &Value, id: u64) -> Result<&Map<String, Value>> {
+    ty.get("def")
+        .and_then(Value::as_object)
+        .ok_or_else(|| anyhow::anyhow!("The type {} has no definition", id))
+}
+
+/// Returns the type id a field, element or tuple entry refers to.
+pub(super) fn field_type(field: &Value) -> Result<u64> {
+    field
+        .get("type")
+        .and_then(Value::as_u64)
+        .or_else(|| field.as_u64())
+        .ok_or_else(|| anyhow::anyhow!("The type {} refers to no type id", field))
+}
+
+/// Returns the index of a variant, which older registries imply by its `position`.
+fn variant_index(variant: &Value, position: usize) -> u8 {
+    variant["index"]
+        .as_u64()
+        .map_or(position as u8, |index| index as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::erc20_metadata;
+
+    #[test]
+    fn harness_has_strategies_of_argument_types() {
+        // when
+        let harness = harness(&erc20_metadata(), &["transfer".into()], None, 16).unwrap();
+
+        // then
+        assert!(harness.contains("const CASES: u32 = 16;"));
+        assert!(harness.contains(
+            "#[test]\nfn fuzz_transfer() {\n    fuzz(\"transfer\", concat(vec![type_1(), \
+            type_0()]));\n}"
+        ));
+        assert!(harness.contains("/// Returns the encodings of `AccountId`.\nfn type_1()"));
+        assert!(harness.contains("    array(type_3(), 32)\n"));
+        assert!(harness.contains("    any::<u128>().prop_map(|value| value.encode()).boxed()\n"));
+        assert!(!harness.contains("fn fuzz_set_data"));
+    }
+
+    #[test]
+    fn constructor_is_deployed_with_default_arguments() {
+        let harness = harness(&erc20_metadata(), &[], None, 64).unwrap();
+
+        // 16 zero bytes of the supply, the index of the first variant of the mode
+        assert!(harness.contains(&format!(
+            "Contract::deploy(node, \"new\", Encoded(vec!{:?}))",
+            vec![0u8; 17]
+        )));
+        assert!(harness.contains("variants(vec![(0, vec![]), (1, vec![type_0()])])"));
+        assert!(harness.contains("fuzz_deploy(\"new\", concat(vec![type_0(), type_5()]));"));
+        assert!(harness.contains("fn fuzz_set_data"));
+        assert!(harness.contains("fn fuzz_total_supply"));
+    }
+
+    #[test]
+    fn unknown_messages_are_rejected() {
+        let err = harness(&erc20_metadata(), &["burn".into()], None, 64).unwrap_err();
+        assert_eq!(err.to_string(), "The contract has no message burn");
+    }
+}
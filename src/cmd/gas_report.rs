@@ -0,0 +1,324 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The costs of all calls of contracts made by a script or scenarios, summed up per message.
+//!
+//! The gas, proof size and storage deposit of a call are measured by dry-running it on the
+//! state it is made on, since the events of submitted calls don't report them. Submitted calls
+//! are also charged the fee the node quotes for their extrinsic by `payment_queryInfo`, which
+//! is an upper bound as the fee for unused gas is refunded.
+
+use super::bench::{measure, number, Measurement};
+use crate::rpc::RpcClient;
+use anyhow::{Context, Result};
+use codec::Encode;
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::{fmt::Write as _, fs, path::PathBuf};
+use structopt::StructOpt;
+use subxt::{contracts::*, system::System, ClientBuilder, DefaultNodeRuntime, Signer};
+use url::Url;
+
+/// Options of the gas report of commands making several calls.
+#[derive(Debug, StructOpt)]
+pub(super) struct GasReportOpts {
+    /// Print the gas, proof size, storage deposits and fees of all calls per message at the end
+    #[structopt(long)]
+    gas_report: bool,
+    /// Write the gas report as JSON to this path, e.g. to track the costs in CI
+    #[structopt(long, parse(from_os_str))]
+    gas_report_json: Option<PathBuf>,
+}
+
+impl GasReportOpts {
+    /// Returns an empty report if one is requested.
+    pub(super) fn report(&self) -> Option<GasReport> {
+        if self.gas_report || self.gas_report_json.is_some() {
+            Some(GasReport::default())
+        } else {
+            None
+        }
+    }
+
+    /// Prints and writes the `report` as requested.
+    pub(super) fn finish(&self, report: &GasReport) -> Result<()> {
+        if self.gas_report {
+            println!("{}", report.table()?);
+        }
+        if let Some(path) = &self.gas_report_json {
+            fs::write(path, serde_json::to_string_pretty(&report.summary())?)
+                .context(format!("Failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// The costs of the calls made so far.
+#[derive(Debug, Default)]
+pub(super) struct GasReport {
+    calls: Vec<(String, Measurement, Option<u128>)>,
+}
+
+/// The summed up costs of calls.
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Costs {
+    calls: u32,
+    ref_time: u64,
+    /// The proof size, if reported by the node.
+    proof_size: Option<u64>,
+    /// The storage deposits charged, negative if refunded, if reported by the node.
+    storage_deposit: Option<i128>,
+    /// The fees of the submitted calls.
+    fees: Option<u128>,
+}
+
+/// The costs of the calls of a message.
+#[derive(Debug, Serialize)]
+struct MessageCosts {
+    message: String,
+    #[serde(flatten)]
+    costs: Costs,
+}
+
+/// The costs of the calls per message and in total.
+#[derive(Debug, Serialize)]
+struct Summary {
+    messages: Vec<MessageCosts>,
+    total: Costs,
+}
+
+impl GasReport {
+    /// Records the costs of the call of the `message` of the contract `dest` by dry-running it
+    /// from `origin`, along with the `fee` of submitted calls.
+    ///
+    /// Calls whose dry-run fails, e.g. expected failures, are not recorded.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn record(
+        &mut self,
+        url: &Url,
+        message: &str,
+        origin: &AccountId32,
+        dest: &AccountId32,
+        value: u128,
+        gas_limit: u64,
+        data: &[u8],
+        fee: Option<u128>,
+    ) -> Result<()> {
+        let call_request = json!({
+            "origin": origin.to_ss58check(),
+            "dest": dest.to_ss58check(),
+            "value": value,
+            "gasLimit": gas_limit,
+            "inputData": format!("0x{}", hex::encode(data)),
+        });
+        let result: Value =
+            RpcClient::new(url)?.request("contracts_call", json!([call_request]))?;
+        match measure(&result) {
+            Ok(measurement) => self.calls.push((message.into(), measurement, fee)),
+            Err(err) => log::debug!("The costs of {} are not recorded: {:#}", message, err),
+        }
+        Ok(())
+    }
+
+    /// Returns the costs per message, in the order of their first call, and in total.
+    fn summary(&self) -> Summary {
+        let mut messages: Vec<MessageCosts> = Vec::new();
+        let mut total = Costs::default();
+        for (message, measurement, fee) in &self.calls {
+            let index = match messages.iter().position(|costs| &costs.message == message) {
+                Some(index) => index,
+                None => {
+                    messages.push(MessageCosts {
+                        message: message.clone(),
+                        costs: Costs::default(),
+                    });
+                    messages.len() - 1
+                }
+            };
+            messages[index].costs.add(measurement, *fee);
+            total.add(measurement, *fee);
+        }
+        Summary { messages, total }
+    }
+
+    /// Returns a table with a line per message and the total.
+    fn table(&self) -> Result<String> {
+        let summary = self.summary();
+        let width = summary
+            .messages
+            .iter()
+            .map(|message| message.message.len())
+            .chain(Some("message".len()))
+            .max()
+            .unwrap_or_default();
+        let cell = |cost: Option<String>| cost.unwrap_or_else(|| "-".into());
+        let row = |table: &mut String, message: &str, costs: &Costs| {
+            writeln!(
+                table,
+                "{:<width$}  {:>5}  {:>16}  {:>12}  {:>20}  {:>20}",
+                message,
+                costs.calls,
+                costs.ref_time,
+                cell(costs.proof_size.map(|size| size.to_string())),
+                cell(costs.storage_deposit.map(|deposit| deposit.to_string())),
+                cell(costs.fees.map(|fees| fees.to_string())),
+                width = width
+            )
+        };
+        let mut table = String::new();
+        writeln!(
+            table,
+            "{:<width$}  {:>5}  {:>16}  {:>12}  {:>20}  {:>20}",
+            "message".bold(),
+            "calls".bold(),
+            "ref_time".bold(),
+            "proof_size".bold(),
+            "storage deposit".bold(),
+            "fees".bold(),
+            width = width
+        )?;
+        for message in &summary.messages {
+            row(&mut table, &message.message, &message.costs)?;
+        }
+        row(&mut table, "total", &summary.total)?;
+        Ok(table.trim_end().to_string())
+    }
+}
+
+impl Costs {
+    fn add(&mut self, measurement: &Measurement, fee: Option<u128>) {
+        fn sum<T: std::ops::Add<Output = T> + Default>(
+            total: Option<T>,
+            cost: Option<T>,
+        ) -> Option<T> {
+            match (total, cost) {
+                (None, None) => None,
+                (total, cost) => Some(total.unwrap_or_default() + cost.unwrap_or_default()),
+            }
+        }
+        self.calls += 1;
+        self.ref_time += measurement.ref_time;
+        self.proof_size = sum(self.proof_size, measurement.proof_size);
+        self.storage_deposit = sum(self.storage_deposit, measurement.storage_deposit);
+        self.fees = sum(self.fees, fee);
+    }
+}
+
+/// Returns the fee the node at `url` quotes for submitting the call of the contract `dest` with
+/// `data`, signed by the `signer`.
+pub(super) fn call_fee(
+    url: &Url,
+    signer: &(dyn Signer<DefaultNodeRuntime> + Send + Sync),
+    dest: &AccountId32,
+    value: u128,
+    gas_limit: u64,
+    data: &[u8],
+) -> Result<u128> {
+    let dest: <DefaultNodeRuntime as System>::Address = dest.clone().into();
+    let extrinsic = async_std::task::block_on(async {
+        let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+            .set_url(&url.to_string())
+            .build()
+            .await?;
+        let call = CallCall {
+            dest: &dest,
+            value,
+            gas_limit,
+            data,
+        };
+        Ok::<_, anyhow::Error>(cli.create_signed(call, signer).await?)
+    })?;
+    let info: Value = RpcClient::new(url)?.request(
+        "payment_queryInfo",
+        json!([format!("0x{}", hex::encode(extrinsic.encode()))]),
+    )?;
+    number(&info["partialFee"]).ok_or_else(|| anyhow::anyhow!("No fee in the result {}", info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> GasReport {
+        let measurement = |ref_time, storage_deposit| Measurement {
+            ref_time,
+            proof_size: None,
+            storage_deposit,
+        };
+        GasReport {
+            calls: vec![
+                ("transfer".into(), measurement(300, Some(100)), Some(10)),
+                ("balance_of".into(), measurement(100, None), None),
+                ("transfer".into(), measurement(200, Some(-50)), Some(20)),
+            ],
+        }
+    }
+
+    #[test]
+    fn costs_are_summed_up_per_message() {
+        let summary = report().summary();
+
+        assert_eq!(summary.messages[0].message, "transfer");
+        assert_eq!(
+            summary.messages[0].costs,
+            Costs {
+                calls: 2,
+                ref_time: 500,
+                proof_size: None,
+                storage_deposit: Some(50),
+                fees: Some(30),
+            }
+        );
+        assert_eq!(summary.messages[1].message, "balance_of");
+        assert_eq!(summary.messages[1].costs.fees, None);
+        assert_eq!(summary.total.calls, 3);
+        assert_eq!(summary.total.ref_time, 600);
+        assert_eq!(summary.total.storage_deposit, Some(50));
+    }
+
+    #[test]
+    fn summary_is_exported_as_json() {
+        let json = serde_json::to_value(&report().summary()).unwrap();
+
+        assert_eq!(
+            json["messages"][0],
+            json!({
+                "message": "transfer",
+                "calls": 2,
+                "refTime": 500,
+                "proofSize": null,
+                "storageDeposit": 50,
+                "fees": 30,
+            })
+        );
+        assert_eq!(json["total"]["refTime"], 600);
+    }
+
+    #[test]
+    fn table_ends_with_the_total() {
+        let table = report().table().unwrap();
+
+        assert_eq!(table.lines().count(), 4);
+        assert!(table
+            .lines()
+            .last()
+            .unwrap()
+            .starts_with("total           3               600"));
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::metadata::blake2_hash;
+use crate::{bundle::ContractBundle, crate_metadata::CrateMetadata, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use impl_serde::serialize as serde_hex;
+use std::{convert::TryFrom, fs, path::PathBuf};
+use structopt::StructOpt;
+
+/// Computes the code hash of a contract offline, exactly as `pallet-contracts` does when the
+/// code is uploaded: the BLAKE2 256-bit hash of the Wasm as is.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "hash")]
+pub struct HashCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Path to the Wasm or the `<name>.contract` bundle, defaults to
+    /// `./target/ink/<name>.wasm`
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+}
+
+impl HashCommand {
+    pub fn exec(&self) -> Result<String> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                CrateMetadata::collect(&manifest_path)?.dest_wasm
+            }
+        };
+        let code = if ContractBundle::is_bundle(&path) {
+            // the Wasm is checked against the code hash recorded in the bundle
+            ContractBundle::load(&path)?.wasm()?
+        } else {
+            fs::read(&path).context(format!(
+                "Failed to read the Wasm {}, build the contract first",
+                path.display()
+            ))?
+        };
+        Ok(serde_hex::to_hex(&blake2_hash(&code).0, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    #[test]
+    fn code_hash_of_wasm_is_computed() {
+        with_tmp_dir(|path| {
+            // given
+            let wasm_path = path.join("flipper.wasm");
+            fs::write(&wasm_path, b"\0asm\x01\0\0\0")?;
+            let cmd = HashCommand::from_iter(&["hash", wasm_path.to_str().unwrap()]);
+
+            // when
+            let hash = cmd.exec()?;
+
+            // then
+            assert_eq!(
+                hash,
+                serde_hex::to_hex(&blake2_hash(b"\0asm\x01\0\0\0").0, false)
+            );
+            assert_eq!(hash.len(), 2 + 64);
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,569 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Import of Solidity ABIs as `#[ink::trait_definition]` crates.
+//!
+//! The Solidity types map to the ink! types:
+//!
+//! - `uintN` and `intN` to the smallest Rust integer holding them, integers wider than 128 bits
+//!   are narrowed to `u128` and `i128`
+//! - `address` to `AccountId`, `string` to `String`, `bytes` to `Vec<u8>`, `bytesN` to `[u8; N]`
+//! - arrays to `Vec<T>` and `[T; N]`
+//! - `tuple`s to the structs of their `internalType`, or to Rust tuples without one
+//!
+//! The functions of the ABI become the messages of the trait, `view` and `pure` functions don't
+//! mutate the contract. Constructors, events and errors are skipped, as trait definitions
+//! contain messages only.
+
+use super::{
+    new::TemplateVariables,
+    trait_definition::{self, ArgSpec, MessageSpec, TraitSpec, TYPE_DERIVES},
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use heck::{CamelCase as _, SnakeCase as _};
+use serde_json::Value;
+use std::{collections::BTreeMap, fs, path::PathBuf};
+use structopt::StructOpt;
+
+/// Keywords of Rust which are valid names in Solidity.
+const RUST_KEYWORDS: [&str; 26] = [
+    "as", "async", "await", "box", "crate", "dyn", "enum", "extern", "fn", "impl", "in", "let",
+    "loop", "match", "mod", "move", "mut", "pub", "ref", "self", "static", "struct", "super",
+    "trait", "type", "where",
+];
+
+/// Generates an `#[ink::trait_definition]` crate from a Solidity ABI, helping to port EVM
+/// interfaces to ink!.
+///
+/// Besides the trait and a stub contract implementing it, the crate contains a `<Trait>Ref`
+/// wrapper calling the messages of a deployed contract by their selectors.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "import-abi")]
+pub struct ImportAbiCommand {
+    /// The name of the generated crate, defaults to the file name of the ABI up to its first `.`
+    #[structopt(long)]
+    name: Option<String>,
+    /// The directory to create the crate in, defaults to the current directory
+    #[structopt(long, parse(from_os_str))]
+    target_dir: Option<PathBuf>,
+    /// Path to the Solidity ABI, either the ABI itself or a compiler artifact containing it
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+}
+
+impl ImportAbiCommand {
+    pub fn exec(&self) -> Result<Option<String>> {
+        let contents = fs::read(&self.path)
+            .context(format!("Failed to read the ABI {}", self.path.display()))?;
+        let abi: Value = serde_json::from_slice(&contents)
+            .context(format!("The ABI {} is no valid JSON", self.path.display()))?;
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => self
+                .path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| file_name.split('.').next())
+                .map(|file_name| file_name.to_snake_case())
+                .ok_or_else(|| anyhow::anyhow!("The name of the crate is required"))?,
+        };
+
+        let (spec, notes) = import(&abi)?;
+        for note in &notes {
+            eprintln!("{} {}", "warning:".yellow().bold(), note);
+        }
+        trait_definition::create(
+            &name,
+            self.target_dir.as_ref(),
+            &spec,
+            true,
+            &TemplateVariables::default(),
+        )
+    }
+}
+
+/// Returns the trait of the functions of the Solidity `abi`, together with notes on the parts
+/// of the ABI which were skipped or changed.
+fn import(abi: &Value) -> Result<(TraitSpec, Vec<String>)> {
+    // compiler artifacts, e.g. of Hardhat or Truffle, contain the ABI in their `abi` field
+    let items = abi
+        .get("abi")
+        .unwrap_or(abi)
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("The ABI is no array of functions and events"))?;
+    let mut types = Types::default();
+    let mut notes = Vec::new();
+    let mut messages = Vec::new();
+    let mut overloads = BTreeMap::new();
+
+    for item in items {
+        // the type of an item defaults to `function`
+        let kind = item
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or("function");
+        let name = item.get("name").and_then(Value::as_str).unwrap_or_default();
+        if kind != "function" {
+            notes.push(format!(
+                "skipped the {} {}, trait definitions contain messages only",
+                kind, name
+            ));
+            continue;
+        }
+        let inputs = params(item, "inputs");
+        let signature = format!(
+            "{}({})",
+            name,
+            inputs
+                .iter()
+                .map(|input| input
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+
+        // Rust has no overloading, overloaded functions are numbered
+        let mut message_name = identifier(name);
+        let overload = overloads.entry(message_name.clone()).or_insert(0);
+        *overload += 1;
+        if *overload > 1 {
+            message_name = format!("{}_{}", message_name, overload);
+            notes.push(format!(
+                "imported the overloaded function {} as {}",
+                signature, message_name
+            ));
+        }
+
+        let args = inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                let name = input
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                Ok(ArgSpec {
+                    name: if name.is_empty() {
+                        format!("arg{}", index)
+                    } else {
+                        identifier(name)
+                    },
+                    ty: types.ink_type(input, &mut notes)?,
+                })
+            })
+            .collect::<Result<_>>()
+            .context(format!("Failed to import the function {}", signature))?;
+        let outputs = params(item, "outputs")
+            .iter()
+            .map(|output| types.ink_type(output, &mut notes))
+            .collect::<Result<Vec<_>>>()
+            .context(format!("Failed to import the function {}", signature))?;
+        let returns = match outputs.as_slice() {
+            [] => None,
+            [output] => Some(output.clone()),
+            outputs => Some(format!("({})", outputs.join(", "))),
+        };
+
+        // older ABIs flag functions by `constant` and `payable` instead of `stateMutability`
+        let flag = |key: &str| item.get(key).and_then(Value::as_bool) == Some(true);
+        let mutability = item
+            .get("stateMutability")
+            .and_then(Value::as_str)
+            .unwrap_or(if flag("payable") {
+                "payable"
+            } else if flag("constant") {
+                "view"
+            } else {
+                "nonpayable"
+            });
+        messages.push(MessageSpec {
+            name: message_name,
+            docs: Some(format!(
+                "Imported from the Solidity function `{}`.",
+                signature
+            )),
+            mutates: mutability != "view" && mutability != "pure",
+            payable: mutability == "payable",
+            // ABIs exported from ink! contracts keep the selectors of their messages
+            selector: item
+                .get("selector")
+                .and_then(Value::as_str)
+                .map(ToString::to_string),
+            args,
+            returns,
+        });
+    }
+
+    let types = types
+        .structs
+        .values()
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok((
+        TraitSpec {
+            trait_name: None,
+            docs: Some("Imported from a Solidity ABI.".to_string()),
+            types: Some(types).filter(|types| !types.is_empty()),
+            messages,
+        },
+        notes,
+    ))
+}
+
+/// Returns the parameters `key` of a function, e.g. its `inputs`.
+fn params<'a>(item: &'a Value, key: &str) -> &'a [Value] {
+    item.get(key)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Returns the Solidity `name` as Rust identifier in snake case.
+fn identifier(name: &str) -> String {
+    let mut identifier = name.to_snake_case();
+    if RUST_KEYWORDS.contains(&identifier.as_str()) {
+        identifier.push('_');
+    }
+    identifier
+}
+
+/// The ink! types of the Solidity types of an ABI.
+#[derive(Default)]
+struct Types {
+    /// The definitions of the structs of the ABI by their names.
+    structs: BTreeMap<String, String>,
+}
+
+impl Types {
+    /// Returns the ink! type of the ABI parameter `param`, adding notes on narrowed integers.
+    fn ink_type(&mut self, param: &Value, notes: &mut Vec<String>) -> Result<String> {
+        let ty = param
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("A parameter has no type"))?;
+
+        if let Some(array) = ty.strip_suffix(']') {
+            let open = array
+                .rfind('[')
+                .ok_or_else(|| anyhow::anyhow!("Invalid array type {}", ty))?;
+            // the element type of e.g. `tuple[]` with the internal type `struct Pair[]`
+            let mut element = param.clone();
+            element["type"] = Value::from(&array[..open]);
+            if let Some(internal) = param.get("internalType").and_then(Value::as_str) {
+                let internal = internal
+                    .rfind('[')
+                    .map_or(internal, |open| &internal[..open]);
+                element["internalType"] = Value::from(internal);
+            }
+            let element = self.ink_type(&element, notes)?;
+            return match &array[open + 1..] {
+                "" => Ok(format!("Vec<{}>", element)),
+                len => {
+                    let len = len
+                        .parse::<usize>()
+                        .map_err(|_| anyhow::anyhow!("Invalid array type {}", ty))?;
+                    Ok(format!("[{}; {}]", element, len))
+                }
+            };
+        }
+
+        let ink_type = match ty {
+            "bool" => "bool".to_string(),
+            "address" => "AccountId".to_string(),
+            "string" => "String".to_string(),
+            "bytes" => "Vec<u8>".to_string(),
+            "tuple" => self.tuple(param, notes)?,
+            _ => {
+                if let Some(len) = ty.strip_prefix("bytes") {
+                    match len.parse::<usize>() {
+                        Ok(len) if (1..=32).contains(&len) => format!("[u8; {}]", len),
+                        _ => anyhow::bail!("Unsupported Solidity type {}", ty),
+                    }
+                } else if let Some(bits) = ty.strip_prefix("uint") {
+                    integer("u", bits, ty, notes)?
+                } else if let Some(bits) = ty.strip_prefix("int") {
+                    integer("i", bits, ty, notes)?
+                } else {
+                    anyhow::bail!("Unsupported Solidity type {}", ty)
+                }
+            }
+        };
+        Ok(ink_type)
+    }
+
+    /// Returns the struct of the `internalType` of the tuple `param`, or the Rust tuple of its
+    /// components without one.
+    fn tuple(&mut self, param: &Value, notes: &mut Vec<String>) -> Result<String> {
+        let components = params(param, "components");
+        let fields = components
+            .iter()
+            .map(|component| self.ink_type(component, notes))
+            .collect::<Result<Vec<_>>>()?;
+        let name = param
+            .get("internalType")
+            .and_then(Value::as_str)
+            .and_then(|internal| internal.strip_prefix("struct "))
+            // structs defined by a contract are qualified by its name, e.g. `Market.Order`
+            .and_then(|internal| internal.rsplit('.').next())
+            .map(|internal| internal.to_camel_case());
+        let name = match name {
+            Some(name) => name,
+            None if fields.len() == 1 => return Ok(format!("({},)", fields[0])),
+            None => return Ok(format!("({})", fields.join(", "))),
+        };
+        if !self.structs.contains_key(&name) {
+            let mut definition = format!("{}pub struct {} {{\n", TYPE_DERIVES, name);
+            for (index, (component, ty)) in components.iter().zip(&fields).enumerate() {
+                let field = component
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .filter(|field| !field.is_empty())
+                    .map_or(format!("field{}", index), identifier);
+                definition.push_str(&format!("    pub {}: {},\n", field, ty));
+            }
+            definition.push_str("}\n");
+            self.structs.insert(name.clone(), definition);
+        }
+        Ok(name)
+    }
+}
+
+/// Returns the Rust integer type with the `prefix` holding the Solidity integer of `bits` bits.
+fn integer(prefix: &str, bits: &str, ty: &str, notes: &mut Vec<String>) -> Result<String> {
+    // `uint` and `int` are aliases of `uint256` and `int256`
+    let bits = if bits.is_empty() {
+        256
+    } else {
+        bits.parse::<u32>()
+            .ok()
+            .filter(|bits| *bits > 0 && *bits <= 256 && bits % 8 == 0)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported Solidity type {}", ty))?
+    };
+    let rust_bits = [8, 16, 32, 64, 128]
+        .iter()
+        .copied()
+        .find(|rust_bits| bits <= *rust_bits)
+        .unwrap_or(128);
+    let rust_type = format!("{}{}", prefix, rust_bits);
+    let note = format!("narrowed {} to {}", ty, rust_type);
+    if bits > 128 && !notes.contains(&note) {
+        notes.push(note);
+    }
+    Ok(rust_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use serde_json::json;
+
+    fn token_abi() -> Value {
+        json!([
+            {
+                "type": "function",
+                "name": "balanceOf",
+                "stateMutability": "view",
+                "inputs": [{ "name": "owner", "type": "address" }],
+                "outputs": [{ "name": "", "type": "uint256" }]
+            },
+            {
+                "type": "function",
+                "name": "transfer",
+                "stateMutability": "nonpayable",
+                "inputs": [
+                    { "name": "to", "type": "address" },
+                    { "name": "value", "type": "uint256" }
+                ],
+                "outputs": [{ "name": "", "type": "bool" }]
+            },
+            {
+                "type": "function",
+                "name": "deposit",
+                "stateMutability": "payable",
+                "inputs": [],
+                "outputs": []
+            },
+            {
+                "type": "event",
+                "name": "Transfer",
+                "inputs": [{ "name": "from", "type": "address", "indexed": true }],
+                "anonymous": false
+            }
+        ])
+    }
+
+    #[test]
+    fn functions_are_imported_as_messages() {
+        // given
+        let abi = json!({ "contractName": "Token", "abi": token_abi() });
+
+        // when
+        let (spec, notes) = import(&abi).unwrap();
+
+        // then
+        let messages = spec
+            .messages
+            .iter()
+            .map(|message| {
+                let args = message
+                    .args
+                    .iter()
+                    .map(|arg| format!("{}: {}", arg.name, arg.ty))
+                    .collect::<Vec<_>>();
+                (
+                    message.name.as_str(),
+                    args.join(", "),
+                    message.returns.as_deref(),
+                    message.mutates,
+                    message.payable,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "balance_of",
+                    "owner: AccountId".to_string(),
+                    Some("u128"),
+                    false,
+                    false
+                ),
+                (
+                    "transfer",
+                    "to: AccountId, value: u128".to_string(),
+                    Some("bool"),
+                    true,
+                    false
+                ),
+                ("deposit", String::new(), None, true, true),
+            ]
+        );
+        assert_eq!(
+            spec.messages[1].docs.as_deref(),
+            Some("Imported from the Solidity function `transfer(address,uint256)`.")
+        );
+        assert_eq!(
+            notes,
+            vec![
+                "narrowed uint256 to u128",
+                "skipped the event Transfer, trait definitions contain messages only",
+            ]
+        );
+    }
+
+    #[test]
+    fn structs_arrays_and_overloads_are_imported() {
+        // given
+        let abi = json!([
+            {
+                "type": "function",
+                "name": "fill",
+                "inputs": [{
+                    "name": "orders",
+                    "type": "tuple[]",
+                    "internalType": "struct Market.Order[]",
+                    "components": [
+                        { "name": "maker", "type": "address" },
+                        { "name": "amount", "type": "uint64" }
+                    ]
+                }],
+                "outputs": [{ "name": "", "type": "bytes4" }, { "name": "", "type": "int24[2]" }]
+            },
+            {
+                "type": "function",
+                "name": "fill",
+                "inputs": [{ "name": "type", "type": "bytes" }],
+                "outputs": [],
+                "selector": "0xCAFEBABE"
+            }
+        ]);
+
+        // when
+        let (spec, notes) = import(&abi).unwrap();
+
+        // then
+        let fill = &spec.messages[0];
+        assert_eq!(fill.args[0].ty, "Vec<Order>");
+        assert_eq!(fill.returns.as_deref(), Some("([u8; 4], [i32; 2])"));
+        assert!(fill.mutates);
+        let types = spec.types.unwrap();
+        assert!(types
+            .ends_with("pub struct Order {\n    pub maker: AccountId,\n    pub amount: u64,\n}\n"));
+
+        let overload = &spec.messages[1];
+        assert_eq!(overload.name, "fill_2");
+        assert_eq!(overload.args[0].name, "type_");
+        assert_eq!(overload.selector.as_deref(), Some("0xCAFEBABE"));
+        assert_eq!(
+            notes,
+            vec!["imported the overloaded function fill(bytes) as fill_2"]
+        );
+    }
+
+    #[test]
+    fn unsupported_types_are_rejected() {
+        let abi = json!([{
+            "name": "price",
+            "inputs": [],
+            "outputs": [{ "name": "", "type": "fixed128x18" }]
+        }]);
+        let err = import(&abi).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to import the function price()");
+        assert_eq!(
+            err.root_cause().to_string(),
+            "Unsupported Solidity type fixed128x18"
+        );
+    }
+
+    #[test]
+    fn trait_and_call_wrapper_are_generated() {
+        with_tmp_dir(|path| {
+            // given
+            let abi = path.join("token.abi.json");
+            fs::write(&abi, serde_json::to_string(&token_abi())?)?;
+            let cmd = ImportAbiCommand {
+                name: None,
+                target_dir: Some(path.to_path_buf()),
+                path: abi,
+            };
+
+            // when
+            cmd.exec()?;
+
+            // then
+            let lib = fs::read_to_string(path.join("token").join("lib.rs"))?;
+            assert!(lib.contains("pub trait Token {\n"));
+            assert!(lib.contains(
+                "    #[ink(message)]\n    \
+                fn transfer(&mut self, to: AccountId, value: u128) -> bool;\n"
+            ));
+            assert!(lib.contains("pub struct TokenRef {\n    account_id: AccountId,\n}\n"));
+            assert!(lib.contains(
+                "    pub fn deposit(&self, transferred_value: Balance) -> ink_env::Result<()> {\n"
+            ));
+            assert!(lib.contains(".push_arg(to)\n                    .push_arg(value),\n"));
+            assert!(lib.contains(".returns::<ink_env::call::utils::ReturnType<bool>>()\n"));
+            assert!(path.join("token").join("stub").join("lib.rs").exists());
+            Ok(())
+        })
+    }
+}
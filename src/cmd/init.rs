@@ -0,0 +1,305 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::new::default_template_file;
+use crate::{
+    crate_metadata::CrateMetadata,
+    workspace::{Manifest, ManifestPath},
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::{convert::TryFrom, fs, path::PathBuf};
+use structopt::StructOpt;
+use toml::value;
+
+/// The attribute marking the module of an ink! contract.
+const CONTRACT_ATTRIBUTE: &str = "#[ink::contract]";
+
+/// Converts the crate into an ink! contract, keeping its existing code.
+///
+/// The `[lib]` crate types, the ink! dependencies and the `std` features are added to the
+/// Cargo.toml, the lib.rs is made `no_std` and gets a contract skeleton appended. If the
+/// resulting crate is not a valid contract crate, the original files are restored.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "init")]
+pub struct InitCommand {
+    /// Path to the Cargo.toml of the crate
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+}
+
+/// The changes of a conversion, together with the original files to restore on failure.
+struct Conversion {
+    lib_path: PathBuf,
+    original_manifest: String,
+    original_lib: Option<String>,
+    added_dependencies: Vec<String>,
+    /// Dependencies which enable their default features, which commonly require `std`.
+    std_dependencies: Vec<String>,
+    /// True if the contract skeleton was added to the lib.rs.
+    added_contract: bool,
+}
+
+impl Conversion {
+    /// Restores the original Cargo.toml and lib.rs.
+    fn restore(&self, manifest_path: &ManifestPath) -> Result<()> {
+        fs::write(manifest_path, &self.original_manifest)?;
+        match &self.original_lib {
+            Some(lib) => fs::write(&self.lib_path, lib)?,
+            None if self.lib_path.exists() => fs::remove_file(&self.lib_path)?,
+            None => (),
+        }
+        Ok(())
+    }
+}
+
+impl InitCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let conversion = convert(&manifest_path)?;
+        if let Err(err) = CrateMetadata::collect(&manifest_path) {
+            conversion.restore(&manifest_path)?;
+            return Err(err.context(
+                "The converted crate is not a valid contract, the original files were restored",
+            ));
+        }
+
+        for dependency in &conversion.std_dependencies {
+            eprintln!(
+                "{} the dependency {} enables its default features, set `default-features = \
+                false` and enable its `std` feature in the `std` feature of the contract if it \
+                supports `no_std`",
+                "warning:".yellow().bold(),
+                dependency
+            );
+        }
+        let mut summary = format!(
+            "Converted the crate at {} into an ink! contract",
+            manifest_path.as_ref().display()
+        );
+        if !conversion.added_dependencies.is_empty() {
+            summary.push_str(&format!(
+                "\n  added dependencies: {}",
+                conversion.added_dependencies.join(", ")
+            ));
+        }
+        if conversion.added_contract {
+            summary.push_str(&format!(
+                "\n  added a contract skeleton to {}, the existing code must compile without \
+                `std`",
+                conversion.lib_path.display()
+            ));
+        }
+        Ok(summary)
+    }
+}
+
+/// Adjusts the Cargo.toml and lib.rs of the crate at `manifest_path` to those of a contract.
+fn convert(manifest_path: &ManifestPath) -> Result<Conversion> {
+    let original_manifest = fs::read_to_string(manifest_path).context("Loading Cargo.toml")?;
+    let mut manifest = Manifest::new(manifest_path.clone())?;
+    let name = manifest.package_name()?.replace("-", "_");
+
+    let template: value::Table = toml::from_str(&default_template_file(&name, "Cargo.toml")?)?;
+    let section = |key: &str| -> Result<value::Table> {
+        template
+            .get(key)
+            .and_then(|section| section.as_table())
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("The template has no {} section", key))
+    };
+    let dependencies = section("dependencies")?;
+
+    manifest.with_crate_types(&["cdylib", "rlib"])?;
+    let added_dependencies = manifest.with_dependencies(&dependencies)?;
+    manifest.with_features(&section("features")?)?;
+    let std_dependencies = manifest
+        .dependencies_with_default_features()
+        .into_iter()
+        .filter(|dependency| !dependencies.contains_key(dependency))
+        .collect();
+
+    let lib_path = manifest_path
+        .absolute_directory()?
+        .join(manifest.lib_path().unwrap_or("src/lib.rs"));
+    let original_lib = if lib_path.exists() {
+        Some(fs::read_to_string(&lib_path)?)
+    } else {
+        None
+    };
+    let lib = contract_source(
+        original_lib.as_deref(),
+        &default_template_file(&name, "lib.rs")?,
+    );
+
+    manifest.write(manifest_path)?;
+    let added_contract = lib.is_some();
+    if let Some(lib) = lib {
+        if let Some(dir) = lib_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&lib_path, lib)?;
+    }
+
+    Ok(Conversion {
+        lib_path,
+        original_manifest,
+        original_lib,
+        added_dependencies,
+        std_dependencies,
+        added_contract,
+    })
+}
+
+/// Returns the `existing` source with the contract of the `template` around it, or `None` if it
+/// already contains a contract.
+///
+/// The crate is made `no_std` unless compiled with the `std` feature, the contract module is
+/// appended after the existing code.
+fn contract_source(existing: Option<&str>, template: &str) -> Option<String> {
+    let existing = match existing {
+        Some(existing) if existing.contains(CONTRACT_ATTRIBUTE) => return None,
+        Some(existing) => existing,
+        None => return Some(template.to_string()),
+    };
+    let contract = &template[template.find(CONTRACT_ATTRIBUTE)?..];
+
+    // inner attributes and doc comments must stay at the beginning of the crate
+    let header_len = existing
+        .lines()
+        .take_while(|line| {
+            let line = line.trim();
+            line.is_empty() || line.starts_with("//!") || line.starts_with("#![")
+        })
+        .map(|line| line.len() + 1)
+        .sum::<usize>()
+        .min(existing.len());
+    let (header, code) = existing.split_at(header_len);
+
+    let mut source = header.trim_end().to_string();
+    if !source.is_empty() {
+        source.push('\n');
+    }
+    if !existing.contains("no_std") {
+        source.push_str("#![cfg_attr(not(feature = \"std\"), no_std)]\n");
+    }
+    if !existing.contains("use ink_lang as ink;") {
+        source.push_str("\nuse ink_lang as ink;\n");
+    }
+    let code = code.trim();
+    if !code.is_empty() {
+        source.push('\n');
+        source.push_str(code);
+        source.push('\n');
+    }
+    source.push('\n');
+    source.push_str(contract);
+    Some(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use std::path::Path;
+
+    const LIB_RS: &str = "//! Arithmetic.\n#![deny(missing_docs)]\n\n\
+        /// Adds two numbers.\npub fn add(a: u32, b: u32) -> u32 {\n    a + b\n}\n";
+
+    /// Returns the manifest of a plain library crate `name` in `dir`, with the `lib_rs`.
+    fn library_crate(dir: &Path, name: &str, lib_rs: &str) -> Result<ManifestPath> {
+        fs::create_dir_all(dir.join("src"))?;
+        fs::write(
+            dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n\
+                [dependencies]\nserde = \"1.0\"\n",
+                name
+            ),
+        )?;
+        fs::write(dir.join("src/lib.rs"), lib_rs)?;
+        ManifestPath::new(dir.join("Cargo.toml"))
+    }
+
+    #[test]
+    fn crate_is_converted_into_a_contract() {
+        with_tmp_dir(|path| {
+            // given
+            let manifest_path = library_crate(path, "my-lib", LIB_RS)?;
+
+            // when
+            let conversion = convert(&manifest_path)?;
+
+            // then
+            let manifest: value::Table = toml::from_str(&fs::read_to_string(&manifest_path)?)?;
+            let crate_types = manifest["lib"]["crate-type"].as_array().unwrap();
+            assert_eq!(crate_types.len(), 2);
+            assert!(crate_types.contains(&"cdylib".into()));
+            assert!(manifest["dependencies"].get("ink_lang").is_some());
+            assert!(manifest["dependencies"].get("serde").is_some());
+            assert!(manifest["features"]["std"]
+                .as_array()
+                .unwrap()
+                .contains(&"ink_env/std".into()));
+            assert!(conversion.added_dependencies.contains(&"scale".to_string()));
+            assert_eq!(conversion.std_dependencies, vec!["serde".to_string()]);
+
+            let lib = fs::read_to_string(path.join("src/lib.rs"))?;
+            assert!(lib.starts_with(
+                "//! Arithmetic.\n#![deny(missing_docs)]\n\
+                #![cfg_attr(not(feature = \"std\"), no_std)]\n\nuse ink_lang as ink;\n"
+            ));
+            assert!(lib.contains("pub fn add(a: u32, b: u32) -> u32"));
+            assert!(lib.contains("#[ink::contract]\nmod my_lib {"));
+            assert!(lib.contains("pub struct MyLib {"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn existing_contracts_are_kept() {
+        with_tmp_dir(|path| {
+            // given
+            let template = default_template_file("flipper", "lib.rs")?;
+            let manifest_path = library_crate(path, "flipper", &template)?;
+
+            // when
+            let conversion = convert(&manifest_path)?;
+
+            // then
+            assert!(!conversion.added_contract);
+            assert_eq!(fs::read_to_string(path.join("src/lib.rs"))?, template);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn original_files_are_restored() {
+        with_tmp_dir(|path| {
+            // given
+            let manifest_path = library_crate(path, "my-lib", LIB_RS)?;
+            let original_manifest = fs::read_to_string(&manifest_path)?;
+
+            // when
+            convert(&manifest_path)?.restore(&manifest_path)?;
+
+            // then
+            assert_eq!(fs::read_to_string(&manifest_path)?, original_manifest);
+            assert_eq!(fs::read_to_string(path.join("src/lib.rs"))?, LIB_RS);
+            Ok(())
+        })
+    }
+}
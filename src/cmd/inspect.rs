@@ -0,0 +1,218 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{metadata, trait_definition::contract_of, verify_metadata::label};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde_json::Value;
+use std::{convert::TryFrom, fmt::Write, fs, path::PathBuf};
+use structopt::StructOpt;
+
+/// Prints the constructors and messages of a contract, i.e. a human-readable view of its ABI.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "inspect")]
+pub struct InspectCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Path to the metadata or the `<name>.contract` bundle, defaults to
+    /// `./target/ink/metadata.json`
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+}
+
+impl InspectCommand {
+    pub fn exec(&self) -> Result<String> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+                metadata::artifact_paths(&crate_metadata).dest_metadata
+            }
+        };
+        let contents = fs::read(&path).context(format!(
+            "Failed to read the metadata {}, build the contract first",
+            path.display()
+        ))?;
+        let metadata: Value = serde_json::from_slice(&contents)
+            .context(format!("The metadata {} is no valid JSON", path.display()))?;
+        let spec = contract_of(&metadata)
+            .get("spec")
+            .ok_or_else(|| anyhow::anyhow!("The metadata contains no contract spec"))?;
+
+        let mut out = String::new();
+        for (kind, title) in &[("constructors", "Constructors"), ("messages", "Messages")] {
+            let items = spec
+                .get(*kind)
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            writeln!(out, "{}", title.bold())?;
+            write_callables(&mut out, items)?;
+        }
+        Ok(out.trim_end().to_string())
+    }
+}
+
+/// Writes a row for each of the constructors or messages, followed by their doc comments.
+fn write_callables(out: &mut String, items: &[Value]) -> Result<()> {
+    let rows = items
+        .iter()
+        .map(|item| {
+            let label = label(item).unwrap_or_else(|| "<unnamed>".to_string());
+            let selector = item
+                .get("selector")
+                .and_then(Value::as_str)
+                .unwrap_or("<none>")
+                .to_string();
+            (label, selector, flags(item), signature(item))
+        })
+        .collect::<Vec<_>>();
+    let label_width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+    let flags_width = rows.iter().map(|row| row.2.len()).max().unwrap_or(0);
+
+    for (item, (label, selector, flags, signature)) in items.iter().zip(&rows) {
+        let mut row = format!("  {:width$}  {}", label, selector, width = label_width);
+        // the flags column is omitted if none of the items has any flags
+        if flags_width > 0 {
+            write!(row, "  {:width$}", flags, width = flags_width)?;
+        }
+        writeln!(out, "{}  {}", row, signature)?;
+        let docs = item
+            .get("docs")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for doc in docs.iter().filter_map(Value::as_str) {
+            writeln!(out, "      {}", doc.trim())?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the mutability and payability of a message, or the payability of a constructor.
+fn flags(item: &Value) -> String {
+    let mut flags = Vec::new();
+    if item.get("mutates").and_then(Value::as_bool) == Some(true) {
+        flags.push("mut");
+    }
+    if item.get("payable").and_then(Value::as_bool) == Some(true) {
+        flags.push("payable");
+    }
+    flags.join(", ")
+}
+
+/// Returns the arguments and the return type, e.g. `(value: Balance) -> bool`.
+fn signature(item: &Value) -> String {
+    let args = item
+        .get("args")
+        .and_then(Value::as_array)
+        .map(|args| {
+            args.iter()
+                .map(|arg| {
+                    format!(
+                        "{}: {}",
+                        label(arg).unwrap_or_else(|| "_".to_string()),
+                        type_name(arg.get("type"))
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let mut signature = format!("({})", args.join(", "));
+    if let Some(return_type) = item.get("returnType").filter(|ty| !ty.is_null()) {
+        write!(signature, " -> {}", type_name(Some(return_type))).expect("writing to a string");
+    }
+    signature
+}
+
+/// Returns the display name of a type, falling back to its id in the type registry.
+fn type_name(ty: Option<&Value>) -> String {
+    let ty = match ty {
+        Some(ty) => ty,
+        None => return "?".to_string(),
+    };
+    let display_name = ty
+        .get("displayName")
+        .and_then(Value::as_array)
+        .map(|segments| {
+            segments
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("::")
+        })
+        .filter(|name| !name.is_empty());
+    match (display_name, ty.get("type")) {
+        (Some(name), _) => name,
+        (None, Some(id)) => format!("#{}", id),
+        (None, None) => "?".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use serde_json::json;
+
+    #[test]
+    fn constructors_and_messages_are_listed() {
+        with_tmp_dir(|path| {
+            // given
+            let metadata = json!({
+                "V1": { "spec": {
+                    "constructors": [{
+                        "name": ["new"], "selector": "0x9bae9d5e", "payable": false,
+                        "args": [{ "name": "init_value", "type": { "type": 0, "displayName": ["bool"] } }],
+                        "docs": [" Creates a new flipper."]
+                    }],
+                    "messages": [
+                        { "name": ["flip"], "selector": "0x633aa551", "mutates": true,
+                          "payable": false, "args": [], "returnType": null, "docs": [] },
+                        { "name": ["get"], "selector": "0x2f865bd9", "mutates": false,
+                          "payable": false, "args": [],
+                          "returnType": { "type": 0, "displayName": ["bool"] }, "docs": [] }
+                    ]
+                } }
+            });
+            let metadata_path = path.join("metadata.json");
+            fs::write(&metadata_path, metadata.to_string())?;
+            let cmd = InspectCommand::from_iter(&["inspect", metadata_path.to_str().unwrap()]);
+
+            // when
+            let output = cmd.exec()?;
+
+            // then
+            assert_eq!(
+                output,
+                format!(
+                    "{}\n  \
+                    new  0x9bae9d5e  (init_value: bool)\n      \
+                    Creates a new flipper.\n\
+                    {}\n  \
+                    flip  0x633aa551  mut  ()\n  \
+                    get   0x2f865bd9       () -> bool",
+                    "Constructors".bold(),
+                    "Messages".bold()
+                )
+            );
+            Ok(())
+        })
+    }
+}
@@ -15,8 +15,13 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use anyhow::Result;
-use subxt::{balances::Balances, contracts::*, system::System, ClientBuilder, DefaultNodeRuntime};
+use serde_json::json;
+use sp_core::crypto::Ss58Codec;
+use subxt::{
+    balances::Balances, contracts::*, system::System, ClientBuilder, DefaultNodeRuntime, Signer,
+};
 
+use super::receipt::{Receipt, ReceiptOpts};
 use crate::{ExtrinsicOpts, HexData};
 
 /// Instantiate a contract stored at the supplied code hash.
@@ -26,6 +31,7 @@ use crate::{ExtrinsicOpts, HexData};
 /// the `ContractsEvent::Instantiated` event.
 pub(crate) fn execute_instantiate(
     extrinsic_opts: &ExtrinsicOpts,
+    receipt_opts: &ReceiptOpts,
     endowment: <DefaultNodeRuntime as Balances>::Balance,
     gas_limit: u64,
     code_hash: <DefaultNodeRuntime as System>::Hash,
@@ -33,18 +39,30 @@ pub(crate) fn execute_instantiate(
 ) -> Result<<DefaultNodeRuntime as System>::AccountId> {
     async_std::task::block_on(async move {
         let cli = ClientBuilder::<DefaultNodeRuntime>::new()
-            .set_url(&extrinsic_opts.url.to_string())
+            .set_url(&extrinsic_opts.url()?.to_string())
             .build()
             .await?;
         let signer = extrinsic_opts.signer()?;
 
         let events = cli
-            .instantiate_and_watch(&signer, endowment, gas_limit, &code_hash, &data.0)
+            .instantiate_and_watch(signer.as_ref(), endowment, gas_limit, &code_hash, &data.0)
             .await?;
         let instantiated = events
             .instantiated()?
             .ok_or(anyhow::anyhow!("Failed to find Instantiated event"))?;
 
+        let receipt = Receipt {
+            command: "instantiate",
+            signer: signer.account_id().clone(),
+            inputs: json!({
+                "codeHash": format!("{:?}", code_hash),
+                "endowment": endowment.to_string(),
+                "data": format!("0x{}", hex::encode(&data.0)),
+            }),
+            result: json!({ "contract": instantiated.contract.to_ss58check() }),
+            gas_limit: Some(gas_limit),
+        };
+        receipt.write(receipt_opts, extrinsic_opts, &events, None)?;
         Ok(instantiated.contract)
     })
 }
@@ -53,8 +71,14 @@ pub(crate) fn execute_instantiate(
 mod tests {
     use std::{fs, io::Write};
 
-    use crate::{cmd::deploy::execute_deploy, util::tests::with_tmp_dir, ExtrinsicOpts, HexData};
+    use crate::{
+        cmd::{deploy::execute_deploy, ReceiptOpts},
+        rpc_fixture,
+        util::tests::with_tmp_dir,
+        ExtrinsicOpts, HexData,
+    };
     use assert_matches::assert_matches;
+    use structopt::StructOpt;
 
     const CONTRACT: &str = r#"
 (module
@@ -64,35 +88,40 @@ mod tests {
 "#;
 
     #[test]
-    #[ignore] // depends on a local substrate node running
+    #[ignore] // its RPC fixture is yet to be recorded, see `rpc_fixture::with_fixture`
     fn instantiate_contract() {
-        with_tmp_dir(|path| {
-            let wasm = wabt::wat2wasm(CONTRACT).expect("invalid wabt");
+        rpc_fixture::with_fixture("instantiate_contract.json", || {
+            with_tmp_dir(|path| {
+                let wasm = wabt::wat2wasm(CONTRACT).expect("invalid wabt");
 
-            let wasm_path = path.join("test.wasm");
-            let mut file = fs::File::create(&wasm_path).unwrap();
-            let _ = file.write_all(&wasm);
+                let wasm_path = path.join("test.wasm");
+                let mut file = fs::File::create(&wasm_path).unwrap();
+                let _ = file.write_all(&wasm);
 
-            let url = url::Url::parse("ws://localhost:9944").unwrap();
-            let extrinsic_opts = ExtrinsicOpts {
-                url,
-                suri: "//Alice".into(),
-                password: None,
-            };
-            let code_hash =
-                execute_deploy(&extrinsic_opts, Some(&wasm_path)).expect("Deploy should succeed");
+                let extrinsic_opts = ExtrinsicOpts::from_iter(&[
+                    "",
+                    "--url",
+                    "ws://localhost:9944",
+                    "--suri",
+                    "//Alice",
+                ]);
+                let receipt_opts = ReceiptOpts::default();
+                let code_hash = execute_deploy(&extrinsic_opts, &receipt_opts, Some(&wasm_path))
+                    .expect("Deploy should succeed");
 
-            let gas_limit = 500_000_000;
-            let result = super::execute_instantiate(
-                &extrinsic_opts,
-                100000000000000,
-                gas_limit,
-                code_hash,
-                HexData::default(),
-            );
+                let gas_limit = 500_000_000;
+                let result = super::execute_instantiate(
+                    &extrinsic_opts,
+                    &receipt_opts,
+                    100000000000000,
+                    gas_limit,
+                    code_hash,
+                    HexData::default(),
+                );
 
-            assert_matches!(result, Ok(_));
-            Ok(())
+                assert_matches!(result, Ok(_));
+                Ok(())
+            })
         })
     }
 }
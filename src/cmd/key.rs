@@ -0,0 +1,331 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use anyhow::Result;
+use colored::Colorize;
+use sp_core::{
+    crypto::{AccountId32, Pair, Ss58Codec},
+    sr25519,
+};
+use structopt::StructOpt;
+use zeroize::Zeroizing;
+
+use crate::secret::SecretOpts;
+
+/// Utilities for the keys of contract deployers.
+#[derive(Debug, StructOpt)]
+pub enum KeyCommand {
+    /// List the addresses of the accounts derived from a secret key URI
+    #[structopt(name = "derive")]
+    Derive(DeriveCommand),
+    /// Generate a key whose address matches a pattern
+    #[structopt(name = "vanity")]
+    Vanity(VanityCommand),
+}
+
+impl KeyCommand {
+    pub fn exec(&self) -> Result<String> {
+        match self {
+            KeyCommand::Derive(derive) => derive.exec(),
+            KeyCommand::Vanity(vanity) => vanity.exec(),
+        }
+    }
+}
+
+/// Enumerates the addresses of accounts derived from a root secret key URI.
+///
+/// Helps to locate which derived account actually holds funds or owns a contract.
+#[derive(Debug, StructOpt)]
+pub struct DeriveCommand {
+    #[structopt(flatten)]
+    secret: SecretOpts,
+    /// Number of indexed derivation paths `//0` to `//<count - 1>` to enumerate
+    #[structopt(name = "count", long, short = "n", default_value = "10")]
+    count: u32,
+    /// Use soft instead of hard junctions for the indexed paths, i.e. `/0` instead of `//0`
+    #[structopt(long)]
+    soft: bool,
+    /// Custom derivation paths to enumerate in addition, e.g. `//stash` or `//1/2`
+    #[structopt(name = "path", long)]
+    paths: Vec<String>,
+}
+
+impl DeriveCommand {
+    pub fn exec(&self) -> Result<String> {
+        let paths = self.paths()?;
+        let pairs = self.secret.derive_pairs(&paths)?;
+        let width = paths.iter().map(String::len).max().unwrap_or_default();
+        let lines = paths
+            .iter()
+            .zip(pairs)
+            .map(|(path, pair)| {
+                let account = AccountId32::from(pair.public().0);
+                format!("{:width$}  {}", path, account.to_ss58check(), width = width)
+            })
+            .collect::<Vec<_>>();
+        Ok(format!(
+            "{}\n\t{}",
+            "Derived accounts:".bold(),
+            lines.join("\n\t")
+        ))
+    }
+
+    /// Returns the indexed derivation paths followed by the custom ones.
+    fn paths(&self) -> Result<Vec<String>> {
+        let separator = if self.soft { "/" } else { "//" };
+        let mut paths = (0..self.count)
+            .map(|index| format!("{}{}", separator, index))
+            .collect::<Vec<_>>();
+        for path in &self.paths {
+            if !path.starts_with('/') {
+                anyhow::bail!(
+                    "Invalid derivation path '{}', junctions must start with `/` or `//`",
+                    path
+                )
+            }
+            paths.push(path.clone());
+        }
+        Ok(paths)
+    }
+}
+
+/// Characters of the base58 alphabet used by SS58 addresses.
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Characters which follow the first character `5` of the addresses of the generic SS58 prefix
+/// 42, as the 32 bytes of a public key only span this range.
+const SECOND_CHARACTERS: &str = "CDEFGH";
+
+/// Generates random keys until the address of one matches a pattern.
+///
+/// The first character of an address is determined by the network prefix, so the pattern is
+/// matched against the characters following it. Every additional character of the pattern
+/// multiplies the expected number of attempts by about 58, so keep patterns short.
+#[derive(Debug, StructOpt)]
+pub struct VanityCommand {
+    /// The characters the address should start with, following the network specific first
+    /// character. The first of them is one of `C` to `H`
+    #[structopt(name = "pattern", long)]
+    pattern: String,
+    /// Match the pattern case-insensitively
+    #[structopt(long)]
+    case_insensitive: bool,
+    /// Number of threads to generate keys on, defaults to the number of CPUs
+    #[structopt(long)]
+    threads: Option<usize>,
+}
+
+impl VanityCommand {
+    pub fn exec(&self) -> Result<String> {
+        self.validate_pattern()?;
+        let threads = self.threads.unwrap_or_else(num_cpus::get).max(1);
+        log::info!(
+            "Generating keys matching '{}' on {} threads",
+            self.pattern,
+            threads
+        );
+
+        let found = Arc::new(AtomicBool::new(false));
+        let attempts = Arc::new(AtomicU64::new(0));
+        let (sender, receiver) = mpsc::channel();
+        let workers = (0..threads)
+            .map(|_| {
+                let found = found.clone();
+                let attempts = attempts.clone();
+                let sender = sender.clone();
+                let matcher = self.matcher();
+                thread::spawn(move || {
+                    while !found.load(Ordering::Relaxed) {
+                        let (pair, seed) = sr25519::Pair::generate();
+                        let seed = Zeroizing::new(seed);
+                        attempts.fetch_add(1, Ordering::Relaxed);
+                        let address = AccountId32::from(pair.public().0).to_ss58check();
+                        if matcher(&address) && !found.swap(true, Ordering::Relaxed) {
+                            let _ = sender.send((address, format!("0x{}", hex::encode(&*seed))));
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(sender);
+
+        let (address, seed) = receiver
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Key generation stopped without a match"))?;
+        let seed = Zeroizing::new(seed);
+        for worker in workers {
+            worker
+                .join()
+                .map_err(|_| anyhow::anyhow!("Key generation thread panicked"))?;
+        }
+
+        Ok(format!(
+            "{} {}\n\t{} {}\n\t{} {}\n\t{}",
+            "Address:".bold(),
+            address,
+            "Secret seed:".bold(),
+            seed.as_str(),
+            "Attempts:".bold(),
+            attempts.load(Ordering::Relaxed),
+            "Store the secret seed securely, it grants full control over the account."
+                .bright_yellow()
+                .bold()
+        ))
+    }
+
+    /// Ensures the pattern can be matched by an SS58 address at all.
+    fn validate_pattern(&self) -> Result<()> {
+        if self.pattern.is_empty() {
+            anyhow::bail!("The pattern must not be empty")
+        }
+        if let Some(invalid) = self.pattern.chars().find(|c| !BASE58_ALPHABET.contains(*c)) {
+            anyhow::bail!(
+                "The pattern contains '{}', which never occurs in an address. \
+                Addresses don't contain `0`, `O`, `I` and `l`.",
+                invalid
+            )
+        }
+        let first = self
+            .pattern
+            .chars()
+            .next()
+            .expect("the pattern is not empty");
+        let reachable = SECOND_CHARACTERS
+            .chars()
+            .any(|c| c == first || (self.case_insensitive && c.eq_ignore_ascii_case(&first)));
+        if !reachable {
+            anyhow::bail!(
+                "The pattern starts with '{}', which never follows the first character of an \
+                address. Patterns start with one of {}.",
+                first,
+                SECOND_CHARACTERS
+                    .chars()
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        Ok(())
+    }
+
+    /// Returns a function matching an address against the pattern.
+    fn matcher(&self) -> impl Fn(&str) -> bool {
+        let case_insensitive = self.case_insensitive;
+        let pattern = if case_insensitive {
+            self.pattern.to_lowercase()
+        } else {
+            self.pattern.clone()
+        };
+        move |address: &str| {
+            let candidate = &address[1..];
+            if case_insensitive {
+                candidate.to_lowercase().starts_with(&pattern)
+            } else {
+                candidate.starts_with(&pattern)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enumerates_indexed_and_custom_paths() {
+        let cmd = DeriveCommand::from_iter(&[
+            "derive", "--suri", "//Alice", "-n", "2", "--path", "//stash",
+        ]);
+        assert_eq!(cmd.paths().unwrap(), vec!["//0", "//1", "//stash"]);
+
+        let cmd = DeriveCommand::from_iter(&["derive", "--suri", "//Alice", "-n", "1", "--soft"]);
+        assert_eq!(cmd.paths().unwrap(), vec!["/0"]);
+    }
+
+    #[test]
+    fn derived_addresses_match_full_secret_uri() {
+        // given
+        let cmd = DeriveCommand::from_iter(&[
+            "derive", "--suri", "//Alice", "-n", "0", "--path", "//stash",
+        ]);
+        let expected = sp_core::sr25519::Pair::from_string("//Alice//stash", None).unwrap();
+
+        // when
+        let output = cmd.exec().expect("deriving accounts failed");
+
+        // then
+        let expected = AccountId32::from(expected.public().0).to_ss58check();
+        assert!(output.contains(&expected));
+    }
+
+    #[test]
+    fn vanity_pattern_is_matched_after_first_character() {
+        let cmd = VanityCommand::from_iter(&["vanity", "--pattern", "Grw"]);
+        let matcher = cmd.matcher();
+        assert!(matcher("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"));
+        assert!(!matcher("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty"));
+
+        let cmd = VanityCommand::from_iter(&["vanity", "--pattern", "grw", "--case-insensitive"]);
+        assert!(cmd.matcher()(
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        ));
+    }
+
+    #[test]
+    fn vanity_rejects_characters_outside_of_base58() {
+        let cmd = VanityCommand::from_iter(&["vanity", "--pattern", "0x"]);
+        assert!(cmd.validate_pattern().is_err());
+    }
+
+    #[test]
+    fn vanity_rejects_unreachable_first_characters() {
+        let cmd = VanityCommand::from_iter(&["vanity", "--pattern", "a"]);
+        assert!(cmd.validate_pattern().is_err());
+
+        let cmd = VanityCommand::from_iter(&["vanity", "--pattern", "g", "--case-insensitive"]);
+        assert!(cmd.validate_pattern().is_ok());
+    }
+
+    #[test]
+    fn vanity_generates_matching_key() {
+        // given
+        let cmd = VanityCommand::from_iter(&["vanity", "--pattern", "D", "--threads", "2"]);
+
+        // when
+        let output = cmd.exec().expect("generating key failed");
+
+        // then
+        let address = output
+            .split_whitespace()
+            .nth(1)
+            .expect("output must contain the address");
+        assert!(cmd.matcher()(address));
+    }
+
+    #[test]
+    fn rejects_paths_without_junction() {
+        let cmd = DeriveCommand::from_iter(&["derive", "--suri", "//Alice", "--path", "stash"]);
+        assert!(cmd.paths().is_err());
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Static checks of ink! contracts via [dylint](https://github.com/trailofbits/dylint).
+//!
+//! The lints are compiler plugins in dynamic libraries, which `cargo dylint` builds with the
+//! toolchain they require and runs as part of a `cargo check` of the contract. cargo-contract
+//! pins the ink! linting libraries, so that every version checks contracts with the same lints,
+//! e.g. for value-receiving constructors without `payable`, non-deterministic APIs and
+//! unbounded iteration over storage.
+
+use crate::{crate_metadata::CrateMetadata, toolchain, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use std::{
+    convert::TryFrom,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+/// The repository of the ink! linting libraries.
+const LINTS_GIT: &str = "https://github.com/paritytech/ink";
+
+/// The tag of the ink! linting libraries pinned by this version of cargo-contract.
+const LINTS_TAG: &str = "v5.0.0";
+
+/// The paths of the linting libraries within the repository.
+const LINTS_PATTERNS: [&str; 2] = ["linting/mandatory", "linting/extra"];
+
+/// Runs the ink! lints on the contract.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "lint")]
+pub struct LintCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Path to a local checkout of the linting libraries, replacing the pinned ones
+    #[structopt(long, parse(from_os_str))]
+    lints_path: Option<PathBuf>,
+    /// Fail on warnings of the lints, not only on errors
+    #[structopt(long)]
+    deny_warnings: bool,
+}
+
+impl LintCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+        execute(
+            &crate_metadata,
+            self.lints_path.as_deref(),
+            self.deny_warnings,
+        )?;
+        Ok("The contract passed the lints".into())
+    }
+}
+
+/// Runs the lints on the contract, from the libraries at `lints_path` or the pinned ones.
+///
+/// Fails if `cargo dylint` is missing, or if a lint reports an error, or any finding with
+/// `deny_warnings`.
+pub(crate) fn execute(
+    crate_metadata: &CrateMetadata,
+    lints_path: Option<&Path>,
+    deny_warnings: bool,
+) -> Result<()> {
+    let cargo = || toolchain::cargo_command(crate_metadata.toolchain.as_deref());
+    let installed = matches!(
+        cargo().args(["dylint", "--version"]).output(),
+        Ok(output) if output.status.success()
+    );
+    if !installed {
+        anyhow::bail!(
+            "Linting requires cargo-dylint, install it by `cargo install cargo-dylint dylint-link`"
+        )
+    }
+
+    let mut dylint = cargo();
+    dylint.args(lint_args(lints_path));
+    dylint
+        .arg("--manifest-path")
+        .arg(crate_metadata.manifest_path.as_ref());
+    if deny_warnings {
+        dylint.env("DYLINT_RUSTFLAGS", "-D warnings");
+    }
+    log::info!("Invoking {:?}", dylint);
+    let status = dylint
+        .status()
+        .context(format!("Error executing `{:?}`", dylint))?;
+    if !status.success() {
+        anyhow::bail!(
+            "The lints found problems in the contract, exit code: {:?}",
+            status.code()
+        )
+    }
+    Ok(())
+}
+
+/// Returns the arguments of `cargo` running all lints of the libraries at `lints_path`, or of
+/// the pinned ones.
+fn lint_args(lints_path: Option<&Path>) -> Vec<String> {
+    let mut args = vec!["dylint".to_string()];
+    match lints_path {
+        Some(path) => {
+            args.push("--path".into());
+            args.push(path.display().to_string());
+        }
+        None => {
+            args.extend(vec![
+                "--git".into(),
+                LINTS_GIT.into(),
+                "--tag".into(),
+                LINTS_TAG.into(),
+            ]);
+            for pattern in &LINTS_PATTERNS {
+                args.push("--pattern".into());
+                args.push(pattern.to_string());
+            }
+        }
+    }
+    args.push("--all".into());
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_lints_are_run_by_default() {
+        assert_eq!(
+            lint_args(None).join(" "),
+            "dylint --git https://github.com/paritytech/ink --tag v5.0.0 \
+            --pattern linting/mandatory --pattern linting/extra --all"
+        );
+    }
+
+    #[test]
+    fn local_lints_replace_the_pinned_ones() {
+        assert_eq!(
+            lint_args(Some(Path::new("../ink/linting"))).join(" "),
+            "dylint --path ../ink/linting --all"
+        );
+    }
+}
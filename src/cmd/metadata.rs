@@ -16,27 +16,37 @@
 
 use crate::{
     crate_metadata::CrateMetadata,
-    maybe_println, util,
+    maybe_println, toolchain, util, verifiable,
     workspace::{ManifestPath, Workspace},
-    UnstableFlags, Verbosity,
+    BuildArtifacts, OptimizationResult, UnstableFlags, Verbosity,
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use blake2::digest::{Update as _, VariableOutput as _};
 use colored::Colorize;
 use contract_metadata::{
     CodeHash, Compiler, Contract, ContractMetadata, Language, Source, SourceCompiler,
     SourceLanguage, SourceWasm, User,
 };
+use impl_serde::serialize as serde_hex;
 use semver::Version;
+use serde_json::{Map, Value};
 use std::{
+    ffi::OsStr,
     fs,
     path::{Path, PathBuf},
 };
 use url::Url;
+use walkdir::WalkDir;
 
 const METADATA_FILE: &str = "metadata.json";
 
+/// The compression of the wasm inside compressed bundles, recorded in `source.compression`.
+pub(crate) const WASM_COMPRESSION: &str = "zstd";
+
+/// The zstd level the wasm inside compressed bundles is compressed with.
+const COMPRESSION_LEVEL: i32 = 19;
+
 /// Metadata generation result.
 pub struct MetadataResult {
     /// Path to the resulting metadata file.
@@ -55,73 +65,144 @@ struct ExtendedMetadataResult {
 /// Generates a file with metadata describing the ABI of the smart-contract.
 ///
 /// It does so by generating and invoking a temporary workspace member.
+///
+/// Writes the metadata and the bundle as requested by `build_artifact`. Without an
+/// `optimization_result`, i.e. if the Wasm was not built, the metadata contains no code hash.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn execute(
     crate_metadata: &CrateMetadata,
-    final_contract_wasm: &Path,
+    optimization_result: Option<&OptimizationResult>,
     verbosity: Verbosity,
-    total_steps: usize,
+    build_artifact: BuildArtifacts,
     unstable_options: &UnstableFlags,
+    features: &[String],
+    no_default_features: bool,
+    compress_bundle: bool,
 ) -> Result<MetadataResult> {
-    util::assert_channel()?;
+    util::assert_channel(crate_metadata.toolchain.as_deref())?;
 
-    let target_directory = crate_metadata.target_directory.clone();
-    let out_path_metadata = target_directory.join(METADATA_FILE);
-
-    let fname_bundle = format!("{}.contract", crate_metadata.package_name);
-    let out_path_bundle = target_directory.join(fname_bundle);
+    let MetadataResult {
+        dest_metadata: out_path_metadata,
+        dest_bundle: out_path_bundle,
+    } = artifact_paths(crate_metadata);
 
     // build the extended contract project metadata
     let ExtendedMetadataResult {
         source,
         contract,
         user,
-    } = extended_metadata(crate_metadata, final_contract_wasm)?;
+    } = extended_metadata(crate_metadata, optimization_result)?;
 
-    let generate_metadata = |manifest_path: &ManifestPath| -> Result<()> {
-        let mut current_progress = 4;
-        maybe_println!(
-            verbosity,
-            " {} {}",
-            format!("[{}/{}]", current_progress, total_steps).bold(),
-            "Generating metadata".bright_green().bold()
-        );
+    let total_steps = build_artifact.steps();
+    let mut current_progress = total_steps;
+    if build_artifact.includes_metadata() && build_artifact.includes_bundle() {
+        current_progress -= 1;
+    }
+    let first_step = if build_artifact.includes_metadata() {
+        "Generating metadata"
+    } else {
+        "Generating bundle"
+    };
+    maybe_println!(
+        verbosity,
+        " {} {}",
+        format!("[{}/{}]", current_progress, total_steps).bold(),
+        first_step.bright_green().bold()
+    );
+    let ink_meta = generate_ink_metadata(
+        crate_metadata,
+        verbosity,
+        unstable_options,
+        features,
+        no_default_features,
+    )?;
+
+    let metadata = ContractMetadata::new(source, contract, user, ink_meta);
+    if build_artifact.includes_metadata() {
+        let mut metadata = metadata.clone();
+        metadata.remove_source_wasm_attribute();
+        let mut metadata = serde_json::to_value(&metadata)?;
+        if optimization_result.is_none() {
+            if let Some(source) = metadata.get_mut("source").and_then(Value::as_object_mut) {
+                source.remove("hash");
+            }
+        }
+        let contents = serde_json::to_string_pretty(&metadata)?;
+        fs::write(&out_path_metadata, contents)?;
+        current_progress += 1;
+    }
+    if build_artifact.includes_bundle() {
+        if build_artifact.includes_metadata() {
+            maybe_println!(
+                verbosity,
+                " {} {}",
+                format!("[{}/{}]", current_progress, total_steps).bold(),
+                "Generating bundle".bright_green().bold()
+            );
+        }
+        let mut bundle = serde_json::to_value(&metadata)?;
+        if compress_bundle {
+            compress_source_wasm(&mut bundle)?;
+        }
+        let contents = serde_json::to_string(&bundle)?;
+        fs::write(&out_path_bundle, contents)?;
+    }
+
+    Ok(MetadataResult {
+        dest_metadata: out_path_metadata,
+        dest_bundle: out_path_bundle,
+    })
+}
+
+/// Returns the ink! metadata of the contract, i.e. its spec, storage layout and types, without
+/// writing it.
+///
+/// It does so by generating and invoking a temporary workspace member, which depends on the
+/// contract with the `features` and, unless `no_default_features` is set, its default features.
+pub(crate) fn generate_ink_metadata(
+    crate_metadata: &CrateMetadata,
+    verbosity: Verbosity,
+    unstable_options: &UnstableFlags,
+    features: &[String],
+    no_default_features: bool,
+) -> Result<serde_json::Map<String, Value>> {
+    let target_directory = crate_metadata.target_directory.clone();
+    let mut ink_meta = None;
+    let mut generate_metadata = |manifest_path: &ManifestPath| -> Result<()> {
         let target_dir_arg = format!("--target-dir={}", target_directory.to_string_lossy());
+        let mut args = vec![
+            "--package".to_string(),
+            "metadata-gen".to_string(),
+            manifest_path.cargo_arg(),
+            target_dir_arg,
+            "--release".to_string(),
+        ];
+        if !features.is_empty() {
+            let features = features
+                .iter()
+                .map(|feature| format!("contract/{}", feature))
+                .collect::<Vec<_>>();
+            args.push(format!("--features={}", features.join(",")));
+        }
         let stdout = util::invoke_cargo(
             "run",
-            &[
-                "--package",
-                "metadata-gen",
-                &manifest_path.cargo_arg(),
-                &target_dir_arg,
-                "--release",
-            ],
+            &args,
             crate_metadata.manifest_path.directory(),
             verbosity,
+            vec![],
+            crate_metadata.toolchain.as_deref(),
         )?;
-
-        let ink_meta: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&stdout)?;
-        let metadata = ContractMetadata::new(source, contract, user, ink_meta);
-        {
-            let mut metadata = metadata.clone();
-            metadata.remove_source_wasm_attribute();
-            let contents = serde_json::to_string_pretty(&metadata)?;
-            fs::write(&out_path_metadata, contents)?;
-            current_progress += 1;
-        }
-
-        maybe_println!(
-            verbosity,
-            " {} {}",
-            format!("[{}/{}]", current_progress, total_steps).bold(),
-            "Generating bundle".bright_green().bold()
-        );
-        let contents = serde_json::to_string(&metadata)?;
-        fs::write(&out_path_bundle, contents)?;
-
+        ink_meta = Some(serde_json::from_slice(&stdout)?);
         Ok(())
     };
 
     if unstable_options.original_manifest {
+        if no_default_features {
+            anyhow::bail!(
+                "`--no-default-features` can't be applied to the metadata generation with \
+                `-Z original-manifest`"
+            )
+        }
         generate_metadata(&crate_metadata.manifest_path)?;
     } else {
         Workspace::new(&crate_metadata.cargo_meta, &crate_metadata.root_package.id)?
@@ -131,24 +212,55 @@ pub(crate) fn execute(
                     .with_profile_release_lto(false)?;
                 Ok(())
             })?
-            .with_metadata_gen_package(crate_metadata.manifest_path.absolute_directory()?)?
-            .using_temp(generate_metadata)?;
+            .with_metadata_gen_package(
+                crate_metadata.manifest_path.absolute_directory()?,
+                !no_default_features,
+            )?
+            .using_temp(&mut generate_metadata)?;
     }
+    ink_meta.ok_or_else(|| anyhow::anyhow!("The metadata generation produced no metadata"))
+}
 
-    Ok(MetadataResult {
-        dest_metadata: out_path_metadata,
-        dest_bundle: out_path_bundle,
-    })
+/// Returns the paths the metadata and the bundle of the contract are written to.
+pub(crate) fn artifact_paths(crate_metadata: &CrateMetadata) -> MetadataResult {
+    let fname_bundle = format!("{}.contract", crate_metadata.package_name);
+    MetadataResult {
+        dest_metadata: crate_metadata.artifact_directory.join(METADATA_FILE),
+        dest_bundle: crate_metadata.artifact_directory.join(fname_bundle),
+    }
+}
+
+/// Replaces the hex encoded wasm in `source.wasm` of the `bundle` with its zstd compressed form.
+///
+/// `source.hash` remains the hash of the uncompressed wasm, which is the code hash on chain.
+pub(crate) fn compress_source_wasm(bundle: &mut Value) -> Result<()> {
+    let source = bundle
+        .get_mut("source")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| anyhow::anyhow!("The bundle has no `source`"))?;
+    let wasm = match source.get("wasm").and_then(Value::as_str) {
+        Some(wasm) => wasm,
+        None => return Ok(()),
+    };
+    let wasm = serde_hex::from_hex(wasm).context("Invalid wasm in the bundle")?;
+    let compressed = zstd::encode_all(&wasm[..], COMPRESSION_LEVEL)?;
+    source.insert(
+        "wasm".into(),
+        serde_json::to_value(SourceWasm::new(compressed))?,
+    );
+    source.insert("compression".into(), WASM_COMPRESSION.into());
+    Ok(())
 }
 
 /// Generate the extended contract project metadata
 fn extended_metadata(
     crate_metadata: &CrateMetadata,
-    final_contract_wasm: &Path,
+    optimization_result: Option<&OptimizationResult>,
 ) -> Result<ExtendedMetadataResult> {
     let contract_package = &crate_metadata.root_package;
     let ink_version = &crate_metadata.ink_version;
-    let rust_version = Version::parse(&rustc_version::version()?.to_string())?;
+    let rustc_version = toolchain::version_meta(crate_metadata.toolchain.as_deref())?;
+    let rust_version = Version::parse(&rustc_version.semver.to_string())?;
     let contract_name = contract_package.name.clone();
     let contract_version = Version::parse(&contract_package.version.to_string())?;
     let contract_authors = contract_package.authors.clone();
@@ -165,9 +277,17 @@ fn extended_metadata(
     let source = {
         let lang = SourceLanguage::new(Language::Ink, ink_version.clone());
         let compiler = SourceCompiler::new(Compiler::RustC, rust_version);
-        let wasm = fs::read(final_contract_wasm)?;
-        let hash = blake2_hash(wasm.as_slice());
-        Source::new(Some(SourceWasm::new(wasm)), hash, lang, compiler)
+        // the code hash is removed again from metadata generated without building the Wasm
+        let (wasm, hash) = match optimization_result {
+            Some(optimization_result) => {
+                let wasm = fs::read(&optimization_result.dest_wasm)?;
+                let hash = blake2_hash(wasm.as_slice());
+                (Some(SourceWasm::new(wasm)), hash)
+            }
+            None => (None, CodeHash([0u8; 32])),
+        };
+        let build_info = build_info(crate_metadata, optimization_result, &rustc_version)?;
+        Source::new(wasm, hash, lang, compiler, Some(build_info))
     };
 
     // Required contract fields
@@ -211,8 +331,126 @@ fn extended_metadata(
     })
 }
 
+/// Returns the provenance of the contract build, enabling later verification of how the
+/// contract was produced.
+fn build_info(
+    crate_metadata: &CrateMetadata,
+    optimization_result: Option<&OptimizationResult>,
+    rustc_version: &rustc_version::VersionMeta,
+) -> Result<Map<String, Value>> {
+    let source_tree_hash = source_tree_hash(&crate_metadata.manifest_path.absolute_directory()?)?;
+    let mut build_info = Map::new();
+    build_info.insert(
+        "rustc_version".into(),
+        rustc_version.short_version_string.clone().into(),
+    );
+    build_info.insert(
+        "cargo_contract_version".into(),
+        env!("CARGO_PKG_VERSION").into(),
+    );
+    if let Some(optimization_result) = optimization_result {
+        build_info.insert(
+            "wasm_opt_version".into(),
+            optimization_result.optimizer_version.clone().into(),
+        );
+        build_info.insert(
+            "wasm_opt_settings".into(),
+            optimization_result.display_settings().into(),
+        );
+    }
+    build_info.insert(
+        "source_tree_hash".into(),
+        serde_json::to_value(source_tree_hash)?,
+    );
+    // set by `build --verifiable` inside the container
+    if let Ok(image) = std::env::var(verifiable::IMAGE_ENV) {
+        build_info.insert("image".into(), image.into());
+    }
+    Ok(build_info)
+}
+
+/// Returns the blake2 hash of the source tree at `directory`.
+///
+/// The relative path and content of every file is hashed in a stable order. Hidden files and
+/// directories, e.g. `.git`, as well as `target` directories are skipped.
+pub(crate) fn source_tree_hash(directory: &Path) -> Result<CodeHash> {
+    tree_hash(directory, |_, file| file)
+}
+
+/// Returns the blake2 hash of the source tree at `directory`, ignoring the doc comments of the
+/// Rust sources.
+///
+/// Doc comments only end up in the metadata, hence this hash changes only if the code does.
+pub(crate) fn code_tree_hash(directory: &Path) -> Result<CodeHash> {
+    tree_hash(directory, |path, file| {
+        if path.extension() == Some(OsStr::new("rs")) {
+            strip_doc_comments(&file)
+        } else {
+            file
+        }
+    })
+}
+
+/// Hashes the files of the tree at `directory`, after passing their contents through
+/// `content_of`.
+fn tree_hash<F>(directory: &Path, content_of: F) -> Result<CodeHash>
+where
+    F: Fn(&Path, Vec<u8>) -> Vec<u8>,
+{
+    let mut content = Vec::new();
+    for relative_path in source_files(directory)? {
+        let path = directory.join(&relative_path);
+        let file = content_of(&path, fs::read(&path)?);
+        content.extend(relative_path.to_string_lossy().as_bytes());
+        content.push(0);
+        content.extend((file.len() as u64).to_le_bytes().iter());
+        content.extend(file);
+    }
+    Ok(blake2_hash(&content))
+}
+
+/// Returns the paths of the source files in the tree at `directory`, relative to it and in a
+/// stable order.
+///
+/// Hidden files and directories, e.g. `.git`, as well as `target` directories are skipped.
+pub(crate) fn source_files(directory: &Path) -> Result<Vec<PathBuf>> {
+    let entries = WalkDir::new(directory)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name.starts_with('.') || name == "target")
+        });
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative_path = entry
+            .path()
+            .strip_prefix(directory)
+            .expect("the entry is within the walked directory; qed");
+        files.push(relative_path.to_path_buf());
+    }
+    Ok(files)
+}
+
+/// Removes the lines consisting of an outer (`///`) or inner (`//!`) doc comment.
+fn strip_doc_comments(source: &[u8]) -> Vec<u8> {
+    source
+        .split(|byte| *byte == b'\n')
+        .filter(|line| {
+            let line = String::from_utf8_lossy(line);
+            let line = line.trim_start();
+            !((line.starts_with("///") && !line.starts_with("////")) || line.starts_with("//!"))
+        })
+        .collect::<Vec<_>>()
+        .join(&b'\n')
+}
+
 /// Returns the blake2 hash of the submitted slice.
-fn blake2_hash(code: &[u8]) -> CodeHash {
+pub(crate) fn blake2_hash(code: &[u8]) -> CodeHash {
     let mut output = [0u8; 32];
     let mut blake2 = blake2::VarBlake2b::new_keyed(&[], 32);
     blake2.update(code);
@@ -225,8 +463,10 @@ fn blake2_hash(code: &[u8]) -> CodeHash {
 mod tests {
     use crate::cmd::metadata::blake2_hash;
     use crate::{
-        cmd, crate_metadata::CrateMetadata, util::tests::with_tmp_dir, BuildArtifacts,
-        ManifestPath, OptimizationPasses, UnstableFlags, Verbosity,
+        cmd::{self, build::ExecuteArgs},
+        crate_metadata::CrateMetadata,
+        util::tests::with_tmp_dir,
+        BuildArtifacts, ManifestPath,
     };
     use contract_metadata::*;
     use serde_json::{Map, Value};
@@ -326,13 +566,12 @@ mod tests {
             fs::create_dir_all(final_contract_wasm_path.parent().unwrap()).unwrap();
             fs::write(final_contract_wasm_path, "TEST FINAL WASM BLOB").unwrap();
 
-            let build_result = cmd::build::execute(
-                &test_manifest.manifest_path,
-                Verbosity::Default,
-                BuildArtifacts::All,
-                UnstableFlags::default(),
-                OptimizationPasses::default(),
-            )?;
+            let args = ExecuteArgs {
+                manifest_path: test_manifest.manifest_path.clone(),
+                build_artifact: BuildArtifacts::All,
+                ..Default::default()
+            };
+            let build_result = cmd::build::execute(args)?;
             let dest_bundle = build_result
                 .metadata_result
                 .expect("Metadata should be generated")
@@ -352,6 +591,9 @@ mod tests {
             let language = source.get("language").expect("source.language not found");
             let compiler = source.get("compiler").expect("source.compiler not found");
             let wasm = source.get("wasm").expect("source.wasm not found");
+            let build_info = source
+                .get("build_info")
+                .expect("source.build_info not found");
 
             let contract = metadata_json.get("contract").expect("contract not found");
             let name = contract.get("name").expect("contract.name not found");
@@ -405,6 +647,19 @@ mod tests {
             assert_eq!(expected_wasm, wasm.as_str().unwrap());
             assert_eq!(expected_language, language.as_str().unwrap());
             assert_eq!(expected_compiler, compiler.as_str().unwrap());
+            assert_eq!(
+                env!("CARGO_PKG_VERSION"),
+                build_info["cargo_contract_version"].as_str().unwrap()
+            );
+            assert_eq!("-O3", build_info["wasm_opt_settings"].as_str().unwrap());
+            assert!(build_info["rustc_version"]
+                .as_str()
+                .unwrap()
+                .starts_with("rustc "));
+            assert!(build_info["source_tree_hash"]
+                .as_str()
+                .unwrap()
+                .starts_with("0x"));
             assert_eq!(crate_metadata.package_name, name.as_str().unwrap());
             assert_eq!(
                 crate_metadata.root_package.version.to_string(),
@@ -431,3 +686,69 @@ mod tests {
         str
     }
 }
+
+#[cfg(test)]
+mod tree_hash_tests {
+    use super::{code_tree_hash, source_tree_hash};
+    use crate::util::tests::with_tmp_dir;
+    use std::fs;
+
+    #[test]
+    fn source_tree_hash_ignores_build_artifacts() {
+        with_tmp_dir(|path| {
+            // given
+            fs::create_dir_all(path.join("src"))?;
+            fs::write(path.join("src/lib.rs"), "fn main() {}")?;
+            let hash = source_tree_hash(path)?;
+
+            // when
+            fs::create_dir_all(path.join("target"))?;
+            fs::write(path.join("target/flipper.wasm"), [0u8; 4])?;
+            fs::create_dir_all(path.join(".git"))?;
+            fs::write(path.join(".git/HEAD"), "ref: refs/heads/master")?;
+
+            // then
+            assert_eq!(source_tree_hash(path)?, hash);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn source_tree_hash_changes_with_sources() {
+        with_tmp_dir(|path| {
+            // given
+            fs::write(path.join("lib.rs"), "fn main() {}")?;
+            let hash = source_tree_hash(path)?;
+
+            // when
+            fs::write(path.join("lib.rs"), "fn main() { }")?;
+
+            // then
+            assert_ne!(source_tree_hash(path)?, hash);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn code_tree_hash_ignores_doc_comments() {
+        with_tmp_dir(|path| {
+            // given
+            fs::write(path.join("lib.rs"), "/// Flips the value.\nfn flip() {}")?;
+            let source_hash = source_tree_hash(path)?;
+            let code_hash = code_tree_hash(path)?;
+
+            // when
+            fs::write(
+                path.join("lib.rs"),
+                "/// Flips the stored value.\nfn flip() {}",
+            )?;
+
+            // then
+            assert_ne!(source_tree_hash(path)?, source_hash);
+            assert_eq!(code_tree_hash(path)?, code_hash);
+            fs::write(path.join("lib.rs"), "/// Flips the value.\nfn flip() { }")?;
+            assert_ne!(code_tree_hash(path)?, code_hash);
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,697 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Export of the ABI of a contract as JSON Schema or OpenRPC document.
+//!
+//! The schemas describe the JSON representation of the values in `@polkadot/api`, which is also
+//! used by the bindings of `cargo contract bindgen`:
+//!
+//! - integers wider than 32 bits are numbers or decimal or hex strings
+//! - byte sequences and arrays, account ids and hashes are hex or SS58 encoded strings
+//! - the fields of structs are camel cased
+//! - enums without fields are the name of their variant, other enums an object with the camel
+//!   cased name of the variant as key
+//! - options are `null` or their value, results an object with an `ok` or `err` key
+
+use super::{
+    trait_definition::{contract_of, docs, Registry},
+    verify_metadata::label,
+};
+use anyhow::{Context, Result};
+use heck::MixedCase as _;
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeMap, fmt, fs, path::PathBuf, str::FromStr};
+use structopt::StructOpt;
+
+/// The formats the ABI can be exported to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SchemaFormat {
+    /// A JSON Schema of the payloads of the constructors and messages.
+    JsonSchema,
+    /// An OpenRPC document with a method per constructor and message.
+    OpenRpc,
+}
+
+impl fmt::Display for SchemaFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaFormat::JsonSchema => write!(f, "JSON Schema"),
+            SchemaFormat::OpenRpc => write!(f, "OpenRPC"),
+        }
+    }
+}
+
+impl FromStr for SchemaFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "json-schema" => Ok(SchemaFormat::JsonSchema),
+            "openrpc" => Ok(SchemaFormat::OpenRpc),
+            _ => anyhow::bail!(
+                "Unknown schema format {}, expected json-schema or openrpc",
+                format
+            ),
+        }
+    }
+}
+
+/// Exports the constructors and messages of the contract as JSON Schema or OpenRPC document, so
+/// that generic API gateways and form builders can validate and construct calls of the contract.
+///
+/// Accepts both the `metadata.json` and the `<name>.contract` bundle.
+#[derive(Debug, StructOpt)]
+pub struct MetadataSchemaCommand {
+    /// The format to export to: `json-schema` or `openrpc`
+    #[structopt(
+        long,
+        default_value = "json-schema",
+        value_name = "json-schema | openrpc"
+    )]
+    format: SchemaFormat,
+    /// Write the schema to this file instead of printing it
+    #[structopt(long, short, parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Path to the metadata
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+}
+
+impl MetadataSchemaCommand {
+    /// Exports the schema to the `--output`, or prints it if none is supplied.
+    pub fn exec(&self) -> Result<Option<String>> {
+        let contents = fs::read(&self.path).context(format!(
+            "Failed to read the metadata {}",
+            self.path.display()
+        ))?;
+        let metadata: Value = serde_json::from_slice(&contents).context(format!(
+            "The metadata {} is no valid JSON",
+            self.path.display()
+        ))?;
+        let schema = serde_json::to_string_pretty(&export(&metadata, self.format)?)?;
+        match &self.output {
+            Some(output) => {
+                fs::write(output, schema)
+                    .context(format!("Failed to write {}", output.display()))?;
+                Ok(Some(format!(
+                    "Exported {} as {} to {}",
+                    self.path.display(),
+                    self.format,
+                    output.display()
+                )))
+            }
+            None => {
+                // printed as is, so that it can be piped into other tools
+                println!("{}", schema);
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// A constructor or message of the contract.
+struct Callable {
+    kind: &'static str,
+    name: String,
+    selector: String,
+    docs: Option<String>,
+    mutates: bool,
+    payable: bool,
+    /// The names and schemas of the arguments.
+    args: Vec<(String, Value)>,
+    /// The schema of the return value, constructors have none.
+    returns: Option<Value>,
+}
+
+/// Returns the schema of the ABI of the `metadata` in the `format`.
+fn export(metadata: &Value, format: SchemaFormat) -> Result<Value> {
+    let contract = contract_of(metadata);
+    let spec = contract
+        .get("spec")
+        .ok_or_else(|| anyhow::anyhow!("The metadata contains no contract spec"))?;
+    let name = metadata
+        .get("contract")
+        .and_then(|contract| contract.get("name"))
+        .and_then(Value::as_str)
+        .unwrap_or("contract");
+    let mut schemas = Schemas {
+        registry: Registry::new(contract),
+        reference_prefix: match format {
+            SchemaFormat::JsonSchema => "#/definitions/",
+            SchemaFormat::OpenRpc => "#/components/schemas/",
+        },
+        names: BTreeMap::new(),
+        definitions: Map::new(),
+    };
+
+    let mut callables = Vec::new();
+    for (kind, key) in &[("constructor", "constructors"), ("message", "messages")] {
+        let items = spec
+            .get(*key)
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for item in items {
+            let name = label(item).ok_or_else(|| anyhow::anyhow!("A {} has no name", kind))?;
+            let selector = item
+                .get("selector")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow::anyhow!("The {} {} has no selector", kind, name))?
+                .to_string();
+            let args = item
+                .get("args")
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .map(|arg| {
+                    let arg_name = label(arg).ok_or_else(|| {
+                        anyhow::anyhow!("An argument of the {} {} has no name", kind, name)
+                    })?;
+                    Ok((arg_name, schemas.type_spec(&arg["type"])?))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let returns = match (*kind, item.get("returnType").filter(|ty| !ty.is_null())) {
+                ("constructor", _) => None,
+                (_, Some(ty)) => Some(schemas.type_spec(ty)?),
+                (_, None) => Some(json!({ "type": "null" })),
+            };
+            callables.push(Callable {
+                kind,
+                name,
+                selector,
+                docs: docs(item),
+                mutates: item.get("mutates").and_then(Value::as_bool) == Some(true),
+                payable: item.get("payable").and_then(Value::as_bool) == Some(true),
+                args,
+                returns,
+            });
+        }
+    }
+
+    Ok(match format {
+        SchemaFormat::JsonSchema => json_schema(name, docs(spec), &callables, schemas.definitions),
+        SchemaFormat::OpenRpc => {
+            let version = metadata
+                .get("contract")
+                .and_then(|contract| contract.get("version"))
+                .and_then(Value::as_str)
+                .unwrap_or("0.0.0");
+            open_rpc(name, version, docs(spec), &callables, schemas.definitions)
+        }
+    })
+}
+
+/// Returns a JSON Schema accepting the payload of any constructor or message, i.e. its selector
+/// and its arguments by name.
+fn json_schema(
+    name: &str,
+    docs: Option<String>,
+    callables: &[Callable],
+    mut definitions: Map<String, Value>,
+) -> Value {
+    let mut calls = Vec::new();
+    for callable in callables {
+        let mut properties = Map::new();
+        for (arg_name, schema) in &callable.args {
+            properties.insert(arg_name.clone(), schema.clone());
+        }
+        let mut call = json!({
+            "title": callable.name,
+            "type": "object",
+            "properties": {
+                "selector": { "const": callable.selector },
+                "args": {
+                    "type": "object",
+                    "properties": properties,
+                    "required": callable.args.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+                    "additionalProperties": false
+                }
+            },
+            "required": ["selector", "args"],
+            "additionalProperties": false,
+            "x-mutates": callable.mutates,
+            "x-payable": callable.payable
+        });
+        if let Some(docs) = &callable.docs {
+            call["description"] = docs.as_str().into();
+        }
+        if let Some(returns) = &callable.returns {
+            call["x-returns"] = returns.clone();
+        }
+        let key = format!("{}.{}", callable.kind, callable.name);
+        calls.push(json!({ "$ref": format!("#/definitions/{}", key) }));
+        definitions.insert(key, call);
+    }
+    let mut schema = json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": name,
+        "oneOf": calls,
+        "definitions": definitions
+    });
+    if let Some(docs) = docs {
+        schema["description"] = docs.into();
+    }
+    schema
+}
+
+/// Returns an OpenRPC document with a method per constructor and message, tagged by their kind.
+fn open_rpc(
+    name: &str,
+    version: &str,
+    docs: Option<String>,
+    callables: &[Callable],
+    definitions: Map<String, Value>,
+) -> Value {
+    let methods = callables
+        .iter()
+        .map(|callable| {
+            let mut method = json!({
+                "name": callable.name,
+                "tags": [{ "name": callable.kind }],
+                "params": callable.args.iter().map(|(name, schema)| json!({
+                    "name": name,
+                    "required": true,
+                    "schema": schema
+                })).collect::<Vec<_>>(),
+                "result": {
+                    "name": "result",
+                    "schema": callable.returns.clone().unwrap_or_else(|| json!({ "type": "null" }))
+                },
+                "x-selector": callable.selector,
+                "x-mutates": callable.mutates,
+                "x-payable": callable.payable
+            });
+            if let Some(docs) = &callable.docs {
+                method["description"] = docs.as_str().into();
+            }
+            method
+        })
+        .collect::<Vec<_>>();
+    let mut info = json!({ "title": name, "version": version });
+    if let Some(docs) = docs {
+        info["description"] = docs.into();
+    }
+    json!({
+        "openrpc": "1.2.6",
+        "info": info,
+        "methods": methods,
+        "components": { "schemas": definitions }
+    })
+}
+
+/// Renders the types of the type registry as JSON Schemas of their JSON representation.
+struct Schemas<'a> {
+    registry: Registry<'a>,
+    /// The prefix of references to the `definitions`.
+    reference_prefix: &'static str,
+    /// The names of the definitions of the custom types, by their id.
+    names: BTreeMap<u64, String>,
+    definitions: Map<String, Value>,
+}
+
+impl<'a> Schemas<'a> {
+    /// Returns the schema of an argument or return type `spec`.
+    fn type_spec(&mut self, spec: &Value) -> Result<Value> {
+        let id = spec
+            .get("type")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow::anyhow!("A type reference has no type id"))?;
+        self.schema(id)
+    }
+
+    /// Returns the schema of the type `id`, adding the definitions of the custom types it refers
+    /// to to the `definitions`.
+    fn schema(&mut self, id: u64) -> Result<Value> {
+        let ty = self.registry.get(id)?;
+        let def = ty
+            .get("def")
+            .and_then(Value::as_object)
+            .ok_or_else(|| anyhow::anyhow!("The type {} has no definition", id))?;
+        let field_type = |field: &Value| {
+            field
+                .get("type")
+                .and_then(Value::as_u64)
+                .or_else(|| field.as_u64())
+                .ok_or_else(|| anyhow::anyhow!("The type {} refers to no type id", field))
+        };
+
+        if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+            return Ok(primitive_schema(primitive));
+        }
+        if let Some(sequence) = def.get("sequence") {
+            let element = field_type(sequence)?;
+            if self.registry.is_primitive(element, "u8") {
+                return Ok(hex_schema(None));
+            }
+            return Ok(json!({ "type": "array", "items": self.schema(element)? }));
+        }
+        if let Some(array) = def.get("array") {
+            let element = field_type(array)?;
+            let len = array.get("len").and_then(Value::as_u64).unwrap_or_default();
+            if self.registry.is_primitive(element, "u8") {
+                return Ok(hex_schema(Some(len)));
+            }
+            return Ok(json!({
+                "type": "array",
+                "items": self.schema(element)?,
+                "minItems": len,
+                "maxItems": len
+            }));
+        }
+        if let Some(compact) = def.get("compact") {
+            return self.schema(field_type(compact)?);
+        }
+        if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+            let fields = tuple
+                .iter()
+                .map(|field| self.schema(field_type(field)?))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(tuple_schema(fields));
+        }
+
+        let path = ty
+            .get("path")
+            .and_then(Value::as_array)
+            .map(|path| path.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let name = path
+            .last()
+            .ok_or_else(|| anyhow::anyhow!("The custom type {} has no path", id))?
+            .to_string();
+        let params = self.registry.params(id)?;
+        match (name.as_str(), params.as_slice()) {
+            ("AccountId", _) => Ok(json!({
+                "type": "string",
+                "description": "SS58 encoded account id"
+            })),
+            ("Hash", _) => Ok(hex_schema(Some(32))),
+            ("Option", [value]) => Ok(json!({
+                "anyOf": [{ "type": "null" }, self.schema(*value)?]
+            })),
+            ("Result", [ok, err]) => Ok(json!({
+                "oneOf": [
+                    variant_schema("ok", self.schema(*ok)?),
+                    variant_schema("err", self.schema(*err)?)
+                ]
+            })),
+            // generic types are inlined, as their type parameters are substituted
+            (_, [_, ..]) => self.structure(def),
+            _ => {
+                if let Some(name) = self.names.get(&id) {
+                    return Ok(json!({ "$ref": format!("{}{}", self.reference_prefix, name) }));
+                }
+                let name = if self.definitions.contains_key(&name) {
+                    format!("{}{}", name, id)
+                } else {
+                    name
+                };
+                // the name is reserved first, as the type may refer to itself
+                self.names.insert(id, name.clone());
+                self.definitions.insert(name.clone(), Value::Null);
+                let mut schema = self.structure(def)?;
+                schema["title"] = path.join("::").into();
+                if let Some(docs) = docs(ty) {
+                    schema["description"] = docs.into();
+                }
+                self.definitions.insert(name.clone(), schema);
+                Ok(json!({ "$ref": format!("{}{}", self.reference_prefix, name) }))
+            }
+        }
+    }
+
+    /// Returns the schema of a struct or enum definition.
+    fn structure(&mut self, def: &Map<String, Value>) -> Result<Value> {
+        if let Some(composite) = def.get("composite") {
+            return self.fields(composite);
+        }
+        let variants = def
+            .get("variant")
+            .and_then(|variant| variant.get("variants"))
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow::anyhow!("The type {:?} can't be exported", def))?;
+        let names = variants
+            .iter()
+            .map(|variant| {
+                variant
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow::anyhow!("A variant has no name"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let has_fields = variants.iter().any(|variant| {
+            matches!(
+                variant.get("fields").and_then(Value::as_array),
+                Some(fields) if !fields.is_empty()
+            )
+        });
+        if !has_fields {
+            return Ok(json!({ "type": "string", "enum": names }));
+        }
+        let variants = variants
+            .iter()
+            .zip(names)
+            .map(|(variant, name)| Ok(variant_schema(&name.to_mixed_case(), self.fields(variant)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(json!({ "oneOf": variants }))
+    }
+
+    /// Returns the schema of the fields of a struct or enum variant.
+    fn fields(&mut self, item: &Value) -> Result<Value> {
+        let fields = item
+            .get("fields")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut named = Map::new();
+        let mut unnamed = Vec::new();
+        for field in fields {
+            let id = field
+                .get("type")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow::anyhow!("A field refers to no type id"))?;
+            let schema = self.schema(id)?;
+            match field.get("name").and_then(Value::as_str) {
+                Some(name) => {
+                    named.insert(name.to_mixed_case(), schema);
+                }
+                None => unnamed.push(schema),
+            }
+        }
+        if !named.is_empty() {
+            let required = named.keys().cloned().collect::<Vec<_>>();
+            return Ok(json!({
+                "type": "object",
+                "properties": named,
+                "required": required,
+                "additionalProperties": false
+            }));
+        }
+        Ok(match unnamed.len() {
+            // newtypes are represented by their field
+            1 => unnamed.remove(0),
+            _ => tuple_schema(unnamed),
+        })
+    }
+}
+
+/// Returns the schema of a primitive.
+fn primitive_schema(primitive: &str) -> Value {
+    let range = |min: i64, max: i64| json!({ "type": "integer", "minimum": min, "maximum": max });
+    match primitive {
+        "bool" => json!({ "type": "boolean" }),
+        "str" => json!({ "type": "string" }),
+        "char" => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+        "u8" => range(0, u8::MAX.into()),
+        "u16" => range(0, u16::MAX.into()),
+        "u32" => range(0, u32::MAX.into()),
+        "i8" => range(i8::MIN.into(), i8::MAX.into()),
+        "i16" => range(i16::MIN.into(), i16::MAX.into()),
+        "i32" => range(i32::MIN.into(), i32::MAX.into()),
+        // wider integers exceed the precision of JSON numbers in most parsers
+        primitive if primitive.starts_with('u') => json!({
+            "anyOf": [
+                { "type": "integer", "minimum": 0 },
+                { "type": "string", "pattern": "^([0-9]+|0x[0-9a-fA-F]+)$" }
+            ]
+        }),
+        _ => json!({
+            "anyOf": [
+                { "type": "integer" },
+                { "type": "string", "pattern": "^(-?[0-9]+|0x[0-9a-fA-F]+)$" }
+            ]
+        }),
+    }
+}
+
+/// Returns the schema of hex encoded bytes, of the length `len` if it is fixed.
+fn hex_schema(len: Option<u64>) -> Value {
+    let pattern = match len {
+        Some(len) => format!("^0x([0-9a-fA-F]{{2}}){{{}}}$", len),
+        None => "^0x([0-9a-fA-F]{2})*$".to_string(),
+    };
+    json!({ "type": "string", "pattern": pattern })
+}
+
+/// Returns the schema of a tuple of the `fields`, `null` for the unit tuple.
+fn tuple_schema(fields: Vec<Value>) -> Value {
+    if fields.is_empty() {
+        return json!({ "type": "null" });
+    }
+    let len = fields.len();
+    json!({
+        "type": "array",
+        "items": fields,
+        "minItems": len,
+        "maxItems": len
+    })
+}
+
+/// Returns the schema of an enum variant, an object with the `name` of the variant as only key.
+fn variant_schema(name: &str, fields: Value) -> Value {
+    json!({
+        "type": "object",
+        "properties": { name: fields },
+        "required": [name],
+        "additionalProperties": false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    fn metadata() -> Value {
+        json!({
+            "contract": { "name": "my_token", "version": "0.1.0" },
+            "V1": {
+                "spec": {
+                    "docs": [" A token."],
+                    "constructors": [{
+                        "label": "new", "selector": "0x9bae9d5e", "payable": false,
+                        "args": [{ "label": "supply", "type": { "type": 1 } }],
+                        "docs": []
+                    }],
+                    "messages": [{
+                        "label": "transfer", "selector": "0x84a15da1",
+                        "mutates": true, "payable": false,
+                        "args": [
+                            { "label": "to", "type": { "type": 2 } },
+                            { "label": "value", "type": { "type": 1 } }
+                        ],
+                        "returnType": { "type": 4 },
+                        "docs": [" Transfers tokens."]
+                    }]
+                },
+                "types": [
+                    { "id": 0, "type": { "def": { "primitive": "u8" } } },
+                    { "id": 1, "type": { "def": { "primitive": "u128" } } },
+                    { "id": 2, "type": { "path": ["ink_env", "types", "AccountId"],
+                      "def": { "composite": { "fields": [{ "type": 3 }] } } } },
+                    { "id": 3, "type": { "def": { "array": { "len": 32, "type": 0 } } } },
+                    { "id": 4, "type": { "path": ["Result"],
+                      "params": [{ "name": "T", "type": 5 }, { "name": "E", "type": 6 }],
+                      "def": { "variant": { "variants": [] } } } },
+                    { "id": 5, "type": { "def": { "tuple": [] } } },
+                    { "id": 6, "type": { "path": ["my_token", "Error"],
+                      "def": { "variant": { "variants": [{ "name": "InsufficientBalance" }] } } } }
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn json_schema_describes_call_payloads() {
+        // when
+        let schema = export(&metadata(), SchemaFormat::JsonSchema).unwrap();
+
+        // then
+        assert_eq!(schema["title"], "my_token");
+        assert_eq!(schema["description"], "A token.");
+        assert_eq!(
+            schema["oneOf"],
+            json!([
+                { "$ref": "#/definitions/constructor.new" },
+                { "$ref": "#/definitions/message.transfer" }
+            ])
+        );
+        let transfer = &schema["definitions"]["message.transfer"];
+        assert_eq!(transfer["properties"]["selector"]["const"], "0x84a15da1");
+        let args = &transfer["properties"]["args"];
+        assert_eq!(args["required"], json!(["to", "value"]));
+        assert_eq!(args["properties"]["to"]["type"], "string");
+        assert_eq!(
+            transfer["x-returns"]["oneOf"][1]["properties"]["err"],
+            json!({ "$ref": "#/definitions/Error" })
+        );
+        assert_eq!(
+            schema["definitions"]["Error"],
+            json!({
+                "type": "string",
+                "enum": ["InsufficientBalance"],
+                "title": "my_token::Error"
+            })
+        );
+    }
+
+    #[test]
+    fn open_rpc_has_a_method_per_callable() {
+        // when
+        let document = export(&metadata(), SchemaFormat::OpenRpc).unwrap();
+
+        // then
+        assert_eq!(document["info"]["version"], "0.1.0");
+        let methods = document["methods"].as_array().unwrap();
+        assert_eq!(methods.len(), 2);
+        assert_eq!(methods[0]["tags"][0]["name"], "constructor");
+        assert_eq!(methods[1]["name"], "transfer");
+        assert_eq!(methods[1]["description"], "Transfers tokens.");
+        assert_eq!(methods[1]["params"][1]["name"], "value");
+        assert_eq!(
+            methods[1]["result"]["schema"]["oneOf"][1]["properties"]["err"],
+            json!({ "$ref": "#/components/schemas/Error" })
+        );
+        assert!(document["components"]["schemas"]["Error"].is_object());
+    }
+
+    #[test]
+    fn schema_is_written_to_output() {
+        with_tmp_dir(|path| {
+            // given
+            let metadata_path = path.join("metadata.json");
+            fs::write(&metadata_path, metadata().to_string())?;
+            let output = path.join("openrpc.json");
+            let cmd = MetadataSchemaCommand::from_iter(&[
+                "schema",
+                "--format",
+                "openrpc",
+                "--output",
+                output.to_str().unwrap(),
+                metadata_path.to_str().unwrap(),
+            ]);
+
+            // when
+            let message = cmd.exec()?;
+
+            // then
+            assert!(message.unwrap().contains("as OpenRPC"));
+            let document: Value = serde_json::from_slice(&fs::read(&output)?)?;
+            assert_eq!(document["openrpc"], "1.2.6");
+            Ok(())
+        })
+    }
+}
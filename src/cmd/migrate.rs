@@ -0,0 +1,345 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::upgrade_deps::{parse_requirement, release};
+use crate::workspace::{Manifest, ManifestPath};
+use anyhow::{Context, Result};
+use regex::Regex;
+use semver::Version;
+use std::{convert::TryFrom, ffi::OsStr, fmt::Write, fs, path::PathBuf};
+use structopt::StructOpt;
+use walkdir::WalkDir;
+
+/// Rewrites the sources of the contract for the breaking syntax changes between two ink!
+/// releases.
+///
+/// Changes which can't be migrated automatically are marked with a `TODO(ink! <release>)`
+/// comment. Run after `cargo contract upgrade-deps`, which updates the Cargo.toml.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "migrate")]
+pub struct MigrateCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// The ink! release the sources are written for, e.g. `3.0.0-rc3`
+    #[structopt(long)]
+    from: String,
+    /// The ink! release to migrate to, defaults to the `ink_lang` version of the Cargo.toml
+    #[structopt(long)]
+    to: Option<String>,
+    /// Only report the changes, without rewriting the sources
+    #[structopt(long)]
+    dry_run: bool,
+}
+
+/// The start of the comments marking the lines to migrate manually.
+const TODO_MARKER: &str = "// TODO(ink! ";
+
+/// How the matches of a migration rule are migrated.
+enum Action {
+    /// Replace the matches by the replacement, which may refer to capture groups.
+    Rewrite(&'static str),
+    /// Mark the lines of the matches with a TODO, as they need to be migrated manually.
+    Todo(&'static str),
+}
+
+/// A breaking syntax change of an ink! release.
+struct Rule {
+    /// The release introducing the change.
+    release: &'static str,
+    pattern: Regex,
+    action: Action,
+}
+
+/// Returns the migration rules of all releases.
+fn rules() -> Vec<Rule> {
+    let rule = |release, pattern, action| Rule {
+        release,
+        pattern: Regex::new(pattern).expect("invalid regex"),
+        action,
+    };
+    vec![
+        rule(
+            "3.0.0-rc4",
+            r#"selector\s*=\s*"(0x[0-9A-Fa-f]{8})""#,
+            Action::Rewrite("selector = $1"),
+        ),
+        rule(
+            "3.0.0-rc4",
+            r"#\[ink\(constructor[^\]]*\)\]\s*(?:pub\s+)?fn\s+\w+\s*\([^)]*\)\s*->\s*Self\s*;",
+            Action::Todo(
+                "trait definitions can no longer contain constructors, move the constructor to \
+                the contracts implementing the trait",
+            ),
+        ),
+        rule(
+            "3.0.0-rc6",
+            r"FromAccountId::from_account_id\(",
+            Action::Todo(
+                "contracts used as dependencies are called via the generated `<Contract>Ref` \
+                type instead of the contract type",
+            ),
+        ),
+        rule(
+            "3.0.0",
+            r"ink_storage::collections\b",
+            Action::Todo(
+                "`ink_storage::collections` were removed, use `ink_storage::Mapping` and derive \
+                `SpreadAllocate` for the storage struct, initialized via \
+                `ink_lang::utils::initialize_contract`",
+            ),
+        ),
+    ]
+}
+
+/// The migrations applied to a source file.
+#[derive(Debug, Default, PartialEq)]
+struct Migration {
+    rewrites: usize,
+    todos: usize,
+}
+
+impl MigrateCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let from = Version::parse(release(&self.from)?.ink)?;
+        let to = match &self.to {
+            Some(to) => release(to)?.ink.to_string(),
+            None => {
+                let manifest = Manifest::new(manifest_path.clone())?;
+                let requirement = manifest.dependency_version("ink_lang").ok_or_else(|| {
+                    anyhow::anyhow!("No 'ink_lang' dependency found, pass the release via --to")
+                })?;
+                parse_requirement(requirement)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "The 'ink_lang' dependency requires no single version, pass the \
+                            release via --to"
+                        )
+                    })?
+                    .to_string()
+            }
+        };
+        let to = Version::parse(&to)?;
+        let rules = rules()
+            .into_iter()
+            .filter(|rule| {
+                let release = Version::parse(rule.release).expect("valid release version");
+                release > from && release <= to
+            })
+            .collect::<Vec<_>>();
+
+        let dir = manifest_path.absolute_directory()?;
+        let mut report = String::new();
+        let mut total = Migration::default();
+        let sources = WalkDir::new(&dir).into_iter().filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || (name != "target" && !name.starts_with('.'))
+        });
+        for entry in sources {
+            let entry = entry?;
+            if entry.path().extension() != Some(OsStr::new("rs")) {
+                continue;
+            }
+            let source = fs::read_to_string(entry.path())
+                .context(format!("Failed to read {}", entry.path().display()))?;
+            let (migrated, migration) = migrate(&source, &rules);
+            if migration == Migration::default() {
+                continue;
+            }
+            if !self.dry_run {
+                fs::write(entry.path(), migrated)?;
+            }
+            writeln!(
+                report,
+                "  {}: {} rewritten, {} to do",
+                entry.path().strip_prefix(&dir)?.display(),
+                migration.rewrites,
+                migration.todos
+            )?;
+            total.rewrites += migration.rewrites;
+            total.todos += migration.todos;
+        }
+
+        if report.is_empty() {
+            return Ok(format!("No migrations from ink! {} to {} apply", from, to));
+        }
+        Ok(format!(
+            "{} the sources from ink! {} to {}\n{}\n{} rewritten, {} marked with `TODO(ink! ..)` \
+            to migrate manually",
+            if self.dry_run {
+                "Would migrate"
+            } else {
+                "Migrated"
+            },
+            from,
+            to,
+            report.trim_end(),
+            total.rewrites,
+            total.todos
+        ))
+    }
+}
+
+/// Applies the migration `rules` to the `source`.
+fn migrate(source: &str, rules: &[Rule]) -> (String, Migration) {
+    let mut source = source.to_string();
+    let mut migration = Migration::default();
+    for rule in rules {
+        match rule.action {
+            Action::Rewrite(replacement) => {
+                migration.rewrites += rule.pattern.find_iter(&source).count();
+                source = rule.pattern.replace_all(&source, replacement).into_owned();
+            }
+            Action::Todo(todo) => {
+                let line_starts = rule
+                    .pattern
+                    .find_iter(&source)
+                    .map(|found| source[..found.start()].rfind('\n').map_or(0, |i| i + 1))
+                    // the markers may mention the matched syntax themselves
+                    .filter(|&line_start| {
+                        !source[line_start..].trim_start().starts_with(TODO_MARKER)
+                    })
+                    .collect::<Vec<_>>();
+                // the TODOs are inserted back to front, keeping the line starts valid
+                for &line_start in line_starts.iter().rev() {
+                    let indent = source[line_start..]
+                        .chars()
+                        .take_while(|c| *c == ' ' || *c == '\t')
+                        .collect::<String>();
+                    let marker = format!("{}{}): {}", TODO_MARKER, rule.release, todo);
+                    let previous_line = source[..line_start]
+                        .trim_end_matches('\n')
+                        .rsplit('\n')
+                        .next()
+                        .unwrap_or_default();
+                    // sources migrated before keep their markers
+                    if previous_line.trim() == marker {
+                        continue;
+                    }
+                    source.insert_str(line_start, &format!("{}{}\n", indent, marker));
+                    migration.todos += 1;
+                }
+            }
+        }
+    }
+    (source, migration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    const SOURCE: &str = r#"use ink_lang as ink;
+
+#[ink::trait_definition]
+pub trait Token {
+    #[ink(constructor)]
+    fn new() -> Self;
+
+    #[ink(message, selector = "0xCAFEBABE")]
+    fn total_supply(&self) -> u128;
+}
+
+#[ink::contract]
+mod token {
+    use ink_storage::collections::HashMap as StorageHashMap;
+}
+"#;
+
+    #[test]
+    fn sources_are_migrated() {
+        with_tmp_dir(|path| {
+            // given
+            fs::write(
+                path.join("Cargo.toml"),
+                "[package]\nname = \"token\"\nversion = \"0.1.0\"\n\n\
+                [dependencies]\nink_lang = { version = \"3.0.0\", default-features = false }\n",
+            )?;
+            fs::write(path.join("lib.rs"), SOURCE)?;
+            let cmd = MigrateCommand::from_iter(&[
+                "migrate",
+                "--manifest-path",
+                path.join("Cargo.toml").to_str().unwrap(),
+                "--from",
+                "3.0.0-rc3",
+            ]);
+
+            // when
+            let report = cmd.exec()?;
+
+            // then
+            let migrated = fs::read_to_string(path.join("lib.rs"))?;
+            assert!(migrated.contains("#[ink(message, selector = 0xCAFEBABE)]"));
+            assert!(migrated.contains(
+                "    // TODO(ink! 3.0.0-rc4): trait definitions can no longer contain \
+                constructors, move the constructor to the contracts implementing the trait\n    \
+                #[ink(constructor)]\n"
+            ));
+            assert!(migrated
+                .contains("    // TODO(ink! 3.0.0): `ink_storage::collections` were removed"));
+            assert!(report.contains("  lib.rs: 1 rewritten, 2 to do"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn migrations_are_idempotent() {
+        // given
+        let rules = rules();
+        let (migrated, _) = migrate(SOURCE, &rules);
+
+        // when
+        let (remigrated, migration) = migrate(&migrated, &rules);
+
+        // then
+        assert_eq!(remigrated, migrated);
+        assert_eq!(migration, Migration::default());
+    }
+
+    #[test]
+    fn only_rules_of_later_releases_apply() {
+        with_tmp_dir(|path| {
+            // given
+            fs::write(
+                path.join("Cargo.toml"),
+                "[package]\nname = \"token\"\nversion = \"0.1.0\"\n",
+            )?;
+            fs::write(path.join("lib.rs"), SOURCE)?;
+            let cmd = MigrateCommand::from_iter(&[
+                "migrate",
+                "--manifest-path",
+                path.join("Cargo.toml").to_str().unwrap(),
+                "--from",
+                "3.0.0-rc4",
+                "--to",
+                "3.0.0-rc6",
+            ]);
+
+            // when
+            let report = cmd.exec()?;
+
+            // then
+            assert_eq!(
+                report,
+                "No migrations from ink! 3.0.0-rc4 to 3.0.0-rc6 apply"
+            );
+            assert_eq!(fs::read_to_string(path.join("lib.rs"))?, SOURCE);
+            Ok(())
+        })
+    }
+}
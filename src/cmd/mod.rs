@@ -14,14 +14,109 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+mod abi_snapshot;
+#[cfg(feature = "extrinsics")]
+mod apply;
+mod audit;
+#[cfg(feature = "extrinsics")]
+mod bench;
+mod bindgen;
 pub mod build;
 #[cfg(feature = "extrinsics")]
+mod bundle;
+#[cfg(feature = "extrinsics")]
+mod call;
+#[cfg(feature = "extrinsics")]
+mod consistency;
+mod convert_metadata;
+mod coverage;
+#[cfg(feature = "extrinsics")]
 mod deploy;
+mod doc;
+mod expand;
+#[cfg(feature = "extrinsics")]
+mod fork;
+mod fuzz;
+#[cfg(feature = "extrinsics")]
+mod gas_report;
+mod hash;
+mod import_abi;
+mod init;
+mod inspect;
 #[cfg(feature = "extrinsics")]
 mod instantiate;
+#[cfg(feature = "extrinsics")]
+mod key;
+mod lint;
 pub mod metadata;
+mod metadata_schema;
+mod migrate;
 pub mod new;
+#[cfg(feature = "extrinsics")]
+mod node;
+mod proptest;
+mod publish_metadata;
+#[cfg(feature = "extrinsics")]
+mod receipt;
+mod registry;
+#[cfg(feature = "extrinsics")]
+mod repl;
+#[cfg(feature = "extrinsics")]
+mod replay;
+mod resolve_trap;
+#[cfg(feature = "extrinsics")]
+mod run;
+#[cfg(feature = "extrinsics")]
+mod scale_json;
+#[cfg(feature = "extrinsics")]
+mod scenario;
+mod selector;
+#[cfg(feature = "extrinsics")]
+mod serve;
+mod solidity_abi;
+mod storage_diff;
+mod storage_migration;
+mod templates;
+mod test;
+pub mod trait_definition;
+#[cfg(feature = "extrinsics")]
+mod tui;
+mod upgrade_deps;
+#[cfg(feature = "extrinsics")]
+mod verify;
+pub mod verify_metadata;
+mod wat;
 
-pub(crate) use self::build::{BuildCommand, CheckCommand};
+pub(crate) use self::{
+    abi_snapshot::AbiSnapshotCommand,
+    audit::AuditCommand,
+    bindgen::BindgenCommand,
+    build::{BuildCommand, CheckCommand},
+    doc::DocCommand,
+    expand::ExpandCommand,
+    fuzz::FuzzCommand,
+    hash::HashCommand,
+    import_abi::ImportAbiCommand,
+    init::InitCommand,
+    inspect::InspectCommand,
+    lint::LintCommand,
+    migrate::MigrateCommand,
+    proptest::ProptestCommand,
+    publish_metadata::PublishMetadataCommand,
+    registry::{PublishCommand, PullCommand},
+    resolve_trap::ResolveTrapCommand,
+    selector::SelectorCommand,
+    templates::{TemplateIndexOpts, TemplatesCommand},
+    test::TestCommand,
+    upgrade_deps::UpgradeDepsCommand,
+    verify_metadata::MetadataCommand,
+    wat::WatCommand,
+};
 #[cfg(feature = "extrinsics")]
-pub(crate) use self::{deploy::execute_deploy, instantiate::execute_instantiate};
+pub(crate) use self::{
+    apply::ApplyCommand, bench::BenchCommand, bundle::BundleCommand, call::CallCommand,
+    consistency::ConsistencyCommand, deploy::execute_deploy, fork::ForkCommand,
+    instantiate::execute_instantiate, key::KeyCommand, node::NodeCommand, receipt::ReceiptOpts,
+    repl::ReplCommand, replay::ReplayCommand, run::RunCommand, scenario::ScenarioCommand,
+    serve::ServeCommand, tui::TuiCommand, verify::VerifyCommand,
+};
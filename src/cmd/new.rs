@@ -15,18 +15,542 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::{
-    env, fs,
-    io::{Cursor, Read, Seek, SeekFrom, Write},
-    path::Path,
+    collections::{BTreeMap, BTreeSet},
+    env,
+    ffi::OsStr,
+    fmt, fs,
+    io::{self, BufRead, Cursor, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use heck::CamelCase as _;
+use regex::Regex;
+use walkdir::WalkDir;
 
+use super::templates::{TemplateIndex, TemplateIndexOpts};
+
+/// The version of ink! the bundled templates depend on.
+pub(crate) const TEMPLATE_INK_VERSION: &str = "3.0.0-rc3";
+
+/// Variables derived from the name of the contract, which can't be supplied.
+const DERIVED_VARIABLES: [&str; 2] = ["name", "camel_name"];
+
+/// Variables of the workspace template, wiring the contracts of a workspace together.
+const WORKSPACE_VARIABLES: [&str; 10] = [
+    "members",
+    "traits",
+    "dependencies",
+    "std_features",
+    "imports",
+    "fields",
+    "constructor_params",
+    "constructor_fields",
+    "messages",
+    "test_args",
+];
+
+/// Well-known template variables as `(variable, description, default)`, which are prompted for if
+/// a template contains them and they are not supplied.
+const PROMPTED_VARIABLES: [(&str, &str, &str); 4] = [
+    ("author", "Author", "[your_name] <[your_email]>"),
+    ("license", "License", "Apache-2.0"),
+    ("symbol", "Token symbol", "TKN"),
+    ("decimals", "Token decimals", "18"),
+];
+
+/// The contract generated by `cargo contract new`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Template {
+    /// A minimal contract flipping a boolean.
+    Flipper,
+    /// An ERC-20 fungible token.
+    Erc20,
+    /// An ERC-721 non-fungible token.
+    Erc721,
+    /// A multisig wallet.
+    Multisig,
+    /// A domain name service.
+    Dns,
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Template::Flipper
+    }
+}
+
+impl Template {
+    /// All bundled templates.
+    pub(crate) const ALL: [Template; 5] = [
+        Template::Flipper,
+        Template::Erc20,
+        Template::Erc721,
+        Template::Multisig,
+        Template::Dns,
+    ];
+
+    /// A short description of the contract generated by the template.
+    pub(crate) fn description(&self) -> &'static str {
+        match self {
+            Template::Flipper => "A minimal contract flipping a boolean",
+            Template::Erc20 => "An ERC-20 fungible token with allowances",
+            Template::Erc721 => "An ERC-721 non-fungible token with approvals",
+            Template::Multisig => "A multisig wallet transferring funds once enough owners confirm",
+            Template::Dns => "A domain name service mapping names to addresses",
+        }
+    }
+
+    /// The directory of the example in the examples archive, `None` for the plain template.
+    fn example_dir(&self) -> Option<&'static str> {
+        match self {
+            Template::Flipper => None,
+            Template::Erc20 => Some("erc20/"),
+            Template::Erc721 => Some("erc721/"),
+            Template::Multisig => Some("multisig/"),
+            Template::Dns => Some("dns/"),
+        }
+    }
+}
+
+impl FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "flipper" => Ok(Template::Flipper),
+            "erc20" => Ok(Template::Erc20),
+            "erc721" => Ok(Template::Erc721),
+            "multisig" => Ok(Template::Multisig),
+            "dns" => Ok(Template::Dns),
+            _ => anyhow::bail!(
+                "Template must be one of `flipper`, `erc20`, `erc721`, `multisig` or `dns`"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Template::Flipper => write!(f, "flipper"),
+            Template::Erc20 => write!(f, "erc20"),
+            Template::Erc721 => write!(f, "erc721"),
+            Template::Multisig => write!(f, "multisig"),
+            Template::Dns => write!(f, "dns"),
+        }
+    }
+}
+
+/// A value of a template variable supplied as `<variable>=<value>`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TemplateVariable {
+    name: String,
+    value: String,
+}
+
+impl FromStr for TemplateVariable {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let mut parts = input.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) if !name.is_empty() => Ok(TemplateVariable {
+                name: name.to_string(),
+                value: value.to_string(),
+            }),
+            _ => anyhow::bail!("Template variables must be given as <variable>=<value>"),
+        }
+    }
+}
+
+/// The values of the template variables, either supplied or prompted for.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct TemplateVariables {
+    /// The supplied values.
+    values: BTreeMap<String, String>,
+    /// Prompt for the values of variables which are not supplied, instead of using the defaults.
+    prompt: bool,
+}
+
+impl TemplateVariables {
+    pub fn new(variables: &[TemplateVariable], prompt: bool) -> Result<Self> {
+        let mut values = BTreeMap::new();
+        for variable in variables {
+            if DERIVED_VARIABLES.contains(&variable.name.as_str()) {
+                anyhow::bail!(
+                    "The template variable {} is derived from the name of the contract",
+                    variable.name
+                )
+            }
+            values.insert(variable.name.clone(), variable.value.clone());
+        }
+        Ok(Self { values, prompt })
+    }
+
+    /// Returns the variables with the `value` of the `variable`.
+    pub(super) fn with(mut self, variable: &str, value: String) -> Self {
+        self.values.insert(variable.to_string(), value);
+        self
+    }
+
+    /// Returns the values of the `used` variables for the contract `name`.
+    fn resolve(&self, name: &str, used: &BTreeSet<String>) -> Result<BTreeMap<String, String>> {
+        let mut values = BTreeMap::new();
+        for variable in used {
+            let value = match variable.as_str() {
+                "name" => name.to_string(),
+                "camel_name" => name.to_camel_case(),
+                _ if self.values.contains_key(variable) => self.values[variable].clone(),
+                "module_name" => name.to_string(),
+                _ => {
+                    let (description, default) = PROMPTED_VARIABLES
+                        .iter()
+                        .find(|(prompted, _, _)| prompted == variable)
+                        .map_or((variable.as_str(), None), |(_, description, default)| {
+                            (*description, Some(*default))
+                        });
+                    match default {
+                        _ if self.prompt => prompt(variable, description, default)?,
+                        Some(default) => default.to_string(),
+                        None => anyhow::bail!(
+                            "The template requires a value of {}, supply it via --var {}=<value>",
+                            variable,
+                            variable
+                        ),
+                    }
+                }
+            };
+            values.insert(variable.clone(), value);
+        }
+        Ok(values)
+    }
+}
+
+/// Creates a new contract project `name` from the default template, using the defaults of the
+/// template variables.
+#[cfg(test)]
 pub(crate) fn execute<P>(name: &str, dir: Option<P>) -> Result<Option<String>>
 where
     P: AsRef<Path>,
 {
+    execute_with_template(
+        name,
+        dir,
+        Template::default(),
+        &TemplateVariables::default(),
+    )
+}
+
+/// Creates a new contract project `name` from the `template`.
+pub(crate) fn execute_with_template<P>(
+    name: &str,
+    dir: Option<P>,
+    template: Template,
+    variables: &TemplateVariables,
+) -> Result<Option<String>>
+where
+    P: AsRef<Path>,
+{
+    let out_dir = out_dir(name, dir)?;
+
+    // the files of an example replace those of the plain template with the same name
+    let mut files = template_files(
+        include_bytes!(concat!(env!("OUT_DIR"), "/template.zip")),
+        "",
+    )?;
+    if let Some(example_dir) = template.example_dir() {
+        files.extend(template_files(
+            include_bytes!(concat!(env!("OUT_DIR"), "/examples.zip")),
+            example_dir,
+        )?);
+    }
+
+    write_files(name, &out_dir, files, variables)?;
+    Ok(Some(format!("Created contract {}", name)))
+}
+
+/// Returns the file `file_name` of the default template for the contract `name`, with the
+/// template variables substituted by their defaults.
+pub(crate) fn default_template_file(name: &str, file_name: &str) -> Result<String> {
+    let files = template_files(
+        include_bytes!(concat!(env!("OUT_DIR"), "/template.zip")),
+        "",
+    )?;
+    let (contents, _) = files
+        .get(file_name)
+        .ok_or_else(|| anyhow::anyhow!("The template has no file {}", file_name))?;
+    let placeholder = placeholder();
+    let used = placeholder
+        .captures_iter(contents)
+        .map(|captures| captures[1].to_string())
+        .collect();
+    let values = TemplateVariables::default().resolve(name, &used)?;
+    Ok(placeholder
+        .replace_all(contents, |captures: &regex::Captures| {
+            values[&captures[1]].clone()
+        })
+        .into_owned())
+}
+
+/// Creates a new contract project `name` from the bundled template or the community template of
+/// the template index with the name `template`.
+pub(crate) fn execute_named<P>(
+    name: &str,
+    dir: Option<P>,
+    template: &str,
+    index: &TemplateIndexOpts,
+    variables: &TemplateVariables,
+) -> Result<Option<String>>
+where
+    P: AsRef<Path>,
+{
+    if let Ok(template) = template.parse::<Template>() {
+        return execute_with_template(name, dir, template, variables);
+    }
+    let index = TemplateIndex::load(index)?;
+    let entry = index.find(template).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown template {}, see `cargo contract templates list` for the available templates",
+            template
+        )
+    })?;
+    match &entry.repository {
+        Some(repository) => {
+            execute_from_git(name, dir, repository, entry.tag.as_deref(), variables)
+        }
+        None => anyhow::bail!("Template {} has no repository", template),
+    }
+}
+
+/// Creates a new contract project `name` from the template in the git `repository`, checked out
+/// at `tag` or the default branch.
+///
+/// The files of the repository are parameterized like the bundled templates: the template
+/// variables are substituted, and a `_Cargo.toml` is renamed to `Cargo.toml`.
+pub(crate) fn execute_from_git<P>(
+    name: &str,
+    dir: Option<P>,
+    repository: &str,
+    tag: Option<&str>,
+    variables: &TemplateVariables,
+) -> Result<Option<String>>
+where
+    P: AsRef<Path>,
+{
+    let out_dir = out_dir(name, dir)?;
+    let files = git_template_files(repository, tag)?;
+    write_files(name, &out_dir, files, variables)?;
+    Ok(Some(format!(
+        "Created contract {} from {}{}",
+        name,
+        repository,
+        tag.map(|tag| format!(" at {}", tag)).unwrap_or_default()
+    )))
+}
+
+/// Creates a new cargo workspace `name` with a contract crate per name of `contracts` and a crate
+/// of traits shared by the contracts.
+///
+/// The first contract is wired to call the other contracts of the workspace, which are its
+/// dependencies with the `ink-as-dependency` feature.
+pub(crate) fn execute_workspace<P>(
+    name: &str,
+    dir: Option<P>,
+    contracts: &[String],
+    variables: &TemplateVariables,
+) -> Result<Option<String>>
+where
+    P: AsRef<Path>,
+{
+    let out_dir = out_dir(name, dir)?;
+    let traits = format!("{}_traits", name);
+    if contracts.is_empty() {
+        anyhow::bail!("A workspace requires at least one contract")
+    }
+    for (index, contract) in contracts.iter().enumerate() {
+        validate_name(contract)?;
+        if contract == "traits" || *contract == traits || contracts[..index].contains(contract) {
+            anyhow::bail!(
+                "The contract name {} is used twice in the workspace",
+                contract
+            )
+        }
+    }
+
+    let files = template_files(
+        include_bytes!(concat!(env!("OUT_DIR"), "/workspace.zip")),
+        "",
+    )?;
+    let (root_files, files): (BTreeMap<_, _>, BTreeMap<_, _>) = files
+        .into_iter()
+        .partition(|(file_name, _)| !file_name.contains('/'));
+    let (trait_files, contract_files): (BTreeMap<_, _>, BTreeMap<_, _>) = files
+        .into_iter()
+        .partition(|(file_name, _)| file_name.starts_with("traits/"));
+    let strip_dir = |files: BTreeMap<String, (String, Option<u32>)>, dir: &str| {
+        files
+            .into_iter()
+            .filter_map(|(file_name, file)| {
+                file_name
+                    .strip_prefix(dir)
+                    .filter(|file_name| !file_name.is_empty())
+                    .map(|file_name| (file_name.to_string(), file))
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+    let trait_files = strip_dir(trait_files, "traits/");
+    let contract_files = strip_dir(contract_files, "contract/");
+
+    // resolve the variables shared by all crates once, so they are only prompted for once
+    let placeholder = placeholder();
+    let shared = root_files
+        .values()
+        .chain(trait_files.values())
+        .chain(contract_files.values())
+        .flat_map(|(contents, _)| placeholder.captures_iter(contents))
+        .map(|captures| captures[1].to_string())
+        .filter(|variable| {
+            // the module name is derived from the name of each crate
+            !DERIVED_VARIABLES.contains(&variable.as_str())
+                && !WORKSPACE_VARIABLES.contains(&variable.as_str())
+                && variable != "module_name"
+        })
+        .collect();
+    let variables = TemplateVariables {
+        values: variables.resolve(name, &shared)?,
+        prompt: false,
+    }
+    .with("traits", traits.clone());
+
+    let members = std::iter::once("traits")
+        .chain(contracts.iter().map(String::as_str))
+        .map(|member| format!("    \"{}\",\n", member))
+        .collect();
+    write_files(
+        name,
+        &out_dir,
+        root_files,
+        &variables.clone().with("members", members),
+    )?;
+    write_files(&traits, &out_dir.join("traits"), trait_files, &variables)?;
+    for (index, contract) in contracts.iter().enumerate() {
+        let callees = if index == 0 { &contracts[1..] } else { &[] };
+        write_files(
+            contract,
+            &out_dir.join(contract),
+            contract_files.clone(),
+            &wiring(variables.clone(), callees),
+        )?;
+    }
+
+    Ok(Some(format!(
+        "Created workspace {} with the contracts {}",
+        name,
+        contracts.join(", ")
+    )))
+}
+
+/// Returns the `variables` with the values wiring a contract to call the `callees`.
+fn wiring(variables: TemplateVariables, callees: &[String]) -> TemplateVariables {
+    let concat = |snippet: &dyn Fn(&str, &str) -> String| {
+        callees
+            .iter()
+            .map(|callee| snippet(callee, &callee.to_camel_case()))
+            .collect::<String>()
+    };
+    let imports = if callees.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "    use ink_env::call::FromAccountId;\n{}",
+            concat(&|callee, camel| format!("    use {}::{};\n", callee, camel))
+        )
+    };
+    variables
+        .with(
+            "dependencies",
+            concat(&|callee, _| {
+                format!(
+                    "{} = {{ path = \"../{}\", default-features = false, features = \
+                    [\"ink-as-dependency\"] }}\n",
+                    callee, callee
+                )
+            }),
+        )
+        .with(
+            "std_features",
+            concat(&|callee, _| format!("    \"{}/std\",\n", callee)),
+        )
+        .with("imports", imports)
+        .with(
+            "fields",
+            concat(&|callee, _| {
+                format!(
+                    "        /// The {} contract.\n        {}: AccountId,\n",
+                    callee, callee
+                )
+            }),
+        )
+        .with(
+            "constructor_params",
+            callees
+                .iter()
+                .map(|callee| format!("{}: AccountId", callee))
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+        .with(
+            "constructor_fields",
+            concat(&|callee, _| format!("                {},\n", callee)),
+        )
+        .with(
+            "messages",
+            concat(&|callee, camel| {
+                format!(
+                    "\n        /// Returns the version of the {callee} contract.\n        \
+                    #[ink(message)]\n        \
+                    pub fn {callee}_version(&self) -> u32 {{\n            \
+                    let {callee}: {camel} = \
+                    FromAccountId::from_account_id(self.{callee});\n            \
+                    {callee}.version()\n        \
+                    }}\n",
+                    callee = callee,
+                    camel = camel
+                )
+            }),
+        )
+        .with(
+            "test_args",
+            callees
+                .iter()
+                .map(|_| "AccountId::from([0x01; 32])")
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+}
+
+/// Returns the directory of the new contract project `name`, checking that the name is valid and
+/// that there is no Cargo package in the directory yet.
+pub(super) fn out_dir<P>(name: &str, dir: Option<P>) -> Result<PathBuf>
+where
+    P: AsRef<Path>,
+{
+    validate_name(name)?;
+
+    let out_dir = dir
+        .map_or(env::current_dir()?, |p| p.as_ref().to_path_buf())
+        .join(name);
+    if out_dir.join("Cargo.toml").exists() {
+        anyhow::bail!("A Cargo package already exists in {}", name);
+    }
+    Ok(out_dir)
+}
+
+/// Checks that `name` is a valid name of a contract.
+pub(super) fn validate_name(name: &str) -> Result<()> {
     if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
         anyhow::bail!("Contract names can only contain alphanumeric characters and underscores");
     }
@@ -39,36 +563,37 @@ where
     {
         anyhow::bail!("Contract names must begin with an alphabetic character");
     }
+    Ok(())
+}
+
+/// Writes the template `files` to `out_dir`, substituting the template variables.
+pub(super) fn write_files(
+    name: &str,
+    out_dir: &Path,
+    files: BTreeMap<String, (String, Option<u32>)>,
+    variables: &TemplateVariables,
+) -> Result<()> {
+    let placeholder = placeholder();
+    let used = files
+        .values()
+        .flat_map(|(contents, _)| placeholder.captures_iter(contents))
+        .map(|captures| captures[1].to_string())
+        .collect();
+    let values = variables.resolve(name, &used)?;
 
-    let out_dir = dir
-        .map_or(env::current_dir()?, |p| p.as_ref().to_path_buf())
-        .join(name);
-    if out_dir.join("Cargo.toml").exists() {
-        anyhow::bail!("A Cargo package already exists in {}", name);
-    }
     if !out_dir.exists() {
         fs::create_dir(&out_dir)?;
     }
 
-    let template = include_bytes!(concat!(env!("OUT_DIR"), "/template.zip"));
-    let mut cursor = Cursor::new(Vec::new());
-    cursor.write_all(template)?;
-    cursor.seek(SeekFrom::Start(0))?;
-
-    let mut archive = zip::ZipArchive::new(cursor)?;
-
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-
+    for (file_name, (contents, mode)) in files {
         // replace template placeholders
-        let contents = contents.replace("{{name}}", name);
-        let contents = contents.replace("{{camel_name}}", &name.to_camel_case());
+        let contents = placeholder.replace_all(&contents, |captures: &regex::Captures| {
+            values[&captures[1]].clone()
+        });
 
-        let outpath = out_dir.join(file.name());
+        let outpath = out_dir.join(&file_name);
 
-        if (&*file.name()).ends_with('/') {
+        if file_name.ends_with('/') {
             fs::create_dir_all(&outpath)?;
         } else {
             if let Some(p) = outpath.parent() {
@@ -82,7 +607,7 @@ where
                 .open(outpath.clone())
                 .map_err(|e| {
                     if e.kind() == std::io::ErrorKind::AlreadyExists {
-                        anyhow::anyhow!("New contract file {} already exists", file.name())
+                        anyhow::anyhow!("New contract file {} already exists", file_name)
                     } else {
                         anyhow::anyhow!(e)
                     }
@@ -91,18 +616,136 @@ where
             outfile.write_all(contents.as_bytes())?;
         }
 
-        // Get and set permissions
+        // Set permissions
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
 
-            if let Some(mode) = file.unix_mode() {
+            if let Some(mode) = mode {
                 fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))?;
             }
         }
+        #[cfg(not(unix))]
+        let _ = mode;
     }
 
-    Ok(Some(format!("Created contract {}", name)))
+    Ok(())
+}
+
+/// Returns the regex matching the placeholders of template variables, handlebars-style
+/// `{{variable}}` or `{{ variable }}`.
+fn placeholder() -> Regex {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").expect("invalid regex")
+}
+
+/// Prompts for the value of the template `variable`, defaulting to `default` if the answer is
+/// empty.
+fn prompt(variable: &str, description: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) => eprint!("{} [{}]: ", description, default),
+        None => eprint!("{}: ", description),
+    }
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    match (answer.trim(), default) {
+        ("", Some(default)) => Ok(default.to_string()),
+        ("", None) => anyhow::bail!("The template requires a value of {}", variable),
+        (answer, _) => Ok(answer.to_string()),
+    }
+}
+
+/// Clones the git `repository` at `tag` and returns the contents and permissions of its files,
+/// keyed by their path relative to the root of the repository.
+fn git_template_files(
+    repository: &str,
+    tag: Option<&str>,
+) -> Result<BTreeMap<String, (String, Option<u32>)>> {
+    let clone_dir = tempfile::Builder::new()
+        .prefix("cargo-contract-template_")
+        .tempdir()?;
+    let mut args = vec!["clone", "--quiet", "--depth", "1"];
+    if let Some(tag) = tag {
+        args.extend(&["--branch", tag]);
+    }
+    let status = Command::new("git")
+        .args(&args)
+        .arg(repository)
+        .arg(clone_dir.path())
+        .status()
+        .context("Executing `git clone` failed, git is required for templates from git")?;
+    if !status.success() {
+        anyhow::bail!("Cloning the template {} failed", repository)
+    }
+
+    let mut files = BTreeMap::new();
+    let entries = WalkDir::new(clone_dir.path())
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git");
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let mut name = entry.path().strip_prefix(clone_dir.path())?.to_path_buf();
+        // Cargo.toml files cause the folder to be excluded from `cargo package`, templates may
+        // therefore name them `_Cargo.toml`
+        if name.file_name() == Some(OsStr::new("_Cargo.toml")) {
+            name.set_file_name("Cargo.toml");
+        }
+        let file_name = name
+            .iter()
+            .map(|component| component.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let contents = fs::read_to_string(entry.path()).context(format!(
+            "Failed to read {} of the template, only text files are supported",
+            file_name
+        ))?;
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(entry.metadata()?.permissions().mode())
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+        files.insert(file_name, (contents, mode));
+    }
+
+    if !files.contains_key("Cargo.toml") {
+        anyhow::bail!(
+            "The template {} contains no Cargo.toml in the root of the repository",
+            repository
+        )
+    }
+    Ok(files)
+}
+
+/// Returns the contents and permissions of the files in the zip `archive` below `prefix`, keyed
+/// by their path relative to `prefix`.
+pub(super) fn template_files(
+    archive: &[u8],
+    prefix: &str,
+) -> Result<BTreeMap<String, (String, Option<u32>)>> {
+    let mut cursor = Cursor::new(Vec::new());
+    cursor.write_all(archive)?;
+    cursor.seek(SeekFrom::Start(0))?;
+
+    let mut archive = zip::ZipArchive::new(cursor)?;
+    let mut files = BTreeMap::new();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let file_name = match file.name().strip_prefix(prefix) {
+            Some(file_name) if !file_name.is_empty() => file_name.to_string(),
+            _ => continue,
+        };
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        files.insert(file_name, (contents, file.unix_mode()));
+    }
+
+    Ok(files)
 }
 
 #[cfg(test)]
@@ -165,6 +808,229 @@ mod tests {
         })
     }
 
+    #[test]
+    fn example_templates_replace_the_contract() {
+        with_tmp_dir(|path| {
+            // given
+            let name = "my_token";
+
+            // when
+            execute_with_template(
+                name,
+                Some(path),
+                Template::Erc20,
+                &TemplateVariables::default(),
+            )?;
+
+            // then
+            let lib = fs::read_to_string(path.join(name).join("lib.rs"))?;
+            let manifest = fs::read_to_string(path.join(name).join("Cargo.toml"))?;
+            assert!(lib.contains("mod my_token {"));
+            assert!(lib.contains("pub struct MyToken {"));
+            assert!(lib.contains("pub fn total_supply(&self) -> Balance"));
+            assert!(manifest.contains("name = \"my_token\""));
+            assert!(path.join(name).join(".gitignore").exists());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn example_templates_can_replace_the_manifest() {
+        with_tmp_dir(|path| {
+            execute_with_template(
+                "wallet",
+                Some(path),
+                Template::Multisig,
+                &TemplateVariables::default(),
+            )?;
+
+            let manifest = fs::read_to_string(path.join("wallet").join("Cargo.toml"))?;
+            assert!(manifest.contains("ink_prelude"));
+            assert!(manifest.contains("name = \"wallet\""));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn templates_are_cloned_from_git() {
+        with_tmp_dir(|path| {
+            // given
+            let repository = path.join("template");
+            fs::create_dir_all(repository.join("src"))?;
+            fs::write(
+                repository.join("_Cargo.toml"),
+                "[package]\nname = \"{{name}}\"\n",
+            )?;
+            fs::write(
+                repository.join("src").join("lib.rs"),
+                "pub struct {{camel_name}};\n",
+            )?;
+            let git = |args: &[&str]| {
+                let status = Command::new("git")
+                    .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                    .args(args)
+                    .current_dir(&repository)
+                    .status()
+                    .expect("git must be installed");
+                assert!(status.success(), "git {:?} failed", args);
+            };
+            git(&["init", "--quiet"]);
+            git(&["add", "."]);
+            git(&["commit", "--quiet", "-m", "template"]);
+            git(&["tag", "v1.2"]);
+            fs::write(repository.join("src").join("lib.rs"), "untagged")?;
+            git(&["commit", "--quiet", "-am", "untagged"]);
+            let url = url::Url::from_file_path(&repository).expect("path must be absolute");
+
+            // when
+            execute_from_git(
+                "my_token",
+                Some(path),
+                url.as_str(),
+                Some("v1.2"),
+                &TemplateVariables::default(),
+            )?;
+
+            // then
+            let project = path.join("my_token");
+            assert_eq!(
+                fs::read_to_string(project.join("Cargo.toml"))?,
+                "[package]\nname = \"my_token\"\n"
+            );
+            assert_eq!(
+                fs::read_to_string(project.join("src").join("lib.rs"))?,
+                "pub struct MyToken;\n"
+            );
+            assert!(!project.join(".git").exists());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn template_variables_are_substituted() {
+        with_tmp_dir(|path| {
+            // given
+            let variables: Vec<TemplateVariable> = vec![
+                "author=Jane Doe <jane@example.com>".parse()?,
+                "symbol=MTK".parse()?,
+            ];
+            let variables = TemplateVariables::new(&variables, false)?;
+
+            // when
+            execute_with_template("my_token", Some(path), Template::Erc20, &variables)?;
+
+            // then
+            let lib = fs::read_to_string(path.join("my_token").join("lib.rs"))?;
+            let manifest = fs::read_to_string(path.join("my_token").join("Cargo.toml"))?;
+            assert!(manifest.contains("authors = [\"Jane Doe <jane@example.com>\"]"));
+            assert!(manifest.contains("license = \"Apache-2.0\""));
+            assert!(lib.contains("mod my_token {"));
+            assert!(lib.contains("\"MTK\""));
+            assert!(!lib.contains("{{"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn unknown_template_variables_require_a_value() {
+        // given
+        let used = vec!["name".to_string(), "owner".to_string()]
+            .into_iter()
+            .collect();
+        let owner: TemplateVariable = "owner=alice".parse().unwrap();
+        let supplied = TemplateVariables::new(&[owner], false).unwrap();
+
+        // when
+        let missing = TemplateVariables::default().resolve("contract", &used);
+        let values = supplied.resolve("contract", &used).unwrap();
+
+        // then
+        assert!(missing.is_err());
+        assert_eq!(values["name"], "contract");
+        assert_eq!(values["owner"], "alice");
+    }
+
+    #[test]
+    fn derived_template_variables_cannot_be_supplied() {
+        let variables: Vec<TemplateVariable> = vec!["name=other".parse().unwrap()];
+        assert!(TemplateVariables::new(&variables, false).is_err());
+        assert!("symbol".parse::<TemplateVariable>().is_err());
+    }
+
+    #[test]
+    fn workspace_contracts_are_wired() {
+        with_tmp_dir(|path| {
+            // given
+            let contracts = vec![
+                "router".to_string(),
+                "factory".to_string(),
+                "pair".to_string(),
+            ];
+
+            // when
+            execute_workspace("dex", Some(path), &contracts, &TemplateVariables::default())?;
+
+            // then
+            let workspace = path.join("dex");
+            let root = fs::read_to_string(workspace.join("Cargo.toml"))?;
+            assert!(root.contains("\"traits\",\n    \"router\",\n    \"factory\",\n    \"pair\""));
+            let traits = fs::read_to_string(workspace.join("traits").join("Cargo.toml"))?;
+            assert!(traits.contains("name = \"dex_traits\""));
+
+            let router = fs::read_to_string(workspace.join("router").join("Cargo.toml"))?;
+            assert!(router.contains(
+                "factory = { path = \"../factory\", default-features = false, \
+                features = [\"ink-as-dependency\"] }"
+            ));
+            assert!(router.contains("\"pair/std\""));
+            let router = fs::read_to_string(workspace.join("router").join("lib.rs"))?;
+            assert!(router.contains("pub fn new(factory: AccountId, pair: AccountId) -> Self"));
+            assert!(router.contains("pub fn pair_version(&self) -> u32"));
+            assert!(router.contains("use dex_traits::Versioned;"));
+
+            let pair = fs::read_to_string(workspace.join("pair").join("lib.rs"))?;
+            assert!(pair.contains("pub fn new() -> Self"));
+            assert!(!pair.contains("FromAccountId"));
+            let pair = fs::read_to_string(workspace.join("pair").join("Cargo.toml"))?;
+            assert!(!pair.contains("ink-as-dependency\"]"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn workspace_contract_names_must_be_unique() {
+        with_tmp_dir(|path| {
+            let contracts = vec!["pair".to_string(), "pair".to_string()];
+            let result = execute_workspace("dex", Some(path), &contracts, &Default::default());
+            assert!(result.is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn templates_are_parsed() {
+        for template in &Template::ALL {
+            let parsed = template
+                .to_string()
+                .parse::<Template>()
+                .expect("template must parse");
+            assert_eq!(parsed, *template);
+        }
+        assert!("erc1155".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn templates_depend_on_template_ink_version() {
+        with_tmp_dir(|path| {
+            execute("ink_version", Some(path))?;
+
+            let manifest = fs::read_to_string(path.join("ink_version").join("Cargo.toml"))?;
+            let dependency = format!("ink_lang = {{ version = \"{}\"", TEMPLATE_INK_VERSION);
+            assert!(manifest.contains(&dependency));
+            Ok(())
+        })
+    }
+
     #[test]
     fn dont_overwrite_existing_files_not_in_cargo_project() {
         with_tmp_dir(|path| {
@@ -0,0 +1,813 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Managing a local development node of the project, running a pinned release of
+//! `substrate-contracts-node` natively or in a Docker container.
+
+use super::metadata::blake2_hash;
+use crate::{crate_metadata::CrateMetadata, rpc::RpcClient, util, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    convert::TryFrom,
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+use url::Url;
+use walkdir::WalkDir;
+
+/// The `substrate-contracts-node` release which is downloaded and run.
+const NODE_VERSION: &str = "v0.1.0";
+
+/// The SHA-256 digests of the archives of the `NODE_VERSION` release, by archive name.
+///
+/// The downloaded archive is verified against its digest before it is unpacked. There is no
+/// download for platforms without a digest.
+const NODE_DIGESTS: &[(&str, &str)] = &[];
+
+/// The image of the node run with `--docker`, pinned to this version of `cargo-contract`.
+///
+/// It is built from `docker/contracts-node/Dockerfile` and published for every release.
+const NODE_IMAGE: &str = concat!("paritytech/contracts-node:", env!("CARGO_PKG_VERSION"));
+
+/// The ports the node listens on inside its container.
+const CONTAINER_WS_PORT: u16 = 9944;
+const CONTAINER_RPC_PORT: u16 = 9933;
+
+/// Directory the chain of the node is kept in inside its container.
+const CONTAINER_CHAIN: &str = "/data";
+
+/// Environment variable of a node binary to run instead of the pinned release.
+const NODE_BINARY_ENV: &str = "CONTRACTS_NODE";
+
+/// How long to wait for a started node to answer RPC requests.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Manages a local development node of the project.
+///
+/// The node keeps its chain in `target/contracts-node` of the project, so that contracts deployed
+/// to it survive restarts until the chain is purged.
+#[derive(Debug, StructOpt)]
+pub enum NodeCommand {
+    /// Start the node in the background, downloading the pinned release if necessary
+    #[structopt(name = "start")]
+    Start(StartCommand),
+    /// Stop the node
+    #[structopt(name = "stop")]
+    Stop(NodeOpts),
+    /// Print whether the node is running, its urls and its best block
+    #[structopt(name = "status")]
+    Status(NodeOpts),
+    /// Print the log of the node
+    #[structopt(name = "logs")]
+    Logs(LogsCommand),
+    /// Remove the chain of the stopped node
+    #[structopt(name = "purge")]
+    Purge(NodeOpts),
+    /// Save the chain of the stopped node as a named snapshot, or restore it
+    #[structopt(name = "snapshot")]
+    Snapshot(SnapshotCommand),
+}
+
+impl NodeCommand {
+    pub fn exec(&self) -> Result<Option<String>> {
+        match self {
+            NodeCommand::Start(start) => start.exec().map(Some),
+            NodeCommand::Stop(opts) => opts.node_dir()?.stop().map(Some),
+            NodeCommand::Status(opts) => opts.node_dir()?.status().map(Some),
+            NodeCommand::Logs(logs) => logs.exec().map(|()| None),
+            NodeCommand::Purge(opts) => opts.node_dir()?.purge().map(Some),
+            NodeCommand::Snapshot(snapshot) => snapshot.exec().map(Some),
+        }
+    }
+}
+
+/// Manages snapshots of the chain of the node, e.g. with deployed contracts and funded accounts
+/// as fixture of tests, in `target/contracts-node/snapshots`.
+///
+/// Snapshots are supported for nodes run natively, whose chain is kept in the project.
+#[derive(Debug, StructOpt)]
+pub enum SnapshotCommand {
+    /// Save the chain of the stopped node as snapshot, replacing a snapshot of the same name
+    #[structopt(name = "save")]
+    Save(SnapshotOpts),
+    /// Replace the chain of the stopped node by the snapshot
+    #[structopt(name = "load")]
+    Load(SnapshotOpts),
+    /// List the snapshots
+    #[structopt(name = "list")]
+    List(NodeOpts),
+}
+
+impl SnapshotCommand {
+    pub fn exec(&self) -> Result<String> {
+        match self {
+            SnapshotCommand::Save(save) => save.opts.node_dir()?.save_snapshot(&save.name),
+            SnapshotCommand::Load(load) => load.opts.node_dir()?.load_snapshot(&load.name),
+            SnapshotCommand::List(opts) => opts.node_dir()?.list_snapshots(),
+        }
+    }
+}
+
+/// A snapshot of the node of the project.
+#[derive(Debug, StructOpt)]
+pub struct SnapshotOpts {
+    #[structopt(flatten)]
+    opts: NodeOpts,
+    /// The name of the snapshot
+    name: String,
+}
+
+/// The project whose node is managed.
+#[derive(Debug, StructOpt)]
+pub struct NodeOpts {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+}
+
+impl NodeOpts {
+    /// Returns the directory of the node of the project.
+    fn node_dir(&self) -> Result<NodeDir> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+        Ok(NodeDir(
+            crate_metadata
+                .cargo_meta
+                .target_directory
+                .join("contracts-node"),
+        ))
+    }
+}
+
+/// Starts the node of the project.
+#[derive(Debug, StructOpt)]
+pub struct StartCommand {
+    #[structopt(flatten)]
+    opts: NodeOpts,
+    /// The port of websocket connections, used by `cargo contract` and polkadot-js apps
+    #[structopt(long, default_value = "9944")]
+    ws_port: u16,
+    /// The port of HTTP RPC requests
+    #[structopt(long, default_value = "9933")]
+    rpc_port: u16,
+    /// Run the node in a Docker container of the image pinned to this release of
+    /// `cargo-contract`, for hosts without a node binary
+    #[structopt(long)]
+    docker: bool,
+}
+
+impl StartCommand {
+    pub fn exec(&self) -> Result<String> {
+        let node_dir = self.opts.node_dir()?;
+        if let Some(state) = node_dir.running()? {
+            anyhow::bail!("The node is already running at {}", state.ws_url())
+        }
+        fs::create_dir_all(&node_dir.0)?;
+        let (state, mut process) = if self.docker {
+            (self.start_container(&node_dir)?, None)
+        } else {
+            let (state, process) = self.start_process(&node_dir)?;
+            (state, Some(process))
+        };
+
+        let rpc = RpcClient::new(&state.rpc_url())?;
+        let started = Instant::now();
+        while rpc.request::<Value>("system_health", json!([])).is_err() {
+            let exited = match &mut process {
+                Some(process) => process.try_wait()?.is_some(),
+                None => !state.is_alive(),
+            };
+            if exited {
+                anyhow::bail!("The node exited on startup, see `cargo contract node logs`")
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                state.stop()?;
+                anyhow::bail!(
+                    "The node didn't answer RPC requests within {}s, see \
+                    `cargo contract node logs`",
+                    STARTUP_TIMEOUT.as_secs()
+                )
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+        fs::write(node_dir.state(), serde_json::to_string_pretty(&state)?)?;
+        Ok(format!(
+            "{} the node at {}",
+            "Started".green().bold(),
+            state.ws_url()
+        ))
+    }
+
+    /// Starts the node binary in the background, logging to `node.log` of the `node_dir`.
+    fn start_process(&self, node_dir: &NodeDir) -> Result<(NodeState, Child)> {
+        let (binary, version) = match std::env::var(NODE_BINARY_ENV) {
+            Ok(binary) => (PathBuf::from(binary), None),
+            Err(_) => (find_or_download()?, Some(NODE_VERSION.to_string())),
+        };
+        let base_path = node_dir.chain().display().to_string();
+        let log = fs::File::create(node_dir.log())?;
+        let process = Command::new(&binary)
+            .args(node_args(&base_path, self.ws_port, self.rpc_port, false))
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()
+            .context(format!("Failed to start {}", binary.display()))?;
+        let state = NodeState {
+            pid: Some(process.id()),
+            container: None,
+            version,
+            ws_port: self.ws_port,
+            rpc_port: self.rpc_port,
+        };
+        Ok((state, process))
+    }
+
+    /// Starts the node in a detached container, with its chain in a volume of the same name.
+    fn start_container(&self, node_dir: &NodeDir) -> Result<NodeState> {
+        let container = node_dir.container();
+        // the container of a previous run is kept for its logs until the next start
+        let _ = docker(&["rm", "--force", &container]);
+        // the log of the container is printed by `docker logs` instead
+        if node_dir.log().exists() {
+            fs::remove_file(node_dir.log())?;
+        }
+        docker(&container_args(&container, self.ws_port, self.rpc_port))?;
+        Ok(NodeState {
+            pid: None,
+            container: Some(container),
+            version: Some(NODE_IMAGE.into()),
+            ws_port: self.ws_port,
+            rpc_port: self.rpc_port,
+        })
+    }
+}
+
+/// Prints the log of the node.
+#[derive(Debug, StructOpt)]
+pub struct LogsCommand {
+    #[structopt(flatten)]
+    opts: NodeOpts,
+    /// Keep printing the log as the node writes it
+    #[structopt(short, long)]
+    follow: bool,
+}
+
+impl LogsCommand {
+    pub fn exec(&self) -> Result<()> {
+        let node_dir = self.opts.node_dir()?;
+        if !node_dir.log().exists() {
+            let container = node_dir.container();
+            let mut args = vec!["logs"];
+            if self.follow {
+                args.push("--follow");
+            }
+            args.push(&container);
+            let status = Command::new(docker_binary()?).args(&args).status()?;
+            if !status.success() {
+                anyhow::bail!("The node has no log, it was not started yet")
+            }
+            return Ok(());
+        }
+        let mut log = fs::File::open(node_dir.log())?;
+        loop {
+            let printed = io::copy(&mut log, &mut io::stdout())?;
+            if !self.follow {
+                return Ok(());
+            }
+            if printed == 0 {
+                thread::sleep(Duration::from_millis(500));
+            }
+        }
+    }
+}
+
+/// The state of a started node, stored in `node.json` of the node directory.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeState {
+    /// The process of a node run natively.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pid: Option<u32>,
+    /// The container of a node run with `--docker`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    container: Option<String>,
+    /// The release or image of the node, `None` if it was run from `CONTRACTS_NODE`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    ws_port: u16,
+    rpc_port: u16,
+}
+
+impl NodeState {
+    fn ws_url(&self) -> Url {
+        Url::parse(&format!("ws://127.0.0.1:{}", self.ws_port)).expect("the url is valid; qed")
+    }
+
+    fn rpc_url(&self) -> Url {
+        Url::parse(&format!("http://127.0.0.1:{}", self.rpc_port)).expect("the url is valid; qed")
+    }
+
+    /// Returns `true` if the process or the container of the node is running.
+    fn is_alive(&self) -> bool {
+        match (&self.container, self.pid) {
+            (Some(container), _) => {
+                docker(&["inspect", "--format", "{{.State.Running}}", container])
+                    .map_or(false, |running| running == "true")
+            }
+            (None, Some(pid)) => is_alive(pid),
+            (None, None) => false,
+        }
+    }
+
+    /// Stops the process or the container of the node.
+    fn stop(&self) -> Result<()> {
+        match (&self.container, self.pid) {
+            (Some(container), _) => docker(&["stop", container]).map(|_| ()),
+            (None, Some(pid)) => kill(pid),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// The directory of the node of a project, containing its chain, log and state.
+struct NodeDir(PathBuf);
+
+impl NodeDir {
+    fn chain(&self) -> PathBuf {
+        self.0.join("chain")
+    }
+
+    fn log(&self) -> PathBuf {
+        self.0.join("node.log")
+    }
+
+    fn state(&self) -> PathBuf {
+        self.0.join("node.json")
+    }
+
+    /// Returns the directory of the snapshot `name`.
+    fn snapshot(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+        if name.is_empty() || !valid {
+            anyhow::bail!(
+                "Invalid snapshot name '{}', use letters, digits, `-`, `_` and `.`",
+                name
+            )
+        }
+        Ok(self.0.join("snapshots").join(name))
+    }
+
+    /// Returns the name of the container and the volume of the node run with `--docker`, unique
+    /// to the project.
+    fn container(&self) -> String {
+        let hash = blake2_hash(self.0.display().to_string().as_bytes()).0;
+        let id = hash[..6]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        format!("cargo-contract-node-{}", id)
+    }
+
+    /// Returns the state of the node if it is running.
+    ///
+    /// The state of a node which is no longer running, e.g. after a reboot, is removed.
+    fn running(&self) -> Result<Option<NodeState>> {
+        let path = self.state();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let state: NodeState = serde_json::from_slice(&fs::read(&path)?)
+            .context(format!("Invalid node state {}", path.display()))?;
+        if state.is_alive() {
+            Ok(Some(state))
+        } else {
+            fs::remove_file(&path)?;
+            Ok(None)
+        }
+    }
+
+    fn stop(&self) -> Result<String> {
+        let state = self
+            .running()?
+            .ok_or_else(|| anyhow::anyhow!("The node is not running"))?;
+        state.stop()?;
+        fs::remove_file(self.state())?;
+        Ok(format!("{} the node", "Stopped".green().bold()))
+    }
+
+    fn status(&self) -> Result<String> {
+        let state = match self.running()? {
+            Some(state) => state,
+            None => return Ok("The node is not running".into()),
+        };
+        let header: Value = RpcClient::new(&state.rpc_url())?
+            .request("chain_getHeader", json!([]))
+            .unwrap_or_default();
+        let best_block = header["number"]
+            .as_str()
+            .and_then(|number| u64::from_str_radix(number.trim_start_matches("0x"), 16).ok())
+            .map_or_else(|| "unknown".to_string(), |number| number.to_string());
+        let (runner, id) = match (&state.container, state.pid) {
+            (Some(container), _) => ("Container:", container.clone()),
+            (None, pid) => (
+                "Process:",
+                pid.map(|pid| pid.to_string()).unwrap_or_default(),
+            ),
+        };
+        Ok(format!(
+            "{} {}\n\t{} {}\n\t{} {}\n\t{} {}\n\t{} {}",
+            "The node is".bold(),
+            "running".green().bold(),
+            "Version:".bold(),
+            state.version.as_deref().unwrap_or(NODE_BINARY_ENV),
+            runner.bold(),
+            id,
+            "Url:".bold(),
+            state.ws_url(),
+            "Best block:".bold(),
+            best_block
+        ))
+    }
+
+    fn purge(&self) -> Result<String> {
+        if self.running()?.is_some() {
+            anyhow::bail!("The node is running, stop it before purging its chain")
+        }
+        if self.chain().exists() {
+            fs::remove_dir_all(self.chain())?;
+        }
+        if which::which("docker").is_ok() {
+            // the chain of a node run with `--docker`, which may not exist
+            let _ = docker(&["rm", "--force", &self.container()]);
+            let _ = docker(&["volume", "rm", &self.container()]);
+        }
+        Ok(format!(
+            "{} the chain in {}",
+            "Purged".green().bold(),
+            self.chain().display()
+        ))
+    }
+
+    fn save_snapshot(&self, name: &str) -> Result<String> {
+        let snapshot = self.snapshot(name)?;
+        if self.running()?.is_some() {
+            anyhow::bail!("The node is running, stop it before saving a snapshot of its chain")
+        }
+        if !self.chain().exists() {
+            anyhow::bail!(
+                "The node has no chain in {}, start it first. Snapshots of nodes run with \
+                `--docker` are not supported",
+                self.chain().display()
+            )
+        }
+        // copied under a temporary name, so that an interrupted copy doesn't replace a snapshot
+        let copy = snapshot.with_file_name(format!("{}.partial", name));
+        if copy.exists() {
+            fs::remove_dir_all(&copy)?;
+        }
+        copy_dir(&self.chain(), &copy)?;
+        if snapshot.exists() {
+            fs::remove_dir_all(&snapshot)?;
+        }
+        fs::rename(&copy, &snapshot)?;
+        Ok(format!(
+            "{} the chain as snapshot {}",
+            "Saved".green().bold(),
+            name.bold()
+        ))
+    }
+
+    fn load_snapshot(&self, name: &str) -> Result<String> {
+        let snapshot = self.snapshot(name)?;
+        if !snapshot.exists() {
+            anyhow::bail!(
+                "There is no snapshot {}, see `cargo contract node snapshot list`",
+                name
+            )
+        }
+        if self.running()?.is_some() {
+            anyhow::bail!("The node is running, stop it before loading a snapshot")
+        }
+        if self.chain().exists() {
+            fs::remove_dir_all(self.chain())?;
+        }
+        copy_dir(&snapshot, &self.chain())?;
+        Ok(format!(
+            "{} the snapshot {}, start the node to continue its chain",
+            "Loaded".green().bold(),
+            name.bold()
+        ))
+    }
+
+    fn list_snapshots(&self) -> Result<String> {
+        let dir = self.0.join("snapshots");
+        if !dir.exists() {
+            return Ok("There are no snapshots".into());
+        }
+        let mut names = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry.path().is_dir() && entry.path().extension() != Some("partial".as_ref())
+            })
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            return Ok("There are no snapshots".into());
+        }
+        names.sort();
+        Ok(names.join("\n"))
+    }
+}
+
+/// Copies the directory `from` with all of its contents to `to`.
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .context(format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the arguments to run a development node with its chain in `base_path`.
+///
+/// With `external` the node listens on all interfaces, to be reachable from outside of its
+/// container.
+fn node_args(base_path: &str, ws_port: u16, rpc_port: u16, external: bool) -> Vec<String> {
+    let mut args = vec![
+        "--dev".into(),
+        "--base-path".into(),
+        base_path.into(),
+        "--ws-port".into(),
+        ws_port.to_string(),
+        "--rpc-port".into(),
+        rpc_port.to_string(),
+        // allow polkadot-js apps to connect
+        "--rpc-cors".into(),
+        "all".into(),
+        "--no-prometheus".into(),
+        "--no-telemetry".into(),
+    ];
+    if external {
+        args.extend(vec!["--ws-external".into(), "--rpc-external".into()]);
+    }
+    args
+}
+
+/// Returns the arguments of `docker` to run the node in the detached `container`, with its ports
+/// published on the `ws_port` and `rpc_port` of the host, bound to localhost only.
+fn container_args(container: &str, ws_port: u16, rpc_port: u16) -> Vec<String> {
+    let mut args = vec![
+        "run".into(),
+        "--detach".into(),
+        "--name".into(),
+        container.into(),
+        "--publish".into(),
+        format!("127.0.0.1:{}:{}", ws_port, CONTAINER_WS_PORT),
+        "--publish".into(),
+        format!("127.0.0.1:{}:{}", rpc_port, CONTAINER_RPC_PORT),
+        "--volume".into(),
+        format!("{}:{}", container, CONTAINER_CHAIN),
+        NODE_IMAGE.into(),
+    ];
+    args.extend(node_args(
+        CONTAINER_CHAIN,
+        CONTAINER_WS_PORT,
+        CONTAINER_RPC_PORT,
+        true,
+    ));
+    args
+}
+
+/// Returns the path of the `docker` binary.
+fn docker_binary() -> Result<PathBuf> {
+    which::which("docker").context(
+        "docker not found! `--docker` runs the node inside a Docker container.\n\
+        Install Docker from https://docs.docker.com/get-docker/",
+    )
+}
+
+/// Runs `docker` with the `args` and returns its trimmed output.
+fn docker<S: AsRef<std::ffi::OsStr>>(args: &[S]) -> Result<String> {
+    let output = Command::new(docker_binary()?).args(args).output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Docker failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Returns `true` if the process `pid` is running.
+fn is_alive(pid: u32) -> bool {
+    let status = if cfg!(windows) {
+        Command::new("tasklist")
+            .args(&["/FI", &format!("PID eq {}", pid), "/NH"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+    } else {
+        Command::new("kill")
+            .args(&["-0", &pid.to_string()])
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+    };
+    status.unwrap_or_default()
+}
+
+/// Terminates the process `pid`.
+fn kill(pid: u32) -> Result<()> {
+    let status = if cfg!(windows) {
+        Command::new("taskkill")
+            .args(&["/PID", &pid.to_string(), "/F"])
+            .status()?
+    } else {
+        Command::new("kill").arg(pid.to_string()).status()?
+    };
+    if !status.success() {
+        anyhow::bail!("Failed to stop the node process {}", pid)
+    }
+    Ok(())
+}
+
+/// Returns the path of the pinned node release, downloading it if necessary.
+fn find_or_download() -> Result<PathBuf> {
+    let install_dir = util::cache_dir(&format!("substrate-contracts-node-{}", NODE_VERSION))?;
+    let binary = install_dir.join("substrate-contracts-node");
+    if binary.exists() {
+        return Ok(binary);
+    }
+
+    let archive_name = release_archive()?;
+    let digest = NODE_DIGESTS
+        .iter()
+        .find(|(name, _)| *name == archive_name)
+        .map(|(_, digest)| *digest)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "There is no pinned checksum of the {} node release, install it manually and \
+                point `{}` to it",
+                archive_name,
+                NODE_BINARY_ENV
+            )
+        })?;
+    let url = format!(
+        "https://github.com/paritytech/substrate-contracts-node/releases/download/{}/{}",
+        NODE_VERSION, archive_name
+    );
+    let archive = util::download_archive(&url, digest, "node release")?;
+
+    fs::create_dir_all(&install_dir)?;
+    let mut entries = tar::Archive::new(GzDecoder::new(&archive[..]));
+    for entry in entries.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.file_name() == Some("substrate-contracts-node".as_ref()) {
+            // unpacked under a temporary name, so that an interrupted download isn't used
+            let unpacked = install_dir.join(".substrate-contracts-node");
+            entry.unpack(&unpacked)?;
+            fs::rename(&unpacked, &binary)?;
+            return Ok(binary);
+        }
+    }
+    anyhow::bail!("The node release does not contain a substrate-contracts-node binary")
+}
+
+/// Returns the name of the release archive for the current platform.
+fn release_archive() -> Result<&'static str> {
+    match std::env::consts::OS {
+        "linux" if std::env::consts::ARCH == "x86_64" => {
+            Ok("substrate-contracts-node-linux.tar.gz")
+        }
+        "macos" => Ok("substrate-contracts-node-mac-universal.tar.gz"),
+        os => anyhow::bail!(
+            "There is no substrate-contracts-node release for {}-{}, install it manually and \
+            point `{}` to it",
+            std::env::consts::ARCH,
+            os,
+            NODE_BINARY_ENV
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    #[test]
+    fn chain_is_kept_in_base_path() {
+        let args = node_args("target/contracts-node/chain", 9944, 9933, false);
+        assert_eq!(
+            &args[..3],
+            ["--dev", "--base-path", "target/contracts-node/chain"]
+        );
+        assert!(!args.contains(&"--tmp".to_string()));
+    }
+
+    #[test]
+    fn stale_state_is_removed() {
+        with_tmp_dir(|path| {
+            // given
+            let node_dir = NodeDir(path.to_path_buf());
+            let state = NodeState {
+                // beyond the maximum pid of Linux and macOS
+                pid: Some(u32::MAX),
+                container: None,
+                version: Some(NODE_VERSION.into()),
+                ws_port: 9944,
+                rpc_port: 9933,
+            };
+            fs::write(node_dir.state(), serde_json::to_string(&state)?)?;
+
+            // when
+            let running = node_dir.running()?;
+
+            // then
+            assert_eq!(running, None);
+            assert!(!node_dir.state().exists());
+            assert_eq!(node_dir.status()?, "The node is not running");
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn container_publishes_ports_to_localhost_only() {
+        let node_dir = NodeDir(PathBuf::from("/project/target/contracts-node"));
+        let container = node_dir.container();
+        assert!(container.starts_with("cargo-contract-node-"));
+        assert_ne!(
+            container,
+            NodeDir(PathBuf::from("/other/target/contracts-node")).container()
+        );
+
+        let args = container_args(&container, 19944, 19933);
+        assert!(args.contains(&"127.0.0.1:19944:9944".to_string()));
+        assert!(args.contains(&"127.0.0.1:19933:9933".to_string()));
+        assert!(args.contains(&format!("{}:/data", container)));
+        assert!(args.ends_with(&["--ws-external".to_string(), "--rpc-external".to_string()]));
+    }
+
+    #[test]
+    fn snapshots_restore_the_chain() {
+        with_tmp_dir(|path| {
+            // given
+            let node_dir = NodeDir(path.to_path_buf());
+            let db = node_dir.chain().join("chains").join("dev").join("db");
+            fs::create_dir_all(&db)?;
+            fs::write(db.join("000001.sst"), "deployed")?;
+            node_dir.save_snapshot("fixture")?;
+            fs::write(db.join("000001.sst"), "changed")?;
+            fs::write(db.join("000002.sst"), "added")?;
+
+            // when
+            node_dir.load_snapshot("fixture")?;
+
+            // then
+            assert_eq!(fs::read_to_string(db.join("000001.sst"))?, "deployed");
+            assert!(!db.join("000002.sst").exists());
+            assert_eq!(node_dir.list_snapshots()?, "fixture");
+            assert!(node_dir.load_snapshot("unknown").is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn snapshot_names_stay_in_the_snapshot_directory() {
+        let node_dir = NodeDir(PathBuf::from("/project/target/contracts-node"));
+        assert!(node_dir.snapshot("fixture-1.0").is_ok());
+        assert!(node_dir.snapshot("../chain").is_err());
+        assert!(node_dir.snapshot("..").is_err());
+        assert!(node_dir.snapshot("").is_err());
+    }
+}
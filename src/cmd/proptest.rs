@@ -0,0 +1,605 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    fuzz::{args, definition, field_type, select_messages},
+    metadata,
+    trait_definition::{contract_of, Registry},
+    verify_metadata::label,
+};
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use heck::SnakeCase as _;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fmt::Write,
+    fs,
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+/// The declarations of the generated strategies module, after its module documentation.
+const STRATEGIES_HEADER: &str = "
+#![allow(dead_code, unused_imports)]
+
+use proptest::prelude::*;
+use std::convert::TryFrom;
+";
+
+/// The maximum number of elements of generated collections.
+const MAX_LEN: usize = 8;
+
+/// Scaffolds property tests of the messages of a contract.
+///
+/// Generates a module with a proptest strategy per argument type of the messages, derived from
+/// the type registry of the contract and producing values of the Rust types of the contract,
+/// and a skeleton test per message receiving its arguments from the strategies. The invariants
+/// are left to the contract author, e.g. by calling the message in the off-chain environment.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "proptest")]
+pub struct ProptestCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Path to the metadata or the `<name>.contract` bundle, defaults to
+    /// `./target/ink/metadata.json`
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+    /// The messages to test, separated by commas, defaults to all messages
+    #[structopt(long, use_delimiter = true)]
+    messages: Vec<String>,
+    /// The number of cases of each test
+    #[structopt(long, default_value = "256")]
+    cases: u32,
+    /// Directory of the generated `properties.rs` and `strategies/mod.rs`, defaults to `tests`
+    /// of the contract
+    #[structopt(short, long, parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+impl ProptestCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+                metadata::artifact_paths(&crate_metadata).dest_metadata
+            }
+        };
+        let contents = fs::read(&path).context(format!(
+            "Failed to read the metadata {}, build the contract first",
+            path.display()
+        ))?;
+        let metadata: Value = serde_json::from_slice(&contents)
+            .context(format!("The metadata {} is no valid JSON", path.display()))?;
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => manifest_path.absolute_directory()?.join("tests"),
+        };
+        let properties_path = output.join("properties.rs");
+        let strategies_path = output.join("strategies").join("mod.rs");
+        for path in &[&properties_path, &strategies_path] {
+            if path.exists() {
+                anyhow::bail!(
+                    "The file {} already exists, remove it to generate it again",
+                    path.display()
+                )
+            }
+        }
+
+        let (strategies, properties) = scaffold(&metadata, &self.messages, self.cases)?;
+        fs::create_dir_all(output.join("strategies"))?;
+        fs::write(&strategies_path, strategies).context(format!(
+            "Failed to write the strategies {}",
+            strategies_path.display()
+        ))?;
+        fs::write(&properties_path, properties).context(format!(
+            "Failed to write the property tests {}",
+            properties_path.display()
+        ))?;
+        Ok(format!(
+            "Generated the property tests {} with the strategies {}, fill in the invariants of \
+            each message and run them by `cargo contract test`. They require the dev-dependency \
+            `proptest`",
+            properties_path.display(),
+            strategies_path.display()
+        ))
+    }
+}
+
+/// Returns the strategies module and the property tests of the `messages` of the contract
+/// described by the `metadata`, all of them if none are selected.
+fn scaffold(metadata: &Value, messages: &[String], cases: u32) -> Result<(String, String)> {
+    let contract = contract_of(metadata);
+    let name = metadata["contract"]["name"].as_str().unwrap_or("contract");
+    let selected = select_messages(contract, messages)?;
+
+    let mut strategies = Strategies {
+        registry: Registry::new(contract),
+        names: BTreeMap::new(),
+        functions: BTreeMap::new(),
+        stack: BTreeSet::new(),
+        recursive: BTreeSet::new(),
+    };
+    let mut properties = String::new();
+    let mut plain_tests = String::new();
+    for message in selected {
+        let label = label(message).ok_or_else(|| anyhow::anyhow!("A message has no name"))?;
+        let test_name = label.replace("::", "_").to_snake_case();
+        let arg_names = message["args"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .map(|(position, arg)| {
+                arg["label"]
+                    .as_str()
+                    .or_else(|| arg["name"].as_str())
+                    .map_or_else(|| format!("arg_{}", position), |name| name.to_snake_case())
+            })
+            .collect::<Vec<_>>();
+        let args = args(message)?
+            .into_iter()
+            .map(|id| strategies.reference(id))
+            .collect::<Result<Vec<_>>>()?;
+        let todo = format!(
+            "        // TODO: call `{}` on the contract in the off-chain environment and assert \
+            its invariants\n",
+            label
+        );
+        if args.is_empty() {
+            write!(
+                plain_tests,
+                "\n#[test]\nfn {}_holds_invariants() {{\n{}}}\n",
+                test_name,
+                todo.replacen("        ", "    ", 1)
+            )?;
+            continue;
+        }
+        write!(
+            properties,
+            "\n    #[test]\n    fn {}_holds_invariants({}) {{\n{}        let _ = {};\n    }}\n",
+            test_name,
+            arg_names
+                .iter()
+                .zip(&args)
+                .map(|(name, strategy)| format!("{} in {}", name, strategy))
+                .collect::<Vec<_>>()
+                .join(", "),
+            todo,
+            match arg_names.as_slice() {
+                [name] => name.clone(),
+                names => format!("({})", names.join(", ")),
+            }
+        )?;
+    }
+
+    let strategies_module = format!(
+        "//! Proptest strategies of the argument types of the messages of the `{}` contract,\n\
+        //! generated by `cargo contract proptest` from its type registry.\n\
+        //!\n\
+        //! The strategies of structs and enums of the contract construct them from their \
+        fields, which\n\
+        //! have to be public to the tests.\n{}{}",
+        name,
+        STRATEGIES_HEADER,
+        strategies.functions.values().cloned().collect::<String>()
+    );
+    let properties = format!(
+        "//! Property tests of the messages of the `{}` contract, scaffolded by \
+        `cargo contract proptest`.\n\
+        //!\n\
+        //! Every test receives random arguments of a message from the `strategies`. Replace \
+        the TODOs by\n\
+        //! calls of the message and assertions of the invariants of the contract. Requires \
+        the\n\
+        //! dev-dependency `proptest`.\n\
+        \n\
+        mod strategies;\n\
+        \n\
+        use proptest::prelude::*;\n\
+        use strategies::*;\n\
+        \n\
+        proptest! {{\n    \
+        #![proptest_config(ProptestConfig::with_cases({}))]\n{}}}\n{}",
+        name, cases, properties, plain_tests
+    );
+    Ok((strategies_module, properties))
+}
+
+/// The strategies of the types of the arguments of the tested messages.
+struct Strategies<'a> {
+    registry: Registry<'a>,
+    /// The name of the generated function of each type.
+    names: BTreeMap<u64, String>,
+    /// The generated functions returning the strategy of a type, by name.
+    functions: BTreeMap<String, String>,
+    /// The types whose functions are being generated, to detect recursive types.
+    stack: BTreeSet<u64>,
+    /// The types referring to themselves, whose strategies are left to the contract author.
+    recursive: BTreeSet<u64>,
+}
+
+impl<'a> Strategies<'a> {
+    /// Returns the expression of the strategy of the type `id`, generating its function.
+    fn reference(&mut self, id: u64) -> Result<String> {
+        let def = definition(self.registry.get(id)?, id)?;
+        if let Some(compact) = def.get("compact") {
+            // compact integers have the Rust type of the integer
+            return self.reference(field_type(compact)?);
+        }
+        if self.stack.contains(&id) {
+            self.recursive.insert(id);
+        }
+        if let Some(name) = self.names.get(&id) {
+            return Ok(format!("{}()", name));
+        }
+
+        let mut name = format!("arb_{}", self.name(id)?);
+        if self.names.values().any(|other| *other == name) {
+            name = format!("{}_{}", name, id);
+        }
+        self.names.insert(id, name.clone());
+        self.stack.insert(id);
+        let strategy = self.strategy(id);
+        self.stack.remove(&id);
+        let ty = self.rust_type(id)?;
+        let strategy = if self.recursive.contains(&id) {
+            format!(
+                "// TODO: a strategy of the recursive type, e.g. by `prop_recursive`\n    \
+                any::<{}>()",
+                ty
+            )
+        } else {
+            strategy?
+        };
+        self.functions.insert(
+            name.clone(),
+            format!(
+                "\n/// Returns a strategy of `{}`.\npub fn {}() -> impl Strategy<Value = {}> \
+                {{\n    {}\n}}\n",
+                ty, name, ty, strategy
+            ),
+        );
+        Ok(format!("{}()", name))
+    }
+
+    /// Returns the name of the type `id` in the name of its strategy, e.g. `vec_u8`.
+    fn name(&self, id: u64) -> Result<String> {
+        let ty = self.registry.get(id)?;
+        let def = definition(ty, id)?;
+        if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+            return Ok(match primitive {
+                "str" => "string".into(),
+                primitive => primitive.into(),
+            });
+        }
+        if let Some(compact) = def.get("compact") {
+            return self.name(field_type(compact)?);
+        }
+        if let Some(sequence) = def.get("sequence") {
+            return Ok(format!("vec_{}", self.name(field_type(sequence)?)?));
+        }
+        if let Some(array) = def.get("array") {
+            return Ok(format!(
+                "array_{}_{}",
+                self.name(field_type(array)?)?,
+                array["len"].as_u64().unwrap_or_default()
+            ));
+        }
+        if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+            if tuple.is_empty() {
+                return Ok("unit".into());
+            }
+            let fields = tuple
+                .iter()
+                .map(|field| self.name(field_type(field)?))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(format!("tuple_{}", fields.join("_")));
+        }
+        let mut name = custom_name(ty, id)?.to_snake_case();
+        for param in self.registry.params(id)? {
+            if param != id {
+                write!(name, "_{}", self.name(param)?)?;
+            }
+        }
+        Ok(name)
+    }
+
+    /// Returns the Rust type of the type `id`, custom types by their path in the contract.
+    fn rust_type(&self, id: u64) -> Result<String> {
+        let ty = self.registry.get(id)?;
+        let def = definition(ty, id)?;
+        if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+            return match primitive {
+                "str" => Ok("String".into()),
+                "u256" | "i256" => anyhow::bail!("The type {} has no Rust equivalent", primitive),
+                primitive => Ok(primitive.into()),
+            };
+        }
+        if let Some(compact) = def.get("compact") {
+            return self.rust_type(field_type(compact)?);
+        }
+        if let Some(sequence) = def.get("sequence") {
+            return Ok(format!("Vec<{}>", self.rust_type(field_type(sequence)?)?));
+        }
+        if let Some(array) = def.get("array") {
+            return Ok(format!(
+                "[{}; {}]",
+                self.rust_type(field_type(array)?)?,
+                array["len"].as_u64().unwrap_or_default()
+            ));
+        }
+        if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+            let fields = tuple
+                .iter()
+                .map(|field| self.rust_type(field_type(field)?))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(match fields.as_slice() {
+                [field] => format!("({},)", field),
+                fields => format!("({})", fields.join(", ")),
+            });
+        }
+        let params = self
+            .registry
+            .params(id)?
+            .into_iter()
+            .map(|param| self.rust_type(param))
+            .collect::<Result<Vec<_>>>()?;
+        let path = custom_path(ty, id)?;
+        Ok(if params.is_empty() {
+            path
+        } else {
+            format!("{}<{}>", path, params.join(", "))
+        })
+    }
+
+    /// Returns the expression of the strategy of the type `id`.
+    fn strategy(&mut self, id: u64) -> Result<String> {
+        let ty = self.registry.get(id)?;
+        let def = definition(ty, id)?;
+        if def.contains_key("primitive") {
+            return Ok(format!("any::<{}>()", self.rust_type(id)?));
+        }
+        if let Some(sequence) = def.get("sequence") {
+            return Ok(format!(
+                "prop::collection::vec({}, 0..{})",
+                self.reference(field_type(sequence)?)?,
+                MAX_LEN
+            ));
+        }
+        if let Some(array) = def.get("array") {
+            return Ok(format!(
+                "prop::collection::vec({}, {})\n        .prop_map(|elements| <{}>::try_from(\
+                elements).expect(\"the number of elements is the length of the array\"))",
+                self.reference(field_type(array)?)?,
+                array["len"].as_u64().unwrap_or_default(),
+                self.rust_type(id)?
+            ));
+        }
+        if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+            if tuple.is_empty() {
+                return Ok("Just(())".into());
+            }
+            let fields = tuple
+                .iter()
+                .map(|field| self.reference(field_type(field)?))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(format!("({},)", fields.join(", ")));
+        }
+
+        let path = custom_path(ty, id)?;
+        let params = self.registry.params(id)?;
+        match (custom_name(ty, id)?, params.as_slice()) {
+            ("AccountId", _) | ("Hash", _) => {
+                return Ok(format!("any::<[u8; 32]>().prop_map({}::from)", path));
+            }
+            ("Option", [some]) => {
+                return Ok(format!("prop::option::of({})", self.reference(*some)?));
+            }
+            ("Result", [ok, err]) => {
+                return Ok(format!(
+                    "prop_oneof![{}.prop_map(Ok), {}.prop_map(Err)]",
+                    self.reference(*ok)?,
+                    self.reference(*err)?
+                ));
+            }
+            ("BTreeMap", [key, value]) => {
+                return Ok(format!(
+                    "prop::collection::btree_map({}, {}, 0..{})",
+                    self.reference(*key)?,
+                    self.reference(*value)?,
+                    MAX_LEN
+                ));
+            }
+            _ => (),
+        }
+        if let Some(composite) = def.get("composite") {
+            return self.construct(&path, composite);
+        }
+        let variants = def["variant"]["variants"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("The type {} has no strategy", id))?;
+        if variants.is_empty() {
+            anyhow::bail!("The enum {} has no variants, so no values", path)
+        }
+        let variants = variants
+            .iter()
+            .map(|variant| {
+                let name = variant["name"]
+                    .as_str()
+                    .ok_or_else(|| anyhow::anyhow!("A variant of {} has no name", path))?;
+                self.construct(&format!("{}::{}", path, name), variant)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(format!(
+            "prop_oneof![\n{}    ]",
+            variants
+                .iter()
+                .map(|variant| format!("        {},\n", variant))
+                .collect::<String>()
+        ))
+    }
+
+    /// Returns the strategy constructing the struct or enum variant `path` from the strategies
+    /// of its fields.
+    fn construct(&mut self, path: &str, item: &Value) -> Result<String> {
+        let fields = item["fields"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if fields.is_empty() {
+            return Ok(format!("Just({})", path));
+        }
+        let strategies = fields
+            .iter()
+            .map(|field| self.reference(field_type(field)?))
+            .collect::<Result<Vec<_>>>()?;
+        let names = fields
+            .iter()
+            .map(|field| field["name"].as_str().map(str::to_string))
+            .collect::<Option<Vec<_>>>();
+        let (bindings, value) = match names {
+            Some(names) => (
+                names.join(", "),
+                format!("{} {{ {} }}", path, names.join(", ")),
+            ),
+            None => {
+                let names = (0..fields.len())
+                    .map(|position| format!("field_{}", position))
+                    .collect::<Vec<_>>();
+                (names.join(", "), format!("{}({})", path, names.join(", ")))
+            }
+        };
+        Ok(format!(
+            "({},).prop_map(|({},)| {})",
+            strategies.join(", "),
+            bindings,
+            value
+        ))
+    }
+}
+
+/// Returns the last segment of the path of the custom type `ty` with the id `id`.
+fn custom_name(ty: &Value, id: u64) -> Result<&str> {
+    ty["path"]
+        .as_array()
+        .and_then(|path| path.last())
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("The custom type {} has no path", id))
+}
+
+/// Returns the path of the custom type `ty` with the id `id` without its type parameters.
+///
+/// The environment types are re-exported by `ink_env`, types of `core` and the prelude are in
+/// scope by their name.
+fn custom_path(ty: &Value, id: u64) -> Result<String> {
+    let name = custom_name(ty, id)?;
+    Ok(match name {
+        "AccountId" | "Hash" => format!("ink_env::{}", name),
+        "Option" | "Result" | "Vec" | "String" => name.to_string(),
+        "BTreeMap" | "BTreeSet" => format!("std::collections::{}", name),
+        _ => ty["path"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("::"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::erc20_metadata;
+    use serde_json::json;
+
+    #[test]
+    fn strategies_produce_the_types_of_the_contract() {
+        // when
+        let (strategies, _) = scaffold(&erc20_metadata(), &[], 256).unwrap();
+
+        // then
+        assert!(strategies.contains(
+            "pub fn arb_account_id() -> impl Strategy<Value = ink_env::AccountId> {\n    \
+            any::<[u8; 32]>().prop_map(ink_env::AccountId::from)\n}"
+        ));
+        assert!(strategies.contains(
+            "pub fn arb_option_mode() -> impl Strategy<Value = Option<erc20::erc20::Mode>> {\n    \
+            prop::option::of(arb_mode())\n}"
+        ));
+        assert!(strategies.contains(
+            "prop_oneof![\n        Just(erc20::erc20::Mode::Off),\n        \
+            (arb_u128(),).prop_map(|(field_0,)| erc20::erc20::Mode::On(field_0)),\n    ]"
+        ));
+        assert!(strategies.contains(
+            "(arb_u128(), arb_u128(),).prop_map(|(min, max,)| erc20::erc20::Limits { min, max })"
+        ));
+        assert!(strategies.contains("prop::collection::vec(arb_u8(), 0..8)"));
+    }
+
+    #[test]
+    fn properties_receive_the_arguments_of_messages() {
+        // when
+        let (_, properties) = scaffold(&erc20_metadata(), &[], 64).unwrap();
+
+        // then
+        assert!(properties.contains("#![proptest_config(ProptestConfig::with_cases(64))]"));
+        assert!(properties.contains(
+            "    fn transfer_holds_invariants(to in arb_account_id(), value in arb_u128()) {\n        \
+            // TODO: call `transfer` on the contract"
+        ));
+        assert!(properties.contains("        let _ = (to, value);\n"));
+        assert!(properties.contains("#[test]\nfn total_supply_holds_invariants() {\n"));
+    }
+
+    #[test]
+    fn only_selected_messages_are_scaffolded() {
+        let (strategies, properties) =
+            scaffold(&erc20_metadata(), &["set_data".into()], 64).unwrap();
+
+        assert!(properties.contains("fn set_data_holds_invariants(data in arb_vec_u8())"));
+        assert!(!properties.contains("transfer"));
+        assert!(!strategies.contains("arb_account_id"));
+    }
+
+    #[test]
+    fn recursive_types_are_left_to_the_author() {
+        let metadata = json!({
+            "types": [
+                { "id": 0, "type": { "path": ["tree", "Node"], "def": { "composite": {
+                  "fields": [{ "name": "children", "type": 1 }] } } } },
+                { "id": 1, "type": { "def": { "sequence": { "type": 0 } } } },
+            ],
+            "spec": { "messages": [
+                { "label": "insert", "args": [{ "label": "node", "type": { "type": 0 } }] },
+            ] },
+        });
+
+        let (strategies, _) = scaffold(&metadata, &[], 64).unwrap();
+
+        assert!(strategies.contains(
+            "    // TODO: a strategy of the recursive type, e.g. by `prop_recursive`\n    \
+            any::<tree::Node>()"
+        ));
+    }
+}
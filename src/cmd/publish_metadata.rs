@@ -0,0 +1,156 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::metadata;
+use crate::{crate_metadata::CrateMetadata, util, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::Deserialize;
+use std::{convert::TryFrom, fs, path::PathBuf};
+use structopt::StructOpt;
+use url::Url;
+
+/// Environment variable holding the bearer token for authenticating with a pinning service.
+const TOKEN_ENV_VAR: &str = "CARGO_CONTRACT_IPFS_TOKEN";
+
+/// Boundary between the parts of the multipart upload.
+const BOUNDARY: &str = "----cargo-contract-upload";
+
+/// Response of the `add` endpoint of the IPFS HTTP API.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AddResponse {
+    hash: String,
+}
+
+/// Publishes the metadata of a contract to IPFS, so that frontends can fetch the ABI of a
+/// deployed contract from a content-addressed source.
+///
+/// The file is added and pinned via the IPFS HTTP API of a node or a pinning service speaking
+/// the same API. If the environment variable `CARGO_CONTRACT_IPFS_TOKEN` is set its value is
+/// sent as a bearer token.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "publish-metadata")]
+pub struct PublishMetadataCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Publish to IPFS, currently the only supported destination
+    #[structopt(long)]
+    ipfs: bool,
+    /// Url of the IPFS HTTP API to publish to
+    #[structopt(long, default_value = "http://127.0.0.1:5001", parse(try_from_str))]
+    ipfs_api: Url,
+    /// Publish the whole `<name>.contract` bundle instead of the metadata
+    #[structopt(long, conflicts_with = "path")]
+    bundle: bool,
+    /// Path to the file to publish, defaults to `./target/ink/metadata.json`
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+}
+
+impl PublishMetadataCommand {
+    pub fn exec(&self) -> Result<String> {
+        if !self.ipfs {
+            anyhow::bail!("No destination to publish to, pass `--ipfs`")
+        }
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                let artifacts = metadata::artifact_paths(&CrateMetadata::collect(&manifest_path)?);
+                if self.bundle {
+                    artifacts.dest_bundle
+                } else {
+                    artifacts.dest_metadata
+                }
+            }
+        };
+        let contents = fs::read(&path).context(format!(
+            "Failed to read {}, build the contract first",
+            path.display()
+        ))?;
+        let cid = self.add_to_ipfs(util::base_name(&path), &contents)?;
+        Ok(format!(
+            "{} {}\n\t{} {}",
+            "Published".bright_green().bold(),
+            path.display(),
+            "CID:".bold(),
+            cid
+        ))
+    }
+
+    /// Adds and pins the file via the IPFS HTTP API, returning its CID.
+    fn add_to_ipfs(&self, file_name: &str, contents: &[u8]) -> Result<String> {
+        let mut url = self
+            .ipfs_api
+            .join("api/v0/add")
+            .context(format!("Invalid IPFS API url {}", self.ipfs_api))?;
+        url.query_pairs_mut()
+            .append_pair("pin", "true")
+            .append_pair("cid-version", "1");
+        let request = ureq::post(url.as_str()).set(
+            "Content-Type",
+            &format!("multipart/form-data; boundary={}", BOUNDARY),
+        );
+        let token = std::env::var(TOKEN_ENV_VAR).ok();
+        let response: AddResponse = util::authorized(request, token.as_deref())
+            .send_bytes(&multipart_body(file_name, contents))
+            .map_err(|err| anyhow::anyhow!("Adding {} to IPFS failed: {}", file_name, err))?
+            .into_json()
+            .context("Invalid response of the IPFS API")?;
+        Ok(response.hash)
+    }
+}
+
+/// Returns the body of a `multipart/form-data` upload of a single file.
+fn multipart_body(file_name: &str, contents: &[u8]) -> Vec<u8> {
+    let mut body = format!(
+        "--{}\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+        Content-Type: application/octet-stream\r\n\r\n",
+        BOUNDARY, file_name
+    )
+    .into_bytes();
+    body.extend(contents);
+    body.extend(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multipart_body_contains_the_file() {
+        let body = multipart_body("metadata.json", b"{}");
+
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "------cargo-contract-upload\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"metadata.json\"\r\n\
+            Content-Type: application/octet-stream\r\n\r\n\
+            {}\r\n\
+            ------cargo-contract-upload--\r\n"
+        );
+    }
+
+    #[test]
+    fn destination_is_required() {
+        let cmd = PublishMetadataCommand::from_iter(&["publish-metadata", "metadata.json"]);
+        assert!(cmd.exec().is_err());
+    }
+}
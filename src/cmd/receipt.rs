@@ -0,0 +1,231 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Receipts of submitted extrinsics, written to the `--receipt` path as durable evidence of what
+//! a deployment did.
+
+use super::{
+    bench::number,
+    scale_json::ScaleJson,
+    serve::contract_event,
+    trait_definition::{contract_of, Registry},
+};
+use crate::{rpc::RpcClient, ExtrinsicOpts};
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use sp_core::{
+    crypto::{AccountId32, Ss58Codec},
+    hashing::blake2_256,
+};
+use std::{convert::TryInto, fs, path::PathBuf};
+use structopt::StructOpt;
+use subxt::{DefaultNodeRuntime, ExtrinsicSuccess, RawEvent};
+use url::Url;
+
+/// Options of the receipt of commands submitting a single extrinsic.
+///
+/// Commands submitting several extrinsics, e.g. `run`, `apply` and `tui`, don't offer them.
+#[derive(Clone, Debug, Default, StructOpt)]
+pub(crate) struct ReceiptOpts {
+    /// Write a JSON receipt of the submitted extrinsic to this path: the inputs, the extrinsic
+    /// hash, the block, the events, the decoded result and the costs
+    #[structopt(long, parse(from_os_str))]
+    receipt: Option<PathBuf>,
+}
+
+impl ReceiptOpts {
+    /// Returns whether a receipt is requested.
+    pub(super) fn is_requested(&self) -> bool {
+        self.receipt.is_some()
+    }
+}
+
+/// The receipt of a submitted extrinsic.
+#[derive(Debug)]
+pub(super) struct Receipt {
+    /// The command which submitted the extrinsic, e.g. `call`.
+    pub(super) command: &'static str,
+    /// The account which signed the extrinsic.
+    pub(super) signer: AccountId32,
+    /// The inputs of the command, e.g. the contract and the call data of a call.
+    pub(super) inputs: Value,
+    /// The outcome of the extrinsic, e.g. the address of an instantiated contract, `null` for
+    /// calls, whose outcome are the events of the contract.
+    pub(super) result: Value,
+    /// The gas limit of calls and instantiations.
+    pub(super) gas_limit: Option<u64>,
+}
+
+impl Receipt {
+    /// Writes the receipt of the extrinsic `success` to the `--receipt` path, if supplied.
+    ///
+    /// The events emitted by the `contract` are decoded with its `metadata`, if supplied.
+    pub(super) fn write(
+        self,
+        receipt_opts: &ReceiptOpts,
+        extrinsic_opts: &ExtrinsicOpts,
+        success: &ExtrinsicSuccess<DefaultNodeRuntime>,
+        contract: Option<(&AccountId32, &Value)>,
+    ) -> Result<()> {
+        let path = match &receipt_opts.receipt {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let url = extrinsic_opts.url()?;
+        let block = format!("{:?}", success.block);
+        let extrinsic_hash = format!("{:?}", success.extrinsic);
+        let fee = quoted_fee(&url, &block, &extrinsic_hash)
+            .map_err(|err| log::warn!("No fee in the receipt: {:#}", err))
+            .ok();
+        let receipt = json!({
+            "command": self.command,
+            "url": url.as_str(),
+            "signer": self.signer.to_ss58check(),
+            "inputs": self.inputs,
+            "extrinsicHash": extrinsic_hash,
+            "block": block,
+            "events": events(&success.events, contract),
+            "result": self.result,
+            "costs": {
+                "gasLimit": self.gas_limit,
+                "weight": weight(&success.events),
+                "partialFee": fee.map(|fee| fee.to_string()),
+            },
+        });
+        fs::write(path, serde_json::to_string_pretty(&receipt)?)
+            .context(format!("Failed to write the receipt {}", path.display()))
+    }
+}
+
+/// Returns the `events` of an extrinsic, with the events emitted by the `contract` decoded with
+/// its metadata.
+fn events(events: &[RawEvent], contract: Option<(&AccountId32, &Value)>) -> Vec<Value> {
+    let spec = contract.map(|(_, metadata)| contract_of(metadata));
+    let registry = spec.map(Registry::new);
+    let codec = registry.as_ref().map(ScaleJson::new);
+    events
+        .iter()
+        .map(|event| {
+            let mut json = json!({
+                "module": event.module,
+                "variant": event.variant,
+                "data": format!("0x{}", hex::encode(&event.data)),
+            });
+            let decoded = match (contract, spec, &codec, contract_event(event)) {
+                (Some((contract, _)), Some(spec), Some(codec), Ok(Some((emitter, data))))
+                    if &emitter == contract =>
+                {
+                    codec.decode_event(spec, &data).ok()
+                }
+                _ => None,
+            };
+            if let Some((name, args)) = decoded {
+                json["decoded"] = json!({ "name": name, "args": args });
+            }
+            json
+        })
+        .collect()
+}
+
+/// Returns the actual weight of an extrinsic, reported by its `System.ExtrinsicSuccess` event
+/// with the `DispatchInfo` starting with the weight.
+fn weight(events: &[RawEvent]) -> Option<u64> {
+    let event = events
+        .iter()
+        .find(|event| event.module == "System" && event.variant == "ExtrinsicSuccess")?;
+    let weight = event.data.get(..8)?.try_into().ok()?;
+    Some(u64::from_le_bytes(weight))
+}
+
+/// Returns the fee the node quotes for the extrinsic with the hash `extrinsic` in the `block` on
+/// the state it was submitted on, which is an upper bound as the fee for unused gas is refunded.
+fn quoted_fee(url: &Url, block: &str, extrinsic: &str) -> Result<u128> {
+    let rpc = RpcClient::new(url)?;
+    let block: Value = rpc.request("chain_getBlock", json!([block]))?;
+    let encoded = block["block"]["extrinsics"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .find(|encoded| {
+            hex::decode(encoded.trim_start_matches("0x")).map_or(false, |bytes| {
+                format!("0x{}", hex::encode(blake2_256(&bytes))) == extrinsic
+            })
+        })
+        .ok_or_else(|| anyhow::anyhow!("The extrinsic {} is not in its block", extrinsic))?;
+    let parent = &block["block"]["header"]["parentHash"];
+    let info: Value = rpc.request("payment_queryInfo", json!([encoded, parent]))?;
+    number(&info["partialFee"]).ok_or_else(|| anyhow::anyhow!("No fee in the result {}", info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+
+    fn event(module: &str, variant: &str, data: Vec<u8>) -> RawEvent {
+        RawEvent {
+            module: module.into(),
+            variant: variant.into(),
+            data,
+        }
+    }
+
+    #[test]
+    fn weight_is_read_from_the_dispatch_info() {
+        let mut dispatch_info = 1_500_000u64.to_le_bytes().to_vec();
+        dispatch_info.extend(&[0, 0]);
+        let events = [
+            event("Contracts", "CodeStored", vec![0; 32]),
+            event("System", "ExtrinsicSuccess", dispatch_info),
+        ];
+
+        assert_eq!(weight(&events), Some(1_500_000));
+        assert_eq!(weight(&events[..1]), None);
+    }
+
+    #[test]
+    fn events_of_the_contract_are_decoded() {
+        let metadata = json!({
+            "spec": {
+                "events": [{
+                    "label": "Flipped",
+                    "args": [{ "label": "value", "type": { "type": 1 }, "indexed": false }]
+                }]
+            },
+            "types": [{ "id": 1, "type": { "def": { "primitive": "bool" } } }]
+        });
+        let contract = AccountId32::from([1; 32]);
+        let other = AccountId32::from([2; 32]);
+        let emitted = |emitter: &AccountId32| {
+            let mut data = emitter.encode();
+            data.extend(vec![0x00, 0x01].encode());
+            event("Contracts", "ContractEmitted", data)
+        };
+
+        let events = events(
+            &[emitted(&contract), emitted(&other)],
+            Some((&contract, &metadata)),
+        );
+
+        assert_eq!(
+            events[0]["decoded"],
+            json!({ "name": "Flipped", "args": { "value": true } })
+        );
+        assert_eq!(events[1].get("decoded"), None);
+        assert_eq!(events[1]["module"], "Contracts");
+    }
+}
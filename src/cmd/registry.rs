@@ -0,0 +1,206 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::metadata;
+use crate::{
+    bundle::ContractBundle,
+    crate_metadata::CrateMetadata,
+    registry::{self, Registry},
+    workspace::ManifestPath,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use impl_serde::serialize as serde_hex;
+use semver::Version;
+use std::{convert::TryFrom, fs, path::PathBuf, str::FromStr};
+use structopt::StructOpt;
+use url::Url;
+
+/// Publishes the `<name>.contract` bundle of the contract to a registry, under the name and
+/// version recorded in the bundle.
+///
+/// If the environment variable `CARGO_CONTRACT_REGISTRY_TOKEN` is set its value is sent as a
+/// bearer token.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "publish")]
+pub struct PublishCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Url of the registry to publish to
+    #[structopt(long, parse(try_from_str))]
+    registry: Url,
+    /// Path to the bundle to publish, defaults to `./target/ink/<name>.contract`
+    #[structopt(parse(from_os_str))]
+    bundle: Option<PathBuf>,
+}
+
+impl PublishCommand {
+    pub fn exec(&self) -> Result<String> {
+        let path = match &self.bundle {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                metadata::artifact_paths(&CrateMetadata::collect(&manifest_path)?).dest_bundle
+            }
+        };
+        let bundle = ContractBundle::load(&path)
+            .context("Build the contract with `cargo contract build` first")?;
+        // only publish bundles which can be deployed
+        let code_hash = bundle.code_hash()?;
+        bundle.wasm()?;
+        let (name, version) = name_and_version(&bundle)?;
+
+        let contents = fs::read(&path)?;
+        let entry =
+            Registry::new(&self.registry).publish(&name, &version, &contents, &code_hash)?;
+        Ok(format!(
+            "{} {}@{} to {}\n\t{} {}",
+            "Published".bright_green().bold(),
+            name,
+            version,
+            self.registry,
+            "Blob:".bold(),
+            entry.blob
+        ))
+    }
+}
+
+/// Pulls a `<name>.contract` bundle from a registry.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "pull")]
+pub struct PullCommand {
+    /// The contract to pull as `<name>@<version>`, the latest version if none is supplied
+    package: PackageSpec,
+    /// Url of the registry to pull from
+    #[structopt(long, parse(try_from_str))]
+    registry: Url,
+    /// Directory to write the bundle to, defaults to the current directory
+    #[structopt(long, parse(from_os_str))]
+    output_dir: Option<PathBuf>,
+}
+
+impl PullCommand {
+    pub fn exec(&self) -> Result<String> {
+        let PackageSpec { name, version } = &self.package;
+        let registry = Registry::new(&self.registry);
+        let index = registry.index(name)?;
+        let entry = registry::resolve(&index, version.as_ref()).ok_or_else(|| match version {
+            Some(version) => anyhow::anyhow!("{}@{} is not published", name, version),
+            None => anyhow::anyhow!("{} is not published", name),
+        })?;
+        let contents = registry.fetch(entry)?;
+
+        let output_dir = self.output_dir.clone().unwrap_or_default();
+        fs::create_dir_all(&output_dir)?;
+        let path = output_dir.join(format!("{}.contract", name));
+        fs::write(&path, &contents)?;
+        let bundle = ContractBundle::load(&path)?;
+        let code_hash = serde_hex::to_hex(&bundle.code_hash()?, false);
+        if code_hash != entry.code_hash {
+            fs::remove_file(&path)?;
+            anyhow::bail!(
+                "The code hash {} of the pulled bundle doesn't match the index of the registry",
+                code_hash
+            )
+        }
+        bundle.wasm()?;
+        Ok(format!(
+            "{} {}@{} to {}",
+            "Pulled".bright_green().bold(),
+            name,
+            entry.version,
+            path.display()
+        ))
+    }
+}
+
+/// A contract of a registry, as `<name>` or `<name>@<version>`.
+#[derive(Debug, PartialEq)]
+struct PackageSpec {
+    name: String,
+    version: Option<Version>,
+}
+
+impl FromStr for PackageSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let mut split = input.splitn(2, '@');
+        let name = split.next().unwrap_or_default();
+        let version = match split.next() {
+            Some(version) => {
+                let version = Version::parse(version)
+                    .context(format!("Invalid version {} of {}", version, name))?;
+                Some(version)
+            }
+            None => None,
+        };
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            anyhow::bail!("Invalid contract name {:?}", name)
+        }
+        Ok(Self {
+            name: name.to_string(),
+            version,
+        })
+    }
+}
+
+/// Returns the name and version of the contract from the `contract` section of the `bundle`.
+fn name_and_version(bundle: &ContractBundle) -> Result<(String, Version)> {
+    let contract = bundle
+        .json()
+        .get("contract")
+        .ok_or_else(|| anyhow::anyhow!("The bundle has no `contract` section"))?;
+    let field = |name: &str| {
+        contract
+            .get(name)
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("The bundle has no `contract.{}`", name))
+    };
+    let version = Version::parse(field("version")?).context("Invalid version in the bundle")?;
+    Ok((field("name")?.to_string(), version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_specs_are_parsed() {
+        assert_eq!(
+            "flipper".parse::<PackageSpec>().unwrap(),
+            PackageSpec {
+                name: "flipper".to_string(),
+                version: None
+            }
+        );
+        assert_eq!(
+            "erc20@1.2.3".parse::<PackageSpec>().unwrap(),
+            PackageSpec {
+                name: "erc20".to_string(),
+                version: Some(Version::new(1, 2, 3))
+            }
+        );
+        assert!("erc20@latest".parse::<PackageSpec>().is_err());
+        assert!("@1.0.0".parse::<PackageSpec>().is_err());
+        assert!("../erc20".parse::<PackageSpec>().is_err());
+    }
+}
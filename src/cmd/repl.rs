@@ -0,0 +1,499 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    call::{display_exec_result, dry_run, resolve_bundle, ContractExecResult},
+    scale_json::{items, ScaleJson},
+    scenario::{closing, split_top_level},
+    trait_definition::{contract_of, Registry},
+    tui::{signature, submit},
+    verify_metadata::label,
+};
+use crate::{ExtrinsicOpts, OriginOpts};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    validate::Validator, Editor, Helper,
+};
+use serde_json::Value;
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::collections::BTreeMap;
+use structopt::StructOpt;
+
+/// The keywords of the REPL, completed along with the message names.
+const KEYWORDS: [&str; 6] = ["let", "submit", "vars", "messages", "help", "quit"];
+
+/// The variable holding the output of the last dry-run.
+const LAST_OUTPUT: &str = "_";
+
+const HELP: &str = "\
+  <message>(<args>..) [with <value>]         dry-run a message, its output is stored in $_
+  submit <message>(<args>..) [with <value>]  submit a call of a message
+  let <name> = <message>(<args>..)           dry-run a message and store its output in $<name>
+  let <name> = <value>                       store a JSON value in $<name>
+  vars                                       list the variables of the session
+  messages                                   list the messages of the contract
+  quit                                       end the session, as does Ctrl-D
+
+Arguments are JSON values, `$<name>` refers to a variable and other inputs are taken as strings.";
+
+/// Starts an interactive session with a deployed contract, to dry-run its messages, store
+/// their outputs in variables and pass them on as arguments, and submit calls explicitly.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "repl")]
+pub struct ReplCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    #[structopt(flatten)]
+    origin_opts: OriginOpts,
+    /// The address of the contract
+    #[structopt(long, parse(try_from_str = crate::parse_account))]
+    contract: AccountId32,
+    /// Maximum amount of gas to be used for calls
+    #[structopt(name = "gas", long, default_value = "500000000")]
+    gas_limit: u64,
+}
+
+/// A line entered at the prompt.
+#[derive(Debug, PartialEq)]
+enum Statement {
+    Eval(Expr),
+    Let(String, Expr),
+    Submit(Invocation),
+    Vars,
+    Messages,
+    Help,
+    Quit,
+}
+
+/// An expression evaluating to a value.
+#[derive(Debug, PartialEq)]
+enum Expr {
+    /// The dry-run of a message, evaluating to its decoded output.
+    Call(Invocation),
+    Arg(Arg),
+}
+
+/// The call of a message.
+#[derive(Debug, PartialEq)]
+struct Invocation {
+    message: String,
+    args: Vec<Arg>,
+    value: u128,
+}
+
+/// An argument of a message.
+#[derive(Debug, PartialEq)]
+enum Arg {
+    Value(Value),
+    Var(String),
+}
+
+impl ReplCommand {
+    pub fn exec(&self) -> Result<Option<String>> {
+        let metadata = Value::Object(
+            resolve_bundle(&self.extrinsic_opts, &self.contract)?
+                .json()
+                .clone(),
+        );
+        let spec = contract_of(&metadata);
+        let registry = Registry::new(spec);
+        let codec = ScaleJson::new(&registry);
+        let messages = items(spec, "messages");
+        let mut session = Session {
+            cmd: self,
+            registry: &registry,
+            codec: &codec,
+            spec,
+            messages,
+            vars: BTreeMap::new(),
+        };
+
+        let mut editor = Editor::<Completions>::new();
+        editor.set_helper(Some(Completions {
+            names: session.names(),
+        }));
+        println!(
+            "Session with the contract {}, enter `help` for the syntax",
+            self.contract.to_ss58check()
+        );
+        loop {
+            let line = match editor.readline("> ") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            editor.add_history_entry(line.as_str());
+            let statement = match parse(&line) {
+                Ok(statement) => statement,
+                Err(err) => {
+                    eprintln!("{} {:#}", "ERROR:".bright_red().bold(), err);
+                    continue;
+                }
+            };
+            if statement == Statement::Quit {
+                return Ok(None);
+            }
+            if let Err(err) = session.exec(statement) {
+                eprintln!("{} {:#}", "ERROR:".bright_red().bold(), err);
+            }
+            if let Some(helper) = editor.helper_mut() {
+                helper.names = session.names();
+            }
+        }
+    }
+}
+
+/// The state of a REPL session.
+struct Session<'a> {
+    cmd: &'a ReplCommand,
+    registry: &'a Registry<'a>,
+    codec: &'a ScaleJson<'a>,
+    spec: &'a Value,
+    messages: &'a [Value],
+    vars: BTreeMap<String, Value>,
+}
+
+impl<'a> Session<'a> {
+    fn exec(&mut self, statement: Statement) -> Result<()> {
+        match statement {
+            Statement::Eval(expr) => {
+                let value = self.eval(&expr)?;
+                if let Expr::Arg(_) = expr {
+                    println!("{}", value);
+                }
+            }
+            Statement::Let(name, expr) => {
+                let value = self.eval(&expr)?;
+                println!("${} = {}", name, value);
+                self.vars.insert(name, value);
+            }
+            Statement::Submit(invocation) => {
+                let (_, data) = self.encode(&invocation)?;
+                submit(
+                    &self.cmd.extrinsic_opts,
+                    self.cmd.gas_limit,
+                    self.codec,
+                    self.spec,
+                    &self.cmd.contract,
+                    invocation.value,
+                    &data,
+                )?;
+            }
+            Statement::Vars => {
+                for (name, value) in &self.vars {
+                    println!("${} = {}", name, value);
+                }
+            }
+            Statement::Messages => {
+                for message in self.messages {
+                    println!("{}", signature(self.registry, message)?);
+                }
+            }
+            Statement::Help => println!("{}", HELP),
+            Statement::Quit => (),
+        }
+        Ok(())
+    }
+
+    /// Returns the value of the `expr`, dry-running the message of calls.
+    fn eval(&mut self, expr: &Expr) -> Result<Value> {
+        let invocation = match expr {
+            Expr::Arg(arg) => return self.resolve(arg),
+            Expr::Call(invocation) => invocation,
+        };
+        let (message, data) = self.encode(invocation)?;
+        let origin = self.cmd.origin_opts.origin(&self.cmd.extrinsic_opts)?;
+        let url = self.cmd.extrinsic_opts.url()?;
+        let result = dry_run(
+            &url,
+            &origin,
+            &self.cmd.contract,
+            invocation.value,
+            self.cmd.gas_limit,
+            &data,
+        )?;
+        println!("{}", display_exec_result(&origin, &result));
+        let output = match &result {
+            ContractExecResult::Success { data, .. } => {
+                let output = hex::decode(data.trim_start_matches("0x"))?;
+                self.codec.decode_output(message, &output)?
+            }
+            ContractExecResult::Error(()) => anyhow::bail!("The dry-run failed"),
+        };
+        println!("\t{} {}", "Output:".bold(), output);
+        self.vars.insert(LAST_OUTPUT.into(), output.clone());
+        Ok(output)
+    }
+
+    /// Returns the message of the `invocation` and its encoded call data.
+    fn encode(&self, invocation: &Invocation) -> Result<(&'a Value, Vec<u8>)> {
+        let message = self
+            .messages
+            .iter()
+            .find(|message| label(message).as_deref() == Some(invocation.message.as_str()))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "The contract has no message `{}`, enter `messages` to list them",
+                    invocation.message
+                )
+            })?;
+        let args = invocation
+            .args
+            .iter()
+            .map(|arg| self.resolve(arg))
+            .collect::<Result<Vec<_>>>()?;
+        let data = self
+            .codec
+            .encode_message(message, &args)
+            .context(format!("Invalid arguments of `{}`", invocation.message))?;
+        Ok((message, data))
+    }
+
+    fn resolve(&self, arg: &Arg) -> Result<Value> {
+        match arg {
+            Arg::Value(value) => Ok(value.clone()),
+            Arg::Var(name) => self
+                .vars
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("The variable ${} is not defined", name)),
+        }
+    }
+
+    /// Returns the words completed at the prompt: the keywords, message names and variables.
+    fn names(&self) -> Vec<String> {
+        let keywords = KEYWORDS.iter().map(|keyword| keyword.to_string());
+        let messages = self.messages.iter().filter_map(label);
+        let vars = self.vars.keys().map(|name| format!("${}", name));
+        keywords.chain(messages).chain(vars).collect()
+    }
+}
+
+/// Parses a `line` entered at the prompt.
+fn parse(line: &str) -> Result<Statement> {
+    let line = line.trim();
+    let (keyword, rest) = match line.find(char::is_whitespace) {
+        Some(index) => (&line[..index], line[index..].trim()),
+        None => (line, ""),
+    };
+    match (keyword, rest) {
+        ("vars", "") => Ok(Statement::Vars),
+        ("messages", "") => Ok(Statement::Messages),
+        ("help", "") => Ok(Statement::Help),
+        ("quit", "") | ("exit", "") => Ok(Statement::Quit),
+        ("submit", invocation) => parse_invocation(invocation).map(Statement::Submit),
+        ("let", binding) => {
+            let eq = binding
+                .find('=')
+                .ok_or_else(|| anyhow::anyhow!("Expected `let <name> = <expr>`"))?;
+            let name = binding[..eq].trim().trim_start_matches('$');
+            if !is_identifier(name) || name == LAST_OUTPUT {
+                anyhow::bail!("Invalid variable name `{}`", name)
+            }
+            Ok(Statement::Let(name.into(), parse_expr(&binding[eq + 1..])?))
+        }
+        _ => parse_expr(line).map(Statement::Eval),
+    }
+}
+
+/// Parses a message call or an argument.
+fn parse_expr(input: &str) -> Result<Expr> {
+    let input = input.trim();
+    match input.find('(') {
+        Some(open) if is_identifier(input[..open].trim()) => {
+            parse_invocation(input).map(Expr::Call)
+        }
+        _ => Ok(Expr::Arg(parse_arg(input))),
+    }
+}
+
+/// Parses `<message>(<args>..) [with <value>]`.
+fn parse_invocation(input: &str) -> Result<Invocation> {
+    let open = input
+        .find('(')
+        .ok_or_else(|| anyhow::anyhow!("Expected `<message>(<args>..)` in `{}`", input))?;
+    let close =
+        closing(input, open).ok_or_else(|| anyhow::anyhow!("Unclosed `(` in `{}`", input))?;
+    let message = input[..open].trim();
+    if !is_identifier(message) {
+        anyhow::bail!("Invalid message name `{}`", message)
+    }
+    let args = split_top_level(&input[open + 1..close])
+        .into_iter()
+        .map(parse_arg)
+        .collect();
+    let value = match input[close + 1..].trim() {
+        "" => 0,
+        rest => rest
+            .strip_prefix("with")
+            .ok_or_else(|| anyhow::anyhow!("Unexpected `{}` after the call", rest))?
+            .trim()
+            .parse()
+            .context("The value is no integer")?,
+    };
+    Ok(Invocation {
+        message: message.into(),
+        args,
+        value,
+    })
+}
+
+/// Parses an argument as variable reference or JSON value.
+///
+/// Inputs which aren't valid JSON are taken as strings, so that e.g. addresses don't need to be
+/// quoted.
+fn parse_arg(input: &str) -> Arg {
+    let input = input.trim();
+    match input.strip_prefix('$') {
+        Some(name) if is_identifier(name) => Arg::Var(name.into()),
+        _ => {
+            Arg::Value(serde_json::from_str(input).unwrap_or_else(|_| Value::String(input.into())))
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Returns the start of the word before the cursor at `pos` in the `line` and the `names`
+/// completing it.
+fn complete(names: &[String], line: &str, pos: usize) -> (usize, Vec<String>) {
+    let start = line[..pos]
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .map_or(0, |index| index + 1);
+    let word = &line[start..pos];
+    let candidates = names
+        .iter()
+        .filter(|name| name.starts_with(word))
+        .cloned()
+        .collect();
+    (start, candidates)
+}
+
+/// Completes the keywords, message names and variables at the prompt.
+struct Completions {
+    names: Vec<String>,
+}
+
+impl Completer for Completions {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete(&self.names, line, pos))
+    }
+}
+
+impl Hinter for Completions {
+    type Hint = String;
+}
+
+impl Highlighter for Completions {}
+
+impl Validator for Completions {}
+
+impl Helper for Completions {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn calls_are_parsed_with_args_and_value() {
+        assert_eq!(
+            parse(r#"transfer($bob, 100, "memo, with comma") with 5"#).unwrap(),
+            Statement::Eval(Expr::Call(Invocation {
+                message: "transfer".into(),
+                args: vec![
+                    Arg::Var("bob".into()),
+                    Arg::Value(json!(100)),
+                    Arg::Value(json!("memo, with comma")),
+                ],
+                value: 5,
+            }))
+        );
+        assert_eq!(
+            parse("submit flip()").unwrap(),
+            Statement::Submit(Invocation {
+                message: "flip".into(),
+                args: Vec::new(),
+                value: 0,
+            })
+        );
+        assert!(parse("flip() for 5").is_err());
+        assert!(parse("submit flip").is_err());
+    }
+
+    #[test]
+    fn variables_are_bound_to_calls_and_values() {
+        assert_eq!(
+            parse("let supply = total_supply()").unwrap(),
+            Statement::Let(
+                "supply".into(),
+                Expr::Call(Invocation {
+                    message: "total_supply".into(),
+                    args: Vec::new(),
+                    value: 0,
+                })
+            )
+        );
+        assert_eq!(
+            parse("let bob = 5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty").unwrap(),
+            Statement::Let(
+                "bob".into(),
+                Expr::Arg(Arg::Value(json!(
+                    "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty"
+                )))
+            )
+        );
+        assert_eq!(
+            parse("$_").unwrap(),
+            Statement::Eval(Expr::Arg(Arg::Var("_".into())))
+        );
+        assert!(parse("let _ = 1").is_err());
+        assert!(parse("let 1x = 1").is_err());
+    }
+
+    #[test]
+    fn the_word_before_the_cursor_is_completed() {
+        let names = vec!["submit".to_string(), "transfer".into(), "$total".into()];
+
+        assert_eq!(complete(&names, "su", 2), (0, vec!["submit".into()]));
+        assert_eq!(
+            complete(&names, "submit tr", 9),
+            (7, vec!["transfer".into()])
+        );
+        assert_eq!(
+            complete(&names, "transfer($t", 11),
+            (9, vec!["$total".into()])
+        );
+        assert_eq!(complete(&names, "x", 1), (0, Vec::<String>::new()));
+    }
+}
@@ -0,0 +1,261 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    call::{dry_run_at, ContractExecResult},
+    fork::block_hash,
+};
+use crate::{rpc::RpcClient, ExtrinsicOpts};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::Deserialize;
+use sp_core::crypto::Ss58Codec;
+use std::{fs, path::PathBuf};
+use structopt::StructOpt;
+
+/// The gas limit of recorded calls which don't supply one.
+const DEFAULT_GAS_LIMIT: u64 = 500_000_000;
+
+/// Replays recorded calls as dry-runs at an old block and on the current state, reporting the
+/// calls whose results or gas consumption differ.
+///
+/// Detects changes of behavior introduced by upgrades of the runtime or of the code of
+/// contracts since the old block. Nothing is submitted.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "replay")]
+pub struct ReplayCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Path to the recorded calls, a JSON array of objects with the `contract` and `origin`
+    /// addresses, the hex encoded call `data` and optionally a `label`, the `value` as decimal
+    /// string and the `gasLimit`
+    #[structopt(parse(from_os_str))]
+    calls: PathBuf,
+    /// The old block to compare the current state with, by number or hash
+    #[structopt(long)]
+    at: String,
+    /// Changes of the consumed gas up to this percentage are tolerated
+    #[structopt(long, default_value = "10")]
+    gas_tolerance: u64,
+}
+
+/// A call to replay.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordedCall {
+    label: Option<String>,
+    contract: String,
+    origin: String,
+    /// The hex encoded call data: the message selector followed by the SCALE encoded arguments.
+    data: String,
+    /// The value transferred with the call as decimal string, `0` if empty.
+    #[serde(default)]
+    value: String,
+    gas_limit: Option<u64>,
+}
+
+impl ReplayCommand {
+    pub fn exec(&self) -> Result<String> {
+        let contents = fs::read_to_string(&self.calls).context(format!(
+            "Failed to read the recorded calls {}",
+            self.calls.display()
+        ))?;
+        let calls: Vec<RecordedCall> = serde_json::from_str(&contents)
+            .context(format!("Invalid recorded calls {}", self.calls.display()))?;
+        let url = self.extrinsic_opts.url()?;
+        let at = block_hash(&RpcClient::new(&url)?, Some(&self.at))?;
+
+        let mut regressions = 0;
+        for (index, call) in calls.iter().enumerate() {
+            let label = call
+                .label
+                .clone()
+                .unwrap_or_else(|| format!("call {}", index));
+            let contract = crate::parse_account(&call.contract)?;
+            let origin = crate::parse_account(&call.origin)?;
+            let data = hex::decode(call.data.trim_start_matches("0x"))
+                .context(format!("The data of {} is no hex", label))?;
+            let value = match call.value.as_str() {
+                "" => 0,
+                value => value
+                    .parse()
+                    .context(format!("The value of {} is no integer", label))?,
+            };
+            let gas_limit = call.gas_limit.unwrap_or(DEFAULT_GAS_LIMIT);
+            let dry_run = |at| dry_run_at(&url, at, &origin, &contract, value, gas_limit, &data);
+            let old = dry_run(Some(at.as_str()))?;
+            let new = dry_run(None)?;
+
+            let differences = compare(&old, &new, self.gas_tolerance);
+            if differences.is_empty() {
+                println!(
+                    "{} {} of {}: {}",
+                    "unchanged".green().bold(),
+                    label,
+                    contract.to_ss58check(),
+                    summary(&new)
+                );
+            } else {
+                regressions += 1;
+                println!(
+                    "{} {} of {}:\n  {}",
+                    "changed".red().bold(),
+                    label,
+                    contract.to_ss58check(),
+                    differences.join("\n  ")
+                );
+            }
+        }
+        if regressions > 0 {
+            anyhow::bail!(
+                "{} of {} calls behave differently than at block {}",
+                regressions,
+                calls.len(),
+                at
+            )
+        }
+        Ok(format!(
+            "All {} calls behave the same as at block {}",
+            calls.len(),
+            at
+        ))
+    }
+}
+
+/// Returns the differences between the dry-run of a call at the old block and on the current
+/// state, tolerating changes of the consumed gas up to `gas_tolerance` percent.
+fn compare(old: &ContractExecResult, new: &ContractExecResult, gas_tolerance: u64) -> Vec<String> {
+    let (old_flags, old_data, old_gas, new_flags, new_data, new_gas) = match (old, new) {
+        (ContractExecResult::Error(()), ContractExecResult::Error(())) => return Vec::new(),
+        (ContractExecResult::Error(()), _) => {
+            return vec![format!("trapped at the old block, now {}", summary(new))]
+        }
+        (_, ContractExecResult::Error(())) => {
+            return vec![format!("{} at the old block, now traps", summary(old))]
+        }
+        (
+            ContractExecResult::Success {
+                flags: old_flags,
+                data: old_data,
+                gas_consumed: old_gas,
+            },
+            ContractExecResult::Success {
+                flags: new_flags,
+                data: new_data,
+                gas_consumed: new_gas,
+            },
+        ) => (old_flags, old_data, old_gas, new_flags, new_data, new_gas),
+    };
+    let mut differences = Vec::new();
+    if old_flags != new_flags {
+        differences.push(format!(
+            "returns the flags {} instead of {}",
+            new_flags, old_flags
+        ));
+    }
+    if old_data != new_data {
+        differences.push(format!("returns {} instead of {}", new_data, old_data));
+    }
+    let change = if new_gas > old_gas {
+        new_gas - old_gas
+    } else {
+        old_gas - new_gas
+    };
+    if change.saturating_mul(100) > old_gas.saturating_mul(gas_tolerance) {
+        let percent = change as f64 * 100.0 / (*old_gas).max(1) as f64;
+        differences.push(format!(
+            "consumes {} gas instead of {} ({}{:.1}%)",
+            new_gas,
+            old_gas,
+            if new_gas > old_gas { "+" } else { "-" },
+            percent
+        ));
+    }
+    differences
+}
+
+/// Returns a short summary of the `result` of a dry-run.
+fn summary(result: &ContractExecResult) -> String {
+    match result {
+        ContractExecResult::Success {
+            flags,
+            data,
+            gas_consumed,
+        } => format!(
+            "returns {} with flags {}, consuming {} gas",
+            data, flags, gas_consumed
+        ),
+        ContractExecResult::Error(()) => "traps".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn success(data: &str, gas_consumed: u64) -> ContractExecResult {
+        ContractExecResult::Success {
+            flags: 0,
+            data: data.into(),
+            gas_consumed,
+        }
+    }
+
+    #[test]
+    fn changes_of_gas_within_the_tolerance_are_ignored() {
+        assert!(compare(&success("0x00", 1000), &success("0x00", 1100), 10).is_empty());
+        assert_eq!(
+            compare(&success("0x00", 1000), &success("0x00", 1101), 10),
+            vec!["consumes 1101 gas instead of 1000 (+10.1%)"]
+        );
+        assert_eq!(
+            compare(&success("0x00", 1000), &success("0x00", 500), 10),
+            vec!["consumes 500 gas instead of 1000 (-50.0%)"]
+        );
+    }
+
+    #[test]
+    fn changed_results_are_reported() {
+        assert_eq!(
+            compare(&success("0x00", 1000), &success("0x01", 1000), 10),
+            vec!["returns 0x01 instead of 0x00"]
+        );
+        assert_eq!(
+            compare(&success("0x00", 1000), &ContractExecResult::Error(()), 10),
+            vec!["returns 0x00 with flags 0, consuming 1000 gas at the old block, now traps"]
+        );
+        assert!(compare(
+            &ContractExecResult::Error(()),
+            &ContractExecResult::Error(()),
+            10
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn recorded_calls_are_parsed() {
+        let calls: Vec<RecordedCall> = serde_json::from_str(
+            r#"[{ "label": "transfer", "contract": "5C4hrfjw9DjXZTzV3MwzrrAr9P1MJhSrvWGWqi1eSuyUpnhM",
+                  "origin": "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+                  "data": "0x84a15da1", "gasLimit": 1000 }]"#,
+        )
+        .unwrap();
+
+        assert_eq!(calls[0].label.as_deref(), Some("transfer"));
+        assert_eq!(calls[0].value, "");
+        assert_eq!(calls[0].gas_limit, Some(1000));
+    }
+}
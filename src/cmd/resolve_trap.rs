@@ -0,0 +1,377 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use gimli::{AttributeValue, EndianSlice, LittleEndian};
+use parity_wasm::elements::{ImportCountType, Module};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+/// The prefix of the names of the custom sections containing DWARF.
+pub(crate) const DWARF_SECTION_PREFIX: &str = ".debug_";
+
+/// The id of the code section of a wasm module.
+const CODE_SECTION_ID: u8 = 10;
+
+/// Returns the path of the debug info sidecar file of the wasm at `wasm`.
+pub(crate) fn debug_info_path(wasm: &Path) -> PathBuf {
+    wasm.with_extension("debug.wasm")
+}
+
+/// Resolves a trap of a contract to the Rust function and its source location.
+///
+/// The trap is identified by the index of the function or by the offset of the trapping
+/// instruction in the wasm, as reported in the node log. Names are only available if the contract
+/// was built with `--debug`, which also writes the DWARF required for source locations to
+/// `<name>.debug.wasm`.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "resolve-trap")]
+pub struct ResolveTrapCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// The index of the trapping function
+    #[structopt(long, required_unless = "offset")]
+    function: Option<u32>,
+    /// The offset of the trapping instruction from the start of the wasm, e.g. `0x1a2b`
+    #[structopt(long, parse(try_from_str = parse_offset))]
+    offset: Option<usize>,
+    /// Path to the debug info written by `--debug` builds, defaults to `<wasm>.debug.wasm`
+    #[structopt(long, parse(from_os_str))]
+    debug_info: Option<PathBuf>,
+    /// Path to the deployed wasm, defaults to `./target/ink/<name>.wasm`
+    #[structopt(parse(from_os_str))]
+    wasm_path: Option<PathBuf>,
+}
+
+impl ResolveTrapCommand {
+    pub fn exec(&self) -> Result<String> {
+        let wasm_path = match &self.wasm_path {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                CrateMetadata::collect(&manifest_path)?.dest_wasm
+            }
+        };
+        let wasm = fs::read(&wasm_path).context(format!(
+            "Failed to read {}, build the contract first",
+            wasm_path.display()
+        ))?;
+        let module: Module = parity_wasm::deserialize_buffer(&wasm)?;
+
+        let index = match (self.function, self.offset) {
+            (Some(index), _) => index,
+            (None, Some(offset)) => function_at_offset(&module, &wasm, offset)?,
+            (None, None) => anyhow::bail!("Either --function or --offset is required"),
+        };
+        let name = function_name(module, index);
+
+        let debug_info_path = self
+            .debug_info
+            .clone()
+            .unwrap_or_else(|| debug_info_path(&wasm_path));
+        let source = match (&name, fs::read(&debug_info_path)) {
+            (Some(name), Ok(debug_info)) => {
+                let debug_info: Module = parity_wasm::deserialize_buffer(&debug_info)
+                    .context(format!("Failed to read {}", debug_info_path.display()))?;
+                source_locations(&debug_info)?.remove(&normalize(name))
+            }
+            (Some(_), Err(_)) => {
+                log::warn!(
+                    "No debug info found at {}, build the contract with `--debug`",
+                    debug_info_path.display()
+                );
+                None
+            }
+            (None, _) => None,
+        };
+
+        Ok(TrapLocation {
+            index,
+            name,
+            source,
+        }
+        .to_string())
+    }
+}
+
+/// Parses a decimal or `0x` prefixed hexadecimal offset.
+fn parse_offset(offset: &str) -> Result<usize> {
+    let parsed = match offset.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => offset.parse(),
+    };
+    parsed.context(format!("Invalid offset {}", offset))
+}
+
+/// The resolved location of a trap.
+struct TrapLocation {
+    index: u32,
+    name: Option<String>,
+    source: Option<SourceLocation>,
+}
+
+impl fmt::Display for TrapLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(
+                f,
+                "function {}: {:#}",
+                self.index,
+                rustc_demangle::demangle(name)
+            )?,
+            None => write!(
+                f,
+                "function {}: <unknown>, build the contract with `--debug` to keep the names",
+                self.index
+            )?,
+        }
+        if let Some(source) = &self.source {
+            write!(f, "\n  at {}:{}", source.file, source.line)?;
+        }
+        Ok(())
+    }
+}
+
+/// The location of the definition of a function in the source code.
+#[derive(Clone, Debug, PartialEq)]
+struct SourceLocation {
+    file: String,
+    line: u64,
+}
+
+/// Returns the index of the function whose body contains the instruction at `offset` from the
+/// start of the `wasm`.
+fn function_at_offset(module: &Module, wasm: &[u8], offset: usize) -> Result<u32> {
+    let imported = module.import_count(ImportCountType::Function) as u32;
+    // skip the magic number and the version
+    let mut pos = 8;
+    while pos < wasm.len() {
+        let id = wasm[pos];
+        pos += 1;
+        let size = read_leb(wasm, &mut pos)? as usize;
+        let end = pos + size;
+        if id == CODE_SECTION_ID {
+            let count = read_leb(wasm, &mut pos)?;
+            for body in 0..count {
+                let size = read_leb(wasm, &mut pos)? as usize;
+                if (pos..pos + size).contains(&offset) {
+                    return Ok(imported + body);
+                }
+                pos += size;
+            }
+            anyhow::bail!("The offset {:#x} is not within a function body", offset)
+        }
+        pos = end;
+    }
+    anyhow::bail!("The wasm contains no code section")
+}
+
+/// Reads an unsigned LEB128 encoded integer at `pos` and advances `pos` past it.
+fn read_leb(bytes: &[u8], pos: &mut usize) -> Result<u32> {
+    let mut result = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes
+            .get(*pos)
+            .ok_or_else(|| anyhow::anyhow!("Unexpected end of the wasm"))?;
+        *pos += 1;
+        result |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+    }
+    anyhow::bail!("Invalid LEB128 integer in the wasm")
+}
+
+/// Returns the name of the function at `index`, if the wasm contains a name section.
+fn function_name(module: Module, index: u32) -> Option<String> {
+    let module = module.parse_names().unwrap_or_else(|(_, m)| m);
+    module
+        .names_section()
+        .and_then(|names| names.functions())
+        .and_then(|functions| functions.names().get(index))
+        .cloned()
+}
+
+/// Returns the demangled name including the hash, so that mangled and demangled names of the
+/// same function are equal.
+fn normalize(name: &str) -> String {
+    rustc_demangle::demangle(name).to_string()
+}
+
+/// Returns the source locations of the functions described by the DWARF sections of
+/// `debug_info`, by their normalized linkage name.
+fn source_locations(debug_info: &Module) -> Result<HashMap<String, SourceLocation>> {
+    let sections = debug_info
+        .custom_sections()
+        .map(|section| (section.name(), section.payload()))
+        .collect::<HashMap<_, _>>();
+    let dwarf = gimli::Dwarf::load(|id: gimli::SectionId| {
+        let data = sections.get(id.name()).copied().unwrap_or(&[][..]);
+        Ok::<_, gimli::Error>(EndianSlice::new(data, LittleEndian))
+    })?;
+
+    let mut locations = HashMap::new();
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs()? {
+            if entry.tag() != gimli::DW_TAG_subprogram {
+                continue;
+            }
+            let name = match entry.attr_value(gimli::DW_AT_linkage_name)? {
+                Some(name) => dwarf.attr_string(&unit, name)?.to_string_lossy(),
+                None => continue,
+            };
+            let line = entry
+                .attr_value(gimli::DW_AT_decl_line)?
+                .and_then(|line| line.udata_value());
+            let file = match entry.attr_value(gimli::DW_AT_decl_file)? {
+                Some(AttributeValue::FileIndex(file)) => file_path(&dwarf, &unit, file)?,
+                _ => None,
+            };
+            if let (Some(file), Some(line)) = (file, line) {
+                locations.insert(normalize(&name), SourceLocation { file, line });
+            }
+        }
+    }
+    Ok(locations)
+}
+
+/// Returns the path of the file at `index` of the line program of the `unit`.
+fn file_path<'a>(
+    dwarf: &gimli::Dwarf<EndianSlice<'a, LittleEndian>>,
+    unit: &gimli::Unit<EndianSlice<'a, LittleEndian>>,
+    index: u64,
+) -> Result<Option<String>> {
+    let header = match &unit.line_program {
+        Some(program) => program.header(),
+        None => return Ok(None),
+    };
+    let file = match header.file(index) {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+    let mut path = PathBuf::new();
+    if let Some(directory) = file.directory(header) {
+        path.push(&*dwarf.attr_string(unit, directory)?.to_string_lossy());
+    }
+    path.push(&*dwarf.attr_string(unit, file.path_name())?.to_string_lossy());
+    Ok(Some(path.display().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    const CONTRACT: &str = r#"
+        (module
+            (func $_ZN11my_contract4flip17h0123456789abcdefE (drop (i32.const 1)) (unreachable))
+            (func $call (export "call") (call $_ZN11my_contract4flip17h0123456789abcdefE))
+            (func $deploy (export "deploy"))
+        )"#;
+
+    fn wasm(names: bool) -> Vec<u8> {
+        wabt::Wat2Wasm::new()
+            .write_debug_names(names)
+            .convert(CONTRACT)
+            .expect("invalid wabt")
+            .as_ref()
+            .to_vec()
+    }
+
+    /// Returns the offset of the `unreachable` instruction of the first function.
+    fn unreachable_offset(wasm: &[u8]) -> usize {
+        // `drop` followed by `unreachable`
+        wasm.windows(2)
+            .position(|window| window == [0x1a, 0x00])
+            .expect("unreachable not found")
+            + 1
+    }
+
+    #[test]
+    fn function_is_found_by_offset() {
+        let wasm = wasm(false);
+        let module = parity_wasm::deserialize_buffer(&wasm).unwrap();
+
+        assert_eq!(
+            function_at_offset(&module, &wasm, unreachable_offset(&wasm)).unwrap(),
+            0
+        );
+        assert!(function_at_offset(&module, &wasm, 0).is_err());
+    }
+
+    #[test]
+    fn trap_is_resolved_to_function_name() {
+        with_tmp_dir(|path| {
+            // given
+            let wasm = wasm(true);
+            let wasm_path = path.join("contract.wasm");
+            fs::write(&wasm_path, &wasm)?;
+            let offset = format!("{:#x}", unreachable_offset(&wasm));
+            let cmd = ResolveTrapCommand::from_iter(&[
+                "resolve-trap",
+                "--offset",
+                &offset,
+                wasm_path.to_str().unwrap(),
+            ]);
+
+            // when
+            let location = cmd.exec()?;
+
+            // then
+            assert_eq!(location, "function 0: my_contract::flip");
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn missing_names_are_reported() {
+        with_tmp_dir(|path| {
+            // given
+            let wasm_path = path.join("contract.wasm");
+            fs::write(&wasm_path, wasm(false))?;
+            let cmd = ResolveTrapCommand::from_iter(&[
+                "resolve-trap",
+                "--function",
+                "1",
+                wasm_path.to_str().unwrap(),
+            ]);
+
+            // when
+            let location = cmd.exec()?;
+
+            // then
+            assert!(location.starts_with("function 1: <unknown>"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn offset_is_parsed() {
+        assert_eq!(parse_offset("0x1a2b").unwrap(), 0x1a2b);
+        assert_eq!(parse_offset("42").unwrap(), 42);
+        assert!(parse_offset("0xzz").is_err());
+    }
+}
@@ -0,0 +1,455 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    build::{self, ExecuteArgs},
+    call::{dry_run, resolve_bundle, ContractExecResult, FLAG_REVERT},
+    deploy::load_contract_code,
+    gas_report::{call_fee, GasReport, GasReportOpts},
+    scale_json::{item, ScaleJson},
+    trait_definition::{contract_of, Registry},
+};
+use crate::{bundle::ContractBundle, deployments, ExtrinsicOpts, OriginOpts};
+use anyhow::Result;
+use colored::Colorize;
+use rhai::{
+    serde::{from_dynamic, to_dynamic},
+    Array, Dynamic, Engine, EvalAltResult,
+};
+use serde_json::Value;
+use sp_core::{
+    crypto::{AccountId32, Ss58Codec},
+    H256,
+};
+use std::{cell::RefCell, collections::HashMap, convert::TryFrom, path::PathBuf, rc::Rc};
+use structopt::StructOpt;
+use subxt::{contracts::*, system::System, ClientBuilder, DefaultNodeRuntime, Signer};
+
+/// Runs a deployment script, to codify multi-step deployments and the seeding of contracts.
+///
+/// The script is written in [Rhai](https://rhai.rs) and submits extrinsics signed by the
+/// supplied secret key URI via these functions:
+///
+/// - `build()` builds the contract of the project and returns the path of its bundle
+/// - `deploy(bundle)` uploads the code of the bundle and returns its code hash
+/// - `instantiate(bundle, constructor, args[, endowment])` returns the address of the contract
+/// - `call(contract, message, args[, value])` submits a call of the message
+/// - `query(contract, message, args)` dry-runs the message and returns its decoded output
+/// - `assert(condition[, message])` and `assert_eq(left, right)` fail the script
+///
+/// Arguments are passed as arrays of their JSON representations, e.g. `[true]` or
+/// `["5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY", 100]`.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "run")]
+pub struct RunCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    #[structopt(flatten)]
+    origin_opts: OriginOpts,
+    /// Path to the script, e.g. `deploy.rhai`
+    #[structopt(parse(from_os_str))]
+    script: PathBuf,
+    /// Maximum amount of gas to be used for instantiations and calls
+    #[structopt(name = "gas", long, default_value = "500000000")]
+    gas_limit: u64,
+    /// Default endowment of instantiated contracts
+    #[structopt(long, default_value = "1000000000000000")]
+    endowment: u128,
+    #[structopt(flatten)]
+    gas_report: GasReportOpts,
+}
+
+impl RunCommand {
+    pub fn exec(&self) -> Result<String> {
+        // the secret is read only once, since a file descriptor can't be read again
+        let signer = self.extrinsic_opts.signer()?;
+        let origin = match self.origin_opts.account() {
+            Some(origin) => origin.clone(),
+            None => signer.account_id().clone(),
+        };
+        let runner = Rc::new(Runner {
+            extrinsic_opts: self.extrinsic_opts.clone(),
+            signer,
+            origin,
+            gas_limit: self.gas_limit,
+            contracts: RefCell::new(HashMap::new()),
+            gas_report: self.gas_report.report().map(RefCell::new),
+        });
+        let mut engine = Engine::new();
+        register_assertions(&mut engine);
+        register_contracts(&mut engine, runner.clone(), self.endowment);
+        let result = engine
+            .consume_file(self.script.clone())
+            .map_err(|err| anyhow::anyhow!("{} failed: {}", self.script.display(), err));
+        // the costs of the calls up to a failure are reported as well
+        if let Some(report) = &runner.gas_report {
+            self.gas_report.finish(&report.borrow())?;
+        }
+        result?;
+        Ok(format!(
+            "{} {}",
+            "Ran".green().bold(),
+            self.script.display()
+        ))
+    }
+}
+
+/// Executes the functions of a script interacting with contracts.
+struct Runner {
+    extrinsic_opts: ExtrinsicOpts,
+    signer: Box<dyn Signer<DefaultNodeRuntime> + Send + Sync>,
+    /// The account queries are dry-run from.
+    origin: AccountId32,
+    gas_limit: u64,
+    /// The metadata of the contracts the script interacted with, by their address.
+    contracts: RefCell<HashMap<AccountId32, Rc<Value>>>,
+    /// The costs of the calls of the script, if reported.
+    gas_report: Option<RefCell<GasReport>>,
+}
+
+impl Runner {
+    /// Builds the contract of the project and returns the path of its bundle.
+    fn build(&self) -> Result<String> {
+        let args = ExecuteArgs {
+            manifest_path: self.extrinsic_opts.manifest_path()?,
+            ..Default::default()
+        };
+        let bundle = build::execute(args)?
+            .metadata_result
+            .expect("the bundle is built for all artifacts; qed")
+            .dest_bundle;
+        Ok(bundle.display().to_string())
+    }
+
+    /// Uploads the code of the `bundle` and returns its code hash.
+    fn deploy(&self, bundle: &str) -> Result<String> {
+        let manifest_path = self.extrinsic_opts.manifest_path()?;
+        let code = load_contract_code(&manifest_path, Some(&PathBuf::from(bundle)))?;
+        let code_hash = async_std::task::block_on(async move {
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_url(&self.extrinsic_opts.url()?.to_string())
+                .build()
+                .await?;
+            let events = cli.put_code_and_watch(self.signer.as_ref(), &code).await?;
+            let code_stored = events
+                .code_stored()?
+                .ok_or_else(|| anyhow::anyhow!("Failed to find CodeStored event"))?;
+            Ok::<_, anyhow::Error>(code_stored.code_hash)
+        })?;
+        println!("{} {} {:?}", "Deployed".green().bold(), bundle, code_hash);
+        Ok(format!("{:?}", code_hash))
+    }
+
+    /// Instantiates the deployed code of the `bundle` with the `constructor` and returns the
+    /// address of the contract.
+    fn instantiate(
+        &self,
+        bundle: &str,
+        constructor: &str,
+        args: Array,
+        endowment: u128,
+    ) -> Result<String> {
+        let bundle = ContractBundle::load(&PathBuf::from(bundle))?;
+        let metadata = Rc::new(Value::Object(bundle.json().clone()));
+        let data = encode(&metadata, "constructors", constructor, args)?;
+        let code_hash = bundle.code_hash()?;
+        let contract = async_std::task::block_on(async move {
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_url(&self.extrinsic_opts.url()?.to_string())
+                .build()
+                .await?;
+            let events = cli
+                .instantiate_and_watch(
+                    self.signer.as_ref(),
+                    endowment,
+                    self.gas_limit,
+                    &H256(code_hash),
+                    &data,
+                )
+                .await?;
+            let instantiated = events
+                .instantiated()?
+                .ok_or_else(|| anyhow::anyhow!("Failed to find Instantiated event"))?;
+            Ok::<_, anyhow::Error>(instantiated.contract)
+        })?;
+        deployments::record_instantiation(
+            &self.extrinsic_opts,
+            Some(&bundle),
+            &contract,
+            &code_hash,
+        )?;
+        println!(
+            "{} {} {}",
+            "Instantiated".green().bold(),
+            constructor,
+            contract.to_ss58check()
+        );
+        self.contracts
+            .borrow_mut()
+            .insert(contract.clone(), metadata);
+        Ok(contract.to_ss58check())
+    }
+
+    /// Submits a call of the `message` of the `contract`, transferring `value` to it.
+    fn call(&self, contract: &str, message: &str, args: Array, value: u128) -> Result<()> {
+        let contract = crate::parse_account(contract)?;
+        let data = encode(&self.metadata(&contract)?, "messages", message, args)?;
+        if let Some(report) = &self.gas_report {
+            let url = self.extrinsic_opts.url()?;
+            let fee = call_fee(
+                &url,
+                self.signer.as_ref(),
+                &contract,
+                value,
+                self.gas_limit,
+                &data,
+            )?;
+            report.borrow_mut().record(
+                &url,
+                message,
+                self.signer.account_id(),
+                &contract,
+                value,
+                self.gas_limit,
+                &data,
+                Some(fee),
+            )?;
+        }
+        let dest: <DefaultNodeRuntime as System>::Address = contract.clone().into();
+        let result = async_std::task::block_on(async move {
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_url(&self.extrinsic_opts.url()?.to_string())
+                .build()
+                .await?;
+            let result = cli
+                .call_and_watch(self.signer.as_ref(), &dest, value, self.gas_limit, &data)
+                .await?;
+            Ok::<_, anyhow::Error>(result)
+        })?;
+        println!(
+            "{} {} in block {:?}",
+            "Called".green().bold(),
+            message,
+            result.block
+        );
+        Ok(())
+    }
+
+    /// Dry-runs the `message` of the `contract` and returns its decoded output.
+    fn query(&self, contract: &str, message: &str, args: Array) -> Result<Value> {
+        let contract = crate::parse_account(contract)?;
+        let metadata = self.metadata(&contract)?;
+        let data = encode(&metadata, "messages", message, args)?;
+        let url = self.extrinsic_opts.url()?;
+        if let Some(report) = &self.gas_report {
+            report.borrow_mut().record(
+                &url,
+                message,
+                &self.origin,
+                &contract,
+                0,
+                self.gas_limit,
+                &data,
+                None,
+            )?;
+        }
+        let output = match dry_run(&url, &self.origin, &contract, 0, self.gas_limit, &data)? {
+            ContractExecResult::Success { flags, .. } if flags & FLAG_REVERT == FLAG_REVERT => {
+                anyhow::bail!("The query of {} reverted", message)
+            }
+            ContractExecResult::Success { data, .. } => hex::decode(data.trim_start_matches("0x"))?,
+            ContractExecResult::Error(()) => anyhow::bail!("The query of {} trapped", message),
+        };
+        let spec = contract_of(&metadata);
+        let registry = Registry::new(spec);
+        ScaleJson::new(&registry).decode_output(item(spec, "messages", message)?, &output)
+    }
+
+    /// Returns the metadata of the `contract`, resolved by its code hash on chain unless the
+    /// script instantiated it.
+    fn metadata(&self, contract: &AccountId32) -> Result<Rc<Value>> {
+        if let Some(metadata) = self.contracts.borrow().get(contract) {
+            return Ok(metadata.clone());
+        }
+        let bundle = resolve_bundle(&self.extrinsic_opts, contract)?;
+        let metadata = Rc::new(Value::Object(bundle.json().clone()));
+        self.contracts
+            .borrow_mut()
+            .insert(contract.clone(), metadata.clone());
+        Ok(metadata)
+    }
+}
+
+/// Registers the functions of scripts interacting with contracts via the `runner`.
+fn register_contracts(engine: &mut Engine, runner: Rc<Runner>, endowment: u128) {
+    let r = runner.clone();
+    engine.register_result_fn("build", move || script_result(r.build()));
+    let r = runner.clone();
+    engine.register_result_fn("deploy", move |bundle: &str| {
+        script_result(r.deploy(bundle))
+    });
+    let r = runner.clone();
+    engine.register_result_fn(
+        "instantiate",
+        move |bundle: &str, constructor: &str, args: Array| {
+            script_result(r.instantiate(bundle, constructor, args, endowment))
+        },
+    );
+    let r = runner.clone();
+    engine.register_result_fn(
+        "instantiate",
+        move |bundle: &str, constructor: &str, args: Array, endowment: i64| {
+            let endowment = balance(endowment)?;
+            script_result(r.instantiate(bundle, constructor, args, endowment))
+        },
+    );
+    let r = runner.clone();
+    engine.register_result_fn("call", move |contract: &str, message: &str, args: Array| {
+        script_result(r.call(contract, message, args, 0))
+    });
+    let r = runner.clone();
+    engine.register_result_fn(
+        "call",
+        move |contract: &str, message: &str, args: Array, value: i64| {
+            let value = balance(value)?;
+            script_result(r.call(contract, message, args, value))
+        },
+    );
+    engine.register_result_fn(
+        "query",
+        move |contract: &str, message: &str, args: Array| {
+            to_dynamic(
+                runner
+                    .query(contract, message, args)
+                    .map_err(script_error)?,
+            )
+        },
+    );
+}
+
+/// Registers the assertions of scripts, which fail the script with a message.
+fn register_assertions(engine: &mut Engine) {
+    engine.register_result_fn("assert", |condition: bool| {
+        assert_script(condition, || "Assertion failed".into())
+    });
+    engine.register_result_fn("assert", |condition: bool, message: &str| {
+        assert_script(condition, || format!("Assertion failed: {}", message))
+    });
+    engine.register_result_fn("assert_eq", |left: Dynamic, right: Dynamic| {
+        let left: Value = from_dynamic(&left)?;
+        let right: Value = from_dynamic(&right)?;
+        assert_script(left == right, || {
+            format!("Assertion failed: {} is not equal to {}", left, right)
+        })
+    });
+}
+
+fn assert_script(
+    condition: bool,
+    message: impl FnOnce() -> String,
+) -> Result<Dynamic, Box<EvalAltResult>> {
+    if condition {
+        Ok(Dynamic::UNIT)
+    } else {
+        Err(message().into())
+    }
+}
+
+/// Returns the call data of the `name` constructor or message of the contract `metadata` with
+/// the script `args`.
+fn encode(metadata: &Value, kind: &str, name: &str, args: Array) -> Result<Vec<u8>> {
+    let spec = contract_of(metadata);
+    let args = args
+        .iter()
+        .map(from_dynamic)
+        .collect::<Result<Vec<Value>, _>>()
+        .map_err(|err| anyhow::anyhow!("Invalid arguments of {}: {}", name, err))?;
+    let registry = Registry::new(spec);
+    ScaleJson::new(&registry).encode_message(item(spec, kind, name)?, &args)
+}
+
+/// Returns the balance of a script integer, which can't be negative.
+fn balance(amount: i64) -> Result<u128, Box<EvalAltResult>> {
+    u128::try_from(amount).map_err(|_| format!("Invalid negative amount {}", amount).into())
+}
+
+fn script_result<T: Clone + Send + Sync + 'static>(
+    result: Result<T>,
+) -> Result<Dynamic, Box<EvalAltResult>> {
+    result.map(Dynamic::from).map_err(script_error)
+}
+
+fn script_error(err: anyhow::Error) -> Box<EvalAltResult> {
+    format!("{:#}", err).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn receipt_is_rejected() {
+        // scripts submit several extrinsics, a single receipt path would be overwritten
+        let cmd = RunCommand::from_iter_safe(&["run", "deploy.rhai", "--receipt", "receipt.json"]);
+        assert!(cmd.is_err());
+    }
+
+    #[test]
+    fn assertions_fail_the_script() {
+        // given
+        let mut engine = Engine::new();
+        register_assertions(&mut engine);
+
+        // when
+        let passed = engine.consume(r#"assert(true); assert_eq([1, "a"], [1, "a"]);"#);
+        let failed = engine.consume(r#"assert_eq(#{ value: 1 }, #{ value: 2 });"#);
+
+        // then
+        assert!(passed.is_ok());
+        let err = failed.expect_err("the values differ").to_string();
+        assert!(
+            err.contains(r#"{"value":1} is not equal to {"value":2}"#),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn script_args_are_encoded_with_the_metadata() {
+        // given
+        let metadata = json!({ "V1": { "spec": {
+            "constructors": [{
+                "label": "new", "selector": "0x9bae9d5e",
+                "args": [{ "label": "init_value", "type": { "type": 0 } }],
+            }],
+            "messages": [],
+        }, "types": [{ "id": 0, "type": { "def": { "primitive": "bool" } } }] } });
+        let engine = Engine::new();
+        let args: Array = engine.eval("[true]").unwrap();
+
+        // when
+        let data = encode(&metadata, "constructors", "new", args.clone()).unwrap();
+        let unknown = encode(&metadata, "constructors", "default", args);
+
+        // then
+        assert_eq!(data, vec![0x9b, 0xae, 0x9d, 0x5e, 1]);
+        assert_eq!(
+            unknown.unwrap_err().to_string(),
+            "The contract has no constructor default"
+        );
+    }
+}
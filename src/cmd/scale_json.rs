@@ -0,0 +1,645 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Conversion of SCALE encoded values from and to their JSON representation in `@polkadot/api`,
+//! as described by the schemas of `cargo contract metadata schema`.
+//!
+//! Integers wider than 32 bits are decoded as numbers if JavaScript represents them exactly, and
+//! as decimal strings otherwise.
+
+use super::{trait_definition::Registry, verify_metadata::label};
+use anyhow::{Context, Result};
+use codec::{Compact, Decode, Encode};
+use heck::MixedCase as _;
+use serde_json::{Map, Value};
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::convert::TryFrom;
+
+/// The largest integer JavaScript numbers represent exactly.
+const MAX_SAFE_INTEGER: u128 = (1 << 53) - 1;
+
+/// Encodes and decodes the values of the types of a type registry.
+pub(super) struct ScaleJson<'a> {
+    registry: &'a Registry<'a>,
+}
+
+impl<'a> ScaleJson<'a> {
+    pub(super) fn new(registry: &'a Registry<'a>) -> Self {
+        Self { registry }
+    }
+
+    /// Returns the call data of the `message`: its selector followed by the encoded `args`.
+    pub(super) fn encode_message(&self, message: &Value, args: &[Value]) -> Result<Vec<u8>> {
+        let name = label(message).unwrap_or_default();
+        let selector = message
+            .get("selector")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("The message {} has no selector", name))?;
+        let mut data = hex::decode(selector.trim_start_matches("0x"))?;
+        let specs = items(message, "args");
+        if specs.len() != args.len() {
+            anyhow::bail!(
+                "The message {} takes {} arguments, but {} were supplied",
+                name,
+                specs.len(),
+                args.len()
+            )
+        }
+        for (spec, value) in specs.iter().zip(args) {
+            self.encode_arg(spec, value, &mut data)?;
+        }
+        Ok(data)
+    }
+
+    /// Appends the encoded `value` of the argument `spec` of a message to `output`.
+    pub(super) fn encode_arg(
+        &self,
+        spec: &Value,
+        value: &Value,
+        output: &mut Vec<u8>,
+    ) -> Result<()> {
+        let name = label(spec).unwrap_or_default();
+        let id = spec["type"]["type"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("The argument {} has no type", name))?;
+        self.encode(id, value, output)
+            .context(format!("Invalid argument {}", name))
+    }
+
+    /// Decodes the `output` of the `message`, which is `null` if the message returns nothing.
+    pub(super) fn decode_output(&self, message: &Value, output: &[u8]) -> Result<Value> {
+        match message["returnType"]["type"].as_u64() {
+            Some(id) => self.decode(id, &mut &output[..]),
+            None => Ok(Value::Null),
+        }
+    }
+
+    /// Returns the name and the arguments of the event `data` of the `contract` spec.
+    ///
+    /// The events of a contract are encoded as enum, starting with the index of the event.
+    pub(super) fn decode_event(&self, contract: &Value, data: &[u8]) -> Result<(String, Value)> {
+        let (index, mut input) = data
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("The event is empty"))?;
+        let event = items(contract, "events")
+            .get(*index as usize)
+            .ok_or_else(|| anyhow::anyhow!("The contract has no event {}", index))?;
+        let mut args = Map::new();
+        for arg in items(event, "args") {
+            let id = arg["type"]["type"]
+                .as_u64()
+                .ok_or_else(|| anyhow::anyhow!("An argument of the event has no type"))?;
+            args.insert(label(arg).unwrap_or_default(), self.decode(id, &mut input)?);
+        }
+        let name = label(event).ok_or_else(|| anyhow::anyhow!("The event has no name"))?;
+        Ok((name, args.into()))
+    }
+
+    /// Appends the SCALE encoding of the JSON `value` of the type `id` to `output`.
+    pub(super) fn encode(&self, id: u64, value: &Value, output: &mut Vec<u8>) -> Result<()> {
+        let ty = self.registry.get(id)?;
+        let def = ty
+            .get("def")
+            .and_then(Value::as_object)
+            .ok_or_else(|| anyhow::anyhow!("The type {} has no definition", id))?;
+        let mismatch = || anyhow::anyhow!("The value {} does not match the type {}", value, id);
+
+        if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+            return encode_primitive(primitive, value, output).context(mismatch());
+        }
+        if let Some(compact) = def.get("compact") {
+            let integer = integer(value).ok_or_else(mismatch)?;
+            let integer = u128::try_from(integer).map_err(|_| mismatch())?;
+            Compact(integer).encode_to(output);
+            return Ok(());
+        }
+        if let Some(sequence) = def.get("sequence") {
+            let element = field_type(sequence)?;
+            if self.registry.is_primitive(element, "u8") {
+                let bytes = hex_bytes(value).ok_or_else(mismatch)?;
+                bytes.encode_to(output);
+                return Ok(());
+            }
+            let items = value.as_array().ok_or_else(mismatch)?;
+            Compact(items.len() as u32).encode_to(output);
+            return items
+                .iter()
+                .try_for_each(|item| self.encode(element, item, output));
+        }
+        if let Some(array) = def.get("array") {
+            let element = field_type(array)?;
+            let len = array.get("len").and_then(Value::as_u64).unwrap_or_default() as usize;
+            if self.registry.is_primitive(element, "u8") {
+                let bytes = hex_bytes(value)
+                    .filter(|bytes| bytes.len() == len)
+                    .ok_or_else(mismatch)?;
+                output.extend(bytes);
+                return Ok(());
+            }
+            let items = value
+                .as_array()
+                .filter(|items| items.len() == len)
+                .ok_or_else(mismatch)?;
+            return items
+                .iter()
+                .try_for_each(|item| self.encode(element, item, output));
+        }
+        if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+            if tuple.is_empty() {
+                return Ok(());
+            }
+            let items = value
+                .as_array()
+                .filter(|items| items.len() == tuple.len())
+                .ok_or_else(mismatch)?;
+            for (field, item) in tuple.iter().zip(items) {
+                self.encode(field_type(field)?, item, output)?;
+            }
+            return Ok(());
+        }
+
+        match (name(ty), self.registry.params(id)?.as_slice()) {
+            (Some("AccountId"), _) => {
+                let account = value
+                    .as_str()
+                    .and_then(|account| {
+                        AccountId32::from_ss58check(account)
+                            .ok()
+                            .map(|account| <[u8; 32]>::from(account).to_vec())
+                            .or_else(|| hex_bytes(value).filter(|bytes| bytes.len() == 32))
+                    })
+                    .ok_or_else(mismatch)?;
+                output.extend(account);
+                Ok(())
+            }
+            (Some("Option"), [some]) => {
+                if value.is_null() {
+                    output.push(0);
+                    Ok(())
+                } else {
+                    output.push(1);
+                    self.encode(*some, value, output)
+                }
+            }
+            (Some("Result"), [ok, err]) => match value.as_object().map(object_entry) {
+                Some(Some(("ok", value))) => {
+                    output.push(0);
+                    self.encode(*ok, value, output)
+                }
+                Some(Some(("err", value))) => {
+                    output.push(1);
+                    self.encode(*err, value, output)
+                }
+                _ => Err(mismatch()),
+            },
+            _ => {
+                if let Some(composite) = def.get("composite") {
+                    return self.encode_fields(composite, value, output);
+                }
+                let variants = variants(def)
+                    .ok_or_else(|| anyhow::anyhow!("The type {} can't be encoded", id))?;
+                // enums without fields are represented by the name of their variant
+                let (name, fields) = match value {
+                    Value::String(name) => (name.as_str(), &Value::Null),
+                    Value::Object(object) => object_entry(object).ok_or_else(mismatch)?,
+                    _ => return Err(mismatch()),
+                };
+                let (index, variant) = variants
+                    .iter()
+                    .enumerate()
+                    .find(|(_, variant)| {
+                        let variant_name = variant.get("name").and_then(Value::as_str);
+                        variant_name == Some(name)
+                            || variant_name
+                                .map(|variant| variant.to_mixed_case())
+                                .as_deref()
+                                == Some(name)
+                    })
+                    .ok_or_else(mismatch)?;
+                output.push(variant_index(variant, index));
+                self.encode_fields(variant, fields, output)
+            }
+        }
+    }
+
+    /// Decodes the value of the type `id` from the start of `input`, advancing it past the value.
+    pub(super) fn decode(&self, id: u64, input: &mut &[u8]) -> Result<Value> {
+        let ty = self.registry.get(id)?;
+        let def = ty
+            .get("def")
+            .and_then(Value::as_object)
+            .ok_or_else(|| anyhow::anyhow!("The type {} has no definition", id))?;
+
+        if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+            return decode_primitive(primitive, input);
+        }
+        if def.get("compact").is_some() {
+            return Ok(unsigned(Compact::<u128>::decode(input)?.0));
+        }
+        if let Some(sequence) = def.get("sequence") {
+            let element = field_type(sequence)?;
+            if self.registry.is_primitive(element, "u8") {
+                return Ok(hex(&Vec::<u8>::decode(input)?));
+            }
+            let len = Compact::<u32>::decode(input)?.0;
+            return (0..len)
+                .map(|_| self.decode(element, input))
+                .collect::<Result<_>>();
+        }
+        if let Some(array) = def.get("array") {
+            let element = field_type(array)?;
+            let len = array.get("len").and_then(Value::as_u64).unwrap_or_default() as usize;
+            if self.registry.is_primitive(element, "u8") {
+                return Ok(hex(&take(input, len)?));
+            }
+            return (0..len)
+                .map(|_| self.decode(element, input))
+                .collect::<Result<_>>();
+        }
+        if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+            if tuple.is_empty() {
+                return Ok(Value::Null);
+            }
+            return tuple
+                .iter()
+                .map(|field| self.decode(field_type(field)?, input))
+                .collect::<Result<_>>();
+        }
+
+        match (name(ty), self.registry.params(id)?.as_slice()) {
+            (Some("AccountId"), _) => {
+                let mut account = [0u8; 32];
+                account.copy_from_slice(&take(input, 32)?);
+                Ok(AccountId32::from(account).to_ss58check().into())
+            }
+            (Some("Option"), [some]) => match take(input, 1)?[0] {
+                0 => Ok(Value::Null),
+                1 => self.decode(*some, input),
+                flag => anyhow::bail!("Invalid option flag {}", flag),
+            },
+            (Some("Result"), [ok, err]) => {
+                let mut result = Map::new();
+                match take(input, 1)?[0] {
+                    0 => result.insert("ok".into(), self.decode(*ok, input)?),
+                    1 => result.insert("err".into(), self.decode(*err, input)?),
+                    flag => anyhow::bail!("Invalid result flag {}", flag),
+                };
+                Ok(result.into())
+            }
+            _ => {
+                if let Some(composite) = def.get("composite") {
+                    return self.decode_fields(composite, input);
+                }
+                let variants = variants(def)
+                    .ok_or_else(|| anyhow::anyhow!("The type {} can't be decoded", id))?;
+                let index = take(input, 1)?[0];
+                let variant = variants
+                    .iter()
+                    .enumerate()
+                    .find(|(position, variant)| variant_index(variant, *position) == index)
+                    .map(|(_, variant)| variant)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid variant {} of type {}", index, id))?;
+                let name = variant
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                let has_fields = variants.iter().any(|variant| !fields(variant).is_empty());
+                if !has_fields {
+                    return Ok(name.into());
+                }
+                let mut object = Map::new();
+                object.insert(name.to_mixed_case(), self.decode_fields(variant, input)?);
+                Ok(object.into())
+            }
+        }
+    }
+
+    /// Appends the encoding of the fields of a struct or enum variant to `output`.
+    fn encode_fields(&self, item: &Value, value: &Value, output: &mut Vec<u8>) -> Result<()> {
+        let fields = fields(item);
+        let mismatch = || anyhow::anyhow!("The value {} does not match the fields", value);
+        match fields {
+            [] => Ok(()),
+            // newtypes are represented by their field
+            [field] if field.get("name").is_none() => {
+                self.encode(field_type(field)?, value, output)
+            }
+            fields if fields[0].get("name").is_none() => {
+                let items = value
+                    .as_array()
+                    .filter(|items| items.len() == fields.len())
+                    .ok_or_else(mismatch)?;
+                for (field, item) in fields.iter().zip(items) {
+                    self.encode(field_type(field)?, item, output)?;
+                }
+                Ok(())
+            }
+            fields => {
+                let object = value.as_object().ok_or_else(mismatch)?;
+                for field in fields {
+                    let name = field
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    let item = object
+                        .get(&name.to_mixed_case())
+                        .or_else(|| object.get(name))
+                        .ok_or_else(|| anyhow::anyhow!("The field {} is missing", name))?;
+                    self.encode(field_type(field)?, item, output)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Decodes the fields of a struct or enum variant.
+    fn decode_fields(&self, item: &Value, input: &mut &[u8]) -> Result<Value> {
+        match fields(item) {
+            [] => Ok(Value::Null),
+            [field] if field.get("name").is_none() => self.decode(field_type(field)?, input),
+            fields if fields[0].get("name").is_none() => fields
+                .iter()
+                .map(|field| self.decode(field_type(field)?, input))
+                .collect::<Result<_>>(),
+            fields => {
+                let mut object = Map::new();
+                for field in fields {
+                    let name = field
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    object.insert(
+                        name.to_mixed_case(),
+                        self.decode(field_type(field)?, input)?,
+                    );
+                }
+                Ok(object.into())
+            }
+        }
+    }
+}
+
+/// Appends the encoding of the `value` of a primitive type to `output`.
+fn encode_primitive(primitive: &str, value: &Value, output: &mut Vec<u8>) -> Result<()> {
+    let mismatch = || anyhow::anyhow!("Expected a {}", primitive);
+    let number = || integer(value).ok_or_else(mismatch);
+    match primitive {
+        "bool" => value.as_bool().ok_or_else(mismatch)?.encode_to(output),
+        "str" => value.as_str().ok_or_else(mismatch)?.encode_to(output),
+        "char" => {
+            let mut chars = value.as_str().ok_or_else(mismatch)?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(char), None) => (char as u32).encode_to(output),
+                _ => return Err(mismatch()),
+            }
+        }
+        "u8" => u8::try_from(number()?)?.encode_to(output),
+        "u16" => u16::try_from(number()?)?.encode_to(output),
+        "u32" => u32::try_from(number()?)?.encode_to(output),
+        "u64" => u64::try_from(number()?)?.encode_to(output),
+        "u128" => u128::try_from(number()?)?.encode_to(output),
+        "i8" => i8::try_from(number()?)?.encode_to(output),
+        "i16" => i16::try_from(number()?)?.encode_to(output),
+        "i32" => i32::try_from(number()?)?.encode_to(output),
+        "i64" => i64::try_from(number()?)?.encode_to(output),
+        "i128" => number()?.encode_to(output),
+        _ => anyhow::bail!("Unsupported primitive {}", primitive),
+    }
+    Ok(())
+}
+
+/// Decodes the value of a primitive type from the start of `input`.
+fn decode_primitive(primitive: &str, input: &mut &[u8]) -> Result<Value> {
+    Ok(match primitive {
+        "bool" => bool::decode(input)?.into(),
+        "str" => String::decode(input)?.into(),
+        "char" => std::char::from_u32(u32::decode(input)?)
+            .ok_or_else(|| anyhow::anyhow!("Invalid char"))?
+            .to_string()
+            .into(),
+        "u8" => u8::decode(input)?.into(),
+        "u16" => u16::decode(input)?.into(),
+        "u32" => u32::decode(input)?.into(),
+        "u64" => unsigned(u64::decode(input)?.into()),
+        "u128" => unsigned(u128::decode(input)?),
+        "i8" => i8::decode(input)?.into(),
+        "i16" => i16::decode(input)?.into(),
+        "i32" => i32::decode(input)?.into(),
+        "i64" => signed(i64::decode(input)?.into()),
+        "i128" => signed(i128::decode(input)?),
+        _ => anyhow::bail!("Unsupported primitive {}", primitive),
+    })
+}
+
+/// Returns the integer of a JSON number, or of a decimal or hex string.
+fn integer(value: &Value) -> Option<i128> {
+    match value {
+        Value::Number(number) => number
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| number.as_u64().map(i128::from)),
+        Value::String(string) => match string.strip_prefix("0x") {
+            Some(hex) => i128::from_str_radix(hex, 16).ok(),
+            None => string.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+fn unsigned(integer: u128) -> Value {
+    if integer <= MAX_SAFE_INTEGER {
+        (integer as u64).into()
+    } else {
+        integer.to_string().into()
+    }
+}
+
+fn signed(integer: i128) -> Value {
+    if integer.unsigned_abs() <= MAX_SAFE_INTEGER {
+        (integer as i64).into()
+    } else {
+        integer.to_string().into()
+    }
+}
+
+/// Returns the bytes of a `0x` prefixed hex string.
+fn hex_bytes(value: &Value) -> Option<Vec<u8>> {
+    hex::decode(value.as_str()?.strip_prefix("0x")?).ok()
+}
+
+fn hex(bytes: &[u8]) -> Value {
+    format!("0x{}", hex::encode(bytes)).into()
+}
+
+/// Returns the next `len` bytes of `input`, advancing it past them.
+fn take(input: &mut &[u8], len: usize) -> Result<Vec<u8>> {
+    if input.len() < len {
+        anyhow::bail!("The encoded value ends unexpectedly")
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes.to_vec())
+}
+
+/// Returns the only entry of an object, e.g. the variant of an enum.
+fn object_entry(object: &Map<String, Value>) -> Option<(&str, &Value)> {
+    match object.iter().collect::<Vec<_>>().as_slice() {
+        [(key, value)] => Some((key.as_str(), *value)),
+        _ => None,
+    }
+}
+
+/// Returns the last segment of the path of a custom type.
+fn name(ty: &Value) -> Option<&str> {
+    ty.get("path")
+        .and_then(Value::as_array)
+        .and_then(|path| path.last())
+        .and_then(Value::as_str)
+}
+
+/// Returns the type id a field, element or tuple entry refers to.
+fn field_type(field: &Value) -> Result<u64> {
+    field
+        .get("type")
+        .and_then(Value::as_u64)
+        .or_else(|| field.as_u64())
+        .ok_or_else(|| anyhow::anyhow!("The type {} refers to no type id", field))
+}
+
+fn fields(item: &Value) -> &[Value] {
+    item.get("fields")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn variants(def: &Map<String, Value>) -> Option<&Vec<Value>> {
+    def.get("variant")
+        .and_then(|variant| variant.get("variants"))
+        .and_then(Value::as_array)
+}
+
+/// Returns the index of a variant, which older registries imply by its `position`.
+fn variant_index(variant: &Value, position: usize) -> u8 {
+    variant
+        .get("index")
+        .and_then(Value::as_u64)
+        .map_or(position as u8, |index| index as u8)
+}
+
+/// Returns the `key` entries of a metadata item, e.g. the `messages` of a contract spec.
+pub(super) fn items<'a>(item: &'a Value, key: &str) -> &'a [Value] {
+    item.get("spec")
+        .unwrap_or(item)
+        .get(key)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Returns the constructor or message `name` of the `kind`, i.e. `constructors` or `messages`,
+/// of a contract spec.
+pub(super) fn item<'a>(contract: &'a Value, kind: &str, name: &str) -> Result<&'a Value> {
+    items(contract, kind)
+        .iter()
+        .find(|item| label(item).as_deref() == Some(name))
+        .with_context(|| format!("The contract has no {} {}", &kind[..kind.len() - 1], name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn contract() -> Value {
+        json!({
+            "types": [
+                { "id": 0, "type": { "def": { "primitive": "u8" } } },
+                { "id": 1, "type": { "path": ["ink_env", "types", "AccountId"],
+                  "def": { "composite": { "fields": [{ "type": 5 }] } } } },
+                { "id": 2, "type": { "path": ["Option"],
+                  "params": [{ "name": "T", "type": 1 }],
+                  "def": { "variant": { "variants": [
+                      { "name": "None" }, { "name": "Some", "fields": [{ "type": 1 }] }
+                  ] } } } },
+                { "id": 3, "type": { "def": { "primitive": "u128" } } },
+                { "id": 4, "type": { "path": ["Result"],
+                  "params": [{ "name": "T", "type": 6 }, { "name": "E", "type": 7 }],
+                  "def": { "variant": { "variants": [] } } } },
+                { "id": 5, "type": { "def": { "array": { "len": 32, "type": 0 } } } },
+                { "id": 6, "type": { "def": { "tuple": [] } } },
+                { "id": 7, "type": { "path": ["my_token", "Error"],
+                  "def": { "variant": { "variants": [
+                      { "name": "InsufficientBalance" },
+                      { "name": "Custom", "fields": [{ "type": 8 }] }
+                  ] } } } },
+                { "id": 8, "type": { "path": ["my_token", "Details"],
+                  "def": { "composite": { "fields": [
+                      { "name": "error_code", "type": 0 },
+                      { "name": "payload", "type": 9 }
+                  ] } } } },
+                { "id": 9, "type": { "def": { "sequence": { "type": 0 } } } }
+            ]
+        })
+    }
+
+    #[test]
+    fn values_are_encoded_from_json() {
+        // given
+        let contract = contract();
+        let registry = Registry::new(&contract);
+        let codec = ScaleJson::new(&registry);
+        let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+        // when
+        let mut account = Vec::new();
+        codec.encode(2, &json!(alice), &mut account).unwrap();
+        let mut balance = Vec::new();
+        codec.encode(3, &json!("0x0100"), &mut balance).unwrap();
+        let mut error = Vec::new();
+        let value = json!({ "err": { "custom": { "errorCode": 7, "payload": "0x0102" } } });
+        codec.encode(4, &value, &mut error).unwrap();
+
+        // then
+        assert_eq!(account.len(), 33);
+        assert_eq!(account[..3], [0x01, 0xd4, 0x35]);
+        assert_eq!(balance, 256u128.encode());
+        assert_eq!(error, vec![0x01, 0x01, 0x07, 0x08, 0x01, 0x02]);
+        assert!(codec.encode(3, &json!(true), &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn values_are_decoded_to_json() {
+        // given
+        let contract = contract();
+        let registry = Registry::new(&contract);
+        let codec = ScaleJson::new(&registry);
+        let balance = u128::MAX.encode();
+
+        // when
+        let error = codec.decode(4, &mut &[0x01, 0x00][..]).unwrap();
+        let ok = codec.decode(4, &mut &[0x00][..]).unwrap();
+        let balance = codec.decode(3, &mut &balance[..]).unwrap();
+        let none = codec.decode(2, &mut &[0x00][..]).unwrap();
+
+        // then
+        assert_eq!(error, json!({ "err": { "insufficientBalance": null } }));
+        assert_eq!(ok, json!({ "ok": null }));
+        assert_eq!(balance, json!(u128::MAX.to_string()));
+        assert_eq!(none, Value::Null);
+        assert!(codec.decode(3, &mut &[0x01][..]).is_err());
+    }
+}
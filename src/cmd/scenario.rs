@@ -0,0 +1,900 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Scenario tests of a contract, executed against a development node.
+//!
+//! A scenario file describes sequences of steps taken by the development accounts:
+//!
+//! ```text
+//! scenario "transfer moves the balance"
+//!   alice instantiates new(1000)
+//!   alice calls transfer(bob, 100)
+//!   expect event Transfer { from: alice, to: bob, value: 100 }
+//!   expect query balance_of(bob) == 100
+//!   bob calls transfer(alice, 5000) fails
+//! ```
+//!
+//! Arguments are JSON values, the names of development accounts (`alice` to `ferdie`) or
+//! `contract`, the address of the contract instantiated by the scenario. `with <value>`
+//! transfers a value with an instantiation or call, `fails` expects a call to trap or revert
+//! instead of submitting it. Events are expected among the ones emitted by the preceding step,
+//! only the listed fields are compared. Queries are dry-run from `alice`.
+
+use super::{
+    build::{self, ExecuteArgs},
+    call::{dry_run, ContractExecResult, FLAG_REVERT},
+    gas_report::{call_fee, GasReport, GasReportOpts},
+    scale_json::{item, ScaleJson},
+    serve::contract_event,
+    trait_definition::{contract_of, Registry},
+    verify::is_code_stored,
+};
+use crate::{bundle::ContractBundle, rpc::RpcClient, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use contract_e2e::Node;
+use heck::CamelCase;
+use serde_json::Value;
+use sp_core::{
+    crypto::{AccountId32, Pair, Ss58Codec},
+    sr25519, H256,
+};
+use std::{
+    convert::TryFrom,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+use subxt::{
+    contracts::*, system::System, Client, ClientBuilder, DefaultNodeRuntime, ExtrinsicSuccess,
+    PairSigner,
+};
+use url::Url;
+
+/// The development accounts acting in scenarios.
+const DEV_ACCOUNTS: [&str; 6] = ["alice", "bob", "charlie", "dave", "eve", "ferdie"];
+
+/// The endowment of instantiated contracts, enough to exceed the subsistence threshold of dev
+/// chains.
+const ENDOWMENT: u128 = 1_000_000_000_000_000;
+
+/// Runs scenario tests of the contract against a development node.
+///
+/// Every scenario runs against its own ephemeral node, spawned from the binary in
+/// `CONTRACTS_NODE` or `substrate-contracts-node` on the `PATH`, unless a `--url` is supplied.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "scenario")]
+pub struct ScenarioCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// The scenario files to run
+    #[structopt(parse(from_os_str), required = true)]
+    files: Vec<PathBuf>,
+    /// Path to the `<name>.contract` bundle of the contract, which is built if not supplied
+    #[structopt(long, parse(from_os_str))]
+    bundle: Option<PathBuf>,
+    /// Websockets url of a running development node to use for all scenarios.
+    ///
+    /// Scenarios instantiating a contract with the same constructor call from the same account
+    /// share its address, which may fail on a shared node.
+    #[structopt(long)]
+    url: Option<Url>,
+    /// Maximum amount of gas to be used for instantiations and calls
+    #[structopt(name = "gas", long, default_value = "500000000")]
+    gas_limit: u64,
+    /// Write a JUnit XML report of the scenarios to this path
+    #[structopt(long, parse(from_os_str))]
+    junit: Option<PathBuf>,
+    #[structopt(flatten)]
+    gas_report: GasReportOpts,
+}
+
+impl ScenarioCommand {
+    pub fn exec(&self) -> Result<String> {
+        let files = self
+            .files
+            .iter()
+            .map(|path| {
+                let contents = fs::read_to_string(path)
+                    .context(format!("Failed to read the scenarios {}", path.display()))?;
+                let scenarios =
+                    parse(&contents).context(format!("Invalid scenarios {}", path.display()))?;
+                Ok((path.as_path(), scenarios))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let bundle = match &self.bundle {
+            Some(bundle) => ContractBundle::load(bundle)?,
+            None => {
+                let args = ExecuteArgs {
+                    manifest_path: ManifestPath::try_from(self.manifest_path.as_ref())?,
+                    ..Default::default()
+                };
+                let bundle = build::execute(args)?
+                    .metadata_result
+                    .expect("the bundle is built for all artifacts; qed")
+                    .dest_bundle;
+                ContractBundle::load(&bundle)?
+            }
+        };
+        let metadata = Value::Object(bundle.json().clone());
+        let spec = contract_of(&metadata);
+        let registry = Registry::new(spec);
+        let codec = ScaleJson::new(&registry);
+
+        let mut report = self.gas_report.report();
+        let mut suites = Vec::new();
+        for (path, scenarios) in files {
+            let mut results = Vec::new();
+            for scenario in scenarios {
+                let started = Instant::now();
+                let result = self.run(&bundle, spec, &codec, &scenario, report.as_mut());
+                match &result {
+                    Ok(()) => println!("scenario {} ... {}", scenario.name, "ok".green()),
+                    Err(err) => {
+                        println!("scenario {} ... {}", scenario.name, "FAILED".red());
+                        println!("\t{}: {:#}", path.display(), err);
+                    }
+                }
+                results.push(ScenarioResult {
+                    name: scenario.name,
+                    duration: started.elapsed(),
+                    failure: result.err().map(|err| format!("{:#}", err)),
+                });
+            }
+            suites.push((path, results));
+        }
+
+        if let Some(report) = &report {
+            self.gas_report.finish(report)?;
+        }
+        if let Some(junit) = &self.junit {
+            fs::write(junit, junit_report(&suites))
+                .context(format!("Failed to write {}", junit.display()))?;
+        }
+        let total = suites
+            .iter()
+            .map(|(_, results)| results.len())
+            .sum::<usize>();
+        let failed = suites
+            .iter()
+            .flat_map(|(_, results)| results)
+            .filter(|result| result.failure.is_some())
+            .count();
+        if failed > 0 {
+            anyhow::bail!("{} of {} scenarios failed", failed, total)
+        }
+        Ok(format!("All {} scenarios passed", total))
+    }
+
+    /// Runs the `scenario` against the node of the `--url` or an ephemeral one.
+    fn run(
+        &self,
+        bundle: &ContractBundle,
+        spec: &Value,
+        codec: &ScaleJson,
+        scenario: &Scenario,
+        gas_report: Option<&mut GasReport>,
+    ) -> Result<()> {
+        // the node is stopped when dropped after the scenario
+        let node;
+        let url = match &self.url {
+            Some(url) => url.clone(),
+            None => {
+                node = Node::spawn()?;
+                Url::parse(node.ws_url())?
+            }
+        };
+        let mut session = Session {
+            url,
+            bundle,
+            spec,
+            codec,
+            gas_limit: self.gas_limit,
+            contract: None,
+            events: Vec::new(),
+            gas_report,
+        };
+        for step in &scenario.steps {
+            session
+                .exec(&step.kind)
+                .context(format!("line {}", step.line))?;
+        }
+        Ok(())
+    }
+}
+
+/// A scenario of a scenario file.
+#[derive(Debug)]
+struct Scenario {
+    name: String,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug)]
+struct Step {
+    /// The line of the step in the scenario file.
+    line: usize,
+    kind: StepKind,
+}
+
+#[derive(Debug, PartialEq)]
+enum StepKind {
+    Instantiate {
+        actor: &'static str,
+        constructor: String,
+        args: Vec<Expr>,
+        value: u128,
+    },
+    Call {
+        actor: &'static str,
+        message: String,
+        args: Vec<Expr>,
+        value: u128,
+        fails: bool,
+    },
+    ExpectEvent {
+        name: String,
+        fields: Vec<(String, Expr)>,
+    },
+    ExpectQuery {
+        message: String,
+        args: Vec<Expr>,
+        expected: Expr,
+    },
+}
+
+/// An argument or expected value of a step.
+#[derive(Debug, PartialEq)]
+enum Expr {
+    Value(Value),
+    /// The address of a development account.
+    Account(&'static str),
+    /// The address of the contract instantiated by the scenario.
+    Contract,
+}
+
+/// The outcome of a scenario, as reported in the JUnit report.
+struct ScenarioResult {
+    name: String,
+    duration: Duration,
+    failure: Option<String>,
+}
+
+/// The state of a running scenario.
+struct Session<'a> {
+    url: Url,
+    bundle: &'a ContractBundle,
+    spec: &'a Value,
+    codec: &'a ScaleJson<'a>,
+    gas_limit: u64,
+    /// The contract instantiated by the scenario.
+    contract: Option<AccountId32>,
+    /// The name and fields of the events of the contract emitted by the preceding step.
+    events: Vec<(String, Value)>,
+    /// The costs of the calls of all scenarios, if reported.
+    gas_report: Option<&'a mut GasReport>,
+}
+
+impl<'a> Session<'a> {
+    fn exec(&mut self, step: &StepKind) -> Result<()> {
+        match step {
+            StepKind::Instantiate {
+                actor,
+                constructor,
+                args,
+                value,
+            } => {
+                let spec = item(self.spec, "constructors", constructor)?;
+                let data = self.codec.encode_message(spec, &self.resolve_all(args)?)?;
+                let code_hash = self.bundle.code_hash()?;
+                let code = if is_code_stored(&RpcClient::new(&self.url)?, &code_hash)? {
+                    None
+                } else {
+                    Some(self.bundle.wasm()?)
+                };
+                let signer = dev_signer(actor)?;
+                let result = async_std::task::block_on(async {
+                    let cli = self.client().await?;
+                    if let Some(code) = code {
+                        cli.put_code_and_watch(&signer, &code).await?;
+                    }
+                    let result = cli
+                        .instantiate_and_watch(
+                            &signer,
+                            ENDOWMENT + value,
+                            self.gas_limit,
+                            &H256(code_hash),
+                            &data,
+                        )
+                        .await?;
+                    Ok::<_, anyhow::Error>(result)
+                })?;
+                let instantiated = result
+                    .instantiated()?
+                    .ok_or_else(|| anyhow::anyhow!("Failed to find Instantiated event"))?;
+                self.contract = Some(instantiated.contract);
+                self.record_events(&result)
+            }
+            StepKind::Call {
+                actor,
+                message,
+                args,
+                value,
+                fails,
+            } => {
+                let spec = item(self.spec, "messages", message)?;
+                let data = self.codec.encode_message(spec, &self.resolve_all(args)?)?;
+                let contract = self.contract()?.clone();
+                self.record_costs(message, actor, *value, &data, !*fails)?;
+                if *fails {
+                    let origin = dev_account(actor)?;
+                    let result =
+                        dry_run(&self.url, &origin, &contract, *value, self.gas_limit, &data)?;
+                    return match result {
+                        ContractExecResult::Success { flags, .. } if flags & FLAG_REVERT == 0 => {
+                            anyhow::bail!(
+                                "Expected the call of {} to fail, but it succeeded",
+                                message
+                            )
+                        }
+                        _ => Ok(()),
+                    };
+                }
+                let dest: <DefaultNodeRuntime as System>::Address = contract.into();
+                let signer = dev_signer(actor)?;
+                let result = async_std::task::block_on(async {
+                    let cli = self.client().await?;
+                    let result = cli
+                        .call_and_watch(&signer, &dest, *value, self.gas_limit, &data)
+                        .await?;
+                    Ok::<_, anyhow::Error>(result)
+                })?;
+                self.record_events(&result)
+            }
+            StepKind::ExpectEvent { name, fields } => {
+                let expected = fields
+                    .iter()
+                    .map(|(field, expr)| Ok((field, self.resolve(expr)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                let found = self.events.iter().any(|(event, args)| {
+                    event == name
+                        && expected
+                            .iter()
+                            .all(|(field, value)| matches(value, &args[field.as_str()]))
+                });
+                if !found {
+                    let emitted = self
+                        .events
+                        .iter()
+                        .map(|(event, args)| format!("{} {}", event, args))
+                        .collect::<Vec<_>>();
+                    anyhow::bail!(
+                        "Expected the event {}, but the step emitted {}",
+                        name,
+                        if emitted.is_empty() {
+                            "none".into()
+                        } else {
+                            emitted.join(", ")
+                        }
+                    )
+                }
+                Ok(())
+            }
+            StepKind::ExpectQuery {
+                message,
+                args,
+                expected,
+            } => {
+                let spec = item(self.spec, "messages", message)?;
+                let data = self.codec.encode_message(spec, &self.resolve_all(args)?)?;
+                self.record_costs(message, DEV_ACCOUNTS[0], 0, &data, false)?;
+                let origin = dev_account(DEV_ACCOUNTS[0])?;
+                let result = dry_run(
+                    &self.url,
+                    &origin,
+                    self.contract()?,
+                    0,
+                    self.gas_limit,
+                    &data,
+                )?;
+                let output = match result {
+                    ContractExecResult::Success { flags, .. } if flags & FLAG_REVERT != 0 => {
+                        anyhow::bail!("The query of {} reverted", message)
+                    }
+                    ContractExecResult::Success { data, .. } => {
+                        hex::decode(data.trim_start_matches("0x"))?
+                    }
+                    ContractExecResult::Error(()) => {
+                        anyhow::bail!("The query of {} trapped", message)
+                    }
+                };
+                let actual = self.codec.decode_output(spec, &output)?;
+                let expected = self.resolve(expected)?;
+                if !matches(&expected, &actual) {
+                    anyhow::bail!(
+                        "Expected {} to return {}, but it returned {}",
+                        message,
+                        expected,
+                        actual
+                    )
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Records the costs of the call of the `message` by the `actor` in the gas report, with the
+    /// fee of the extrinsic if the call is `submitted`.
+    fn record_costs(
+        &mut self,
+        message: &str,
+        actor: &str,
+        value: u128,
+        data: &[u8],
+        submitted: bool,
+    ) -> Result<()> {
+        let contract = self.contract()?.clone();
+        let report = match &mut self.gas_report {
+            Some(report) => report,
+            None => return Ok(()),
+        };
+        let fee = if submitted {
+            let signer = dev_signer(actor)?;
+            Some(call_fee(
+                &self.url,
+                &signer,
+                &contract,
+                value,
+                self.gas_limit,
+                data,
+            )?)
+        } else {
+            None
+        };
+        report.record(
+            &self.url,
+            message,
+            &dev_account(actor)?,
+            &contract,
+            value,
+            self.gas_limit,
+            data,
+            fee,
+        )
+    }
+
+    async fn client(&self) -> Result<Client<DefaultNodeRuntime>> {
+        Ok(ClientBuilder::<DefaultNodeRuntime>::new()
+            .set_url(&self.url.to_string())
+            .build()
+            .await?)
+    }
+
+    /// Keeps the events emitted by the contract with the `result` of a step.
+    fn record_events(&mut self, result: &ExtrinsicSuccess<DefaultNodeRuntime>) -> Result<()> {
+        let contract = self.contract()?.clone();
+        self.events.clear();
+        for event in &result.events {
+            if let Some((emitter, data)) = contract_event(event)? {
+                if emitter == contract {
+                    self.events.push(self.codec.decode_event(self.spec, &data)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn contract(&self) -> Result<&AccountId32> {
+        self.contract
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("The scenario instantiated no contract yet"))
+    }
+
+    fn resolve(&self, expr: &Expr) -> Result<Value> {
+        Ok(match expr {
+            Expr::Value(value) => value.clone(),
+            Expr::Account(name) => dev_account(name)?.to_ss58check().into(),
+            Expr::Contract => self.contract()?.to_ss58check().into(),
+        })
+    }
+
+    fn resolve_all(&self, exprs: &[Expr]) -> Result<Vec<Value>> {
+        exprs.iter().map(|expr| self.resolve(expr)).collect()
+    }
+}
+
+/// Returns `true` if the decoded `actual` value is the `expected` one.
+///
+/// Large integers are decoded as strings, hence they also match expected numbers.
+fn matches(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Number(expected), Value::String(actual)) => &expected.to_string() == actual,
+        _ => expected == actual,
+    }
+}
+
+/// Returns the key pair of the development account `name`, e.g. `//Alice` for `alice`.
+fn dev_pair(name: &str) -> Result<sr25519::Pair> {
+    sr25519::Pair::from_string(&format!("//{}", name.to_camel_case()), None)
+        .map_err(|_| anyhow::anyhow!("Invalid development account {}", name))
+}
+
+fn dev_signer(name: &str) -> Result<PairSigner<DefaultNodeRuntime, sr25519::Pair>> {
+    Ok(PairSigner::new(dev_pair(name)?))
+}
+
+fn dev_account(name: &str) -> Result<AccountId32> {
+    Ok(AccountId32::from(dev_pair(name)?.public().0))
+}
+
+/// Parses the scenarios of a scenario file.
+fn parse(contents: &str) -> Result<Vec<Scenario>> {
+    let mut scenarios: Vec<Scenario> = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("scenario ") {
+            scenarios.push(Scenario {
+                name: name.trim().trim_matches('"').to_string(),
+                steps: Vec::new(),
+            });
+            continue;
+        }
+        let scenario = scenarios.last_mut().ok_or_else(|| {
+            anyhow::anyhow!(
+                "line {}: steps must follow a `scenario \"name\"`",
+                index + 1
+            )
+        })?;
+        scenario.steps.push(Step {
+            line: index + 1,
+            kind: parse_step(line).context(format!("line {}", index + 1))?,
+        });
+    }
+    Ok(scenarios)
+}
+
+fn parse_step(line: &str) -> Result<StepKind> {
+    if let Some(rest) = line.strip_prefix("expect event ") {
+        let (name, fields) = match rest.find('{') {
+            Some(open) => (&rest[..open], parse_fields(&rest[open..])?),
+            None => (rest, Vec::new()),
+        };
+        return Ok(StepKind::ExpectEvent {
+            name: name.trim().into(),
+            fields,
+        });
+    }
+    if let Some(rest) = line.strip_prefix("expect query ") {
+        let (message, args, rest) = parse_invocation(rest)?;
+        let expected = rest
+            .strip_prefix("==")
+            .ok_or_else(|| anyhow::anyhow!("Expected `== <value>` after the query"))?;
+        return Ok(StepKind::ExpectQuery {
+            message,
+            args,
+            expected: parse_expr(expected.trim())?,
+        });
+    }
+
+    let mut words = line.splitn(3, ' ');
+    let (actor, verb, rest) = match (words.next(), words.next(), words.next()) {
+        (Some(actor), Some(verb), Some(rest)) => (actor, verb, rest),
+        _ => anyhow::bail!("Unknown step `{}`", line),
+    };
+    let actor = DEV_ACCOUNTS
+        .iter()
+        .find(|account| **account == actor)
+        .copied()
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown account {}, scenarios are run by {}",
+                actor,
+                DEV_ACCOUNTS.join(", ")
+            )
+        })?;
+    let (name, args, rest) = parse_invocation(rest)?;
+    let (rest, fails) = match rest.strip_suffix("fails") {
+        Some(rest) => (rest.trim(), true),
+        None => (rest, false),
+    };
+    let value = match rest.strip_prefix("with ") {
+        Some(value) => value
+            .trim()
+            .parse()
+            .context(format!("Invalid value {}", value))?,
+        None if rest.is_empty() => 0,
+        None => anyhow::bail!("Unexpected `{}`", rest),
+    };
+    match verb {
+        "instantiates" if !fails => Ok(StepKind::Instantiate {
+            actor,
+            constructor: name,
+            args,
+            value,
+        }),
+        "calls" => Ok(StepKind::Call {
+            actor,
+            message: name,
+            args,
+            value,
+            fails,
+        }),
+        _ => anyhow::bail!("Unknown step `{}`", line),
+    }
+}
+
+/// Parses `name(args..)` at the start of `input` and returns the rest of it.
+fn parse_invocation(input: &str) -> Result<(String, Vec<Expr>, &str)> {
+    let open = input
+        .find('(')
+        .ok_or_else(|| anyhow::anyhow!("Expected `name(args..)` in `{}`", input))?;
+    let close =
+        closing(input, open).ok_or_else(|| anyhow::anyhow!("Unclosed `(` in `{}`", input))?;
+    let args = split_top_level(&input[open + 1..close])
+        .into_iter()
+        .map(parse_expr)
+        .collect::<Result<_>>()?;
+    Ok((input[..open].trim().into(), args, input[close + 1..].trim()))
+}
+
+/// Parses the fields `{ name: value, .. }` of an expected event.
+fn parse_fields(input: &str) -> Result<Vec<(String, Expr)>> {
+    let fields = input
+        .trim()
+        .strip_prefix('{')
+        .and_then(|fields| fields.strip_suffix('}'))
+        .ok_or_else(|| anyhow::anyhow!("Expected `{{ name: value, .. }}` in `{}`", input))?;
+    split_top_level(fields)
+        .into_iter()
+        .map(|field| {
+            let colon = field
+                .find(':')
+                .ok_or_else(|| anyhow::anyhow!("Expected `name: value` in `{}`", field))?;
+            Ok((
+                field[..colon].trim().into(),
+                parse_expr(field[colon + 1..].trim())?,
+            ))
+        })
+        .collect()
+}
+
+fn parse_expr(input: &str) -> Result<Expr> {
+    if input == "contract" {
+        return Ok(Expr::Contract);
+    }
+    if let Some(account) = DEV_ACCOUNTS.iter().find(|account| **account == input) {
+        return Ok(Expr::Account(*account));
+    }
+    serde_json::from_str(input)
+        .map(Expr::Value)
+        .context(format!("Invalid value `{}`", input))
+}
+
+/// Returns the index of the bracket closing the one at `open`.
+pub(super) fn closing(input: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in input.char_indices().skip_while(|(index, _)| *index < open) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '(' | '[' | '{' if !in_string => depth += 1,
+            ')' | ']' | '}' if !in_string => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Splits the `input` at the commas outside of brackets and strings.
+pub(super) fn split_top_level(input: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    let (mut in_string, mut escaped) = (false, false);
+    for (index, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '(' | '[' | '{' if !in_string => depth += 1,
+            ')' | ']' | '}' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                parts.push(input[start..index].trim());
+                start = index + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(input[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Returns the JUnit XML report of the scenario results of the files.
+fn junit_report(suites: &[(&Path, Vec<ScenarioResult>)]) -> String {
+    let mut report = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    for (path, results) in suites {
+        let failures = results
+            .iter()
+            .filter(|result| result.failure.is_some())
+            .count();
+        let duration = results
+            .iter()
+            .map(|result| result.duration)
+            .sum::<Duration>();
+        let _ = writeln!(
+            report,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">",
+            xml_escape(&path.display().to_string()),
+            results.len(),
+            failures,
+            duration.as_secs_f64()
+        );
+        for result in results {
+            let _ = write!(
+                report,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                xml_escape(&result.name),
+                xml_escape(&path.display().to_string()),
+                result.duration.as_secs_f64()
+            );
+            match &result.failure {
+                Some(failure) => {
+                    let _ = writeln!(
+                        report,
+                        ">\n      <failure message=\"{}\"/>\n    </testcase>",
+                        xml_escape(failure)
+                    );
+                }
+                None => report.push_str("/>\n"),
+            }
+        }
+        report.push_str("  </testsuite>\n");
+    }
+    report.push_str("</testsuites>\n");
+    report
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn scenarios_are_parsed() {
+        // given
+        let contents = r#"
+# transfers of the token
+scenario "transfer moves the balance"
+  alice instantiates new(1000)
+  alice calls transfer(bob, 100) with 5
+  expect event Transfer { from: alice, to: bob, value: 100, data: [1, 2] }
+  expect query balance_of(bob) == 100
+  bob calls transfer(contract, "a, b") fails
+"#;
+
+        // when
+        let scenarios = parse(contents).expect("the scenarios are valid");
+
+        // then
+        assert_eq!(scenarios.len(), 1);
+        assert_eq!(scenarios[0].name, "transfer moves the balance");
+        let steps = scenarios[0]
+            .steps
+            .iter()
+            .map(|step| &step.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps[1],
+            &StepKind::Call {
+                actor: "alice",
+                message: "transfer".into(),
+                args: vec![Expr::Account("bob"), Expr::Value(json!(100))],
+                value: 5,
+                fails: false,
+            }
+        );
+        assert_eq!(
+            steps[2],
+            &StepKind::ExpectEvent {
+                name: "Transfer".into(),
+                fields: vec![
+                    ("from".into(), Expr::Account("alice")),
+                    ("to".into(), Expr::Account("bob")),
+                    ("value".into(), Expr::Value(json!(100))),
+                    ("data".into(), Expr::Value(json!([1, 2]))),
+                ],
+            }
+        );
+        assert_eq!(
+            steps[3],
+            &StepKind::ExpectQuery {
+                message: "balance_of".into(),
+                args: vec![Expr::Account("bob")],
+                expected: Expr::Value(json!(100)),
+            }
+        );
+        assert_eq!(
+            steps[4],
+            &StepKind::Call {
+                actor: "bob",
+                message: "transfer".into(),
+                args: vec![Expr::Contract, Expr::Value(json!("a, b"))],
+                value: 0,
+                fails: true,
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_steps_are_reported_with_their_line() {
+        let err = parse("scenario \"unknown\"\n  mallory calls transfer(bob, 1)\n")
+            .expect_err("mallory is no development account");
+        assert_eq!(
+            format!("{:#}", err),
+            "line 2: Unknown account mallory, scenarios are run by alice, bob, charlie, dave, eve, \
+            ferdie"
+        );
+    }
+
+    #[test]
+    fn junit_report_lists_failures() {
+        // given
+        let results = vec![
+            ScenarioResult {
+                name: "transfer".into(),
+                duration: Duration::from_millis(1500),
+                failure: None,
+            },
+            ScenarioResult {
+                name: "approve <all>".into(),
+                duration: Duration::from_millis(500),
+                failure: Some("line 3: Expected \"x\"".into()),
+            },
+        ];
+
+        // when
+        let report = junit_report(&[(Path::new("erc20.scenario"), results)]);
+
+        // then
+        assert!(report
+            .contains(r#"<testsuite name="erc20.scenario" tests="2" failures="1" time="2.000">"#));
+        assert!(report
+            .contains(r#"<testcase name="transfer" classname="erc20.scenario" time="1.500"/>"#));
+        assert!(report.contains(r#"<failure message="line 3: Expected &quot;x&quot;"/>"#));
+        assert!(report.contains(r#"<testcase name="approve &lt;all&gt;""#));
+    }
+}
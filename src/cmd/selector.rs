@@ -0,0 +1,72 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::metadata::blake2_hash;
+use anyhow::Result;
+use impl_serde::serialize as serde_hex;
+use structopt::StructOpt;
+
+/// Computes the selector of a message or constructor, i.e. the first 4 bytes of the BLAKE2
+/// 256-bit hash of its label.
+///
+/// Messages of trait implementations are identified by the label prefixed with the trait:
+/// `<trait>::<label>`.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "selector")]
+pub struct SelectorCommand {
+    /// The label of the message or constructor, e.g. `flip`
+    label: String,
+    /// The trait defining the message, prefixed with its namespace if it has one
+    #[structopt(long = "trait", value_name = "trait")]
+    trait_name: Option<String>,
+}
+
+impl SelectorCommand {
+    pub fn exec(&self) -> Result<String> {
+        if self.label.is_empty() {
+            anyhow::bail!("The label must not be empty")
+        }
+        let selector = selector(self.trait_name.as_deref(), &self.label);
+        Ok(serde_hex::to_hex(&selector, false))
+    }
+}
+
+/// Returns the selector of the message or constructor `label`, optionally defined by a trait.
+pub(super) fn selector(trait_name: Option<&str>, label: &str) -> [u8; 4] {
+    let input = match trait_name {
+        Some(trait_name) => format!("{}::{}", trait_name, label),
+        None => label.to_string(),
+    };
+    let hash = blake2_hash(input.as_bytes()).0;
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selectors_of_inherent_messages() {
+        assert_eq!(selector(None, "new"), [0x9b, 0xae, 0x9d, 0x5e]);
+        assert_eq!(selector(None, "flip"), [0x63, 0x3a, 0xa5, 0x51]);
+    }
+
+    #[test]
+    fn selectors_of_trait_messages() {
+        let cmd = SelectorCommand::from_iter(&["selector", "--trait", "Erc20", "transfer"]);
+        assert_eq!(cmd.exec().unwrap(), "0x46607e68");
+    }
+}
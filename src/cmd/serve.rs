@@ -0,0 +1,556 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A local web dashboard of the contracts of the project, served by `cargo contract serve`.
+//!
+//! The page at `/` is backed by a JSON API:
+//!
+//! - `GET /api/project` lists the contracts of the workspace with their deployments per network
+//! - `GET /api/contracts/<address>` returns the messages and events of a deployed contract,
+//!   resolved from its metadata like `cargo contract call --message` does
+//! - `POST /api/contracts/<address>/dry-run` dry-runs a message of the contract with
+//!   `{ "message": "<label>", "args": [..], "origin": "<ss58>", "value": "0" }`, where the
+//!   arguments are in the JSON representation of `cargo contract metadata schema`, and returns
+//!   the decoded result
+//! - `GET /api/events?after=<id>` returns the decoded contract events emitted after the event `id`
+
+use super::{
+    call::{dry_run, ContractExecResult, FLAG_REVERT},
+    scale_json::{items, ScaleJson},
+    trait_definition::{contract_of, docs, Registry},
+    verify::contract_code_hash,
+    verify_metadata::label,
+};
+use crate::{
+    crate_metadata,
+    deployments::{Deployments, MetadataSources},
+    rpc::RpcClient,
+    workspace::ManifestPath,
+    ExtrinsicOpts, OriginOpts,
+};
+use anyhow::{Context, Result};
+use codec::Decode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io::Read,
+    sync::{Arc, Mutex},
+    thread,
+};
+use structopt::StructOpt;
+use subxt::{ClientBuilder, DefaultNodeRuntime, EventSubscription, RawEvent};
+use url::Url;
+
+/// The page of the dashboard.
+const INDEX_HTML: &str = include_str!("serve.html");
+
+/// The number of recent events the dashboard keeps.
+const MAX_EVENTS: usize = 1000;
+
+/// The gas limit of dry-runs if none is supplied.
+const DEFAULT_GAS_LIMIT: u64 = 500_000_000;
+
+/// Starts a local web dashboard listing the contracts of the project and their deployments per
+/// network, dry-running messages with forms generated from the metadata and showing the decoded
+/// events of the contracts live.
+///
+/// Dry-runs and events use the node of `--url` or the `--network` profile.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "serve")]
+pub struct ServeCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    #[structopt(flatten)]
+    origin_opts: OriginOpts,
+    /// The port to serve the dashboard on, bound to localhost only
+    #[structopt(long, default_value = "8000")]
+    port: u16,
+}
+
+impl ServeCommand {
+    pub fn exec(&self) -> Result<Option<String>> {
+        let network = self.extrinsic_opts.network()?.unwrap_or_default();
+        let dashboard = Arc::new(Dashboard {
+            manifest_path: self.extrinsic_opts.manifest_path()?,
+            url: self.extrinsic_opts.url()?,
+            registry: network.registry,
+            ipfs_gateway: network.ipfs_gateway,
+            origin: self.origin_opts.account().cloned(),
+            metadata: Default::default(),
+            events: Default::default(),
+        });
+        let server = tiny_http::Server::http(("127.0.0.1", self.port)).map_err(|err| {
+            anyhow::anyhow!(
+                "Failed to serve the dashboard on port {}: {}",
+                self.port,
+                err
+            )
+        })?;
+
+        let watcher = dashboard.clone();
+        thread::spawn(move || {
+            let watched = watch_contract_events(&watcher.url, |emitter, data| {
+                watcher.record_event(&emitter.to_ss58check(), &data)
+            });
+            if let Err(err) = watched {
+                log::warn!("Watching the events of {} failed: {:#}", watcher.url, err);
+            }
+        });
+        println!(
+            "Serving the dashboard of {} at http://127.0.0.1:{}",
+            dashboard.url, self.port
+        );
+
+        for mut request in server.incoming_requests() {
+            let mut body = Vec::new();
+            let response = request
+                .as_reader()
+                .read_to_end(&mut body)
+                .map_err(Into::into)
+                .and_then(|_| dashboard.route(request.method().as_str(), request.url(), &body));
+            let (status, content_type, body): (u16, _, _) = match response {
+                Ok(Some((content_type, body))) => (200, content_type, body),
+                Ok(None) => (
+                    404,
+                    "application/json",
+                    json!({ "error": "Not found" }).to_string(),
+                ),
+                Err(err) => (
+                    400,
+                    "application/json",
+                    json!({ "error": format!("{:#}", err) }).to_string(),
+                ),
+            };
+            let header = tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type)
+                .expect("the content types are valid header values");
+            let response = tiny_http::Response::from_string(body)
+                .with_status_code(status)
+                .with_header(header);
+            if let Err(err) = request.respond(response) {
+                log::warn!("Failed to respond to a request: {}", err);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The state of the dashboard, shared by the server and the watcher of the events.
+struct Dashboard {
+    manifest_path: ManifestPath,
+    /// The node to dry-run the messages with and to watch the events of.
+    url: Url,
+    registry: Option<Url>,
+    ipfs_gateway: Option<Url>,
+    /// The origin of dry-runs which don't supply one.
+    origin: Option<AccountId32>,
+    /// The metadata of the contracts by their address.
+    metadata: Mutex<HashMap<String, Arc<Value>>>,
+    events: Mutex<Events>,
+}
+
+/// The recent events of the contracts.
+#[derive(Default)]
+struct Events {
+    next_id: u64,
+    events: VecDeque<Value>,
+}
+
+/// A dry-run of a message requested by the dashboard.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DryRunRequest {
+    message: String,
+    #[serde(default)]
+    args: Vec<Value>,
+    origin: Option<String>,
+    /// The value transferred with the call as decimal string, `0` if empty.
+    #[serde(default)]
+    value: String,
+    gas_limit: Option<u64>,
+}
+
+impl Dashboard {
+    /// Returns the content type and body of the response to the request of `url`, or `None` if
+    /// there is nothing at the `url`.
+    fn route(
+        &self,
+        method: &str,
+        url: &str,
+        body: &[u8],
+    ) -> Result<Option<(&'static str, String)>> {
+        let (path, query) = match url.find('?') {
+            Some(index) => (&url[..index], &url[index + 1..]),
+            None => (url, ""),
+        };
+        let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+        let response = match (method, segments.as_slice()) {
+            ("GET", [""]) => return Ok(Some(("text/html; charset=utf-8", INDEX_HTML.into()))),
+            ("GET", ["api", "project"]) => self.project()?,
+            ("GET", ["api", "events"]) => {
+                let after = query
+                    .split('&')
+                    .find_map(|param| param.strip_prefix("after="))
+                    .map(str::parse)
+                    .transpose()
+                    .context("Invalid event id")?;
+                self.recent_events(after)
+            }
+            ("GET", ["api", "contracts", address]) => contract(&*self.metadata(address)?)?,
+            ("POST", ["api", "contracts", address, "dry-run"]) => {
+                let request = serde_json::from_slice(body).context("Invalid dry-run request")?;
+                self.dry_run(address, &request)?
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(("application/json", response.to_string())))
+    }
+
+    /// Returns the contracts of the workspace with their deployments grouped by network.
+    fn project(&self) -> Result<Value> {
+        let contracts = crate_metadata::workspace_contracts(&self.manifest_path)?
+            .into_iter()
+            .map(|(name, manifest_path)| {
+                let mut networks = BTreeMap::<String, Vec<Value>>::new();
+                for deployment in Deployments::load(&manifest_path)?.deployments {
+                    let network = deployment
+                        .network
+                        .clone()
+                        .unwrap_or_else(|| deployment.url.to_string());
+                    networks.entry(network).or_default().push(json!({
+                        "name": deployment.name,
+                        "contract": deployment.contract,
+                        "codeHash": deployment.code_hash,
+                        "url": deployment.url,
+                    }));
+                }
+                Ok(json!({
+                    "name": name,
+                    "manifestPath": manifest_path.as_ref(),
+                    "networks": networks,
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(json!({ "node": self.url, "contracts": contracts }))
+    }
+
+    /// Returns the metadata of the contract at `address`, resolved by its code hash on chain.
+    fn metadata(&self, address: &str) -> Result<Arc<Value>> {
+        if let Some(metadata) = self.metadata.lock().expect("poisoned lock").get(address) {
+            return Ok(metadata.clone());
+        }
+        let account = AccountId32::from_ss58check(address)
+            .map_err(|err| anyhow::anyhow!("Invalid address {}: {:?}", address, err))?;
+        let code_hash = contract_code_hash(&RpcClient::new(&self.url)?, &account)?;
+        let sources = MetadataSources {
+            manifest_path: &self.manifest_path,
+            registry: self.registry.as_ref(),
+            ipfs_gateway: self.ipfs_gateway.as_ref(),
+        };
+        let metadata = Arc::new(Value::Object(
+            sources.resolve(address, &code_hash)?.json().clone(),
+        ));
+        self.metadata
+            .lock()
+            .expect("poisoned lock")
+            .insert(address.to_string(), metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Dry-runs the message of the `request` via the `contracts_call` RPC.
+    fn dry_run(&self, address: &str, request: &DryRunRequest) -> Result<Value> {
+        let metadata = self.metadata(address)?;
+        let contract = contract_of(&metadata);
+        let registry = Registry::new(contract);
+        let codec = ScaleJson::new(&registry);
+        let message = items(contract, "messages")
+            .iter()
+            .find(|message| label(message).as_deref() == Some(request.message.as_str()))
+            .ok_or_else(|| anyhow::anyhow!("The contract has no message {}", request.message))?;
+        let data = codec.encode_message(message, &request.args)?;
+
+        let origin = match &request.origin {
+            Some(origin) if !origin.is_empty() => AccountId32::from_ss58check(origin)
+                .map_err(|err| anyhow::anyhow!("Invalid origin {}: {:?}", origin, err))?,
+            _ => self.origin.clone().ok_or_else(|| {
+                anyhow::anyhow!("No origin supplied, enter one or start with `--origin`")
+            })?,
+        };
+        let value = match request.value.trim() {
+            "" => 0,
+            value => value.parse::<u128>().context("Invalid value")?,
+        };
+        let dest = AccountId32::from_ss58check(address)
+            .map_err(|err| anyhow::anyhow!("Invalid address {}: {:?}", address, err))?;
+        let gas_limit = request.gas_limit.unwrap_or(DEFAULT_GAS_LIMIT);
+        let result = dry_run(&self.url, &origin, &dest, value, gas_limit, &data)?;
+
+        match result {
+            ContractExecResult::Success {
+                flags,
+                data,
+                gas_consumed,
+            } => {
+                let bytes = hex::decode(data.trim_start_matches("0x"))?;
+                let output = codec.decode_output(message, &bytes)?;
+                Ok(json!({
+                    "success": true,
+                    "reverted": flags & FLAG_REVERT == FLAG_REVERT,
+                    "gasConsumed": gas_consumed,
+                    "data": data,
+                    "output": output,
+                }))
+            }
+            ContractExecResult::Error(()) => Ok(json!({ "success": false })),
+        }
+    }
+
+    /// Returns the recent events emitted after the event `after`, and the id of the last event.
+    fn recent_events(&self, after: Option<u64>) -> Value {
+        let events = self.events.lock().expect("poisoned lock");
+        let recent = events
+            .events
+            .iter()
+            .filter(|event| after.map_or(true, |after| event["id"].as_u64() > Some(after)))
+            .cloned()
+            .collect::<Vec<_>>();
+        json!({ "events": recent, "last": events.next_id.checked_sub(1) })
+    }
+
+    /// Records the decoded event `data` emitted by the contract at `address`.
+    ///
+    /// Events of contracts whose metadata can't be resolved are recorded with their raw data.
+    fn record_event(&self, address: &str, data: &[u8]) {
+        let decoded = self
+            .metadata(address)
+            .and_then(|metadata| decode_event(&metadata, data));
+        let mut events = self.events.lock().expect("poisoned lock");
+        let mut event = json!({ "id": events.next_id, "contract": address });
+        match decoded {
+            Ok((name, args)) => {
+                event["name"] = name.into();
+                event["args"] = args;
+            }
+            Err(err) => {
+                event["data"] = format!("0x{}", hex::encode(data)).into();
+                event["error"] = format!("{:#}", err).into();
+            }
+        }
+        events.next_id += 1;
+        events.events.push_back(event);
+        if events.events.len() > MAX_EVENTS {
+            events.events.pop_front();
+        }
+    }
+}
+
+/// Calls `on_event` with the emitter and the data of the events of contracts on the node at `url`
+/// until the connection is closed.
+pub(super) fn watch_contract_events(
+    url: &Url,
+    mut on_event: impl FnMut(AccountId32, Vec<u8>),
+) -> Result<()> {
+    async_std::task::block_on(async move {
+        let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+            .set_url(url.as_str())
+            .build()
+            .await?;
+        let subscription = cli.subscribe_events().await?;
+        let mut events = EventSubscription::<DefaultNodeRuntime>::new(
+            subscription,
+            cli.events_decoder().clone(),
+        );
+        while let Some(event) = events.next().await {
+            if let Some((emitter, data)) = contract_event(&event?)? {
+                on_event(emitter, data);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Returns the emitter and the data of the `event` if it was emitted by a contract.
+pub(super) fn contract_event(event: &RawEvent) -> Result<Option<(AccountId32, Vec<u8>)>> {
+    let is_contract_event = event.module == "Contracts"
+        && (event.variant == "ContractExecution" || event.variant == "ContractEmitted");
+    if !is_contract_event {
+        return Ok(None);
+    }
+    Ok(Some(Decode::decode(&mut &event.data[..])?))
+}
+
+/// Returns the messages and events of the contract `metadata`, with the Rust types of their
+/// arguments as hints for the forms of the dashboard.
+fn contract(metadata: &Value) -> Result<Value> {
+    let contract = contract_of(metadata);
+    let registry = Registry::new(contract);
+    let mut definitions = BTreeMap::new();
+    let mut args = |item: &Value| {
+        items(item, "args")
+            .iter()
+            .map(|arg| {
+                Ok(json!({
+                    "name": label(arg),
+                    "type": registry.type_spec(&arg["type"], &mut definitions)?,
+                }))
+            })
+            .collect::<Result<Vec<_>>>()
+    };
+    let messages = items(contract, "messages")
+        .iter()
+        .map(|message| {
+            Ok(json!({
+                "name": label(message),
+                "selector": message.get("selector"),
+                "mutates": message.get("mutates"),
+                "payable": message.get("payable"),
+                "docs": docs(message),
+                "args": args(message)?,
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let events = items(contract, "events")
+        .iter()
+        .map(|event| {
+            Ok(json!({
+                "name": label(event),
+                "docs": docs(event),
+                "args": args(event)?,
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(json!({
+        "name": metadata.pointer("/contract/name"),
+        "messages": messages,
+        "events": events,
+    }))
+}
+
+/// Returns the name and the arguments of the event `data` of the contract `metadata`.
+fn decode_event(metadata: &Value, data: &[u8]) -> Result<(String, Value)> {
+    let contract = contract_of(metadata);
+    let registry = Registry::new(contract);
+    ScaleJson::new(&registry).decode_event(contract, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    fn metadata() -> Value {
+        json!({
+            "contract": { "name": "flipper" },
+            "V1": {
+                "spec": {
+                    "events": [{
+                        "label": "Flipped",
+                        "args": [{ "label": "value", "type": { "type": 1 }, "indexed": false }],
+                        "docs": []
+                    }],
+                    "messages": [{
+                        "label": "flip", "selector": "0x633aa551",
+                        "mutates": true, "payable": false,
+                        "args": [{
+                            "label": "times", "type": { "type": 2, "displayName": ["u8"] }
+                        }],
+                        "returnType": null,
+                        "docs": [" Flips the value."]
+                    }]
+                },
+                "types": [
+                    { "id": 1, "type": { "def": { "primitive": "bool" } } },
+                    { "id": 2, "type": { "def": { "primitive": "u8" } } }
+                ]
+            }
+        })
+    }
+
+    fn dashboard(manifest_path: ManifestPath) -> Dashboard {
+        Dashboard {
+            manifest_path,
+            url: Url::parse("ws://localhost:9944").unwrap(),
+            registry: None,
+            ipfs_gateway: None,
+            origin: None,
+            metadata: Default::default(),
+            events: Default::default(),
+        }
+    }
+
+    #[test]
+    fn messages_and_events_are_listed() {
+        let contract = contract(&metadata()).unwrap();
+        assert_eq!(
+            contract["messages"],
+            json!([{
+                "name": "flip", "selector": "0x633aa551", "mutates": true, "payable": false,
+                "docs": "Flips the value.", "args": [{ "name": "times", "type": "u8" }]
+            }])
+        );
+        assert_eq!(contract["events"][0]["args"][0]["type"], "bool");
+    }
+
+    #[test]
+    fn events_are_decoded_and_listed_after_their_id() {
+        with_tmp_dir(|path| {
+            // given
+            let dashboard = dashboard(ManifestPath::new(&path.join("Cargo.toml"))?);
+            let address = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+            dashboard
+                .metadata
+                .lock()
+                .unwrap()
+                .insert(address.to_string(), Arc::new(metadata()));
+
+            // when
+            dashboard.record_event(address, &[0x00, 0x01]);
+            dashboard.record_event(address, &[0x07]);
+
+            // then
+            let events = dashboard.recent_events(Some(0));
+            assert_eq!(events["last"], 1);
+            assert_eq!(events["events"].as_array().unwrap().len(), 1);
+            assert_eq!(events["events"][0]["data"], "0x07");
+            assert_eq!(
+                dashboard.recent_events(None)["events"][0],
+                json!({
+                    "id": 0, "contract": address, "name": "Flipped", "args": { "value": true }
+                })
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn page_and_api_are_routed() {
+        with_tmp_dir(|path| {
+            let dashboard = dashboard(ManifestPath::new(&path.join("Cargo.toml"))?);
+
+            let (content_type, page) = dashboard.route("GET", "/", &[])?.unwrap();
+            assert_eq!(content_type, "text/html; charset=utf-8");
+            assert!(page.contains("<title>cargo contract</title>"));
+
+            let (_, events) = dashboard.route("GET", "/api/events?after=3", &[])?.unwrap();
+            assert_eq!(events, r#"{"events":[],"last":null}"#);
+
+            assert!(dashboard.route("GET", "/api/unknown", &[])?.is_none());
+            assert!(dashboard.route("GET", "/api/events?after=x", &[]).is_err());
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,534 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Export of the ABI of a contract in the JSON format of Solidity ABIs.
+//!
+//! The types of ink! map to Solidity types where they have an equivalent:
+//!
+//! - integers to `uintN` and `intN`, `char` to `uint32`
+//! - strings to `string`, byte sequences to `bytes`, byte arrays of up to 32 bytes to `bytesN`
+//! - account ids and hashes to `bytes32`, as they are 32 bytes instead of 20 byte addresses
+//! - structs and tuples to `tuple`, enums without fields to `uint8`
+//! - the `Ok` value of a returned `Result`, as errors revert the call in Solidity
+//!
+//! Messages, constructors and events referring to other types, e.g. `Option`, are skipped. The
+//! selector of each constructor and message is kept, since EVM tools derive different ones.
+
+use super::{
+    trait_definition::{contract_of, Registry},
+    verify_metadata::label,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde_json::{json, Map, Value};
+use std::{fs, path::PathBuf};
+use structopt::StructOpt;
+
+/// Exports the messages, constructors and events of the contract as Solidity ABI, for EVM tools
+/// like ABI explorers and The Graph mappings on chains supporting both.
+///
+/// Accepts both the `metadata.json` and the `<name>.contract` bundle.
+#[derive(Debug, StructOpt)]
+pub struct SolidityAbiCommand {
+    /// Write the ABI to this file instead of printing it
+    #[structopt(long, short, parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Path to the metadata
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+}
+
+impl SolidityAbiCommand {
+    /// Exports the ABI to the `--output`, or prints it if none is supplied.
+    pub fn exec(&self) -> Result<Option<String>> {
+        let contents = fs::read(&self.path).context(format!(
+            "Failed to read the metadata {}",
+            self.path.display()
+        ))?;
+        let metadata: Value = serde_json::from_slice(&contents).context(format!(
+            "The metadata {} is no valid JSON",
+            self.path.display()
+        ))?;
+        let (abi, skipped) = export(&metadata)?;
+        for item in &skipped {
+            eprintln!("{} skipped {}", "warning:".yellow().bold(), item);
+        }
+        let abi = serde_json::to_string_pretty(&abi)?;
+        match &self.output {
+            Some(output) => {
+                fs::write(output, abi).context(format!("Failed to write {}", output.display()))?;
+                Ok(Some(format!(
+                    "Exported {} as Solidity ABI to {}",
+                    self.path.display(),
+                    output.display()
+                )))
+            }
+            None => {
+                // printed as is, so that it can be piped into other tools
+                println!("{}", abi);
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Returns the Solidity ABI of the `metadata`, together with the items which were skipped
+/// because their types have no Solidity equivalent.
+fn export(metadata: &Value) -> Result<(Value, Vec<String>)> {
+    let contract = contract_of(metadata);
+    let spec = contract
+        .get("spec")
+        .ok_or_else(|| anyhow::anyhow!("The metadata contains no contract spec"))?;
+    let types = Types {
+        registry: Registry::new(contract),
+    };
+    let items = |kind: &str| {
+        spec.get(kind)
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    };
+    let mut abi = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (index, constructor) in items("constructors").iter().enumerate() {
+        let name = label(constructor).unwrap_or_default();
+        // Solidity contracts have a single constructor
+        if index > 0 {
+            skipped.push(format!(
+                "the constructor `{}`, Solidity ABIs have a single constructor",
+                name
+            ));
+            continue;
+        }
+        match types.params(constructor, false) {
+            Ok(inputs) => abi.push(json!({
+                "type": "constructor",
+                "inputs": inputs,
+                "stateMutability": state_mutability(constructor, true),
+                "selector": constructor.get("selector").cloned().unwrap_or_default()
+            })),
+            Err(reason) => skipped.push(format!("the constructor `{}`: {}", name, reason)),
+        }
+    }
+
+    for message in items("messages") {
+        let label = label(message).unwrap_or_default();
+        // messages of traits are labeled `Trait::message`
+        let name = label.rsplit("::").next().unwrap_or_default();
+        let outputs = match message.get("returnType").filter(|ty| !ty.is_null()) {
+            Some(ty) => types.outputs(ty),
+            None => Ok(Vec::new()),
+        };
+        match (types.params(message, false), outputs) {
+            (Ok(inputs), Ok(outputs)) => abi.push(json!({
+                "type": "function",
+                "name": name,
+                "inputs": inputs,
+                "outputs": outputs,
+                "stateMutability": state_mutability(message, false),
+                "selector": message.get("selector").cloned().unwrap_or_default()
+            })),
+            (Err(reason), _) | (_, Err(reason)) => {
+                skipped.push(format!("the message `{}`: {}", label, reason))
+            }
+        }
+    }
+
+    for event in items("events") {
+        let name = label(event).unwrap_or_default();
+        match types.params(event, true) {
+            Ok(inputs) => abi.push(json!({
+                "type": "event",
+                "name": name,
+                "inputs": inputs,
+                "anonymous": false
+            })),
+            Err(reason) => skipped.push(format!("the event `{}`: {}", name, reason)),
+        }
+    }
+    Ok((Value::Array(abi), skipped))
+}
+
+/// Returns the Solidity state mutability of a constructor or message.
+fn state_mutability(item: &Value, constructor: bool) -> &'static str {
+    let flag = |key: &str| item.get(key).and_then(Value::as_bool) == Some(true);
+    if flag("payable") {
+        "payable"
+    } else if constructor || flag("mutates") {
+        "nonpayable"
+    } else {
+        "view"
+    }
+}
+
+/// A Solidity type, with the components of tuples and the internal type of structs and enums.
+type AbiType = (String, Option<Vec<Value>>, Option<String>);
+
+/// Renders the types of the type registry as Solidity ABI types, or the reason why a type has no
+/// Solidity equivalent.
+struct Types<'a> {
+    registry: Registry<'a>,
+}
+
+impl<'a> Types<'a> {
+    /// Returns the ABI parameters of the arguments of a constructor, message or event.
+    fn params(&self, item: &Value, event: bool) -> std::result::Result<Vec<Value>, String> {
+        let args = item
+            .get("args")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut params = Vec::new();
+        for arg in args {
+            let id = type_id(&arg["type"])?;
+            let mut param = self.param(&label(arg).unwrap_or_default(), id)?;
+            if event {
+                let indexed = arg.get("indexed").and_then(Value::as_bool) == Some(true);
+                param["indexed"] = indexed.into();
+            }
+            params.push(param);
+        }
+        Ok(params)
+    }
+
+    /// Returns the ABI outputs of the return type `spec` of a message.
+    ///
+    /// A `Result` returns its `Ok` value, since errors revert the call in Solidity.
+    fn outputs(&self, spec: &Value) -> std::result::Result<Vec<Value>, String> {
+        let mut id = type_id(spec)?;
+        if let Some((name, params)) = self.custom_type(id)? {
+            if let ("Result", [ok, _]) = (name.as_str(), params.as_slice()) {
+                id = *ok;
+            }
+        }
+        if self.is_unit(id) {
+            return Ok(Vec::new());
+        }
+        Ok(vec![self.param("", id)?])
+    }
+
+    /// Returns the ABI parameter `name` of the type `id`.
+    fn param(&self, name: &str, id: u64) -> std::result::Result<Value, String> {
+        let (ty, components, internal_type) = self.abi_type(id)?;
+        let mut param = Map::new();
+        param.insert("name".into(), name.into());
+        param.insert("type".into(), ty.into());
+        if let Some(components) = components {
+            param.insert("components".into(), components.into());
+        }
+        if let Some(internal_type) = internal_type {
+            param.insert("internalType".into(), internal_type.into());
+        }
+        Ok(Value::Object(param))
+    }
+
+    /// Returns the Solidity type of the type `id`.
+    fn abi_type(&self, id: u64) -> std::result::Result<AbiType, String> {
+        let ty = self.registry.get(id).map_err(|err| err.to_string())?;
+        let def = ty
+            .get("def")
+            .and_then(Value::as_object)
+            .ok_or_else(|| format!("the type {} has no definition", id))?;
+        let field_type = |field: &Value| {
+            field
+                .get("type")
+                .and_then(Value::as_u64)
+                .or_else(|| field.as_u64())
+                .ok_or_else(|| format!("the type {} refers to no type id", id))
+        };
+
+        if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+            let ty = match primitive {
+                "bool" => "bool".to_string(),
+                "str" => "string".to_string(),
+                "char" => "uint32".to_string(),
+                integer if integer.starts_with('u') => format!("uint{}", &integer[1..]),
+                integer => format!("int{}", &integer[1..]),
+            };
+            return Ok((ty, None, None));
+        }
+        if let Some(sequence) = def.get("sequence") {
+            let element = field_type(sequence)?;
+            if self.registry.is_primitive(element, "u8") {
+                return Ok(("bytes".to_string(), None, None));
+            }
+            let (ty, components, internal_type) = self.abi_type(element)?;
+            return Ok((format!("{}[]", ty), components, internal_type));
+        }
+        if let Some(array) = def.get("array") {
+            let element = field_type(array)?;
+            let len = array.get("len").and_then(Value::as_u64).unwrap_or_default();
+            if self.registry.is_primitive(element, "u8") && len > 0 && len <= 32 {
+                return Ok((format!("bytes{}", len), None, None));
+            }
+            let (ty, components, internal_type) = self.abi_type(element)?;
+            return Ok((format!("{}[{}]", ty, len), components, internal_type));
+        }
+        if let Some(compact) = def.get("compact") {
+            return self.abi_type(field_type(compact)?);
+        }
+        if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+            if tuple.is_empty() {
+                return Err("the unit type has no Solidity equivalent".to_string());
+            }
+            let components = tuple
+                .iter()
+                .map(|field| self.param("", field_type(field)?))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            return Ok(("tuple".to_string(), Some(components), None));
+        }
+
+        let (name, _) = self
+            .custom_type(id)?
+            .ok_or_else(|| format!("the custom type {} has no path", id))?;
+        match name.as_str() {
+            "AccountId" | "Hash" => return Ok(("bytes32".to_string(), None, None)),
+            "Option" | "Result" => return Err(format!("`{}` has no Solidity equivalent", name)),
+            _ => (),
+        }
+        if let Some(composite) = def.get("composite") {
+            let fields = composite
+                .get("fields")
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            return match fields {
+                [] => Err(format!(
+                    "the unit struct `{}` has no Solidity equivalent",
+                    name
+                )),
+                // newtypes are represented by their field
+                [field] if field.get("name").is_none() => self.abi_type(field_type(field)?),
+                fields => {
+                    let components = fields
+                        .iter()
+                        .map(|field| {
+                            let field_name = field
+                                .get("name")
+                                .and_then(Value::as_str)
+                                .unwrap_or_default();
+                            self.param(field_name, field_type(field)?)
+                        })
+                        .collect::<std::result::Result<Vec<_>, _>>()?;
+                    Ok((
+                        "tuple".to_string(),
+                        Some(components),
+                        Some(format!("struct {}", name)),
+                    ))
+                }
+            };
+        }
+        let variants = def
+            .get("variant")
+            .and_then(|variant| variant.get("variants"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let has_fields = variants.iter().any(|variant| {
+            matches!(
+                variant.get("fields").and_then(Value::as_array),
+                Some(fields) if !fields.is_empty()
+            )
+        });
+        if has_fields {
+            return Err(format!(
+                "the enum `{}` has variants with fields, which Solidity enums can't have",
+                name
+            ));
+        }
+        Ok(("uint8".to_string(), None, Some(format!("enum {}", name))))
+    }
+
+    /// Returns the name and the type parameters of the type `id`, if it is a custom type.
+    fn custom_type(&self, id: u64) -> std::result::Result<Option<(String, Vec<u64>)>, String> {
+        let ty = self.registry.get(id).map_err(|err| err.to_string())?;
+        let name = ty
+            .get("path")
+            .and_then(Value::as_array)
+            .and_then(|path| path.last())
+            .and_then(Value::as_str);
+        match name {
+            Some(name) => {
+                let params = self.registry.params(id).map_err(|err| err.to_string())?;
+                Ok(Some((name.to_string(), params)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns `true` if the type `id` is the unit tuple.
+    fn is_unit(&self, id: u64) -> bool {
+        let tuple = self
+            .registry
+            .get(id)
+            .ok()
+            .and_then(|ty| ty.get("def"))
+            .and_then(|def| def.get("tuple"))
+            .and_then(Value::as_array);
+        matches!(tuple, Some(fields) if fields.is_empty())
+    }
+}
+
+/// Returns the id of the type reference `spec`.
+fn type_id(spec: &Value) -> std::result::Result<u64, String> {
+    spec.get("type")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| "a type reference has no type id".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> Value {
+        json!({
+            "V1": {
+                "spec": {
+                    "constructors": [{
+                        "label": "new", "selector": "0x9bae9d5e", "payable": false,
+                        "args": [{ "label": "supply", "type": { "type": 1 } }]
+                    }, {
+                        "label": "default", "selector": "0xed4b9d1b", "payable": false,
+                        "args": []
+                    }],
+                    "messages": [{
+                        "label": "Token::transfer", "selector": "0x84a15da1",
+                        "mutates": true, "payable": false,
+                        "args": [
+                            { "label": "to", "type": { "type": 2 } },
+                            { "label": "value", "type": { "type": 1 } }
+                        ],
+                        "returnType": { "type": 4 }
+                    }, {
+                        "label": "owner", "selector": "0xfeaea4fa",
+                        "mutates": false, "payable": false, "args": [],
+                        "returnType": { "type": 7 }
+                    }],
+                    "events": [{
+                        "label": "Transfer",
+                        "args": [
+                            { "label": "to", "type": { "type": 2 }, "indexed": true },
+                            { "label": "value", "type": { "type": 1 }, "indexed": false }
+                        ]
+                    }]
+                },
+                "types": [
+                    { "id": 0, "type": { "def": { "primitive": "u8" } } },
+                    { "id": 1, "type": { "def": { "primitive": "u128" } } },
+                    { "id": 2, "type": { "path": ["ink_env", "types", "AccountId"],
+                      "def": { "composite": { "fields": [{ "type": 3 }] } } } },
+                    { "id": 3, "type": { "def": { "array": { "len": 32, "type": 0 } } } },
+                    { "id": 4, "type": { "path": ["Result"],
+                      "params": [{ "name": "T", "type": 5 }, { "name": "E", "type": 6 }],
+                      "def": { "variant": { "variants": [] } } } },
+                    { "id": 5, "type": { "def": { "tuple": [] } } },
+                    { "id": 6, "type": { "path": ["my_token", "Error"],
+                      "def": { "variant": { "variants": [{ "name": "InsufficientBalance" }] } } } },
+                    { "id": 7, "type": { "path": ["Option"],
+                      "params": [{ "name": "T", "type": 2 }],
+                      "def": { "variant": { "variants": [] } } } }
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn messages_constructors_and_events_are_exported() {
+        // when
+        let (abi, _) = export(&metadata()).unwrap();
+
+        // then
+        assert_eq!(
+            abi,
+            json!([{
+                "type": "constructor",
+                "inputs": [{ "name": "supply", "type": "uint128" }],
+                "stateMutability": "nonpayable",
+                "selector": "0x9bae9d5e"
+            }, {
+                "type": "function",
+                "name": "transfer",
+                "inputs": [
+                    { "name": "to", "type": "bytes32" },
+                    { "name": "value", "type": "uint128" }
+                ],
+                "outputs": [],
+                "stateMutability": "nonpayable",
+                "selector": "0x84a15da1"
+            }, {
+                "type": "event",
+                "name": "Transfer",
+                "inputs": [
+                    { "name": "to", "type": "bytes32", "indexed": true },
+                    { "name": "value", "type": "uint128", "indexed": false }
+                ],
+                "anonymous": false
+            }])
+        );
+    }
+
+    #[test]
+    fn items_without_solidity_equivalent_are_skipped() {
+        // when
+        let (_, skipped) = export(&metadata()).unwrap();
+
+        // then
+        assert_eq!(
+            skipped,
+            vec![
+                "the constructor `default`, Solidity ABIs have a single constructor",
+                "the message `owner`: `Option` has no Solidity equivalent",
+            ]
+        );
+    }
+
+    #[test]
+    fn structs_and_enums_are_mapped_to_tuples_and_uint8() {
+        // given
+        let metadata = json!({
+            "types": [
+                { "path": ["token", "Info"], "def": { "composite": { "fields": [
+                    { "name": "name", "type": 2 }, { "name": "kind", "type": 3 }
+                ] } } },
+                { "def": { "primitive": "str" } },
+                { "path": ["token", "Kind"], "def": { "variant": { "variants": [
+                    { "name": "Fungible" }, { "name": "NonFungible" }
+                ] } } }
+            ]
+        });
+        let types = Types {
+            registry: Registry::new(&metadata),
+        };
+
+        // when
+        let param = types.param("info", 1).unwrap();
+
+        // then
+        assert_eq!(
+            param,
+            json!({
+                "name": "info",
+                "type": "tuple",
+                "components": [
+                    { "name": "name", "type": "string" },
+                    { "name": "kind", "type": "uint8", "internalType": "enum Kind" }
+                ],
+                "internalType": "struct Info"
+            })
+        );
+    }
+}
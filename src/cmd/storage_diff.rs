@@ -0,0 +1,467 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Comparison of the storage layouts of two versions of a contract.
+//!
+//! The layouts are flattened into their cells: the path of the field from the root of the
+//! storage, e.g. `balances[_]` for the values of a mapping, the storage key and the structural
+//! type of the value. A new version of the code, e.g. set by `set_code_hash`, reads the state
+//! written by the old one, which only works if every cell it keeps has the same key and type.
+
+use super::{
+    storage_migration,
+    trait_definition::{contract_of, Registry},
+    verify_metadata::label,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+/// Compares the storage layouts of two versions of a contract and fails on changes which
+/// would corrupt the state across an upgrade of its code.
+///
+/// Accepts both the `metadata.json` and the `<name>.contract` bundle.
+#[derive(Debug, StructOpt)]
+pub struct StorageDiffCommand {
+    /// Path to the metadata of the deployed version
+    #[structopt(parse(from_os_str))]
+    old: PathBuf,
+    /// Path to the metadata of the version to upgrade to
+    #[structopt(parse(from_os_str))]
+    new: PathBuf,
+    /// Generate a migration module to this path if the layouts are incompatible, e.g.
+    /// `src/migration.rs`
+    #[structopt(long, parse(from_os_str))]
+    migration: Option<PathBuf>,
+}
+
+impl StorageDiffCommand {
+    pub fn exec(&self) -> Result<Option<String>> {
+        let old = Layout::read(&self.old)?;
+        let new = Layout::read(&self.new)?;
+        let changes = diff(&old, &new);
+        let lines = changes
+            .iter()
+            .map(|change| {
+                let kind = if change.is_compatible() {
+                    "compatible:".green()
+                } else {
+                    "incompatible:".red()
+                };
+                format!("{} {}", kind.bold(), change)
+            })
+            .collect::<Vec<_>>();
+        let incompatible = changes.iter().any(|change| !change.is_compatible());
+        if let (true, Some(migration)) = (incompatible, &self.migration) {
+            if migration.exists() {
+                anyhow::bail!(
+                    "The migration {} already exists, remove it to generate it again",
+                    migration.display()
+                )
+            }
+            fs::write(migration, storage_migration::stub(&old, &new, &changes)?).context(
+                format!("Failed to write the migration {}", migration.display()),
+            )?;
+            return Ok(Some(format!(
+                "The storage layouts are incompatible:\n  {}\n\nGenerated the migration {}, \
+                declare it by `mod migration;` and resolve its TODOs",
+                lines.join("\n  "),
+                migration.display()
+            )));
+        }
+        if incompatible {
+            anyhow::bail!(
+                "The storage layout of {} is incompatible with the one of {}:\n  {}",
+                self.new.display(),
+                self.old.display(),
+                lines.join("\n  ")
+            )
+        }
+        if lines.is_empty() {
+            return Ok(Some("The storage layouts are identical".into()));
+        }
+        Ok(Some(format!(
+            "The storage layouts are compatible:\n  {}",
+            lines.join("\n  ")
+        )))
+    }
+}
+
+/// The cells of the storage layout of a contract, by their path.
+#[derive(Debug, Default)]
+pub(super) struct Layout {
+    pub cells: BTreeMap<String, Cell>,
+}
+
+/// A cell of the storage layout.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Cell {
+    /// The storage key, prefixed by the offsets and strategies of enclosing mappings and arrays.
+    pub key: String,
+    /// The Rust type of the value.
+    pub ty: String,
+    /// The definitions of the custom types the value refers to, which make up its encoding.
+    pub definitions: Vec<String>,
+}
+
+impl Layout {
+    /// Reads the storage layout of the metadata at `path`.
+    pub(super) fn read(path: &Path) -> Result<Self> {
+        let contents =
+            fs::read(path).context(format!("Failed to read the metadata {}", path.display()))?;
+        let metadata: Value = serde_json::from_slice(&contents)
+            .context(format!("The metadata {} is no valid JSON", path.display()))?;
+        Self::new(&metadata).context(format!("Invalid storage layout in {}", path.display()))
+    }
+
+    /// Returns the storage layout of the contract described by the `metadata`.
+    pub(super) fn new(metadata: &Value) -> Result<Self> {
+        let contract = contract_of(metadata);
+        let storage = contract
+            .get("storage")
+            .ok_or_else(|| anyhow::anyhow!("The metadata contains no storage layout"))?;
+        let mut layout = Self::default();
+        layout.flatten(&Registry::new(contract), storage, "", "")?;
+        Ok(layout)
+    }
+
+    /// Adds the cells of the `layout` at `path`, whose keys are prefixed by `prefix`.
+    fn flatten(
+        &mut self,
+        registry: &Registry,
+        layout: &Value,
+        path: &str,
+        prefix: &str,
+    ) -> Result<()> {
+        let field_path = |field: &Value, position: usize| {
+            let name = label(field).unwrap_or_else(|| position.to_string());
+            if path.is_empty() {
+                name
+            } else {
+                format!("{}.{}", path, name)
+            }
+        };
+        if let Some(cell) = layout.get("cell") {
+            let key = cell["key"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("The cell {} has no key", path))?;
+            let ty = cell["ty"]
+                .as_u64()
+                .ok_or_else(|| anyhow::anyhow!("The cell {} has no type", path))?;
+            let mut definitions = BTreeMap::new();
+            let rendered = registry.render(ty, &mut definitions)?;
+            self.cells.insert(
+                path.to_string(),
+                Cell {
+                    key: format!("{}{}", prefix, key),
+                    ty: rendered,
+                    definitions: definitions.values().cloned().collect(),
+                },
+            );
+        } else if let Some(fields) = layout["struct"]["fields"].as_array() {
+            for (position, field) in fields.iter().enumerate() {
+                self.flatten(
+                    registry,
+                    &field["layout"],
+                    &field_path(field, position),
+                    prefix,
+                )?;
+            }
+        } else if let Some(hash) = layout.get("hash") {
+            let strategy = &hash["strategy"];
+            let prefix = format!(
+                "{}{}/{}({}..{})/",
+                prefix,
+                hash["offset"].as_str().unwrap_or_default(),
+                strategy["hasher"].as_str().unwrap_or_default(),
+                strategy["prefix"].as_str().unwrap_or_default(),
+                strategy["postfix"].as_str().unwrap_or_default()
+            );
+            self.flatten(registry, &hash["layout"], &format!("{}[_]", path), &prefix)?;
+        } else if let Some(array) = layout.get("array") {
+            let prefix = format!(
+                "{}{}/{}x{}/",
+                prefix,
+                array["offset"].as_str().unwrap_or_default(),
+                array["len"].as_u64().unwrap_or_default(),
+                array["cellsPerElem"].as_u64().unwrap_or_default()
+            );
+            self.flatten(registry, &array["layout"], &format!("{}[_]", path), &prefix)?;
+        } else if let Some(variants) = layout["enum"]["variants"].as_object() {
+            let dispatch_key = layout["enum"]["dispatchKey"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("The enum {} has no dispatch key", path))?;
+            self.cells.insert(
+                format!("{}::discriminant", path),
+                Cell {
+                    key: format!("{}{}", prefix, dispatch_key),
+                    ty: "u8".into(),
+                    definitions: Vec::new(),
+                },
+            );
+            for (index, variant) in variants {
+                let fields = variant["fields"]
+                    .as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                for (position, field) in fields.iter().enumerate() {
+                    let name = label(field).unwrap_or_else(|| position.to_string());
+                    self.flatten(
+                        registry,
+                        &field["layout"],
+                        &format!("{}::{}.{}", path, index, name),
+                        prefix,
+                    )?;
+                }
+            }
+        } else {
+            anyhow::bail!("The layout of {} is of an unknown kind", path)
+        }
+        Ok(())
+    }
+}
+
+/// A change of the storage layout between two versions of a contract.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Change {
+    /// A cell was added at a key no cell used before.
+    Added { path: String, key: String },
+    /// A cell was removed, its value stays in storage unused.
+    Removed { path: String, key: String },
+    /// A cell is stored at another key, so its value is lost.
+    Moved {
+        path: String,
+        from: String,
+        to: String,
+    },
+    /// The type of a cell changed, so its value is decoded differently.
+    TypeChanged {
+        path: String,
+        from: String,
+        to: String,
+    },
+    /// A new cell uses the key of a removed one, so its value is decoded from the old one.
+    KeyReused {
+        path: String,
+        previous: String,
+        key: String,
+    },
+}
+
+impl Change {
+    /// Returns `true` if the new version still reads the state written by the old one.
+    pub(super) fn is_compatible(&self) -> bool {
+        matches!(self, Change::Added { .. })
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, key } => write!(f, "`{}` added at key {}", path, key),
+            Change::Removed { path, key } => write!(
+                f,
+                "`{}` removed, its value stays at key {} unused",
+                path, key
+            ),
+            Change::Moved { path, from, to } => {
+                write!(f, "`{}` moved from key {} to {}", path, from, to)
+            }
+            Change::TypeChanged { path, from, to } if from == to => {
+                write!(f, "`{}` changed the definition of its type `{}`", path, to)
+            }
+            Change::TypeChanged { path, from, to } => {
+                write!(f, "`{}` changed its type from `{}` to `{}`", path, from, to)
+            }
+            Change::KeyReused {
+                path,
+                previous,
+                key,
+            } => write!(
+                f,
+                "`{}` reuses key {} of the removed `{}`, whose value it decodes",
+                path, key, previous
+            ),
+        }
+    }
+}
+
+/// Returns the changes of the storage layout from `old` to `new`.
+pub(super) fn diff(old: &Layout, new: &Layout) -> Vec<Change> {
+    let old_paths = old
+        .cells
+        .iter()
+        .map(|(path, cell)| (cell.key.as_str(), path.as_str()))
+        .collect::<BTreeMap<_, _>>();
+    let mut changes = Vec::new();
+    for (path, old_cell) in &old.cells {
+        match new.cells.get(path) {
+            None => changes.push(Change::Removed {
+                path: path.clone(),
+                key: old_cell.key.clone(),
+            }),
+            Some(new_cell) if new_cell.key != old_cell.key => changes.push(Change::Moved {
+                path: path.clone(),
+                from: old_cell.key.clone(),
+                to: new_cell.key.clone(),
+            }),
+            Some(new_cell)
+                if new_cell.ty != old_cell.ty || new_cell.definitions != old_cell.definitions =>
+            {
+                changes.push(Change::TypeChanged {
+                    path: path.clone(),
+                    from: old_cell.ty.clone(),
+                    to: new_cell.ty.clone(),
+                })
+            }
+            Some(_) => (),
+        }
+    }
+    for (path, new_cell) in &new.cells {
+        if old.cells.contains_key(path) {
+            continue;
+        }
+        match old_paths.get(new_cell.key.as_str()) {
+            Some(previous) => changes.push(Change::KeyReused {
+                path: path.clone(),
+                previous: previous.to_string(),
+                key: new_cell.key.clone(),
+            }),
+            None => changes.push(Change::Added {
+                path: path.clone(),
+                key: new_cell.key.clone(),
+            }),
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata(fields: Value) -> Value {
+        json!({
+            "V1": {
+                "types": [
+                    { "id": 0, "type": { "def": { "primitive": "u128" } } },
+                    { "id": 1, "type": { "def": { "primitive": "u64" } } },
+                    { "id": 2, "type": { "path": ["erc20", "Owner"], "def": { "composite": {
+                      "fields": [{ "name": "account", "type": 0 }] } } } },
+                ],
+                "storage": { "struct": { "fields": fields } },
+            },
+        })
+    }
+
+    fn cell(name: &str, key: &str, ty: u64) -> Value {
+        json!({ "name": name, "layout": { "cell": { "key": key, "ty": ty } } })
+    }
+
+    fn balances(offset: &str) -> Value {
+        json!({ "name": "balances", "layout": { "hash": {
+            "offset": offset,
+            "strategy": { "hasher": "Blake2x256", "prefix": "0x", "postfix": "" },
+            "layout": { "cell": { "key": offset, "ty": 0 } },
+        } } })
+    }
+
+    fn changes(old: Value, new: Value) -> Vec<Change> {
+        let old = Layout::new(&metadata(old)).unwrap();
+        let new = Layout::new(&metadata(new)).unwrap();
+        diff(&old, &new)
+    }
+
+    #[test]
+    fn appended_fields_are_compatible() {
+        let changes = changes(
+            json!([cell("total_supply", "0x00", 0), balances("0x01")]),
+            json!([
+                cell("total_supply", "0x00", 0),
+                balances("0x01"),
+                cell("owner", "0x02", 2)
+            ]),
+        );
+
+        assert_eq!(
+            changes,
+            vec![Change::Added {
+                path: "owner".into(),
+                key: "0x02".into()
+            }]
+        );
+        assert!(changes[0].is_compatible());
+    }
+
+    #[test]
+    fn moved_keys_and_changed_types_are_incompatible() {
+        let changes = changes(
+            json!([cell("total_supply", "0x00", 0), balances("0x01")]),
+            json!([cell("total_supply", "0x00", 1), balances("0x02")]),
+        );
+
+        assert_eq!(
+            changes,
+            vec![
+                Change::Moved {
+                    path: "balances[_]".into(),
+                    from: "0x01/Blake2x256(0x..)/0x01".into(),
+                    to: "0x02/Blake2x256(0x..)/0x02".into(),
+                },
+                Change::TypeChanged {
+                    path: "total_supply".into(),
+                    from: "u128".into(),
+                    to: "u64".into(),
+                },
+            ]
+        );
+        assert!(changes.iter().all(|change| !change.is_compatible()));
+    }
+
+    #[test]
+    fn removed_and_renamed_fields_are_incompatible() {
+        let changes = changes(
+            json!([cell("total_supply", "0x00", 0), cell("owner", "0x01", 2)]),
+            json!([cell("supply", "0x00", 0)]),
+        );
+
+        assert_eq!(
+            changes,
+            vec![
+                Change::Removed {
+                    path: "owner".into(),
+                    key: "0x01".into()
+                },
+                Change::Removed {
+                    path: "total_supply".into(),
+                    key: "0x00".into()
+                },
+                Change::KeyReused {
+                    path: "supply".into(),
+                    previous: "total_supply".into(),
+                    key: "0x00".into()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generation of a migration module for incompatible changes of the storage layout.
+//!
+//! The module mirrors the cells of the old and the new layout in the `Storage` structs of its
+//! `old` and `new` modules, which read and write them at their keys. Its `migrate` function reads
+//! the old storage, builds the new one and writes it, with a TODO for each value which can't be
+//! taken over as it is. Cells of mappings and enums can't be enumerated, they are left to TODOs.
+
+use super::storage_diff::{Cell, Change, Layout};
+use anyhow::Result;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+/// The declarations of the `old` and `new` modules, before their type definitions.
+const MODULE_HEADER: &str = "    #![allow(unused_imports)]
+
+    use ink_env::{AccountId, Hash};
+    use ink_prelude::{string::String, vec::Vec};
+    use ink_primitives::Key;
+";
+
+/// Returns the source of the migration module from the `old` to the `new` layout.
+pub(super) fn stub(old: &Layout, new: &Layout, changes: &[Change]) -> Result<String> {
+    let mut source = String::from(
+        "//! Migration of the storage to a new layout, generated by\n\
+        //! `cargo contract metadata storage-diff --migration`.\n\
+        //!\n\
+        //! Changes of the storage layout:\n",
+    );
+    for change in changes {
+        writeln!(source, "//! - {}", change)?;
+    }
+    source.push_str(
+        "//!\n\
+        //! Resolve the TODOs and call `migrate` once after setting the new code, e.g. from a \
+        message\n\
+        //! only the owner of the contract may call:\n\
+        //!\n\
+        //! ```ignore\n\
+        //! #[ink(message)]\n\
+        //! pub fn migrate(&mut self) {\n\
+        //!     assert_eq!(self.env().caller(), self.owner);\n\
+        //!     crate::migration::migrate();\n\
+        //! }\n\
+        //! ```\n",
+    );
+    write!(source, "\n{}\n{}", module("old", old)?, module("new", new)?)?;
+
+    let old_fields = fields(old);
+    let mut body = String::new();
+    for (field, (path, _)) in fields(new) {
+        let changed = changes.iter().find(|change| match change {
+            Change::TypeChanged { path: changed, .. } | Change::KeyReused { path: changed, .. } => {
+                changed == path
+            }
+            _ => false,
+        });
+        let value = match (changed, old_fields.contains_key(&field)) {
+            (Some(change), _) => format!(
+                "// TODO: {}\n        {}: todo!(\"convert the old value\"),",
+                change, field
+            ),
+            (None, true) => format!("{}: old.{},", field, field),
+            (None, false) => format!(
+                "// TODO: initialize the added `{}`\n        {}: Default::default(),",
+                path, field
+            ),
+        };
+        writeln!(body, "        {}", value)?;
+    }
+    let mut todos = String::new();
+    for change in changes {
+        let path = match change {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Moved { path, .. }
+            | Change::TypeChanged { path, .. }
+            | Change::KeyReused { path, .. } => path,
+        };
+        let cell = new.cells.get(path).or_else(|| old.cells.get(path));
+        let is_field = cell.and_then(|cell| field_name(path, &cell.key)).is_some();
+        if let Change::Removed { .. } = change {
+            writeln!(
+                todos,
+                "    // TODO: {}, clear it by `ink_env::clear_contract_storage` if it's no \
+                longer needed",
+                change
+            )?;
+        } else if !is_field && !change.is_compatible() {
+            // the values of added mappings need no migration
+            writeln!(
+                todos,
+                "    // TODO: {}, migrate the values of the keys known to the contract",
+                change
+            )?;
+        }
+    }
+    write!(
+        source,
+        "\n/// Migrates the storage from the old to the new layout.\n\
+        pub fn migrate() {{\n    \
+        let old = old::Storage::read();\n    \
+        let new = new::Storage {{\n{}    }};\n    \
+        new.write();\n{}}}\n",
+        body, todos
+    )?;
+    Ok(source)
+}
+
+/// Returns the module `name` with the type definitions and the `Storage` struct of the
+/// `layout`.
+fn module(name: &str, layout: &Layout) -> Result<String> {
+    let definitions = layout
+        .cells
+        .values()
+        .flat_map(|cell| cell.definitions.iter())
+        .filter(|definition| !definition.is_empty())
+        .collect::<BTreeSet<_>>();
+    let mut source = format!(
+        "/// The types and cells of the {} storage layout.\nmod {} {{\n{}",
+        name, name, MODULE_HEADER
+    );
+    for definition in definitions {
+        source.push('\n');
+        for line in definition.lines() {
+            writeln!(source, "    {}", line)?;
+        }
+    }
+
+    let fields = fields(layout);
+    let mut declarations = String::new();
+    let mut reads = String::new();
+    let mut writes = String::new();
+    for (field, (path, cell)) in &fields {
+        let key = key_literal(&cell.key)?;
+        writeln!(
+            declarations,
+            "        /// `{}`\n        pub {}: {},",
+            path, field, cell.ty
+        )?;
+        writeln!(
+            reads,
+            "                {}: ink_env::get_contract_storage(&Key::from({}))\n                    \
+            .expect(\"the value decodes\")\n                    \
+            .expect(\"the value is stored\"),",
+            field, key
+        )?;
+        writeln!(
+            writes,
+            "            ink_env::set_contract_storage(&Key::from({}), &self.{});",
+            key, field
+        )?;
+    }
+    write!(
+        source,
+        "\n    /// The cells of the storage, apart from the values of mappings and enums.\n    \
+        pub struct Storage {{\n{}    }}\n\
+        \n    \
+        impl Storage {{\n        \
+        pub fn read() -> Self {{\n            \
+        Self {{\n{}            }}\n        \
+        }}\n\
+        \n        \
+        pub fn write(&self) {{\n{}        }}\n    \
+        }}\n\
+        }}\n",
+        declarations, reads, writes
+    )?;
+    Ok(source)
+}
+
+/// Returns the cells of the `layout` which are fields of the `Storage` struct, by field name.
+fn fields(layout: &Layout) -> BTreeMap<String, (&str, &Cell)> {
+    layout
+        .cells
+        .iter()
+        .filter_map(|(path, cell)| {
+            field_name(path, &cell.key).map(|field| (field, (path.as_str(), cell)))
+        })
+        .collect()
+}
+
+/// Returns the name of the field of the cell at `path`, unless it is stored within a mapping,
+/// array or enum.
+fn field_name(path: &str, key: &str) -> Option<String> {
+    if key.contains('/') || path.contains("::") || path.contains('[') {
+        return None;
+    }
+    Some(path.replace('.', "_"))
+}
+
+/// Returns the Rust array literal of the hex encoded storage `key`.
+fn key_literal(key: &str) -> Result<String> {
+    let hex = key.trim_start_matches("0x");
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|start| {
+            hex.get(start..start + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| anyhow::anyhow!("The storage key {} is no hex", key))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if bytes.len() != 32 {
+        anyhow::bail!("The storage key {} has no 32 bytes", key)
+    }
+    Ok(format!("{:?}", bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::storage_diff::diff, *};
+    use serde_json::json;
+
+    fn layout(supply: &str, extra: Option<&str>) -> Layout {
+        let key = |byte: u8| format!("0x{:02x}{}", byte, "00".repeat(31));
+        let mut fields = vec![
+            json!({ "name": "total_supply", "layout": { "cell": { "key": key(0), "ty": 0 } } }),
+            json!({ "name": "balances", "layout": { "hash": {
+                "offset": key(1),
+                "strategy": { "hasher": "Blake2x256", "prefix": "0x", "postfix": "" },
+                "layout": { "cell": { "key": key(1), "ty": 2 } },
+            } } }),
+        ];
+        if let Some(extra) = extra {
+            fields.push(json!({ "name": extra, "layout": { "cell": { "key": key(2), "ty": 1 } } }));
+        }
+        Layout::new(&json!({
+            "types": [
+                { "id": 0, "type": { "def": { "primitive": supply } } },
+                { "id": 1, "type": { "def": { "primitive": "bool" } } },
+                { "id": 2, "type": { "def": { "primitive": "u128" } } },
+            ],
+            "storage": { "struct": { "fields": fields } },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn migration_converts_changed_cells() {
+        let old = layout("u128", Some("paused"));
+        let new = layout("u64", Some("frozen"));
+        let changes = diff(&old, &new);
+
+        let stub = stub(&old, &new, &changes).unwrap();
+
+        assert!(stub.contains("mod old {"));
+        assert!(stub.contains("        pub total_supply: u128,\n"));
+        assert!(stub.contains("        pub total_supply: u64,\n"));
+        assert!(stub.contains(
+            "        // TODO: `total_supply` changed its type from `u128` to `u64`\n        \
+            total_supply: todo!(\"convert the old value\"),\n"
+        ));
+        assert!(stub.contains("        // TODO: `frozen` reuses key 0x02"));
+        assert!(!stub.contains("balances"));
+    }
+
+    #[test]
+    fn unchanged_cells_are_taken_over() {
+        let old = layout("u128", None);
+        let new = layout("u128", Some("paused"));
+        let changes = diff(&old, &new);
+
+        let stub = stub(&old, &new, &changes).unwrap();
+
+        assert!(stub.contains("        total_supply: old.total_supply,\n"));
+        assert!(stub.contains(
+            "        // TODO: initialize the added `paused`\n        paused: Default::default(),\n"
+        ));
+        assert!(stub.contains(&format!(
+            "ink_env::set_contract_storage(&Key::from({:?}), &self.paused);",
+            {
+                let mut key = [0u8; 32];
+                key[0] = 2;
+                key
+            }
+        )));
+    }
+
+    #[test]
+    fn keys_must_have_32_bytes() {
+        assert!(key_literal("0x0001").is_err());
+        assert!(key_literal(&format!("0x{}", "zz".repeat(32))).is_err());
+    }
+}
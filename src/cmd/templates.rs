@@ -0,0 +1,288 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Discovery of contract templates.
+//!
+//! Besides the templates bundled with cargo-contract, community templates are described by a
+//! template index, read from a file or fetched from a url, which contains the templates as JSON:
+//!
+//! ```json
+//! [{
+//!   "name": "psp22",
+//!   "description": "A PSP-22 fungible token",
+//!   "repository": "https://github.com/org/psp22-template",
+//!   "tag": "v1.2",
+//!   "ink": ">=3.0.0-rc3"
+//! }]
+//! ```
+//!
+//! Community templates are instantiated from their git repository by
+//! `cargo contract new <name> --template <template>`.
+
+use super::new::{Template, TEMPLATE_INK_VERSION};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use semver::VersionReq;
+use serde::Deserialize;
+use std::{fmt::Write, fs};
+use structopt::StructOpt;
+
+/// Environment variable holding the location of the template index, if `--template-index` is
+/// not given.
+const INDEX_ENV_VAR: &str = "CARGO_CONTRACT_TEMPLATE_INDEX";
+
+/// Discover the bundled and community contract templates.
+#[derive(Debug, StructOpt)]
+pub enum TemplatesCommand {
+    /// List all templates
+    #[structopt(name = "list")]
+    List(TemplateIndexOpts),
+    /// Search the templates by their name and description
+    #[structopt(name = "search")]
+    Search {
+        #[structopt(flatten)]
+        index: TemplateIndexOpts,
+        /// The term to search for
+        query: String,
+    },
+}
+
+/// Location of the index of community templates.
+#[derive(Debug, StructOpt)]
+pub struct TemplateIndexOpts {
+    /// Path or http(s) url of the index of community templates, defaults to
+    /// `$CARGO_CONTRACT_TEMPLATE_INDEX`
+    #[structopt(long = "template-index", value_name = "path | url")]
+    location: Option<String>,
+}
+
+impl TemplateIndexOpts {
+    /// Returns the location of the template index, if any is configured.
+    fn location(&self) -> Option<String> {
+        self.location
+            .clone()
+            .or_else(|| std::env::var(INDEX_ENV_VAR).ok())
+    }
+}
+
+/// A template described by the template index.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct TemplateEntry {
+    pub name: String,
+    pub description: String,
+    /// The git repository of the template, `None` for the bundled templates.
+    #[serde(default)]
+    pub repository: Option<String>,
+    /// The tag of the repository to check out.
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// The ink! versions the template requires.
+    #[serde(default)]
+    pub ink: Option<VersionReq>,
+}
+
+/// The bundled templates together with the community templates of the template index.
+#[derive(Debug)]
+pub(crate) struct TemplateIndex {
+    entries: Vec<TemplateEntry>,
+}
+
+impl TemplateIndex {
+    /// Loads the template index configured by `opts`.
+    pub fn load(opts: &TemplateIndexOpts) -> Result<Self> {
+        let mut entries = bundled()?;
+        if let Some(location) = opts.location() {
+            let community = load(&location)?;
+            for entry in &community {
+                if entry.repository.is_none() {
+                    anyhow::bail!(
+                        "Template {} of the template index {} has no repository",
+                        entry.name,
+                        location
+                    )
+                }
+            }
+            // bundled templates take precedence over community templates of the same name
+            entries.extend(
+                community
+                    .into_iter()
+                    .filter(|entry| entry.name.parse::<Template>().is_err()),
+            );
+        }
+        Ok(Self { entries })
+    }
+
+    /// Returns the template `name`.
+    pub fn find(&self, name: &str) -> Option<&TemplateEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Returns the templates whose name or description contains `query`, ignoring case.
+    fn search(&self, query: &str) -> Vec<&TemplateEntry> {
+        let query = query.to_lowercase();
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry.name.to_lowercase().contains(&query)
+                    || entry.description.to_lowercase().contains(&query)
+            })
+            .collect()
+    }
+}
+
+impl TemplatesCommand {
+    pub fn exec(&self) -> Result<String> {
+        match self {
+            TemplatesCommand::List(opts) => {
+                let index = TemplateIndex::load(opts)?;
+                table(&index.entries.iter().collect::<Vec<_>>())
+            }
+            TemplatesCommand::Search { index, query } => {
+                let index = TemplateIndex::load(index)?;
+                let entries = index.search(query);
+                if entries.is_empty() {
+                    return Ok(format!("No templates match {}", query));
+                }
+                table(&entries)
+            }
+        }
+    }
+}
+
+/// Returns a line per template with its name, required ink! versions, source and description.
+fn table(entries: &[&TemplateEntry]) -> Result<String> {
+    let width = entries
+        .iter()
+        .map(|entry| entry.name.len())
+        .max()
+        .unwrap_or_default();
+    let mut table = String::new();
+    for entry in entries {
+        let ink = entry
+            .ink
+            .as_ref()
+            .map_or("*".to_string(), ToString::to_string);
+        let source = match (&entry.repository, &entry.tag) {
+            (Some(repository), Some(tag)) => format!("{} at {}", repository, tag),
+            (Some(repository), None) => repository.clone(),
+            (None, _) => "bundled".to_string(),
+        };
+        writeln!(
+            table,
+            "{:<width$}  ink! {:<16} {}\n{:<width$}  {}",
+            entry.name.bold(),
+            ink,
+            entry.description,
+            "",
+            source.dimmed(),
+            width = width
+        )?;
+    }
+    Ok(table.trim_end().to_string())
+}
+
+/// Returns the index entries of the bundled templates.
+fn bundled() -> Result<Vec<TemplateEntry>> {
+    let ink = VersionReq::parse(&format!("={}", TEMPLATE_INK_VERSION))?;
+    Ok(Template::ALL
+        .iter()
+        .map(|template| TemplateEntry {
+            name: template.to_string(),
+            description: template.description().to_string(),
+            repository: None,
+            tag: None,
+            ink: Some(ink.clone()),
+        })
+        .collect())
+}
+
+/// Loads the template index from the file at `location`, or fetches it if it is an http(s) url.
+fn load(location: &str) -> Result<Vec<TemplateEntry>> {
+    let contents = if location.starts_with("http://") || location.starts_with("https://") {
+        ureq::get(location)
+            .call()
+            .map_err(|err| anyhow::anyhow!("Fetching the template index failed: {}", err))?
+            .into_string()?
+    } else {
+        fs::read_to_string(location).context(format!("Failed to read {}", location))?
+    };
+    serde_json::from_str(&contents).context(format!("Invalid template index {}", location))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    #[test]
+    fn community_templates_are_searchable() {
+        with_tmp_dir(|path| {
+            // given
+            let location = path.join("templates.json");
+            fs::write(
+                &location,
+                r#"[{
+                    "name": "psp22",
+                    "description": "A PSP-22 fungible token",
+                    "repository": "https://github.com/org/psp22-template",
+                    "tag": "v1.2",
+                    "ink": ">=3.0.0-rc3"
+                }, {
+                    "name": "erc20",
+                    "description": "Shadows the bundled template",
+                    "repository": "https://github.com/org/erc20-template"
+                }]"#,
+            )?;
+            let opts = TemplateIndexOpts {
+                location: Some(location.to_string_lossy().to_string()),
+            };
+
+            // when
+            let index = TemplateIndex::load(&opts)?;
+
+            // then
+            let psp22 = index
+                .find("psp22")
+                .expect("community template must be found");
+            assert_eq!(psp22.tag.as_deref(), Some("v1.2"));
+            assert_eq!(index.find("erc20").unwrap().repository, None);
+            let names = index
+                .search("FUNGIBLE")
+                .iter()
+                .map(|entry| entry.name.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(names, vec!["erc20", "erc721", "psp22"]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn community_templates_require_a_repository() {
+        with_tmp_dir(|path| {
+            let location = path.join("templates.json");
+            fs::write(
+                &location,
+                r#"[{"name": "psp22", "description": "A PSP-22 fungible token"}]"#,
+            )?;
+            let opts = TemplateIndexOpts {
+                location: Some(location.to_string_lossy().to_string()),
+            };
+
+            assert!(TemplateIndex::load(&opts).is_err());
+            Ok(())
+        })
+    }
+}
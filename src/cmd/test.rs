@@ -0,0 +1,134 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    build::{self, ExecuteArgs},
+    coverage,
+};
+use crate::{crate_metadata::CrateMetadata, toolchain, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use contract_e2e::{Node, BUNDLE_ENV, CARGO_CONTRACT_ENV, NODE_RPC_URL_ENV, NODE_WS_URL_ENV};
+use std::{convert::TryFrom, env, path::PathBuf};
+use structopt::StructOpt;
+
+/// The feature of the contract its end-to-end tests are gated behind.
+const E2E_FEATURE: &str = "e2e-tests";
+
+/// Runs the tests of the contract off-chain, or its end-to-end tests against an ephemeral node.
+///
+/// For `--coverage` the off-chain tests are run with coverage instrumentation, see
+/// [`coverage`].
+///
+/// For `--e2e` a development node is spawned and the contract is built once, then the tests are
+/// run with the `e2e-tests` feature of the contract enabled. They deploy and call the contract
+/// with the harness of the `contract-e2e` crate, which picks up the node and the bundle. The node
+/// is stopped once the tests finished.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "test")]
+pub struct TestCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Run the end-to-end tests against an ephemeral node, spawned from the binary in
+    /// `CONTRACTS_NODE` or `substrate-contracts-node` on the `PATH`
+    #[structopt(long)]
+    e2e: bool,
+    /// Run the off-chain tests with coverage instrumentation and report the coverage of each
+    /// module and message, written to `target/ink/coverage` as `coverage.json` and `lcov.info`
+    #[structopt(long, conflicts_with = "e2e")]
+    coverage: bool,
+    /// Arguments passed on to `cargo test`, e.g. a test name filter
+    #[structopt(last = true)]
+    args: Vec<String>,
+}
+
+impl TestCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let crate_metadata = CrateMetadata::collect(&manifest_path)?;
+        if self.coverage {
+            return coverage::execute(&crate_metadata, &self.args);
+        }
+        let mut cargo = toolchain::cargo_command(crate_metadata.toolchain.as_deref());
+        cargo
+            .arg("test")
+            .arg("--manifest-path")
+            .arg(manifest_path.as_ref());
+        if !self.e2e {
+            return run(cargo.args(&self.args));
+        }
+        if !cfg!(feature = "extrinsics") {
+            anyhow::bail!(
+                "End-to-end tests deploy the contract, which requires cargo-contract to be \
+                installed with the `extrinsics` feature"
+            )
+        }
+
+        let args = ExecuteArgs {
+            manifest_path: manifest_path.clone(),
+            ..Default::default()
+        };
+        let bundle = build::execute(args)?
+            .metadata_result
+            .expect("the bundle is built for all artifacts; qed")
+            .dest_bundle;
+        let node = Node::spawn()?;
+        let cargo_contract = env::current_exe()?;
+        cargo
+            .args(["--features", E2E_FEATURE])
+            .args(&self.args)
+            .env(NODE_WS_URL_ENV, node.ws_url())
+            .env(NODE_RPC_URL_ENV, node.rpc_url())
+            .env(BUNDLE_ENV, bundle)
+            .env(CARGO_CONTRACT_ENV, cargo_contract);
+        // the node is stopped when dropped after the tests
+        run(&mut cargo)
+    }
+}
+
+/// Runs `cargo test` with its output shown as it happens.
+pub(super) fn run(cargo: &mut std::process::Command) -> Result<String> {
+    log::info!("Invoking cargo: {:?}", cargo);
+    let status = cargo
+        .status()
+        .context(format!("Error executing `{:?}`", cargo))?;
+    if !status.success() {
+        anyhow::bail!("The tests failed with exit code: {:?}", status.code())
+    }
+    Ok("All tests passed".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn args_after_separator_are_passed_to_cargo_test() {
+        let test = TestCommand::from_iter(&["test", "--e2e", "--", "flip", "--nocapture"]);
+        assert!(test.e2e);
+        assert_eq!(test.args, vec!["flip", "--nocapture"]);
+    }
+
+    #[test]
+    fn coverage_is_limited_to_off_chain_tests() {
+        assert!(
+            TestCommand::from_iter_safe(&["test", "--coverage"])
+                .unwrap()
+                .coverage
+        );
+        assert!(TestCommand::from_iter_safe(&["test", "--coverage", "--e2e"]).is_err());
+    }
+}
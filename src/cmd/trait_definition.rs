@@ -0,0 +1,874 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generation of `#[ink::trait_definition]` crates.
+//!
+//! The messages of the trait are described by a TOML spec file:
+//!
+//! ```toml
+//! trait = "Psp22"
+//! docs = "The PSP-22 fungible token standard."
+//! # definitions of the custom types the messages refer to, copied as is
+//! types = "pub type Error = u8;"
+//!
+//! [[message]]
+//! name = "transfer"
+//! docs = "Transfers `value` tokens to the account `to`."
+//! mutates = true
+//! selector = "0xdb20f9f5"
+//! args = [{ name = "to", type = "AccountId" }, { name = "value", type = "Balance" }]
+//! returns = "Result<(), Error>"
+//! ```
+//!
+//! or are taken from the metadata of an existing contract, whose custom types are generated from
+//! its type registry.
+
+use super::{
+    new::{out_dir, template_files, validate_name, write_files, TemplateVariables},
+    selector::selector,
+    verify_metadata::label,
+};
+use anyhow::{Context, Result};
+use heck::CamelCase as _;
+use impl_serde::serialize as serde_hex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    fmt::Write,
+    fs,
+    path::Path,
+};
+
+/// The attributes of the struct and enum definitions of the generated crate.
+pub(super) const TYPE_DERIVES: &str =
+    "#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]\n\
+    #[cfg_attr(feature = \"std\", derive(scale_info::TypeInfo))]\n";
+
+/// Custom types which are defined by the generated crate or `core` and the ink! prelude.
+const PROVIDED_TYPES: [&str; 9] = [
+    "AccountId",
+    "Balance",
+    "Hash",
+    "Timestamp",
+    "BlockNumber",
+    "Option",
+    "Result",
+    "String",
+    "Vec",
+];
+
+/// The trait described by a spec file, the metadata of a contract or a Solidity ABI.
+#[derive(Debug, Deserialize)]
+pub(super) struct TraitSpec {
+    /// The name of the trait, defaults to the camel case name of the crate.
+    #[serde(rename = "trait")]
+    pub(super) trait_name: Option<String>,
+    pub(super) docs: Option<String>,
+    /// Definitions of the custom types the messages refer to.
+    pub(super) types: Option<String>,
+    #[serde(rename = "message", default)]
+    pub(super) messages: Vec<MessageSpec>,
+}
+
+/// A message of the trait.
+#[derive(Debug, Deserialize)]
+pub(super) struct MessageSpec {
+    pub(super) name: String,
+    pub(super) docs: Option<String>,
+    #[serde(default)]
+    pub(super) mutates: bool,
+    #[serde(default)]
+    pub(super) payable: bool,
+    /// The selector of the message, defaults to the one ink! derives from the trait and the name.
+    pub(super) selector: Option<String>,
+    #[serde(default)]
+    pub(super) args: Vec<ArgSpec>,
+    pub(super) returns: Option<String>,
+}
+
+/// An argument of a message.
+#[derive(Debug, Deserialize)]
+pub(super) struct ArgSpec {
+    pub(super) name: String,
+    #[serde(rename = "type")]
+    pub(super) ty: String,
+}
+
+/// Creates the trait definition crate `name` described by the spec file or contract metadata at
+/// `from`, together with a stub contract implementing the trait in its `stub` directory.
+pub(crate) fn execute<P>(
+    name: &str,
+    dir: Option<P>,
+    from: &Path,
+    variables: &TemplateVariables,
+) -> Result<Option<String>>
+where
+    P: AsRef<Path>,
+{
+    validate_name(name)?;
+    let contents =
+        fs::read_to_string(from).context(format!("Failed to read {}", from.display()))?;
+    let spec = if from.extension() == Some(OsStr::new("toml")) {
+        toml::from_str(&contents).context(format!("Invalid trait spec {}", from.display()))?
+    } else {
+        let metadata = serde_json::from_str(&contents)
+            .context(format!("The metadata {} is no valid JSON", from.display()))?;
+        from_metadata(&metadata)?
+    };
+    create(name, dir, &spec, false, variables)
+}
+
+/// Creates the trait definition crate `name` of the `spec`, together with a stub contract
+/// implementing the trait in its `stub` directory.
+///
+/// With `calls`, the crate also contains a `<Trait>Ref` wrapper calling the messages of a
+/// deployed contract by their selectors, for contracts not implementing the trait with ink!.
+pub(super) fn create<P>(
+    name: &str,
+    dir: Option<P>,
+    spec: &TraitSpec,
+    calls: bool,
+    variables: &TemplateVariables,
+) -> Result<Option<String>>
+where
+    P: AsRef<Path>,
+{
+    validate_name(name)?;
+    let trait_name = spec
+        .trait_name
+        .clone()
+        .unwrap_or_else(|| name.to_camel_case());
+    if spec.messages.is_empty() {
+        anyhow::bail!("The trait {} has no messages", trait_name)
+    }
+    let (messages, stub_messages) = render_messages(&trait_name, &spec.messages)?;
+    let calls = if calls {
+        render_calls(&trait_name, &spec.messages)?
+    } else {
+        String::new()
+    };
+
+    let out_dir = out_dir(name, dir)?;
+    let files = template_files(include_bytes!(concat!(env!("OUT_DIR"), "/trait.zip")), "")?;
+    let (stub_files, trait_files): (BTreeMap<_, _>, BTreeMap<_, _>) = files
+        .into_iter()
+        .partition(|(file_name, _)| file_name.starts_with("stub/"));
+    let stub_files = stub_files
+        .into_iter()
+        .filter_map(|(file_name, file)| {
+            file_name
+                .strip_prefix("stub/")
+                .filter(|file_name| !file_name.is_empty())
+                .map(|file_name| (file_name.to_string(), file))
+        })
+        .collect();
+
+    let variables = variables
+        .clone()
+        .with("trait_name", trait_name.clone())
+        .with("trait_crate", name.to_string());
+    let types = spec
+        .types
+        .as_ref()
+        .map_or(String::new(), |types| format!("\n{}\n", types.trim_end()));
+    write_files(
+        name,
+        &out_dir,
+        trait_files,
+        &variables
+            .clone()
+            .with("docs", doc_comment(spec.docs.as_deref(), "")?)
+            .with("types", types)
+            .with("messages", messages)
+            .with("calls", calls),
+    )?;
+    write_files(
+        &format!("{}_stub", name),
+        &out_dir.join("stub"),
+        stub_files,
+        &variables.with("stub_messages", stub_messages),
+    )?;
+
+    Ok(Some(format!(
+        "Created the trait definition {} with a stub implementation in {}",
+        trait_name,
+        out_dir.join("stub").display()
+    )))
+}
+
+/// Returns the messages of the trait definition and of its stub implementation.
+fn render_messages(trait_name: &str, messages: &[MessageSpec]) -> Result<(String, String)> {
+    let mut names = BTreeSet::new();
+    let mut definitions = String::new();
+    let mut stubs = String::new();
+    for message in messages {
+        if !names.insert(&message.name) {
+            anyhow::bail!("The trait defines the message {} twice", message.name)
+        }
+        let default_selector = serde_hex::to_hex(&selector(Some(trait_name), &message.name), false);
+        let selector = message
+            .selector
+            .as_ref()
+            .map(|selector| selector.to_lowercase())
+            .filter(|selector| *selector != default_selector);
+        let mut flags = String::new();
+        if message.payable {
+            flags.push_str(", payable");
+        }
+        let args = message
+            .args
+            .iter()
+            .map(|arg| format!(", {}: {}", arg.name, arg.ty))
+            .collect::<String>();
+        let signature = format!(
+            "fn {}({}self{}){}",
+            message.name,
+            if message.mutates { "&mut " } else { "&" },
+            args,
+            message
+                .returns
+                .as_ref()
+                .map_or(String::new(), |returns| format!(" -> {}", returns))
+        );
+
+        definitions.push_str(&doc_comment(message.docs.as_deref(), "    ")?);
+        match &selector {
+            // the selector of the message must be kept, e.g. to implement a standard
+            Some(selector) => writeln!(
+                definitions,
+                "    #[ink(message{}, selector = \"{}\")]",
+                flags, selector
+            )?,
+            None => writeln!(definitions, "    #[ink(message{})]", flags)?,
+        }
+        writeln!(definitions, "    {};\n", signature)?;
+
+        write!(
+            stubs,
+            "        #[ink(message{})]\n        {} {{\n            todo!()\n        }}\n\n",
+            flags, signature
+        )?;
+    }
+    Ok((
+        definitions.trim_end_matches('\n').to_string() + "\n",
+        stubs.trim_end_matches('\n').to_string() + "\n",
+    ))
+}
+
+/// Returns the `<Trait>Ref` wrapper calling the `messages` of a deployed contract.
+fn render_calls(trait_name: &str, messages: &[MessageSpec]) -> Result<String> {
+    let mut methods = String::new();
+    for message in messages {
+        let selector = match &message.selector {
+            Some(selector) => selector.clone(),
+            None => serde_hex::to_hex(&selector(Some(trait_name), &message.name), false),
+        };
+        let mut params = String::new();
+        let mut value = String::new();
+        if message.payable {
+            params.push_str(", transferred_value: Balance");
+            value.push_str("\n            .transferred_value(transferred_value)");
+        }
+        let mut input = format!(
+            "ink_env::call::ExecutionInput::new(ink_env::call::Selector::new({}))",
+            selector_bytes(&selector)?
+        );
+        for arg in &message.args {
+            write!(params, ", {}: {}", arg.name, arg.ty)?;
+            write!(input, "\n                    .push_arg({})", arg.name)?;
+        }
+        let (output, returns) = match &message.returns {
+            Some(returns) => (
+                returns.as_str(),
+                format!("ink_env::call::utils::ReturnType<{}>", returns),
+            ),
+            None => ("()", "()".to_string()),
+        };
+
+        methods.push('\n');
+        methods.push_str(&doc_comment(message.docs.as_deref(), "    ")?);
+        write!(
+            methods,
+            "    pub fn {}(&self{}) -> ink_env::Result<{}> {{\n        \
+            ink_env::call::build_call::<DefaultEnvironment>()\n            \
+            .callee(self.account_id){}\n            \
+            .exec_input(\n                {},\n            )\n            \
+            .returns::<{}>()\n            \
+            .fire()\n    \
+            }}\n",
+            message.name, params, output, value, input, returns
+        )?;
+    }
+    Ok(format!(
+        "\n/// Calls the messages of a deployed `{name}` contract by their selectors.\n\
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]\n\
+        pub struct {name}Ref {{\n    \
+        account_id: AccountId,\n\
+        }}\n\n\
+        impl {name}Ref {{\n    \
+        /// Returns a reference to the contract at `account_id`.\n    \
+        pub fn new(account_id: AccountId) -> Self {{\n        \
+        Self {{ account_id }}\n    \
+        }}\n\n    \
+        /// Returns the account id of the contract.\n    \
+        pub fn account_id(&self) -> AccountId {{\n        \
+        self.account_id\n    \
+        }}\n{methods}}}\n",
+        name = trait_name,
+        methods = methods
+    ))
+}
+
+/// Returns the hex encoded `selector` as Rust array of bytes, e.g. `[0x0f, 0x75, 0x5a, 0x56]`.
+pub(super) fn selector_bytes(selector: &str) -> Result<String> {
+    let hex = selector.trim_start_matches("0x");
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .filter(|byte| u8::from_str_radix(byte, 16).is_ok())
+                .map(|byte| format!("0x{}", byte.to_lowercase()))
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|bytes| bytes.len() == 4)
+        .ok_or_else(|| anyhow::anyhow!("The selector {} is no 4 byte hex string", selector))?;
+    Ok(format!("[{}]", bytes.join(", ")))
+}
+
+/// Returns the `docs` as doc comment lines, indented by `indent`.
+pub(super) fn doc_comment(docs: Option<&str>, indent: &str) -> Result<String> {
+    let mut comment = String::new();
+    for line in docs.unwrap_or_default().trim().lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            writeln!(comment, "{}///", indent)?;
+        } else {
+            writeln!(comment, "{}/// {}", indent, line)?;
+        }
+    }
+    Ok(comment)
+}
+
+/// Returns the trait described by the messages of the contract `metadata`.
+///
+/// The trait is named after the trait the messages are defined by, if all of them are defined
+/// by the same trait.
+fn from_metadata(metadata: &Value) -> Result<TraitSpec> {
+    let contract = contract_of(metadata);
+    let spec = contract
+        .get("spec")
+        .ok_or_else(|| anyhow::anyhow!("The metadata contains no contract spec"))?;
+    let registry = Registry::new(contract);
+    let mut definitions = BTreeMap::new();
+
+    let mut traits = BTreeSet::new();
+    let mut messages = Vec::new();
+    let items = spec
+        .get("messages")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for item in items {
+        let item_label = label(item).ok_or_else(|| anyhow::anyhow!("A message has no name"))?;
+        let mut segments = item_label.rsplitn(2, "::");
+        let name = segments.next().unwrap_or_default().to_string();
+        traits.insert(segments.next().map(ToString::to_string));
+        let args = item
+            .get("args")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|arg| {
+                Ok(ArgSpec {
+                    name: label(arg).ok_or_else(|| {
+                        anyhow::anyhow!("An argument of the message {} has no name", name)
+                    })?,
+                    ty: registry.type_spec(&arg["type"], &mut definitions)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let returns = match item.get("returnType").filter(|ty| !ty.is_null()) {
+            Some(ty) => Some(registry.type_spec(ty, &mut definitions)?),
+            None => None,
+        };
+        messages.push(MessageSpec {
+            docs: docs(item),
+            mutates: item.get("mutates").and_then(Value::as_bool) == Some(true),
+            payable: item.get("payable").and_then(Value::as_bool) == Some(true),
+            selector: item
+                .get("selector")
+                .and_then(Value::as_str)
+                .map(ToString::to_string),
+            args,
+            returns,
+            name,
+        });
+    }
+
+    let trait_name = match traits.into_iter().collect::<Vec<_>>().as_slice() {
+        [Some(trait_name)] => trait_name.rsplit("::").next().map(ToString::to_string),
+        _ => None,
+    };
+    let types = definitions.values().cloned().collect::<Vec<_>>().join("\n");
+    Ok(TraitSpec {
+        trait_name,
+        docs: docs(spec),
+        types: Some(types).filter(|types| !types.is_empty()),
+        messages,
+    })
+}
+
+/// Returns the contract of the `metadata`, which older ink! versions store at the top level and
+/// newer ones nest in the version of the metadata format.
+pub(crate) fn contract_of(metadata: &Value) -> &Value {
+    metadata.get("V1").unwrap_or(metadata)
+}
+
+/// Returns the doc comment of a metadata item, stored as lines in `docs`.
+pub(super) fn docs(item: &Value) -> Option<String> {
+    let lines = item
+        .get("docs")
+        .and_then(Value::as_array)?
+        .iter()
+        .filter_map(Value::as_str)
+        .map(|line| line.strip_prefix(' ').unwrap_or(line))
+        .collect::<Vec<_>>();
+    Some(lines.join("\n")).filter(|docs| !docs.trim().is_empty())
+}
+
+/// The type registry of the contract metadata, rendering its types as Rust source.
+///
+/// Registries of older ink! versions identify types by their 1-based position, newer ones
+/// contain the `id` of each type.
+pub(super) struct Registry<'a> {
+    types: BTreeMap<u64, &'a Value>,
+    /// The attributes of the generated struct and enum definitions.
+    derives: &'static str,
+}
+
+impl<'a> Registry<'a> {
+    pub(super) fn new(contract: &'a Value) -> Self {
+        let types = contract
+            .get("types")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .map(
+                |(index, entry)| match entry.get("id").and_then(Value::as_u64) {
+                    Some(id) => (id, entry.get("type").unwrap_or(&Value::Null)),
+                    None => (index as u64 + 1, entry),
+                },
+            )
+            .collect();
+        Self {
+            types,
+            derives: TYPE_DERIVES,
+        }
+    }
+
+    /// Sets the attributes of the generated struct and enum definitions.
+    pub(super) fn with_derives(mut self, derives: &'static str) -> Self {
+        self.derives = derives;
+        self
+    }
+
+    /// Returns the Rust type of an argument or return type `spec` of a message.
+    ///
+    /// The display name of the type is used if it has no type parameters, so that aliases like
+    /// `Balance` are kept.
+    pub(super) fn type_spec(
+        &self,
+        spec: &Value,
+        definitions: &mut BTreeMap<String, String>,
+    ) -> Result<String> {
+        let id = spec
+            .get("type")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow::anyhow!("A type reference has no type id"))?;
+        let display_name = spec
+            .get("displayName")
+            .and_then(Value::as_array)
+            .and_then(|segments| segments.last())
+            .and_then(Value::as_str);
+        match display_name {
+            Some(name) if self.params(id)?.is_empty() => {
+                // custom types still need a definition
+                self.render(id, definitions)?;
+                Ok(name.to_string())
+            }
+            _ => self.render(id, definitions),
+        }
+    }
+
+    pub(super) fn get(&self, id: u64) -> Result<&'a Value> {
+        self.types
+            .get(&id)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("The type registry contains no type {}", id))
+    }
+
+    /// Returns `true` if the type `id` is the `primitive` type, e.g. `u8`.
+    pub(super) fn is_primitive(&self, id: u64, primitive: &str) -> bool {
+        self.get(id)
+            .ok()
+            .and_then(|ty| ty.get("def"))
+            .and_then(|def| def.get("primitive"))
+            .and_then(Value::as_str)
+            == Some(primitive)
+    }
+
+    /// Returns the ids of the type parameters of the type `id`.
+    pub(super) fn params(&self, id: u64) -> Result<Vec<u64>> {
+        Ok(self
+            .get(id)?
+            .get("params")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            // older registries list the ids of the parameters, newer ones name them
+            .filter_map(|param| param.as_u64().or_else(|| param["type"].as_u64()))
+            .collect())
+    }
+
+    /// Returns the Rust source of the type `id`, adding the definitions of the custom types it
+    /// refers to to `definitions`.
+    pub(super) fn render(
+        &self,
+        id: u64,
+        definitions: &mut BTreeMap<String, String>,
+    ) -> Result<String> {
+        let ty = self.get(id)?;
+        let def = ty
+            .get("def")
+            .and_then(Value::as_object)
+            .ok_or_else(|| anyhow::anyhow!("The type {} has no definition", id))?;
+        let field_type = |field: &Value, definitions: &mut BTreeMap<String, String>| {
+            let id = field
+                .get("type")
+                .and_then(Value::as_u64)
+                .or_else(|| field.as_u64())
+                .ok_or_else(|| anyhow::anyhow!("The type {} refers to no type id", field))?;
+            self.render(id, definitions)
+        };
+
+        if let Some(primitive) = def.get("primitive").and_then(Value::as_str) {
+            return Ok(match primitive {
+                "str" => "String".to_string(),
+                primitive => primitive.to_string(),
+            });
+        }
+        if let Some(sequence) = def.get("sequence") {
+            return Ok(format!("Vec<{}>", field_type(sequence, definitions)?));
+        }
+        if let Some(array) = def.get("array") {
+            let len = array.get("len").and_then(Value::as_u64).unwrap_or_default();
+            return Ok(format!("[{}; {}]", field_type(array, definitions)?, len));
+        }
+        if let Some(compact) = def.get("compact") {
+            return field_type(compact, definitions);
+        }
+        if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+            let fields = tuple
+                .iter()
+                .map(|field| field_type(field, definitions))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(match fields.as_slice() {
+                [field] => format!("({},)", field),
+                fields => format!("({})", fields.join(", ")),
+            });
+        }
+
+        let name = ty
+            .get("path")
+            .and_then(Value::as_array)
+            .and_then(|path| path.last())
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("The custom type {} has no path", id))?
+            .to_string();
+        let params = self
+            .params(id)?
+            .into_iter()
+            .map(|param| self.render(param, definitions))
+            .collect::<Result<Vec<_>>>()?;
+        if !PROVIDED_TYPES.contains(&name.as_str()) && !definitions.contains_key(&name) {
+            // the name is reserved first, as the type may refer to itself
+            definitions.insert(name.clone(), String::new());
+            let definition = self.definition(&name, def, definitions)?;
+            definitions.insert(name.clone(), definition);
+        }
+        Ok(if params.is_empty() {
+            name
+        } else {
+            format!("{}<{}>", name, params.join(", "))
+        })
+    }
+
+    /// Returns the definition of the custom struct or enum `name`.
+    fn definition(
+        &self,
+        name: &str,
+        def: &serde_json::Map<String, Value>,
+        definitions: &mut BTreeMap<String, String>,
+    ) -> Result<String> {
+        let mut source = self.derives.to_string();
+        if let Some(composite) = def.get("composite") {
+            let fields = self.fields(composite, "pub ", definitions)?;
+            match fields {
+                Fields::Unit => writeln!(source, "pub struct {};", name)?,
+                Fields::Unnamed(fields) => {
+                    writeln!(source, "pub struct {}({});", name, fields.join(", "))?
+                }
+                Fields::Named(fields) => writeln!(
+                    source,
+                    "pub struct {} {{\n{}}}",
+                    name,
+                    fields
+                        .iter()
+                        .map(|field| format!("    {},\n", field))
+                        .collect::<String>()
+                )?,
+            }
+        } else if let Some(variant) = def.get("variant") {
+            writeln!(source, "pub enum {} {{", name)?;
+            let variants = variant
+                .get("variants")
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            for variant in variants {
+                let variant_name = variant
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow::anyhow!("A variant of {} has no name", name))?;
+                match self.fields(variant, "", definitions)? {
+                    Fields::Unit => writeln!(source, "    {},", variant_name)?,
+                    Fields::Unnamed(fields) => {
+                        writeln!(source, "    {}({}),", variant_name, fields.join(", "))?
+                    }
+                    Fields::Named(fields) => {
+                        writeln!(source, "    {} {{ {} }},", variant_name, fields.join(", "))?
+                    }
+                }
+            }
+            writeln!(source, "}}")?;
+        } else {
+            anyhow::bail!("The type {} can't be generated", name)
+        }
+        Ok(source)
+    }
+
+    /// Returns the `fields` of a struct or enum variant, prefixed with `visibility`.
+    fn fields(
+        &self,
+        item: &Value,
+        visibility: &str,
+        definitions: &mut BTreeMap<String, String>,
+    ) -> Result<Fields> {
+        let fields = item
+            .get("fields")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if fields.is_empty() {
+            return Ok(Fields::Unit);
+        }
+        let mut rendered = Vec::new();
+        let mut named = true;
+        for field in fields {
+            let ty = field
+                .get("type")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow::anyhow!("A field refers to no type id"))?;
+            let ty = self.render(ty, definitions)?;
+            match field.get("name").and_then(Value::as_str) {
+                Some(name) => rendered.push(format!("{}{}: {}", visibility, name, ty)),
+                None => {
+                    named = false;
+                    rendered.push(format!("{}{}", visibility, ty))
+                }
+            }
+        }
+        Ok(if named {
+            Fields::Named(rendered)
+        } else {
+            Fields::Unnamed(rendered)
+        })
+    }
+}
+
+/// The rendered fields of a struct or enum variant.
+enum Fields {
+    Unit,
+    Unnamed(Vec<String>),
+    Named(Vec<String>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use serde_json::json;
+
+    #[test]
+    fn trait_is_generated_from_spec_file() {
+        with_tmp_dir(|path| {
+            // given
+            let spec = path.join("psp22.toml");
+            fs::write(
+                &spec,
+                r#"
+                docs = "The PSP-22 fungible token standard."
+                types = "pub type Error = u8;"
+
+                [[message]]
+                name = "total_supply"
+                returns = "Balance"
+
+                [[message]]
+                name = "transfer"
+                docs = "Transfers `value` tokens to the account `to`."
+                mutates = true
+                selector = "0xDB20F9F5"
+                args = [{ name = "to", type = "AccountId" }, { name = "value", type = "Balance" }]
+                returns = "Result<(), Error>"
+                "#,
+            )?;
+
+            // when
+            execute("psp22", Some(path), &spec, &Default::default())?;
+
+            // then
+            let lib = fs::read_to_string(path.join("psp22").join("lib.rs"))?;
+            assert!(lib.contains("\npub type Error = u8;\n"));
+            assert!(lib.contains(
+                "/// The PSP-22 fungible token standard.\n\
+                #[ink::trait_definition]\npub trait Psp22 {\n"
+            ));
+            assert!(lib.contains("    #[ink(message)]\n    fn total_supply(&self) -> Balance;\n"));
+            assert!(lib.contains(
+                "    /// Transfers `value` tokens to the account `to`.\n    \
+                #[ink(message, selector = \"0xdb20f9f5\")]\n    \
+                fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), Error>;\n"
+            ));
+            let stub = fs::read_to_string(path.join("psp22").join("stub").join("lib.rs"))?;
+            assert!(stub.contains("mod psp22_stub {\n    use psp22::*;"));
+            assert!(stub.contains("impl Psp22 for Psp22Stub {"));
+            assert!(stub.contains(
+                "fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), Error> {\n"
+            ));
+            let stub_manifest =
+                fs::read_to_string(path.join("psp22").join("stub").join("Cargo.toml"))?;
+            assert!(stub_manifest.contains("psp22 = { path = \"..\", default-features = false }"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn default_selectors_are_omitted() {
+        let messages = vec![MessageSpec {
+            name: "transfer".to_string(),
+            docs: None,
+            mutates: true,
+            payable: false,
+            selector: Some("0x46607e68".to_string()),
+            args: Vec::new(),
+            returns: None,
+        }];
+
+        let (definitions, _) = render_messages("Erc20", &messages).unwrap();
+
+        assert_eq!(
+            definitions,
+            "    #[ink(message)]\n    fn transfer(&mut self);\n"
+        );
+    }
+
+    #[test]
+    fn trait_and_custom_types_are_taken_from_metadata() {
+        // given
+        let metadata = json!({
+            "V1": {
+                "spec": {
+                    "docs": [" A token."],
+                    "messages": [{
+                        "label": "Token::owner_of", "selector": "0x29b8f3ca",
+                        "mutates": false, "payable": false,
+                        "args": [{ "label": "id", "type": { "type": 0, "displayName": ["u32"] } }],
+                        "returnType": { "type": 2, "displayName": ["Option"] },
+                        "docs": [" Returns the owner of the token."]
+                    }, {
+                        "label": "Token::burn", "selector": "0x12345678",
+                        "mutates": true, "payable": false,
+                        "args": [{ "label": "id", "type": { "type": 0, "displayName": ["u32"] } }],
+                        "returnType": { "type": 3, "displayName": ["Result"] },
+                        "docs": []
+                    }]
+                },
+                "types": [
+                    { "id": 0, "type": { "def": { "primitive": "u32" } } },
+                    { "id": 1, "type": { "path": ["ink_env", "types", "AccountId"],
+                      "def": { "composite": { "fields": [{ "type": 5 }] } } } },
+                    { "id": 2, "type": { "path": ["Option"],
+                      "params": [{ "name": "T", "type": 1 }],
+                      "def": { "variant": { "variants": [
+                          { "name": "None" }, { "name": "Some", "fields": [{ "type": 1 }] }
+                      ] } } } },
+                    { "id": 3, "type": { "path": ["Result"],
+                      "params": [{ "name": "T", "type": 6 }, { "name": "E", "type": 4 }],
+                      "def": { "variant": { "variants": [] } } } },
+                    { "id": 4, "type": { "path": ["token", "Error"],
+                      "def": { "variant": { "variants": [
+                          { "name": "NotOwner" }, { "name": "TokenNotFound" }
+                      ] } } } },
+                    { "id": 5, "type": { "def": { "array": { "len": 32, "type": 7 } } } },
+                    { "id": 6, "type": { "def": { "tuple": [] } } },
+                    { "id": 7, "type": { "def": { "primitive": "u8" } } }
+                ]
+            }
+        });
+
+        // when
+        let spec = from_metadata(&metadata).unwrap();
+        let (definitions, _) =
+            render_messages(spec.trait_name.as_deref().unwrap(), &spec.messages).unwrap();
+
+        // then
+        assert_eq!(spec.trait_name.as_deref(), Some("Token"));
+        assert_eq!(spec.docs.as_deref(), Some("A token."));
+        assert_eq!(
+            spec.types.as_deref(),
+            Some(
+                "#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]\n\
+                #[cfg_attr(feature = \"std\", derive(scale_info::TypeInfo))]\n\
+                pub enum Error {\n    NotOwner,\n    TokenNotFound,\n}\n"
+            )
+        );
+        assert!(definitions.contains(
+            "    /// Returns the owner of the token.\n    #[ink(message)]\n    \
+            fn owner_of(&self, id: u32) -> Option<AccountId>;\n"
+        ));
+        assert!(definitions.contains(
+            "    #[ink(message, selector = \"0x12345678\")]\n    \
+            fn burn(&mut self, id: u32) -> Result<(), Error>;\n"
+        ));
+    }
+}
@@ -0,0 +1,371 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    call::{display_exec_result, dry_run, resolve_bundle, ContractExecResult},
+    scale_json::{items, ScaleJson},
+    serve::{contract_event, watch_contract_events},
+    trait_definition::{contract_of, docs, Registry},
+    verify_metadata::label,
+};
+use crate::{deployments::Deployments, ExtrinsicOpts, OriginOpts};
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Input, Select};
+use serde_json::Value;
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::collections::BTreeMap;
+use structopt::StructOpt;
+use subxt::{contracts::*, system::System, ClientBuilder, DefaultNodeRuntime};
+
+/// Browses the messages of a deployed contract in an interactive terminal UI, to fill in their
+/// arguments with validation against the metadata, dry-run or submit them and watch the events
+/// of the contract.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "tui")]
+pub struct TuiCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    #[structopt(flatten)]
+    origin_opts: OriginOpts,
+    /// The address of the contract, selected from the deployment records if not supplied
+    #[structopt(long, parse(try_from_str = crate::parse_account))]
+    contract: Option<AccountId32>,
+    /// Maximum amount of gas to be used for calls
+    #[structopt(name = "gas", long, default_value = "500000000")]
+    gas_limit: u64,
+}
+
+/// What to do with a filled in message.
+enum Action {
+    DryRun,
+    Submit,
+    Back,
+}
+
+impl TuiCommand {
+    pub fn exec(&self) -> Result<Option<String>> {
+        let theme = ColorfulTheme::default();
+        let contract = match &self.contract {
+            Some(contract) => contract.clone(),
+            None => self.select_contract(&theme)?,
+        };
+        let metadata = Value::Object(
+            resolve_bundle(&self.extrinsic_opts, &contract)?
+                .json()
+                .clone(),
+        );
+        let spec = contract_of(&metadata);
+        let registry = Registry::new(spec);
+        let codec = ScaleJson::new(&registry);
+        let messages = items(spec, "messages");
+        let mut choices = messages
+            .iter()
+            .map(|message| signature(&registry, message))
+            .collect::<Result<Vec<_>>>()?;
+        choices.push("Watch events".into());
+        choices.push("Quit".into());
+
+        loop {
+            let choice = Select::with_theme(&theme)
+                .with_prompt(format!("Contract {}", contract.to_ss58check()))
+                .items(&choices)
+                .default(0)
+                .interact()?;
+            match messages.get(choice) {
+                Some(message) => self.call(&theme, &registry, &codec, spec, &contract, message)?,
+                None if choice == messages.len() => {
+                    println!("Watching the events of the contract, press Ctrl-C to quit");
+                    watch_contract_events(&self.extrinsic_opts.url()?, |emitter, data| {
+                        if emitter == contract {
+                            println!("{}", display_event(&codec, spec, &data));
+                        }
+                    })?;
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Prompts for a contract of the deployment records, or for its address if there are none.
+    fn select_contract(&self, theme: &ColorfulTheme) -> Result<AccountId32> {
+        let url = self.extrinsic_opts.url()?;
+        let deployments = Deployments::load(&self.extrinsic_opts.manifest_path()?)?
+            .deployments
+            .into_iter()
+            .filter(|deployment| deployment.url == url)
+            .collect::<Vec<_>>();
+        let mut choices = deployments
+            .iter()
+            .map(|deployment| match &deployment.name {
+                Some(name) => format!("{} {}", name, deployment.contract),
+                None => deployment.contract.clone(),
+            })
+            .collect::<Vec<_>>();
+        choices.push("Enter an address".into());
+        let choice = if deployments.is_empty() {
+            deployments.len()
+        } else {
+            Select::with_theme(theme)
+                .with_prompt(format!("Contracts deployed to {}", url))
+                .items(&choices)
+                .default(0)
+                .interact()?
+        };
+        let address = match deployments.get(choice) {
+            Some(deployment) => deployment.contract.clone(),
+            None => Input::<String>::with_theme(theme)
+                .with_prompt("Address of the contract")
+                .validate_with(|address: &String| {
+                    AccountId32::from_ss58check(address)
+                        .map(|_| ())
+                        .map_err(|err| format!("Invalid address: {:?}", err))
+                })
+                .interact_text()?,
+        };
+        crate::parse_account(&address)
+    }
+
+    /// Prompts for the arguments of the `message`, then dry-runs or submits it.
+    fn call(
+        &self,
+        theme: &ColorfulTheme,
+        registry: &Registry,
+        codec: &ScaleJson,
+        spec: &Value,
+        contract: &AccountId32,
+        message: &Value,
+    ) -> Result<()> {
+        if let Some(docs) = docs(message) {
+            println!("{}", docs.dimmed());
+        }
+        let mut definitions = BTreeMap::new();
+        let args = items(message, "args")
+            .iter()
+            .map(|arg| {
+                let ty = registry.type_spec(&arg["type"], &mut definitions)?;
+                let input = Input::<String>::with_theme(theme)
+                    .with_prompt(format!("{}: {}", label(arg).unwrap_or_default(), ty))
+                    .validate_with(|input: &String| {
+                        parse_arg(codec, arg, input)
+                            .map(|_| ())
+                            .map_err(|err| format!("{:#}", err))
+                    })
+                    .interact_text()?;
+                parse_arg(codec, arg, &input)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let data = codec.encode_message(message, &args)?;
+        let value = if message["payable"].as_bool().unwrap_or_default() {
+            Input::<u128>::with_theme(theme)
+                .with_prompt("Value")
+                .default(0)
+                .interact_text()?
+        } else {
+            0
+        };
+
+        let mutates = message["mutates"].as_bool().unwrap_or_default();
+        loop {
+            let action = if mutates {
+                let choice = Select::with_theme(theme)
+                    .items(&["Dry-run", "Submit", "Back"])
+                    .default(0)
+                    .interact()?;
+                match choice {
+                    0 => Action::DryRun,
+                    1 => Action::Submit,
+                    _ => Action::Back,
+                }
+            } else {
+                Action::DryRun
+            };
+            match action {
+                Action::DryRun => {
+                    let origin = self.origin_opts.origin(&self.extrinsic_opts)?;
+                    let url = self.extrinsic_opts.url()?;
+                    let result = dry_run(&url, &origin, contract, value, self.gas_limit, &data)?;
+                    println!("{}", display_exec_result(&origin, &result));
+                    if let ContractExecResult::Success { data, .. } = &result {
+                        let output = hex::decode(data.trim_start_matches("0x"))?;
+                        let output = codec.decode_output(message, &output)?;
+                        println!("\t{} {}", "Output:".bold(), output);
+                    }
+                    if !mutates {
+                        return Ok(());
+                    }
+                }
+                Action::Submit => {
+                    return submit(
+                        &self.extrinsic_opts,
+                        self.gas_limit,
+                        codec,
+                        spec,
+                        contract,
+                        value,
+                        &data,
+                    )
+                }
+                Action::Back => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Submits the call of the `contract` and prints the events it emitted.
+pub(super) fn submit(
+    extrinsic_opts: &ExtrinsicOpts,
+    gas_limit: u64,
+    codec: &ScaleJson,
+    spec: &Value,
+    contract: &AccountId32,
+    value: u128,
+    data: &[u8],
+) -> Result<()> {
+    let signer = extrinsic_opts.signer()?;
+    let dest: <DefaultNodeRuntime as System>::Address = contract.clone().into();
+    let result = async_std::task::block_on(async move {
+        let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+            .set_url(&extrinsic_opts.url()?.to_string())
+            .build()
+            .await?;
+        let result = cli
+            .call_and_watch(signer.as_ref(), &dest, value, gas_limit, data)
+            .await?;
+        Ok::<_, anyhow::Error>(result)
+    })?;
+    println!("Call included in block {:?}", result.block);
+    for event in &result.events {
+        if let Some((emitter, data)) = contract_event(event)? {
+            if &emitter == contract {
+                println!("{}", display_event(codec, spec, &data));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the signature of the `message` as it is shown in the list of messages.
+pub(super) fn signature(registry: &Registry, message: &Value) -> Result<String> {
+    let mut definitions = BTreeMap::new();
+    let args = items(message, "args")
+        .iter()
+        .map(|arg| {
+            let ty = registry.type_spec(&arg["type"], &mut definitions)?;
+            Ok(format!("{}: {}", label(arg).unwrap_or_default(), ty))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut signature = format!(
+        "{}({})",
+        label(message).unwrap_or_default(),
+        args.join(", ")
+    );
+    if !message["returnType"].is_null() {
+        let ty = registry.type_spec(&message["returnType"], &mut definitions)?;
+        signature.push_str(&format!(" -> {}", ty));
+    }
+    if message["mutates"].as_bool().unwrap_or_default() {
+        signature.push_str(" [mutates]");
+    }
+    if message["payable"].as_bool().unwrap_or_default() {
+        signature.push_str(" [payable]");
+    }
+    Ok(signature)
+}
+
+/// Parses the `input` of the argument `spec` as JSON, validated by encoding it.
+///
+/// Inputs which aren't valid JSON are taken as strings, so that e.g. addresses don't need to be
+/// quoted.
+fn parse_arg(codec: &ScaleJson, spec: &Value, input: &str) -> Result<Value> {
+    let value = serde_json::from_str(input).unwrap_or_else(|_| Value::String(input.into()));
+    codec.encode_arg(spec, &value, &mut Vec::new())?;
+    Ok(value)
+}
+
+/// Returns a human readable representation of the event `data` of a contract.
+pub(super) fn display_event(codec: &ScaleJson, spec: &Value, data: &[u8]) -> String {
+    match codec.decode_event(spec, data) {
+        Ok((name, args)) => format!("{} {} {}", "Event".bold(), name.bright_green(), args),
+        Err(err) => format!(
+            "{} 0x{} ({:#})",
+            "Undecoded event".bold(),
+            hex::encode(data),
+            err
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec() -> Value {
+        json!({
+            "spec": {
+                "events": [{
+                    "label": "Flipped",
+                    "args": [{ "label": "value", "type": { "type": 1 }, "indexed": false }]
+                }],
+                "messages": [{
+                    "label": "flip", "selector": "0x633aa551", "mutates": true, "payable": true,
+                    "args": [{ "label": "times", "type": { "type": 2, "displayName": ["u8"] } }],
+                    "returnType": { "type": 1, "displayName": ["bool"] }
+                }]
+            },
+            "types": [
+                { "id": 1, "type": { "def": { "primitive": "bool" } } },
+                { "id": 2, "type": { "def": { "primitive": "u8" } } }
+            ]
+        })
+    }
+
+    #[test]
+    fn messages_are_listed_by_signature() {
+        let spec = spec();
+        let registry = Registry::new(&spec);
+        assert_eq!(
+            signature(&registry, &spec["spec"]["messages"][0]).unwrap(),
+            "flip(times: u8) -> bool [mutates] [payable]"
+        );
+    }
+
+    #[test]
+    fn args_are_validated_against_their_type() {
+        let spec = spec();
+        let registry = Registry::new(&spec);
+        let codec = ScaleJson::new(&registry);
+        let arg = &spec["spec"]["messages"][0]["args"][0];
+
+        assert_eq!(parse_arg(&codec, arg, "42").unwrap(), json!(42));
+        assert!(parse_arg(&codec, arg, "256").is_err());
+        assert!(parse_arg(&codec, arg, "flip").is_err());
+    }
+
+    #[test]
+    fn events_are_displayed_decoded() {
+        let spec = spec();
+        let registry = Registry::new(&spec);
+        let codec = ScaleJson::new(&registry);
+
+        let decoded = display_event(&codec, &spec, &[0x00, 0x01]);
+        assert!(decoded.contains("Flipped"));
+        assert!(decoded.ends_with(r#"{"value":true}"#));
+        let undecoded = display_event(&codec, &spec, &[0x01]);
+        assert!(undecoded.contains("0x01 (The contract has no event 1)"));
+    }
+}
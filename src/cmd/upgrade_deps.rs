@@ -0,0 +1,295 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::workspace::{Manifest, ManifestPath};
+use anyhow::Result;
+use colored::Colorize;
+use semver::Version;
+use std::{convert::TryFrom, fmt::Write, path::PathBuf};
+use structopt::StructOpt;
+
+/// An ink! release together with the versions of the crates it has to be used with.
+pub(super) struct Release {
+    pub ink: &'static str,
+    scale: &'static str,
+    scale_info: &'static str,
+    /// API changes of the release which require manual edits of contracts.
+    changes: &'static [&'static str],
+}
+
+/// Matches the names of the packages to update, together with the version to update them to.
+type PackageUpdate<'a> = (&'a dyn Fn(&str) -> bool, &'a str);
+
+/// The ink! releases contracts can be upgraded to, in ascending order.
+const RELEASES: [Release; 5] = [
+    Release {
+        ink: "3.0.0-rc3",
+        scale: "2.1",
+        scale_info: "0.6",
+        changes: &[],
+    },
+    Release {
+        ink: "3.0.0-rc4",
+        scale: "2.1",
+        scale_info: "0.6",
+        changes: &[
+            "Trait definitions were redesigned, `#[ink::trait_definition]`s can no longer \
+            contain constructors",
+            "Custom selectors are integers instead of strings, e.g. `selector = 0xCAFEBABE`",
+        ],
+    },
+    Release {
+        ink: "3.0.0-rc5",
+        scale: "2.1",
+        scale_info: "1.0",
+        changes: &[
+            "scale-info 1.0 changed the type registry of the metadata, types deriving \
+            `scale_info::TypeInfo` need to be built against it",
+        ],
+    },
+    Release {
+        ink: "3.0.0-rc6",
+        scale: "2.1",
+        scale_info: "1.0",
+        changes: &[
+            "Contracts used as dependencies are called via the generated `<Contract>Ref` type \
+            instead of the contract type",
+        ],
+    },
+    Release {
+        ink: "3.0.0",
+        scale: "3",
+        scale_info: "2",
+        changes: &[
+            "`ink_storage::collections` were removed in favour of `ink_storage::Mapping`, \
+            storage structs holding a `Mapping` derive `SpreadAllocate` and are initialized \
+            via `ink_lang::utils::initialize_contract`",
+            "parity-scale-codec 3 and scale-info 2 are required by all crates whose types are \
+            used by the contract",
+        ],
+    },
+];
+
+/// Rewrites the dependencies of the contract to another ink! release.
+///
+/// All ink! crates, `parity-scale-codec` and `scale-info` are updated consistently. The API
+/// changes between the releases which require manual edits are reported.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "upgrade-deps")]
+pub struct UpgradeDepsCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// The ink! release to use, e.g. `3.0.0-rc4`
+    version: String,
+    /// Only report the changes, without rewriting the Cargo.toml
+    #[structopt(long)]
+    dry_run: bool,
+}
+
+impl UpgradeDepsCommand {
+    pub fn exec(&self) -> Result<String> {
+        let target = release(&self.version)?;
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let mut manifest = Manifest::new(manifest_path.clone())?;
+
+        let packages: [PackageUpdate; 3] = [
+            (
+                &|package| package.starts_with("ink_") || package.starts_with("ink-"),
+                target.ink,
+            ),
+            (&|package| package == "parity-scale-codec", target.scale),
+            (&|package| package == "scale-info", target.scale_info),
+        ];
+        let mut updated = Vec::new();
+        for (matches, version) in &packages {
+            for (name, previous) in manifest.with_dependency_version(*matches, version)? {
+                updated.push((name, previous, *version));
+            }
+        }
+        let current = updated
+            .iter()
+            .find(|(name, _, _)| name == "ink_lang")
+            .and_then(|(_, previous, _)| parse_requirement(previous));
+        if updated.is_empty() {
+            return Ok(format!("The contract already uses ink! {}", target.ink));
+        }
+        if !self.dry_run {
+            manifest.write(&manifest_path)?;
+        }
+
+        let mut report = String::new();
+        writeln!(
+            report,
+            "{} the dependencies of {} to ink! {}",
+            if self.dry_run {
+                "Would update"
+            } else {
+                "Updated"
+            },
+            manifest_path.as_ref().display(),
+            target.ink
+        )?;
+        for (name, previous, version) in &updated {
+            writeln!(report, "  {} {} -> {}", name, previous, version)?;
+        }
+        let changes = changes(current.as_ref(), target)?;
+        if !changes.is_empty() {
+            writeln!(
+                report,
+                "\n{}",
+                "Changes which require manual edits of the contract:".bold()
+            )?;
+            for (release, change) in changes {
+                writeln!(report, "  {}: {}", release, change)?;
+            }
+        }
+        Ok(report.trim_end().to_string())
+    }
+}
+
+/// Returns the known ink! release `version`.
+pub(super) fn release(version: &str) -> Result<&'static Release> {
+    RELEASES
+        .iter()
+        .find(|release| release.ink == version)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown ink! release {}, supported are {}",
+                version,
+                RELEASES
+                    .iter()
+                    .map(|release| release.ink)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+}
+
+/// Returns the version of a plain version requirement like `=3.0.0-rc3`.
+pub(super) fn parse_requirement(requirement: &str) -> Option<Version> {
+    Version::parse(requirement.trim_start_matches(|c| "=^~ ".contains(c))).ok()
+}
+
+/// Returns the API changes of the releases after `current` up to the `target`, or up to the
+/// `target` if the current release is unknown.
+fn changes(
+    current: Option<&Version>,
+    target: &Release,
+) -> Result<Vec<(&'static str, &'static str)>> {
+    let target = Version::parse(target.ink)?;
+    let mut changes = Vec::new();
+    for release in &RELEASES {
+        let version = Version::parse(release.ink)?;
+        if current.iter().all(|current| version > **current) && version <= target {
+            changes.extend(release.changes.iter().map(|change| (release.ink, *change)));
+        }
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use std::fs;
+    use toml::value;
+
+    #[test]
+    fn dependencies_are_upgraded_consistently() {
+        with_tmp_dir(|path| {
+            // given
+            let manifest_path = path.join("Cargo.toml");
+            fs::write(
+                &manifest_path,
+                r#"
+                [package]
+                name = "flipper"
+                version = "0.1.0"
+
+                [dependencies]
+                ink_lang = { version = "3.0.0-rc3", default-features = false }
+                ink_env = "3.0.0-rc3"
+                ink_storage = { path = "../ink/crates/storage" }
+                scale = { package = "parity-scale-codec", version = "2.1", features = ["derive"] }
+                scale-info = { version = "0.6.0", optional = true }
+                "#,
+            )?;
+            let cmd = UpgradeDepsCommand::from_iter(&[
+                "upgrade-deps",
+                "--manifest-path",
+                manifest_path.to_str().unwrap(),
+                "3.0.0-rc5",
+            ]);
+
+            // when
+            let report = cmd.exec()?;
+
+            // then
+            let manifest: value::Table = toml::from_str(&fs::read_to_string(&manifest_path)?)?;
+            let dependencies = &manifest["dependencies"];
+            assert_eq!(
+                dependencies["ink_lang"]["version"].as_str(),
+                Some("3.0.0-rc5")
+            );
+            assert_eq!(dependencies["ink_env"].as_str(), Some("3.0.0-rc5"));
+            assert!(dependencies["ink_storage"].get("version").is_none());
+            assert_eq!(dependencies["scale"]["version"].as_str(), Some("2.1"));
+            assert_eq!(dependencies["scale-info"]["version"].as_str(), Some("1.0"));
+            assert_eq!(dependencies["scale-info"]["optional"].as_bool(), Some(true));
+
+            assert!(report.contains("  ink_lang 3.0.0-rc3 -> 3.0.0-rc5\n"));
+            assert!(report.contains("  scale-info 0.6.0 -> 1.0\n"));
+            assert!(!report.contains("  scale "));
+            assert!(report.contains("3.0.0-rc4: Custom selectors are integers"));
+            assert!(report.contains("3.0.0-rc5: scale-info 1.0"));
+            assert!(!report.contains("3.0.0-rc6:"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn dry_run_leaves_the_manifest_untouched() {
+        with_tmp_dir(|path| {
+            // given
+            let manifest_path = path.join("Cargo.toml");
+            let manifest = "[package]\nname = \"flipper\"\nversion = \"0.1.0\"\n\n\
+                [dependencies]\nink_lang = \"3.0.0-rc3\"\n";
+            fs::write(&manifest_path, manifest)?;
+            let cmd = UpgradeDepsCommand::from_iter(&[
+                "upgrade-deps",
+                "--manifest-path",
+                manifest_path.to_str().unwrap(),
+                "--dry-run",
+                "3.0.0",
+            ]);
+
+            // when
+            let report = cmd.exec()?;
+
+            // then
+            assert!(report.starts_with("Would update"));
+            assert_eq!(fs::read_to_string(&manifest_path)?, manifest);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn unknown_releases_are_rejected() {
+        assert!(release("2.1.0").is_err());
+        assert!(release("3.0.0-rc4").is_ok());
+    }
+}
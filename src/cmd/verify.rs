@@ -0,0 +1,300 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    build::{self, ExecuteArgs},
+    deploy::load_contract_code,
+    metadata::blake2_hash,
+};
+use crate::{
+    bundle::ContractBundle,
+    crate_metadata::CrateMetadata,
+    rpc::RpcClient,
+    verification_service::{VerificationService, VerificationStatus},
+    workspace::ManifestPath,
+    BuildArtifacts, ExtrinsicOpts,
+};
+use anyhow::Result;
+use codec::Decode;
+use colored::Colorize;
+use impl_serde::serialize as serde_hex;
+use serde_json::json;
+use sp_core::{
+    crypto::{AccountId32, Ss58Codec},
+    hashing::{twox_128, twox_64},
+    H256,
+};
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+/// Verifies that the code of a contract on chain was built from the local sources.
+///
+/// The contract is built locally, unless an artifact is supplied, and the hash of its code is
+/// compared with the one on chain. The build has to be reproducible for the hashes to match,
+/// i.e. use the same toolchain and build settings as the build of the deployed code.
+///
+/// With `--publish` the sources and the bundle are submitted to a source verification service
+/// once the local verification succeeded, see `verification_service` for its protocol.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "verify")]
+pub struct VerifyCommand {
+    #[structopt(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// The address of the contract whose code to verify
+    #[structopt(
+        name = "contract",
+        long,
+        parse(try_from_str = crate::parse_account),
+        required_unless = "code-hash"
+    )]
+    contract: Option<AccountId32>,
+    /// The hash of the code on chain to verify
+    #[structopt(
+        name = "code-hash",
+        long,
+        parse(try_from_str = crate::parse_code_hash),
+        conflicts_with = "contract"
+    )]
+    code_hash: Option<H256>,
+    /// Path to the Wasm or the `<name>.contract` bundle to verify instead of building the
+    /// contract
+    #[structopt(long, parse(from_os_str))]
+    wasm: Option<PathBuf>,
+    /// Submit the sources, the build settings and the bundle to the verification service
+    /// `--service`, and wait for it to reproduce the code
+    #[structopt(long, requires = "service")]
+    publish: bool,
+    /// Url of the source verification service to `--publish` to
+    #[structopt(name = "service", long, parse(try_from_str))]
+    service: Option<url::Url>,
+}
+
+/// The prefix of the information about an alive contract stored by `pallet-contracts`, which
+/// contains the hash of its code.
+#[derive(Debug, Decode)]
+struct AliveContractInfo {
+    _trie_id: Vec<u8>,
+    _storage_size: u32,
+    _pair_count: u32,
+    code_hash: [u8; 32],
+}
+
+/// The information about a contract stored by `pallet-contracts`.
+#[derive(Debug, Decode)]
+enum ContractInfo {
+    Alive(AliveContractInfo),
+    Tombstone,
+}
+
+impl VerifyCommand {
+    pub fn exec(&self) -> Result<String> {
+        let manifest_path = self.extrinsic_opts.manifest_path()?;
+        let artifact = self.local_artifact(&manifest_path)?;
+        let local_hash = blake2_hash(&load_contract_code(&manifest_path, Some(&artifact))?).0;
+        let rpc = RpcClient::new(&self.extrinsic_opts.url()?)?;
+        let (on_chain_hash, source) = match (&self.contract, &self.code_hash) {
+            (Some(contract), _) => (
+                contract_code_hash(&rpc, contract)?,
+                format!("contract {}", contract.to_ss58check()),
+            ),
+            (None, Some(code_hash)) => {
+                let code = stored_code(&rpc, code_hash)?;
+                (blake2_hash(&code).0, "the code stored on chain".to_string())
+            }
+            (None, None) => anyhow::bail!("Either --contract or --code-hash is required"),
+        };
+        if let Some(code_hash) = &self.code_hash {
+            // the stored code is looked up by its hash, so a mismatch means a corrupted chain
+            // state or a broken node
+            if code_hash.0 != on_chain_hash {
+                anyhow::bail!(
+                    "The code stored for {} hashes to {}",
+                    to_hex(&code_hash.0),
+                    to_hex(&on_chain_hash)
+                )
+            }
+        }
+
+        if local_hash != on_chain_hash {
+            anyhow::bail!(
+                "{}\n\t{} {}\n\t{} {}",
+                "Mismatch: the local code differs from the code on chain".bold(),
+                "Local code hash:".bold(),
+                to_hex(&local_hash),
+                "On-chain code hash:".bold(),
+                to_hex(&on_chain_hash)
+            )
+        }
+        let mut out = format!(
+            "{} the local code matches {}\n\t{} {}",
+            "Success:".bright_green().bold(),
+            source,
+            "Code hash:".bold(),
+            to_hex(&local_hash)
+        );
+        if let Some(service) = self.service.as_ref().filter(|_| self.publish) {
+            let status = self.publish(service, &manifest_path, &artifact)?;
+            write!(out, "\n\t{} {}", "Verification service:".bold(), status)?;
+        }
+        Ok(out)
+    }
+
+    /// Submits the contract to the verification `service` and waits for the verification.
+    ///
+    /// Returns the message of the service about the successful verification.
+    fn publish(
+        &self,
+        service: &url::Url,
+        manifest_path: &ManifestPath,
+        artifact: &Path,
+    ) -> Result<String> {
+        if !ContractBundle::is_bundle(artifact) {
+            anyhow::bail!(
+                "Publishing requires the `<name>.contract` bundle, pass it via `--wasm` or omit \
+                `--wasm` to build it"
+            )
+        }
+        let crate_metadata = CrateMetadata::collect(manifest_path)?;
+        let bundle = ContractBundle::load(artifact)?;
+        let service = VerificationService::new(service);
+        let id = service.submit(&crate_metadata, &bundle, self.contract.as_ref())?;
+        match service.wait_for(&id)? {
+            VerificationStatus::Verified { message } => {
+                Ok(message.unwrap_or_else(|| format!("verification {} succeeded", id)))
+            }
+            VerificationStatus::Failed { message } => anyhow::bail!(
+                "The verification {} failed: {}",
+                id,
+                message.unwrap_or_else(|| "no details given".to_string())
+            ),
+            VerificationStatus::Pending => {
+                unreachable!("`wait_for` returns completed verifications")
+            }
+        }
+    }
+
+    /// Returns the path of the supplied artifact, or builds the contract.
+    ///
+    /// Only the Wasm is built, unless the bundle is required to `--publish` the contract.
+    fn local_artifact(&self, manifest_path: &ManifestPath) -> Result<PathBuf> {
+        if let Some(wasm) = &self.wasm {
+            return Ok(wasm.clone());
+        }
+        let build_artifact = if self.publish {
+            BuildArtifacts::BundleOnly
+        } else {
+            BuildArtifacts::CodeOnly
+        };
+        let args = ExecuteArgs {
+            manifest_path: manifest_path.clone(),
+            build_artifact,
+            ..Default::default()
+        };
+        let result = build::execute(args)?;
+        let artifact = match result.metadata_result {
+            Some(metadata_result) => Some(metadata_result.dest_bundle),
+            None => result.dest_wasm,
+        };
+        Ok(artifact.expect("the Wasm is built for `CodeOnly` and `BundleOnly`; qed"))
+    }
+}
+
+/// Returns the hash of the code of the `contract`.
+pub(super) fn contract_code_hash(rpc: &RpcClient, contract: &AccountId32) -> Result<[u8; 32]> {
+    let account: &[u8] = contract.as_ref();
+    let mut key = storage_prefix("ContractInfoOf");
+    key.extend(twox_64(account).iter());
+    key.extend(account);
+    let info = fetch_storage(rpc, &key)?
+        .ok_or_else(|| anyhow::anyhow!("No contract found at {}", contract.to_ss58check()))?;
+    match ContractInfo::decode(&mut &info[..])? {
+        ContractInfo::Alive(info) => Ok(info.code_hash),
+        ContractInfo::Tombstone => anyhow::bail!(
+            "The contract {} was evicted, only its tombstone is left",
+            contract.to_ss58check()
+        ),
+    }
+}
+
+/// Returns the code with the `code_hash` as uploaded to the chain.
+pub(super) fn stored_code(rpc: &RpcClient, code_hash: &H256) -> Result<Vec<u8>> {
+    let mut key = storage_prefix("PristineCode");
+    key.extend(code_hash.as_bytes());
+    let code = fetch_storage(rpc, &key)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "No code with hash {} is stored on chain",
+            to_hex(&code_hash.0)
+        )
+    })?;
+    // the code is stored SCALE encoded
+    Ok(Vec::<u8>::decode(&mut &code[..])?)
+}
+
+/// Returns `true` if code with the `code_hash` was uploaded to the chain.
+pub(super) fn is_code_stored(rpc: &RpcClient, code_hash: &[u8; 32]) -> Result<bool> {
+    let mut key = storage_prefix("PristineCode");
+    key.extend(code_hash);
+    Ok(fetch_storage(rpc, &key)?.is_some())
+}
+
+/// Returns the prefix of the keys of the `pallet-contracts` storage item `name`.
+fn storage_prefix(name: &str) -> Vec<u8> {
+    let mut prefix = twox_128(b"Contracts").to_vec();
+    prefix.extend(twox_128(name.as_bytes()).iter());
+    prefix
+}
+
+/// Fetches the value stored under `key` at the latest block.
+fn fetch_storage(rpc: &RpcClient, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    let value: Option<String> = rpc.request("state_getStorage", json!([to_hex(key)]))?;
+    value
+        .map(|value| serde_hex::from_hex(&value).map_err(|err| anyhow::anyhow!("{}", err)))
+        .transpose()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    serde_hex::to_hex(bytes, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+
+    #[test]
+    fn code_hash_is_decoded_from_contract_info() {
+        // given
+        let mut info = vec![0u8];
+        info.extend(vec![1u8, 2].encode());
+        info.extend(12u32.encode());
+        info.extend(3u32.encode());
+        info.extend([7u8; 32].iter());
+        // remaining fields, e.g. the rent allowance, are ignored
+        info.extend(100u128.encode());
+
+        // when
+        let decoded = ContractInfo::decode(&mut &info[..]).unwrap();
+
+        // then
+        match decoded {
+            ContractInfo::Alive(info) => assert_eq!(info.code_hash, [7u8; 32]),
+            ContractInfo::Tombstone => panic!("the contract must be alive"),
+        }
+    }
+}
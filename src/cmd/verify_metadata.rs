@@ -0,0 +1,515 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    convert_metadata::ConvertMetadataCommand,
+    metadata_schema::MetadataSchemaCommand,
+    solidity_abi::SolidityAbiCommand,
+    storage_diff::{Layout, StorageDiffCommand},
+    trait_definition::contract_of,
+};
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+/// The type definitions whose values cargo-contract knows how to SCALE encode and decode.
+const KNOWN_TYPE_DEFS: [&str; 9] = [
+    "composite",
+    "variant",
+    "sequence",
+    "array",
+    "tuple",
+    "primitive",
+    "compact",
+    "phantom",
+    "bitSequence",
+];
+
+/// The keys which refer to other types in the type definitions, the spec and the storage layout.
+pub(super) const TYPE_REFERENCE_KEYS: [&str; 4] = ["type", "ty", "bitStoreType", "bitOrderType"];
+
+/// Inspect and convert contract metadata.
+#[derive(Debug, StructOpt)]
+pub enum MetadataCommand {
+    /// Check metadata, e.g. received from third parties, for validity
+    #[structopt(name = "verify")]
+    Verify(VerifyMetadataCommand),
+    /// Convert metadata between the V0 and V1 formats
+    #[structopt(name = "convert")]
+    Convert(ConvertMetadataCommand),
+    /// Export the constructors and messages as JSON Schema or OpenRPC document
+    #[structopt(name = "schema")]
+    Schema(MetadataSchemaCommand),
+    /// Export the messages, constructors and events as Solidity ABI, for EVM tools
+    #[structopt(name = "solidity-abi")]
+    SolidityAbi(SolidityAbiCommand),
+    /// Compare the storage layouts of two versions of the contract for upgrade safety
+    #[structopt(name = "storage-diff")]
+    StorageDiff(StorageDiffCommand),
+}
+
+impl MetadataCommand {
+    pub fn exec(&self) -> Result<Option<String>> {
+        match self {
+            MetadataCommand::Verify(verify) => verify.exec().map(Some),
+            MetadataCommand::Convert(convert) => convert.exec(),
+            MetadataCommand::Schema(schema) => schema.exec(),
+            MetadataCommand::SolidityAbi(solidity_abi) => solidity_abi.exec(),
+            MetadataCommand::StorageDiff(storage_diff) => storage_diff.exec(),
+        }
+    }
+}
+
+/// Checks the structure of metadata, the uniqueness of its selectors and the consistency of its
+/// type registry.
+///
+/// Accepts both the `metadata.json` and the `<name>.contract` bundle.
+#[derive(Debug, StructOpt)]
+pub struct VerifyMetadataCommand {
+    /// Path to the metadata
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+}
+
+impl VerifyMetadataCommand {
+    pub fn exec(&self) -> Result<String> {
+        let contents = fs::read(&self.path).context(format!(
+            "Failed to read the metadata {}",
+            self.path.display()
+        ))?;
+        let metadata: Value = serde_json::from_slice(&contents).context(format!(
+            "The metadata {} is no valid JSON",
+            self.path.display()
+        ))?;
+        let summary = verify(&metadata).map_err(|problems| {
+            anyhow::anyhow!(
+                "The metadata {} is invalid:\n  {}",
+                self.path.display(),
+                problems.join("\n  ")
+            )
+        })?;
+        Ok(format!(
+            "The metadata {} is valid: {}",
+            self.path.display(),
+            summary
+        ))
+    }
+}
+
+/// Verifies the `metadata`, returning a summary of its contents or all problems found.
+fn verify(metadata: &Value) -> std::result::Result<String, Vec<String>> {
+    let mut problems = Vec::new();
+    let contract = contract_of(metadata);
+    let metadata = metadata
+        .as_object()
+        .ok_or_else(|| vec!["The metadata is no JSON object".to_string()])?;
+
+    let contract = match contract
+        .as_object()
+        .filter(|contract| contract.contains_key("spec"))
+    {
+        Some(contract) => contract,
+        None => {
+            let versions = metadata
+                .keys()
+                .filter(|key| key.starts_with('V'))
+                .cloned()
+                .collect::<Vec<_>>();
+            return Err(vec![if versions.is_empty() {
+                "The metadata contains no contract spec".to_string()
+            } else {
+                format!(
+                    "Unsupported metadata version {}, expected V1",
+                    versions.join(", ")
+                )
+            }]);
+        }
+    };
+
+    let types = registered_types(contract, &mut problems);
+    let spec = match contract.get("spec").and_then(Value::as_object) {
+        Some(spec) => spec,
+        None => {
+            problems.push("`spec` is no JSON object".to_string());
+            return Err(problems);
+        }
+    };
+
+    let mut counts = Vec::new();
+    for kind in &["constructors", "messages"] {
+        let items = match spec.get(*kind).and_then(Value::as_array) {
+            Some(items) => items,
+            None => {
+                problems.push(format!("`spec.{}` is missing", kind));
+                continue;
+            }
+        };
+        counts.push(format!("{}: {}", kind, items.len()));
+        verify_callables(kind, items, &types, &mut problems);
+    }
+    if let Some(events) = spec.get("events").and_then(Value::as_array) {
+        for (index, event) in events.iter().enumerate() {
+            let context = format!(
+                "event {}",
+                label(event).unwrap_or_else(|| index.to_string())
+            );
+            verify_args(&context, event, &types, &mut problems);
+        }
+    }
+    if let Some(storage) = contract.get("storage") {
+        verify_references("storage", storage, &types, &mut problems);
+    }
+
+    if problems.is_empty() {
+        counts.push(format!("types: {}", types.len()));
+        Ok(counts.join(", "))
+    } else {
+        Err(problems)
+    }
+}
+
+/// Validates freshly generated `metadata` for `cargo contract check`: besides the checks of
+/// [`verify`], no two cells of the storage layout may share a key.
+pub(super) fn validate(metadata: &Value) -> Result<String> {
+    let summary = verify(metadata).map_err(|problems| {
+        anyhow::anyhow!("The metadata is invalid:\n  {}", problems.join("\n  "))
+    })?;
+    let layout = Layout::new(metadata).context("Invalid storage layout")?;
+    let mut cells = HashMap::new();
+    let mut problems = Vec::new();
+    for (path, cell) in &layout.cells {
+        // the fields of different enum variants share their keys
+        if path.contains("::") && !path.ends_with("::discriminant") {
+            continue;
+        }
+        if let Some(other) = cells.insert(cell.key.as_str(), path) {
+            problems.push(format!(
+                "`{}` and `{}` share the storage key {}",
+                other, path, cell.key
+            ));
+        }
+    }
+    if !problems.is_empty() {
+        anyhow::bail!(
+            "The storage layout is invalid:\n  {}",
+            problems.join("\n  ")
+        )
+    }
+    Ok(summary)
+}
+
+/// Returns the ids of the types in the registry, after checking their definitions.
+///
+/// Registries of older ink! versions identify types by their 1-based position, newer ones
+/// contain the `id` of each type.
+fn registered_types(contract: &Map<String, Value>, problems: &mut Vec<String>) -> BTreeSet<u64> {
+    let types = match contract.get("types").and_then(Value::as_array) {
+        Some(types) => types,
+        None => {
+            problems.push("The type registry `types` is missing".to_string());
+            return BTreeSet::new();
+        }
+    };
+    let entries = types
+        .iter()
+        .enumerate()
+        .map(
+            |(index, entry)| match entry.get("id").and_then(Value::as_u64) {
+                Some(id) => (id, entry.get("type").unwrap_or(&Value::Null)),
+                None => (index as u64 + 1, entry),
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let ids = entries.iter().map(|(id, _)| *id).collect::<BTreeSet<_>>();
+    if ids.len() != entries.len() {
+        problems.push("The type registry contains duplicate ids".to_string());
+    }
+    for (id, ty) in &entries {
+        let context = format!("type {}", id);
+        match ty.get("def").and_then(Value::as_object) {
+            Some(def) => {
+                for kind in def.keys() {
+                    if !KNOWN_TYPE_DEFS.contains(&kind.as_str()) {
+                        problems.push(format!("{} has the unknown definition `{}`", context, kind));
+                    }
+                }
+                if let Some(tuple) = def.get("tuple").and_then(Value::as_array) {
+                    for field in tuple {
+                        verify_reference(&context, field, &ids, problems);
+                    }
+                }
+                verify_references(&context, &ty["def"], &ids, problems);
+            }
+            None => problems.push(format!("{} has no definition", context)),
+        }
+        if let Some(params) = ty.get("params").and_then(Value::as_array) {
+            for param in params {
+                // older registries list the ids of the parameters, newer ones name them
+                match param {
+                    Value::Number(_) => verify_reference(&context, param, &ids, problems),
+                    _ => verify_references(&context, param, &ids, problems),
+                }
+            }
+        }
+    }
+    ids
+}
+
+/// Verifies the constructors or messages of the spec.
+fn verify_callables(
+    kind: &str,
+    items: &[Value],
+    types: &BTreeSet<u64>,
+    problems: &mut Vec<String>,
+) {
+    let mut selectors = HashMap::new();
+    for (index, item) in items.iter().enumerate() {
+        let name = label(item).unwrap_or_else(|| {
+            problems.push(format!("{} {} has no name", kind, index));
+            index.to_string()
+        });
+        let context = format!("{} `{}`", kind.trim_end_matches('s'), name);
+        match item.get("selector").and_then(Value::as_str) {
+            Some(selector) if is_selector(selector) => {
+                if let Some(other) = selectors.insert(selector.to_lowercase(), name.clone()) {
+                    problems.push(format!(
+                        "{} has the selector {} of `{}`",
+                        context, selector, other
+                    ));
+                }
+            }
+            Some(selector) => problems.push(format!(
+                "{} has the invalid selector {}, expected 4 hex encoded bytes",
+                context, selector
+            )),
+            None => problems.push(format!("{} has no selector", context)),
+        }
+        verify_args(&context, item, types, problems);
+        if let Some(return_type) = item.get("returnType").filter(|ty| !ty.is_null()) {
+            verify_references(&context, return_type, types, problems);
+        }
+    }
+}
+
+/// Verifies the types of the `args` of a constructor, message or event.
+fn verify_args(context: &str, item: &Value, types: &BTreeSet<u64>, problems: &mut Vec<String>) {
+    match item.get("args").and_then(Value::as_array) {
+        Some(args) => {
+            for arg in args {
+                match arg.get("type") {
+                    Some(ty) => verify_references(context, ty, types, problems),
+                    None => problems.push(format!("{} has an argument without type", context)),
+                }
+            }
+        }
+        None => problems.push(format!("{} has no `args`", context)),
+    }
+}
+
+/// Verifies all type references nested in `value`.
+fn verify_references(
+    context: &str,
+    value: &Value,
+    types: &BTreeSet<u64>,
+    problems: &mut Vec<String>,
+) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                if TYPE_REFERENCE_KEYS.contains(&key.as_str()) && value.is_number() {
+                    verify_reference(context, value, types, problems);
+                } else {
+                    verify_references(context, value, types, problems);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                verify_references(context, value, types, problems);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Verifies that the type reference `id` is registered.
+fn verify_reference(context: &str, id: &Value, types: &BTreeSet<u64>, problems: &mut Vec<String>) {
+    match id.as_u64() {
+        Some(id) if types.contains(&id) => (),
+        _ => problems.push(format!("{} refers to the unknown type {}", context, id)),
+    }
+}
+
+/// Returns the label of a constructor, message or event, which older ink! versions store as
+/// path in `name`.
+pub(crate) fn label(item: &Value) -> Option<String> {
+    if let Some(label) = item.get("label").and_then(Value::as_str) {
+        return Some(label.to_string());
+    }
+    match item.get("name")? {
+        Value::String(name) => Some(name.clone()),
+        Value::Array(segments) => segments
+            .iter()
+            .map(|segment| segment.as_str())
+            .collect::<Option<Vec<_>>>()
+            .map(|segments| segments.join("::")),
+        _ => None,
+    }
+}
+
+/// Returns `true` if `selector` consists of 4 hex encoded bytes.
+fn is_selector(selector: &str) -> bool {
+    let digits = selector.trim_start_matches("0x");
+    digits.len() == 8 && digits.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use serde_json::json;
+
+    fn metadata() -> Value {
+        json!({
+            "metadataVersion": "0.1.0",
+            "spec": {
+                "constructors": [
+                    { "name": ["new"], "selector": "0x9bae9d5e", "args": [
+                        { "name": "init_value", "type": { "type": 1, "displayName": ["bool"] } }
+                    ] }
+                ],
+                "messages": [
+                    { "name": ["flip"], "selector": "0x633aa551", "args": [], "returnType": null },
+                    { "name": ["get"], "selector": "0x2f865bd9", "args": [],
+                      "returnType": { "type": 1, "displayName": ["bool"] } }
+                ],
+                "events": []
+            },
+            "storage": { "struct": { "fields": [
+                { "name": "value", "layout": { "cell": { "key": "0x00", "ty": 1 } } }
+            ] } },
+            "types": [
+                { "def": { "primitive": "bool" } },
+                { "def": { "sequence": { "type": 1 } } }
+            ]
+        })
+    }
+
+    #[test]
+    fn valid_metadata_is_accepted() {
+        assert_eq!(
+            verify(&metadata()),
+            Ok("constructors: 1, messages: 2, types: 2".to_string())
+        );
+    }
+
+    #[test]
+    fn nested_v1_metadata_with_type_ids_is_accepted() {
+        // given
+        let mut contract = metadata();
+        contract["types"] = json!([
+            { "id": 1, "type": { "def": { "primitive": "bool" } } },
+            { "id": 2, "type": { "def": { "tuple": [1, 1] } } }
+        ]);
+
+        // when
+        let res = verify(&json!({ "source": {}, "V1": contract }));
+
+        // then
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn duplicate_selectors_and_dangling_types_are_reported() {
+        // given
+        let mut metadata = metadata();
+        metadata["spec"]["messages"][1]["selector"] = json!("0x633AA551");
+        metadata["spec"]["constructors"][0]["args"][0]["type"]["type"] = json!(7);
+        metadata["types"][1]["def"] = json!({ "unknown": {} });
+
+        // when
+        let problems = verify(&metadata).unwrap_err();
+
+        // then
+        assert_eq!(
+            problems,
+            vec![
+                "type 2 has the unknown definition `unknown`".to_string(),
+                "constructor `new` refers to the unknown type 7".to_string(),
+                "message `get` has the selector 0x633AA551 of `flip`".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_version_is_reported() {
+        let problems = verify(&json!({ "V9": {} })).unwrap_err();
+        assert_eq!(
+            problems,
+            vec!["Unsupported metadata version V9, expected V1".to_string()]
+        );
+    }
+
+    #[test]
+    fn invalid_metadata_file_fails() {
+        with_tmp_dir(|path| {
+            // given
+            let metadata_path = path.join("metadata.json");
+            fs::write(
+                &metadata_path,
+                r#"{ "spec": { "messages": [] }, "types": [] }"#,
+            )?;
+            let cmd =
+                VerifyMetadataCommand::from_iter(&["verify", metadata_path.to_str().unwrap()]);
+
+            // when
+            let res = cmd.exec();
+
+            // then
+            assert!(res
+                .unwrap_err()
+                .to_string()
+                .ends_with("invalid:\n  `spec.constructors` is missing"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn overlapping_storage_cells_are_reported() {
+        // given
+        assert!(validate(&metadata()).is_ok());
+        let mut metadata = metadata();
+        metadata["storage"]["struct"]["fields"] = json!([
+            { "name": "value", "layout": { "cell": { "key": "0x00", "ty": 1 } } },
+            { "name": "other", "layout": { "cell": { "key": "0x00", "ty": 1 } } }
+        ]);
+
+        // when
+        let res = validate(&metadata);
+
+        // then
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "The storage layout is invalid:\n  `other` and `value` share the storage key 0x00"
+        );
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::{crate_metadata::CrateMetadata, workspace::ManifestPath};
+use anyhow::{Context, Result};
+use parity_wasm::elements::{Internal, Module};
+use std::{convert::TryFrom, fs, path::PathBuf};
+use structopt::StructOpt;
+
+/// Prints the WebAssembly text format of a contract.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "wat")]
+pub struct WatCommand {
+    /// Path to the Cargo.toml of the contract
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Print only the function with this export name, name or index, e.g. `call`,
+    /// `my_contract::flip` or `42`.
+    ///
+    /// Names other than the export names are only available if the contract was built with
+    /// `--debug` or `--keep-section name`.
+    #[structopt(long)]
+    function: Option<String>,
+    /// Path to the wasm to print, defaults to `./target/ink/<name>.wasm`
+    #[structopt(parse(from_os_str))]
+    wasm_path: Option<PathBuf>,
+}
+
+impl WatCommand {
+    pub fn exec(&self) -> Result<String> {
+        let wasm_path = match &self.wasm_path {
+            Some(path) => path.clone(),
+            None => {
+                let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+                CrateMetadata::collect(&manifest_path)?.dest_wasm
+            }
+        };
+        let wasm = fs::read(&wasm_path).context(format!(
+            "Failed to read {}, build the contract first",
+            wasm_path.display()
+        ))?;
+        let wat = wasmprinter::print_bytes(&wasm)?;
+
+        let function = match &self.function {
+            Some(function) => function,
+            None => return Ok(wat),
+        };
+        let module: Module = parity_wasm::deserialize_buffer(&wasm)?;
+        let index = function_index(module, function)?;
+        extract_function(&wat, index)
+            .map(ToString::to_string)
+            .ok_or_else(|| anyhow::anyhow!("Function {} not found in the printed wasm", index))
+    }
+}
+
+/// Returns the index of the function with the export name, name or index `function`.
+///
+/// Names match both in their mangled and demangled form, without the hash.
+fn function_index(module: Module, function: &str) -> Result<u32> {
+    if let Ok(index) = function.parse::<u32>() {
+        return Ok(index);
+    }
+    let exported = module.export_section().and_then(|exports| {
+        exports
+            .entries()
+            .iter()
+            .find(|export| export.field() == function)
+            .and_then(|export| match export.internal() {
+                Internal::Function(index) => Some(*index),
+                _ => None,
+            })
+    });
+    if let Some(index) = exported {
+        return Ok(index);
+    }
+
+    let module = module.parse_names().unwrap_or_else(|(_, m)| m);
+    let names = module
+        .names_section()
+        .and_then(|names| names.functions())
+        .map(|functions| functions.names().iter().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let matches = names
+        .iter()
+        .filter(|(_, name)| {
+            name.as_str() == function || format!("{:#}", rustc_demangle::demangle(name)) == function
+        })
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [(index, _)] => Ok(*index),
+        [] if names.is_empty() => anyhow::bail!(
+            "Function {} not found. The wasm contains no names, build the contract with \
+            `--debug` or `--keep-section name` to keep them",
+            function
+        ),
+        [] => anyhow::bail!("Function {} not found", function),
+        _ => anyhow::bail!(
+            "The name {} is ambiguous, use one of the indices {}",
+            function,
+            matches
+                .iter()
+                .map(|(index, _)| index.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Returns the `func` expression of the function at `index` from the text format `wat`.
+///
+/// The functions are identified by the `(;<index>;)` comment following their name.
+fn extract_function(wat: &str, index: u32) -> Option<&str> {
+    let marker = format!("(;{};)", index);
+    let mut search_from = 0;
+    while let Some(offset) = wat[search_from..].find("(func ") {
+        let start = search_from + offset;
+        let header_end = wat[start..].find('\n').map_or(wat.len(), |end| start + end);
+        if wat[start..header_end].contains(&marker) {
+            let end = expression_end(&wat[start..])?;
+            return Some(&wat[start..start + end]);
+        }
+        search_from = start + "(func ".len();
+    }
+    None
+}
+
+/// Returns the length of the parenthesized expression at the start of `wat`.
+///
+/// Parentheses within strings and comments are skipped.
+fn expression_end(wat: &str) -> Option<usize> {
+    let bytes = wat.as_bytes();
+    let mut depth = 0;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'"' => {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != b'"' {
+                    if bytes[pos] == b'\\' {
+                        pos += 1;
+                    }
+                    pos += 1;
+                }
+            }
+            b'(' if bytes.get(pos + 1) == Some(&b';') => {
+                pos += wat[pos..].find(";)")? + 1;
+            }
+            b';' if bytes.get(pos + 1) == Some(&b';') => {
+                pos += wat[pos..].find('\n').unwrap_or(wat.len() - pos);
+            }
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos + 1);
+                }
+            }
+            _ => (),
+        }
+        pos += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    const CONTRACT: &str = r#"
+        (module
+            (import "seal0" "seal_input" (func $seal_input (param i32 i32)))
+            (func $_ZN11my_contract4flip17h0123456789abcdefE (param i32)
+                (call $seal_input (local.get 0) (i32.const 0)))
+            (func $call (export "call") (call $_ZN11my_contract4flip17h0123456789abcdefE (i32.const 1)))
+            (func $deploy (export "deploy"))
+            (memory 1)
+            (data (i32.const 0) "(unbalanced")
+        )"#;
+
+    fn wasm() -> Vec<u8> {
+        wabt::Wat2Wasm::new()
+            .write_debug_names(true)
+            .convert(CONTRACT)
+            .expect("invalid wabt")
+            .as_ref()
+            .to_vec()
+    }
+
+    #[test]
+    fn function_is_resolved_by_export_name_name_and_index() {
+        let module = || parity_wasm::deserialize_buffer(&wasm()).unwrap();
+
+        assert_eq!(function_index(module(), "call").unwrap(), 2);
+        assert_eq!(function_index(module(), "my_contract::flip").unwrap(), 1);
+        assert_eq!(
+            function_index(module(), "_ZN11my_contract4flip17h0123456789abcdefE").unwrap(),
+            1
+        );
+        assert_eq!(function_index(module(), "3").unwrap(), 3);
+        assert!(function_index(module(), "unknown").is_err());
+    }
+
+    #[test]
+    fn single_function_is_printed() {
+        with_tmp_dir(|path| {
+            // given
+            let wasm_path = path.join("contract.wasm");
+            fs::write(&wasm_path, wasm())?;
+            let cmd = WatCommand::from_iter(&[
+                "wat",
+                "--function",
+                "my_contract::flip",
+                wasm_path.to_str().unwrap(),
+            ]);
+
+            // when
+            let wat = cmd.exec()?;
+
+            // then
+            assert!(wat.starts_with("(func $_ZN11my_contract4flip17h0123456789abcdefE (;1;)"));
+            assert!(wat.ends_with(')'));
+            assert!(wat.contains("call $seal_input"));
+            assert!(!wat.contains("export"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn whole_module_is_printed() {
+        with_tmp_dir(|path| {
+            // given
+            let wasm_path = path.join("contract.wasm");
+            fs::write(&wasm_path, wasm())?;
+            let cmd = WatCommand::from_iter(&["wat", wasm_path.to_str().unwrap()]);
+
+            // when
+            let wat = cmd.exec()?;
+
+            // then
+            assert!(wat.starts_with("(module"));
+            assert!(wat.contains("(export \"deploy\""));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn expression_end_skips_strings_and_comments() {
+        let wat = "(func (;0;) (data \"(\") ;; )\n (nop)) (func)";
+        assert_eq!(expression_end(wat), Some(wat.find(" (func)").unwrap()));
+    }
+}
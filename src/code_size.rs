@@ -0,0 +1,142 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The code size budget, i.e. the maximum size of the optimized contract.
+//!
+//! The budget is declared in the `[package.metadata.contract.code-size]` of the contract's
+//! `Cargo.toml`, either as a number of bytes or as the network profile whose chain limits the
+//! size of contracts via `MaxCodeLen`:
+//!
+//! ```toml
+//! [package.metadata.contract.code-size]
+//! max = 65536
+//! # network = "production"
+//! exceeded = "warn"
+//! ```
+
+#[cfg(feature = "extrinsics")]
+use crate::network::NetworkProfile;
+use crate::workspace::ManifestPath;
+use anyhow::Result;
+
+/// The maximum size of the optimized contract.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MaxCodeSize {
+    /// The maximum size in bytes.
+    Bytes(u32),
+    /// The `MaxCodeLen` of the chain of the network profile with this name.
+    Network(String),
+}
+
+impl MaxCodeSize {
+    /// Returns the maximum size in bytes, querying the chain of the network profile if required.
+    pub fn bytes(&self, manifest_path: &ManifestPath) -> Result<u32> {
+        match self {
+            MaxCodeSize::Bytes(bytes) => Ok(*bytes),
+            #[cfg(feature = "extrinsics")]
+            MaxCodeSize::Network(name) => NetworkProfile::load(manifest_path, name)?.max_code_len(),
+            #[cfg(not(feature = "extrinsics"))]
+            MaxCodeSize::Network(name) => anyhow::bail!(
+                "Deriving the code size budget of {} from the network `{}` requires the \
+                `extrinsics` feature",
+                manifest_path.as_ref().display(),
+                name
+            ),
+        }
+    }
+}
+
+/// What to do if the optimized contract exceeds the budget.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BudgetExceeded {
+    /// Fail the build.
+    Fail,
+    /// Print a warning together with the size report.
+    Warn,
+}
+
+impl Default for BudgetExceeded {
+    fn default() -> Self {
+        BudgetExceeded::Fail
+    }
+}
+
+impl std::str::FromStr for BudgetExceeded {
+    type Err = anyhow::Error;
+
+    fn from_str(exceeded: &str) -> Result<Self> {
+        match exceeded {
+            "fail" => Ok(BudgetExceeded::Fail),
+            "warn" => Ok(BudgetExceeded::Warn),
+            _ => anyhow::bail!("Unknown action '{}', expected fail or warn", exceeded),
+        }
+    }
+}
+
+/// The code size budget of a contract.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeSizeBudget {
+    pub max: MaxCodeSize,
+    pub exceeded: BudgetExceeded,
+}
+
+impl CodeSizeBudget {
+    /// Returns the message describing the violation of the budget of `max_size` bytes, if the
+    /// contract of `size` bytes exceeds it.
+    pub fn violation(max_size: u32, size: u64) -> Option<String> {
+        if size <= u64::from(max_size) {
+            return None;
+        }
+        Some(format!(
+            "The optimized contract has {} bytes, which exceeds the code size budget of {} bytes \
+            by {} bytes",
+            size,
+            max_size,
+            size - u64::from(max_size)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn violation_is_reported_only_above_budget() {
+        assert_eq!(CodeSizeBudget::violation(100, 100), None);
+        assert_eq!(
+            CodeSizeBudget::violation(100, 120),
+            Some(
+                "The optimized contract has 120 bytes, which exceeds the code size budget of \
+                100 bytes by 20 bytes"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn exceeded_action_is_parsed() {
+        assert_eq!(
+            "fail".parse::<BudgetExceeded>().unwrap(),
+            BudgetExceeded::Fail
+        );
+        assert_eq!(
+            "warn".parse::<BudgetExceeded>().unwrap(),
+            BudgetExceeded::Warn
+        );
+        assert!("ignore".parse::<BudgetExceeded>().is_err());
+    }
+}
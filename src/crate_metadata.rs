@@ -36,7 +36,11 @@ pub struct CrateMetadata {
     pub documentation: Option<Url>,
     pub homepage: Option<Url>,
     pub user: Option<Map<String, Value>>,
+    /// The toolchain pinned via `toolchain` in `[package.metadata.contract]`, if any.
+    pub toolchain: Option<String>,
     pub target_directory: PathBuf,
+    /// Directory the wasm, metadata and bundle artifacts are written to.
+    pub artifact_directory: PathBuf,
 }
 
 impl CrateMetadata {
@@ -50,6 +54,8 @@ impl CrateMetadata {
 
         let absolute_manifest_path = manifest_path.absolute_directory()?;
         let absolute_workspace_root = metadata.workspace_root.canonicalize()?;
+        // the contracts of a workspace get separate target directories, so that cargo doesn't
+        // serialize their concurrent builds on the lock of a shared one
         if absolute_manifest_path != absolute_workspace_root {
             target_directory = target_directory.join(package_name.clone());
         }
@@ -85,6 +91,7 @@ impl CrateMetadata {
             documentation,
             homepage,
             user,
+            toolchain,
         } = get_cargo_toml_metadata(manifest_path)?;
 
         let crate_metadata = CrateMetadata {
@@ -98,10 +105,63 @@ impl CrateMetadata {
             documentation,
             homepage,
             user,
+            toolchain,
+            artifact_directory: target_directory.clone().into(),
             target_directory: target_directory.into(),
         };
         Ok(crate_metadata)
     }
+
+    /// Writes the artifacts to `artifact_directory` instead of the target directory.
+    pub fn with_artifact_directory(mut self, artifact_directory: PathBuf) -> Self {
+        self.dest_wasm = artifact_directory
+            .join(&self.package_name)
+            .with_extension("wasm");
+        self.artifact_directory = artifact_directory;
+        self
+    }
+
+    /// Path of the record of the contract's section and function sizes of the previous build.
+    pub fn size_record(&self) -> PathBuf {
+        self.target_directory
+            .join(format!("{}.sizes.json", self.package_name))
+    }
+}
+
+/// Returns the names and manifests of all ink! contracts which are members of the workspace of
+/// the manifest at `manifest_path`.
+///
+/// Contracts are recognized by their dependency on `ink_lang` and their `cdylib` target, crates
+/// shared by the contracts, e.g. of trait definitions, are not built as contracts.
+pub fn workspace_contracts(manifest_path: &ManifestPath) -> Result<Vec<(String, ManifestPath)>> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path.as_ref())
+        .no_deps()
+        .exec()
+        .context("Error invoking `cargo metadata`")?;
+    metadata
+        .packages
+        .iter()
+        .filter(|package| metadata.workspace_members.contains(&package.id))
+        .filter(|package| {
+            package
+                .dependencies
+                .iter()
+                .any(|dependency| dependency.name == "ink_lang")
+        })
+        .filter(|package| {
+            package
+                .targets
+                .iter()
+                .any(|target| target.crate_types.iter().any(|ty| ty == "cdylib"))
+        })
+        .map(|package| {
+            Ok((
+                package.name.clone(),
+                ManifestPath::new(&package.manifest_path)?,
+            ))
+        })
+        .collect()
 }
 
 /// Get the result of `cargo metadata`, together with the root package id.
@@ -133,6 +193,7 @@ struct ExtraMetadata {
     documentation: Option<Url>,
     homepage: Option<Url>,
     user: Option<Map<String, Value>>,
+    toolchain: Option<String>,
 }
 
 /// Read extra metadata not available via `cargo metadata` directly from `Cargo.toml`
@@ -166,9 +227,22 @@ fn get_cargo_toml_metadata(manifest_path: &ManifestPath) -> Result<ExtraMetadata
         })
         .transpose()?;
 
+    let toolchain = toml
+        .get("package")
+        .and_then(|v| v.get("metadata"))
+        .and_then(|v| v.get("contract"))
+        .and_then(|v| v.get("toolchain"))
+        .map(|v| {
+            v.as_str()
+                .map(ToString::to_string)
+                .ok_or_else(|| anyhow::anyhow!("toolchain should be a string"))
+        })
+        .transpose()?;
+
     Ok(ExtraMetadata {
         documentation,
         homepage,
         user,
+        toolchain,
     })
 }
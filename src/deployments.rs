@@ -0,0 +1,346 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Records of the deployments of a contract and an address book of known contracts, kept in
+//! `deployments.json` next to the `Cargo.toml` of the contract so that they can be committed:
+//!
+//! ```json
+//! {
+//!   "deployments": [{
+//!     "name": "flipper",
+//!     "network": "production",
+//!     "url": "wss://rpc.example.com/",
+//!     "contract": "5Gr..",
+//!     "codeHash": "0x..",
+//!     "bundle": "target/ink/flipper.contract"
+//!   }],
+//!   "addressBook": {
+//!     "5Fe..": { "name": "erc20", "metadataCid": "bafy.." }
+//!   }
+//! }
+//! ```
+//!
+//! `instantiate` records a deployment for every instantiated contract, the address book is
+//! maintained by hand.
+
+use crate::{bundle::ContractBundle, registry::Registry, workspace::ManifestPath, ExtrinsicOpts};
+use anyhow::{Context, Result};
+use impl_serde::serialize as serde_hex;
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+/// The name of the file holding the records.
+const FILE_NAME: &str = "deployments.json";
+
+/// An instantiated contract.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Deployment {
+    /// The name of the contract, as recorded in its bundle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The network profile the contract was instantiated on, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    /// Websockets url of the node the contract was instantiated with.
+    pub url: Url,
+    /// The SS58 address of the contract.
+    pub contract: String,
+    /// The code hash of the contract.
+    pub code_hash: String,
+    /// Path to the bundle the contract was instantiated from, relative to the contract directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<PathBuf>,
+}
+
+/// A known contract, which is not necessarily instantiated from this project.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AddressBookEntry {
+    /// The name of the contract, under which it is published to registries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The IPFS CID of the metadata or the bundle of the contract.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_cid: Option<String>,
+}
+
+/// The contents of `deployments.json`.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Deployments {
+    #[serde(default)]
+    pub deployments: Vec<Deployment>,
+    /// Known contracts by their SS58 address.
+    #[serde(default)]
+    pub address_book: BTreeMap<String, AddressBookEntry>,
+}
+
+impl Deployments {
+    /// Loads the records of the contract at `manifest_path`, which are empty if there are none.
+    pub fn load(manifest_path: &ManifestPath) -> Result<Self> {
+        let path = Self::path(manifest_path)?;
+        if !path.exists() {
+            return Ok(Default::default());
+        }
+        let contents = fs::read(&path)?;
+        serde_json::from_slice(&contents).context(format!("Invalid {}", path.display()))
+    }
+
+    /// Records the `deployment` of the contract at `manifest_path`.
+    pub fn record(manifest_path: &ManifestPath, deployment: Deployment) -> Result<()> {
+        let mut deployments = Self::load(manifest_path)?;
+        deployments.deployments.push(deployment);
+        let path = Self::path(manifest_path)?;
+        fs::write(&path, serde_json::to_string_pretty(&deployments)?)?;
+        Ok(())
+    }
+
+    /// Returns the names the contract at the SS58 `address` is known under.
+    pub fn names_of(&self, address: &str) -> Vec<&str> {
+        let known_names = self
+            .address_book
+            .get(address)
+            .and_then(|entry| entry.name.as_deref())
+            .into_iter()
+            .chain(
+                self.deployments
+                    .iter()
+                    .filter(|deployment| deployment.contract == address)
+                    .filter_map(|deployment| deployment.name.as_deref()),
+            );
+        let mut names = Vec::new();
+        for name in known_names {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    fn path(manifest_path: &ManifestPath) -> Result<PathBuf> {
+        Ok(manifest_path.absolute_directory()?.join(FILE_NAME))
+    }
+}
+
+/// Sources the metadata of a contract can be resolved from if it isn't present locally.
+pub(crate) struct MetadataSources<'a> {
+    pub manifest_path: &'a ManifestPath,
+    pub registry: Option<&'a Url>,
+    pub ipfs_gateway: Option<&'a Url>,
+}
+
+impl<'a> MetadataSources<'a> {
+    /// Resolves the metadata of the contract at the SS58 `address` with the on-chain `code_hash`.
+    ///
+    /// The sources are tried in order: the bundles of the deployment records, the registry under
+    /// the names the contract is known by, and the IPFS CID of the address book. Only metadata
+    /// of the same code hash is accepted.
+    pub fn resolve(&self, address: &str, code_hash: &[u8; 32]) -> Result<ContractBundle> {
+        let deployments = Deployments::load(self.manifest_path)?;
+        let hex_code_hash = serde_hex::to_hex(code_hash, false);
+        let directory = self.manifest_path.absolute_directory()?;
+
+        for deployment in &deployments.deployments {
+            let bundle = match &deployment.bundle {
+                Some(bundle) if deployment.code_hash == hex_code_hash => directory.join(bundle),
+                _ => continue,
+            };
+            if bundle.exists() {
+                if let Some(bundle) = matching(ContractBundle::load(&bundle), code_hash) {
+                    return Ok(bundle);
+                }
+            }
+        }
+
+        if let Some(registry) = self.registry {
+            let registry = Registry::new(registry);
+            for name in deployments.names_of(address) {
+                let entry = registry
+                    .index(name)?
+                    .into_iter()
+                    .find(|entry| entry.code_hash == hex_code_hash);
+                if let Some(entry) = entry {
+                    let contents = registry.fetch(&entry)?;
+                    let source = PathBuf::from(format!("{}@{}", name, entry.version));
+                    if let Some(bundle) =
+                        matching(ContractBundle::parse(&source, &contents), code_hash)
+                    {
+                        return Ok(bundle);
+                    }
+                }
+            }
+        }
+
+        let cid = deployments
+            .address_book
+            .get(address)
+            .and_then(|entry| entry.metadata_cid.as_ref());
+        if let (Some(gateway), Some(cid)) = (self.ipfs_gateway, cid) {
+            let contents = fetch_from_ipfs(gateway, cid)?;
+            let source = PathBuf::from(format!("ipfs://{}", cid));
+            if let Some(bundle) = matching(ContractBundle::parse(&source, &contents), code_hash) {
+                return Ok(bundle);
+            }
+        }
+
+        anyhow::bail!(
+            "No metadata of the code {} of the contract {} found. Pass the bundle via `--bundle`, \
+            or configure a `registry` or `ipfs-gateway` in the network profile",
+            hex_code_hash,
+            address
+        )
+    }
+}
+
+/// Returns the `bundle` if it describes the code with the `code_hash`.
+fn matching(bundle: Result<ContractBundle>, code_hash: &[u8; 32]) -> Option<ContractBundle> {
+    match bundle {
+        Ok(bundle) if bundle.code_hash().ok().as_ref() == Some(code_hash) => Some(bundle),
+        Ok(_) => None,
+        Err(err) => {
+            log::debug!("Skipping metadata: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Fetches the file with the `cid` via the IPFS HTTP `gateway`.
+fn fetch_from_ipfs(gateway: &Url, cid: &str) -> Result<Vec<u8>> {
+    let url = gateway
+        .join(&format!("ipfs/{}", cid))
+        .context(format!("Invalid IPFS gateway url {}", gateway))?;
+    let mut contents = Vec::new();
+    ureq::get(url.as_str())
+        .call()
+        .map_err(|err| anyhow::anyhow!("Fetching {} from IPFS failed: {}", cid, err))?
+        .into_reader()
+        .read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+/// Records the instantiation of the `contract` with the `code_hash`, if it was instantiated from a
+/// contract project.
+pub(crate) fn record_instantiation(
+    extrinsic_opts: &ExtrinsicOpts,
+    bundle: Option<&ContractBundle>,
+    contract: &AccountId32,
+    code_hash: &[u8; 32],
+) -> Result<()> {
+    let manifest_path = extrinsic_opts.manifest_path()?;
+    if !manifest_path.as_ref().exists() {
+        return Ok(());
+    }
+    let directory = manifest_path.absolute_directory()?;
+    let deployment = Deployment {
+        name: bundle
+            .and_then(ContractBundle::name)
+            .map(ToString::to_string),
+        network: extrinsic_opts.network.clone(),
+        url: extrinsic_opts.url()?,
+        contract: contract.to_ss58check(),
+        code_hash: serde_hex::to_hex(code_hash, false),
+        bundle: bundle.map(|bundle| relative_bundle_path(&directory, bundle.path())),
+    };
+    Deployments::record(&manifest_path, deployment)
+}
+
+/// Returns the path of the `bundle` relative to the contract `directory`, if it is inside it.
+fn relative_bundle_path(directory: &Path, bundle: &Path) -> PathBuf {
+    bundle
+        .canonicalize()
+        .ok()
+        .and_then(|bundle| {
+            directory
+                .canonicalize()
+                .ok()
+                .and_then(|directory| bundle.strip_prefix(directory).ok().map(Path::to_path_buf))
+        })
+        .unwrap_or_else(|| bundle.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+
+    const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+    #[test]
+    fn metadata_is_resolved_from_deployment_records() {
+        with_tmp_dir(|path| {
+            // given
+            let manifest_path = ManifestPath::new(path.join("Cargo.toml"))?;
+            let code_hash = [7u8; 32];
+            let hex_code_hash = serde_hex::to_hex(&code_hash, false);
+            fs::write(
+                path.join("flipper.contract"),
+                format!(r#"{{ "source": {{ "hash": "{}" }} }}"#, hex_code_hash),
+            )?;
+            let deployment = Deployment {
+                name: Some("flipper".to_string()),
+                network: None,
+                url: Url::parse("ws://localhost:9944")?,
+                contract: ALICE.to_string(),
+                code_hash: hex_code_hash,
+                bundle: Some("flipper.contract".into()),
+            };
+            Deployments::record(&manifest_path, deployment.clone())?;
+            let sources = MetadataSources {
+                manifest_path: &manifest_path,
+                registry: None,
+                ipfs_gateway: None,
+            };
+
+            // when
+            let resolved = sources.resolve(ALICE, &code_hash);
+            let other_code = sources.resolve(ALICE, &[8u8; 32]);
+
+            // then
+            assert_eq!(resolved?.code_hash()?, code_hash);
+            assert!(other_code.is_err());
+            assert_eq!(
+                Deployments::load(&manifest_path)?.deployments,
+                vec![deployment]
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn names_are_taken_from_address_book_and_deployments() {
+        let deployments: Deployments = serde_json::from_str(&format!(
+            r#"{{
+                "deployments": [{{
+                    "name": "erc20", "url": "ws://localhost:9944", "contract": "{}", "codeHash": "0x00"
+                }}],
+                "addressBook": {{ "{}": {{ "name": "token" }} }}
+            }}"#,
+            ALICE, ALICE
+        ))
+        .unwrap();
+
+        assert_eq!(deployments.names_of(ALICE), vec!["token", "erc20"]);
+        assert!(deployments.names_of("5F").is_empty());
+    }
+}
@@ -14,18 +14,60 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+mod advisories;
+mod build_cache;
+mod bundle;
 mod cmd;
+mod code_size;
 mod crate_metadata;
+#[cfg(feature = "extrinsics")]
+mod deployments;
+#[cfg(feature = "extrinsics")]
+mod network;
+mod polkavm;
+mod registry;
+#[cfg(feature = "extrinsics")]
+mod remote_signer;
+#[cfg(feature = "extrinsics")]
+mod rpc;
+#[cfg(feature = "extrinsics")]
+mod rpc_fixture;
+#[cfg(feature = "extrinsics")]
+mod secret;
+mod size_report;
+mod stack_height;
+mod toolchain;
 mod util;
 mod validate_wasm;
+mod verifiable;
+#[cfg(feature = "extrinsics")]
+mod verification_service;
+mod wasm_compatibility;
+#[cfg(not(feature = "binaryen-as-dependency"))]
+mod wasm_opt;
 mod workspace;
 
 use self::workspace::ManifestPath;
 
-use crate::cmd::{metadata::MetadataResult, BuildCommand, CheckCommand};
+use crate::cmd::{
+    metadata::MetadataResult, AbiSnapshotCommand, AuditCommand, BindgenCommand, BuildCommand,
+    CheckCommand, DocCommand, ExpandCommand, FuzzCommand, HashCommand, ImportAbiCommand,
+    InitCommand, InspectCommand, LintCommand, MetadataCommand, MigrateCommand, ProptestCommand,
+    PublishCommand, PublishMetadataCommand, PullCommand, ResolveTrapCommand, SelectorCommand,
+    TemplateIndexOpts, TemplatesCommand, TestCommand, UpgradeDepsCommand, WatCommand,
+};
+#[cfg(feature = "extrinsics")]
+use crate::cmd::{
+    ApplyCommand, BenchCommand, BundleCommand, CallCommand, ConsistencyCommand, ForkCommand,
+    KeyCommand, NodeCommand, ReceiptOpts, ReplCommand, ReplayCommand, RunCommand, ScenarioCommand,
+    ServeCommand, TuiCommand, VerifyCommand,
+};
 
 #[cfg(feature = "extrinsics")]
-use sp_core::{crypto::Pair, sr25519, H256};
+use sp_core::{
+    crypto::{AccountId32, Pair, Ss58Codec},
+    sr25519, H256,
+};
 use std::{
     convert::TryFrom,
     fmt::{Display, Formatter, Result as DisplayResult},
@@ -33,7 +75,7 @@ use std::{
     str::FromStr,
 };
 #[cfg(feature = "extrinsics")]
-use subxt::PairSigner;
+use subxt::{DefaultNodeRuntime, PairSigner, Signer};
 
 use anyhow::{Error, Result};
 use colored::Colorize;
@@ -70,33 +112,122 @@ impl std::str::FromStr for HexData {
     }
 }
 
+/// Url of the node to connect to if neither `--url` nor a network profile is supplied.
+#[cfg(feature = "extrinsics")]
+const DEFAULT_URL: &str = "ws://localhost:9944";
+
 /// Arguments required for creating and sending an extrinsic to a substrate node
 #[cfg(feature = "extrinsics")]
-#[derive(Debug, StructOpt)]
+#[derive(Clone, Debug, StructOpt)]
 pub(crate) struct ExtrinsicOpts {
-    /// Websockets url of a substrate node
-    #[structopt(
-        name = "url",
-        long,
-        parse(try_from_str),
-        default_value = "ws://localhost:9944"
-    )]
-    url: url::Url,
-    /// Secret key URI for the account deploying the contract.
-    #[structopt(name = "suri", long, short)]
-    suri: String,
-    /// Password for the secret key
-    #[structopt(name = "password", long, short)]
-    password: Option<String>,
+    /// Path to the Cargo.toml of the contract, defaults to the one in the current directory
+    #[structopt(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Websockets url of a substrate node, defaults to `ws://localhost:9944`.
+    ///
+    /// Overrides the url of the `--network` profile.
+    #[structopt(name = "url", long, parse(try_from_str))]
+    url: Option<url::Url>,
+    /// Network profile from `[package.metadata.contract.networks]` of the `Cargo.toml`.
+    ///
+    /// A profile supplies the url of the node and optionally a `remote-signer` endpoint, which is
+    /// used to sign extrinsics if no secret key URI is supplied.
+    #[structopt(name = "network", long)]
+    network: Option<String>,
+    #[structopt(flatten)]
+    secret: secret::SecretOpts,
 }
 
 #[cfg(feature = "extrinsics")]
 impl ExtrinsicOpts {
-    pub fn signer(&self) -> Result<PairSigner<subxt::DefaultNodeRuntime, sr25519::Pair>> {
-        let pair =
-            sr25519::Pair::from_string(&self.suri, self.password.as_ref().map(String::as_ref))
-                .map_err(|_| anyhow::anyhow!("Secret string error"))?;
-        Ok(PairSigner::new(pair))
+    /// Returns the manifest of the contract supplied via `--manifest-path`, or the one in the
+    /// current directory.
+    pub fn manifest_path(&self) -> Result<ManifestPath> {
+        ManifestPath::try_from(self.manifest_path.as_ref())
+    }
+
+    /// Returns the network profile selected via `--network`, if any.
+    fn network(&self) -> Result<Option<network::NetworkProfile>> {
+        self.network
+            .as_ref()
+            .map(|name| network::NetworkProfile::load(&self.manifest_path()?, name))
+            .transpose()
+    }
+
+    /// Returns the url of the node to connect to.
+    ///
+    /// The url supplied via `--url` takes precedence over the one of the network profile. If the
+    /// RPC traffic is recorded or replayed, this is the url of the local proxy doing so.
+    pub fn url(&self) -> Result<url::Url> {
+        let url = if let Some(url) = &self.url {
+            url.clone()
+        } else if let Some(url) = self.network()?.and_then(|network| network.url) {
+            url
+        } else {
+            url::Url::parse(DEFAULT_URL).expect("the default url is valid")
+        };
+        rpc_fixture::redirect(url)
+    }
+
+    /// Returns the signer for the supplied secret key URI, or the remote signer of the network
+    /// profile if no secret key URI is supplied.
+    ///
+    /// The secret material is zeroized once the key pair has been derived.
+    pub fn signer(&self) -> Result<Box<dyn Signer<DefaultNodeRuntime> + Send + Sync>> {
+        if !self.secret.is_present() {
+            if let Some(endpoint) = self.network()?.and_then(|network| network.remote_signer) {
+                let signer = remote_signer::RemoteSigner::connect(&endpoint)?;
+                return Ok(Box::new(signer));
+            }
+        }
+        let pair: sr25519::Pair = self.secret.pair()?;
+        Ok(Box::new(PairSigner::new(pair)))
+    }
+}
+
+/// Options of the commands dry-running calls.
+#[cfg(feature = "extrinsics")]
+#[derive(Clone, Debug, Default, StructOpt)]
+pub(crate) struct OriginOpts {
+    /// The account to dry-run calls from.
+    ///
+    /// Dry-runs do not require any secret material if this account is supplied. It may differ
+    /// from the account of the secret key URI, which allows simulating calls from accounts you
+    /// don't control, e.g. to check admin-gated messages.
+    #[structopt(name = "origin", long, parse(try_from_str = parse_account))]
+    origin: Option<AccountId32>,
+}
+
+#[cfg(feature = "extrinsics")]
+impl OriginOpts {
+    /// Returns the account supplied via `--origin`, if any.
+    pub fn account(&self) -> Option<&AccountId32> {
+        self.origin.as_ref()
+    }
+
+    /// Returns the account to use as the origin of read-only calls and dry-runs.
+    ///
+    /// The account supplied via `--origin` takes precedence over the account of the secret key
+    /// URI or the remote signer of the `extrinsic_opts`.
+    pub fn origin(&self, extrinsic_opts: &ExtrinsicOpts) -> Result<AccountId32> {
+        if let Some(origin) = &self.origin {
+            return Ok(origin.clone());
+        }
+        if !extrinsic_opts.secret.is_present() {
+            if let Some(endpoint) = extrinsic_opts
+                .network()?
+                .and_then(|network| network.remote_signer)
+            {
+                let signer = remote_signer::RemoteSigner::connect(&endpoint)?;
+                return Ok(signer.account().clone());
+            }
+            anyhow::bail!(
+                "No origin for the call supplied. Either specify the account via `--origin` \
+                or supply a secret key URI"
+            )
+        }
+        let pair = extrinsic_opts.secret.pair()?;
+        Ok(AccountId32::from(pair.public().0))
     }
 }
 
@@ -126,6 +257,30 @@ impl Display for OptimizationPasses {
     }
 }
 
+#[cfg(feature = "binaryen-as-dependency")]
+impl OptimizationPasses {
+    /// Returns the binaryen optimization level, matching the one `wasm-opt` uses for this setting.
+    pub(crate) fn to_passes(&self) -> u32 {
+        match self {
+            OptimizationPasses::Zero => 0,
+            OptimizationPasses::One => 1,
+            OptimizationPasses::Two => 2,
+            OptimizationPasses::Three => 3,
+            OptimizationPasses::Four => 4,
+            OptimizationPasses::S | OptimizationPasses::Z => 2,
+        }
+    }
+
+    /// Returns the binaryen shrink level, matching the one `wasm-opt` uses for this setting.
+    pub(crate) fn to_shrink(&self) -> u32 {
+        match self {
+            OptimizationPasses::S => 1,
+            OptimizationPasses::Z => 2,
+            _ => 0,
+        }
+    }
+}
+
 impl Default for OptimizationPasses {
     fn default() -> OptimizationPasses {
         OptimizationPasses::Three
@@ -180,6 +335,12 @@ pub enum Verbosity {
     Verbose,
 }
 
+impl Default for Verbosity {
+    fn default() -> Self {
+        Verbosity::Default
+    }
+}
+
 impl Verbosity {
     /// Returns `true` if output should be printed (i.e. verbose output is set).
     pub(crate) fn is_verbose(&self) -> bool {
@@ -242,21 +403,53 @@ pub enum BuildArtifacts {
     #[structopt(name = "all")]
     All,
     /// Only the Wasm is created, generation of metadata and a bundled `<name>.contract` file is skipped
-    #[structopt(name = "code-only")]
+    #[structopt(name = "wasm")]
     CodeOnly,
+    /// Only the metadata is generated, the Wasm is not built
+    #[structopt(name = "metadata")]
+    MetadataOnly,
+    /// The Wasm and the bundled `<name>.contract` file are created, the separate metadata is skipped
+    #[structopt(name = "bundle")]
+    BundleOnly,
     CheckOnly,
 }
 
+impl Default for BuildArtifacts {
+    fn default() -> Self {
+        BuildArtifacts::All
+    }
+}
+
 impl BuildArtifacts {
     /// Returns the number of steps required to complete a build artifact.
     /// Used as output on the cli.
     pub fn steps(&self) -> usize {
         match self {
             BuildArtifacts::All => 5,
+            BuildArtifacts::BundleOnly => 4,
             BuildArtifacts::CodeOnly => 3,
             BuildArtifacts::CheckOnly => 2,
+            BuildArtifacts::MetadataOnly => 1,
         }
     }
+
+    /// Returns `true` if the Wasm is built and optimized.
+    pub fn includes_wasm(&self) -> bool {
+        !matches!(
+            self,
+            BuildArtifacts::CheckOnly | BuildArtifacts::MetadataOnly
+        )
+    }
+
+    /// Returns `true` if the `metadata.json` is generated.
+    pub fn includes_metadata(&self) -> bool {
+        matches!(self, BuildArtifacts::All | BuildArtifacts::MetadataOnly)
+    }
+
+    /// Returns `true` if the bundled `<name>.contract` file is generated.
+    pub fn includes_bundle(&self) -> bool {
+        matches!(self, BuildArtifacts::All | BuildArtifacts::BundleOnly)
+    }
 }
 
 impl std::str::FromStr for BuildArtifacts {
@@ -264,12 +457,76 @@ impl std::str::FromStr for BuildArtifacts {
     fn from_str(artifact: &str) -> Result<Self, Self::Err> {
         match artifact {
             "all" => Ok(BuildArtifacts::All),
-            "code-only" => Ok(BuildArtifacts::CodeOnly),
+            "wasm" | "code-only" => Ok(BuildArtifacts::CodeOnly),
+            "metadata" => Ok(BuildArtifacts::MetadataOnly),
+            "bundle" => Ok(BuildArtifacts::BundleOnly),
             _ => Err("Could not parse build artifact".to_string()),
         }
     }
 }
 
+/// The format of the result of a build.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum OutputType {
+    /// Human readable report of the build.
+    HumanReadable,
+    /// Machine readable JSON, e.g. for deploy scripts.
+    Json,
+}
+
+impl Default for OutputType {
+    fn default() -> Self {
+        OutputType::HumanReadable
+    }
+}
+
+impl std::str::FromStr for OutputType {
+    type Err = String;
+    fn from_str(output: &str) -> Result<Self, Self::Err> {
+        match output {
+            "human" => Ok(OutputType::HumanReadable),
+            "json" => Ok(OutputType::Json),
+            _ => Err("Could not parse output type, expected human or json".to_string()),
+        }
+    }
+}
+
+/// The executor a contract is built for.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BuildTarget {
+    /// Wasm, executed by `pallet-contracts`.
+    Wasm,
+    /// PolkaVM blobs for the experimental RISC-V based contracts executor.
+    RiscV,
+}
+
+impl Default for BuildTarget {
+    fn default() -> Self {
+        BuildTarget::Wasm
+    }
+}
+
+impl BuildTarget {
+    /// Returns the rustc target the contract is compiled to.
+    pub fn rustc_target(&self) -> &'static str {
+        match self {
+            BuildTarget::Wasm => "wasm32-unknown-unknown",
+            BuildTarget::RiscV => polkavm::RUSTC_TARGET,
+        }
+    }
+}
+
+impl std::str::FromStr for BuildTarget {
+    type Err = String;
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        match target {
+            "wasm" => Ok(BuildTarget::Wasm),
+            "riscv" => Ok(BuildTarget::RiscV),
+            _ => Err("Could not parse build target, expected wasm or riscv".to_string()),
+        }
+    }
+}
+
 /// Result of the metadata generation process.
 pub struct BuildResult {
     /// Path to the resulting Wasm file.
@@ -284,6 +541,12 @@ pub struct BuildResult {
     pub build_artifact: BuildArtifacts,
     /// The verbosity flags.
     pub verbosity: Verbosity,
+    /// If requested the breakdown of the contract size.
+    pub size_report: Option<size_report::SizeReport>,
+    /// The size changes since the previous build, if any.
+    pub size_changes: Option<size_report::SizeDiff>,
+    /// The format the result is printed in.
+    pub output_type: OutputType,
 }
 
 /// Result of the optimization process.
@@ -294,20 +557,39 @@ pub struct OptimizationResult {
     pub original_size: f64,
     /// The Wasm size after optimizations have been applied.
     pub optimized_size: f64,
+    /// The number of optimization passes passed to wasm-opt.
+    pub optimization_passes: OptimizationPasses,
+    /// Custom passes run by wasm-opt in addition to the optimization passes.
+    pub custom_passes: Vec<String>,
+    /// Whether the names were preserved by the optimizations.
+    pub debug_info: bool,
+    /// The version of the optimizer, e.g. the output of `wasm-opt --version`.
+    pub optimizer_version: String,
+    /// The estimated Wasm size on-chain, after `pallet-contracts` instrumented it.
+    pub instrumented_size: Option<f64>,
+}
+
+impl OptimizationResult {
+    /// Returns the wasm-opt arguments used for the optimization.
+    pub fn display_settings(&self) -> String {
+        let mut settings = format!("-O{}", self.optimization_passes);
+        for pass in &self.custom_passes {
+            settings.push_str(&format!(" --{}", pass));
+        }
+        if self.debug_info {
+            settings.push_str(" -g");
+        }
+        settings
+    }
 }
 
 impl BuildResult {
     pub fn display(&self) -> String {
-        let optimization = self.display_optimization();
-        let size_diff = format!(
-            "\nOriginal wasm size: {}, Optimized: {}\n\n",
-            format!("{:.1}K", optimization.0).bold(),
-            format!("{:.1}K", optimization.1).bold(),
-        );
-        debug_assert!(
-            optimization.1 > 0.0,
-            "optimized file size must be greater 0"
-        );
+        let size_diff = if self.build_artifact.includes_wasm() {
+            self.display_sizes()
+        } else {
+            "\n".to_string()
+        };
 
         if self.build_artifact == BuildArtifacts::CodeOnly {
             let out = format!(
@@ -328,7 +610,11 @@ impl BuildResult {
             size_diff,
             self.target_directory.display().to_string().bold(),
         );
-        if let Some(metadata_result) = self.metadata_result.as_ref() {
+        if let Some(metadata_result) = self
+            .metadata_result
+            .as_ref()
+            .filter(|_| self.build_artifact.includes_bundle())
+        {
             let bundle = format!(
                 "  - {} (code + metadata)\n",
                 util::base_name(&metadata_result.dest_bundle).bold()
@@ -342,7 +628,11 @@ impl BuildResult {
             );
             out.push_str(&wasm);
         }
-        if let Some(metadata_result) = self.metadata_result.as_ref() {
+        if let Some(metadata_result) = self
+            .metadata_result
+            .as_ref()
+            .filter(|_| self.build_artifact.includes_metadata())
+        {
             let metadata = format!(
                 "  - {} (the contract's metadata)",
                 util::base_name(&metadata_result.dest_metadata).bold()
@@ -352,15 +642,160 @@ impl BuildResult {
         out
     }
 
-    /// Returns a tuple of `(original_size, optimized_size)`.
+    /// Returns the artifact paths, hashes and sizes of the build as JSON.
+    ///
+    /// The hashes are the hex encoded BLAKE2 256-bit hashes of the optimized Wasm, which is the
+    /// on-chain code hash, and of the metadata file.
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        let hash = |path: &PathBuf| -> Result<String> {
+            let content = std::fs::read(path)
+                .map_err(|err| anyhow::anyhow!("Reading {} failed: {}", path.display(), err))?;
+            let hash = cmd::metadata::blake2_hash(&content)
+                .0
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>();
+            Ok(format!("0x{}", hash))
+        };
+        let code_hash = self.dest_wasm.as_ref().map(hash).transpose()?;
+        let metadata_result = self.metadata_result.as_ref();
+        let dest_metadata = metadata_result
+            .filter(|_| self.build_artifact.includes_metadata())
+            .map(|m| &m.dest_metadata);
+        let dest_bundle = metadata_result
+            .filter(|_| self.build_artifact.includes_bundle())
+            .map(|m| &m.dest_bundle);
+        let metadata_hash = dest_metadata.map(hash).transpose()?;
+        let (original_size, optimized_size, instrumented_size) = match &self.optimization_result {
+            Some(optimization) => (
+                Some(optimization.original_size),
+                Some(optimization.optimized_size),
+                optimization.instrumented_size,
+            ),
+            None => (None, None, None),
+        };
+        Ok(serde_json::json!({
+            "artifact_directory": self.target_directory,
+            "dest_wasm": self.dest_wasm,
+            "dest_metadata": dest_metadata,
+            "dest_bundle": dest_bundle,
+            "code_hash": code_hash,
+            "metadata_hash": metadata_hash,
+            "original_size_kb": original_size,
+            "optimized_size_kb": optimized_size,
+            "instrumented_size_kb": instrumented_size,
+        }))
+    }
+
+    /// Returns the original and optimized size of the Wasm, preceded by the size report and the
+    /// size changes if any.
+    fn display_sizes(&self) -> String {
+        let optimization = self.display_optimization();
+        let instrumented = match self
+            .optimization_result
+            .as_ref()
+            .and_then(|optimization| optimization.instrumented_size)
+        {
+            Some(size) => format!(", Instrumented: {}", format!("{:.1}K", size).bold()),
+            None => String::new(),
+        };
+        let size_diff = format!(
+            "\nOriginal wasm size: {}, Optimized: {}{} (wasm-opt {})\n\n",
+            format!("{:.1}K", optimization.0).bold(),
+            format!("{:.1}K", optimization.1).bold(),
+            instrumented,
+            optimization.2.bold(),
+        );
+        debug_assert!(
+            optimization.1 > 0.0,
+            "optimized file size must be greater 0"
+        );
+
+        let size_diff = match &self.size_changes {
+            Some(changes) => format!("{}{}", changes.display(), size_diff),
+            None => size_diff,
+        };
+        match &self.size_report {
+            Some(report) => format!("{}\n{}", report.display(), size_diff),
+            None => size_diff,
+        }
+    }
+
+    /// Returns a tuple of `(original_size, optimized_size, optimization_settings)`.
     ///
     /// Panics if no optimization result is available.
-    fn display_optimization(&self) -> (f64, f64) {
+    fn display_optimization(&self) -> (f64, f64, String) {
         let optimization = self
             .optimization_result
             .as_ref()
             .expect("optimization result must exist");
-        (optimization.original_size, optimization.optimized_size)
+        (
+            optimization.original_size,
+            optimization.optimized_size,
+            optimization.display_settings(),
+        )
+    }
+}
+
+/// Result of building all contracts of a workspace.
+pub struct WorkspaceBuildResult {
+    /// The name of each contract together with the result of its build.
+    pub contracts: Vec<(String, BuildResult)>,
+    /// The verbosity flags.
+    pub verbosity: Verbosity,
+    /// The format the result is printed in.
+    pub output_type: OutputType,
+}
+
+impl WorkspaceBuildResult {
+    /// Returns the results of the builds as JSON, each with the name of its contract.
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        self.contracts
+            .iter()
+            .map(|(name, result)| {
+                let mut json = result.to_json()?;
+                json["name"] = name.as_str().into();
+                Ok(json)
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Into::into)
+    }
+
+    pub fn display(&self) -> String {
+        let name_width = self
+            .contracts
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(std::iter::once("Contract".len()))
+            .max()
+            .unwrap_or_default();
+        let mut out = format!(
+            "\nBuilt {} contracts:\n\n  {:name_width$}  {:>10}  {:>10}  {}\n",
+            self.contracts.len(),
+            "Contract".bold(),
+            "Original".bold(),
+            "Optimized".bold(),
+            "Artifacts".bold(),
+            name_width = name_width
+        );
+        for (name, result) in &self.contracts {
+            let (original, optimized) = match &result.optimization_result {
+                Some(optimization) => (
+                    format!("{:.1}K", optimization.original_size),
+                    format!("{:.1}K", optimization.optimized_size),
+                ),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            out.push_str(&format!(
+                "  {:name_width$}  {:>10}  {:>10}  {}\n",
+                name,
+                original,
+                optimized,
+                result.target_directory.display(),
+                name_width = name_width
+            ));
+        }
+        out
     }
 }
 
@@ -374,7 +809,49 @@ enum Command {
         /// The optional target directory for the contract project
         #[structopt(short, long, parse(from_os_str))]
         target_dir: Option<PathBuf>,
+        /// The template of the contract, a bundled template (flipper, erc20, erc721, multisig,
+        /// dns) or a community template of the template index; defaults to flipper
+        #[structopt(long)]
+        template: Option<String>,
+        #[structopt(flatten)]
+        template_index: TemplateIndexOpts,
+        /// Generate the contract from the template in this git repository instead
+        #[structopt(long, value_name = "url", conflicts_with = "template")]
+        template_git: Option<String>,
+        /// The tag of the template repository to check out, defaults to the default branch
+        #[structopt(long, requires = "template-git")]
+        tag: Option<String>,
+        /// Value of a template variable, e.g. `--var author="Jane Doe"`. Variables of the
+        /// template without a value are prompted for
+        #[structopt(long = "var", value_name = "variable=value", number_of_values = 1)]
+        vars: Vec<cmd::new::TemplateVariable>,
+        /// Use the defaults of the template variables without a value instead of prompting
+        #[structopt(long)]
+        no_prompt: bool,
+        /// Generate a cargo workspace `<name>` with a crate per contract of `--contracts` and a
+        /// crate of shared traits, the first contract is wired to call the others
+        #[structopt(
+            long,
+            requires = "contracts",
+            conflicts_with_all = &["template", "template-git"]
+        )]
+        workspace: bool,
+        /// The contracts of the workspace, separated by commas
+        #[structopt(long, use_delimiter = true, requires = "workspace")]
+        contracts: Vec<String>,
+        /// Generate an `#[ink::trait_definition]` crate with a stub implementation of the trait
+        /// from this trait spec file (`.toml`) or the metadata of a contract instead
+        #[structopt(
+            long,
+            value_name = "path",
+            parse(from_os_str),
+            conflicts_with_all = &["template", "template-git", "workspace"]
+        )]
+        trait_from: Option<PathBuf>,
     },
+    /// Convert the crate into an ink! contract, keeping its existing code
+    #[structopt(name = "init")]
+    Init(InitCommand),
     /// Compiles the contract, generates metadata, bundles both together in a `<name>.contract` file
     #[structopt(name = "build")]
     Build(BuildCommand),
@@ -384,16 +861,82 @@ enum Command {
     /// Check that the code builds as Wasm; does not output any `<name>.contract` artifact to the `target/` directory
     #[structopt(name = "check")]
     Check(CheckCommand),
-    /// Test the smart contract off-chain
+    /// Test the smart contract off-chain, or end-to-end against an ephemeral node with `--e2e`
     #[structopt(name = "test")]
-    Test {},
+    Test(TestCommand),
+    /// Print the WebAssembly text format of the contract, or of one of its functions
+    #[structopt(name = "wat")]
+    Wat(WatCommand),
+    /// Print the code generated by the ink! macros, or of one of its items
+    #[structopt(name = "expand")]
+    Expand(ExpandCommand),
+    /// Resolve a trap of the contract to the Rust function and its source location
+    #[structopt(name = "resolve-trap")]
+    ResolveTrap(ResolveTrapCommand),
+    /// Print the constructors and messages of the contract with their selectors and arguments
+    #[structopt(name = "inspect")]
+    Inspect(InspectCommand),
+    /// Generate a typed TypeScript or Rust client of the contract from its metadata
+    #[structopt(name = "bindgen")]
+    Bindgen(BindgenCommand),
+    /// Generate an `#[ink::trait_definition]` crate with a call wrapper from a Solidity ABI
+    #[structopt(name = "import-abi")]
+    ImportAbi(ImportAbiCommand),
+    /// Generate Markdown or HTML documentation of the contract from its metadata
+    #[structopt(name = "doc")]
+    Doc(DocCommand),
+    /// Generate a proptest harness fuzzing the messages of the contract against a dev node
+    #[structopt(name = "fuzz")]
+    Fuzz(FuzzCommand),
+    /// Scaffold proptest strategies of the argument types of the messages and property tests
+    #[structopt(name = "proptest")]
+    Proptest(ProptestCommand),
+    /// Compute the selector of a message or constructor
+    #[structopt(name = "selector")]
+    Selector(SelectorCommand),
+    /// Compute the code hash of the contract offline, as the chain would when uploading it
+    #[structopt(name = "hash")]
+    Hash(HashCommand),
+    /// Store a normalized snapshot of the ABI of the contract or check it against the snapshot
+    #[structopt(name = "abi-snapshot")]
+    AbiSnapshot(AbiSnapshotCommand),
+    /// Scan the Wasm of the contract for red flags
+    #[structopt(name = "audit")]
+    Audit(AuditCommand),
+    /// Run the ink! lints on the sources of the contract
+    #[structopt(name = "lint")]
+    Lint(LintCommand),
+    /// List and search the bundled and community contract templates
+    #[structopt(name = "templates")]
+    Templates(TemplatesCommand),
+    /// Rewrite the dependencies of the contract to another ink! release
+    #[structopt(name = "upgrade-deps")]
+    UpgradeDeps(UpgradeDepsCommand),
+    /// Rewrite the sources of the contract for the breaking syntax changes of ink! releases
+    #[structopt(name = "migrate")]
+    Migrate(MigrateCommand),
+    /// Verify and convert contract metadata
+    #[structopt(name = "metadata")]
+    Metadata(MetadataCommand),
+    /// Publish the metadata of the contract to IPFS
+    #[structopt(name = "publish-metadata")]
+    PublishMetadata(PublishMetadataCommand),
+    /// Publish the `<name>.contract` bundle of the contract to a registry
+    #[structopt(name = "publish")]
+    Publish(PublishCommand),
+    /// Pull a `<name>.contract` bundle from a registry
+    #[structopt(name = "pull")]
+    Pull(PullCommand),
     /// Upload the smart contract code to the chain
     #[cfg(feature = "extrinsics")]
     #[structopt(name = "deploy")]
     Deploy {
         #[structopt(flatten)]
         extrinsic_opts: ExtrinsicOpts,
-        /// Path to wasm contract code, defaults to `./target/ink/<name>.wasm`
+        #[structopt(flatten)]
+        receipt_opts: ReceiptOpts,
+        /// Path to wasm contract code or the `<name>.contract` bundle, defaults to
+        /// `./target/ink/<name>.wasm`
         #[structopt(parse(from_os_str))]
         wasm_path: Option<PathBuf>,
     },
@@ -403,19 +946,90 @@ enum Command {
     Instantiate {
         #[structopt(flatten)]
         extrinsic_opts: ExtrinsicOpts,
+        #[structopt(flatten)]
+        receipt_opts: ReceiptOpts,
         /// Transfers an initial balance to the instantiated contract
         #[structopt(name = "endowment", long, default_value = "0")]
         endowment: u128,
         /// Maximum amount of gas to be used for this command
         #[structopt(name = "gas", long, default_value = "500000000")]
         gas_limit: u64,
-        /// The hash of the smart contract code already uploaded to the chain
-        #[structopt(long, parse(try_from_str = parse_code_hash))]
-        code_hash: H256,
-        /// Hex encoded data to call a contract constructor
-        #[structopt(long)]
+        /// The hash of the smart contract code already uploaded to the chain, defaults to the
+        /// code hash of the `--bundle`
+        #[structopt(long, parse(try_from_str = parse_code_hash), required_unless = "bundle")]
+        code_hash: Option<H256>,
+        /// Path to the `<name>.contract` bundle of the contract
+        #[structopt(long, parse(from_os_str))]
+        bundle: Option<PathBuf>,
+        /// Name of the constructor to call, its selector is taken from the `--bundle`
+        #[structopt(long, requires = "bundle")]
+        constructor: Option<String>,
+        /// Hex encoded data to call a contract constructor.
+        ///
+        /// If a `--constructor` is supplied, only its SCALE encoded arguments.
+        #[structopt(long, default_value = "")]
         data: HexData,
     },
+    /// Call a deployed smart contract, or dry-run the call via RPC
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "call")]
+    Call(CallCommand),
+    /// Inspect and generate keys of contract deployers
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "key")]
+    Key(KeyCommand),
+    /// Sign and verify contract bundles
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "bundle")]
+    Bundle(BundleCommand),
+    /// Verify that the code of a deployed contract was built from the local sources
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "verify")]
+    Verify(VerifyCommand),
+    /// Check that the same code of a contract is live on all networks it is deployed to
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "consistency")]
+    Consistency(ConsistencyCommand),
+    /// Start a local web dashboard to dry-run messages of deployed contracts and watch their events
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "serve")]
+    Serve(ServeCommand),
+    /// Serve a read-only fork of a live chain at a block, to dry-run calls against its state
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "fork")]
+    Fork(ForkCommand),
+    /// Replay recorded calls as dry-runs at an old block and now, reporting changed results and gas
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "replay")]
+    Replay(ReplayCommand),
+    /// Browse, dry-run and submit the messages of a deployed contract in an interactive terminal UI
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "tui")]
+    Tui(TuiCommand),
+    /// Start an interactive session with a deployed contract to dry-run its messages and submit calls
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "repl")]
+    Repl(ReplCommand),
+    /// Start, stop and inspect a local development node of the project
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "node")]
+    Node(NodeCommand),
+    /// Run a deployment script, e.g. `deploy.rhai`, building, deploying and calling contracts
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "run")]
+    Run(RunCommand),
+    /// Apply a declarative deployment manifest, e.g. `deploy.yaml`, after printing its plan
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "apply")]
+    Apply(ApplyCommand),
+    /// Run scenario files of steps and expectations, e.g. `erc20.scenario`, against a dev node
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "scenario")]
+    Scenario(ScenarioCommand),
+    /// Benchmark the gas of messages of a deployed contract listed in e.g. `bench.yaml`
+    #[cfg(feature = "extrinsics")]
+    #[structopt(name = "bench")]
+    Bench(BenchCommand),
 }
 
 #[cfg(feature = "extrinsics")]
@@ -429,6 +1043,12 @@ fn parse_code_hash(input: &str) -> Result<H256> {
     Ok(H256(arr))
 }
 
+#[cfg(feature = "extrinsics")]
+fn parse_account(input: &str) -> Result<AccountId32> {
+    AccountId32::from_ss58check(input)
+        .map_err(|err| anyhow::anyhow!("Invalid SS58 address '{}': {:?}", input, err))
+}
+
 fn main() {
     env_logger::init();
 
@@ -452,10 +1072,76 @@ fn main() {
 
 fn exec(cmd: Command) -> Result<Option<String>> {
     match &cmd {
-        Command::New { name, target_dir } => cmd::new::execute(name, target_dir.as_ref()),
+        Command::New {
+            name,
+            target_dir,
+            template,
+            template_index,
+            template_git,
+            tag,
+            vars,
+            no_prompt,
+            workspace,
+            contracts,
+            trait_from,
+        } => {
+            let variables = cmd::new::TemplateVariables::new(vars, !no_prompt)?;
+            if *workspace {
+                return cmd::new::execute_workspace(
+                    name,
+                    target_dir.as_ref(),
+                    contracts,
+                    &variables,
+                );
+            }
+            if let Some(from) = trait_from {
+                return cmd::trait_definition::execute(name, target_dir.as_ref(), from, &variables);
+            }
+            match (template, template_git) {
+                (_, Some(repository)) => cmd::new::execute_from_git(
+                    name,
+                    target_dir.as_ref(),
+                    repository,
+                    tag.as_deref(),
+                    &variables,
+                ),
+                (Some(template), None) => cmd::new::execute_named(
+                    name,
+                    target_dir.as_ref(),
+                    template,
+                    template_index,
+                    &variables,
+                ),
+                (None, None) => cmd::new::execute_with_template(
+                    name,
+                    target_dir.as_ref(),
+                    Default::default(),
+                    &variables,
+                ),
+            }
+        }
+        Command::Init(init) => init.exec().map(Some),
+        Command::Build(build) if build.is_verifiable_build() => {
+            build.exec_verifiable()?;
+            Ok(None)
+        }
+        Command::Build(build) if build.is_workspace_build() => {
+            let result = build.exec_workspace()?;
+            if result.output_type == OutputType::Json {
+                println!("{}", serde_json::to_string_pretty(&result.to_json()?)?);
+                Ok(None)
+            } else if result.verbosity.is_verbose() {
+                Ok(Some(result.display()))
+            } else {
+                Ok(None)
+            }
+        }
         Command::Build(build) => {
             let result = build.exec()?;
-            if result.verbosity.is_verbose() {
+            if result.output_type == OutputType::Json {
+                println!("{}", serde_json::to_string_pretty(&result.to_json()?)?);
+                Ok(None)
+            } else if result.verbosity.is_verbose() {
                 Ok(Some(result.display()))
             } else {
                 Ok(None)
@@ -478,31 +1164,119 @@ fn exec(cmd: Command) -> Result<Option<String>> {
         Command::GenerateMetadata {} => Err(anyhow::anyhow!(
             "Command deprecated, use `cargo contract build` instead"
         )),
-        Command::Test {} => Err(anyhow::anyhow!("Command unimplemented")),
+        Command::Test(test) => test.exec().map(Some),
+        Command::Wat(wat) => {
+            // printed as is, so that it can be piped into other tools
+            println!("{}", wat.exec()?);
+            Ok(None)
+        }
+        Command::Expand(expand) => {
+            // printed as is, so that it can be piped into other tools
+            println!("{}", expand.exec()?);
+            Ok(None)
+        }
+        Command::ResolveTrap(resolve_trap) => resolve_trap.exec().map(Some),
+        Command::Inspect(inspect) => inspect.exec().map(Some),
+        Command::Bindgen(bindgen) => bindgen.exec().map(Some),
+        Command::ImportAbi(import_abi) => import_abi.exec(),
+        Command::Doc(doc) => doc.exec().map(Some),
+        Command::Fuzz(fuzz) => fuzz.exec().map(Some),
+        Command::Proptest(proptest) => proptest.exec().map(Some),
+        Command::Selector(selector) => selector.exec().map(Some),
+        Command::Hash(hash) => hash.exec().map(Some),
+        Command::AbiSnapshot(abi_snapshot) => abi_snapshot.exec().map(Some),
+        Command::Audit(audit) => audit.exec().map(Some),
+        Command::Lint(lint) => lint.exec().map(Some),
+        Command::Templates(templates) => templates.exec().map(Some),
+        Command::UpgradeDeps(upgrade) => upgrade.exec().map(Some),
+        Command::Migrate(migrate) => migrate.exec().map(Some),
+        Command::Metadata(metadata) => metadata.exec(),
+        Command::PublishMetadata(publish) => publish.exec().map(Some),
+        Command::Publish(publish) => publish.exec().map(Some),
+        Command::Pull(pull) => pull.exec().map(Some),
         #[cfg(feature = "extrinsics")]
         Command::Deploy {
             extrinsic_opts,
+            receipt_opts,
             wasm_path,
         } => {
-            let code_hash = cmd::execute_deploy(extrinsic_opts, wasm_path.as_ref())?;
+            let code_hash = cmd::execute_deploy(extrinsic_opts, receipt_opts, wasm_path.as_ref())?;
             Ok(Some(format!("Code hash: {:?}", code_hash)))
         }
         #[cfg(feature = "extrinsics")]
         Command::Instantiate {
             extrinsic_opts,
+            receipt_opts,
             endowment,
             code_hash,
+            bundle,
+            constructor,
             gas_limit,
             data,
         } => {
+            let bundle = bundle
+                .as_ref()
+                .map(|path| bundle::ContractBundle::load(path))
+                .transpose()?;
+            let code_hash = match (code_hash, &bundle) {
+                (Some(code_hash), _) => *code_hash,
+                (None, Some(bundle)) => H256(bundle.code_hash()?),
+                (None, None) => anyhow::bail!("Either --code-hash or --bundle is required"),
+            };
+            let data = match (constructor, &bundle) {
+                (Some(constructor), Some(bundle)) => {
+                    let mut selector =
+                        bundle.selector(bundle::SpecItem::Constructor, constructor)?;
+                    selector.extend(&data.0);
+                    HexData(selector)
+                }
+                _ => data.clone(),
+            };
             let contract_account = cmd::execute_instantiate(
                 extrinsic_opts,
+                receipt_opts,
                 *endowment,
                 *gas_limit,
-                *code_hash,
-                data.clone(),
+                code_hash,
+                data,
+            )?;
+            deployments::record_instantiation(
+                extrinsic_opts,
+                bundle.as_ref(),
+                &contract_account,
+                &code_hash.0,
             )?;
             Ok(Some(format!("Contract account: {:?}", contract_account)))
         }
+        #[cfg(feature = "extrinsics")]
+        Command::Call(call) => call.exec().map(Some),
+        #[cfg(feature = "extrinsics")]
+        Command::Key(key) => key.exec().map(Some),
+        #[cfg(feature = "extrinsics")]
+        Command::Bundle(bundle) => bundle.exec().map(Some),
+        #[cfg(feature = "extrinsics")]
+        Command::Verify(verify) => verify.exec().map(Some),
+        #[cfg(feature = "extrinsics")]
+        Command::Consistency(consistency) => consistency.exec().map(Some),
+        #[cfg(feature = "extrinsics")]
+        Command::Serve(serve) => serve.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Fork(fork) => fork.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Replay(replay) => replay.exec().map(Some),
+        #[cfg(feature = "extrinsics")]
+        Command::Tui(tui) => tui.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Repl(repl) => repl.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Node(node) => node.exec(),
+        #[cfg(feature = "extrinsics")]
+        Command::Run(run) => run.exec().map(Some),
+        #[cfg(feature = "extrinsics")]
+        Command::Apply(apply) => apply.exec().map(Some),
+        #[cfg(feature = "extrinsics")]
+        Command::Scenario(scenario) => scenario.exec().map(Some),
+        #[cfg(feature = "extrinsics")]
+        Command::Bench(bench) => bench.exec().map(Some),
     }
 }
@@ -0,0 +1,154 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use subxt::{ClientBuilder, DefaultNodeRuntime};
+use url::Url;
+
+use crate::workspace::{Manifest, ManifestPath};
+
+/// Settings for a network the contract is deployed to.
+///
+/// Profiles are defined in the `Cargo.toml` of the contract, e.g.
+///
+/// ```toml
+/// [package.metadata.contract.networks.production]
+/// url = "wss://rpc.example.com"
+/// remote-signer = "https://signer.example.com"
+/// registry = "https://registry.example.com"
+/// ipfs-gateway = "https://ipfs.io"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct NetworkProfile {
+    /// Websockets url of a node of the network.
+    pub url: Option<Url>,
+    /// Endpoint of a remote signing service holding the keys for this network.
+    pub remote_signer: Option<Url>,
+    /// Registry to resolve the metadata of contracts from, see `registry`.
+    pub registry: Option<Url>,
+    /// IPFS HTTP gateway to fetch the metadata of contracts in the address book from.
+    pub ipfs_gateway: Option<Url>,
+}
+
+impl NetworkProfile {
+    /// Loads the profile `name` from the manifest at `manifest_path`.
+    pub fn load(manifest_path: &ManifestPath, name: &str) -> Result<Self> {
+        let manifest = Manifest::new(manifest_path.clone())?;
+        let profile = manifest.get_network_profile(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No network profile `{}` found in `[package.metadata.contract.networks]` of {}",
+                name,
+                manifest_path.as_ref().display()
+            )
+        })?;
+        profile
+            .clone()
+            .try_into()
+            .context(format!("Invalid network profile `{}`", name))
+    }
+
+    /// Queries the maximum size of contracts, `MaxCodeLen` of the contracts pallet, from a node
+    /// of the network.
+    ///
+    /// Older chains name the constant `MaxCodeSize`.
+    pub fn max_code_len(&self) -> Result<u32> {
+        let url = self
+            .url
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("The network profile has no url"))?;
+        async_std::task::block_on(async move {
+            let cli = ClientBuilder::<DefaultNodeRuntime>::new()
+                .set_url(url.as_str())
+                .build()
+                .await?;
+            let contracts = cli.metadata().module("Contracts")?;
+            let max_code_len = contracts
+                .constant("MaxCodeLen")
+                .or_else(|_| contracts.constant("MaxCodeSize"))
+                .context(format!("The chain at {} has no MaxCodeLen", url))?;
+            Ok(max_code_len.value::<u32>()?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use std::fs;
+
+    const MANIFEST: &str = r#"
+[package]
+name = "flipper"
+version = "0.1.0"
+
+[package.metadata.contract.networks.production]
+url = "wss://rpc.example.com"
+remote-signer = "https://signer.example.com"
+registry = "https://registry.example.com"
+ipfs-gateway = "https://ipfs.io"
+
+[package.metadata.contract.networks.local]
+url = "ws://localhost:9944"
+"#;
+
+    #[test]
+    fn loads_network_profiles_from_manifest() {
+        with_tmp_dir(|path| {
+            // given
+            let manifest_path = path.join("Cargo.toml");
+            fs::write(&manifest_path, MANIFEST)?;
+            let manifest_path = ManifestPath::new(&manifest_path)?;
+
+            // when
+            let production = NetworkProfile::load(&manifest_path, "production")?;
+            let local = NetworkProfile::load(&manifest_path, "local")?;
+
+            // then
+            assert_eq!(
+                production.url,
+                Some(Url::parse("wss://rpc.example.com").unwrap())
+            );
+            assert_eq!(
+                production.remote_signer,
+                Some(Url::parse("https://signer.example.com").unwrap())
+            );
+            assert_eq!(
+                production.ipfs_gateway,
+                Some(Url::parse("https://ipfs.io").unwrap())
+            );
+            assert_eq!(local.remote_signer, None);
+            assert_eq!(local.registry, None);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn missing_network_profile_is_an_error() {
+        with_tmp_dir(|path| {
+            let manifest_path = path.join("Cargo.toml");
+            fs::write(&manifest_path, MANIFEST)?;
+            let manifest_path = ManifestPath::new(&manifest_path)?;
+
+            let res = NetworkProfile::load(&manifest_path, "staging");
+
+            assert!(res.is_err());
+            Ok(())
+        })
+    }
+}
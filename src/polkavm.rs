@@ -0,0 +1,140 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Experimental support for building contracts as PolkaVM blobs, for the RISC-V based contracts
+//! executor.
+//!
+//! The contract is compiled to a RISC-V ELF by cargo, which is linked into a PolkaVM blob by
+//! `polkatool link`. Install `polkatool` via `cargo install polkatool`.
+//!
+//! The `riscv32emac-unknown-none-polkavm` target is not built into the released compilers, the
+//! contract has to be built with a toolchain supporting it, e.g. pinned via `toolchain` in the
+//! `[package.metadata.contract]` of its `Cargo.toml`.
+
+use crate::{crate_metadata::CrateMetadata, toolchain, OptimizationPasses, OptimizationResult};
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    str,
+};
+
+/// The rustc target the contract is compiled to.
+pub(crate) const RUSTC_TARGET: &str = "riscv32emac-unknown-none-polkavm";
+
+/// Ensures the compiler of the pinned `toolchain`, or of the active one if none is pinned,
+/// supports the [`RUSTC_TARGET`].
+pub(crate) fn ensure_target_supported(toolchain: Option<&str>) -> Result<()> {
+    let output = toolchain::rustc_command(toolchain)
+        .args(["--print", "target-list"])
+        .output()
+        .context("Executing `rustc --print target-list` failed")?;
+    let targets = str::from_utf8(&output.stdout)?;
+    if !targets.lines().any(|target| target.trim() == RUSTC_TARGET) {
+        anyhow::bail!(
+            "Unsupported toolchain: the compiler doesn't support the `{}` target of the RISC-V \
+            backend.\nBuild the contract with a toolchain supporting it, e.g. by pinning it via \
+            `toolchain` in the `[package.metadata.contract]` of your `Cargo.toml`.",
+            RUSTC_TARGET
+        )
+    }
+    Ok(())
+}
+
+/// Returns the path of the PolkaVM blob of the contract.
+pub(crate) fn dest_blob(crate_metadata: &CrateMetadata) -> PathBuf {
+    crate_metadata
+        .artifact_directory
+        .join(format!("{}.polkavm", crate_metadata.package_name))
+}
+
+/// Links the ELF produced by cargo into a PolkaVM blob.
+///
+/// The blob takes the place of the optimized wasm in the result, so that the metadata and the
+/// bundle are generated the same way as for wasm contracts.
+pub(crate) fn link(crate_metadata: &CrateMetadata) -> Result<OptimizationResult> {
+    let elf = find_elf(crate_metadata)?;
+    let polkatool = which::which("polkatool").context(
+        "polkatool not found! It links the RISC-V ELF of the contract into a PolkaVM blob.\n\
+        Install it via `cargo install polkatool`.",
+    )?;
+    let dest_blob = dest_blob(crate_metadata);
+
+    let output = Command::new(&polkatool)
+        .arg("link")
+        .arg("--strip")
+        .arg("--output")
+        .arg(&dest_blob)
+        .arg(&elf)
+        .output()
+        .context(format!("Executing {} failed", polkatool.display()))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Linking the PolkaVM blob failed.\n\nThe error which polkatool returned was: \n{}",
+            str::from_utf8(&output.stderr)?.trim()
+        )
+    }
+
+    Ok(OptimizationResult {
+        dest_wasm: dest_blob.clone(),
+        original_size: fs::metadata(&elf)?.len() as f64 / 1000.0,
+        optimized_size: fs::metadata(&dest_blob)?.len() as f64 / 1000.0,
+        optimization_passes: OptimizationPasses::Zero,
+        custom_passes: Vec::new(),
+        debug_info: false,
+        optimizer_version: polkatool_version(&polkatool)?,
+        instrumented_size: None,
+    })
+}
+
+/// Returns the path of the ELF produced by cargo.
+///
+/// Depending on the crate type of the contract it is either a binary or a shared library.
+fn find_elf(crate_metadata: &CrateMetadata) -> Result<PathBuf> {
+    let release = crate_metadata
+        .target_directory
+        .join(RUSTC_TARGET)
+        .join("release");
+    let candidates = [
+        release.join(&crate_metadata.package_name),
+        release.join(format!("{}.elf", crate_metadata.package_name)),
+        release.join(format!("lib{}.so", crate_metadata.package_name)),
+    ];
+    candidates
+        .iter()
+        .find(|candidate| candidate.is_file())
+        .cloned()
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No RISC-V ELF of the contract found, expected one of {}",
+                candidates
+                    .iter()
+                    .map(|candidate| candidate.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+}
+
+/// Returns the version of `polkatool`, e.g. `polkatool 0.9.0`.
+fn polkatool_version(polkatool: &Path) -> Result<String> {
+    let output = Command::new(polkatool)
+        .arg("--version")
+        .output()
+        .context(format!("Executing {} failed", polkatool.display()))?;
+    Ok(str::from_utf8(&output.stdout)?.trim().to_string())
+}
@@ -0,0 +1,191 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Client of a registry of `<name>.contract` bundles, so that teams can share audited bundles.
+//!
+//! The registry has to speak the following HTTP/JSON protocol:
+//!
+//! - `GET {endpoint}/index/{name}` returns the published versions of the contract `name` as
+//!   `[{ "version": "<semver>", "blob": "0x<hex>", "codeHash": "0x<hex>" }]`, or `404` if none.
+//! - `POST {endpoint}/index/{name}` with an entry of the index publishes a version.
+//! - `GET {endpoint}/blobs/{blob}` returns the bundle with the BLAKE2 256-bit hash `blob`.
+//! - `PUT {endpoint}/blobs/{blob}` uploads the bundle with the hash `blob`.
+//!
+//! Bundles are addressed by their content, hence they are verified against the index when
+//! pulled. If the environment variable `CARGO_CONTRACT_REGISTRY_TOKEN` is set its value is sent
+//! as a bearer token with every request.
+
+use crate::{cmd::metadata::blake2_hash, util};
+use anyhow::{Context, Result};
+use impl_serde::serialize as serde_hex;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use url::Url;
+
+/// Environment variable holding the bearer token for authenticating with the registry.
+const TOKEN_ENV_VAR: &str = "CARGO_CONTRACT_REGISTRY_TOKEN";
+
+/// A published version of a contract, as listed in the index of the registry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IndexEntry {
+    pub version: Version,
+    /// The BLAKE2 256-bit hash of the bundle.
+    pub blob: String,
+    /// The code hash of the contract.
+    pub code_hash: String,
+}
+
+/// Client of a bundle registry.
+pub(crate) struct Registry {
+    endpoint: Url,
+    token: Option<String>,
+}
+
+impl Registry {
+    /// Creates a client of the registry at `endpoint`.
+    pub fn new(endpoint: &Url) -> Self {
+        Self {
+            endpoint: endpoint.clone(),
+            token: std::env::var(TOKEN_ENV_VAR).ok(),
+        }
+    }
+
+    /// Returns the published versions of the contract `name`.
+    pub fn index(&self, name: &str) -> Result<Vec<IndexEntry>> {
+        let url = self.url(&format!("index/{}", name))?;
+        match util::authorized(ureq::get(url.as_str()), self.token.as_deref()).call() {
+            Ok(response) => response
+                .into_json()
+                .context(format!("Invalid index of {} in the registry", name)),
+            Err(ureq::Error::Status(404, _)) => Ok(Vec::new()),
+            Err(err) => anyhow::bail!("Fetching the index of {} failed: {}", name, err),
+        }
+    }
+
+    /// Uploads the `bundle` and publishes it as `version` of the contract `name`.
+    pub fn publish(
+        &self,
+        name: &str,
+        version: &Version,
+        bundle: &[u8],
+        code_hash: &[u8; 32],
+    ) -> Result<IndexEntry> {
+        if self
+            .index(name)?
+            .iter()
+            .any(|entry| &entry.version == version)
+        {
+            anyhow::bail!("{}@{} is already published", name, version)
+        }
+        let entry = IndexEntry {
+            version: version.clone(),
+            blob: serde_hex::to_hex(&blake2_hash(bundle).0, false),
+            code_hash: serde_hex::to_hex(code_hash, false),
+        };
+        let url = self.url(&format!("blobs/{}", entry.blob))?;
+        util::authorized(ureq::put(url.as_str()), self.token.as_deref())
+            .send_bytes(bundle)
+            .map_err(|err| anyhow::anyhow!("Uploading the bundle failed: {}", err))?;
+        let url = self.url(&format!("index/{}", name))?;
+        util::authorized(ureq::post(url.as_str()), self.token.as_deref())
+            .send_json(serde_json::to_value(&entry)?)
+            .map_err(|err| anyhow::anyhow!("Publishing {}@{} failed: {}", name, version, err))?;
+        Ok(entry)
+    }
+
+    /// Downloads the bundle of the index `entry`, after checking it against its hash.
+    pub fn fetch(&self, entry: &IndexEntry) -> Result<Vec<u8>> {
+        let url = self.url(&format!("blobs/{}", entry.blob))?;
+        let mut bundle = Vec::new();
+        util::authorized(ureq::get(url.as_str()), self.token.as_deref())
+            .call()
+            .map_err(|err| {
+                anyhow::anyhow!("Downloading the bundle {} failed: {}", entry.blob, err)
+            })?
+            .into_reader()
+            .read_to_end(&mut bundle)?;
+        if serde_hex::to_hex(&blake2_hash(&bundle).0, false) != entry.blob {
+            anyhow::bail!(
+                "The bundle downloaded from the registry doesn't match its hash {}",
+                entry.blob
+            )
+        }
+        Ok(bundle)
+    }
+
+    /// Returns the url of the `path` of the registry.
+    fn url(&self, path: &str) -> Result<Url> {
+        let mut endpoint = self.endpoint.clone();
+        if !endpoint.path().ends_with('/') {
+            let base = format!("{}/", endpoint.path());
+            endpoint.set_path(&base);
+        }
+        endpoint
+            .join(path)
+            .context(format!("Invalid registry url {}", self.endpoint))
+    }
+}
+
+/// Returns the entry of `version` in the `index`, or of the latest version if none is supplied.
+pub(crate) fn resolve<'a>(
+    index: &'a [IndexEntry],
+    version: Option<&Version>,
+) -> Option<&'a IndexEntry> {
+    match version {
+        Some(version) => index.iter().find(|entry| &entry.version == version),
+        None => index.iter().max_by(|a, b| a.version.cmp(&b.version)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(version: &str) -> IndexEntry {
+        IndexEntry {
+            version: Version::parse(version).unwrap(),
+            blob: "0x00".to_string(),
+            code_hash: "0x00".to_string(),
+        }
+    }
+
+    #[test]
+    fn versions_are_resolved() {
+        let index = vec![entry("1.0.0"), entry("1.10.0"), entry("1.2.0")];
+
+        assert_eq!(resolve(&index, None), Some(&index[1]));
+        assert_eq!(
+            resolve(&index, Some(&Version::parse("1.2.0").unwrap())),
+            Some(&index[2])
+        );
+        assert_eq!(
+            resolve(&index, Some(&Version::parse("2.0.0").unwrap())),
+            None
+        );
+        assert_eq!(resolve(&[], None), None);
+    }
+
+    #[test]
+    fn index_entries_use_camel_case() {
+        let json = serde_json::to_value(entry("0.1.0")).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "version": "0.1.0", "blob": "0x00", "codeHash": "0x00" })
+        );
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signing of extrinsics by a remote service, e.g. a KMS, so that no private key has to be present
+//! on the machine deploying the contract.
+//!
+//! The service has to speak the following HTTP/JSON protocol:
+//!
+//! - `GET {endpoint}/address` returns the account of the signing key as
+//!   `{ "address": "<ss58 address>" }`.
+//! - `POST {endpoint}/sign` with `{ "address": "<ss58 address>", "payload": "0x<hex>" }` returns
+//!   the sr25519 signature of the payload as `{ "signature": "0x<hex>" }`.
+//!
+//! If the environment variable `CARGO_CONTRACT_REMOTE_SIGNER_TOKEN` is set its value is sent as a
+//! bearer token with every request.
+
+use std::{future::Future, pin::Pin};
+
+use crate::util;
+use anyhow::{Context, Result};
+use codec::Encode;
+use serde::{Deserialize, Serialize};
+use sp_core::{
+    crypto::{AccountId32, Ss58Codec},
+    sr25519,
+};
+use subxt::{
+    extrinsic::SignedPayload, system::System, DefaultNodeRuntime, Signer, UncheckedExtrinsic,
+};
+use url::Url;
+use zeroize::Zeroizing;
+
+/// Environment variable holding the bearer token for authenticating with the remote signer.
+const TOKEN_ENV_VAR: &str = "CARGO_CONTRACT_REMOTE_SIGNER_TOKEN";
+
+#[derive(Deserialize)]
+struct AddressResponse {
+    address: String,
+}
+
+#[derive(Serialize)]
+struct SignRequest {
+    address: String,
+    payload: String,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    signature: String,
+}
+
+/// Signs extrinsics by sending their payload to a remote signing service.
+pub(crate) struct RemoteSigner {
+    endpoint: Url,
+    token: Option<Zeroizing<String>>,
+    account_id: AccountId32,
+}
+
+impl RemoteSigner {
+    /// Connects to the signing service at `endpoint` and discovers the account of its key.
+    pub fn connect(endpoint: &Url) -> Result<Self> {
+        let token = std::env::var(TOKEN_ENV_VAR).ok().map(Zeroizing::new);
+        let mut signer = Self {
+            endpoint: endpoint.clone(),
+            token,
+            account_id: AccountId32::new([0u8; 32]),
+        };
+        let response: AddressResponse = util::authorized(
+            ureq::get(signer.url("address")?.as_str()),
+            signer.token.as_deref().map(String::as_str),
+        )
+        .call()
+        .map_err(|err| anyhow::anyhow!("Remote signer address discovery failed: {}", err))?
+        .into_json()
+        .context("Invalid address response of remote signer")?;
+        signer.account_id = AccountId32::from_ss58check(&response.address).map_err(|err| {
+            anyhow::anyhow!(
+                "Invalid address '{}' returned by remote signer: {:?}",
+                response.address,
+                err
+            )
+        })?;
+        log::info!(
+            "Using remote signer {} for account {}",
+            signer.endpoint,
+            response.address
+        );
+        Ok(signer)
+    }
+
+    /// Returns the account of the remote signing key.
+    pub fn account(&self) -> &AccountId32 {
+        &self.account_id
+    }
+
+    /// Requests the signature of `payload` from the remote signing service.
+    fn sign_payload(&self, payload: &[u8]) -> Result<sr25519::Signature> {
+        let request = SignRequest {
+            address: self.account_id.to_ss58check(),
+            payload: format!("0x{}", hex::encode(payload)),
+        };
+        let response: SignResponse = util::authorized(
+            ureq::post(self.url("sign")?.as_str()),
+            self.token.as_deref().map(String::as_str),
+        )
+        .send_json(serde_json::to_value(request)?)
+        .map_err(|err| anyhow::anyhow!("Remote signer failed to sign payload: {}", err))?
+        .into_json()
+        .context("Invalid sign response of remote signer")?;
+        decode_signature(&response.signature)
+    }
+
+    /// Returns the url of the `path` of the signing service.
+    fn url(&self, path: &str) -> Result<Url> {
+        let mut endpoint = self.endpoint.clone();
+        if !endpoint.path().ends_with('/') {
+            let base = format!("{}/", endpoint.path());
+            endpoint.set_path(&base);
+        }
+        endpoint
+            .join(path)
+            .context(format!("Invalid remote signer url {}", self.endpoint))
+    }
+}
+
+/// Decodes a hex encoded sr25519 signature, with or without `0x` prefix.
+fn decode_signature(signature: &str) -> Result<sr25519::Signature> {
+    let bytes = hex::decode(signature.trim_start_matches("0x"))
+        .context("Remote signer returned a signature which is not hex encoded")?;
+    if bytes.len() != 64 {
+        anyhow::bail!(
+            "Remote signer returned a signature of {} bytes, expected 64",
+            bytes.len()
+        )
+    }
+    let mut raw = [0u8; 64];
+    raw.copy_from_slice(&bytes);
+    Ok(sr25519::Signature::from_raw(raw))
+}
+
+impl Signer<DefaultNodeRuntime> for RemoteSigner {
+    fn account_id(&self) -> &<DefaultNodeRuntime as System>::AccountId {
+        &self.account_id
+    }
+
+    fn nonce(&self) -> Option<<DefaultNodeRuntime as System>::Index> {
+        None
+    }
+
+    fn sign(
+        &self,
+        extrinsic: SignedPayload<DefaultNodeRuntime>,
+    ) -> Pin<
+        Box<
+            dyn Future<Output = std::result::Result<UncheckedExtrinsic<DefaultNodeRuntime>, String>>
+                + Send
+                + Sync,
+        >,
+    > {
+        let signature = extrinsic.using_encoded(|payload| self.sign_payload(payload));
+        let result = signature
+            .map(|signature| {
+                let (call, extra, _) = extrinsic.deconstruct();
+                UncheckedExtrinsic::<DefaultNodeRuntime>::new_signed(
+                    call,
+                    self.account_id.clone().into(),
+                    signature.into(),
+                    extra,
+                )
+            })
+            .map_err(|err| format!("{:?}", err));
+        Box::pin(futures::future::ready(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_prefixed_signature() {
+        let signature = format!("0x{}", "ab".repeat(64));
+        let decoded = decode_signature(&signature).expect("decoding signature failed");
+        assert_eq!(decoded.0, [0xab; 64]);
+    }
+
+    #[test]
+    fn rejects_signature_of_wrong_length() {
+        assert!(decode_signature("0x0102").is_err());
+        assert!(decode_signature("not hex").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use url::Url;
+
+/// Minimal JSON-RPC client for the node requests which are not covered by `subxt`.
+///
+/// Requests are sent via HTTP, a websocket url of the node is mapped to the
+/// corresponding HTTP url on the same host and port.
+pub(crate) struct RpcClient {
+    endpoint: Url,
+}
+
+impl RpcClient {
+    /// Creates a new client for the node at `url`.
+    pub fn new(url: &Url) -> Result<Self> {
+        let mut endpoint = url.clone();
+        let scheme = match url.scheme() {
+            "ws" | "http" => "http",
+            "wss" | "https" => "https",
+            scheme => anyhow::bail!("Unsupported url scheme '{}' for RPC requests", scheme),
+        };
+        endpoint
+            .set_scheme(scheme)
+            .map_err(|_| anyhow::anyhow!("Failed to convert '{}' to a HTTP url", url))?;
+        Ok(Self { endpoint })
+    }
+
+    /// Returns the HTTP url the requests are sent to.
+    pub fn endpoint(&self) -> &str {
+        self.endpoint.as_str()
+    }
+
+    /// Sends the request for `method` with the supplied `params` and decodes the result.
+    pub fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        log::debug!("RPC request `{}` to {}: {}", method, self.endpoint, params);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response: Value = ureq::post(self.endpoint.as_str())
+            .send_json(request)
+            .map_err(|err| {
+                anyhow::anyhow!(
+                    "RPC request `{}` to {} failed: {}",
+                    method,
+                    self.endpoint,
+                    err
+                )
+            })?
+            .into_json()
+            .context(format!(
+                "Invalid JSON response for RPC request `{}`",
+                method
+            ))?;
+
+        if let Some(error) = response.get("error") {
+            anyhow::bail!("RPC request `{}` returned an error: {}", method, error);
+        }
+        let result = response
+            .get("result")
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No result for RPC request `{}`", method))?;
+        serde_json::from_value(result)
+            .context(format!("Unexpected result for RPC request `{}`", method))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn websocket_urls_are_mapped_to_http() {
+        let client = RpcClient::new(&Url::parse("ws://localhost:9944").unwrap()).unwrap();
+        assert_eq!(client.endpoint.as_str(), "http://localhost:9944/");
+
+        let client = RpcClient::new(&Url::parse("wss://example.com/rpc").unwrap()).unwrap();
+        assert_eq!(client.endpoint.as_str(), "https://example.com/rpc");
+    }
+
+    #[test]
+    fn unsupported_schemes_are_rejected() {
+        let res = RpcClient::new(&Url::parse("ftp://localhost").unwrap());
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,493 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Recording and replaying of the RPC traffic of a command run.
+//!
+//! With `CARGO_CONTRACT_RPC_RECORD=<fixture.json>` the commands connect to the node via a local
+//! proxy, which records every request with the responses and subscription notifications it
+//! caused into the fixture. With `CARGO_CONTRACT_RPC_REPLAY=<fixture.json>` the proxy answers
+//! the requests from the fixture instead, so that no node is required.
+//!
+//! Requests are replayed by their method and params. Requests whose params differ from the
+//! recorded ones, e.g. extrinsics carrying a fresh signature, get the responses of the next
+//! recorded request of the same method.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use tungstenite::{Message, WebSocket};
+use url::Url;
+
+/// The environment variable enabling the recording of the RPC traffic into a fixture.
+pub(crate) const RECORD_ENV: &str = "CARGO_CONTRACT_RPC_RECORD";
+
+/// The environment variable enabling the replay of the RPC traffic from a fixture.
+pub(crate) const REPLAY_ENV: &str = "CARGO_CONTRACT_RPC_REPLAY";
+
+lazy_static::lazy_static! {
+    /// The proxy of the command run, started on the first connection to a node.
+    static ref PROXY: Mutex<Option<Url>> = Mutex::new(None);
+}
+
+/// Returns the url to connect to for the node at `url`.
+///
+/// This is the url of the recording or replaying proxy if enabled by [`RECORD_ENV`] or
+/// [`REPLAY_ENV`], otherwise `url` itself.
+pub(crate) fn redirect(url: Url) -> Result<Url> {
+    let mode = match (std::env::var_os(RECORD_ENV), std::env::var_os(REPLAY_ENV)) {
+        (None, None) => return Ok(url),
+        (Some(_), Some(_)) => {
+            anyhow::bail!("Only one of {} and {} can be set", RECORD_ENV, REPLAY_ENV)
+        }
+        (Some(path), None) => Mode::Record {
+            upstream: url,
+            path: path.into(),
+        },
+        (None, Some(path)) => Mode::Replay(Fixture::load(Path::new(&path))?),
+    };
+    let mut proxy = PROXY.lock().expect("the proxy lock is never poisoned; qed");
+    if let Some(url) = &*proxy {
+        return Ok(url.clone());
+    }
+    let url = Proxy::start(mode)?;
+    *proxy = Some(url.clone());
+    Ok(url)
+}
+
+/// Runs `f` with the RPC traffic replayed from the fixture `name` in `src/cmd/fixtures`.
+///
+/// If [`RECORD_ENV`] is set, the traffic with the node is recorded into the fixture instead, e.g.
+/// `CARGO_CONTRACT_RPC_RECORD=1 cargo test --features extrinsics deploy_contract -- --ignored`
+/// with a `substrate-contracts-node --dev` running. The runs are serialized, since the fixture is
+/// selected via the environment of the process.
+#[cfg(test)]
+pub(crate) fn with_fixture<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    lazy_static::lazy_static! {
+        static ref RUN: Mutex<()> = Mutex::new(());
+    }
+    let _run = RUN.lock().unwrap_or_else(|err| err.into_inner());
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src/cmd/fixtures")
+        .join(name);
+    let env = if std::env::var_os(RECORD_ENV).is_some() {
+        RECORD_ENV
+    } else {
+        REPLAY_ENV
+    };
+    std::env::set_var(env, &path);
+    *PROXY.lock().expect("the proxy lock is never poisoned; qed") = None;
+    let result = f();
+    std::env::remove_var(REPLAY_ENV);
+    result
+}
+
+/// The recorded RPC traffic of a command run.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Fixture {
+    exchanges: Vec<Exchange>,
+}
+
+/// A request with the responses and subscription notifications it caused.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Exchange {
+    request: Value,
+    responses: Vec<Value>,
+}
+
+impl Fixture {
+    fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .context(format!("Failed to read the RPC fixture {}", path.display()))?;
+        serde_json::from_str(&json).context(format!("Invalid RPC fixture {}", path.display()))
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?).context(format!(
+            "Failed to write the RPC fixture {}",
+            path.display()
+        ))
+    }
+}
+
+enum Mode {
+    /// Forwards the requests to the `upstream` node, recording them into the fixture at `path`.
+    Record { upstream: Url, path: PathBuf },
+    /// Answers the requests from the fixture.
+    Replay(Fixture),
+}
+
+/// A local proxy recording or replaying the RPC traffic of websocket and HTTP connections.
+struct Proxy {
+    mode: Mode,
+    /// The recorded exchanges.
+    recorded: Fixture,
+    /// Whether the exchange of the fixture at the same index was replayed already.
+    replayed: Vec<bool>,
+}
+
+impl Proxy {
+    /// Starts the proxy on a local port and returns its websocket url.
+    fn start(mode: Mode) -> Result<Url> {
+        let listener = TcpListener::bind("127.0.0.1:0").context("Failed to start the RPC proxy")?;
+        let url = Url::parse(&format!("ws://{}", listener.local_addr()?))?;
+        let replayed = match &mode {
+            Mode::Replay(fixture) => vec![false; fixture.exchanges.len()],
+            Mode::Record { .. } => Vec::new(),
+        };
+        let proxy = Arc::new(Mutex::new(Proxy {
+            mode,
+            recorded: Fixture::default(),
+            replayed,
+        }));
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+                let proxy = proxy.clone();
+                thread::spawn(move || {
+                    if let Err(err) = serve(&proxy, stream) {
+                        log::warn!("RPC proxy connection failed: {:#}", err);
+                    }
+                });
+            }
+        });
+        log::debug!("RPC proxy listening on {}", url);
+        Ok(url)
+    }
+
+    fn upstream(&self) -> Option<&Url> {
+        match &self.mode {
+            Mode::Record { upstream, .. } => Some(upstream),
+            Mode::Replay(_) => None,
+        }
+    }
+
+    /// Records the `request`, returning the index of its exchange.
+    fn record(&mut self, request: Value) -> Result<usize> {
+        self.recorded.exchanges.push(Exchange {
+            request,
+            responses: Vec::new(),
+        });
+        self.save()?;
+        Ok(self.recorded.exchanges.len() - 1)
+    }
+
+    /// Records the `response` to the request of the exchange at `index`.
+    fn record_response(&mut self, index: usize, response: Value) -> Result<()> {
+        self.recorded.exchanges[index].responses.push(response);
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        match &self.mode {
+            Mode::Record { path, .. } => self.recorded.save(path),
+            Mode::Replay(_) => Ok(()),
+        }
+    }
+
+    /// Returns the recorded responses to the `request`, with the id of the request.
+    fn replay(&mut self, request: &Value) -> Vec<Value> {
+        let exchanges = match &self.mode {
+            Mode::Replay(fixture) => &fixture.exchanges,
+            Mode::Record { .. } => return Vec::new(),
+        };
+        let unused = |index: &usize| !self.replayed[*index];
+        let same_method = |index: &usize| exchanges[*index].request["method"] == request["method"];
+        let found = (0..exchanges.len())
+            .filter(unused)
+            .filter(same_method)
+            .find(|index| exchanges[*index].request["params"] == request["params"])
+            .or_else(|| (0..exchanges.len()).filter(unused).find(same_method));
+        let index = match found {
+            Some(index) => index,
+            None => {
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": {
+                        "code": -32000,
+                        "message": format!("No recorded response for `{}`", request["method"]),
+                    },
+                })]
+            }
+        };
+        self.replayed[index] = true;
+        let exchange = &exchanges[index];
+        exchange
+            .responses
+            .iter()
+            .cloned()
+            .map(|mut response| {
+                if response.get("id").is_some() && response["id"] == exchange.request["id"] {
+                    response["id"] = request["id"].clone();
+                }
+                response
+            })
+            .collect()
+    }
+}
+
+/// Serves a connection of a client, which is either a websocket or a HTTP request.
+fn serve(proxy: &Mutex<Proxy>, stream: TcpStream) -> Result<()> {
+    let mut head = [0; 4096];
+    let len = stream.peek(&mut head)?;
+    let head = String::from_utf8_lossy(&head[..len]).to_lowercase();
+    if head.contains("upgrade: websocket") {
+        let client = tungstenite::accept(stream)
+            .map_err(|err| anyhow::anyhow!("Websocket handshake failed: {}", err))?;
+        let upstream = lock(proxy).upstream().cloned();
+        match upstream {
+            Some(upstream) => record_websocket(proxy, client, &upstream),
+            None => replay_websocket(proxy, client),
+        }
+    } else {
+        serve_http(proxy, stream)
+    }
+}
+
+fn lock(proxy: &Mutex<Proxy>) -> std::sync::MutexGuard<'_, Proxy> {
+    proxy.lock().expect("the proxy lock is never poisoned; qed")
+}
+
+/// Forwards the messages between the `client` and the `upstream` node, recording them.
+fn record_websocket(
+    proxy: &Mutex<Proxy>,
+    mut client: WebSocket<TcpStream>,
+    upstream: &Url,
+) -> Result<()> {
+    let host = upstream
+        .host_str()
+        .ok_or_else(|| anyhow::anyhow!("No host in the url {}", upstream))?;
+    let port = upstream.port_or_known_default().unwrap_or(9944);
+    if upstream.scheme() != "ws" {
+        anyhow::bail!("Only `ws://` nodes can be recorded, not {}", upstream)
+    }
+    let stream = TcpStream::connect((host, port))
+        .context(format!("Failed to connect to the node at {}", upstream))?;
+    let (mut node, _) = tungstenite::client(upstream.as_str(), stream)
+        .map_err(|err| anyhow::anyhow!("Websocket handshake with {} failed: {}", upstream, err))?;
+    client.get_ref().set_nonblocking(true)?;
+    node.get_ref().set_nonblocking(true)?;
+
+    // the exchanges of the requests and subscriptions of the connection by their id
+    let mut requests = HashMap::new();
+    let mut subscriptions = HashMap::new();
+    loop {
+        let mut idle = true;
+        if let Some(text) = read_text(&mut client)? {
+            idle = false;
+            let request: Value = serde_json::from_str(&text)?;
+            let index = lock(proxy).record(request.clone())?;
+            requests.insert(request["id"].to_string(), index);
+            node.write_message(Message::Text(text))?;
+        }
+        if let Some(text) = read_text(&mut node)? {
+            idle = false;
+            let response: Value = serde_json::from_str(&text)?;
+            let index = match response.get("id") {
+                Some(id) => requests.get(&id.to_string()).copied(),
+                None => subscriptions
+                    .get(&response["params"]["subscription"].to_string())
+                    .copied(),
+            };
+            if let Some(index) = index {
+                if response.get("id").is_some() && !response["result"].is_null() {
+                    // a subscription id, the later notifications belong to the exchange
+                    subscriptions.insert(response["result"].to_string(), index);
+                }
+                lock(proxy).record_response(index, response)?;
+            }
+            client.write_message(Message::Text(text))?;
+        }
+        flush(&mut client)?;
+        flush(&mut node)?;
+        if !client.can_read() || !node.can_read() {
+            return Ok(());
+        }
+        if idle {
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
+
+/// Answers the requests of the `client` from the fixture.
+fn replay_websocket(proxy: &Mutex<Proxy>, mut client: WebSocket<TcpStream>) -> Result<()> {
+    loop {
+        let text = match client.read_message() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Ok(_) => continue,
+            Err(err) => return Err(err.into()),
+        };
+        let request: Value = serde_json::from_str(&text)?;
+        for response in lock(proxy).replay(&request) {
+            client.write_message(Message::Text(response.to_string()))?;
+        }
+    }
+}
+
+/// Returns the next text message of the non-blocking `socket`, if any.
+fn read_text(socket: &mut WebSocket<TcpStream>) -> Result<Option<String>> {
+    match socket.read_message() {
+        Ok(Message::Text(text)) => Ok(Some(text)),
+        Ok(_) | Err(tungstenite::Error::ConnectionClosed) => Ok(None),
+        Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Writes the pending messages of the non-blocking `socket`.
+fn flush(socket: &mut WebSocket<TcpStream>) -> Result<()> {
+    match socket.write_pending() {
+        Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// Answers a HTTP request of a client, forwarding it to the node if recording.
+fn serve_http(proxy: &Mutex<Proxy>, stream: TcpStream) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    let request: Value = serde_json::from_slice(&body)?;
+
+    let upstream = lock(proxy).upstream().cloned();
+    let response = match upstream {
+        Some(upstream) => {
+            let index = lock(proxy).record(request.clone())?;
+            let response: Value = ureq::post(crate::rpc::RpcClient::new(&upstream)?.endpoint())
+                .send_json(request)?
+                .into_json()?;
+            lock(proxy).record_response(index, response.clone())?;
+            response
+        }
+        None => lock(proxy)
+            .replay(&request)
+            .into_iter()
+            .next()
+            .unwrap_or(Value::Null),
+    };
+
+    let body = response.to_string();
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+        Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+    Ok(stream.flush()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::RpcClient;
+
+    fn exchange(id: u64, method: &str, params: Value, result: Value) -> Exchange {
+        Exchange {
+            request: json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }),
+            responses: vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+        }
+    }
+
+    #[test]
+    fn requests_are_replayed_by_method_and_params() {
+        // given
+        let fixture = Fixture {
+            exchanges: vec![
+                exchange(1, "state_call", json!(["a"]), json!("0x01")),
+                exchange(2, "state_call", json!(["b"]), json!("0x02")),
+                exchange(
+                    3,
+                    "author_submitExtrinsic",
+                    json!(["0xaa"]),
+                    json!("0xhash"),
+                ),
+            ],
+        };
+        let mut proxy = Proxy {
+            replayed: vec![false; fixture.exchanges.len()],
+            mode: Mode::Replay(fixture),
+            recorded: Fixture::default(),
+        };
+        let request = |id, method, params| json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+
+        // when
+        let b = proxy.replay(&request(7, "state_call", json!(["b"])));
+        let submitted = proxy.replay(&request(8, "author_submitExtrinsic", json!(["0xbb"])));
+        let unknown = proxy.replay(&request(9, "author_submitExtrinsic", json!(["0xbb"])));
+
+        // then
+        assert_eq!(
+            b,
+            vec![json!({ "jsonrpc": "2.0", "id": 7, "result": "0x02" })]
+        );
+        assert_eq!(
+            submitted,
+            vec![json!({ "jsonrpc": "2.0", "id": 8, "result": "0xhash" })]
+        );
+        assert_eq!(unknown[0]["error"]["code"], -32000);
+    }
+
+    #[test]
+    fn http_requests_are_replayed() {
+        crate::util::tests::with_tmp_dir(|path| {
+            // given
+            let fixture = Fixture {
+                exchanges: vec![exchange(
+                    1,
+                    "chain_getBlockHash",
+                    json!([0]),
+                    json!("0x1234"),
+                )],
+            };
+            let fixture_path = path.join("fixture.json");
+            fixture.save(&fixture_path)?;
+            let url = Proxy::start(Mode::Replay(Fixture::load(&fixture_path)?))?;
+
+            // when
+            let hash: String = RpcClient::new(&url)?.request("chain_getBlockHash", json!([0]))?;
+
+            // then
+            assert_eq!(hash, "0x1234");
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,330 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use sp_core::{crypto::Pair, sr25519};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use structopt::StructOpt;
+use zeroize::Zeroizing;
+
+/// The maximum length of a secret read from a file or a file descriptor, in bytes.
+const MAX_SECRET_LEN: usize = 4096;
+
+/// Arguments for supplying the secret key URI of an account.
+///
+/// Secrets passed as command line arguments are visible to other users via the process list and
+/// may end up in the shell history. Hence reading them from a protected file or from a file
+/// descriptor is preferred.
+#[derive(Clone, StructOpt)]
+pub(crate) struct SecretOpts {
+    /// Secret key URI for the account deploying the contract.
+    ///
+    /// Prefer `--suri-file` or `--suri-fd` for anything but development accounts like `//Alice`,
+    /// arguments are visible to other users of the system.
+    #[structopt(name = "suri", long, short)]
+    suri: Option<String>,
+    /// Path to a file containing the secret key URI. The file must not be accessible by other users.
+    #[structopt(
+        name = "suri-file",
+        long,
+        parse(from_os_str),
+        conflicts_with_all = &["suri", "suri-fd"]
+    )]
+    suri_file: Option<PathBuf>,
+    /// File descriptor to read the secret key URI from, e.g. `--suri-fd 3 3<secret.txt`.
+    #[structopt(name = "suri-fd", long, conflicts_with = "suri")]
+    suri_fd: Option<i32>,
+    /// Password for the secret key
+    #[structopt(name = "password", long, short)]
+    password: Option<String>,
+    /// Path to a file containing the password for the secret key. The file must not be
+    /// accessible by other users.
+    #[structopt(
+        name = "password-file",
+        long,
+        parse(from_os_str),
+        conflicts_with = "password"
+    )]
+    password_file: Option<PathBuf>,
+    /// The secret key URI once it has been read, since a file descriptor can be read only once.
+    ///
+    /// Shared by the clones of the options, e.g. to sign multiple extrinsics.
+    #[structopt(skip)]
+    cached_suri: Arc<Mutex<Option<Zeroizing<String>>>>,
+}
+
+impl fmt::Debug for SecretOpts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |secret: &Option<String>| secret.as_ref().map(|_| "<redacted>");
+        f.debug_struct("SecretOpts")
+            .field("suri", &redacted(&self.suri))
+            .field("suri_file", &self.suri_file)
+            .field("suri_fd", &self.suri_fd)
+            .field("password", &redacted(&self.password))
+            .field("password_file", &self.password_file)
+            .finish()
+    }
+}
+
+impl SecretOpts {
+    /// Returns `true` if any source for the secret key URI has been supplied.
+    pub fn is_present(&self) -> bool {
+        self.suri.is_some() || self.suri_file.is_some() || self.suri_fd.is_some()
+    }
+
+    /// Derives the key pair from the supplied secret key URI.
+    ///
+    /// The password is zeroized once the key pair has been derived, the secret key URI once the
+    /// options are dropped. Secrets passed via `--suri` or `--password` are not zeroized, they
+    /// remain in the arguments of the process.
+    pub fn pair(&self) -> Result<sr25519::Pair> {
+        let mut pairs = self.derive_pairs(&[""])?;
+        Ok(pairs.remove(0))
+    }
+
+    /// Derives the key pairs for the derivation `paths`, e.g. `//0` or `/soft//hard`, relative to
+    /// the supplied secret key URI.
+    ///
+    /// The secret is read only once per invocation, since a file descriptor can't be read again.
+    pub fn derive_pairs<P: AsRef<str>>(&self, paths: &[P]) -> Result<Vec<sr25519::Pair>> {
+        let root = self.secret_uri()?;
+        let password = self.password()?;
+        paths
+            .iter()
+            .map(|path| {
+                let mut suri =
+                    Zeroizing::new(String::with_capacity(root.len() + path.as_ref().len()));
+                suri.push_str(&root);
+                suri.push_str(path.as_ref());
+                sr25519::Pair::from_string(&suri, password.as_ref().map(|p| p.as_str()))
+                    .map_err(|_| anyhow::anyhow!("Secret string error"))
+            })
+            .collect()
+    }
+
+    /// Returns the secret key URI, reading it from the supplied source on the first call.
+    fn secret_uri(&self) -> Result<Zeroizing<String>> {
+        let mut cached = self.cached_suri.lock().expect("poisoned lock");
+        if let Some(suri) = &*cached {
+            return Ok(suri.clone());
+        }
+        let suri = self.read_secret_uri()?;
+        *cached = Some(suri.clone());
+        Ok(suri)
+    }
+
+    /// Reads the secret key URI from the supplied source.
+    fn read_secret_uri(&self) -> Result<Zeroizing<String>> {
+        if let Some(suri) = &self.suri {
+            if !is_dev_uri(suri) {
+                warn_secret_on_command_line("--suri", "--suri-file");
+            }
+            return Ok(Zeroizing::new(suri.clone()));
+        }
+        if let Some(path) = &self.suri_file {
+            return read_protected_file(path);
+        }
+        if let Some(fd) = self.suri_fd {
+            return read_file_descriptor(fd);
+        }
+        anyhow::bail!(
+            "No secret key URI supplied, use one of `--suri`, `--suri-file` or `--suri-fd`"
+        )
+    }
+
+    /// Reads the optional password from the supplied source.
+    fn password(&self) -> Result<Option<Zeroizing<String>>> {
+        if let Some(password) = &self.password {
+            warn_secret_on_command_line("--password", "--password-file");
+            return Ok(Some(Zeroizing::new(password.clone())));
+        }
+        self.password_file
+            .as_ref()
+            .map(|path| read_protected_file(path))
+            .transpose()
+    }
+}
+
+/// Returns `true` if the secret key URI consists only of a hard derivation path, like the well
+/// known development accounts `//Alice` or `//Bob`.
+fn is_dev_uri(suri: &str) -> bool {
+    suri.starts_with("//")
+}
+
+/// Prints a prominent warning that secret material was passed as a command line argument.
+fn warn_secret_on_command_line(arg: &str, alternative: &str) {
+    eprintln!(
+        "{} {}",
+        "warning:".bright_red().bold(),
+        format!(
+            "a secret was passed via `{}`. Command line arguments are visible to other users \
+            of this system and may be stored in your shell history. Use `{}` instead.",
+            arg, alternative
+        )
+        .bold()
+    );
+}
+
+/// Reads a secret from a file which must not be accessible by other users.
+fn read_protected_file(path: &Path) -> Result<Zeroizing<String>> {
+    let file = File::open(path).context(format!("Failed to open {}", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = file.metadata()?.permissions().mode();
+        if mode & 0o077 != 0 {
+            anyhow::bail!(
+                "The secret file '{}' is accessible by other users (mode {:o}).\n\
+                Restrict its permissions, e.g. with `chmod 600 {}`.",
+                path.display(),
+                mode & 0o777,
+                path.display()
+            );
+        }
+    }
+
+    read_secret(file)
+}
+
+/// Reads a secret from an already opened file descriptor.
+#[cfg(unix)]
+fn read_file_descriptor(fd: i32) -> Result<Zeroizing<String>> {
+    use std::os::unix::io::FromRawFd;
+
+    if fd <= 2 {
+        anyhow::bail!("Reading the secret from stdin, stdout or stderr is not supported");
+    }
+    // SAFETY: the descriptor has been passed to us explicitly by the invoking process, so we
+    // take ownership of it. It is closed once the secret has been read.
+    let file = unsafe { File::from_raw_fd(fd) };
+    read_secret(file)
+}
+
+#[cfg(not(unix))]
+fn read_file_descriptor(_fd: i32) -> Result<Zeroizing<String>> {
+    anyhow::bail!("Reading the secret from a file descriptor is only supported on unix systems")
+}
+
+/// Reads the whole content of `reader` as a secret, stripping a trailing newline.
+///
+/// The secret is read into a buffer of a fixed size, which is never reallocated. Otherwise copies
+/// of the secret would be left behind in freed memory without being zeroized.
+fn read_secret<R: Read>(mut reader: R) -> Result<Zeroizing<String>> {
+    // one byte more than allowed in order to detect longer secrets
+    let mut buffer = Zeroizing::new(vec![0u8; MAX_SECRET_LEN + 1]);
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    if len > MAX_SECRET_LEN {
+        anyhow::bail!(
+            "The secret exceeds the maximum length of {} bytes",
+            MAX_SECRET_LEN
+        );
+    }
+    let secret = std::str::from_utf8(&buffer[..len]).context("The secret is not valid UTF-8")?;
+    let secret = secret.trim_end_matches(&['\r', '\n'][..]);
+    let mut copy = Zeroizing::new(String::with_capacity(secret.len()));
+    copy.push_str(secret);
+    Ok(copy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::tests::with_tmp_dir;
+    use std::fs;
+
+    #[test]
+    fn read_secret_strips_trailing_newline() {
+        let secret = read_secret(&b"//Alice\n"[..]).expect("reading secret failed");
+        assert_eq!(secret.as_str(), "//Alice");
+    }
+
+    #[test]
+    fn read_secret_rejects_too_long_secret() {
+        let secret = vec![b'a'; MAX_SECRET_LEN + 1];
+        let res = read_secret(&secret[..]);
+        assert!(res.is_err());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("exceeds the maximum length"));
+    }
+
+    #[test]
+    fn dev_uris_are_detected() {
+        assert!(is_dev_uri("//Alice"));
+        assert!(!is_dev_uri(
+            "bottom drive obey lake curtain smoke basket hold race lonely fit walk//Alice"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_secret_file_readable_by_others() {
+        use std::os::unix::fs::PermissionsExt;
+        with_tmp_dir(|path| {
+            // given
+            let secret_path = path.join("secret");
+            fs::write(&secret_path, "//Alice")?;
+            fs::set_permissions(&secret_path, fs::Permissions::from_mode(0o644))?;
+
+            // when
+            let res = read_protected_file(&secret_path);
+
+            // then
+            assert!(res.is_err());
+            assert!(res
+                .unwrap_err()
+                .to_string()
+                .contains("is accessible by other users"));
+            Ok(())
+        })
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_protected_secret_file() {
+        use std::os::unix::fs::PermissionsExt;
+        with_tmp_dir(|path| {
+            // given
+            let secret_path = path.join("secret");
+            fs::write(&secret_path, "//Alice\n")?;
+            fs::set_permissions(&secret_path, fs::Permissions::from_mode(0o600))?;
+
+            // when
+            let secret = read_protected_file(&secret_path)?;
+
+            // then
+            assert_eq!(secret.as_str(), "//Alice");
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,631 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Breaks the size of a contract down by function and crate.
+//!
+//! The functions and data segments of the module form a graph, rooted at the exported entry
+//! points and the function table. An item *dominates* another one if every path from the root to
+//! the other item passes through it. The *retained* size of an item is its own (*shallow*) size
+//! plus the sizes of all items it dominates, i.e. the size which would be saved if the item was
+//! removed from the contract.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use parity_wasm::elements::{
+    ImportCountType, Instruction, Internal, Module, Section, Serialize as _,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Number of items shown in the report.
+const MAX_ITEMS: usize = 20;
+
+/// Name of the crate for items without a path, e.g. `memcpy`.
+const UNKNOWN_CRATE: &str = "<other>";
+
+/// An item of the contract, i.e. a function or a data segment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeItem {
+    /// The demangled name of the item.
+    pub name: String,
+    /// The size of the item itself in bytes.
+    pub shallow: u32,
+    /// The size of the item and all items it dominates in bytes.
+    pub retained: u32,
+}
+
+/// Size report of a contract.
+#[derive(Debug, Clone)]
+pub struct SizeReport {
+    /// All items reachable from the entry points, by descending retained size.
+    pub items: Vec<SizeItem>,
+    /// The shallow sizes of the items, summed up per crate, by descending size.
+    pub crates: Vec<(String, u32)>,
+    /// The size of all items.
+    pub total: u32,
+}
+
+impl SizeReport {
+    /// Analyzes the supplied module.
+    ///
+    /// The module must still contain the `name` section in order to attribute items to functions
+    /// and crates. Functions without a name are reported by their index.
+    pub fn analyze(module: &Module) -> Result<Self> {
+        let module = module.clone().parse_names().unwrap_or_else(|(_, m)| m);
+        let names = module
+            .names_section()
+            .and_then(|names| names.functions())
+            .map(|functions| functions.names().clone());
+
+        let imported = module.import_count(ImportCountType::Function) as u32;
+        let bodies = module
+            .code_section()
+            .map(|code| code.bodies().to_vec())
+            .unwrap_or_default();
+        let data = module
+            .data_section()
+            .map(|data| data.entries().to_vec())
+            .unwrap_or_default();
+
+        // node 0 is the root, followed by all functions of the function index space and the
+        // data segments
+        let functions = imported as usize + bodies.len();
+        let node_count = 1 + functions + data.len();
+        let function_node = |index: u32| index as usize + 1;
+        let mut graph = Graph::new(node_count);
+        let mut items = vec![
+            SizeItem {
+                name: String::new(),
+                shallow: 0,
+                retained: 0,
+            };
+            node_count
+        ];
+
+        for index in 0..functions as u32 {
+            let name = names
+                .as_ref()
+                .and_then(|names| names.get(index))
+                .map(|name| format!("{:#}", rustc_demangle::demangle(name)))
+                .unwrap_or_else(|| format!("func[{}]", index));
+            let shallow = match index.checked_sub(imported) {
+                Some(body) => {
+                    let body = &bodies[body as usize];
+                    for instruction in body.code().elements() {
+                        if let Instruction::Call(callee) = instruction {
+                            graph.add_edge(function_node(index), function_node(*callee));
+                        }
+                    }
+                    let mut buf = Vec::new();
+                    body.clone()
+                        .serialize(&mut buf)
+                        .map_err(|err| anyhow::anyhow!("Serializing function failed: {}", err))?;
+                    buf.len() as u32
+                }
+                None => 0,
+            };
+            items[function_node(index)] = SizeItem {
+                name,
+                shallow,
+                retained: 0,
+            };
+        }
+        for (index, segment) in data.iter().enumerate() {
+            let node = 1 + functions + index;
+            items[node] = SizeItem {
+                name: format!("data[{}]", index),
+                shallow: segment.value().len() as u32,
+                retained: 0,
+            };
+            graph.add_edge(0, node);
+        }
+
+        // the exported functions and the functions in the table are called from the outside
+        if let Some(exports) = module.export_section() {
+            for export in exports.entries() {
+                if let Internal::Function(index) = export.internal() {
+                    graph.add_edge(0, function_node(*index));
+                }
+            }
+        }
+        if let Some(elements) = module.elements_section() {
+            for segment in elements.entries() {
+                for index in segment.members() {
+                    graph.add_edge(0, function_node(*index));
+                }
+            }
+        }
+
+        let dominators = graph.immediate_dominators();
+        for &node in graph.postorder().iter() {
+            items[node].retained += items[node].shallow;
+            if node != 0 {
+                let retained = items[node].retained;
+                let dominator = dominators[node].expect("node is reachable; qed");
+                items[dominator].retained += retained;
+            }
+        }
+
+        let reachable = graph.postorder();
+        let mut crates = BTreeMap::<String, u32>::new();
+        for &node in reachable.iter().filter(|node| **node != 0) {
+            *crates.entry(crate_name(&items[node].name)).or_default() += items[node].shallow;
+        }
+        let mut crates = crates.into_iter().collect::<Vec<_>>();
+        crates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let total = items[0].retained;
+        let mut items = reachable
+            .into_iter()
+            .filter(|node| *node != 0)
+            .map(|node| items[node].clone())
+            .collect::<Vec<_>>();
+        items.sort_by(|a, b| {
+            b.retained
+                .cmp(&a.retained)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        Ok(SizeReport {
+            items,
+            crates,
+            total,
+        })
+    }
+
+    /// Returns a human readable table of the largest items and crates.
+    pub fn display(&self) -> String {
+        let percent = |size: u32| {
+            if self.total == 0 {
+                0.0
+            } else {
+                size as f64 / self.total as f64 * 100.0
+            }
+        };
+        let mut out = format!(
+            "{}\n {:>9} {:>7} {:>9}  {}\n",
+            "Size report (before wasm-opt):".bold(),
+            "Retained",
+            "%",
+            "Shallow",
+            "Item"
+        );
+        for item in self.items.iter().take(MAX_ITEMS) {
+            out.push_str(&format!(
+                " {:>9} {:>6.2}% {:>9}  {}\n",
+                item.retained,
+                percent(item.retained),
+                item.shallow,
+                item.name
+            ));
+        }
+        if self.items.len() > MAX_ITEMS {
+            out.push_str(&format!(
+                " ... and {} more items\n",
+                self.items.len() - MAX_ITEMS
+            ));
+        }
+        out.push_str(&format!("\n {:>9} {:>7}  {}\n", "Shallow", "%", "Crate"));
+        for (name, size) in &self.crates {
+            out.push_str(&format!(
+                " {:>9} {:>6.2}%  {}\n",
+                size,
+                percent(*size),
+                name
+            ));
+        }
+        out.push_str(&format!(" {:>9} {:>6.2}%  {}", self.total, 100.0, "Total"));
+        out
+    }
+}
+
+/// Sizes of the sections and functions of a build, kept to compare the next build against.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeRecord {
+    /// Size of each section of the final wasm in bytes.
+    pub sections: BTreeMap<String, u32>,
+    /// Size of each function in bytes, before wasm-opt optimizations.
+    ///
+    /// Sizes of functions with the same demangled name, e.g. instances of a generic function,
+    /// are summed up.
+    pub functions: BTreeMap<String, u32>,
+}
+
+impl SizeRecord {
+    /// Creates the record from the size report of the build and its final wasm.
+    pub fn new(report: &SizeReport, final_wasm: &Module) -> Result<Self> {
+        let mut functions = BTreeMap::new();
+        for item in &report.items {
+            *functions.entry(item.name.clone()).or_default() += item.shallow;
+        }
+        let mut sections = BTreeMap::new();
+        for section in final_wasm.sections() {
+            let mut buf = Vec::new();
+            section
+                .clone()
+                .serialize(&mut buf)
+                .map_err(|err| anyhow::anyhow!("Serializing section failed: {}", err))?;
+            *sections.entry(section_name(section)).or_default() += buf.len() as u32;
+        }
+        Ok(SizeRecord {
+            sections,
+            functions,
+        })
+    }
+
+    /// Replaces the record at `path` with this record.
+    ///
+    /// Returns the changes compared to the replaced record, if there was one.
+    pub fn update(&self, path: &Path) -> Result<Option<SizeDiff>> {
+        let previous = match fs::read(path) {
+            Ok(json) => serde_json::from_slice::<SizeRecord>(&json)
+                .map_err(|err| log::warn!("Ignoring invalid size record: {}", err))
+                .ok(),
+            Err(_) => None,
+        };
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json).context(format!("Writing size record to {}", path.display()))?;
+        Ok(previous.map(|previous| SizeDiff::new(&previous, self)))
+    }
+}
+
+/// Returns the name of a section, as used in the size record.
+fn section_name(section: &Section) -> String {
+    let name = match section {
+        Section::Unparsed { id, .. } => return format!("unparsed[{}]", id),
+        Section::Custom(custom) => return format!("custom[{}]", custom.name()),
+        Section::Type(_) => "type",
+        Section::Import(_) => "import",
+        Section::Function(_) => "function",
+        Section::Table(_) => "table",
+        Section::Memory(_) => "memory",
+        Section::Global(_) => "global",
+        Section::Export(_) => "export",
+        Section::Start(_) => "start",
+        Section::Element(_) => "element",
+        Section::DataCount(_) => "datacount",
+        Section::Code(_) => "code",
+        Section::Data(_) => "data",
+        Section::Name(_) => "custom[name]",
+        Section::Reloc(_) => "reloc",
+    };
+    name.to_string()
+}
+
+/// A changed item between two builds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeChange {
+    /// The item, e.g. `section code` or `function core::fmt::write`.
+    pub item: String,
+    /// The size in the previous build, `None` if the item was added.
+    pub old: Option<u32>,
+    /// The size in this build, `None` if the item was removed.
+    pub new: Option<u32>,
+}
+
+impl SizeChange {
+    /// The difference in size in bytes.
+    pub fn delta(&self) -> i64 {
+        self.new.unwrap_or_default() as i64 - self.old.unwrap_or_default() as i64
+    }
+}
+
+/// Changes of the sizes of sections and functions since the previous build.
+#[derive(Debug, Clone, Default)]
+pub struct SizeDiff {
+    /// The changed sections and functions, by descending absolute delta.
+    pub changes: Vec<SizeChange>,
+}
+
+impl SizeDiff {
+    /// Compares the `current` record to the `previous` one.
+    pub fn new(previous: &SizeRecord, current: &SizeRecord) -> Self {
+        let mut changes = Vec::new();
+        let mut compare =
+            |kind: &str, previous: &BTreeMap<String, u32>, current: &BTreeMap<String, u32>| {
+                let mut names = previous.keys().chain(current.keys()).collect::<Vec<_>>();
+                names.sort();
+                names.dedup();
+                for name in names {
+                    let (old, new) = (previous.get(name).copied(), current.get(name).copied());
+                    if old != new {
+                        changes.push(SizeChange {
+                            item: format!("{} {}", kind, name),
+                            old,
+                            new,
+                        });
+                    }
+                }
+            };
+        compare("section", &previous.sections, &current.sections);
+        compare("function", &previous.functions, &current.functions);
+        changes.sort_by(|a, b| {
+            b.delta()
+                .abs()
+                .cmp(&a.delta().abs())
+                .then_with(|| a.item.cmp(&b.item))
+        });
+        SizeDiff { changes }
+    }
+
+    /// Returns `true` if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns a human readable table of the largest changes.
+    pub fn display(&self) -> String {
+        let size = |size: Option<u32>| size.map_or("-".to_string(), |size| size.to_string());
+        let mut out = format!(
+            "{}\n {:>8} {:>9} {:>9}  {}\n",
+            "Size changes since the previous build:".bold(),
+            "Delta",
+            "Old",
+            "New",
+            "Item"
+        );
+        for change in self.changes.iter().take(MAX_ITEMS) {
+            let delta = format!("{:+}", change.delta());
+            let delta = match (change.old, change.new) {
+                (None, _) => format!("{:>8}", delta).green(),
+                (_, None) => format!("{:>8}", delta).red(),
+                _ if change.delta() > 0 => format!("{:>8}", delta).yellow(),
+                _ => format!("{:>8}", delta).normal(),
+            };
+            let status = match (change.old, change.new) {
+                (None, _) => " (added)",
+                (_, None) => " (removed)",
+                _ => "",
+            };
+            out.push_str(&format!(
+                " {} {:>9} {:>9}  {}{}\n",
+                delta,
+                size(change.old),
+                size(change.new),
+                change.item,
+                status
+            ));
+        }
+        if self.changes.len() > MAX_ITEMS {
+            out.push_str(&format!(
+                " ... and {} more changes\n",
+                self.changes.len() - MAX_ITEMS
+            ));
+        }
+        out
+    }
+}
+
+/// Returns the name of the crate the demangled item `name` is defined in.
+///
+/// For trait implementations like `<T as core::fmt::Debug>::fmt` this is the crate of the
+/// implementing type.
+pub(crate) fn crate_name(name: &str) -> String {
+    let path = name.trim_start_matches(['<', '&']);
+    let path = path.trim_start_matches("mut ").trim_start_matches("dyn ");
+    match path.find("::") {
+        Some(end) if !path[..end].contains(' ') => path[..end].to_string(),
+        _ => UNKNOWN_CRATE.to_string(),
+    }
+}
+
+/// Directed graph with node `0` as the root.
+struct Graph {
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    fn new(nodes: usize) -> Self {
+        Graph {
+            successors: vec![Vec::new(); nodes],
+            predecessors: vec![Vec::new(); nodes],
+        }
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize) {
+        if to < self.successors.len() && !self.successors[from].contains(&to) {
+            self.successors[from].push(to);
+            self.predecessors[to].push(from);
+        }
+    }
+
+    /// Returns the nodes reachable from the root in depth-first postorder.
+    fn postorder(&self) -> Vec<usize> {
+        let mut visited = vec![false; self.successors.len()];
+        let mut order = Vec::new();
+        // stack of (node, index of the next successor to visit)
+        let mut stack = vec![(0, 0)];
+        visited[0] = true;
+        while let Some((node, next)) = stack.pop() {
+            if let Some(&successor) = self.successors[node].get(next) {
+                stack.push((node, next + 1));
+                if !visited[successor] {
+                    visited[successor] = true;
+                    stack.push((successor, 0));
+                }
+            } else {
+                order.push(node);
+            }
+        }
+        order
+    }
+
+    /// Computes the immediate dominator of every node reachable from the root, using the
+    /// iterative algorithm of Cooper, Harvey and Kennedy.
+    ///
+    /// The root is its own dominator, unreachable nodes have none.
+    fn immediate_dominators(&self) -> Vec<Option<usize>> {
+        let postorder = self.postorder();
+        let mut position = vec![None; self.successors.len()];
+        for (index, node) in postorder.iter().enumerate() {
+            position[*node] = Some(index);
+        }
+
+        let mut dominators = vec![None; self.successors.len()];
+        dominators[0] = Some(0);
+        let intersect = |dominators: &[Option<usize>], mut a: usize, mut b: usize| {
+            while a != b {
+                while position[a] < position[b] {
+                    a = dominators[a].expect("processed nodes have a dominator; qed");
+                }
+                while position[b] < position[a] {
+                    b = dominators[b].expect("processed nodes have a dominator; qed");
+                }
+            }
+            a
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in postorder.iter().rev().filter(|node| **node != 0) {
+                let mut processed = self.predecessors[node]
+                    .iter()
+                    .copied()
+                    .filter(|pred| dominators[*pred].is_some());
+                let first = processed
+                    .next()
+                    .expect("in reverse postorder a predecessor has been processed; qed");
+                let dominator = processed.fold(first, |dominator, pred| {
+                    intersect(&dominators, pred, dominator)
+                });
+                if dominators[node] != Some(dominator) {
+                    dominators[node] = Some(dominator);
+                    changed = true;
+                }
+            }
+        }
+        dominators
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_module(contract: &str) -> Module {
+        let wasm = wabt::Wat2Wasm::new()
+            .write_debug_names(true)
+            .convert(contract)
+            .expect("invalid wabt");
+        parity_wasm::deserialize_buffer(wasm.as_ref()).expect("deserializing must work")
+    }
+
+    #[test]
+    fn shared_callees_are_retained_by_common_dominator() {
+        // given
+        let contract = r#"
+            (module
+                (func $call (export "call") (call $dispatch))
+                (func $deploy (export "deploy") (call $dispatch))
+                (func $dispatch (call $a) (call $b))
+                (func $a (call $shared))
+                (func $b (call $shared))
+                (func $shared (nop) (nop) (nop))
+            )"#;
+        let module = create_module(contract);
+
+        // when
+        let report = SizeReport::analyze(&module).expect("analysis failed");
+
+        // then
+        let item = |name: &str| {
+            report
+                .items
+                .iter()
+                .find(|item| item.name.ends_with(name))
+                .unwrap_or_else(|| panic!("item {} not found", name))
+                .clone()
+        };
+        let (dispatch, a, b, shared) = (item("dispatch"), item("a"), item("b"), item("shared"));
+        // `shared` is reachable via `a` and `b`, so neither of them dominates it
+        assert_eq!(a.retained, a.shallow);
+        assert_eq!(b.retained, b.shallow);
+        assert_eq!(
+            dispatch.retained,
+            dispatch.shallow + a.shallow + b.shallow + shared.shallow
+        );
+        assert_eq!(
+            report.total,
+            report.items.iter().map(|item| item.shallow).sum::<u32>()
+        );
+        assert_eq!(report.items[0].name, "dispatch");
+    }
+
+    #[test]
+    fn size_diff_contains_added_removed_and_grown_items() {
+        // given
+        let record = |functions: &[(&str, u32)]| SizeRecord {
+            sections: Default::default(),
+            functions: functions
+                .iter()
+                .map(|(name, size)| (name.to_string(), *size))
+                .collect(),
+        };
+        let previous = record(&[("removed", 10), ("grown", 20), ("unchanged", 30)]);
+        let current = record(&[("added", 5), ("grown", 50), ("unchanged", 30)]);
+
+        // when
+        let diff = SizeDiff::new(&previous, &current);
+
+        // then
+        let changes = diff
+            .changes
+            .iter()
+            .map(|change| (change.item.as_str(), change.delta()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                ("function grown", 30),
+                ("function removed", -10),
+                ("function added", 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn size_record_update_returns_diff_to_previous_build() {
+        crate::util::tests::with_tmp_dir(|path| {
+            // given
+            let path = path.join("sizes.json");
+            let mut record = SizeRecord::default();
+            record.sections.insert("code".to_string(), 100);
+            assert!(record.update(&path)?.is_none());
+
+            // when
+            record.sections.insert("code".to_string(), 120);
+            let diff = record.update(&path)?.expect("previous record must exist");
+
+            // then
+            assert_eq!(diff.changes.len(), 1);
+            assert_eq!(diff.changes[0].delta(), 20);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn crates_are_extracted_from_demangled_names() {
+        assert_eq!(crate_name("core::fmt::write"), "core");
+        assert_eq!(
+            crate_name("<core::fmt::Error as core::fmt::Debug>::fmt"),
+            "core"
+        );
+        assert_eq!(crate_name("<&T as core::fmt::Display>::fmt"), UNKNOWN_CRATE);
+        assert_eq!(crate_name("memcpy"), UNKNOWN_CRATE);
+    }
+}
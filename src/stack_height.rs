@@ -0,0 +1,344 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Estimation of the stack height of a contract, as limited by the `stack_height` of the
+//! `pallet-contracts` schedule.
+//!
+//! `pallet-contracts` instruments the contract to count the cost of every called function, which
+//! is the number of its parameters and locals plus the maximum height of its operand stack.
+//! The estimate is the maximum total cost of any call chain starting at the `call` or `deploy`
+//! export. Indirect calls are not followed, hence the actual stack height may be larger.
+
+use anyhow::Result;
+use parity_wasm::elements::{
+    BlockType, FuncBody, ImportCountType, Instruction, Internal, Module, Type,
+};
+use std::collections::HashMap;
+
+/// The estimated stack height of the deepest call chain of a contract.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackHeight {
+    /// The estimated stack height.
+    pub height: u32,
+    /// The function indices of the deepest call chain, starting at the exported function.
+    pub call_chain: Vec<u32>,
+}
+
+/// Estimates the maximum stack height of the contract.
+///
+/// Returns `None` if a recursive call chain is reachable from the exports, since its height is
+/// not bounded statically.
+pub fn estimate(module: &Module) -> Result<Option<StackHeight>> {
+    let estimator = Estimator::new(module)?;
+    let entries = module
+        .export_section()
+        .map(|exports| {
+            exports
+                .entries()
+                .iter()
+                .filter(|export| export.field() == "call" || export.field() == "deploy")
+                .filter_map(|export| match export.internal() {
+                    Internal::Function(index) => Some(*index),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut memo = HashMap::new();
+    let mut deepest: Option<StackHeight> = None;
+    for entry in entries {
+        let height = match estimator.chain(entry, &mut memo, &mut Vec::new()) {
+            Some(height) => height,
+            None => return Ok(None),
+        };
+        if deepest.iter().all(|deepest| height.height > deepest.height) {
+            deepest = Some(height);
+        }
+    }
+    Ok(Some(deepest.unwrap_or(StackHeight {
+        height: 0,
+        call_chain: Vec::new(),
+    })))
+}
+
+/// Computes the stack costs of the functions of a module.
+struct Estimator<'a> {
+    /// The number of imported functions, which precede the defined functions in the index space.
+    imported: u32,
+    /// The parameter and result counts of each function in the index space.
+    signatures: Vec<(u32, u32)>,
+    /// The parameter and result counts of each type.
+    types: Vec<(u32, u32)>,
+    bodies: &'a [FuncBody],
+}
+
+impl<'a> Estimator<'a> {
+    fn new(module: &'a Module) -> Result<Self> {
+        let types = module
+            .type_section()
+            .map(|section| section.types())
+            .unwrap_or_default()
+            .iter()
+            .map(|Type::Function(func)| (func.params().len() as u32, func.results().len() as u32))
+            .collect::<Vec<_>>();
+        let signature = |type_ref: u32| {
+            types
+                .get(type_ref as usize)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("Unknown function type {}", type_ref))
+        };
+        let mut signatures = Vec::new();
+        if let Some(imports) = module.import_section() {
+            for import in imports.entries() {
+                if let parity_wasm::elements::External::Function(type_ref) = import.external() {
+                    signatures.push(signature(*type_ref)?);
+                }
+            }
+        }
+        if let Some(functions) = module.function_section() {
+            for function in functions.entries() {
+                signatures.push(signature(function.type_ref())?);
+            }
+        }
+        Ok(Self {
+            imported: module.import_count(ImportCountType::Function) as u32,
+            signatures,
+            bodies: module
+                .code_section()
+                .map(|section| section.bodies())
+                .unwrap_or_default(),
+            types,
+        })
+    }
+
+    /// Returns the deepest call chain starting at the function `index`, or `None` if it is
+    /// recursive.
+    fn chain(
+        &self,
+        index: u32,
+        memo: &mut HashMap<u32, StackHeight>,
+        visiting: &mut Vec<u32>,
+    ) -> Option<StackHeight> {
+        if let Some(height) = memo.get(&index) {
+            return Some(height.clone());
+        }
+        // the host functions don't use the stack of the contract
+        let body = match index.checked_sub(self.imported) {
+            Some(body) => self.bodies.get(body as usize)?,
+            None => {
+                return Some(StackHeight {
+                    height: 0,
+                    call_chain: Vec::new(),
+                })
+            }
+        };
+        if visiting.contains(&index) {
+            return None;
+        }
+        visiting.push(index);
+
+        let mut deepest_callee = StackHeight {
+            height: 0,
+            call_chain: Vec::new(),
+        };
+        for instruction in body.code().elements() {
+            if let Instruction::Call(callee) = instruction {
+                let callee = self.chain(*callee, memo, visiting)?;
+                if callee.height > deepest_callee.height {
+                    deepest_callee = callee;
+                }
+            }
+        }
+        visiting.pop();
+
+        let mut call_chain = vec![index];
+        call_chain.extend(deepest_callee.call_chain);
+        let height = StackHeight {
+            height: self.cost(index, body) + deepest_callee.height,
+            call_chain,
+        };
+        memo.insert(index, height.clone());
+        Some(height)
+    }
+
+    /// Returns the stack cost of the function at `index`, i.e. the number of its parameters and
+    /// locals plus the maximum height of its operand stack.
+    fn cost(&self, index: u32, body: &FuncBody) -> u32 {
+        let params = self
+            .signatures
+            .get(index as usize)
+            .map_or(0, |(params, _)| *params);
+        let locals = body.locals().iter().map(|local| local.count()).sum::<u32>();
+        params + locals + self.max_operand_height(body)
+    }
+
+    /// Simulates the operand stack of the function body and returns its maximum height.
+    ///
+    /// At the end of a block the height is reset to its height at the start of the block plus
+    /// the block results.
+    fn max_operand_height(&self, body: &FuncBody) -> u32 {
+        let mut height: u32 = 0;
+        let mut max_height = 0;
+        // the heights at the start of the open blocks and their result counts
+        let mut frames: Vec<(u32, u32)> = Vec::new();
+        for instruction in body.code().elements() {
+            let (pops, pushes) = match instruction {
+                Instruction::Block(block_type) | Instruction::Loop(block_type) => {
+                    frames.push((height, block_results(block_type)));
+                    (0, 0)
+                }
+                Instruction::If(block_type) => {
+                    height = height.saturating_sub(1);
+                    frames.push((height, block_results(block_type)));
+                    (0, 0)
+                }
+                Instruction::Else => {
+                    if let Some((start, _)) = frames.last() {
+                        height = *start;
+                    }
+                    (0, 0)
+                }
+                Instruction::End => {
+                    if let Some((start, results)) = frames.pop() {
+                        height = start + results;
+                    }
+                    (0, 0)
+                }
+                Instruction::Call(callee) => self
+                    .signatures
+                    .get(*callee as usize)
+                    .copied()
+                    .unwrap_or_default(),
+                Instruction::CallIndirect(type_ref, _) => {
+                    let (params, results) = self
+                        .types
+                        .get(*type_ref as usize)
+                        .copied()
+                        .unwrap_or_default();
+                    (params + 1, results)
+                }
+                instruction => stack_effect(instruction),
+            };
+            height = height.saturating_sub(pops) + pushes;
+            max_height = max_height.max(height);
+        }
+        max_height
+    }
+}
+
+/// Returns the number of results of a block.
+fn block_results(block_type: &BlockType) -> u32 {
+    match block_type {
+        BlockType::Value(_) => 1,
+        _ => 0,
+    }
+}
+
+/// Returns the number of operands popped and pushed by an instruction which neither affects the
+/// control flow nor calls a function.
+fn stack_effect(instruction: &Instruction) -> (u32, u32) {
+    use Instruction::*;
+    match instruction {
+        Unreachable | Nop | Br(_) | Return | TeeLocal(_) => (0, 0),
+        I32Const(_) | I64Const(_) | F32Const(_) | F64Const(_) | GetLocal(_) | GetGlobal(_)
+        | CurrentMemory(_) => (0, 1),
+        Drop | SetLocal(_) | SetGlobal(_) | BrIf(_) | BrTable(_) => (1, 0),
+        Select => (3, 1),
+        I32Store(..) | I64Store(..) | F32Store(..) | F64Store(..) | I32Store8(..)
+        | I32Store16(..) | I64Store8(..) | I64Store16(..) | I64Store32(..) => (2, 0),
+        I32Eq | I32Ne | I32LtS | I32LtU | I32GtS | I32GtU | I32LeS | I32LeU | I32GeS | I32GeU
+        | I64Eq | I64Ne | I64LtS | I64LtU | I64GtS | I64GtU | I64LeS | I64LeU | I64GeS | I64GeU
+        | F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge | F64Eq | F64Ne | F64Lt | F64Gt | F64Le
+        | F64Ge | I32Add | I32Sub | I32Mul | I32DivS | I32DivU | I32RemS | I32RemU | I32And
+        | I32Or | I32Xor | I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr | I64Add | I64Sub
+        | I64Mul | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or | I64Xor | I64Shl
+        | I64ShrS | I64ShrU | I64Rotl | I64Rotr | F32Add | F32Sub | F32Mul | F32Div | F32Min
+        | F32Max | F32Copysign | F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max
+        | F64Copysign => (2, 1),
+        // the remaining instructions are unary operations, conversions, loads and
+        // `memory.grow`, which replace their operand
+        _ => (1, 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_module(contract: &str) -> Module {
+        let wasm = wabt::wat2wasm(contract).expect("invalid wabt");
+        parity_wasm::deserialize_buffer(&wasm).expect("deserializing must work")
+    }
+
+    #[test]
+    fn deepest_call_chain_is_estimated() {
+        // given
+        let contract = r#"
+            (module
+                (func (;0;) (export "call") (call 1 (i32.const 0)) (call 2))
+                (func (;1;) (param i32) (local i64 i64)
+                    (drop (i32.add (local.get 0) (i32.const 1))))
+                (func (;2;) (drop (i32.const 1)))
+                (func (;3;) (export "deploy") (call 2))
+            )"#;
+        let module = create_module(contract);
+
+        // when
+        let height = estimate(&module).unwrap().unwrap();
+
+        // then
+        // function 0 has an operand stack of one, function 1 has one parameter, two locals and
+        // an operand stack of two
+        assert_eq!(height.height, 6);
+        assert_eq!(height.call_chain, vec![0, 1]);
+    }
+
+    #[test]
+    fn block_results_are_kept_on_the_stack() {
+        // given
+        let contract = r#"
+            (module
+                (func (;0;) (export "call")
+                    (drop (block (result i32) (i32.const 1)))
+                    (drop (i32.const 1)))
+            )"#;
+        let module = create_module(contract);
+
+        // when
+        let height = estimate(&module).unwrap().unwrap();
+
+        // then
+        assert_eq!(height.height, 1);
+    }
+
+    #[test]
+    fn recursion_is_not_estimated() {
+        // given
+        let contract = r#"
+            (module
+                (func (;0;) (export "call") (call 1))
+                (func (;1;) (call 0))
+            )"#;
+        let module = create_module(contract);
+
+        // when
+        let height = estimate(&module).unwrap();
+
+        // then
+        assert_eq!(height, None);
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Support for the toolchain pinned via `toolchain` in the `[package.metadata.contract]` of the
+//! contract's `Cargo.toml`, e.g. `toolchain = "nightly-2021-05-20"`.
+//!
+//! The pinned toolchain is installed via `rustup` if it is missing, and all cargo invocations for
+//! the contract are run with it via `rustup run <toolchain>`.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use rustc_version::VersionMeta;
+use std::{
+    io::{self, BufRead, Write},
+    process::Command,
+    str,
+};
+
+/// Environment variable which, if set to `yes`, allows installing the pinned toolchain without
+/// asking first.
+///
+/// Useful for non-interactive environments like CI.
+const INSTALL_ENV_VAR: &str = "CARGO_CONTRACT_INSTALL_TOOLCHAIN";
+
+/// Returns the `rustc` version of the pinned `toolchain`, or of the active one if none is
+/// pinned.
+pub(crate) fn version_meta(toolchain: Option<&str>) -> Result<VersionMeta> {
+    match toolchain {
+        Some(toolchain) => {
+            let mut cmd = Command::new("rustup");
+            cmd.args(["run", toolchain, "rustc"]);
+            VersionMeta::for_command(cmd).context(format!(
+                "Determining the rustc version of the {} toolchain failed",
+                toolchain
+            ))
+        }
+        None => Ok(rustc_version::version_meta()?),
+    }
+}
+
+/// Returns the command invoking `cargo` of the pinned `toolchain`, or of the active one if none
+/// is pinned.
+pub(crate) fn cargo_command(toolchain: Option<&str>) -> Command {
+    match toolchain {
+        Some(toolchain) => {
+            let mut cmd = Command::new("rustup");
+            cmd.args(["run", toolchain, "cargo"]);
+            cmd
+        }
+        None => {
+            let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+            Command::new(cargo)
+        }
+    }
+}
+
+/// Returns the command invoking `rustc` of the pinned `toolchain`, or of the active one if none
+/// is pinned.
+pub(crate) fn rustc_command(toolchain: Option<&str>) -> Command {
+    match toolchain {
+        Some(toolchain) => {
+            let mut cmd = Command::new("rustup");
+            cmd.args(["run", toolchain, "rustc"]);
+            cmd
+        }
+        None => {
+            let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+            Command::new(rustc)
+        }
+    }
+}
+
+/// Ensures the pinned `toolchain` is installed, together with the components required for
+/// building contracts.
+///
+/// If it is missing the user is asked whether it should be installed via `rustup`.
+pub(crate) fn ensure_installed(toolchain: &str) -> Result<()> {
+    let output = Command::new("rustup")
+        .args(["toolchain", "list"])
+        .output()
+        .context(
+            "Executing `rustup toolchain list` failed, rustup is required for pinned toolchains",
+        )?;
+    let installed = str::from_utf8(&output.stdout)?;
+    if is_installed(installed, toolchain) {
+        log::info!("Using the pinned toolchain {}", toolchain);
+        return Ok(());
+    }
+    if !install_confirmed(toolchain)? {
+        anyhow::bail!(
+            "The pinned toolchain {} is not installed. Install it via\n\
+            rustup toolchain install {} --component rust-src --target wasm32-unknown-unknown",
+            toolchain,
+            toolchain
+        )
+    }
+    let status = Command::new("rustup")
+        .args([
+            "toolchain",
+            "install",
+            toolchain,
+            "--profile",
+            "minimal",
+            "--component",
+            "rust-src",
+            "--target",
+            "wasm32-unknown-unknown",
+        ])
+        .status()
+        .context("Executing `rustup toolchain install` failed")?;
+    if !status.success() {
+        anyhow::bail!("Installing the pinned toolchain {} failed", toolchain)
+    }
+    Ok(())
+}
+
+/// Returns `true` if `toolchain` is contained in the output of `rustup toolchain list`.
+///
+/// The listed toolchains carry the host triple, e.g. `nightly-2021-05-20-x86_64-unknown-linux-gnu`,
+/// which a pinned toolchain usually omits. The host triple never starts with a digit, unlike the
+/// date of a toolchain.
+fn is_installed(toolchain_list: &str, toolchain: &str) -> bool {
+    toolchain_list.lines().any(|line| {
+        let installed = line.split_whitespace().next().unwrap_or_default();
+        match installed.strip_prefix(toolchain) {
+            Some("") => true,
+            Some(host) => matches!(
+                host.strip_prefix('-'),
+                Some(host) if !host.starts_with(|c: char| c.is_ascii_digit())
+            ),
+            None => false,
+        }
+    })
+}
+
+/// Asks the user whether the pinned `toolchain` should be installed.
+fn install_confirmed(toolchain: &str) -> Result<bool> {
+    if std::env::var(INSTALL_ENV_VAR).as_deref() == Ok("yes") {
+        return Ok(true);
+    }
+    eprint!(
+        "{} The contract pins the toolchain {}, which is not installed. Install it via rustup? \
+        [y/N] ",
+        "warning:".yellow().bold(),
+        toolchain
+    );
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOOLCHAIN_LIST: &str = "stable-x86_64-unknown-linux-gnu (default)\n\
+        nightly-2021-05-20-x86_64-unknown-linux-gnu\n";
+
+    #[test]
+    fn installed_toolchain_is_detected() {
+        assert!(is_installed(TOOLCHAIN_LIST, "nightly-2021-05-20"));
+        assert!(is_installed(
+            TOOLCHAIN_LIST,
+            "nightly-2021-05-20-x86_64-unknown-linux-gnu"
+        ));
+        assert!(is_installed(TOOLCHAIN_LIST, "stable"));
+    }
+
+    #[test]
+    fn missing_toolchain_is_detected() {
+        assert!(!is_installed(TOOLCHAIN_LIST, "nightly"));
+        assert!(!is_installed(TOOLCHAIN_LIST, "nightly-2021-05-2"));
+        assert!(!is_installed(TOOLCHAIN_LIST, "nightly-2021-06-01"));
+    }
+}
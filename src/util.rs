@@ -14,14 +14,23 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::Verbosity;
+use crate::{toolchain, Verbosity};
 use anyhow::{Context, Result};
 use rustc_version::Channel;
-use std::{ffi::OsStr, path::Path, process::Command};
+use sha2::{Digest, Sha256};
+use std::{
+    ffi::OsStr,
+    io::Read,
+    path::{Path, PathBuf},
+};
 
-/// Check whether the current rust channel is valid: `nightly` is recommended.
-pub fn assert_channel() -> Result<()> {
-    let meta = rustc_version::version_meta()?;
+/// Maximum size of a downloaded release archive.
+const MAX_ARCHIVE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Check whether the rust channel of the pinned `toolchain`, or the current one if none is pinned,
+/// is valid: `nightly` is recommended.
+pub fn assert_channel(toolchain: Option<&str>) -> Result<()> {
+    let meta = toolchain::version_meta(toolchain)?;
     match meta.channel {
         Channel::Dev | Channel::Nightly => Ok(()),
         Channel::Stable | Channel::Beta => {
@@ -37,25 +46,36 @@ pub fn assert_channel() -> Result<()> {
 
 /// Run cargo with the supplied args
 ///
+/// The environment variables in `env` are set for the cargo process, or removed if their value
+/// is `None`. Cargo of the pinned `toolchain` is used, if any.
+///
 /// If successful, returns the stdout bytes
 pub(crate) fn invoke_cargo<I, S, P>(
     command: &str,
     args: I,
     working_dir: Option<P>,
     verbosity: Verbosity,
+    env: Vec<(&str, Option<&str>)>,
+    toolchain: Option<&str>,
 ) -> Result<Vec<u8>>
 where
     I: IntoIterator<Item = S> + std::fmt::Debug,
     S: AsRef<OsStr>,
     P: AsRef<Path>,
 {
-    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
-    let mut cmd = Command::new(cargo);
+    let mut cmd = toolchain::cargo_command(toolchain);
     if let Some(path) = working_dir {
         log::debug!("Setting cargo working dir to '{}'", path.as_ref().display());
         cmd.current_dir(path);
     }
 
+    for (key, value) in env {
+        match value {
+            Some(value) => cmd.env(key, value),
+            None => cmd.env_remove(key),
+        };
+    }
+
     cmd.arg(command);
     cmd.args(args);
     match verbosity {
@@ -92,6 +112,59 @@ pub(crate) fn base_name(path: &Path) -> &str {
         .expect("must be valid utf-8")
 }
 
+/// Returns the directory `name` within the cache directory of `cargo-contract`, which
+/// downloaded releases are installed into.
+pub(crate) fn cache_dir(name: &str) -> Result<PathBuf> {
+    let cache_dir = dirs::cache_dir()
+        .ok_or_else(|| anyhow::anyhow!("Unable to determine the cache directory"))?;
+    Ok(cache_dir.join("cargo-contract").join(name))
+}
+
+/// Downloads the release archive at `url` and verifies it against its SHA-256 `digest`.
+///
+/// The `download` is named in the error if the checksum doesn't match.
+pub(crate) fn download_archive(url: &str, digest: &str, download: &str) -> Result<Vec<u8>> {
+    eprintln!("Downloading {}", url);
+    let response = ureq::get(url)
+        .call()
+        .map_err(|err| anyhow::anyhow!("Downloading {} failed: {}", url, err))?;
+    let mut archive = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_ARCHIVE_SIZE)
+        .read_to_end(&mut archive)
+        .context(format!("Downloading {} failed", url))?;
+    verify_checksum(&archive, digest, download)?;
+    Ok(archive)
+}
+
+/// Attaches the bearer `token`, if any, to the `request` of an authenticated service.
+pub(crate) fn authorized(request: ureq::Request, token: Option<&str>) -> ureq::Request {
+    match token {
+        Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+        None => request,
+    }
+}
+
+/// Verifies the SHA-256 checksum of the downloaded `content` against the hex encoded `digest`.
+///
+/// The `download` is named in the error.
+fn verify_checksum(content: &[u8], digest: &str, download: &str) -> Result<()> {
+    let actual = Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    if actual != digest.to_lowercase() {
+        anyhow::bail!(
+            "Checksum mismatch of the downloaded {}: expected {}, got {}",
+            download,
+            digest,
+            actual
+        )
+    }
+    Ok(())
+}
+
 /// Prints to stdout if `verbosity.is_verbose()` is `true`.
 #[macro_export]
 macro_rules! maybe_println {
@@ -104,6 +177,7 @@ macro_rules! maybe_println {
 
 #[cfg(test)]
 pub mod tests {
+    use super::verify_checksum;
     use std::path::Path;
 
     pub fn with_tmp_dir<F>(f: F)
@@ -118,4 +192,59 @@ pub mod tests {
         // catch test panics in order to clean up temp dir which will be very large
         f(tmp_dir.path()).expect("Error executing test with tmp dir")
     }
+
+    #[test]
+    fn checksum_is_verified() {
+        // sha256 of `hello`
+        let digest = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(verify_checksum(b"hello", digest, "archive").is_ok());
+        assert!(verify_checksum(b"hello!", digest, "archive").is_err());
+        assert!(verify_checksum(b"hello", "", "archive").is_err());
+    }
+
+    /// Returns the metadata of an ERC-20 like contract, covering every kind of type definition.
+    pub fn erc20_metadata() -> serde_json::Value {
+        serde_json::json!({
+            "contract": { "name": "erc20" },
+            "V1": {
+                "types": [
+                    { "id": 0, "type": { "def": { "primitive": "u128" } } },
+                    { "id": 1, "type": { "path": ["ink_env", "types", "AccountId"],
+                      "def": { "composite": { "fields": [{ "type": 2 }] } } } },
+                    { "id": 2, "type": { "def": { "array": { "len": 32, "type": 3 } } } },
+                    { "id": 3, "type": { "def": { "primitive": "u8" } } },
+                    { "id": 4, "type": { "def": { "sequence": { "type": 3 } } } },
+                    { "id": 5, "type": { "path": ["erc20", "erc20", "Mode"], "def": { "variant": {
+                      "variants": [{ "name": "Off", "index": 0 },
+                                   { "name": "On", "index": 1, "fields": [{ "type": 0 }] }] } } } },
+                    { "id": 6, "type": { "path": ["Option"], "params": [5], "def": { "variant": {
+                      "variants": [{ "name": "None" }, { "name": "Some", "fields": [{ "type": 5 }] }]
+                    } } } },
+                    { "id": 7, "type": { "path": ["erc20", "erc20", "Limits"],
+                      "def": { "composite": { "fields": [
+                        { "name": "min", "type": 0 }, { "name": "max", "type": 0 }] } } } },
+                ],
+                "spec": {
+                    "constructors": [{ "label": "new", "args": [
+                        { "label": "supply", "type": { "type": 0 } },
+                        { "label": "mode", "type": { "type": 5 } },
+                    ] }],
+                    "messages": [
+                        { "label": "transfer", "args": [
+                            { "label": "to", "type": { "type": 1 } },
+                            { "label": "value", "type": { "type": 0 } },
+                        ] },
+                        { "label": "set_mode", "args": [
+                            { "label": "mode", "type": { "type": 6 } },
+                            { "label": "limits", "type": { "type": 7 } },
+                        ] },
+                        { "label": "set_data", "args": [
+                            { "label": "data", "type": { "type": 4 } },
+                        ] },
+                        { "label": "total_supply", "args": [] },
+                    ],
+                },
+            },
+        })
+    }
 }
@@ -14,15 +14,75 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+use crate::{size_report::crate_name, stack_height};
 use anyhow::Result;
 use colored::Colorize;
 use impl_serde::serialize as serde_hex;
-use parity_wasm::elements::Module;
+use parity_wasm::elements::{
+    Error as WasmError, External, ImportCountType, Instruction, Internal, Module, Type, ValueType,
+};
+use std::{fmt, str::FromStr};
 
 /// Marker inserted by the ink! codegen for an error which can't
 /// be checked at compile time.
 const INK_ENFORCE_ERR: &str = "__ink_enforce_error_";
 
+/// The host functions provided by `pallet-contracts`, by the module they are imported from.
+///
+/// Functions of a newer version of the API are provided in a new module, e.g. `seal1`.
+/// The `gas` function of `seal0` is omitted, since it is injected by the gas metering of
+/// `pallet-contracts` and must not be imported by the contract itself.
+const HOST_FUNCTIONS: &[(&str, &[&str])] = &[
+    (
+        "seal0",
+        &[
+            "seal_set_storage",
+            "seal_clear_storage",
+            "seal_get_storage",
+            "seal_transfer",
+            "seal_call",
+            "seal_instantiate",
+            "seal_terminate",
+            "seal_input",
+            "seal_return",
+            "seal_caller",
+            "seal_address",
+            "seal_weight_to_fee",
+            "seal_gas_left",
+            "seal_balance",
+            "seal_value_transferred",
+            "seal_random",
+            "seal_now",
+            "seal_minimum_balance",
+            "seal_tombstone_deposit",
+            "seal_restore_to",
+            "seal_deposit_event",
+            "seal_set_rent_allowance",
+            "seal_rent_allowance",
+            "seal_println",
+            "seal_debug_message",
+            "seal_block_number",
+            "seal_hash_sha2_256",
+            "seal_hash_keccak_256",
+            "seal_hash_blake2_256",
+            "seal_hash_blake2_128",
+            "seal_call_chain_extension",
+            "seal_rent_params",
+            "seal_ecdsa_recover",
+        ],
+    ),
+    (
+        "seal1",
+        &[
+            "seal_call",
+            "seal_instantiate",
+            "seal_terminate",
+            "seal_random",
+            "seal_restore_to",
+        ],
+    ),
+];
+
 /// Errors which may occur when forwarding a call is not allowed.
 ///
 /// We insert markers for these errors in the generated contract code.
@@ -71,23 +131,27 @@ pub enum EnforcedErrors {
 
 /// Validates the import section in the Wasm.
 ///
-/// The checks currently fall into two categories:
+/// The checks currently fall into three categories:
 /// - Known bugs for which we want to recommend a solution.
 /// - Markers inserted by the ink! codegen for errors which can't be checked at compile time.
+/// - Imports which are not provided by `pallet-contracts`, the upload of the contract would fail.
+///
+/// If the module still contains the `name` section, the functions calling an unknown import are
+/// reported together with their crate, as they are likely responsible for it.
 pub fn validate_import_section(module: &Module) -> Result<()> {
     let imports = match module.import_section() {
-        Some(section) => section.entries().iter(),
+        Some(section) => section.entries(),
         None => {
             // the module does not contain any imports,
             // hence no further validation is necessary.
             return Ok(());
         }
     };
-    let original_imports_len = imports.len();
     let mut errs = Vec::new();
+    let mut function_index = 0;
 
-    let filtered_imports = imports.filter(|section| {
-        let field = section.field();
+    for import in imports {
+        let field = import.field();
         if field.contains("panic") {
             errs.push(String::from(
                 "An unexpected panic function import was found in the contract Wasm.\n\
@@ -99,18 +163,488 @@ pub fn validate_import_section(module: &Module) -> Result<()> {
             ));
         } else if field.starts_with(INK_ENFORCE_ERR) {
             errs.push(parse_linker_error(field));
+        } else if let Err(err) = check_import(import.module(), field, import.external()) {
+            let callers = callers(module, function_index);
+            if callers.is_empty() {
+                errs.push(err);
+            } else {
+                errs.push(format!(
+                    "{}\n\nIt is called by:\n{}",
+                    err,
+                    callers
+                        .iter()
+                        .map(|caller| format!("  - {} (crate `{}`)", caller, crate_name(caller)))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ));
+            }
         }
+        if let External::Function(_) = import.external() {
+            function_index += 1;
+        }
+    }
 
-        match check_import(field) {
-            Ok(_) => true,
-            Err(err) => {
-                errs.push(err);
-                false
+    if !errs.is_empty() {
+        anyhow::bail!(format!(
+            "Validation of the Wasm failed.\n\n\n{}",
+            errs.into_iter()
+                .map(|err| format!("{} {}", "ERROR:".to_string().bold(), err))
+                .collect::<Vec<String>>()
+                .join("\n\n\n")
+        ));
+    }
+    Ok(())
+}
+
+/// Returns `Ok` if the import is provided by `pallet-contracts`.
+///
+/// Besides the host functions only the linear memory may be imported, as `env.memory`.
+pub(crate) fn check_import(module: &str, field: &str, external: &External) -> Result<(), String> {
+    match external {
+        External::Memory(_) if module == "env" && field == "memory" => return Ok(()),
+        External::Function(_) => (),
+        _ => {
+            return Err(format!(
+                "An unexpected import was found in the contract Wasm: {}.{}.\n\
+                Only the host functions and the linear memory `env.memory` may be imported.",
+                module, field
+            ))
+        }
+    }
+    match HOST_FUNCTIONS.iter().find(|(name, _)| *name == module) {
+        Some((_, functions)) if functions.contains(&field) => Ok(()),
+        Some(_) => Err(format!(
+            "An unexpected import function was found in the contract Wasm: {}.\n\
+            The module `{}` of pallet-contracts does not provide a host function of this name.",
+            field, module
+        )),
+        None => Err(format!(
+            "An unexpected import function was found in the contract Wasm: {}.\n\
+            Host functions are imported from one of the following modules of pallet-contracts, \
+            but it is imported from `{}`:\n{}",
+            field,
+            module,
+            HOST_FUNCTIONS
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Returns the demangled names of the functions calling the function at `index`.
+///
+/// Requires the `name` section, without it no callers are returned.
+fn callers(module: &Module, index: u32) -> Vec<String> {
+    let module = module.clone().parse_names().unwrap_or_else(|(_, m)| m);
+    let names = match module.names_section().and_then(|names| names.functions()) {
+        Some(functions) => functions.names(),
+        None => return Vec::new(),
+    };
+    let imported = module.import_count(ImportCountType::Function) as u32;
+    let bodies = match module.code_section() {
+        Some(code) => code.bodies(),
+        None => return Vec::new(),
+    };
+    let mut callers = bodies
+        .iter()
+        .enumerate()
+        .filter(|(_, body)| {
+            body.code()
+                .elements()
+                .iter()
+                .any(|instruction| instruction == &Instruction::Call(index))
+        })
+        .filter_map(|(body, _)| names.get(imported + body as u32))
+        .map(|name| format!("{:#}", rustc_demangle::demangle(name)))
+        .collect::<Vec<_>>();
+    callers.sort();
+    callers.dedup();
+    callers
+}
+
+/// Proposals extending the Wasm MVP, which are not supported by every chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WasmFeature {
+    SignExt,
+    BulkMemory,
+    SaturatingFloatToInt,
+    Simd,
+    Threads,
+    MultiValue,
+    MutableGlobals,
+    ReferenceTypes,
+}
+
+impl WasmFeature {
+    const ALL: [WasmFeature; 8] = [
+        WasmFeature::SignExt,
+        WasmFeature::BulkMemory,
+        WasmFeature::SaturatingFloatToInt,
+        WasmFeature::Simd,
+        WasmFeature::Threads,
+        WasmFeature::MultiValue,
+        WasmFeature::MutableGlobals,
+        WasmFeature::ReferenceTypes,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            WasmFeature::SignExt => "sign-ext",
+            WasmFeature::BulkMemory => "bulk-memory",
+            WasmFeature::SaturatingFloatToInt => "saturating-float-to-int",
+            WasmFeature::Simd => "simd",
+            WasmFeature::Threads => "threads",
+            WasmFeature::MultiValue => "multi-value",
+            WasmFeature::MutableGlobals => "mutable-globals",
+            WasmFeature::ReferenceTypes => "reference-types",
+        }
+    }
+
+    /// The name of the proposal as target feature of `rustc`.
+    fn target_feature(self) -> &'static str {
+        match self {
+            WasmFeature::SignExt => "sign-ext",
+            WasmFeature::BulkMemory => "bulk-memory",
+            WasmFeature::SaturatingFloatToInt => "nontrapping-fptoint",
+            WasmFeature::Simd => "simd128",
+            WasmFeature::Threads => "atomics",
+            WasmFeature::MultiValue => "multivalue",
+            WasmFeature::MutableGlobals => "mutable-globals",
+            WasmFeature::ReferenceTypes => "reference-types",
+        }
+    }
+
+    /// The name of the proposal as feature of `wasm-opt`.
+    pub fn binaryen_feature(self) -> &'static str {
+        match self {
+            WasmFeature::SaturatingFloatToInt => "nontrapping-float-to-int",
+            WasmFeature::Simd => "simd",
+            WasmFeature::Threads => "threads",
+            WasmFeature::MultiValue => "multivalue",
+            feature => feature.name(),
+        }
+    }
+
+    /// Returns `true` if the contract may be compiled with the proposal.
+    fn is_controllable(self) -> bool {
+        matches!(self, WasmFeature::MultiValue | WasmFeature::MutableGlobals)
+    }
+
+    /// Returns `true` if the instructions of the proposal can't be represented by `parity-wasm`
+    /// as used by `pwasm-utils`, hence can't be post-processed.
+    fn is_unprocessable(self) -> bool {
+        matches!(self, WasmFeature::SignExt | WasmFeature::BulkMemory)
+    }
+}
+
+/// Rejects enabling the proposals whose instructions can't be post-processed yet, i.e.
+/// `sign-ext` and `bulk-memory`.
+///
+/// The contract is always compiled without them, enabling them would only let the build fail
+/// later on.
+pub fn ensure_processable(enabled: &[WasmFeature]) -> Result<()> {
+    let unprocessable = enabled
+        .iter()
+        .filter(|feature| feature.is_unprocessable())
+        .map(|feature| format!("`{}`", feature))
+        .collect::<Vec<_>>();
+    if !unprocessable.is_empty() {
+        anyhow::bail!(
+            "Can't enable {} via `--wasm-features`, since cargo-contract can't post-process the \
+            instructions of these proposals yet. Remove them from the enabled proposals.",
+            unprocessable.join(", ")
+        )
+    }
+    Ok(())
+}
+
+/// Returns the proposals among the `allowed` ones which the contract is compiled with.
+///
+/// The remaining proposals are never used even if the target chain supports them.
+pub fn enabled_features(allowed: &[WasmFeature]) -> Vec<WasmFeature> {
+    WasmFeature::ALL
+        .iter()
+        .copied()
+        .filter(|feature| feature.is_controllable() && allowed.contains(feature))
+        .collect()
+}
+
+/// Returns the `-C target-feature` value which enables exactly the `enabled` proposals, e.g.
+/// `-sign-ext,-bulk-memory,..`.
+///
+/// Newer compilers enable some proposals by default, which older schedules of `pallet-contracts`
+/// reject. Since the standard library is built together with the contract, it is compiled with
+/// the same target features.
+pub fn target_features(enabled: &[WasmFeature]) -> String {
+    WasmFeature::ALL
+        .iter()
+        .map(|feature| {
+            let sign = if enabled.contains(feature) { '+' } else { '-' };
+            format!("{}{}", sign, feature.target_feature())
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl fmt::Display for WasmFeature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for WasmFeature {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        WasmFeature::ALL
+            .iter()
+            .copied()
+            .find(|feature| feature.name() == input)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown wasm feature '{}', expected one of: {}",
+                    input,
+                    WasmFeature::ALL
+                        .iter()
+                        .map(|feature| feature.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// Validates that the Wasm only uses the proposals beyond the Wasm MVP which are `allowed` by
+/// the target chain.
+///
+/// Only the proposals which can be represented after parsing are detected here, i.e.
+/// `multi-value` and `mutable-globals`. Instructions of the other proposals already fail the
+/// parsing of the Wasm, see [`describe_deserialization_error`].
+pub fn validate_features(module: &Module, allowed: &[WasmFeature]) -> Result<()> {
+    let mut used = Vec::new();
+
+    let multi_value = module
+        .type_section()
+        .map(|section| section.types())
+        .unwrap_or_default()
+        .iter()
+        .any(|Type::Function(func)| func.results().len() > 1);
+    if multi_value {
+        used.push(WasmFeature::MultiValue);
+    }
+
+    let mutable_global_import = module
+        .import_section()
+        .map(|section| section.entries())
+        .unwrap_or_default()
+        .iter()
+        .any(|entry| match entry.external() {
+            External::Global(global) => global.is_mutable(),
+            _ => false,
+        });
+    let imported_globals = module.import_count(ImportCountType::Global);
+    let mutable_global_export = module
+        .export_section()
+        .map(|section| section.entries())
+        .unwrap_or_default()
+        .iter()
+        .any(|entry| match entry.internal() {
+            Internal::Global(index) => {
+                let global = (*index as usize)
+                    .checked_sub(imported_globals)
+                    .and_then(|index| module.global_section()?.entries().get(index));
+                matches!(global, Some(global) if global.global_type().is_mutable())
             }
+            _ => false,
+        });
+    if mutable_global_import || mutable_global_export {
+        used.push(WasmFeature::MutableGlobals);
+    }
+
+    let disallowed = used
+        .into_iter()
+        .filter(|feature| !allowed.contains(feature))
+        .collect::<Vec<_>>();
+    if !disallowed.is_empty() {
+        anyhow::bail!(
+            "Validation of the Wasm failed.\n\n\n{} The contract Wasm uses the proposals {}, which \
+            are not supported by the target chain.\nIf the chain supports them, allow them via \
+            `--wasm-features` or `wasm-features` in the `[package.metadata.contract]` of your \
+            `Cargo.toml`.",
+            "ERROR:".bold(),
+            disallowed
+                .iter()
+                .map(|feature| format!("`{}`", feature))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+    Ok(())
+}
+
+/// Turns an error of parsing the Wasm into a descriptive error if it was caused by an
+/// instruction or type of a proposal beyond the Wasm MVP.
+///
+/// The instructions of these proposals can't be represented by `parity-wasm` as used by
+/// `pwasm-utils`, hence they are rejected even if the target chain supports them, i.e. they are
+/// `allowed`.
+pub fn describe_deserialization_error(err: WasmError, allowed: &[WasmFeature]) -> anyhow::Error {
+    let feature = match err {
+        WasmError::UnknownOpcode(0xC0..=0xC4) => Some(WasmFeature::SignExt),
+        WasmError::UnknownOpcode(0xFC) => Some(WasmFeature::BulkMemory),
+        WasmError::UnknownOpcode(0xFD) | WasmError::UnknownValueType(-0x05) => {
+            Some(WasmFeature::Simd)
         }
+        WasmError::UnknownOpcode(0xFE) => Some(WasmFeature::Threads),
+        WasmError::UnknownOpcode(0x1C)
+        | WasmError::UnknownOpcode(0x25..=0x26)
+        | WasmError::UnknownOpcode(0xD0..=0xD2)
+        | WasmError::UnknownValueType(-0x11)
+        | WasmError::UnknownValueType(-0x10)
+        | WasmError::UnknownTableElementType(_) => Some(WasmFeature::ReferenceTypes),
+        _ => None,
+    };
+    match feature {
+        Some(feature) if allowed.contains(&feature) => anyhow::anyhow!(
+            "The contract Wasm uses the `{}` proposal ({}), which is enabled via `--wasm-features` \
+            but can't be post-processed by cargo-contract yet. Remove it from the enabled \
+            proposals.",
+            feature,
+            err
+        ),
+        Some(WasmFeature::BulkMemory) => anyhow::anyhow!(
+            "The contract Wasm uses the `{}` or `{}` proposal ({}), which is not supported by \
+            pallet-contracts.",
+            WasmFeature::BulkMemory,
+            WasmFeature::SaturatingFloatToInt,
+            err
+        ),
+        Some(feature) => anyhow::anyhow!(
+            "The contract Wasm uses the `{}` proposal ({}), which is not supported by \
+            pallet-contracts.",
+            feature,
+            err
+        ),
+        None => anyhow::anyhow!("{}", err),
+    }
+}
+
+/// Limits of the `pallet-contracts` schedule of the target chain.
+///
+/// The defaults are the limits of the default schedule of `pallet-contracts`. Chains may
+/// configure different limits, which can be set in the `[package.metadata.contract.limits]` of
+/// the contract's `Cargo.toml`, e.g. `memory-pages = 32`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScheduleLimits {
+    /// The maximum number of linear memory pages of 64 KiB.
+    pub memory_pages: u32,
+    /// The maximum number of elements of the table.
+    pub table_size: u32,
+    /// The maximum height of the stack, as counted by the stack height instrumentation.
+    pub stack_height: u32,
+}
+
+impl Default for ScheduleLimits {
+    fn default() -> Self {
+        Self {
+            memory_pages: 16,
+            table_size: 4096,
+            stack_height: 64 * 1024,
+        }
+    }
+}
+
+/// Validates the declared memory and table of the Wasm against the `limits` of the schedule.
+///
+/// If `estimate_stack_height` is `true` the stack height of the deepest call chain is estimated
+/// and validated as well. The estimate does not follow indirect calls.
+pub fn validate_limits(
+    module: &Module,
+    limits: &ScheduleLimits,
+    estimate_stack_height: bool,
+) -> Result<()> {
+    let mut errs = Vec::new();
+
+    let memory = module.import_section().and_then(|section| {
+        section
+            .entries()
+            .iter()
+            .find_map(|entry| match entry.external() {
+                External::Memory(memory) => Some(memory),
+                _ => None,
+            })
     });
+    if let Some(memory) = memory {
+        let limits_exceeded = memory.limits().initial() > limits.memory_pages
+            || matches!(
+                memory.limits().maximum(),
+                Some(maximum) if maximum > limits.memory_pages
+            );
+        if limits_exceeded {
+            errs.push(format!(
+                "The contract declares a linear memory of {} to {} pages, \
+                the schedule allows at most {} pages.",
+                memory.limits().initial(),
+                memory
+                    .limits()
+                    .maximum()
+                    .map_or("unlimited".to_string(), |maximum| maximum.to_string()),
+                limits.memory_pages
+            ));
+        }
+    }
+
+    if let Some(tables) = module.table_section() {
+        for table in tables.entries() {
+            if table.limits().initial() > limits.table_size {
+                errs.push(format!(
+                    "The contract declares a table of {} elements, the schedule allows at most {}.\n\
+                    Large tables are usually caused by many trait objects or function pointers.",
+                    table.limits().initial(),
+                    limits.table_size
+                ));
+            }
+        }
+    }
+
+    if estimate_stack_height {
+        match stack_height::estimate(module)? {
+            Some(estimate) if estimate.height > limits.stack_height => {
+                let names = module.clone().parse_names().unwrap_or_else(|(_, m)| m);
+                let names = names
+                    .names_section()
+                    .and_then(|names| names.functions())
+                    .map(|functions| functions.names());
+                errs.push(format!(
+                    "The estimated stack height of the contract is {}, the schedule allows at \
+                    most {}.\nThe deepest call chain is:\n{}",
+                    estimate.height,
+                    limits.stack_height,
+                    estimate
+                        .call_chain
+                        .iter()
+                        .map(|index| {
+                            let name = names
+                                .and_then(|names| names.get(*index))
+                                .map(|name| format!("{:#}", rustc_demangle::demangle(name)))
+                                .unwrap_or_else(|| format!("func[{}]", index));
+                            format!("  - {}", name)
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ));
+            }
+            Some(_) => (),
+            None => log::warn!(
+                "The stack height of the contract can't be estimated, since it contains recursive \
+                calls"
+            ),
+        }
+    }
 
-    if original_imports_len != filtered_imports.count() {
+    if !errs.is_empty() {
         anyhow::bail!(format!(
             "Validation of the Wasm failed.\n\n\n{}",
             errs.into_iter()
@@ -122,22 +656,156 @@ pub fn validate_import_section(module: &Module) -> Result<()> {
     Ok(())
 }
 
-/// Returns `true` if the import is allowed.
-fn check_import(field: &str) -> Result<(), String> {
-    let allowed_prefixes = ["seal", "memory"];
-    if allowed_prefixes
-        .iter()
-        .any(|prefix| field.starts_with(prefix))
-    {
-        Ok(())
-    } else {
-        let msg = format!(
-            "An unexpected import function was found in the contract Wasm: {}.\n\
-            The only allowed import functions are those starting with one of the following prefixes:\n\
-            {}", field, allowed_prefixes.join(", ")
+/// Validates that the Wasm does not use floating point numbers.
+///
+/// `pallet-contracts` only accepts deterministic code and rejects floating point instructions and
+/// types at upload time. They are usually pulled in by a dependency, hence the offending
+/// functions are reported by name if the module still contains the `name` section.
+pub fn validate_no_floating_point(module: &Module) -> Result<()> {
+    let module = module.clone().parse_names().unwrap_or_else(|(_, m)| m);
+    let names = module
+        .names_section()
+        .and_then(|names| names.functions())
+        .map(|functions| functions.names());
+    let imported = module.import_count(ImportCountType::Function) as u32;
+    let types = module
+        .type_section()
+        .map(|section| section.types())
+        .unwrap_or_default();
+    let is_float = |ty: &ValueType| matches!(ty, ValueType::F32 | ValueType::F64);
+    let has_float_signature = |type_ref: u32| match types.get(type_ref as usize) {
+        Some(Type::Function(func)) => {
+            func.params().iter().any(is_float) || func.results().iter().any(is_float)
+        }
+        None => false,
+    };
+    let signatures = module
+        .function_section()
+        .map(|section| section.entries())
+        .unwrap_or_default();
+    let bodies = module
+        .code_section()
+        .map(|section| section.bodies())
+        .unwrap_or_default();
+
+    let mut offending = Vec::new();
+    for (body_index, body) in bodies.iter().enumerate() {
+        let float_signature = matches!(
+            signatures.get(body_index),
+            Some(func) if has_float_signature(func.type_ref())
         );
-        Err(msg)
+        let float_locals = body
+            .locals()
+            .iter()
+            .any(|local| is_float(&local.value_type()));
+        let float_instructions = body.code().elements().iter().any(is_float_instruction);
+        if float_signature || float_locals || float_instructions {
+            let index = imported + body_index as u32;
+            let name = names
+                .and_then(|names| names.get(index))
+                .map(|name| format!("{:#}", rustc_demangle::demangle(name)))
+                .unwrap_or_else(|| format!("func[{}]", index));
+            offending.push(name);
+        }
     }
+    if let Some(globals) = module.global_section() {
+        for (index, global) in globals.entries().iter().enumerate() {
+            if is_float(&global.global_type().content_type()) {
+                offending.push(format!("global[{}]", index));
+            }
+        }
+    }
+
+    if !offending.is_empty() {
+        anyhow::bail!(
+            "Validation of the Wasm failed.\n\n\n{} Floating point numbers are used by the \
+            contract Wasm, which would be rejected by pallet-contracts since their results are \
+            not deterministic.\nThey are used by:\n{}",
+            "ERROR:".bold(),
+            offending
+                .iter()
+                .map(|name| format!("  - {} (crate `{}`)", name, crate_name(name)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }
+    Ok(())
+}
+
+/// Returns `true` if the instruction operates on floating point numbers.
+fn is_float_instruction(instruction: &Instruction) -> bool {
+    use Instruction::*;
+    matches!(
+        instruction,
+        F32Load(..)
+            | F64Load(..)
+            | F32Store(..)
+            | F64Store(..)
+            | F32Const(_)
+            | F64Const(_)
+            | F32Eq
+            | F32Ne
+            | F32Lt
+            | F32Gt
+            | F32Le
+            | F32Ge
+            | F64Eq
+            | F64Ne
+            | F64Lt
+            | F64Gt
+            | F64Le
+            | F64Ge
+            | F32Abs
+            | F32Neg
+            | F32Ceil
+            | F32Floor
+            | F32Trunc
+            | F32Nearest
+            | F32Sqrt
+            | F32Add
+            | F32Sub
+            | F32Mul
+            | F32Div
+            | F32Min
+            | F32Max
+            | F32Copysign
+            | F64Abs
+            | F64Neg
+            | F64Ceil
+            | F64Floor
+            | F64Trunc
+            | F64Nearest
+            | F64Sqrt
+            | F64Add
+            | F64Sub
+            | F64Mul
+            | F64Div
+            | F64Min
+            | F64Max
+            | F64Copysign
+            | I32TruncSF32
+            | I32TruncUF32
+            | I32TruncSF64
+            | I32TruncUF64
+            | I64TruncSF32
+            | I64TruncUF32
+            | I64TruncSF64
+            | I64TruncUF64
+            | F32ConvertSI32
+            | F32ConvertUI32
+            | F32ConvertSI64
+            | F32ConvertUI64
+            | F32DemoteF64
+            | F64ConvertSI32
+            | F64ConvertUI32
+            | F64ConvertSI64
+            | F64ConvertUI64
+            | F64PromoteF32
+            | I32ReinterpretF32
+            | I64ReinterpretF64
+            | F32ReinterpretI32
+            | F64ReinterpretI64
+    )
 }
 
 /// Extracts the ink! linker error marker from the `field`, parses it, and
@@ -194,7 +862,11 @@ fn parse_linker_error(field: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::validate_import_section;
+    use super::{
+        describe_deserialization_error, enabled_features, ensure_processable, target_features,
+        validate_features, validate_import_section, validate_limits, validate_no_floating_point,
+        ScheduleLimits, WasmFeature,
+    };
     use parity_wasm::elements::Module;
 
     fn create_module(contract: &str) -> Module {
@@ -289,15 +961,265 @@ mod tests {
             .contains("An unexpected import function was found in the contract Wasm: some_fn."));
     }
 
+    #[test]
+    fn must_catch_unknown_host_function() {
+        // given
+        let contract = r#"
+            (module
+                (type (;0;) (func (param i32 i32 i32)))
+                (import "seal0" "seal_foo" (func (;0;) (type 0)))
+            )"#;
+        let module = create_module(contract);
+
+        // when
+        let res = validate_import_section(&module);
+
+        // then
+        assert!(res.is_err());
+        assert!(res.unwrap_err().to_string().contains(
+            "The module `seal0` of pallet-contracts does not provide a host function of this name."
+        ));
+    }
+
+    #[test]
+    fn must_report_callers_of_unknown_import() {
+        // given
+        let contract = r#"
+            (module
+                (import "seal0" "seal_foo" (func $seal_foo))
+                (func $_ZN8some_dep4frob17h0123456789abcdefE (call $seal_foo))
+                (func $call (export "call") (call $_ZN8some_dep4frob17h0123456789abcdefE))
+            )"#;
+        let wasm = wabt::Wat2Wasm::new()
+            .write_debug_names(true)
+            .convert(contract)
+            .expect("invalid wabt");
+        let module =
+            parity_wasm::deserialize_buffer(wasm.as_ref()).expect("deserializing must work");
+
+        // when
+        let res = validate_import_section(&module);
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains("It is called by:\n  - some_dep::frob (crate `some_dep`)"));
+        assert!(!err.contains("call (crate"));
+    }
+
+    #[test]
+    fn must_catch_float_instructions() {
+        // given
+        let contract = r#"
+            (module
+                (func $_ZN7libm_rs4sqrt17h0123456789abcdefE (param i32) (result i32)
+                    (i32.trunc_f32_s (f32.sqrt (f32.convert_i32_s (local.get 0)))))
+                (func $call (export "call") (drop (call $_ZN7libm_rs4sqrt17h0123456789abcdefE (i32.const 4))))
+            )"#;
+        let wasm = wabt::Wat2Wasm::new()
+            .write_debug_names(true)
+            .convert(contract)
+            .expect("invalid wabt");
+        let module =
+            parity_wasm::deserialize_buffer(wasm.as_ref()).expect("deserializing must work");
+
+        // when
+        let res = validate_no_floating_point(&module);
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains("  - libm_rs::sqrt (crate `libm_rs`)"));
+        assert!(!err.contains("  - call"));
+    }
+
+    #[test]
+    fn must_catch_float_signatures() {
+        // given
+        let contract = r#"
+            (module
+                (func (;0;) (param f64))
+            )"#;
+        let module = create_module(contract);
+
+        // when
+        let res = validate_no_floating_point(&module);
+
+        // then
+        assert!(res.unwrap_err().to_string().contains("  - func[0]"));
+    }
+
+    #[test]
+    fn must_accept_integer_only_code() {
+        // given
+        let contract = r#"
+            (module
+                (func (;0;) (param i64) (result i32) (i32.wrap_i64 (local.get 0)))
+            )"#;
+        let module = create_module(contract);
+
+        // when
+        let res = validate_no_floating_point(&module);
+
+        // then
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn must_catch_exceeded_memory_and_table_limits() {
+        // given
+        let contract = r#"
+            (module
+                (import "env" "memory" (memory (;0;) 2 32))
+                (table (;0;) 5000 funcref)
+            )"#;
+        let module = create_module(contract);
+
+        // when
+        let res = validate_limits(&module, &ScheduleLimits::default(), false);
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains("linear memory of 2 to 32 pages, the schedule allows at most 16"));
+        assert!(err.contains("table of 5000 elements, the schedule allows at most 4096"));
+    }
+
+    #[test]
+    fn must_catch_exceeded_stack_height() {
+        // given
+        let contract = r#"
+            (module
+                (func (;0;) (export "call") (local i64 i64 i64 i64))
+            )"#;
+        let module = create_module(contract);
+        let limits = ScheduleLimits {
+            stack_height: 3,
+            ..Default::default()
+        };
+
+        // when
+        let res = validate_limits(&module, &limits, true);
+
+        // then
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("The estimated stack height of the contract is 4"));
+        assert!(validate_limits(&module, &limits, false).is_ok());
+    }
+
+    #[test]
+    fn must_catch_disallowed_wasm_features() {
+        // given
+        let contract = r#"
+            (module
+                (import "env" "counter" (global (;0;) (mut i32)))
+            )"#;
+        let module = create_module(contract);
+
+        // when
+        let res = validate_features(&module, &[]);
+
+        // then
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("uses the proposals `mutable-globals`"));
+        assert!(validate_features(&module, &[WasmFeature::MutableGlobals]).is_ok());
+    }
+
+    #[test]
+    fn must_describe_unsupported_instructions() {
+        // given
+        let contract = r#"
+            (module
+                (memory 1)
+                (func (;0;) (memory.fill (i32.const 0) (i32.const 0) (i32.const 1)))
+            )"#;
+        let mut features = wabt::Features::new();
+        features.enable_bulk_memory();
+        let wasm = wabt::wat2wasm_with_features(contract, features).expect("invalid wabt");
+
+        // when
+        let err = parity_wasm::deserialize_buffer::<Module>(&wasm)
+            .map_err(|err| describe_deserialization_error(err, &[]))
+            .unwrap_err();
+
+        // then
+        assert!(err.to_string().contains("uses the `bulk-memory`"));
+    }
+
+    #[test]
+    fn sign_ext_instructions_must_be_described() {
+        // given
+        let contract = r#"
+            (module
+                (func (;0;) (param i32) (result i32) (i32.extend8_s (local.get 0)))
+            )"#;
+        let mut features = wabt::Features::new();
+        features.enable_sign_extension();
+        let wasm = wabt::wat2wasm_with_features(contract, features).expect("invalid wabt");
+
+        // when
+        let err = parity_wasm::deserialize_buffer::<Module>(&wasm)
+            .map_err(|err| describe_deserialization_error(err, &[]))
+            .unwrap_err();
+
+        // then
+        assert!(err.to_string().contains("uses the `sign-ext` proposal"));
+    }
+
+    #[test]
+    fn target_features_follow_allowed_proposals() {
+        // when
+        let enabled = enabled_features(&[
+            WasmFeature::MutableGlobals,
+            WasmFeature::MultiValue,
+            WasmFeature::Simd,
+        ]);
+
+        // then
+        assert_eq!(
+            enabled,
+            vec![WasmFeature::MultiValue, WasmFeature::MutableGlobals]
+        );
+        assert_eq!(
+            target_features(&enabled),
+            "-sign-ext,-bulk-memory,-nontrapping-fptoint,-simd128,-atomics,+multivalue,\
+            +mutable-globals,-reference-types"
+        );
+    }
+
+    #[test]
+    fn unprocessable_proposals_must_not_be_enabled() {
+        // when
+        let res = ensure_processable(&[WasmFeature::MutableGlobals, WasmFeature::SignExt]);
+
+        // then
+        assert!(ensure_processable(&[WasmFeature::MutableGlobals]).is_ok());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("Can't enable `sign-ext` via `--wasm-features`"));
+    }
+
+    #[test]
+    fn wasm_features_are_parsed() {
+        assert_eq!(
+            "bulk-memory".parse::<WasmFeature>().unwrap(),
+            WasmFeature::BulkMemory
+        );
+        assert!("gc".parse::<WasmFeature>().is_err());
+    }
+
     #[test]
     fn must_validate_successfully() {
         // given
         let contract = r#"
             (module
                 (type (;0;) (func (param i32 i32 i32)))
-                (import "env" "seal_foo" (func (;5;) (type 0)))
-                (import "env" "memory" (func (;5;) (type 0)))
-                (func (;5;) (type 0))
+                (import "seal0" "seal_input" (func (;0;) (type 0)))
+                (import "seal1" "seal_call" (func (;1;) (type 0)))
+                (import "env" "memory" (memory (;0;) 2 16))
+                (func (;2;) (type 0))
             )"#;
         let module = create_module(contract);
 
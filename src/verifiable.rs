@@ -0,0 +1,213 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verifiable builds, which run inside a pinned Docker image.
+//!
+//! The image fixes the versions of `rustc`, `wasm-opt` and `cargo-contract`, hence anyone can
+//! reproduce the Wasm byte-for-byte from the sources and the image digest recorded in the
+//! metadata.
+
+use crate::crate_metadata::CrateMetadata;
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Component, Path},
+    process::Command,
+    str,
+};
+
+/// The image verifiable builds run in by default, containing this version of `cargo-contract`.
+///
+/// It is built from `docker/contracts-verifiable/Dockerfile` and published for every release.
+pub(crate) const DEFAULT_IMAGE: &str = concat!(
+    "paritytech/contracts-verifiable:",
+    env!("CARGO_PKG_VERSION")
+);
+
+/// Environment variable carrying the digest of the image into the container, where it is
+/// recorded in the `build_info` of the metadata.
+pub(crate) const IMAGE_ENV: &str = "CARGO_CONTRACT_BUILD_IMAGE";
+
+/// Directory the workspace of the contract is mounted to inside the container.
+const CONTAINER_WORKSPACE: &str = "/contract";
+
+/// Directory the cargo target directory is mounted to inside the container.
+const CONTAINER_TARGET: &str = "/target";
+
+/// Arguments of `cargo contract build` which only apply to the host.
+const HOST_ARGS: [&str; 3] = ["--verifiable", "--image", "--manifest-path"];
+
+/// Builds the contract with `build_args` inside the `image`.
+///
+/// The artifacts are written to the target directory of the host, as for a local build.
+pub(crate) fn execute(
+    crate_metadata: &CrateMetadata,
+    image: &str,
+    build_args: &[String],
+) -> Result<()> {
+    let docker = which::which("docker").context(
+        "docker not found! Verifiable builds run inside a Docker container.\n\
+        Install Docker from https://docs.docker.com/get-docker/",
+    )?;
+    let digest = pull(&docker, image)?;
+    log::info!("Building inside {}", digest);
+
+    let workspace_root = crate_metadata.cargo_meta.workspace_root.canonicalize()?;
+    let target_directory = &crate_metadata.cargo_meta.target_directory;
+    fs::create_dir_all(target_directory)?;
+    let target_directory = target_directory.canonicalize()?;
+    let working_directory = container_path(
+        crate_metadata
+            .manifest_path
+            .absolute_directory()?
+            .strip_prefix(&workspace_root)
+            .context("The contract must be located inside its workspace")?,
+    );
+
+    let status = Command::new(&docker)
+        .arg("run")
+        .arg("--rm")
+        .arg("--env")
+        .arg(format!("{}={}", IMAGE_ENV, digest))
+        .arg("--env")
+        .arg(format!("CARGO_TARGET_DIR={}", CONTAINER_TARGET))
+        .arg("--volume")
+        .arg(format!(
+            "{}:{}",
+            workspace_root.display(),
+            CONTAINER_WORKSPACE
+        ))
+        .arg("--volume")
+        .arg(format!(
+            "{}:{}",
+            target_directory.display(),
+            CONTAINER_TARGET
+        ))
+        .arg("--workdir")
+        .arg(working_directory)
+        .arg(&digest)
+        .args(["cargo", "contract", "build"])
+        .args(build_args)
+        .status()
+        .context(format!("Executing {} failed", docker.display()))?;
+    if !status.success() {
+        anyhow::bail!("The verifiable build inside {} failed", digest)
+    }
+    Ok(())
+}
+
+/// Returns the arguments of `cargo contract build` to pass to the build inside the container,
+/// i.e. the supplied `args` of the process without the ones which only apply to the host.
+pub(crate) fn container_args(args: &[String]) -> Vec<String> {
+    let mut container_args = Vec::new();
+    let mut args = args
+        .iter()
+        .skip_while(|arg| arg.as_str() != "build")
+        .skip(1);
+    while let Some(arg) = args.next() {
+        let name = arg.split('=').next().unwrap_or_default();
+        if HOST_ARGS.contains(&name) {
+            // the value of an option is either attached via `=` or the next argument
+            if name != "--verifiable" && !arg.contains('=') {
+                args.next();
+            }
+            continue;
+        }
+        container_args.push(arg.clone());
+    }
+    container_args
+}
+
+/// Pulls the `image` and returns its digest, e.g. `paritytech/contracts-verifiable@sha256:...`.
+fn pull(docker: &Path, image: &str) -> Result<String> {
+    let status = Command::new(docker)
+        .args(["pull", image])
+        .status()
+        .context(format!("Executing {} failed", docker.display()))?;
+    if !status.success() {
+        anyhow::bail!("Pulling the image {} failed", image)
+    }
+    let output = Command::new(docker)
+        .args([
+            "image",
+            "inspect",
+            "--format",
+            "{{index .RepoDigests 0}}",
+            image,
+        ])
+        .output()
+        .context(format!("Executing {} failed", docker.display()))?;
+    let digest = str::from_utf8(&output.stdout)?.trim();
+    if !output.status.success() || digest.is_empty() {
+        anyhow::bail!(
+            "The image {} has no digest, only images pulled from a registry can be pinned",
+            image
+        )
+    }
+    Ok(digest.to_string())
+}
+
+/// Returns the path inside the container of the `relative` path in the workspace.
+fn container_path(relative: &Path) -> String {
+    relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(component) => Some(component.to_string_lossy()),
+            _ => None,
+        })
+        .fold(CONTAINER_WORKSPACE.to_string(), |path, component| {
+            format!("{}/{}", path, component)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn host_args_are_not_passed_to_the_container() {
+        let container_args = container_args(&args(&[
+            "cargo-contract",
+            "contract",
+            "build",
+            "--verifiable",
+            "--image",
+            "example/image:1.0",
+            "--manifest-path=contracts/flipper/Cargo.toml",
+            "--generate",
+            "wasm",
+            "--features=a,b",
+        ]));
+
+        assert_eq!(
+            container_args,
+            args(&["--generate", "wasm", "--features=a,b"])
+        );
+    }
+
+    #[test]
+    fn container_path_is_nested_in_the_workspace() {
+        assert_eq!(container_path(Path::new("")), "/contract");
+        assert_eq!(
+            container_path(Path::new("contracts/flipper")),
+            "/contract/contracts/flipper"
+        );
+    }
+}
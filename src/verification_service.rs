@@ -0,0 +1,230 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Submission of contracts to a source verification service, which rebuilds the contract from
+//! its sources and publishes them if the build reproduces the code on chain.
+//!
+//! The service has to speak the following HTTP/JSON protocol:
+//!
+//! - `POST {endpoint}/verifications` with
+//!   `{ "contract": "<ss58 address>" | null, "codeHash": "0x<hex>", "buildInfo": {..},
+//!   "bundle": {..}, "sourceArchive": "0x<hex>" }` starts a verification and returns its id as
+//!   `{ "id": "<id>" }`. The source archive is a gzipped tarball of the contract directory.
+//! - `GET {endpoint}/verifications/{id}` returns the status of the verification as
+//!   `{ "status": "pending" | "verified" | "failed", "message": "<details>" }`.
+//!
+//! If the environment variable `CARGO_CONTRACT_VERIFICATION_TOKEN` is set its value is sent as a
+//! bearer token with every request.
+
+use crate::{
+    bundle::ContractBundle, cmd::metadata::source_files, crate_metadata::CrateMetadata, util,
+};
+use anyhow::{Context, Result};
+use flate2::{write::GzEncoder, Compression};
+use impl_serde::serialize as serde_hex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::{fs, path::Path, thread, time::Duration};
+use url::Url;
+
+/// Environment variable holding the bearer token for authenticating with the service.
+const TOKEN_ENV_VAR: &str = "CARGO_CONTRACT_VERIFICATION_TOKEN";
+
+/// Interval between two requests for the status of a verification.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Number of status requests after which waiting for the verification is given up.
+const MAX_POLLS: u32 = 120;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerificationRequest {
+    contract: Option<String>,
+    code_hash: String,
+    build_info: Value,
+    bundle: Value,
+    source_archive: String,
+}
+
+#[derive(Deserialize)]
+struct VerificationResponse {
+    id: String,
+}
+
+/// The status of a verification, as reported by the service.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase", tag = "status")]
+pub(crate) enum VerificationStatus {
+    /// The service is still rebuilding the contract.
+    Pending,
+    /// The build of the service reproduced the code, the sources are published.
+    Verified {
+        #[serde(default)]
+        message: Option<String>,
+    },
+    /// The build of the service failed or didn't reproduce the code.
+    Failed {
+        #[serde(default)]
+        message: Option<String>,
+    },
+}
+
+/// Client of a source verification service.
+pub(crate) struct VerificationService {
+    endpoint: Url,
+    token: Option<String>,
+}
+
+impl VerificationService {
+    /// Creates a client of the service at `endpoint`.
+    pub fn new(endpoint: &Url) -> Self {
+        Self {
+            endpoint: endpoint.clone(),
+            token: std::env::var(TOKEN_ENV_VAR).ok(),
+        }
+    }
+
+    /// Submits the sources of the contract together with its `bundle`, returning the id of the
+    /// verification.
+    pub fn submit(
+        &self,
+        crate_metadata: &CrateMetadata,
+        bundle: &ContractBundle,
+        contract: Option<&AccountId32>,
+    ) -> Result<String> {
+        let json = bundle.json();
+        let build_info = json
+            .get("source")
+            .and_then(|source| source.get("build_info"))
+            .cloned()
+            .unwrap_or(Value::Null);
+        let request = VerificationRequest {
+            contract: contract.map(|contract| contract.to_ss58check()),
+            code_hash: serde_hex::to_hex(&bundle.code_hash()?, false),
+            build_info,
+            bundle: Value::Object(json.clone()),
+            source_archive: serde_hex::to_hex(&source_archive(crate_metadata)?, false),
+        };
+        let response: VerificationResponse = util::authorized(
+            ureq::post(self.url("verifications")?.as_str()),
+            self.token.as_deref(),
+        )
+        .send_json(serde_json::to_value(request)?)
+        .map_err(|err| anyhow::anyhow!("Submitting the verification failed: {}", err))?
+        .into_json()
+        .context("Invalid response of the verification service")?;
+        log::info!(
+            "Submitted verification {} to {}",
+            response.id,
+            self.endpoint
+        );
+        Ok(response.id)
+    }
+
+    /// Polls the status of the verification `id` until it is completed.
+    pub fn wait_for(&self, id: &str) -> Result<VerificationStatus> {
+        let url = self.url(&format!("verifications/{}", id))?;
+        for _ in 0..MAX_POLLS {
+            let status: VerificationStatus =
+                util::authorized(ureq::get(url.as_str()), self.token.as_deref())
+                    .call()
+                    .map_err(|err| {
+                        anyhow::anyhow!("Requesting the verification status failed: {}", err)
+                    })?
+                    .into_json()
+                    .context("Invalid status response of the verification service")?;
+            if status != VerificationStatus::Pending {
+                return Ok(status);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        anyhow::bail!(
+            "The verification {} is still pending after {} seconds",
+            id,
+            MAX_POLLS as u64 * POLL_INTERVAL.as_secs()
+        )
+    }
+
+    /// Returns the url of the `path` of the service.
+    fn url(&self, path: &str) -> Result<Url> {
+        let mut endpoint = self.endpoint.clone();
+        if !endpoint.path().ends_with('/') {
+            let base = format!("{}/", endpoint.path());
+            endpoint.set_path(&base);
+        }
+        endpoint.join(path).context(format!(
+            "Invalid verification service url {}",
+            self.endpoint
+        ))
+    }
+}
+
+/// Returns a gzipped tarball of the sources of the contract.
+///
+/// The `Cargo.lock` of the workspace is included, so that the service builds with the same
+/// dependencies. The entries carry no timestamps or owners, hence the archive only depends on
+/// the sources.
+fn source_archive(crate_metadata: &CrateMetadata) -> Result<Vec<u8>> {
+    let directory = crate_metadata.manifest_path.absolute_directory()?;
+    let mut files = source_files(&directory)?
+        .into_iter()
+        .map(|relative_path| (directory.join(&relative_path), relative_path))
+        .collect::<Vec<_>>();
+    let lock_file = crate_metadata.cargo_meta.workspace_root.join("Cargo.lock");
+    if lock_file.exists()
+        && !files
+            .iter()
+            .any(|(_, path)| path == Path::new("Cargo.lock"))
+    {
+        files.push((lock_file, "Cargo.lock".into()));
+    }
+
+    let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (path, relative_path) in files {
+        let contents = fs::read(&path).context(format!("Failed to read {}", path.display()))?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_cksum();
+        archive.append_data(&mut header, &relative_path, &contents[..])?;
+    }
+    Ok(archive.into_inner()?.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verification_status_is_decoded() {
+        let pending: VerificationStatus =
+            serde_json::from_str(r#"{ "status": "pending" }"#).unwrap();
+        let verified: VerificationStatus =
+            serde_json::from_str(r#"{ "status": "verified", "message": "published" }"#).unwrap();
+        let failed: VerificationStatus = serde_json::from_str(r#"{ "status": "failed" }"#).unwrap();
+
+        assert_eq!(pending, VerificationStatus::Pending);
+        assert_eq!(
+            verified,
+            VerificationStatus::Verified {
+                message: Some("published".to_string())
+            }
+        );
+        assert_eq!(failed, VerificationStatus::Failed { message: None });
+    }
+}
@@ -0,0 +1,535 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Checks the dependency tree of a contract for crates which can't be used by Wasm contracts,
+//! because they link the Rust standard library or rely on facilities of an operating system.
+//!
+//! Such crates fail the build with compiler or linker errors which don't point to the
+//! dependency introducing them, hence each incompatibility is reported together with the path
+//! of dependencies leading to the crate and the dependency edge causing it.
+
+use crate::workspace::ManifestPath;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    fmt,
+    process::Command,
+};
+
+/// Crates which rely on facilities of an operating system, unavailable to contracts, together
+/// with the features which make them compatible with Wasm.
+const OS_DEPENDENT: [(&str, &[&str]); 6] = [
+    ("getrandom", &["custom", "js"]),
+    ("ring", &[]),
+    ("openssl-sys", &[]),
+    ("tokio", &[]),
+    ("mio", &[]),
+    ("socket2", &[]),
+];
+
+/// A dependency of the contract which can't be used by Wasm contracts.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Incompatibility {
+    /// The name and version of the incompatible crate.
+    pub package: String,
+    pub reason: String,
+    /// The names and versions of the crates from the contract to the incompatible crate.
+    pub path: Vec<String>,
+    /// The dependency edges introducing the incompatibility.
+    pub causes: Vec<String>,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}\n\tvia {}",
+            self.package,
+            self.reason,
+            self.path.join(" -> ")
+        )?;
+        for cause in &self.causes {
+            write!(f, "\n\tbecause {}", cause)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the dependencies of the contract, built for the `rustc_target` with the `features`,
+/// which can't be used by Wasm contracts.
+pub(crate) fn check(
+    manifest_path: &ManifestPath,
+    rustc_target: &str,
+    features: &[String],
+) -> Result<Vec<Incompatibility>> {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("metadata")
+        .arg("--format-version=1")
+        .arg(manifest_path.cargo_arg())
+        .arg(format!("--filter-platform={}", rustc_target))
+        .arg("--no-default-features");
+    if !features.is_empty() {
+        cmd.arg(format!("--features={}", features.join(",")));
+    }
+    let output = cmd
+        .output()
+        .context("Error invoking `cargo metadata` for the dependency tree")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`cargo metadata` failed to resolve the dependency tree: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+    let metadata: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(analyze(&metadata))
+}
+
+/// The resolved dependency graph of the `cargo metadata` output.
+struct Graph<'a> {
+    packages: HashMap<&'a str, &'a Value>,
+    nodes: HashMap<&'a str, &'a Value>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(metadata: &'a Value) -> Self {
+        let by_id = |items: Option<&'a Value>| {
+            items
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|item| Some((item.get("id")?.as_str()?, item)))
+                .collect::<HashMap<_, _>>()
+        };
+        Self {
+            packages: by_id(metadata.get("packages")),
+            nodes: by_id(
+                metadata
+                    .get("resolve")
+                    .and_then(|resolve| resolve.get("nodes")),
+            ),
+        }
+    }
+
+    /// Returns the name and version of the package `id`.
+    fn display(&self, id: &str) -> String {
+        let package = self.packages.get(id);
+        let field = |key| {
+            package
+                .and_then(|package| package.get(key))
+                .and_then(Value::as_str)
+                .unwrap_or("?")
+        };
+        format!("{} {}", field("name"), field("version"))
+    }
+
+    fn name(&self, id: &str) -> &'a str {
+        self.packages
+            .get(id)
+            .and_then(|package| package.get("name"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+    }
+
+    /// Returns the enabled features of the package `id`.
+    fn features(&self, id: &str) -> BTreeSet<&'a str> {
+        self.nodes
+            .get(id)
+            .and_then(|node| node.get("features"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(Value::as_str)
+            .collect()
+    }
+
+    /// Returns the ids of the packages the package `id` depends on for the target, i.e. without
+    /// build and dev dependencies.
+    fn dependencies(&self, id: &str) -> Vec<&'a str> {
+        self.nodes
+            .get(id)
+            .and_then(|node| node.get("deps"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter(|dep| match dep.get("dep_kinds").and_then(Value::as_array) {
+                Some(kinds) => kinds
+                    .iter()
+                    .any(|kind| kind.get("kind").unwrap_or(&Value::Null).is_null()),
+                None => true,
+            })
+            .filter_map(|dep| dep.get("pkg").and_then(Value::as_str))
+            .collect()
+    }
+
+    /// Returns `true` if the package `id` is a procedural macro, which runs on the host.
+    fn is_proc_macro(&self, id: &str) -> bool {
+        self.packages
+            .get(id)
+            .and_then(|package| package.get("targets"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|target| target.get("kind").and_then(Value::as_array))
+            .flatten()
+            .any(|kind| kind == "proc-macro")
+    }
+
+    /// Returns the declaration of the dependency of the package `parent` on the `child`
+    /// package, with the name it is known by in the `parent`.
+    fn declaration(&self, parent: &str, child: &str) -> Option<(&'a Value, &'a str)> {
+        let child_name = self.name(child);
+        self.packages
+            .get(parent)?
+            .get("dependencies")?
+            .as_array()?
+            .iter()
+            .filter(|dep| dep.get("kind").unwrap_or(&Value::Null).is_null())
+            .find(|dep| dep.get("name").and_then(Value::as_str) == Some(child_name))
+            .map(|dep| {
+                let name = dep
+                    .get("rename")
+                    .and_then(Value::as_str)
+                    .unwrap_or(child_name);
+                (dep, name)
+            })
+    }
+
+    /// Returns `true` if the default features of the package `id` include `feature`.
+    fn default_features_include(&self, id: &str, feature: &str) -> bool {
+        self.packages
+            .get(id)
+            .and_then(|package| package.get("features"))
+            .and_then(|features| features.get("default"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .any(|entry| entry == feature)
+    }
+
+    /// Returns the reasons why the package `parent` enables the `std` feature of the `child`,
+    /// and whether all of them are the `std` feature of the `parent` itself.
+    fn std_causes(&self, parent: &str, child: &str) -> (Vec<String>, bool) {
+        let (parent_name, child_name) = (self.display(parent), self.name(child));
+        let (declaration, dep_name) = match self.declaration(parent, child) {
+            Some(declaration) => declaration,
+            None => return (Vec::new(), false),
+        };
+        let mut causes = Vec::new();
+        let mut propagated = true;
+        let declared_features = declaration
+            .get("features")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if declared_features.iter().any(|feature| feature == "std") {
+            causes.push(format!(
+                "{} enables the `std` feature of {}",
+                parent_name, child_name
+            ));
+            propagated = false;
+        }
+        let uses_default_features = declaration
+            .get("uses_default_features")
+            .and_then(Value::as_bool)
+            .unwrap_or(true);
+        if uses_default_features && self.default_features_include(child, "std") {
+            causes.push(format!(
+                "{} doesn't set `default-features = false` for {}, whose default features \
+                include `std`",
+                parent_name, child_name
+            ));
+            propagated = false;
+        }
+        let definitions = self
+            .packages
+            .get(parent)
+            .and_then(|package| package.get("features"));
+        for feature in self.features(parent) {
+            let enables_std = definitions
+                .and_then(|definitions| definitions.get(feature))
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|entry| {
+                    entry == format!("{}/std", dep_name) || entry == format!("{}?/std", dep_name)
+                });
+            if enables_std {
+                causes.push(format!(
+                    "the `{}` feature of {} enables `{}/std`",
+                    feature, parent_name, dep_name
+                ));
+                propagated &= feature == "std";
+            }
+        }
+        let propagated = propagated && !causes.is_empty();
+        (causes, propagated)
+    }
+}
+
+/// Returns the dependencies in the `cargo metadata` output which can't be used by Wasm
+/// contracts.
+fn analyze(metadata: &Value) -> Vec<Incompatibility> {
+    let graph = Graph::new(metadata);
+    let root = match metadata
+        .get("resolve")
+        .and_then(|resolve| resolve.get("root"))
+        .and_then(Value::as_str)
+    {
+        Some(root) => root,
+        None => return Vec::new(),
+    };
+
+    // the shortest paths to the dependencies compiled for the target, procedural macros and
+    // their dependencies run on the host
+    let mut parents = HashMap::new();
+    let mut order = vec![root];
+    let mut queue = VecDeque::from(vec![root]);
+    while let Some(id) = queue.pop_front() {
+        for dependency in graph.dependencies(id) {
+            if dependency == root
+                || parents.contains_key(dependency)
+                || graph.is_proc_macro(dependency)
+            {
+                continue;
+            }
+            parents.insert(dependency, id);
+            order.push(dependency);
+            queue.push_back(dependency);
+        }
+    }
+    let path = |id: &str| {
+        let mut path = vec![graph.display(id)];
+        let mut current = id;
+        while let Some(parent) = parents.get(current) {
+            path.push(graph.display(parent));
+            current = parent;
+        }
+        path.reverse();
+        path
+    };
+    let dependents = |child: &str| {
+        order
+            .iter()
+            .copied()
+            .filter(|parent| graph.dependencies(parent).contains(&child))
+            .collect::<Vec<_>>()
+    };
+
+    let mut incompatibilities = Vec::new();
+    for id in order.iter().copied().filter(|id| *id != root) {
+        let name = graph.name(id);
+        let features = graph.features(id);
+        if features.contains("std") {
+            let mut causes = Vec::new();
+            let mut all_propagated = true;
+            for parent in dependents(id) {
+                let (parent_causes, propagated) = graph.std_causes(parent, id);
+                all_propagated &= propagated;
+                causes.extend(parent_causes);
+            }
+            // the `std` feature of a dependent, which is reported itself, is the root cause
+            if all_propagated && !causes.is_empty() {
+                continue;
+            }
+            if causes.is_empty() {
+                causes.push(
+                    "a build dependency or procedural macro enables it, whose features cargo \
+                    unifies with those of the contract; set `resolver = \"2\"` in the \
+                    `[package]` section to keep them apart"
+                        .to_string(),
+                );
+            }
+            incompatibilities.push(Incompatibility {
+                package: graph.display(id),
+                reason: "has its `std` feature enabled, linking the Rust standard library which \
+                    conflicts with the `no_std` contract"
+                    .to_string(),
+                path: path(id),
+                causes,
+            });
+        }
+        if let Some((_, compatible_with)) = OS_DEPENDENT
+            .iter()
+            .find(|(os_dependent, _)| *os_dependent == name)
+        {
+            if compatible_with
+                .iter()
+                .any(|feature| features.contains(feature))
+            {
+                continue;
+            }
+            incompatibilities.push(Incompatibility {
+                package: graph.display(id),
+                reason: "relies on facilities of an operating system, which are unavailable to \
+                    contracts"
+                    .to_string(),
+                path: path(id),
+                causes: dependents(id)
+                    .into_iter()
+                    .map(|parent| format!("{} depends on {}", graph.display(parent), name))
+                    .collect(),
+            });
+        }
+    }
+    incompatibilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn package(name: &str, dependencies: Value, features: Value) -> Value {
+        json!({
+            "id": format!("{} 1.0.0", name),
+            "name": name,
+            "version": "1.0.0",
+            "dependencies": dependencies,
+            "features": features,
+            "targets": [{ "kind": ["lib"] }]
+        })
+    }
+
+    fn node(name: &str, deps: &[&str], features: &[&str]) -> Value {
+        json!({
+            "id": format!("{} 1.0.0", name),
+            "deps": deps.iter().map(|dep| json!({
+                "pkg": format!("{} 1.0.0", dep),
+                "dep_kinds": [{ "kind": null, "target": null }]
+            })).collect::<Vec<_>>(),
+            "features": features
+        })
+    }
+
+    #[test]
+    fn dependency_edges_enabling_std_are_reported() {
+        // given
+        let metadata = json!({
+            "packages": [
+                package("flipper", json!([
+                    { "name": "helper", "uses_default_features": false, "features": ["std"] },
+                    { "name": "codec", "uses_default_features": false, "features": [] }
+                ]), json!({})),
+                package("helper", json!([
+                    { "name": "serde", "uses_default_features": true, "features": [] },
+                    { "name": "getrandom", "uses_default_features": false, "features": [] }
+                ]), json!({ "std": ["serde/std"] })),
+                package("serde", json!([]), json!({ "default": ["std"], "std": [] })),
+                package("codec", json!([]), json!({ "std": [] })),
+                package("getrandom", json!([]), json!({ "js": [] }))
+            ],
+            "resolve": {
+                "root": "flipper 1.0.0",
+                "nodes": [
+                    node("flipper", &["helper", "codec"], &[]),
+                    node("helper", &["serde", "getrandom"], &["std"]),
+                    node("serde", &[], &["default", "std"]),
+                    node("codec", &[], &[]),
+                    node("getrandom", &[], &[])
+                ]
+            }
+        });
+
+        // when
+        let incompatibilities = analyze(&metadata);
+
+        // then
+        assert_eq!(
+            incompatibilities
+                .iter()
+                .map(|incompatibility| incompatibility.package.as_str())
+                .collect::<Vec<_>>(),
+            vec!["helper 1.0.0", "serde 1.0.0", "getrandom 1.0.0"]
+        );
+        assert_eq!(
+            incompatibilities[0].causes,
+            vec!["flipper 1.0.0 enables the `std` feature of helper"]
+        );
+        assert_eq!(
+            incompatibilities[1].path,
+            vec!["flipper 1.0.0", "helper 1.0.0", "serde 1.0.0"]
+        );
+        assert_eq!(
+            incompatibilities[1].causes[0],
+            "helper 1.0.0 doesn't set `default-features = false` for serde, whose default \
+            features include `std`"
+        );
+        assert_eq!(
+            incompatibilities[2].causes,
+            vec!["helper 1.0.0 depends on getrandom"]
+        );
+    }
+
+    #[test]
+    fn propagated_std_features_are_not_reported_twice() {
+        // given
+        let metadata = json!({
+            "packages": [
+                package("flipper", json!([
+                    { "name": "helper", "uses_default_features": false, "features": ["std"] }
+                ]), json!({})),
+                package("helper", json!([
+                    { "name": "codec", "uses_default_features": false, "features": [] }
+                ]), json!({ "std": ["codec/std"] })),
+                package("codec", json!([]), json!({ "std": [] }))
+            ],
+            "resolve": {
+                "root": "flipper 1.0.0",
+                "nodes": [
+                    node("flipper", &["helper"], &[]),
+                    node("helper", &["codec"], &["std"]),
+                    node("codec", &[], &["std"])
+                ]
+            }
+        });
+
+        // when
+        let incompatibilities = analyze(&metadata);
+
+        // then
+        assert_eq!(incompatibilities.len(), 1);
+        assert_eq!(incompatibilities[0].package, "helper 1.0.0");
+    }
+
+    #[test]
+    fn compatible_features_of_os_dependent_crates_are_accepted() {
+        let metadata = json!({
+            "packages": [
+                package("flipper", json!([
+                    { "name": "getrandom", "uses_default_features": false, "features": ["custom"] }
+                ]), json!({})),
+                package("getrandom", json!([]), json!({ "custom": [] }))
+            ],
+            "resolve": {
+                "root": "flipper 1.0.0",
+                "nodes": [
+                    node("flipper", &["getrandom"], &[]),
+                    node("getrandom", &[], &["custom"])
+                ]
+            }
+        });
+
+        assert!(analyze(&metadata).is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright 2018-2021 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Locating the `wasm-opt` binary, downloading a pinned `binaryen` release if it is not installed.
+
+use crate::util;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use flate2::read::GzDecoder;
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+/// The `binaryen` release which is downloaded if `wasm-opt` is not installed.
+const BINARYEN_VERSION: &str = "version_101";
+
+/// The SHA-256 digests of the archives of the pinned `binaryen` release, by platform.
+///
+/// Downloads are verified against these digests rather than against the checksums published
+/// with the release, which would be replaced together with a tampered archive. On platforms
+/// without a digest `wasm-opt` has to be installed manually.
+const BINARYEN_DIGESTS: &[(&str, &str)] = &[];
+
+/// Environment variable which, if set to `yes`, allows downloading without asking first.
+///
+/// Useful for non-interactive environments like CI.
+const DOWNLOAD_ENV_VAR: &str = "CARGO_CONTRACT_DOWNLOAD_WASM_OPT";
+
+/// Returns the path of a `wasm-opt` binary.
+///
+/// Uses the binary found in the `PATH`, otherwise a previously downloaded one. If neither exists
+/// the user is asked whether the pinned `binaryen` release should be downloaded. Returns `None`
+/// if the download is declined, or if there is no pinned release for the current platform.
+pub(crate) fn find_or_download() -> Result<Option<PathBuf>> {
+    if let Ok(path) = which::which("wasm-opt") {
+        return Ok(Some(path));
+    }
+    let install_dir = install_dir()?;
+    let cached = wasm_opt_path(&install_dir);
+    if cached.exists() {
+        log::info!("Using downloaded wasm-opt at {}", cached.display());
+        return Ok(Some(cached));
+    }
+    let (archive_name, digest) = match release_archive() {
+        Some(archive) => archive,
+        None => return Ok(None),
+    };
+    if !download_confirmed(&install_dir)? {
+        return Ok(None);
+    }
+    download(&install_dir, &archive_name, digest)?;
+    Ok(Some(cached))
+}
+
+/// Returns the directory the pinned `binaryen` release is installed into.
+fn install_dir() -> Result<PathBuf> {
+    util::cache_dir(&format!("binaryen-{}", BINARYEN_VERSION))
+}
+
+/// Returns the path of the `wasm-opt` binary within the installed `binaryen` release.
+fn wasm_opt_path(install_dir: &Path) -> PathBuf {
+    let binary = if cfg!(windows) {
+        "wasm-opt.exe"
+    } else {
+        "wasm-opt"
+    };
+    install_dir.join("bin").join(binary)
+}
+
+/// Returns the name of the release archive for the current platform and its SHA-256 digest, if
+/// the release is pinned for it.
+fn release_archive() -> Option<(String, &'static str)> {
+    let platform = match (std::env::consts::ARCH, std::env::consts::OS) {
+        ("x86_64", "linux") => "x86_64-linux",
+        ("x86_64", "macos") => "x86_64-macos",
+        ("x86_64", "windows") => "x86_64-windows",
+        _ => return None,
+    };
+    let digest = BINARYEN_DIGESTS
+        .iter()
+        .find(|(digest_platform, _)| *digest_platform == platform)
+        .map(|(_, digest)| *digest)?;
+    Some((
+        format!("binaryen-{}-{}.tar.gz", BINARYEN_VERSION, platform),
+        digest,
+    ))
+}
+
+/// Asks the user whether the pinned `binaryen` release should be downloaded.
+fn download_confirmed(install_dir: &Path) -> Result<bool> {
+    if std::env::var(DOWNLOAD_ENV_VAR).as_deref() == Ok("yes") {
+        return Ok(true);
+    }
+    eprint!(
+        "{} wasm-opt was not found. Download binaryen {} into {}? [y/N] ",
+        "warning:".yellow().bold(),
+        BINARYEN_VERSION,
+        install_dir.display()
+    );
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Downloads the archive of the pinned `binaryen` release, verifies it against its SHA-256
+/// `digest` and unpacks it into `install_dir`.
+fn download(install_dir: &Path, archive_name: &str, digest: &str) -> Result<()> {
+    let url = format!(
+        "https://github.com/WebAssembly/binaryen/releases/download/{}/{}",
+        BINARYEN_VERSION, archive_name
+    );
+    let archive = util::download_archive(&url, digest, "binaryen release")?;
+
+    let parent = install_dir
+        .parent()
+        .expect("the install dir is within the cache dir; qed");
+    fs::create_dir_all(parent)?;
+    let unpack_dir = tempfile::Builder::new()
+        .prefix(".binaryen.")
+        .tempdir_in(parent)?;
+    tar::Archive::new(GzDecoder::new(&archive[..]))
+        .unpack(unpack_dir.path())
+        .context("Unpacking the binaryen release failed")?;
+
+    // the archive contains a single `binaryen-<version>` directory
+    let unpacked = unpack_dir
+        .path()
+        .join(format!("binaryen-{}", BINARYEN_VERSION));
+    if !wasm_opt_path(&unpacked).exists() {
+        anyhow::bail!("The binaryen release does not contain a wasm-opt binary")
+    }
+    fs::rename(&unpacked, install_dir).context(format!(
+        "Moving the binaryen release to {} failed",
+        install_dir.display()
+    ))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wasm_opt_is_located_in_bin_dir() {
+        let path = wasm_opt_path(Path::new("binaryen"));
+        assert!(path.starts_with("binaryen/bin"));
+    }
+}
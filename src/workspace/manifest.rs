@@ -16,8 +16,12 @@
 
 use anyhow::{Context, Result};
 
-use super::{metadata, Profile};
-use crate::OptimizationPasses;
+use super::{metadata, profile, Profile, ProfileOverrides};
+use crate::{
+    code_size::{BudgetExceeded, CodeSizeBudget, MaxCodeSize},
+    validate_wasm::{ScheduleLimits, WasmFeature},
+    OptimizationPasses,
+};
 
 use std::convert::TryFrom;
 use std::{
@@ -114,6 +118,8 @@ pub struct Manifest {
     toml: value::Table,
     /// True if a metadata package should be generated for this manifest
     metadata_package: bool,
+    /// True if the metadata package activates the default features of the contract
+    metadata_default_features: bool,
 }
 
 impl Manifest {
@@ -128,6 +134,7 @@ impl Manifest {
             path: manifest_path,
             toml,
             metadata_package: false,
+            metadata_default_features: true,
         })
     }
 
@@ -162,6 +169,177 @@ impl Manifest {
         Ok(self)
     }
 
+    /// Add the `crate_types` to the `[lib] crate-type = []` section, creating the section if it
+    /// doesn't exist yet.
+    ///
+    /// A missing `crate-type` starts out as `["rlib"]`, the crate type cargo uses by default.
+    pub fn with_crate_types(&mut self, crate_types: &[&str]) -> Result<&mut Self> {
+        let lib = self
+            .toml
+            .entry("lib")
+            .or_insert_with(|| value::Value::Table(Default::default()))
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("lib should be a table"))?;
+        let existing = lib
+            .entry("crate-type")
+            .or_insert_with(|| value::Value::Array(vec!["rlib".into()]))
+            .as_array_mut()
+            .ok_or_else(|| anyhow::anyhow!("crate-types should be an Array"))?;
+        for crate_type in crate_types {
+            if !crate_type_exists(crate_type, existing) {
+                existing.push((*crate_type).into());
+            }
+        }
+        Ok(self)
+    }
+
+    /// Get the name of the `[package]`.
+    pub fn package_name(&self) -> Result<&str> {
+        self.toml
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str())
+            .ok_or_else(|| anyhow::anyhow!("package name not found"))
+    }
+
+    /// Get the `path` of the `[lib]` section, if it is set.
+    pub fn lib_path(&self) -> Option<&str> {
+        self.toml.get("lib")?.get("path")?.as_str()
+    }
+
+    /// Add the `dependencies` which are not yet in the `[dependencies]` section.
+    ///
+    /// Returns the names of the added dependencies.
+    pub fn with_dependencies(&mut self, dependencies: &value::Table) -> Result<Vec<String>> {
+        let existing = self
+            .toml
+            .entry("dependencies")
+            .or_insert_with(|| value::Value::Table(Default::default()))
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("dependencies should be a table"))?;
+        let mut added = Vec::new();
+        for (name, dependency) in dependencies {
+            if !existing.contains_key(name) {
+                existing.insert(name.clone(), dependency.clone());
+                added.push(name.clone());
+            }
+        }
+        Ok(added)
+    }
+
+    /// Get the names of the dependencies which don't set `default-features = false`.
+    pub fn dependencies_with_default_features(&self) -> Vec<String> {
+        let dependencies = match self.toml.get("dependencies").and_then(|d| d.as_table()) {
+            Some(dependencies) => dependencies,
+            None => return Vec::new(),
+        };
+        dependencies
+            .iter()
+            .filter(|(_, dependency)| {
+                dependency
+                    .get("default-features")
+                    .and_then(|default_features| default_features.as_bool())
+                    .unwrap_or(true)
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Get the version requirement of the dependency on the `package`, which may be renamed.
+    pub fn dependency_version(&self, package: &str) -> Option<&str> {
+        self.toml
+            .get("dependencies")?
+            .as_table()?
+            .iter()
+            .find(|(name, dependency)| {
+                dependency
+                    .get("package")
+                    .and_then(|package| package.as_str())
+                    .unwrap_or(name)
+                    == package
+            })
+            .and_then(|(_, dependency)| match dependency {
+                value::Value::String(version) => Some(version.as_str()),
+                dependency => dependency.get("version")?.as_str(),
+            })
+    }
+
+    /// Set the version of the dependencies whose package `matches` to `version`, including
+    /// renamed dependencies and those of `[dev-dependencies]`.
+    ///
+    /// Path and git dependencies are left untouched. Returns the names of the updated
+    /// dependencies with their previous version requirement.
+    pub fn with_dependency_version(
+        &mut self,
+        matches: &dyn Fn(&str) -> bool,
+        version: &str,
+    ) -> Result<Vec<(String, String)>> {
+        let mut updated = Vec::new();
+        for section in &["dependencies", "dev-dependencies"] {
+            let dependencies = match self.toml.get_mut(*section) {
+                Some(dependencies) => dependencies
+                    .as_table_mut()
+                    .ok_or_else(|| anyhow::anyhow!("{} should be a table", section))?,
+                None => continue,
+            };
+            for (name, dependency) in dependencies.iter_mut() {
+                let package = dependency
+                    .get("package")
+                    .and_then(|package| package.as_str())
+                    .unwrap_or(name);
+                if !matches(package)
+                    || dependency.get("path").is_some()
+                    || dependency.get("git").is_some()
+                {
+                    continue;
+                }
+                let previous = match dependency {
+                    value::Value::String(previous) => {
+                        std::mem::replace(previous, version.to_string())
+                    }
+                    value::Value::Table(dependency) => dependency
+                        .insert("version".into(), version.into())
+                        .and_then(|previous| previous.as_str().map(ToOwned::to_owned))
+                        .unwrap_or_else(|| "*".to_string()),
+                    _ => anyhow::bail!("dependency {} should be a string or table", name),
+                };
+                if previous != version {
+                    updated.push((name.clone(), previous));
+                }
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Merge the `features` into the `[features]` section.
+    ///
+    /// Missing features are added, the entries of existing features are extended by the missing
+    /// entries.
+    pub fn with_features(&mut self, features: &value::Table) -> Result<&mut Self> {
+        let existing = self
+            .toml
+            .entry("features")
+            .or_insert_with(|| value::Value::Table(Default::default()))
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("features should be a table"))?;
+        for (name, entries) in features {
+            let entries = entries
+                .as_array()
+                .ok_or_else(|| anyhow::anyhow!("feature {} should be an Array", name))?;
+            let feature = existing
+                .entry(name.clone())
+                .or_insert_with(|| value::Value::Array(Vec::new()))
+                .as_array_mut()
+                .ok_or_else(|| anyhow::anyhow!("feature {} should be an Array", name))?;
+            for entry in entries {
+                if !feature.contains(entry) {
+                    feature.push(entry.clone());
+                }
+            }
+        }
+        Ok(self)
+    }
+
     /// Extract `optimization-passes` from `[package.metadata.contract]`
     pub fn get_profile_optimization_passes(&mut self) -> Option<OptimizationPasses> {
         self.toml
@@ -176,6 +354,164 @@ impl Manifest {
             .map(Into::into)
     }
 
+    /// Extract `custom-passes` from `[package.metadata.contract]`
+    pub fn get_profile_custom_passes(&mut self) -> Result<Option<Vec<String>>> {
+        let passes = match self
+            .toml
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("contract"))
+            .and_then(|contract| contract.get("custom-passes"))
+        {
+            Some(passes) => passes,
+            None => return Ok(None),
+        };
+        let passes = passes
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("custom-passes should be an array of strings"))?
+            .iter()
+            .map(|pass| {
+                pass.as_str()
+                    .map(ToOwned::to_owned)
+                    .ok_or_else(|| anyhow::anyhow!("custom-passes should be an array of strings"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(passes))
+    }
+
+    /// Extract `wasm-features` from `[package.metadata.contract]`
+    pub fn get_profile_wasm_features(&self) -> Result<Option<Vec<WasmFeature>>> {
+        let features = match self
+            .toml
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("contract"))
+            .and_then(|contract| contract.get("wasm-features"))
+        {
+            Some(features) => features,
+            None => return Ok(None),
+        };
+        let features = features
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("wasm-features should be an array of strings"))?
+            .iter()
+            .map(|feature| {
+                feature
+                    .as_str()
+                    .ok_or_else(|| anyhow::anyhow!("wasm-features should be an array of strings"))?
+                    .parse()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(features))
+    }
+
+    /// Extract the schedule limits of the target chain from `[package.metadata.contract.limits]`
+    ///
+    /// Limits which are not set keep their default.
+    pub fn get_profile_schedule_limits(&self) -> Result<ScheduleLimits> {
+        let mut limits = ScheduleLimits::default();
+        let table = match self
+            .toml
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("contract"))
+            .and_then(|contract| contract.get("limits"))
+        {
+            Some(table) => table
+                .as_table()
+                .ok_or_else(|| anyhow::anyhow!("limits should be a table"))?,
+            None => return Ok(limits),
+        };
+        for (key, value) in table {
+            let limit = value
+                .as_integer()
+                .and_then(|limit| u32::try_from(limit).ok())
+                .ok_or_else(|| anyhow::anyhow!("limits.{} should be a positive integer", key))?;
+            match key.as_str() {
+                "memory-pages" => limits.memory_pages = limit,
+                "table-size" => limits.table_size = limit,
+                "stack-height" => limits.stack_height = limit,
+                _ => anyhow::bail!(
+                    "Unknown limit '{}', expected memory-pages, table-size or stack-height",
+                    key
+                ),
+            }
+        }
+        Ok(limits)
+    }
+
+    /// Extract the code size budget from `[package.metadata.contract.code-size]`
+    pub fn get_profile_code_size_budget(&self) -> Result<Option<CodeSizeBudget>> {
+        let table = match self
+            .toml
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("contract"))
+            .and_then(|contract| contract.get("code-size"))
+        {
+            Some(table) => table
+                .as_table()
+                .ok_or_else(|| anyhow::anyhow!("code-size should be a table"))?,
+            None => return Ok(None),
+        };
+        let mut max = None;
+        let mut network = None;
+        let mut exceeded = BudgetExceeded::default();
+        for (key, value) in table {
+            match key.as_str() {
+                "max" => {
+                    max = Some(
+                        value
+                            .as_integer()
+                            .and_then(|max| u32::try_from(max).ok())
+                            .ok_or_else(|| {
+                                anyhow::anyhow!("code-size.max should be a positive integer")
+                            })?,
+                    )
+                }
+                "network" => {
+                    network = Some(
+                        value
+                            .as_str()
+                            .ok_or_else(|| anyhow::anyhow!("code-size.network should be a string"))?
+                            .to_string(),
+                    )
+                }
+                "exceeded" => {
+                    exceeded = value
+                        .as_str()
+                        .ok_or_else(|| anyhow::anyhow!("code-size.exceeded should be a string"))?
+                        .parse()?
+                }
+                _ => anyhow::bail!(
+                    "Unknown code-size setting '{}', expected max, network or exceeded",
+                    key
+                ),
+            }
+        }
+        let max = match (max, network) {
+            (Some(max), None) => MaxCodeSize::Bytes(max),
+            (None, Some(network)) => MaxCodeSize::Network(network),
+            _ => anyhow::bail!("code-size requires either max or network"),
+        };
+        Ok(Some(CodeSizeBudget { max, exceeded }))
+    }
+
+    /// Extract the network profile `name` from `[package.metadata.contract.networks]`
+    #[cfg(feature = "extrinsics")]
+    pub fn get_network_profile(&self, name: &str) -> Option<&value::Value> {
+        self.toml
+            .get("package")?
+            .as_table()?
+            .get("metadata")?
+            .as_table()?
+            .get("contract")?
+            .as_table()?
+            .get("networks")?
+            .as_table()?
+            .get(name)
+    }
+
     /// Set `optimization-passes` in `[package.metadata.contract]`
     #[cfg(test)]
     pub fn set_profile_optimization_passes(
@@ -244,6 +580,31 @@ impl Manifest {
         Ok(self)
     }
 
+    /// Overwrite the `[profile.release]` settings with the `overrides`.
+    pub fn with_profile_release_overrides(
+        &mut self,
+        overrides: &ProfileOverrides,
+    ) -> Result<&mut Self> {
+        let profile_release = self.get_profile_release_table_mut()?;
+        overrides.apply(profile_release);
+        Ok(self)
+    }
+
+    /// Checks that the `[profile.release]` settings are compatible with on-chain execution.
+    ///
+    /// Returns warnings for settings which are compatible, but not advisable for contracts.
+    pub fn check_profile_release_compatibility(&self) -> Result<Vec<String>> {
+        match self
+            .toml
+            .get("profile")
+            .and_then(|profile| profile.get("release"))
+        {
+            Some(value::Value::Table(release)) => profile::check_compatibility(release),
+            Some(_) => anyhow::bail!("release should be a table"),
+            None => Ok(Vec::new()),
+        }
+    }
+
     /// Get mutable reference to `[profile.release]` section
     fn get_profile_release_table_mut(&mut self) -> Result<&mut value::Table> {
         let profile = self
@@ -272,7 +633,10 @@ impl Manifest {
     }
 
     /// Adds a metadata package to the manifest workspace for generating metadata
-    pub fn with_metadata_package(&mut self) -> Result<&mut Self> {
+    ///
+    /// The package depends on the contract with its default features if `default_features` is
+    /// set.
+    pub fn with_metadata_package(&mut self, default_features: bool) -> Result<&mut Self> {
         let workspace = self
             .toml
             .entry("workspace")
@@ -302,6 +666,7 @@ impl Manifest {
         }
 
         self.metadata_package = true;
+        self.metadata_default_features = default_features;
         Ok(self)
     }
 
@@ -445,7 +810,12 @@ impl Manifest {
                 .as_table()
                 .ok_or_else(|| anyhow::anyhow!("ink_metadata dependency should be a table"))?;
 
-            metadata::generate_package(dir, contract_package_name, ink_metadata.clone())?;
+            metadata::generate_package(
+                dir,
+                contract_package_name,
+                self.metadata_default_features,
+                ink_metadata.clone(),
+            )?;
         }
 
         let updated_toml = toml::to_string(&self.toml)?;
@@ -466,8 +836,12 @@ fn crate_type_exists(crate_type: &str, crate_types: &[value::Value]) -> bool {
 
 #[cfg(test)]
 mod test {
-    use super::ManifestPath;
-    use crate::util::tests::with_tmp_dir;
+    use super::{Manifest, ManifestPath};
+    use crate::{
+        code_size::{BudgetExceeded, CodeSizeBudget, MaxCodeSize},
+        util::tests::with_tmp_dir,
+        validate_wasm::ScheduleLimits,
+    };
     use std::fs;
 
     #[test]
@@ -489,4 +863,77 @@ mod test {
             Ok(())
         })
     }
+
+    #[test]
+    fn schedule_limits_are_read_from_manifest() {
+        with_tmp_dir(|path| {
+            // given
+            let cargo_toml_path = path.join("Cargo.toml");
+            fs::write(
+                &cargo_toml_path,
+                "[package.metadata.contract.limits]\nmemory-pages = 32\n",
+            )?;
+            let manifest = Manifest::new(ManifestPath::new(cargo_toml_path)?)?;
+
+            // when
+            let limits = manifest.get_profile_schedule_limits()?;
+
+            // then
+            assert_eq!(
+                limits,
+                ScheduleLimits {
+                    memory_pages: 32,
+                    ..Default::default()
+                }
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn code_size_budget_is_read_from_manifest() {
+        with_tmp_dir(|path| {
+            // given
+            let cargo_toml_path = path.join("Cargo.toml");
+            fs::write(
+                &cargo_toml_path,
+                "[package.metadata.contract.code-size]\nnetwork = \"production\"\n\
+                exceeded = \"warn\"\n",
+            )?;
+            let manifest = Manifest::new(ManifestPath::new(cargo_toml_path)?)?;
+
+            // when
+            let budget = manifest.get_profile_code_size_budget()?;
+
+            // then
+            assert_eq!(
+                budget,
+                Some(CodeSizeBudget {
+                    max: MaxCodeSize::Network("production".to_string()),
+                    exceeded: BudgetExceeded::Warn,
+                })
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn code_size_budget_requires_max_or_network() {
+        with_tmp_dir(|path| {
+            // given
+            let cargo_toml_path = path.join("Cargo.toml");
+            fs::write(
+                &cargo_toml_path,
+                "[package.metadata.contract.code-size]\nexceeded = \"warn\"\n",
+            )?;
+            let manifest = Manifest::new(ManifestPath::new(cargo_toml_path)?)?;
+
+            // when
+            let res = manifest.get_profile_code_size_budget();
+
+            // then
+            assert!(res.is_err());
+            Ok(())
+        })
+    }
 }
@@ -24,10 +24,12 @@ use toml::value;
 /// # Note
 ///
 /// `ink!` dependencies are copied from the containing contract workspace to ensure the same
-/// versions are utilized.
+/// versions are utilized. The contract is depended on with its default features if
+/// `default_features` is set.
 pub(super) fn generate_package<P: AsRef<Path>>(
     target_dir: P,
     contract_package_name: &str,
+    default_features: bool,
     mut ink_metadata_dependency: value::Table,
 ) -> Result<()> {
     let dir = target_dir.as_ref();
@@ -54,6 +56,9 @@ pub(super) fn generate_package<P: AsRef<Path>>(
         .as_table_mut()
         .expect("contract dependency is a table specified in the template");
     contract.insert("package".into(), contract_package_name.into());
+    if !default_features {
+        contract.insert("default-features".into(), false.into());
+    }
 
     // make ink_metadata dependency use default features
     ink_metadata_dependency.remove("default-features");
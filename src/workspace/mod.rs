@@ -21,7 +21,7 @@ mod profile;
 #[doc(inline)]
 pub use self::{
     manifest::{Manifest, ManifestPath},
-    profile::Profile,
+    profile::{Lto, PanicStrategy, Profile, ProfileOverrides},
 };
 
 use anyhow::Result;
@@ -123,10 +123,15 @@ impl Workspace {
 
     /// Generates a package to invoke for generating contract metadata.
     ///
-    /// The contract metadata will be generated for the package found at `package_path`.
-    pub(super) fn with_metadata_gen_package(&mut self, package_path: PathBuf) -> Result<&mut Self> {
+    /// The contract metadata will be generated for the package found at `package_path`, with
+    /// its default features if `default_features` is set.
+    pub(super) fn with_metadata_gen_package(
+        &mut self,
+        package_path: PathBuf,
+        default_features: bool,
+    ) -> Result<&mut Self> {
         self.with_contract_manifest(&package_path, |manifest| {
-            manifest.with_metadata_package()?;
+            manifest.with_metadata_package(default_features)?;
             Ok(())
         })
     }
@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+use anyhow::Result;
+use std::str::FromStr;
 use toml::value;
 
 /// Subset of cargo profile settings to configure defaults for building contracts
@@ -59,6 +61,80 @@ impl Profile {
     }
 }
 
+/// Profile settings which take precedence over both the preferred defaults and the user defined
+/// settings of `[profile.release]`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProfileOverrides {
+    pub lto: Option<Lto>,
+    pub codegen_units: Option<u32>,
+    pub overflow_checks: Option<bool>,
+    pub panic: Option<PanicStrategy>,
+}
+
+impl ProfileOverrides {
+    /// Returns `true` if no setting is overridden.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Set the overridden settings in the profile, replacing any existing values.
+    pub(super) fn apply(&self, profile: &mut value::Table) {
+        if let Some(lto) = self.lto {
+            profile.insert("lto".into(), lto.to_toml_value());
+        }
+        if let Some(codegen_units) = self.codegen_units {
+            profile.insert("codegen-units".into(), codegen_units.into());
+        }
+        if let Some(overflow_checks) = self.overflow_checks {
+            profile.insert("overflow-checks".into(), overflow_checks.into());
+        }
+        if let Some(panic) = self.panic {
+            profile.insert("panic".into(), panic.to_toml_value());
+        }
+    }
+}
+
+/// Checks that the profile settings are compatible with the on-chain execution of the contract.
+///
+/// Returns warnings for settings which are compatible, but not advisable for contracts.
+pub(super) fn check_compatibility(profile: &value::Table) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    match profile.get("panic").map(|panic| panic.as_str()) {
+        None | Some(Some("abort")) => (),
+        Some(Some("unwind")) => anyhow::bail!(
+            "`panic = \"unwind\"` is not supported for contracts, unwinding is not available \
+            on-chain. Use `panic = \"abort\"` instead."
+        ),
+        Some(_) => anyhow::bail!("panic should be either \"abort\" or \"unwind\""),
+    }
+    match profile
+        .get("overflow-checks")
+        .map(|checks| checks.as_bool())
+    {
+        None | Some(Some(true)) => (),
+        Some(Some(false)) => warnings.push(
+            "overflow checks are disabled, arithmetic overflows in the contract wrap around \
+            silently instead of trapping."
+                .to_string(),
+        ),
+        Some(None) => anyhow::bail!("overflow-checks should be a boolean"),
+    }
+    match profile.get("codegen-units").map(|units| units.as_integer()) {
+        None => (),
+        Some(Some(units)) if units > 0 => (),
+        Some(_) => anyhow::bail!("codegen-units should be a positive integer"),
+    }
+    match profile.get("lto") {
+        None | Some(value::Value::Boolean(_)) => (),
+        Some(value::Value::String(lto)) if lto == "fat" || lto == "thin" => (),
+        Some(value::Value::String(lto)) if lto == "off" => warnings.push(
+            "link-time optimization is disabled, the contract is considerably larger.".to_string(),
+        ),
+        Some(_) => anyhow::bail!("lto should be a boolean or one of \"fat\", \"thin\", \"off\""),
+    }
+    Ok(warnings)
+}
+
 /// The [`opt-level`](https://doc.rust-lang.org/cargo/reference/profiles.html#opt-level) setting
 #[allow(unused)]
 #[derive(Clone, Copy)]
@@ -85,8 +161,7 @@ impl OptLevel {
 }
 
 /// The [`link-time-optimization`](https://doc.rust-lang.org/cargo/reference/profiles.html#lto) setting.
-#[derive(Clone, Copy)]
-#[allow(unused)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Lto {
     /// Sets `lto = false`
     ThinLocal,
@@ -109,9 +184,25 @@ impl Lto {
     }
 }
 
+impl FromStr for Lto {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input {
+            "fat" | "true" => Ok(Lto::Fat),
+            "thin" => Ok(Lto::Thin),
+            "off" => Ok(Lto::Off),
+            "false" => Ok(Lto::ThinLocal),
+            _ => anyhow::bail!(
+                "Unknown lto setting '{}', expected fat, thin, off or false",
+                input
+            ),
+        }
+    }
+}
+
 /// The `panic` setting.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, PartialOrd, Ord)]
-#[allow(unused)]
 pub enum PanicStrategy {
     Unwind,
     Abort,
@@ -126,6 +217,21 @@ impl PanicStrategy {
     }
 }
 
+impl FromStr for PanicStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input {
+            "abort" => Ok(PanicStrategy::Abort),
+            "unwind" => Ok(PanicStrategy::Unwind),
+            _ => anyhow::bail!(
+                "Unknown panic strategy '{}', expected abort or unwind",
+                input
+            ),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,4 +281,69 @@ mod tests {
 
         assert_eq!(expected, manifest_profile)
     }
+
+    #[test]
+    fn overrides_replace_user_defined_settings() {
+        let overrides = ProfileOverrides {
+            lto: Some(Lto::Thin),
+            codegen_units: None,
+            overflow_checks: Some(false),
+            panic: None,
+        };
+
+        let manifest_toml = r#"
+            panic = "abort"
+            lto = "fat"
+            overflow-checks = true
+            codegen-units = 1
+        "#;
+        let mut expected = toml::value::Table::new();
+        expected.insert("panic".into(), value::Value::String("abort".into()));
+        expected.insert("lto".into(), value::Value::String("thin".into()));
+        expected.insert("overflow-checks".into(), value::Value::Boolean(false));
+        expected.insert("codegen-units".into(), value::Value::Integer(1));
+
+        let mut manifest_profile = toml::from_str(manifest_toml).unwrap();
+
+        overrides.apply(&mut manifest_profile);
+
+        assert_eq!(expected, manifest_profile)
+    }
+
+    #[test]
+    fn default_profile_is_compatible() {
+        let mut manifest_profile = toml::value::Table::new();
+        Profile::default_contract_release().merge(&mut manifest_profile);
+
+        let warnings = check_compatibility(&manifest_profile).unwrap();
+
+        assert!(warnings.is_empty())
+    }
+
+    #[test]
+    fn unwinding_profile_is_rejected() {
+        let manifest_profile = toml::from_str(r#"panic = "unwind""#).unwrap();
+
+        let result = check_compatibility(&manifest_profile);
+
+        assert!(result.is_err())
+    }
+
+    #[test]
+    fn disabled_overflow_checks_are_warned_about() {
+        let manifest_profile = toml::from_str("overflow-checks = false").unwrap();
+
+        let warnings = check_compatibility(&manifest_profile).unwrap();
+
+        assert_eq!(1, warnings.len())
+    }
+
+    #[test]
+    fn profile_settings_are_parsed() {
+        assert_eq!(Lto::Fat, "true".parse().unwrap());
+        assert_eq!(Lto::ThinLocal, "false".parse().unwrap());
+        assert_eq!(PanicStrategy::Abort, "abort".parse().unwrap());
+        assert!("fast".parse::<Lto>().is_err());
+        assert!("exit".parse::<PanicStrategy>().is_err());
+    }
 }
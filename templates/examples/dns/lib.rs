@@ -0,0 +1,195 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+
+#[ink::contract]
+mod {{module_name}} {
+    use ink_storage::collections::HashMap as StorageHashMap;
+
+    /// A simple domain name service, mapping names to addresses.
+    #[ink(storage)]
+    #[derive(Default)]
+    pub struct {{camel_name}} {
+        /// Mapping from name to the address it resolves to.
+        name_to_address: StorageHashMap<Hash, AccountId>,
+        /// Mapping from name to the owner of the name.
+        name_to_owner: StorageHashMap<Hash, AccountId>,
+    }
+
+    /// Emitted whenever a new name is registered.
+    #[ink(event)]
+    pub struct Register {
+        #[ink(topic)]
+        name: Hash,
+        #[ink(topic)]
+        from: AccountId,
+    }
+
+    /// Emitted whenever the address of a name changes.
+    #[ink(event)]
+    pub struct SetAddress {
+        #[ink(topic)]
+        name: Hash,
+        from: AccountId,
+        #[ink(topic)]
+        old_address: Option<AccountId>,
+        #[ink(topic)]
+        new_address: AccountId,
+    }
+
+    /// Emitted whenever the owner of a name changes.
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        name: Hash,
+        from: AccountId,
+        #[ink(topic)]
+        old_owner: Option<AccountId>,
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    /// The domain name service error types.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// Returned if the name already exists upon registration.
+        NameAlreadyExists,
+        /// Returned if the caller is not the owner of the name.
+        CallerIsNotOwner,
+    }
+
+    /// The domain name service result type.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl {{camel_name}} {
+        /// Creates a new domain name service without any registered names.
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Default::default()
+        }
+
+        /// Registers the name, owned by the caller.
+        #[ink(message)]
+        pub fn register(&mut self, name: Hash) -> Result<()> {
+            let caller = self.env().caller();
+            if self.name_to_owner.contains_key(&name) {
+                return Err(Error::NameAlreadyExists)
+            }
+            self.name_to_owner.insert(name, caller);
+            self.env().emit_event(Register { name, from: caller });
+            Ok(())
+        }
+
+        /// Sets the address the name resolves to. Only the owner of the name can do so.
+        #[ink(message)]
+        pub fn set_address(&mut self, name: Hash, new_address: AccountId) -> Result<()> {
+            let caller = self.env().caller();
+            self.ensure_owner(name, caller)?;
+            let old_address = self.name_to_address.insert(name, new_address);
+            self.env().emit_event(SetAddress {
+                name,
+                from: caller,
+                old_address,
+                new_address,
+            });
+            Ok(())
+        }
+
+        /// Transfers the ownership of the name. Only the owner of the name can do so.
+        #[ink(message)]
+        pub fn transfer(&mut self, name: Hash, to: AccountId) -> Result<()> {
+            let caller = self.env().caller();
+            self.ensure_owner(name, caller)?;
+            let old_owner = self.name_to_owner.insert(name, to);
+            self.env().emit_event(Transfer {
+                name,
+                from: caller,
+                old_owner,
+                new_owner: to,
+            });
+            Ok(())
+        }
+
+        /// Returns the address the name resolves to, if any.
+        #[ink(message)]
+        pub fn get_address(&self, name: Hash) -> Option<AccountId> {
+            self.name_to_address.get(&name).copied()
+        }
+
+        /// Returns the owner of the name, if it is registered.
+        #[ink(message)]
+        pub fn get_owner(&self, name: Hash) -> Option<AccountId> {
+            self.name_to_owner.get(&name).copied()
+        }
+
+        /// Returns an error if `caller` is not the owner of the name.
+        fn ensure_owner(&self, name: Hash, caller: AccountId) -> Result<()> {
+            if self.get_owner(name) != Some(caller) {
+                return Err(Error::CallerIsNotOwner)
+            }
+            Ok(())
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        /// Imports `ink_lang` so we can use `#[ink::test]`.
+        use ink_lang as ink;
+
+        fn default_accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
+            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts")
+        }
+
+        /// Names can only be registered once.
+        #[ink::test]
+        fn register_works() {
+            let accounts = default_accounts();
+            let name = Hash::from([0x99; 32]);
+            let mut contract = {{camel_name}}::new();
+
+            assert_eq!(contract.register(name), Ok(()));
+            assert_eq!(contract.get_owner(name), Some(accounts.alice));
+            assert_eq!(contract.register(name), Err(Error::NameAlreadyExists));
+        }
+
+        /// The owner sets the address a name resolves to.
+        #[ink::test]
+        fn set_address_works() {
+            let accounts = default_accounts();
+            let name = Hash::from([0x99; 32]);
+            let mut contract = {{camel_name}}::new();
+
+            assert_eq!(
+                contract.set_address(name, accounts.bob),
+                Err(Error::CallerIsNotOwner)
+            );
+            assert_eq!(contract.register(name), Ok(()));
+            assert_eq!(contract.set_address(name, accounts.bob), Ok(()));
+            assert_eq!(contract.get_address(name), Some(accounts.bob));
+        }
+
+        /// Transferred names are owned by the new owner.
+        #[ink::test]
+        fn transfer_works() {
+            let accounts = default_accounts();
+            let name = Hash::from([0x99; 32]);
+            let mut contract = {{camel_name}}::new();
+            assert_eq!(contract.register(name), Ok(()));
+
+            assert_eq!(contract.transfer(name, accounts.bob), Ok(()));
+            assert_eq!(contract.get_owner(name), Some(accounts.bob));
+            assert_eq!(
+                contract.transfer(name, accounts.alice),
+                Err(Error::CallerIsNotOwner)
+            );
+        }
+    }
+}
@@ -0,0 +1,266 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+
+#[ink::contract]
+mod {{module_name}} {
+    use ink_prelude::string::String;
+    use ink_storage::{
+        collections::HashMap as StorageHashMap,
+        lazy::Lazy,
+    };
+
+    /// A simple ERC-20 token.
+    #[ink(storage)]
+    pub struct {{camel_name}} {
+        /// Total token supply.
+        total_supply: Lazy<Balance>,
+        /// Mapping from owner to number of owned tokens.
+        balances: StorageHashMap<AccountId, Balance>,
+        /// Mapping of the token amount which an account is allowed to withdraw
+        /// from another account.
+        allowances: StorageHashMap<(AccountId, AccountId), Balance>,
+    }
+
+    /// Event emitted when a token transfer occurs.
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+    }
+
+    /// Event emitted when an approval occurs that `spender` is allowed to withdraw
+    /// up to the amount of `value` tokens from `owner`.
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
+    }
+
+    /// The ERC-20 error types.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// Returned if not enough balance to fulfill a request is available.
+        InsufficientBalance,
+        /// Returned if not enough allowance to fulfill a request is available.
+        InsufficientAllowance,
+    }
+
+    /// The ERC-20 result type.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// The symbol of the token.
+    const TOKEN_SYMBOL: &str = "{{symbol}}";
+
+    /// The number of decimals of the token.
+    const TOKEN_DECIMALS: u8 = {{decimals}};
+
+    impl {{camel_name}} {
+        /// Creates a new ERC-20 contract with the specified initial supply, which is owned by
+        /// the caller.
+        #[ink(constructor)]
+        pub fn new(initial_supply: Balance) -> Self {
+            let caller = Self::env().caller();
+            let mut balances = StorageHashMap::new();
+            balances.insert(caller, initial_supply);
+            let instance = Self {
+                total_supply: Lazy::new(initial_supply),
+                balances,
+                allowances: StorageHashMap::new(),
+            };
+            Self::env().emit_event(Transfer {
+                from: None,
+                to: Some(caller),
+                value: initial_supply,
+            });
+            instance
+        }
+
+        /// Returns the total token supply.
+        #[ink(message)]
+        pub fn total_supply(&self) -> Balance {
+            *self.total_supply
+        }
+
+        /// Returns the symbol of the token.
+        #[ink(message)]
+        pub fn token_symbol(&self) -> String {
+            String::from(TOKEN_SYMBOL)
+        }
+
+        /// Returns the number of decimals of the token.
+        #[ink(message)]
+        pub fn token_decimals(&self) -> u8 {
+            TOKEN_DECIMALS
+        }
+
+        /// Returns the account balance for the specified `owner`.
+        ///
+        /// Returns `0` if the account is non-existent.
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            self.balances.get(&owner).copied().unwrap_or(0)
+        }
+
+        /// Returns the amount which `spender` is still allowed to withdraw from `owner`.
+        ///
+        /// Returns `0` if no allowance has been set.
+        #[ink(message)]
+        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.allowances.get(&(owner, spender)).copied().unwrap_or(0)
+        }
+
+        /// Transfers `value` amount of tokens from the caller's account to account `to`.
+        ///
+        /// On success a `Transfer` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InsufficientBalance` error if there are not enough tokens on
+        /// the caller's account balance.
+        #[ink(message)]
+        pub fn transfer(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            let from = self.env().caller();
+            self.transfer_from_to(from, to, value)
+        }
+
+        /// Allows `spender` to withdraw from the caller's account multiple times, up to
+        /// the `value` amount.
+        ///
+        /// If this function is called again it overwrites the current allowance with `value`.
+        ///
+        /// An `Approval` event is emitted.
+        #[ink(message)]
+        pub fn approve(&mut self, spender: AccountId, value: Balance) -> Result<()> {
+            let owner = self.env().caller();
+            self.allowances.insert((owner, spender), value);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value,
+            });
+            Ok(())
+        }
+
+        /// Transfers `value` tokens on the behalf of `from` to the account `to`.
+        ///
+        /// On success a `Transfer` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InsufficientAllowance` error if there are not enough tokens allowed
+        /// for the caller to withdraw from `from`.
+        ///
+        /// Returns `InsufficientBalance` error if there are not enough tokens on
+        /// the account balance of `from`.
+        #[ink(message)]
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let caller = self.env().caller();
+            let allowance = self.allowance(from, caller);
+            if allowance < value {
+                return Err(Error::InsufficientAllowance)
+            }
+            self.transfer_from_to(from, to, value)?;
+            self.allowances.insert((from, caller), allowance - value);
+            Ok(())
+        }
+
+        /// Transfers `value` amount of tokens from the account `from` to the account `to`.
+        fn transfer_from_to(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let from_balance = self.balance_of(from);
+            if from_balance < value {
+                return Err(Error::InsufficientBalance)
+            }
+            self.balances.insert(from, from_balance - value);
+            let to_balance = self.balance_of(to);
+            self.balances.insert(to, to_balance + value);
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value,
+            });
+            Ok(())
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        /// Imports `ink_lang` so we can use `#[ink::test]`.
+        use ink_lang as ink;
+
+        fn default_accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
+            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts")
+        }
+
+        /// The initial supply is owned by the creator of the contract.
+        #[ink::test]
+        fn new_works() {
+            let contract = {{camel_name}}::new(100);
+            assert_eq!(contract.total_supply(), 100);
+            assert_eq!(contract.token_symbol(), "{{symbol}}");
+            assert_eq!(contract.balance_of(default_accounts().alice), 100);
+        }
+
+        /// Tokens can only be transferred up to the balance of the caller.
+        #[ink::test]
+        fn transfer_works() {
+            let accounts = default_accounts();
+            let mut contract = {{camel_name}}::new(100);
+
+            assert_eq!(contract.transfer(accounts.bob, 10), Ok(()));
+            assert_eq!(contract.balance_of(accounts.alice), 90);
+            assert_eq!(contract.balance_of(accounts.bob), 10);
+            assert_eq!(
+                contract.transfer(accounts.bob, 91),
+                Err(Error::InsufficientBalance)
+            );
+        }
+
+        /// Approvals are recorded as allowances.
+        #[ink::test]
+        fn approve_works() {
+            let accounts = default_accounts();
+            let mut contract = {{camel_name}}::new(100);
+
+            assert_eq!(contract.approve(accounts.bob, 20), Ok(()));
+            assert_eq!(contract.allowance(accounts.alice, accounts.bob), 20);
+            assert_eq!(contract.allowance(accounts.bob, accounts.alice), 0);
+        }
+
+        /// Tokens can't be transferred on behalf of an account without an allowance.
+        #[ink::test]
+        fn transfer_from_requires_allowance() {
+            let accounts = default_accounts();
+            let mut contract = {{camel_name}}::new(100);
+
+            assert_eq!(
+                contract.transfer_from(accounts.bob, accounts.charlie, 10),
+                Err(Error::InsufficientAllowance)
+            );
+        }
+    }
+}
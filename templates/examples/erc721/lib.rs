@@ -0,0 +1,303 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+
+#[ink::contract]
+mod {{module_name}} {
+    use ink_storage::collections::HashMap as StorageHashMap;
+
+    /// A token ID.
+    pub type TokenId = u32;
+
+    /// A simple ERC-721 non-fungible token.
+    #[ink(storage)]
+    #[derive(Default)]
+    pub struct {{camel_name}} {
+        /// Mapping from token to owner.
+        token_owner: StorageHashMap<TokenId, AccountId>,
+        /// Mapping from token to approvals users.
+        token_approvals: StorageHashMap<TokenId, AccountId>,
+        /// Mapping from owner to number of owned tokens.
+        owned_tokens_count: StorageHashMap<AccountId, u32>,
+        /// Mapping from owner to operator approvals.
+        operator_approvals: StorageHashMap<(AccountId, AccountId), bool>,
+    }
+
+    /// The ERC-721 error types.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// The caller doesn't own the token.
+        NotOwner,
+        /// The caller is neither the owner nor approved for the token.
+        NotApproved,
+        /// The token was already minted.
+        TokenExists,
+        /// The token doesn't exist.
+        TokenNotFound,
+        /// The operation is not allowed, e.g. approving oneself.
+        NotAllowed,
+    }
+
+    /// Event emitted when a token transfer occurs.
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        #[ink(topic)]
+        id: TokenId,
+    }
+
+    /// Event emitted when a token approve occurs.
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        #[ink(topic)]
+        id: TokenId,
+    }
+
+    /// Event emitted when an operator is enabled or disabled for an owner.
+    /// The operator can manage all NFTs of the owner.
+    #[ink(event)]
+    pub struct ApprovalForAll {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        operator: AccountId,
+        approved: bool,
+    }
+
+    impl {{camel_name}} {
+        /// Creates a new ERC-721 token contract.
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Default::default()
+        }
+
+        /// Returns the number of tokens of the `owner`.
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId) -> u32 {
+            self.owned_tokens_count.get(&owner).copied().unwrap_or(0)
+        }
+
+        /// Returns the owner of the token.
+        #[ink(message)]
+        pub fn owner_of(&self, id: TokenId) -> Option<AccountId> {
+            self.token_owner.get(&id).copied()
+        }
+
+        /// Returns the approved account of the token.
+        #[ink(message)]
+        pub fn get_approved(&self, id: TokenId) -> Option<AccountId> {
+            self.token_approvals.get(&id).copied()
+        }
+
+        /// Returns `true` if the `operator` is approved to manage all tokens of the `owner`.
+        #[ink(message)]
+        pub fn is_approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool {
+            self.operator_approvals
+                .get(&(owner, operator))
+                .copied()
+                .unwrap_or(false)
+        }
+
+        /// Approves or disapproves the `operator` to manage all tokens of the caller.
+        #[ink(message)]
+        pub fn set_approval_for_all(
+            &mut self,
+            operator: AccountId,
+            approved: bool,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if operator == caller {
+                return Err(Error::NotAllowed)
+            }
+            self.operator_approvals.insert((caller, operator), approved);
+            self.env().emit_event(ApprovalForAll {
+                owner: caller,
+                operator,
+                approved,
+            });
+            Ok(())
+        }
+
+        /// Approves the account `to` to transfer the token on behalf of the caller.
+        #[ink(message)]
+        pub fn approve(&mut self, to: AccountId, id: TokenId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if !(owner == caller || self.is_approved_for_all(owner, caller)) {
+                return Err(Error::NotAllowed)
+            }
+            if to == owner {
+                return Err(Error::NotAllowed)
+            }
+            self.token_approvals.insert(id, to);
+            self.env().emit_event(Approval {
+                from: caller,
+                to,
+                id,
+            });
+            Ok(())
+        }
+
+        /// Transfers the token from the caller to the account `to`.
+        #[ink(message)]
+        pub fn transfer(&mut self, to: AccountId, id: TokenId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.transfer_token_from(caller, to, id)
+        }
+
+        /// Transfers the token of the account `from` to the account `to`.
+        #[ink(message)]
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+        ) -> Result<(), Error> {
+            self.transfer_token_from(from, to, id)
+        }
+
+        /// Creates a new token, owned by the caller.
+        #[ink(message)]
+        pub fn mint(&mut self, id: TokenId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if self.token_owner.contains_key(&id) {
+                return Err(Error::TokenExists)
+            }
+            self.add_token_to(caller, id);
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(caller),
+                id,
+            });
+            Ok(())
+        }
+
+        /// Deletes an existing token. Only the owner can burn the token.
+        #[ink(message)]
+        pub fn burn(&mut self, id: TokenId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if owner != caller {
+                return Err(Error::NotOwner)
+            }
+            self.remove_token_from(owner, id);
+            self.env().emit_event(Transfer {
+                from: Some(caller),
+                to: None,
+                id,
+            });
+            Ok(())
+        }
+
+        /// Transfers the token `id` from the account `from` to the account `to`, if the caller
+        /// is the owner or approved.
+        fn transfer_token_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if owner != from {
+                return Err(Error::NotOwner)
+            }
+            if !self.approved_or_owner(caller, owner, id) {
+                return Err(Error::NotApproved)
+            }
+            self.remove_token_from(from, id);
+            self.add_token_to(to, id);
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                id,
+            });
+            Ok(())
+        }
+
+        /// Returns `true` if the `caller` is the `owner` of the token or approved for it.
+        fn approved_or_owner(&self, caller: AccountId, owner: AccountId, id: TokenId) -> bool {
+            caller == owner
+                || self.get_approved(id) == Some(caller)
+                || self.is_approved_for_all(owner, caller)
+        }
+
+        fn add_token_to(&mut self, to: AccountId, id: TokenId) {
+            self.token_owner.insert(id, to);
+            let count = self.balance_of(to);
+            self.owned_tokens_count.insert(to, count + 1);
+        }
+
+        fn remove_token_from(&mut self, from: AccountId, id: TokenId) {
+            self.token_owner.take(&id);
+            self.token_approvals.take(&id);
+            let count = self.balance_of(from);
+            self.owned_tokens_count.insert(from, count.saturating_sub(1));
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        /// Imports `ink_lang` so we can use `#[ink::test]`.
+        use ink_lang as ink;
+
+        fn default_accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
+            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts")
+        }
+
+        /// Minted tokens are owned by the caller.
+        #[ink::test]
+        fn mint_works() {
+            let accounts = default_accounts();
+            let mut contract = {{camel_name}}::new();
+
+            assert_eq!(contract.balance_of(accounts.alice), 0);
+            assert_eq!(contract.mint(1), Ok(()));
+            assert_eq!(contract.balance_of(accounts.alice), 1);
+            assert_eq!(contract.owner_of(1), Some(accounts.alice));
+            assert_eq!(contract.mint(1), Err(Error::TokenExists));
+        }
+
+        /// Tokens change their owner when transferred.
+        #[ink::test]
+        fn transfer_works() {
+            let accounts = default_accounts();
+            let mut contract = {{camel_name}}::new();
+            assert_eq!(contract.mint(1), Ok(()));
+
+            assert_eq!(contract.transfer(accounts.bob, 1), Ok(()));
+            assert_eq!(contract.owner_of(1), Some(accounts.bob));
+            assert_eq!(contract.balance_of(accounts.alice), 0);
+            assert_eq!(contract.balance_of(accounts.bob), 1);
+            assert_eq!(contract.transfer(accounts.bob, 1), Err(Error::NotOwner));
+        }
+
+        /// Burnt tokens no longer exist.
+        #[ink::test]
+        fn burn_works() {
+            let accounts = default_accounts();
+            let mut contract = {{camel_name}}::new();
+            assert_eq!(contract.mint(1), Ok(()));
+
+            assert_eq!(contract.burn(1), Ok(()));
+            assert_eq!(contract.owner_of(1), None);
+            assert_eq!(contract.balance_of(accounts.alice), 0);
+            assert_eq!(contract.burn(1), Err(Error::TokenNotFound));
+        }
+    }
+}
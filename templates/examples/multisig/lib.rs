@@ -0,0 +1,267 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+
+#[ink::contract]
+mod {{module_name}} {
+    use ink_prelude::vec::Vec;
+    use ink_storage::collections::{
+        HashMap as StorageHashMap,
+        Vec as StorageVec,
+    };
+
+    /// The id of a transaction.
+    pub type TransactionId = u32;
+
+    /// A multisig wallet, transferring funds once enough owners confirmed the transfer.
+    #[ink(storage)]
+    pub struct {{camel_name}} {
+        /// The owners of the wallet.
+        owners: StorageVec<AccountId>,
+        /// The number of confirmations required to execute a transaction.
+        requirement: u32,
+        /// The submitted transactions as `(recipient, value)`.
+        transactions: StorageHashMap<TransactionId, (AccountId, Balance)>,
+        /// The confirmations of the transactions by the owners.
+        confirmations: StorageHashMap<(TransactionId, AccountId), ()>,
+        /// The number of confirmations per transaction.
+        confirmation_count: StorageHashMap<TransactionId, u32>,
+        /// The id of the next submitted transaction.
+        next_id: TransactionId,
+    }
+
+    /// Emitted when an owner submits a transaction.
+    #[ink(event)]
+    pub struct Submission {
+        #[ink(topic)]
+        transaction: TransactionId,
+    }
+
+    /// Emitted when an owner confirms a transaction.
+    #[ink(event)]
+    pub struct Confirmation {
+        #[ink(topic)]
+        transaction: TransactionId,
+        #[ink(topic)]
+        from: AccountId,
+    }
+
+    /// Emitted when an owner revokes the confirmation of a transaction.
+    #[ink(event)]
+    pub struct Revocation {
+        #[ink(topic)]
+        transaction: TransactionId,
+        #[ink(topic)]
+        from: AccountId,
+    }
+
+    /// Emitted when a transaction is executed.
+    #[ink(event)]
+    pub struct Execution {
+        #[ink(topic)]
+        transaction: TransactionId,
+    }
+
+    /// The multisig error types.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// The caller is not an owner of the wallet.
+        NotOwner,
+        /// The transaction doesn't exist.
+        TransactionNotFound,
+        /// The caller already confirmed the transaction.
+        AlreadyConfirmed,
+        /// The caller didn't confirm the transaction.
+        NotConfirmed,
+        /// The transaction doesn't have enough confirmations.
+        NotEnoughConfirmations,
+        /// The transfer of the funds failed.
+        TransferFailed,
+    }
+
+    /// The multisig result type.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl {{camel_name}} {
+        /// Creates a new wallet of the `owners`, requiring `requirement` confirmations per
+        /// transaction.
+        ///
+        /// # Panics
+        ///
+        /// If `requirement` is zero or exceeds the number of owners.
+        #[ink(constructor)]
+        pub fn new(owners: Vec<AccountId>, requirement: u32) -> Self {
+            assert!(
+                requirement > 0 && requirement as usize <= owners.len(),
+                "requirement must be between 1 and the number of owners"
+            );
+            let mut storage_owners = StorageVec::new();
+            for owner in owners {
+                storage_owners.push(owner);
+            }
+            Self {
+                owners: storage_owners,
+                requirement,
+                transactions: StorageHashMap::new(),
+                confirmations: StorageHashMap::new(),
+                confirmation_count: StorageHashMap::new(),
+                next_id: 0,
+            }
+        }
+
+        /// Submits a transfer of `value` to `to`, confirmed by the caller.
+        #[ink(message)]
+        pub fn submit(&mut self, to: AccountId, value: Balance) -> Result<TransactionId> {
+            self.ensure_owner()?;
+            let id = self.next_id;
+            self.next_id += 1;
+            self.transactions.insert(id, (to, value));
+            self.env().emit_event(Submission { transaction: id });
+            self.confirm(id)?;
+            Ok(id)
+        }
+
+        /// Confirms the transaction.
+        #[ink(message)]
+        pub fn confirm(&mut self, id: TransactionId) -> Result<()> {
+            let caller = self.ensure_owner()?;
+            self.ensure_transaction(id)?;
+            if self.confirmations.insert((id, caller), ()).is_some() {
+                return Err(Error::AlreadyConfirmed)
+            }
+            let count = self.confirmation_count(id);
+            self.confirmation_count.insert(id, count + 1);
+            self.env().emit_event(Confirmation {
+                transaction: id,
+                from: caller,
+            });
+            Ok(())
+        }
+
+        /// Revokes the confirmation of the transaction by the caller.
+        #[ink(message)]
+        pub fn revoke(&mut self, id: TransactionId) -> Result<()> {
+            let caller = self.ensure_owner()?;
+            self.ensure_transaction(id)?;
+            if self.confirmations.take(&(id, caller)).is_none() {
+                return Err(Error::NotConfirmed)
+            }
+            let count = self.confirmation_count(id);
+            self.confirmation_count.insert(id, count - 1);
+            self.env().emit_event(Revocation {
+                transaction: id,
+                from: caller,
+            });
+            Ok(())
+        }
+
+        /// Executes the transaction once it has enough confirmations.
+        #[ink(message)]
+        pub fn execute(&mut self, id: TransactionId) -> Result<()> {
+            self.ensure_owner()?;
+            self.ensure_transaction(id)?;
+            if self.confirmation_count(id) < self.requirement {
+                return Err(Error::NotEnoughConfirmations)
+            }
+            let (to, value) = self.transactions.take(&id).ok_or(Error::TransactionNotFound)?;
+            self.confirmation_count.take(&id);
+            for owner in self.owners.iter() {
+                self.confirmations.take(&(id, *owner));
+            }
+            self.env()
+                .transfer(to, value)
+                .map_err(|_| Error::TransferFailed)?;
+            self.env().emit_event(Execution { transaction: id });
+            Ok(())
+        }
+
+        /// Returns the number of confirmations of the transaction.
+        #[ink(message)]
+        pub fn confirmation_count(&self, id: TransactionId) -> u32 {
+            self.confirmation_count.get(&id).copied().unwrap_or(0)
+        }
+
+        /// Returns the number of confirmations required to execute a transaction.
+        #[ink(message)]
+        pub fn requirement(&self) -> u32 {
+            self.requirement
+        }
+
+        /// Returns the caller if it is an owner of the wallet.
+        fn ensure_owner(&self) -> Result<AccountId> {
+            let caller = self.env().caller();
+            if !self.owners.iter().any(|owner| *owner == caller) {
+                return Err(Error::NotOwner)
+            }
+            Ok(caller)
+        }
+
+        /// Returns an error if the transaction doesn't exist.
+        fn ensure_transaction(&self, id: TransactionId) -> Result<()> {
+            if !self.transactions.contains_key(&id) {
+                return Err(Error::TransactionNotFound)
+            }
+            Ok(())
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        /// Imports `ink_lang` so we can use `#[ink::test]`.
+        use ink_lang as ink;
+
+        fn default_accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
+            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts")
+        }
+
+        /// Submitted transactions are confirmed by the submitter.
+        #[ink::test]
+        fn submit_works() {
+            let accounts = default_accounts();
+            let mut contract = {{camel_name}}::new(vec![accounts.alice, accounts.bob], 2);
+
+            let id = contract.submit(accounts.charlie, 10).expect("submit must work");
+            assert_eq!(contract.confirmation_count(id), 1);
+            assert_eq!(contract.confirm(id), Err(Error::AlreadyConfirmed));
+        }
+
+        /// Revoked confirmations no longer count.
+        #[ink::test]
+        fn revoke_works() {
+            let accounts = default_accounts();
+            let mut contract = {{camel_name}}::new(vec![accounts.alice, accounts.bob], 2);
+            let id = contract.submit(accounts.charlie, 10).expect("submit must work");
+
+            assert_eq!(contract.revoke(id), Ok(()));
+            assert_eq!(contract.confirmation_count(id), 0);
+            assert_eq!(contract.revoke(id), Err(Error::NotConfirmed));
+        }
+
+        /// Transactions require enough confirmations to be executed.
+        #[ink::test]
+        fn execute_requires_confirmations() {
+            let accounts = default_accounts();
+            let mut contract = {{camel_name}}::new(vec![accounts.alice, accounts.bob], 2);
+            let id = contract.submit(accounts.charlie, 10).expect("submit must work");
+
+            assert_eq!(contract.execute(id), Err(Error::NotEnoughConfirmations));
+            assert_eq!(contract.execute(id + 1), Err(Error::TransactionNotFound));
+        }
+
+        /// The requirement can't exceed the number of owners.
+        #[ink::test]
+        #[should_panic(expected = "requirement must be between 1 and the number of owners")]
+        fn requirement_must_not_exceed_owners() {
+            let accounts = default_accounts();
+            {{camel_name}}::new(vec![accounts.alice], 2);
+        }
+    }
+}
@@ -3,7 +3,7 @@
 use ink_lang as ink;
 
 #[ink::contract]
-mod {{name}} {
+mod {{module_name}} {
 
     /// Defines the storage of your contract.
     /// Add new fields to the below struct in order
@@ -0,0 +1,22 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_env::{DefaultEnvironment, Environment};
+use ink_lang as ink;
+
+pub use ink_prelude::{string::String, vec::Vec};
+
+/// The account id type of the default environment.
+pub type AccountId = <DefaultEnvironment as Environment>::AccountId;
+/// The balance type of the default environment.
+pub type Balance = <DefaultEnvironment as Environment>::Balance;
+/// The hash type of the default environment.
+pub type Hash = <DefaultEnvironment as Environment>::Hash;
+/// The timestamp type of the default environment.
+pub type Timestamp = <DefaultEnvironment as Environment>::Timestamp;
+/// The block number type of the default environment.
+pub type BlockNumber = <DefaultEnvironment as Environment>::BlockNumber;
+{{types}}
+{{docs}}#[ink::trait_definition]
+pub trait {{trait_name}} {
+{{messages}}}
+{{calls}}
\ No newline at end of file
@@ -0,0 +1,26 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+// the arguments of the messages are unused until the messages are implemented
+#![allow(unused_variables)]
+
+use ink_lang as ink;
+
+#[ink::contract]
+mod {{module_name}} {
+    use {{trait_crate}}::*;
+
+    /// A stub implementation of `{{trait_name}}`, whose messages are yet to be implemented.
+    #[ink(storage)]
+    #[derive(Default)]
+    pub struct {{camel_name}} {}
+
+    impl {{camel_name}} {
+        /// Creates a new contract.
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Default::default()
+        }
+    }
+
+    impl {{trait_name}} for {{camel_name}} {
+{{stub_messages}}    }
+}
@@ -0,0 +1,62 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+
+pub use self::{{module_name}}::{{camel_name}};
+
+#[ink::contract]
+mod {{module_name}} {
+    use {{traits}}::Versioned;
+{{imports}}
+    /// Defines the storage of your contract.
+    #[ink(storage)]
+    pub struct {{camel_name}} {
+        /// The account which instantiated the contract.
+        owner: AccountId,
+{{fields}}    }
+
+    impl {{camel_name}} {
+        /// Creates a new contract, owned by the caller.
+        #[ink(constructor)]
+        pub fn new({{constructor_params}}) -> Self {
+            Self {
+                owner: Self::env().caller(),
+{{constructor_fields}}            }
+        }
+
+        /// Returns the account which instantiated the contract.
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+{{messages}}    }
+
+    impl Versioned for {{camel_name}} {
+        #[ink(message)]
+        fn version(&self) -> u32 {
+            1
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        /// Imports `ink_lang` so we can use `#[ink::test]`.
+        use ink_lang as ink;
+
+        /// The contract is owned by the account instantiating it.
+        #[ink::test]
+        fn new_works() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts");
+            let contract = {{camel_name}}::new({{test_args}});
+            assert_eq!(contract.owner(), accounts.alice);
+            assert_eq!(contract.version(), 1);
+        }
+    }
+}
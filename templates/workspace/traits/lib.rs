@@ -0,0 +1,13 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+
+/// Messages shared by the contracts of the workspace.
+///
+/// Contracts implement the trait to be callable via it by the other contracts of the workspace.
+#[ink::trait_definition]
+pub trait Versioned {
+    /// Returns the version of the contract.
+    #[ink(message)]
+    fn version(&self) -> u32;
+}